@@ -0,0 +1,255 @@
+// 告警去重 — `logtool watch` 的告警判定
+//
+// 每个可疑来源首次出现立即告警；冷却期内只有事件数翻倍或严重级别升高才再次告警，
+// 冷却期过后仍然活跃则重复提醒一次。不再出现且超过冷却期的来源视为已恢复并被清除。
+// 已确认（`logtool ack`）的告警只在严重级别升高时再次触发，此时确认标记被清除。
+// 记录随 State 持久化，watch 重启后不会把仍在冷却期内的告警重新发一遍。
+// 落在维护窗口内的事件不计入告警判定；当前处于维护窗口时 watch 整轮不判定（见 cli 的 run_watch）。
+
+use crate::{SourceStats, suspect_id};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// watch 默认检查间隔（秒）
+pub const DEFAULT_WATCH_INTERVAL_SECS: u64 = 60;
+/// 同一来源再次告警前的默认冷却时间（秒）
+pub const DEFAULT_ALERT_COOLDOWN_SECS: u64 = 30 * 60;
+
+/// `logtool watch` 专用参数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchOptions {
+    pub interval_secs: u64,
+    pub cooldown_secs: u64,
+    /// 每条告警通过 `sh -c` 执行的命令
+    pub on_alert: Option<String>,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            interval_secs: DEFAULT_WATCH_INTERVAL_SECS,
+            cooldown_secs: DEFAULT_ALERT_COOLDOWN_SECS,
+            on_alert: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertReason {
+    /// 首次出现
+    New,
+    /// 冷却期后仍然活跃
+    Repeat,
+    /// 冷却期内事件数达到上次告警时的两倍
+    CountDoubled,
+    /// 冷却期内严重级别升高
+    SeverityWorsened,
+}
+
+impl AlertReason {
+    pub fn slug(self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::Repeat => "repeat",
+            Self::CountDoubled => "count_doubled",
+            Self::SeverityWorsened => "severity_worsened",
+        }
+    }
+
+    pub fn label_cn(self) -> &'static str {
+        match self {
+            Self::New => "首次告警",
+            Self::Repeat => "冷却期后仍活跃",
+            Self::CountDoubled => "事件数翻倍",
+            Self::SeverityWorsened => "严重级别升高",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alert {
+    pub id: String,
+    pub count: u64,
+    pub priority: u8,
+    pub reason: AlertReason,
+    pub sample_message: String,
+}
+
+/// 上次告警时的情况
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlertRecord {
+    pub last_alert_at: u64,
+    pub count: u64,
+    pub priority: u8,
+    /// 最近一次检查中出现的时间
+    pub last_seen_at: u64,
+    /// `logtool ack` 确认的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acknowledged_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlertState {
+    /// 以 suspect_id 为键
+    pub records: BTreeMap<String, AlertRecord>,
+}
+
+impl AlertState {
+    /// 判定一轮分析结果；只考虑最高严重级别不低于 threshold 的来源，事件数不含维护窗口内的部分
+    pub fn evaluate(
+        &mut self,
+        suspects: &[SourceStats],
+        threshold: u8,
+        now: u64,
+        cooldown_secs: u64,
+    ) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for suspect in suspects.iter().filter(|s| s.worst_priority <= threshold) {
+            let count = suspect.count.saturating_sub(suspect.maintenance_count);
+            // 事件全在维护窗口内：不告警，也不算作仍然活跃
+            if count == 0 {
+                continue;
+            }
+            let id = suspect_id(suspect.kind, &suspect.source);
+            let reason = match self.records.get_mut(&id) {
+                None => Some(AlertReason::New),
+                Some(record) => {
+                    record.last_seen_at = now;
+                    if record.acknowledged_at.is_some() {
+                        (suspect.worst_priority < record.priority)
+                            .then_some(AlertReason::SeverityWorsened)
+                    } else if now.saturating_sub(record.last_alert_at) >= cooldown_secs {
+                        Some(AlertReason::Repeat)
+                    } else if count >= record.count.saturating_mul(2) {
+                        Some(AlertReason::CountDoubled)
+                    } else if suspect.worst_priority < record.priority {
+                        Some(AlertReason::SeverityWorsened)
+                    } else {
+                        None
+                    }
+                }
+            };
+            let Some(reason) = reason else {
+                continue;
+            };
+            self.records.insert(
+                id.clone(),
+                AlertRecord {
+                    last_alert_at: now,
+                    count,
+                    priority: suspect.worst_priority,
+                    last_seen_at: now,
+                    acknowledged_at: None,
+                },
+            );
+            alerts.push(Alert {
+                id,
+                count,
+                priority: suspect.worst_priority,
+                reason,
+                sample_message: suspect.sample_message.clone(),
+            });
+        }
+
+        // 不再出现且已过冷却期的来源视为恢复，下次出现重新按首次告警处理
+        self.records
+            .retain(|_, record| now.saturating_sub(record.last_seen_at) < cooldown_secs);
+        alerts
+    }
+
+    /// 确认一条活动告警；不存在时返回 false
+    pub fn acknowledge(&mut self, id: &str, now: u64) -> bool {
+        match self.records.get_mut(id) {
+            Some(record) => {
+                record.acknowledged_at = Some(now);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SourceKind;
+    use crate::test_support::sample_suspect;
+
+    fn reasons(state: &mut AlertState, count: u64, worst: u8, now: u64) -> Vec<AlertReason> {
+        let suspect = SourceStats {
+            sample_message: "failed".to_string(),
+            ..sample_suspect(SourceKind::Unit, "flappy.service", count, worst)
+        };
+        state
+            .evaluate(&[suspect], 3, now, 600)
+            .into_iter()
+            .map(|a| a.reason)
+            .collect()
+    }
+
+    #[test]
+    fn suppresses_until_count_doubles_or_severity_worsens() {
+        let mut state = AlertState::default();
+        assert_eq!(reasons(&mut state, 5, 3, 0), vec![AlertReason::New]);
+        assert!(reasons(&mut state, 9, 3, 60).is_empty());
+        assert_eq!(
+            reasons(&mut state, 10, 3, 120),
+            vec![AlertReason::CountDoubled]
+        );
+        assert!(reasons(&mut state, 12, 3, 180).is_empty());
+        assert_eq!(
+            reasons(&mut state, 12, 2, 240),
+            vec![AlertReason::SeverityWorsened]
+        );
+        // 冷却期（600 秒）过后仍活跃则重复提醒
+        assert_eq!(reasons(&mut state, 12, 2, 840), vec![AlertReason::Repeat]);
+    }
+
+    #[test]
+    fn forgets_resolved_suspects_and_skips_below_threshold() {
+        let mut state = AlertState::default();
+        assert_eq!(reasons(&mut state, 5, 3, 0), vec![AlertReason::New]);
+        assert!(state.evaluate(&[], 3, 300, 600).is_empty());
+        assert!(state.records.contains_key("unit:flappy.service"));
+        state.evaluate(&[], 3, 600, 600);
+        assert!(state.records.is_empty());
+
+        // 被规则降级到阈值以下的来源不告警
+        assert!(reasons(&mut state, 50, 6, 700).is_empty());
+    }
+
+    #[test]
+    fn events_inside_maintenance_windows_do_not_alert() {
+        let mut state = AlertState::default();
+        let mut maintenance = sample_suspect(SourceKind::Unit, "flappy.service", 40, 3);
+        maintenance.maintenance_count = 40;
+        assert!(state.evaluate(&[maintenance.clone()], 3, 0, 600).is_empty());
+        assert!(state.records.is_empty());
+
+        // 只有窗口外的事件计数：5 条 → 首次告警，之后窗口内再多也不算翻倍
+        maintenance.count = 45;
+        let alerts = state.evaluate(&[maintenance.clone()], 3, 60, 600);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].count, 5);
+        maintenance.count = 80;
+        maintenance.maintenance_count = 75;
+        assert!(state.evaluate(&[maintenance], 3, 120, 600).is_empty());
+    }
+
+    #[test]
+    fn acknowledged_alert_fires_again_only_when_severity_worsens() {
+        let mut state = AlertState::default();
+        assert_eq!(reasons(&mut state, 5, 3, 0), vec![AlertReason::New]);
+        assert!(state.acknowledge("unit:flappy.service", 10));
+        assert!(!state.acknowledge("unit:other.service", 10));
+
+        assert!(reasons(&mut state, 50, 3, 60).is_empty());
+        assert!(reasons(&mut state, 50, 3, 900).is_empty());
+        assert_eq!(
+            reasons(&mut state, 50, 2, 960),
+            vec![AlertReason::SeverityWorsened]
+        );
+        assert_eq!(state.records["unit:flappy.service"].acknowledged_at, None);
+    }
+}
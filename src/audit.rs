@@ -0,0 +1,490 @@
+// 审计拒绝 — 从审计记录中提取 AppArmor / SELinux（AVC）拒绝与 seccomp 拦截，解码进程、被拒绝的操作、
+// 对象与系统调用，按 (机制, 进程, 操作) 归并后单独成节，不再作为不透明的消息字符串计入排行
+//
+// journald 经审计 socket 收到的记录为 `_TRANSPORT=audit`（`AVC apparmor="DENIED" …`）；没有启用
+// systemd-journald-audit.socket 时，同样的记录由内核以 `audit: type=1400 audit(…): …` 写入 kmsg。
+// 两者一次查询：`journalctl _TRANSPORT=audit _TRANSPORT=kernel --grep`。查询失败时不列出。
+// 字符串字段可能被审计子系统写成十六进制（含空格等字符时），按原样解码。
+
+use crate::redact::Redactor;
+use crate::timeutil::format_local_minute;
+use crate::{Config, add_window_args, parse_json_event};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// 报告中最多列出的拒绝类别，按次数取最多的
+pub const MAX_DENIALS: usize = 10;
+const AUDIT_PATTERN: &str = "apparmor=\"DENIED\"|avc: +denied|type=1326|^SECCOMP ";
+/// arch=c000003e（x86_64）下常见的系统调用号
+const X86_64_SYSCALLS: &[(u32, &str)] = &[
+    (0, "read"),
+    (1, "write"),
+    (2, "open"),
+    (4, "stat"),
+    (9, "mmap"),
+    (10, "mprotect"),
+    (16, "ioctl"),
+    (21, "access"),
+    (41, "socket"),
+    (42, "connect"),
+    (49, "bind"),
+    (56, "clone"),
+    (57, "fork"),
+    (59, "execve"),
+    (62, "kill"),
+    (90, "chmod"),
+    (101, "ptrace"),
+    (105, "setuid"),
+    (157, "prctl"),
+    (165, "mount"),
+    (169, "reboot"),
+    (175, "init_module"),
+    (231, "exit_group"),
+    (257, "openat"),
+    (272, "unshare"),
+    (298, "perf_event_open"),
+    (310, "process_vm_readv"),
+    (313, "finit_module"),
+    (317, "seccomp"),
+    (321, "bpf"),
+    (322, "execveat"),
+    (425, "io_uring_setup"),
+    (435, "clone3"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditMechanism {
+    AppArmor,
+    Selinux,
+    Seccomp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditDenial {
+    pub mechanism: AuditMechanism,
+    /// 被拒绝的进程名（comm）
+    pub process: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exe: Option<String>,
+    /// AppArmor 配置名或 SELinux 源上下文（scontext）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// `open（r）`、`{ read } file`、`openat（ERRNO）`
+    pub operation: String,
+    /// 最近一次被拒绝的对象（name / path），seccomp 没有
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub count: u64,
+    /// 最近一次拒绝的时间（UTC 微秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_us: Option<u64>,
+    /// 可执行文件（或以路径命名的 AppArmor 配置）所属的包
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+}
+
+impl AuditDenial {
+    /// --redact：进程、路径与对象取自原始消息，同样遮盖（在反查所属包之后进行）
+    pub fn redact(&mut self, redactor: &Redactor) {
+        self.process = redactor.redact(&self.process);
+        for field in [&mut self.exe, &mut self.profile, &mut self.target] {
+            *field = field.as_deref().map(|value| redactor.redact(value));
+        }
+    }
+}
+
+/// 一条审计记录中解码出的拒绝
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Denial {
+    pub mechanism: AuditMechanism,
+    pub process: String,
+    pub exe: Option<String>,
+    pub profile: Option<String>,
+    pub operation: String,
+    pub target: Option<String>,
+}
+
+/// `key=value` / `key="value"` 字段；未加引号的十六进制字符串按审计编码解码
+fn fields(message: &str) -> HashMap<&str, String> {
+    let mut fields = HashMap::new();
+    let mut rest = message;
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].rsplit(' ').next().unwrap_or_default();
+        let after = &rest[eq + 1..];
+        let (value, tail) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                (
+                    quoted[..end].to_string(),
+                    quoted.get(end + 1..).unwrap_or_default(),
+                )
+            }
+            None => {
+                let end = after.find(' ').unwrap_or(after.len());
+                (decode_hex(&after[..end]), &after[end..])
+            }
+        };
+        if !key.is_empty() {
+            fields.entry(key).or_insert(value);
+        }
+        rest = tail;
+    }
+    fields
+}
+
+/// 审计子系统把含特殊字符的字符串写成大写十六进制（`2F746D702F6120622E747874`）
+fn decode_hex(value: &str) -> String {
+    let is_hex = value.len() >= 2
+        && value.len().is_multiple_of(2)
+        && value
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('A'..='F').contains(&c));
+    // 纯数字（pid=1234）不是编码后的字符串
+    if !is_hex || value.chars().all(|c| c.is_ascii_digit()) {
+        return value.to_string();
+    }
+    let bytes = (0..value.len())
+        .step_by(2)
+        .filter_map(|i| u8::from_str_radix(&value[i..i + 2], 16).ok())
+        .collect::<Vec<_>>();
+    match String::from_utf8(bytes) {
+        Ok(text) if !text.chars().any(char::is_control) => text,
+        _ => value.to_string(),
+    }
+}
+
+/// 解析一条审计记录；不是拒绝（AppArmor 的 ALLOWED/STATUS、seccomp 的 LOG/ALLOW 等）时为 None
+pub fn parse_denial(message: &str) -> Option<Denial> {
+    let fields = fields(message);
+    let field = |key: &str| fields.get(key).filter(|v| !v.is_empty()).cloned();
+    let process = field("comm")?;
+    if fields.get("apparmor").map(String::as_str) == Some("DENIED") {
+        let operation = field("operation")?;
+        let operation = match field("denied_mask") {
+            Some(mask) => format!("{operation}（{mask}）"),
+            None => operation,
+        };
+        return Some(Denial {
+            mechanism: AuditMechanism::AppArmor,
+            process,
+            exe: field("exe"),
+            profile: field("profile"),
+            operation,
+            target: field("name"),
+        });
+    }
+    if let Some(at) = message.find("avc:") {
+        let rest = message[at + 4..].trim_start();
+        let body = rest.strip_prefix("denied")?.trim_start();
+        let permissions = body.strip_prefix('{')?.split('}').next()?.trim();
+        let operation = match field("tclass") {
+            Some(class) => format!("{{ {permissions} }} {class}"),
+            None => format!("{{ {permissions} }}"),
+        };
+        return Some(Denial {
+            mechanism: AuditMechanism::Selinux,
+            process,
+            exe: field("exe"),
+            profile: field("scontext"),
+            operation,
+            target: field("path").or_else(|| field("name")),
+        });
+    }
+    let syscall = field("syscall")?.parse::<u32>().ok()?;
+    let action = seccomp_action(&field("code")?)?;
+    let name = match field("arch").as_deref() {
+        Some("c000003e") => X86_64_SYSCALLS
+            .iter()
+            .find(|(number, _)| *number == syscall)
+            .map(|(_, name)| name.to_string()),
+        _ => None,
+    }
+    .unwrap_or_else(|| format!("syscall {syscall}"));
+    Some(Denial {
+        mechanism: AuditMechanism::Seccomp,
+        process,
+        exe: field("exe"),
+        profile: None,
+        operation: format!("{name}（{action}）"),
+        target: None,
+    })
+}
+
+/// seccomp 过滤器返回值的动作部分；只记录、放行的不算拒绝
+fn seccomp_action(code: &str) -> Option<&'static str> {
+    let code = u32::from_str_radix(code.trim_start_matches("0x"), 16).ok()?;
+    match code & 0xffff_0000 {
+        0x8000_0000 => Some("KILL_PROCESS"),
+        0x0000_0000 => Some("KILL_THREAD"),
+        0x0003_0000 => Some("TRAP"),
+        0x0005_0000 => Some("ERRNO"),
+        0x7fc0_0000 => Some("USER_NOTIF"),
+        0x7ff0_0000 => Some("TRACE"),
+        _ => None,
+    }
+}
+
+/// 窗口内的审计拒绝，按次数降序取前 MAX_DENIALS 类；任何一步失败都按“没有拒绝”处理
+pub fn collect_denials(config: &Config) -> Vec<AuditDenial> {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager").arg("--quiet");
+    add_window_args(&mut cmd, config);
+    let Some(output) = cmd
+        .arg("_TRANSPORT=audit")
+        .arg("_TRANSPORT=kernel")
+        .arg(format!("--grep={AUDIT_PATTERN}"))
+        .arg("--output=json")
+        .arg("--output-fields=MESSAGE")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+    else {
+        return Vec::new();
+    };
+    let denials = group_denials(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| parse_json_event(line).ok())
+            .filter_map(|event| Some((parse_denial(&event.message)?, event.timestamp_us))),
+    );
+    #[cfg(feature = "packages")]
+    let denials = crate::packages::resolve_packages_for_denials(denials);
+    denials
+}
+
+/// 按 (机制, 进程, 操作, 配置) 归并，按次数降序、再按最近出现排序
+pub fn group_denials(events: impl IntoIterator<Item = (Denial, Option<u64>)>) -> Vec<AuditDenial> {
+    type Key = (AuditMechanism, String, String, Option<String>);
+    let mut groups: HashMap<Key, AuditDenial> = HashMap::new();
+    for (denial, timestamp_us) in events {
+        let key = (
+            denial.mechanism,
+            denial.process.clone(),
+            denial.operation.clone(),
+            denial.profile.clone(),
+        );
+        let entry = groups.entry(key).or_insert_with(|| AuditDenial {
+            mechanism: denial.mechanism,
+            process: denial.process.clone(),
+            exe: None,
+            profile: denial.profile.clone(),
+            operation: denial.operation.clone(),
+            target: None,
+            count: 0,
+            last_us: None,
+            package: None,
+        });
+        entry.count += 1;
+        if timestamp_us >= entry.last_us {
+            entry.last_us = timestamp_us;
+            entry.exe = denial.exe.or(entry.exe.take());
+            entry.target = denial.target.or(entry.target.take());
+        }
+    }
+    let mut denials = groups.into_values().collect::<Vec<_>>();
+    denials.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then(b.last_us.cmp(&a.last_us))
+            .then_with(|| a.process.cmp(&b.process))
+            .then_with(|| a.operation.cmp(&b.operation))
+    });
+    denials.truncate(MAX_DENIALS);
+    denials
+}
+
+fn mechanism_label(mechanism: AuditMechanism) -> &'static str {
+    match mechanism {
+        AuditMechanism::AppArmor => "AppArmor",
+        AuditMechanism::Selinux => "SELinux",
+        AuditMechanism::Seccomp => "seccomp",
+    }
+}
+
+/// `配置 /usr/sbin/cupsd，/usr/sbin/cupsd（cups-daemon）`
+fn attribution(denial: &AuditDenial) -> String {
+    let mut parts = Vec::new();
+    if let Some(profile) = &denial.profile {
+        let label = match denial.mechanism {
+            AuditMechanism::Selinux => "上下文",
+            _ => "配置",
+        };
+        parts.push(format!("{label} {profile}"));
+    }
+    match (&denial.exe, &denial.package) {
+        (Some(exe), Some(package)) => parts.push(format!("{exe}（{package}）")),
+        (Some(exe), None) => parts.push(exe.clone()),
+        (None, Some(package)) => parts.push(format!("包 {package}")),
+        (None, None) => {}
+    }
+    parts.join("，")
+}
+
+fn last_seen(denial: &AuditDenial, offset_secs: i64) -> Option<String> {
+    denial
+        .last_us
+        .map(|at| format!("最近 {}", format_local_minute(at, offset_secs)))
+}
+
+pub fn write_audit_section<W: Write>(
+    out: &mut W,
+    denials: &[AuditDenial],
+    offset_secs: i64,
+) -> io::Result<()> {
+    for denial in denials {
+        let mut line = format!(
+            "  ×{} [{}] {}  {}",
+            denial.count,
+            mechanism_label(denial.mechanism),
+            denial.process,
+            denial.operation
+        );
+        if let Some(target) = &denial.target {
+            line.push_str(&format!(" {target}"));
+        }
+        for extra in [Some(attribution(denial)), last_seen(denial, offset_secs)]
+            .into_iter()
+            .flatten()
+            .filter(|text| !text.is_empty())
+        {
+            line.push_str("  ");
+            line.push_str(&extra);
+        }
+        writeln!(out, "{line}")?;
+    }
+    Ok(())
+}
+
+/// 逐句版本（--linear）
+pub fn audit_sentences(denials: &[AuditDenial], offset_secs: i64) -> Vec<String> {
+    denials
+        .iter()
+        .map(|denial| {
+            let mut sentence = format!(
+                "{} 拒绝进程 {} 的 {} 共 {} 次",
+                mechanism_label(denial.mechanism),
+                denial.process,
+                denial.operation,
+                denial.count
+            );
+            if let Some(target) = &denial.target {
+                sentence.push_str(&format!("，对象 {target}"));
+            }
+            let attribution = attribution(denial);
+            if !attribution.is_empty() {
+                sentence.push_str(&format!("，{attribution}"));
+            }
+            if let Some(seen) = last_seen(denial, offset_secs) {
+                sentence.push_str(&format!("，{seen}"));
+            }
+            sentence.push('。');
+            sentence
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redaction_masks_users_in_paths() {
+        let mut denial = AuditDenial {
+            mechanism: AuditMechanism::AppArmor,
+            process: "evince".to_string(),
+            exe: Some("/usr/bin/evince".to_string()),
+            profile: Some("/usr/bin/evince".to_string()),
+            operation: "open（r）".to_string(),
+            target: Some("/home/alice/Documents/tax.pdf".to_string()),
+            count: 1,
+            last_us: None,
+            package: Some("evince".to_string()),
+        };
+        denial.redact(&Redactor::with_identity(
+            Vec::new(),
+            vec!["alice".to_string()],
+        ));
+        assert_eq!(
+            denial.target.as_deref(),
+            Some("/home/<user>/Documents/tax.pdf")
+        );
+        assert_eq!(denial.exe.as_deref(), Some("/usr/bin/evince"));
+        assert_eq!(denial.package.as_deref(), Some("evince"));
+    }
+
+    #[test]
+    fn decodes_apparmor_selinux_and_seccomp_denials() {
+        let apparmor = parse_denial(
+            r#"AVC apparmor="DENIED" operation="open" class="file" profile="/usr/sbin/cupsd" name="/etc/ssl/private/key.pem" pid=1234 comm="cupsd" requested_mask="r" denied_mask="r" fsuid=0 ouid=0"#,
+        )
+        .expect("应识别 AppArmor 拒绝");
+        assert_eq!(
+            apparmor,
+            Denial {
+                mechanism: AuditMechanism::AppArmor,
+                process: "cupsd".to_string(),
+                exe: None,
+                profile: Some("/usr/sbin/cupsd".to_string()),
+                operation: "open（r）".to_string(),
+                target: Some("/etc/ssl/private/key.pem".to_string()),
+            }
+        );
+        // 内核写入 kmsg 的格式，comm 为十六进制编码（含空格）
+        let hex = parse_denial(
+            r#"audit: type=1400 audit(1704069000.123:45): apparmor="DENIED" operation="exec" profile="snap.app" name="/usr/bin/sh" pid=9 comm=6D7920617070 requested_mask="x" denied_mask="x""#,
+        )
+        .expect("应识别内核格式");
+        assert_eq!(hex.process, "my app");
+        assert!(
+            parse_denial(r#"AVC apparmor="STATUS" operation="profile_load" name="x" pid=1 comm="apparmor_parser""#)
+                .is_none()
+        );
+
+        let selinux = parse_denial(
+            r#"AVC avc:  denied  { read write } for  pid=812 comm="httpd" name="index.html" dev="sda1" ino=1234 scontext=system_u:system_r:httpd_t:s0 tcontext=unconfined_u:object_r:user_home_t:s0 tclass=file permissive=0"#,
+        )
+        .expect("应识别 AVC 拒绝");
+        assert_eq!(selinux.operation, "{ read write } file");
+        assert_eq!(
+            selinux.profile.as_deref(),
+            Some("system_u:system_r:httpd_t:s0")
+        );
+        assert_eq!(selinux.target.as_deref(), Some("index.html"));
+
+        let seccomp = parse_denial(
+            r#"SECCOMP auid=1000 uid=1000 gid=1000 ses=2 pid=4321 comm="chrome" exe="/opt/google/chrome/chrome" sig=0 arch=c000003e syscall=257 compat=0 ip=0x7f3a code=0x50000"#,
+        )
+        .expect("应识别 seccomp 拦截");
+        assert_eq!(seccomp.operation, "openat（ERRNO）");
+        assert_eq!(seccomp.exe.as_deref(), Some("/opt/google/chrome/chrome"));
+        let logged =
+            r#"SECCOMP auid=1000 pid=1 comm="snap" arch=c000003e syscall=1 code=0x7ffc0000"#;
+        assert!(parse_denial(logged).is_none());
+
+        let base = 1_704_069_000_000_000; // 2024-01-01 00:30 UTC
+        let mut denials = group_denials([
+            (seccomp.clone(), Some(base)),
+            (apparmor.clone(), Some(base)),
+            (apparmor.clone(), Some(base + 60_000_000)),
+        ]);
+        assert_eq!(denials.len(), 2);
+        assert_eq!(denials[0].count, 2);
+        denials[0].package = Some("cups-daemon".to_string());
+        let mut out = Vec::new();
+        write_audit_section(&mut out, &denials, 0).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert_eq!(
+            text,
+            "  ×2 [AppArmor] cupsd  open（r） /etc/ssl/private/key.pem  配置 /usr/sbin/cupsd，包 cups-daemon  最近 2024-01-01 00:31\n  ×1 [seccomp] chrome  openat（ERRNO）  /opt/google/chrome/chrome  最近 2024-01-01 00:30\n"
+        );
+        assert_eq!(
+            audit_sentences(&denials, 0)[1],
+            "seccomp 拒绝进程 chrome 的 openat（ERRNO） 共 1 次，/opt/google/chrome/chrome，最近 2024-01-01 00:30。"
+        );
+    }
+}
@@ -0,0 +1,307 @@
+// 基线对比 — `logtool baseline save <文件>` 记下平常各来源的事件数与窗口长度，
+// 之后 `--against-baseline <文件>` 按每小时事件数对比，标出明显高于基线的来源，
+// 适合本来就有固定“底噪”的服务器：只关心比平常多出来的部分
+//
+// 基线与对比都在 CLI 一侧读写文件，daemon 不需要访问基线路径。两次运行应使用相同的过滤条件
+// （--priority、--unit 等），级别阈值不同时只提示、仍照常对比。写入在 `<基线文件>.lock` 锁下原子替换，
+// cron 与手动同时保存时不会交错。
+
+use crate::options::display_width;
+use crate::state::{LOCK_WAIT, StateLock};
+use crate::timeutil::{format_duration_cn, format_local_minute};
+use crate::{SourceKind, SourceStats, source_label_cn, suspect_id, write_file_atomically};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub const BASELINE_VERSION: u32 = 1;
+/// 每小时事件数达到基线的多少倍才算明显高于基线
+pub const RATE_FACTOR: u64 = 3;
+/// 本窗口少于这么多条的来源不标出，避免 1 条变 3 条这样的噪声
+pub const MIN_EVENTS: u64 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub version: u32,
+    /// 保存时间（UTC 微秒）
+    pub saved_at_us: u64,
+    /// 基线窗口长度（秒）
+    pub window_secs: u64,
+    /// 保存时的级别阈值（--priority）
+    pub priority: u8,
+    pub matched: usize,
+    pub sources: Vec<BaselineSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaselineSource {
+    pub kind: SourceKind,
+    pub source: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaselineDeviation {
+    pub id: String,
+    pub source_kind: SourceKind,
+    pub source: String,
+    /// 基线窗口内的事件数，基线中没有该来源时为 0
+    pub baseline: u64,
+    pub current: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BaselineReport {
+    pub path: String,
+    pub saved_at_us: u64,
+    pub baseline_window_secs: u64,
+    pub baseline_sources: usize,
+    pub window_secs: u64,
+    /// 明显高于基线的来源，按每小时事件数降序
+    pub exceeded: Vec<BaselineDeviation>,
+}
+
+impl Baseline {
+    /// 由一次分析的全部来源生成基线
+    pub fn from_suspects(
+        suspects: &[SourceStats],
+        matched: usize,
+        window_secs: u64,
+        priority: u8,
+        now_secs: u64,
+    ) -> Self {
+        Baseline {
+            version: BASELINE_VERSION,
+            saved_at_us: now_secs * 1_000_000,
+            window_secs,
+            priority,
+            matched,
+            sources: suspects
+                .iter()
+                .map(|s| BaselineSource {
+                    kind: s.kind,
+                    source: s.source.clone(),
+                    count: s.count,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), String> {
+        let json =
+            serde_json::to_vec_pretty(self).map_err(|err| format!("序列化基线失败：{err}"))?;
+        let _lock = StateLock::acquire(path, LOCK_WAIT)?;
+        write_file_atomically(path, &json)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| {
+            format!(
+                "读取基线文件 {} 失败：{err}\n修复：先运行 logtool baseline save {}",
+                path.display(),
+                path.display()
+            )
+        })?;
+        let baseline: Baseline = serde_json::from_str(&text).map_err(|err| {
+            format!(
+                "基线文件 {} 格式错误：{err}\n修复：重新运行 logtool baseline save {}",
+                path.display(),
+                path.display()
+            )
+        })?;
+        if baseline.version != BASELINE_VERSION || baseline.window_secs == 0 {
+            return Err(format!(
+                "基线文件 {} 的版本不受支持\n修复：重新运行 logtool baseline save {}",
+                path.display(),
+                path.display()
+            ));
+        }
+        Ok(baseline)
+    }
+}
+
+/// 本窗口 current 条（window_secs 秒）是否明显高于基线 baseline 条（baseline_secs 秒）
+fn exceeds(current: u64, window_secs: u64, baseline: u64, baseline_secs: u64) -> bool {
+    current >= MIN_EVENTS
+        && u128::from(current) * u128::from(baseline_secs)
+            >= u128::from(RATE_FACTOR) * u128::from(baseline) * u128::from(window_secs)
+}
+
+/// 全部来源与基线对比，最多列出 top 个
+pub fn compare_with_baseline(
+    baseline: &Baseline,
+    path: &str,
+    suspects: &[SourceStats],
+    window_secs: u64,
+    top: usize,
+) -> BaselineReport {
+    let counts = baseline
+        .sources
+        .iter()
+        .map(|s| ((s.kind, s.source.as_str()), s.count))
+        .collect::<HashMap<_, _>>();
+    let mut exceeded = suspects
+        .iter()
+        .filter_map(|s| {
+            let previous = counts
+                .get(&(s.kind, s.source.as_str()))
+                .copied()
+                .unwrap_or(0);
+            exceeds(s.count, window_secs, previous, baseline.window_secs).then(|| {
+                BaselineDeviation {
+                    id: suspect_id(s.kind, &s.source),
+                    source_kind: s.kind,
+                    source: s.source.clone(),
+                    baseline: previous,
+                    current: s.count,
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    exceeded.sort_by(|a, b| b.current.cmp(&a.current).then_with(|| a.id.cmp(&b.id)));
+    exceeded.truncate(top);
+    BaselineReport {
+        path: path.to_string(),
+        saved_at_us: baseline.saved_at_us,
+        baseline_window_secs: baseline.window_secs,
+        baseline_sources: baseline.sources.len(),
+        window_secs,
+        exceeded,
+    }
+}
+
+/// 每小时事件数，保留一位小数
+fn per_hour(count: u64, window_secs: u64) -> String {
+    let tenths = u128::from(count) * 36_000 / u128::from(window_secs.max(1));
+    format!("{}.{}", tenths / 10, tenths % 10)
+}
+
+/// 本窗口速率为基线的几倍，保留一位小数；基线中没有该来源时为 None
+fn ratio(report: &BaselineReport, deviation: &BaselineDeviation) -> Option<String> {
+    if deviation.baseline == 0 {
+        return None;
+    }
+    let tenths = u128::from(deviation.current) * u128::from(report.baseline_window_secs) * 10
+        / (u128::from(deviation.baseline) * u128::from(report.window_secs.max(1)));
+    Some(format!("{}.{}", tenths / 10, tenths % 10))
+}
+
+fn baseline_label(report: &BaselineReport, offset_secs: i64) -> String {
+    format!(
+        "{}（保存于 {}，窗口 {}，{} 个来源）",
+        report.path,
+        format_local_minute(report.saved_at_us, offset_secs),
+        format_duration_cn(report.baseline_window_secs),
+        report.baseline_sources
+    )
+}
+
+pub fn write_baseline_section<W: Write>(
+    out: &mut W,
+    report: &BaselineReport,
+    offset_secs: i64,
+) -> io::Result<()> {
+    writeln!(out, "  基线：{}", baseline_label(report, offset_secs))?;
+    if report.exceeded.is_empty() {
+        writeln!(out, "  没有来源的每小时事件数达到基线的 {RATE_FACTOR} 倍")?;
+        return Ok(());
+    }
+    writeln!(out, "  每小时事件数达到基线 {RATE_FACTOR} 倍以上的来源：")?;
+    for deviation in &report.exceeded {
+        let current = per_hour(deviation.current, report.window_secs);
+        let (label, change) = match ratio(report, deviation) {
+            Some(ratio) => (
+                "偏高",
+                format!(
+                    "{} → {current} 条/小时（{ratio} 倍）",
+                    per_hour(deviation.baseline, report.baseline_window_secs)
+                ),
+            ),
+            None => ("新出现", format!("基线中没有 → {current} 条/小时")),
+        };
+        writeln!(
+            out,
+            "  {label}{}  [{}] {}  {change}",
+            " ".repeat(6 - display_width(label)),
+            source_label_cn(deviation.source_kind),
+            deviation.source
+        )?;
+    }
+    Ok(())
+}
+
+/// 逐句版本（--linear）
+pub fn baseline_sentences(report: &BaselineReport, offset_secs: i64) -> Vec<String> {
+    let mut sentences = vec![format!(
+        "与基线 {} 对比：{} 个来源的每小时事件数达到基线的 {RATE_FACTOR} 倍以上。",
+        baseline_label(report, offset_secs),
+        report.exceeded.len()
+    )];
+    sentences.extend(report.exceeded.iter().map(|deviation| {
+        let current = per_hour(deviation.current, report.window_secs);
+        match ratio(report, deviation) {
+            Some(ratio) => format!(
+                "{} {} 每小时 {current} 条，基线每小时 {} 条，为基线的 {ratio} 倍。",
+                source_label_cn(deviation.source_kind),
+                deviation.source,
+                per_hour(deviation.baseline, report.baseline_window_secs)
+            ),
+            None => format!(
+                "{} {} 每小时 {current} 条，基线中没有该来源。",
+                source_label_cn(deviation.source_kind),
+                deviation.source
+            ),
+        }
+    }));
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_suspect;
+
+    #[test]
+    fn flags_sources_well_above_the_noise_floor() {
+        // 基线为一天，本窗口为两小时
+        let baseline = Baseline::from_suspects(
+            &[
+                sample_suspect(SourceKind::Unit, "noisy.service", 480, 3),
+                sample_suspect(SourceKind::Unit, "web.service", 24, 3),
+                sample_suspect(SourceKind::Unit, "cron.service", 24, 3),
+            ],
+            528,
+            86_400,
+            3,
+            1_704_069_000,
+        );
+        let current = [
+            sample_suspect(SourceKind::Unit, "noisy.service", 50, 3), // 每小时 25 条，基线 20 条：正常
+            sample_suspect(SourceKind::Unit, "web.service", 12, 3), // 每小时 6 条，基线 1 条：6 倍
+            sample_suspect(SourceKind::Unit, "fresh.service", 8, 3), // 基线中没有
+            sample_suspect(SourceKind::Unit, "cron.service", 4, 3), // 每小时 2 条，但少于 MIN_EVENTS
+            sample_suspect(SourceKind::Unit, "tiny.service", 2, 3), // 基线中没有，但太少
+        ];
+        let report = compare_with_baseline(&baseline, "/tmp/base.json", &current, 7200, 10);
+        let flagged = report
+            .exceeded
+            .iter()
+            .map(|d| (d.source.as_str(), d.baseline, d.current))
+            .collect::<Vec<_>>();
+        assert_eq!(flagged, [("web.service", 24, 12), ("fresh.service", 0, 8)]);
+
+        let mut out = Vec::new();
+        write_baseline_section(&mut out, &report, 0).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert!(
+            text.contains("  基线：/tmp/base.json（保存于 2024-01-01 00:30，窗口 1 天，3 个来源）")
+        );
+        assert!(text.contains("  偏高    [服务单元] web.service  1.0 → 6.0 条/小时（6.0 倍）"));
+        assert!(text.contains("  新出现  [服务单元] fresh.service  基线中没有 → 4.0 条/小时"));
+        assert_eq!(
+            baseline_sentences(&report, 0)[1],
+            "服务单元 web.service 每小时 6.0 条，基线每小时 1.0 条，为基线的 6.0 倍。"
+        );
+    }
+}
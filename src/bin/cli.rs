@@ -11,29 +11,83 @@
 //   logtool doctor                            # 运行环境自检
 //   logtool boots                             # 查看启动周期列表
 
+use logtool::alert::{Alert, WatchOptions};
+use logtool::baseline::{self, Baseline};
+use logtool::compare::{self, CompareResponse};
+use logtool::diff;
+use logtool::options;
+use logtool::packages::{self, PackageMap};
+use logtool::rules::{self, LintLevel, RuleSet};
+use logtool::settings::{self, MaintenanceMatcher, Settings};
+use logtool::sink::open_forward_sink;
+use logtool::socket::{self, SOCKET_NAME};
+use logtool::state::{self, Note, State};
+use logtool::template::{render_template, template_context};
+use logtool::timeutil::{
+    format_duration_cn, format_local_minute, format_local_second, local_utc_offset_secs,
+    resolve_time_spec, unix_now_secs,
+};
+use logtool::trend;
+use logtool::viewer::{StatusDocument, VIEWER_SOCKET_NAME, ViewerSnapshot};
 use logtool::{
-    Action, AnalyzeResponse, Config, ErrorResponse, RunMode, SOCKET_PATH, StreamLine, help_text,
-    parse_args, print_analysis_report,
+    Action, AnalyzeResponse, Config, DEFAULT_SINCE, DaemonStats, ErrorResponse, LiveFilters,
+    ReportFormat, ResourceStats, RotatingFileWriter, RunMode, SOCKET_PATH,
+    STREAM_OUTPUT_ROTATE_BYTES, StreamControl, StreamErrorKind, StreamLine, SuspectEvidence,
+    analyze_journal, daemon_error, emergency_banner, fail_on_triggered, fit_response,
+    format_last_words_line, format_note, format_summary_line, help_text, looks_like_clean_shutdown,
+    parse_args, parse_boot_list, parse_json_event, parse_live_command, parse_sample_line,
+    priority_label_cn, read_journal_events, spawn_stream_control_reader,
+    stream_journal_live_to_writer, stream_journal_to_writer, suspect_id, truncate_stream_line,
+    write_analysis_report, write_evidence, write_file_atomically, write_json_line,
+    write_linear_report, write_logfmt_suspects, write_ndjson_suspects,
 };
 use std::fs;
-use std::io::{self, BufRead, BufReader, Write};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::net::Shutdown;
 use std::os::unix::fs::{MetadataExt, PermissionsExt};
 use std::os::unix::net::UnixStream;
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicI32, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 use std::{env, process};
 
+const FORWARD_OPEN_TIMEOUT: Duration = Duration::from_secs(5);
+/// logtool status 等待查看 socket 响应的上限；状态栏轮询时不应卡住
+const STATUS_READ_TIMEOUT: Duration = Duration::from_secs(2);
+/// doctor 只校验最近写入的几个 journal 文件，并限制 journalctl --verify 的耗时
+const VERIFY_RECENT_FILES: usize = 4;
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(15);
+const JOURNAL_DIRS: &[&str] = &["/var/log/journal", "/run/log/journal"];
+const SERVICE_UNIT_PATHS: &[&str] = &[
+    "/etc/systemd/system/logtool.service",
+    "/lib/systemd/system/logtool.service",
+];
+/// --fail-on 触发时的退出码（1 保留给运行错误）
+const EXIT_FAIL_ON: i32 = 2;
+/// 流模式下 journalctl 自身失败（参数无效、中途退出）时的退出码
+const EXIT_STREAM_FAILED: i32 = 3;
+
+/// 单次执行模式下要求的非零退出码；交互模式每条命令后清零
+static EXIT_STATUS: AtomicI32 = AtomicI32::new(0);
+
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
     let result = if args.is_empty() {
-        run_interactive_shell()
+        maybe_run_first_run_wizard().and_then(|()| run_interactive_shell())
     } else {
         run_single_command(args)
     };
 
+    let status = EXIT_STATUS.load(AtomicOrdering::Relaxed);
     if let Err(err) = result {
         eprintln!("错误：{err}");
-        process::exit(1);
+        process::exit(if status != 0 { status } else { 1 });
+    }
+    if status != 0 {
+        process::exit(status);
     }
 }
 
@@ -54,11 +108,77 @@ fn execute_action(action: Action) -> Result<(), String> {
             Ok(())
         }
         Action::Doctor => run_doctor(),
+        Action::ForceUnlock => run_force_unlock(),
         Action::ListBoots => print_boot_list(),
-        Action::Run(config) => send_request(&config),
+        Action::LastWords { boot, lines } => run_last_words(&boot, lines),
+        Action::Ack { id } => run_ack(&id),
+        Action::Map { scan, json } => run_map(scan, json),
+        Action::Note { id, text } => run_note(id.as_deref(), text),
+        Action::Diff { old, new, json } => run_diff(&old, &new, json),
+        Action::Man => {
+            print!("{}", options::render_man_page());
+            Ok(())
+        }
+        Action::Completions { shell } => {
+            print!("{}", options::render_completions(&shell)?);
+            Ok(())
+        }
+        Action::RulesTest { sample, rules_path } => {
+            run_rules_test(sample.as_deref(), rules_path.as_deref())
+        }
+        Action::RulesLint { rules_path } => run_rules_lint(rules_path.as_deref()),
+        Action::Status { json } => run_status(json),
+        Action::Stats { rules } => {
+            if Settings::load()?.local_mode {
+                return Err(
+                    "本地模式下没有守护进程统计\n修复：在配置文件中关闭 local_mode 并启动 logtool 服务"
+                        .to_string(),
+                );
+            }
+            let config = Config {
+                mode: RunMode::Stats,
+                ..Config::default()
+            };
+            let stream = connect_and_send(&config)?;
+            handle_stats_response(&stream, rules)
+        }
+        Action::Run(mut config) => {
+            let settings = Settings::load()?;
+            apply_settings(&mut config, &settings);
+            send_request(&config, settings.local_mode)
+        }
+        Action::Watch {
+            mut config,
+            options,
+        } => {
+            let settings = Settings::load()?;
+            apply_settings(&mut config, &settings);
+            run_watch(&config, &options, settings.local_mode)
+        }
+        Action::BaselineSave { mut config, path } => {
+            let settings = Settings::load()?;
+            apply_settings(&mut config, &settings);
+            run_baseline_save(&config, &path, settings.local_mode)
+        }
     }
 }
 
+fn apply_settings(config: &mut Config, settings: &Settings) {
+    for warning in &settings.warnings {
+        eprintln!("警告：{warning}");
+    }
+    config.maintenance_windows = settings.maintenance_windows.clone();
+    if let Some(since) = &settings.default_since
+        && !config.since_explicit
+        && config.since.as_deref() == Some(DEFAULT_SINCE)
+    {
+        config.since = Some(since.clone());
+    }
+    config.max_message_chars = config.max_message_chars.or(settings.max_message_chars);
+    config.max_line_bytes = config.max_line_bytes.or(settings.max_line_bytes);
+    config.max_response_bytes = config.max_response_bytes.or(settings.max_response_bytes);
+}
+
 fn run_interactive_shell() -> Result<(), String> {
     println!("进入 logtool 交互模式。输入 help 查看命令，输入 exit 退出。");
 
@@ -105,6 +225,7 @@ fn run_interactive_shell() -> Result<(), String> {
         if let Err(err) = run_single_command(args) {
             eprintln!("错误：{err}");
         }
+        EXIT_STATUS.store(0, AtomicOrdering::Relaxed);
     }
 
     Ok(())
@@ -192,16 +313,84 @@ fn split_interactive_line(line: &str) -> Result<Vec<String>, String> {
     Ok(args)
 }
 
-fn send_request(config: &Config) -> Result<(), String> {
-    // 连接守护进程
-    let mut stream = UnixStream::connect(SOCKET_PATH).map_err(|err| {
+fn send_request(config: &Config, local_mode: bool) -> Result<(), String> {
+    if local_mode && config.archive {
+        return Err(
+            "本地模式下没有守护进程归档\n修复：在配置文件中关闭 local_mode，或改用 --output 保存到本地文件"
+                .to_string(),
+        );
+    }
+    let stream = if local_mode {
+        spawn_local_worker(config)?
+    } else {
+        connect_and_send(config)?
+    };
+
+    // 读取响应
+    match config.mode {
+        RunMode::Analyze => handle_analyze_response(&stream, config),
+        RunMode::Stream => handle_stream_response(&stream, config),
+        RunMode::Stats => handle_stats_response(&stream, false),
+        RunMode::PackageMap => Err("包映射请通过 logtool map 查询".to_string()),
+        RunMode::Compare => handle_compare_response(&stream, config),
+    }
+}
+
+/// 本地模式：在线程中执行 daemon 的同一套逻辑，通过 socketpair 复用响应处理
+fn spawn_local_worker(config: &Config) -> Result<UnixStream, String> {
+    let (reader, mut writer) = UnixStream::pair().map_err(|e| format!("创建本地通道失败：{e}"))?;
+    let config = config.clone();
+    thread::spawn(move || {
+        let result = match config.mode {
+            RunMode::Analyze => analyze_journal(&config).and_then(|mut response| {
+                if let Some(limit) = config.max_response_bytes {
+                    fit_response(&mut response, limit);
+                }
+                write_json_line(&mut writer, &response, "分析响应")
+            }),
+            // 失败已在流内回报
+            RunMode::Stream if config.live => {
+                let live = Arc::new(Mutex::new(LiveFilters::from_config(&config)));
+                let controls = writer.try_clone().map(|control_end| {
+                    spawn_stream_control_reader(BufReader::new(control_end), Arc::clone(&live))
+                });
+                let _ = stream_journal_live_to_writer(&config, &mut writer, Some(&live));
+                let _ = writer.shutdown(Shutdown::Read);
+                if let Ok(controls) = controls {
+                    let _ = controls.join();
+                }
+                Ok(())
+            }
+            RunMode::Stream => {
+                let _ = stream_journal_to_writer(&config, &mut writer);
+                Ok(())
+            }
+            RunMode::Stats => Err("本地模式下没有守护进程统计".to_string()),
+            RunMode::PackageMap => Err("本地模式下没有守护进程包映射".to_string()),
+            RunMode::Compare => compare::compare_units(&config)
+                .and_then(|response| write_json_line(&mut writer, &response, "对比响应")),
+        };
+        if let Err(err) = result {
+            let _ = write_json_line(&mut writer, &daemon_error(err), "错误响应");
+        }
+    });
+    Ok(reader)
+}
+
+fn connect_and_send(config: &Config) -> Result<UnixStream, String> {
+    // 连接守护进程：与 daemon 相同的目录回退顺序
+    let dirs = socket::env_socket_dirs(None);
+    let (mut stream, _) = socket::connect_first(&dirs, SOCKET_NAME).map_err(|err| {
         format!(
-             "无法连接到 logtool 守护进程（{SOCKET_PATH}）：{err}\n\n\
+             "无法连接到 logtool 守护进程（{err}）\n\n\
              可能的原因：\n\
              1. 守护进程未启动 → 运行：sudo systemctl start logtool\n\
              2. 权限不足（未加入组）→ 运行：sudo usermod -aG logtool $USER\n\
              3. 权限不足（组已加入但当前会话未生效）→ 运行：newgrp logtool（或注销后重新登录）\n\
-             4. 首次使用 → 先安装服务：sudo cp logtool.service /etc/systemd/system/ && sudo systemctl start logtool"
+             4. 首次使用 → 先安装服务：sudo cp logtool.service /etc/systemd/system/ && sudo systemctl start logtool\n\
+             5. 不使用守护进程 → 在配置文件中设置 \"local_mode\": true（以当前用户身份直接读取日志）\n\
+             6. 守护进程使用了其他 Socket 目录（/run 不可写的容器、CI）→ 设置 {}=<目录>",
+            socket::SOCKET_DIR_ENV
         )
     })?;
 
@@ -215,44 +404,582 @@ fn send_request(config: &Config) -> Result<(), String> {
         .write_all(b"\n")
         .map_err(|e| format!("发送换行符失败：{e}"))?;
     stream.flush().map_err(|e| format!("刷新请求失败：{e}"))?;
+    Ok(stream)
+}
 
-    // 读取响应
-    match config.mode {
-        RunMode::Analyze => handle_analyze_response(&stream),
-        RunMode::Stream => handle_stream_response(&stream),
+fn request_analysis(config: &Config, local_mode: bool) -> Result<AnalyzeResponse, String> {
+    let stream = if local_mode {
+        spawn_local_worker(config)?
+    } else {
+        connect_and_send(config)?
+    };
+    read_single_response_within(&stream, config.max_response_bytes)
+}
+
+/// watch：按间隔重复分析，经 AlertState 去重后输出告警；单轮失败只警告不退出
+///
+/// 告警记录保存在状态文件中：每轮先重新读取（获取其他进程写入的 ack），判定后写回。
+fn run_watch(config: &Config, options: &WatchOptions, local_mode: bool) -> Result<(), String> {
+    let threshold = config.priority.threshold();
+    let state_path = state::require_state_path()?;
+    let mut state = State::load_from(&state_path)?;
+    let mut maintenance =
+        MaintenanceMatcher::new(&config.maintenance_windows, local_utc_offset_secs());
+    let mut in_maintenance = false;
+    eprintln!(
+        "watch：每 {} 秒检查一次（级别 {threshold} 及更严重，冷却 {} 秒），告警状态：{}，按 Ctrl+C 退出",
+        options.interval_secs,
+        options.cooldown_secs,
+        state_path.display()
+    );
+
+    loop {
+        // 维护窗口内整轮不判定告警，窗口结束后照常检查
+        let paused = maintenance.contains(unix_now_secs().saturating_mul(1_000_000));
+        if paused != in_maintenance {
+            in_maintenance = paused;
+            if paused {
+                eprintln!("watch：处于维护窗口内，暂停告警");
+            } else {
+                eprintln!("watch：维护窗口结束，恢复告警");
+            }
+        }
+        if paused {
+            thread::sleep(Duration::from_secs(options.interval_secs));
+            continue;
+        }
+        match request_analysis(config, local_mode) {
+            Ok(response) => {
+                let now = unix_now_secs();
+                let suspects = &response.suspects[..response.suspects.len().min(config.top)];
+                let updated = State::update(&state_path, |latest| {
+                    let alerts =
+                        latest
+                            .alerts
+                            .evaluate(suspects, threshold, now, options.cooldown_secs);
+                    state = latest.clone();
+                    Ok(alerts)
+                });
+                let alerts = match updated {
+                    Ok(alerts) => alerts,
+                    Err(err) => {
+                        eprintln!("警告：{err}，沿用内存中的告警状态（本轮不保存）");
+                        state
+                            .alerts
+                            .evaluate(suspects, threshold, now, options.cooldown_secs)
+                    }
+                };
+                for alert in alerts {
+                    emit_alert(&alert, now, options.on_alert.as_deref());
+                }
+            }
+            Err(err) => eprintln!("警告：本轮检查失败：{err}"),
+        }
+        thread::sleep(Duration::from_secs(options.interval_secs));
     }
 }
 
-fn handle_analyze_response(stream: &UnixStream) -> Result<(), String> {
-    let reader = BufReader::new(stream);
-    let mut lines = reader.lines();
+/// baseline save：分析一次，把全部来源的事件数与窗口长度写入基线文件
+fn run_baseline_save(config: &Config, path: &str, local_mode: bool) -> Result<(), String> {
+    let (since, until) = trend::current_window(
+        config,
+        "baseline save",
+        unix_now_secs(),
+        local_utc_offset_secs(),
+    )?;
+    let response = request_analysis(config, local_mode)?;
+    let baseline = Baseline::from_suspects(
+        &response.suspects,
+        response.metrics.matched,
+        until - since,
+        config.priority.threshold(),
+        unix_now_secs(),
+    );
+    baseline.save_to(Path::new(path))?;
+    eprintln!(
+        "基线已写入：{path}（{} 个来源，共 {} 条，窗口 {}）",
+        baseline.sources.len(),
+        baseline.matched,
+        format_duration_cn(baseline.window_secs)
+    );
+    Ok(())
+}
+
+fn run_diff(old_path: &str, new_path: &str, json: bool) -> Result<(), String> {
+    let old = diff::load_snapshot(Path::new(old_path))?;
+    let new = diff::load_snapshot(Path::new(new_path))?;
+    let report = diff::diff_snapshots(&old, old_path, &new, new_path);
+    let mut buffer = Vec::new();
+    if json {
+        write_json_line(&mut buffer, &report, "快照对比")?;
+    } else {
+        diff::write_diff(&mut buffer, &report).map_err(|e| format!("生成对比失败：{e}"))?;
+    }
+    let _ = io::stdout().write_all(&buffer);
+    Ok(())
+}
+
+fn run_note(id: Option<&str>, text: Option<String>) -> Result<(), String> {
+    let path = state::require_state_path()?;
+    let Some(id) = id else {
+        let state = State::load_from(&path)?;
+        if state.notes.is_empty() {
+            println!("暂无备注");
+        }
+        for (id, note) in &state.notes {
+            println!("{id}：{}", format_note(note));
+        }
+        return Ok(());
+    };
+
+    match text {
+        Some(text) => {
+            State::update(&path, |state| {
+                state.notes.insert(
+                    id.to_string(),
+                    Note {
+                        text,
+                        author: state::current_user(),
+                        updated_at: unix_now_secs(),
+                    },
+                );
+                Ok(())
+            })?;
+            println!("已为 {id} 添加备注");
+        }
+        None => {
+            State::update(&path, |state| match state.notes.remove(id) {
+                Some(_) => Ok(()),
+                None => Err(format!("{id} 没有备注")),
+            })?;
+            println!("已清除 {id} 的备注");
+        }
+    }
+    Ok(())
+}
+
+fn run_force_unlock() -> Result<(), String> {
+    let path = state::require_state_path()?;
+    let lock = state::lock_path(&path);
+    match state::force_unlock(&path)? {
+        None => println!("没有状态锁：{}", lock.display()),
+        Some((Some(pid), true)) => {
+            println!("已移除状态锁：{}", lock.display());
+            println!("警告：持锁进程 {pid} 仍在运行，它写回时可能覆盖其他进程的修改");
+        }
+        Some((Some(pid), false)) => {
+            println!("已移除状态锁：{}（持锁进程 {pid} 已退出）", lock.display());
+        }
+        Some((None, _)) => println!("已移除状态锁：{}", lock.display()),
+    }
+    Ok(())
+}
+
+/// 从本地状态填入备注；状态文件不可用时只提示，不影响报告
+fn attach_notes(response: &mut AnalyzeResponse) {
+    let Some(path) = state::state_path() else {
+        return;
+    };
+    let state = match State::load_from(&path) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("警告：{err}，报告中不显示备注");
+            return;
+        }
+    };
+    if state.notes.is_empty() {
+        return;
+    }
+    for suspect in &mut response.suspects {
+        suspect.note = state
+            .notes
+            .get(&suspect_id(suspect.kind, &suspect.source))
+            .cloned();
+    }
+}
+
+/// --against-baseline：按本窗口长度与基线对比；级别阈值与保存时不同只提示
+fn attach_baseline(
+    response: &mut AnalyzeResponse,
+    config: &Config,
+    path: &str,
+    baseline: &Baseline,
+) -> Result<(), String> {
+    let (since, until) = trend::current_window(
+        config,
+        "--against-baseline",
+        unix_now_secs(),
+        local_utc_offset_secs(),
+    )?;
+    let threshold = config.priority.threshold();
+    if baseline.priority != threshold {
+        eprintln!(
+            "警告：基线按级别 {} 及更严重保存，本次为级别 {threshold}，对比结果可能偏高或偏低",
+            baseline.priority
+        );
+    }
+    response.baseline = Some(baseline::compare_with_baseline(
+        baseline,
+        path,
+        &response.suspects,
+        until - since,
+        config.top,
+    ));
+    Ok(())
+}
+
+fn run_ack(id: &str) -> Result<(), String> {
+    let path = state::require_state_path()?;
+    State::update(&path, |state| {
+        if state.alerts.acknowledge(id, unix_now_secs()) {
+            return Ok(());
+        }
+        let active = state.alerts.records.keys().cloned().collect::<Vec<_>>();
+        let hint = if active.is_empty() {
+            "当前没有活动告警".to_string()
+        } else {
+            format!("活动告警：{}", active.join("、"))
+        };
+        Err(format!("没有 {id} 的活动告警\n{hint}"))
+    })?;
+    println!("已确认 {id}：此后仅在严重级别升高时再次告警");
+    Ok(())
+}
+
+/// 守护进程已学到的映射 + 可选的 dpkg 数据库全量扫描；冲突时以扫描结果为准
+fn run_map(scan: bool, json: bool) -> Result<(), String> {
+    let mut map = PackageMap::default();
+    if Settings::load()?.local_mode {
+        if !scan {
+            return Err(
+                "本地模式下没有守护进程缓存的包映射\n修复：运行 logtool map --scan 直接扫描 dpkg 数据库"
+                    .to_string(),
+            );
+        }
+    } else {
+        let config = Config {
+            mode: RunMode::PackageMap,
+            ..Config::default()
+        };
+        match connect_and_send(&config).and_then(|stream| read_single_response(&stream)) {
+            Ok(learned) => map = learned,
+            Err(err) if scan => {
+                let reason = err.lines().next().unwrap_or_default();
+                eprintln!("提示：未能获取守护进程已学到的映射（{reason}），仅输出扫描结果");
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    if scan {
+        map.merge(packages::scan_dpkg_info(Path::new(
+            packages::DPKG_INFO_DIR,
+        ))?);
+    }
+
+    // 映射可能有上千行，常接 head/grep；管道提前关闭不算错误
+    if json {
+        let mut text =
+            serde_json::to_string_pretty(&map).map_err(|e| format!("序列化包映射失败：{e}"))?;
+        text.push('\n');
+        let _ = io::stdout().write_all(text.as_bytes());
+        return Ok(());
+    }
+    if map.is_empty() {
+        println!("尚未学到任何包映射（守护进程只记录分析中反查过的来源）");
+        println!("提示：运行 logtool map --scan 扫描 dpkg 数据库得到完整映射");
+        return Ok(());
+    }
+    let _ = io::stdout().write_all(format_package_map(&map).as_bytes());
+    Ok(())
+}
+
+fn format_package_map(map: &PackageMap) -> String {
+    let mut out = String::new();
+    for (title, entries) in [("服务单元", &map.units), ("可执行文件", &map.executables)] {
+        if entries.is_empty() {
+            continue;
+        }
+        let width = entries.keys().map(|k| k.chars().count()).max().unwrap_or(0);
+        out.push_str(&format!("{title}（{}）：\n", entries.len()));
+        for (source, package) in entries {
+            out.push_str(&format!("  {source:<width$}  {package}\n"));
+        }
+    }
+    out
+}
+
+fn emit_alert(alert: &Alert, now: u64, command: Option<&str>) {
+    let when = format_local_minute(now * 1_000_000, local_utc_offset_secs());
+    println!("{}", format_alert_line(alert, &when));
+    let _ = io::stdout().flush();
+
+    let Some(command) = command else {
+        return;
+    };
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("LOGTOOL_ALERT_ID", &alert.id)
+        .env("LOGTOOL_ALERT_COUNT", alert.count.to_string())
+        .env("LOGTOOL_ALERT_PRIORITY", alert.priority.to_string())
+        .env("LOGTOOL_ALERT_REASON", alert.reason.slug())
+        .env("LOGTOOL_ALERT_MESSAGE", &alert.sample_message)
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("警告：告警命令退出状态异常：{status}"),
+        Err(err) => eprintln!("警告：执行告警命令失败：{err}"),
+    }
+}
 
-    let response_line = lines
-        .next()
-        .ok_or_else(|| "守护进程无响应".to_string())?
+fn format_alert_line(alert: &Alert, when: &str) -> String {
+    format!(
+        "[{when}] 告警 {} | 事件数={} | 最高严重级别={}({}) | 原因={}",
+        alert.id,
+        alert.count,
+        alert.priority,
+        priority_label_cn(alert.priority),
+        alert.reason.label_cn()
+    )
+}
+
+/// 读取 daemon 的单行 JSON 响应；若为 ErrorResponse 则转换为错误信息
+fn read_single_response<T: serde::de::DeserializeOwned>(stream: &UnixStream) -> Result<T, String> {
+    read_single_response_within(stream, None)
+}
+
+/// 同 read_single_response；max_bytes（--max-resp-bytes）为 Some 时超长的响应不读入内存，直接报错
+fn read_single_response_within<T: serde::de::DeserializeOwned>(
+    stream: &UnixStream,
+    max_bytes: Option<usize>,
+) -> Result<T, String> {
+    let limit = max_bytes.map_or(u64::MAX, |max| max as u64 + 1);
+    let mut reader = BufReader::new(stream).take(limit);
+    let mut response_line = String::new();
+    let read = reader
+        .read_line(&mut response_line)
         .map_err(|e| format!("读取响应失败：{e}"))?;
+    if read == 0 {
+        return Err("守护进程无响应".to_string());
+    }
+    if let Some(max) = max_bytes
+        && !response_line.ends_with('\n')
+        && response_line.len() > max
+    {
+        return Err(format!(
+            "响应超过 --max-resp-bytes 上限（{max} 字节）\n修复：升级 logtool 守护进程（旧版本不会压缩响应），或调大该上限"
+        ));
+    }
+    let response_line = response_line.trim_end_matches('\n');
 
-    let response: AnalyzeResponse = match serde_json::from_str(&response_line) {
-        Ok(response) => response,
+    match serde_json::from_str(response_line) {
+        Ok(response) => Ok(response),
         Err(_) => {
-            if let Ok(error) = serde_json::from_str::<ErrorResponse>(&response_line) {
+            if let Ok(error) = serde_json::from_str::<ErrorResponse>(response_line) {
                 return Err(format_daemon_error(&error));
             }
-            return Err("解析响应 JSON 失败：响应格式不受支持".to_string());
+            Err("解析响应 JSON 失败：响应格式不受支持".to_string())
         }
+    }
+}
+
+fn handle_analyze_response(stream: &UnixStream, config: &Config) -> Result<(), String> {
+    let baseline = match &config.against_baseline {
+        Some(path) => Some((path, Baseline::load(Path::new(path))?)),
+        None => None,
     };
+    let mut response: AnalyzeResponse =
+        read_single_response_within(stream, config.max_response_bytes)?;
+    attach_notes(&mut response);
+    if let Some((path, baseline)) = baseline {
+        attach_baseline(&mut response, config, path, &baseline)?;
+    }
+    let evidence = std::mem::take(&mut response.evidence);
+
+    let rendered = render_analysis_output(config, &response)?;
+    match &config.output_path {
+        Some(path) => {
+            write_file_atomically(Path::new(path), &rendered)?;
+            eprintln!("报告已写入：{path}");
+        }
+        None => write_stdout_paged(&rendered, !config.no_pager)?,
+    }
+    if let Some(dir) = &config.evidence_dir {
+        save_evidence(Path::new(dir), &evidence, &response)?;
+    }
+    // 文本报告顶部已有横幅；机器可读格式与写入文件时另在标准错误提示，避免被脚本吞掉
+    let banner_in_output = config.format == ReportFormat::Text
+        && !config.summary_only
+        && config.template_path.is_none()
+        && config.output_path.is_none();
+    if response.metrics.emergency_outside_filter > 0 && !banner_in_output {
+        eprintln!(
+            "{}",
+            emergency_banner(response.metrics.emergency_outside_filter)
+        );
+    }
+
+    if let Some((hits, level)) = fail_on_triggered(config, &response.metrics) {
+        eprintln!(
+            "检测到 {hits} 条级别 {level}({}) 或更严重的事件，退出码 {EXIT_FAIL_ON}",
+            priority_label_cn(level)
+        );
+        EXIT_STATUS.store(EXIT_FAIL_ON, AtomicOrdering::Relaxed);
+    }
+    Ok(())
+}
 
-    print_analysis_report(&response);
+fn save_evidence(
+    dir: &Path,
+    evidence: &[SuspectEvidence],
+    response: &AnalyzeResponse,
+) -> Result<(), String> {
+    if response
+        .truncated
+        .as_ref()
+        .is_some_and(|t| t.evidence_dropped)
+    {
+        eprintln!(
+            "警告：原始条目超过 --max-resp-bytes 上限，未写入 {}\n修复：加大 --max-resp-bytes，或用 --since / --max-lines 缩小窗口",
+            dir.display()
+        );
+        return Ok(());
+    }
+    let written = write_evidence(dir, evidence)?;
+    let entries = evidence.iter().map(|e| e.entries.len()).sum::<usize>();
+    eprintln!(
+        "原始条目已写入：{}（{} 个来源，共 {entries} 条）",
+        dir.display(),
+        written.len()
+    );
+    Ok(())
+}
+
+/// 与 journalctl 一致：标准输出为终端且内容超过一屏时交给分页器
+fn write_stdout_paged(bytes: &[u8], allow_pager: bool) -> Result<(), String> {
+    let stdout = io::stdout();
+    let line_count = bytes.iter().filter(|b| **b == b'\n').count();
+    if allow_pager
+        && stdout.is_terminal()
+        && line_count >= terminal_height()
+        && let Some(pager) = pager_command()
+    {
+        match run_pager(&pager, bytes) {
+            Ok(()) => return Ok(()),
+            Err(err) => eprintln!("提示：分页器 {pager} 不可用（{err}），直接输出"),
+        }
+    }
+    stdout
+        .lock()
+        .write_all(bytes)
+        .map_err(|e| format!("输出报告失败：{e}"))
+}
+
+/// LOGTOOL_PAGER → PAGER → less；设为空或 cat 表示不分页
+fn pager_command() -> Option<String> {
+    let pager = env::var("LOGTOOL_PAGER")
+        .or_else(|_| env::var("PAGER"))
+        .unwrap_or_else(|_| "less".to_string());
+    let pager = pager.trim();
+    if pager.is_empty() || pager == "cat" {
+        return None;
+    }
+    Some(pager.to_string())
+}
+
+fn run_pager(pager: &str, bytes: &[u8]) -> Result<(), String> {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(pager).stdin(Stdio::piped());
+    // 与 journalctl 相同的 less 默认值：一屏内直接退出、保留颜色、退出后不清屏
+    if env::var_os("LESS").is_none() {
+        cmd.env("LESS", "FRXMK");
+    }
+    let mut child = cmd.spawn().map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // 用户提前退出分页器会导致 BrokenPipe，属于正常情况
+        match stdin.write_all(bytes) {
+            Err(err) if err.kind() != io::ErrorKind::BrokenPipe => return Err(err.to_string()),
+            _ => {}
+        }
+    }
+    child.wait().map_err(|e| e.to_string())?;
     Ok(())
 }
 
-fn handle_stream_response(stream: &UnixStream) -> Result<(), String> {
+/// 终端行数：优先 `stty size`，其次 $LINES，默认 24
+fn terminal_height() -> usize {
+    let from_stty = fs::File::open("/dev/tty").ok().and_then(|tty| {
+        let output = Command::new("stty")
+            .arg("size")
+            .stdin(tty)
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        parse_stty_rows(&String::from_utf8_lossy(&output.stdout))
+    });
+    from_stty
+        .or_else(|| env::var("LINES").ok().and_then(|v| v.trim().parse().ok()))
+        .filter(|rows| *rows > 0)
+        .unwrap_or(24)
+}
+
+fn parse_stty_rows(output: &str) -> Option<usize> {
+    output.split_whitespace().next()?.parse().ok()
+}
+
+fn render_analysis_output(config: &Config, response: &AnalyzeResponse) -> Result<Vec<u8>, String> {
+    let mut buffer = Vec::new();
+    if config.summary_only {
+        buffer.extend_from_slice(format_summary_line(response).as_bytes());
+        buffer.push(b'\n');
+        return Ok(buffer);
+    }
+
+    if let Some(path) = &config.template_path {
+        let template =
+            fs::read_to_string(path).map_err(|e| format!("读取模板文件 {path} 失败：{e}"))?;
+        let rendered = render_template(&template, &template_context(response))
+            .map_err(|e| format!("模板 {path} 无效：{e}"))?;
+        return Ok(rendered.into_bytes());
+    }
+
+    match config.format {
+        ReportFormat::Text if config.linear => {
+            write_linear_report(&mut buffer, response).map_err(|e| format!("生成报告失败：{e}"))?
+        }
+        ReportFormat::Text => write_analysis_report(&mut buffer, response)
+            .map_err(|e| format!("生成报告失败：{e}"))?,
+        ReportFormat::Json => write_json_line(&mut buffer, response, "分析结果")?,
+        ReportFormat::Ndjson => write_ndjson_suspects(&mut buffer, response)?,
+        ReportFormat::Logfmt => write_logfmt_suspects(&mut buffer, response)?,
+    }
+    Ok(buffer)
+}
+
+fn handle_stream_response(stream: &UnixStream, config: &Config) -> Result<(), String> {
     let reader = BufReader::new(stream);
+    let mut out: Box<dyn Write> = match &config.output_path {
+        Some(path) => Box::new(RotatingFileWriter::open(
+            Path::new(path),
+            STREAM_OUTPUT_ROTATE_BYTES,
+        )?),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut forward = match &config.forward_path {
+        Some(path) => Some(open_forward_sink(Path::new(path), FORWARD_OPEN_TIMEOUT)?),
+        None => None,
+    };
+    if config.live {
+        let control = stream
+            .try_clone()
+            .map_err(|e| format!("创建控制通道失败：{e}"))?;
+        spawn_live_input(control);
+    }
 
     for maybe_line in reader.lines() {
         let line = maybe_line.map_err(|e| format!("读取流响应失败：{e}"))?;
 
-        let msg: StreamLine = match serde_json::from_str(&line) {
+        let mut msg: StreamLine = match serde_json::from_str(&line) {
             Ok(msg) => msg,
             Err(_) => {
                 if let Ok(error) = serde_json::from_str::<ErrorResponse>(&line) {
@@ -262,20 +989,74 @@ fn handle_stream_response(stream: &UnixStream) -> Result<(), String> {
             }
         };
 
-        if let Some(error) = msg.error {
-            return Err(format!("流式请求失败：{error}"));
+        if msg.error.is_some() {
+            out.flush().map_err(|e| format!("刷新流输出失败：{e}"))?;
+            if msg.error_kind == Some(StreamErrorKind::JournalctlExit) {
+                EXIT_STATUS.store(EXIT_STREAM_FAILED, AtomicOrdering::Relaxed);
+            }
+            return Err(format_stream_error(&msg));
         }
 
         if msg.done {
             break;
         }
+        // daemon 已按同一上限截断，这里兜底旧版本 daemon
+        if let Some(limit) = config.max_line_bytes {
+            msg.line = truncate_stream_line(msg.line, limit, config.output_json);
+        }
+
+        if let Some(target) = forward.as_mut()
+            && let Err(err) = write_json_line(target, &msg, "转发消息")
+        {
+            // 消费端退出不应中断主流输出
+            eprintln!("警告：{err}，已停止转发");
+            forward = None;
+        }
 
-        println!("{}", msg.line);
+        // 整行一次写入，避免文件轮转把一行拆到两个文件
+        let mut text = msg.line;
+        text.push('\n');
+        out.write_all(text.as_bytes())
+            .map_err(|e| format!("写入流输出失败：{e}"))?;
     }
 
+    out.flush().map_err(|e| format!("刷新流输出失败：{e}"))?;
     Ok(())
 }
 
+fn format_stream_error(msg: &StreamLine) -> String {
+    let error = msg.error.as_deref().unwrap_or_default();
+    let mut out = match (msg.error_kind, msg.exit_code) {
+        (Some(StreamErrorKind::JournalctlExit), Some(code)) => {
+            format!("journalctl 异常退出（退出码 {code}）")
+        }
+        (Some(StreamErrorKind::JournalctlExit), None) => "journalctl 被异常终止".to_string(),
+        _ => format!("流式请求失败：{error}"),
+    };
+    match (&msg.stderr, msg.error_kind) {
+        (Some(stderr), _) => {
+            out.push_str("\njournalctl 输出：");
+            for line in stderr.lines().filter(|l| !l.trim().is_empty()) {
+                out.push_str(&format!("\n  {}", line.trim_end()));
+            }
+        }
+        (None, Some(StreamErrorKind::JournalctlExit)) => {
+            out.push_str(&format!("\n{error}"));
+        }
+        _ => {}
+    }
+    match msg.error_kind {
+        Some(StreamErrorKind::JournalctlExit) => out.push_str(
+            "\n修复：检查 --since/--until/--unit 等参数，可加 --show-command 查看实际执行的 journalctl 命令",
+        ),
+        Some(StreamErrorKind::JournalctlUnavailable) => {
+            out.push_str("\n修复：先运行 journalctl --version 检查可用性")
+        }
+        _ => {}
+    }
+    out
+}
+
 fn format_daemon_error(error: &ErrorResponse) -> String {
     let mut out = format!("守护进程返回错误：{}", error.error);
     if let Some(code) = &error.code {
@@ -287,6 +1068,153 @@ fn format_daemon_error(error: &ErrorResponse) -> String {
     out
 }
 
+fn handle_compare_response(stream: &UnixStream, config: &Config) -> Result<(), String> {
+    let response: CompareResponse = read_single_response(stream)?;
+    let mut out = io::stdout().lock();
+    let result = if config.format == ReportFormat::Json {
+        write_json_line(&mut out, &response, "对比结果")
+    } else {
+        compare::write_compare_report(&mut out, &response, local_utc_offset_secs())
+            .map_err(|e| format!("输出对比结果失败：{e}"))
+    };
+    let _ = out.flush();
+    result
+}
+
+/// --live：从标准输入读取指令，转为控制消息发给正在输出的流
+fn spawn_live_input(mut control: UnixStream) {
+    eprintln!(
+        "实时过滤：输入 +关键词 添加、-关键词 移除、p <级别> 修改优先级（如 p 4 或 p 2..4），回车生效"
+    );
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                return;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match parse_live_command(&line) {
+                Ok(command) => {
+                    if write_json_line(&mut control, &command, "控制消息").is_err() {
+                        return;
+                    }
+                    eprintln!("已应用：{}", describe_live_command(&command));
+                }
+                Err(err) => eprintln!("{err}"),
+            }
+        }
+    });
+}
+
+fn describe_live_command(command: &StreamControl) -> String {
+    match command {
+        StreamControl::AddGrep { term } => format!("添加关键词 {term}"),
+        StreamControl::RemoveGrep { term } => format!("移除关键词 {term}"),
+        StreamControl::SetPriority { priority } => format!("优先级改为 {priority}"),
+    }
+}
+
+fn print_resource_stats(resources: &ResourceStats, format_time: &dyn Fn(u64) -> String) {
+    let secs = |us: u64| format!("{:.3} 秒", us as f64 / 1_000_000.0);
+    let mib = |kib: u64| format!("{:.1} MiB", kib as f64 / 1024.0);
+    println!("守护进程峰值内存：{}", mib(resources.daemon_max_rss_kib));
+    if resources.measured == 0 {
+        return;
+    }
+    println!(
+        "分析资源（{} 次）：CPU 合计 {}，平均 {}，单次最高 {}；journalctl 峰值内存最高 {}",
+        resources.measured,
+        secs(resources.cpu_us_total),
+        secs(resources.cpu_us_total / resources.measured),
+        secs(resources.cpu_us_max),
+        mib(resources.journalctl_max_rss_kib)
+    );
+    println!("最耗 CPU 的分析（守护进程 + journalctl）：");
+    for cost in &resources.heaviest {
+        println!(
+            "  {:>10}  journalctl {:>10}  {}  {}",
+            secs(cost.cpu_us),
+            mib(cost.journalctl_max_rss_kib),
+            format_time(cost.at),
+            cost.command
+        );
+    }
+}
+
+/// 查看 socket 连接后直接写出一行快照，不需要发送请求
+fn read_viewer_status() -> Result<StatusDocument, String> {
+    let (stream, _) = socket::connect_first(&socket::env_socket_dirs(None), VIEWER_SOCKET_NAME)
+        .map_err(|err| {
+            format!(
+                "无法连接只读查看 Socket（{err}）\n修复：确认 logtool 服务正在运行（systemctl status logtool）"
+            )
+        })?;
+    stream
+        .set_read_timeout(Some(STATUS_READ_TIMEOUT))
+        .map_err(|e| format!("设置读取超时失败：{e}"))?;
+    let mut line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut line)
+        .map_err(|e| format!("读取健康状况失败：{e}"))?;
+    let snapshot: ViewerSnapshot = serde_json::from_str(&line)
+        .map_err(|_| "解析健康状况失败：响应格式不受支持".to_string())?;
+    Ok(StatusDocument::from_snapshot(&snapshot, unix_now_secs()))
+}
+
+/// 读取失败时仍输出 unknown 状态并以 0 退出，状态栏脚本不必单独处理错误
+fn run_status(json: bool) -> Result<(), String> {
+    let status = read_viewer_status().unwrap_or_else(StatusDocument::unavailable);
+    if json {
+        let line =
+            serde_json::to_string(&status).map_err(|e| format!("序列化健康状况失败：{e}"))?;
+        println!("{line}");
+    } else {
+        println!("{}", status.line());
+    }
+    Ok(())
+}
+
+fn handle_stats_response(stream: &UnixStream, show_rules: bool) -> Result<(), String> {
+    let stats: DaemonStats = read_single_response(stream)?;
+    let offset = local_utc_offset_secs();
+    let format_time = |secs: u64| format_local_minute(secs * 1_000_000, offset);
+
+    let uptime_min = unix_now_secs().saturating_sub(stats.started_at) / 60;
+    println!(
+        "守护进程启动于 {}（已运行 {} 小时 {} 分）",
+        format_time(stats.started_at),
+        uptime_min / 60,
+        uptime_min % 60
+    );
+    println!("请求总数：{}，分析次数：{}", stats.requests, stats.analyses);
+    if let Some(resources) = &stats.resources {
+        print_resource_stats(resources, &format_time);
+    }
+
+    let dead = stats.rules.iter().filter(|r| r.hits == 0).count();
+    println!("规则：{} 条，其中 {dead} 条从未命中", stats.rules.len());
+    if !show_rules {
+        if !stats.rules.is_empty() {
+            println!("提示：运行 logtool stats --rules 查看各规则命中次数");
+        }
+        return Ok(());
+    }
+
+    for rule in &stats.rules {
+        let last = rule
+            .last_hit_at
+            .map(format_time)
+            .unwrap_or_else(|| "从未命中".to_string());
+        let file = rule.file.as_deref().unwrap_or("（已删除）");
+        println!(
+            "  {:>8} 次 / {:>4} 次分析  最近：{last:<16}  {}  {file}",
+            rule.hits, rule.analyses, rule.id
+        );
+    }
+    Ok(())
+}
+
 fn print_boot_list() -> Result<(), String> {
     let output = Command::new("journalctl")
         .arg("--no-pager")
@@ -314,6 +1242,214 @@ fn print_boot_list() -> Result<(), String> {
     Ok(())
 }
 
+/// last-words：与 boots 一样以当前用户身份直接调用 journalctl
+fn run_last_words(boot: &str, lines: usize) -> Result<(), String> {
+    let output = Command::new("journalctl")
+        .arg("--no-pager")
+        .arg("--list-boots")
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| format!("执行 journalctl --list-boots 失败：{e}"))?;
+    let boots = parse_boot_list(&String::from_utf8_lossy(&output.stdout));
+    let known = || {
+        if boots.is_empty() {
+            return "无".to_string();
+        }
+        boots
+            .iter()
+            .map(|(offset, _)| offset.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let (offset, boot_id) = boots
+        .iter()
+        .find(|(offset, id)| boot.parse::<i64>() == Ok(*offset) || id.eq_ignore_ascii_case(boot))
+        .cloned()
+        .ok_or_else(|| {
+            format!(
+                "找不到启动周期 {boot}\n修复：运行 logtool boots 查看可用的启动周期（当前可用偏移：{}）；\n\
+                 若列表只有 0，journald 未持久化日志（见 README“重启后日志保留”）",
+                known()
+            )
+        })?;
+
+    let output = Command::new("journalctl")
+        .arg("--no-pager")
+        .arg(format!("--boot={boot_id}"))
+        .arg(format!("--lines={lines}"))
+        .arg("--output=json")
+        .arg("--output-fields=PRIORITY,MESSAGE,_SYSTEMD_UNIT,_COMM,SYSLOG_IDENTIFIER")
+        .output()
+        .map_err(|e| format!("执行 journalctl 失败：{e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(format!("journalctl 读取启动周期 {offset} 失败：{stderr}"));
+    }
+    let events = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| parse_json_event(line).ok())
+        .collect::<Vec<_>>();
+
+    let tz = local_utc_offset_secs();
+    println!(
+        "启动周期 {offset}（{boot_id}）的最后 {} 条日志（全部级别）：",
+        events.len()
+    );
+    println!();
+    for event in &events {
+        println!("{}", format_last_words_line(event, tz));
+    }
+    println!();
+
+    let last_at = events.iter().rev().find_map(|event| event.timestamp_us);
+    let next = boots
+        .iter()
+        .find(|(next_offset, _)| *next_offset == offset + 1);
+    match (next, last_at) {
+        (None, _) => println!("这是当前启动周期，尚未结束。"),
+        (Some((next_offset, next_id)), Some(last_at)) => match first_entry_timestamp(next_id) {
+            Some(first_at) => println!(
+                "⏱  最后一条日志之后 {}，下一次启动（{next_offset}）的第一条日志：{}",
+                format_duration_cn(first_at.saturating_sub(last_at) / 1_000_000),
+                format_local_second(first_at, tz)
+            ),
+            None => println!("⏱  无法读取下一次启动（{next_offset}）的第一条日志"),
+        },
+        (Some(_), None) => {}
+    }
+    if next.is_some() && !events.is_empty() && !looks_like_clean_shutdown(&events) {
+        println!(
+            "⚠️  最后的日志中没有正常关机记录（Journal stopped / systemd-shutdown 等），\n   \
+             更可能是死机、断电或内核崩溃；若日志在故障前很久就已中断，可能是 journald 尚未落盘。"
+        );
+    }
+    Ok(())
+}
+
+/// 某个启动周期第一条日志的时间戳：只读第一行后结束 journalctl
+fn first_entry_timestamp(boot_id: &str) -> Option<u64> {
+    let mut child = Command::new("journalctl")
+        .arg("--no-pager")
+        .arg(format!("--boot={boot_id}"))
+        .arg("--output=json")
+        .arg("--output-fields=MESSAGE")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut line = String::new();
+    let read = child
+        .stdout
+        .take()
+        .map(|stdout| BufReader::new(stdout).read_line(&mut line));
+    let _ = child.kill();
+    let _ = child.wait();
+    read?.ok()?;
+    parse_json_event(&line).ok()?.timestamp_us
+}
+
+fn run_rules_test(sample: Option<&str>, rules_path: Option<&str>) -> Result<(), String> {
+    let rule_dir = rules_path.unwrap_or(rules::SYSTEM_RULES_DIR);
+    let rule_set = RuleSet::load_path(Path::new(rule_dir))?;
+    if rule_set.is_empty() {
+        println!("未在 {rule_dir} 找到任何规则。");
+        return Ok(());
+    }
+
+    let (events, origin) = match sample {
+        Some(path) => {
+            let text =
+                fs::read_to_string(path).map_err(|e| format!("读取样本文件 {path} 失败：{e}"))?;
+            let events = text
+                .lines()
+                .filter_map(parse_sample_line)
+                .collect::<Vec<_>>();
+            (events, path.to_string())
+        }
+        None => {
+            let config = Config::default();
+            let events = read_journal_events(&config)?;
+            let origin = format!(
+                "最近日志（{}，优先级 ≤ {}）",
+                config.since.as_deref().unwrap_or("全部"),
+                config.priority
+            );
+            (events, origin)
+        }
+    };
+
+    println!(
+        "规则测试：{} 条规则，样本来源：{origin}，事件数：{}",
+        rule_set.rules.len(),
+        events.len()
+    );
+    println!();
+
+    let results = rules::test_rules(&rule_set, &events);
+    let mut unclaimed = events.len();
+    for result in &results {
+        unclaimed -= result.effective;
+        println!(
+            "[{}] 命中={} 生效={} 耗时={}µs（{}）",
+            result.id,
+            result.matched,
+            result.effective,
+            result.elapsed.as_micros(),
+            result.file.display()
+        );
+        if result.matched > result.effective {
+            println!(
+                "     注意：{} 条命中被排在前面的规则抢先归类",
+                result.matched - result.effective
+            );
+        }
+        for example in &result.examples {
+            println!("     示例：{}", logtool::truncate_for_display(example, 160));
+        }
+    }
+
+    println!();
+    println!("未被任何规则命中的事件：{unclaimed}");
+    Ok(())
+}
+
+fn run_rules_lint(rules_path: Option<&str>) -> Result<(), String> {
+    let rule_dir = rules_path.unwrap_or(rules::SYSTEM_RULES_DIR);
+    let issues = rules::lint_rules(Path::new(rule_dir))?;
+    if issues.is_empty() {
+        println!("[OK] {rule_dir} 中的规则未发现问题");
+        return Ok(());
+    }
+
+    let mut errors = 0usize;
+    for issue in &issues {
+        let level = match issue.level {
+            LintLevel::Error => {
+                errors += 1;
+                "ERROR"
+            }
+            LintLevel::Warning => "WARN",
+        };
+        match &issue.rule_id {
+            Some(id) => println!(
+                "[{level}] {} [{id}]：{}",
+                issue.file.display(),
+                issue.message
+            ),
+            None => println!("[{level}] {}：{}", issue.file.display(), issue.message),
+        }
+    }
+
+    let warnings = issues.len() - errors;
+    if errors > 0 {
+        return Err(format!(
+            "规则检查未通过：{errors} 个错误，{warnings} 个警告"
+        ));
+    }
+    println!("规则检查通过：{warnings} 个警告");
+    Ok(())
+}
+
 fn run_doctor() -> Result<(), String> {
     println!("logtool doctor");
     println!(
@@ -325,6 +1461,7 @@ fn run_doctor() -> Result<(), String> {
 
     check_journalctl()?;
     check_journal_persistence();
+    check_journal_integrity();
     check_user_access();
     check_socket_status();
     check_daemon_connection();
@@ -361,6 +1498,207 @@ fn check_journal_persistence() {
     }
 }
 
+/// journalctl --verify 的结果：按文件归类
+#[derive(Debug, Default, PartialEq, Eq)]
+struct VerifyReport {
+    passed: Vec<String>,
+    /// (文件, 原因)
+    failed: Vec<(String, String)>,
+    /// 已启用前向安全封印（FSS），但未提供 --verify-key 无法校验封印
+    sealed: Vec<String>,
+}
+
+/// 解析 `PASS: <文件>`、`FAIL: <文件> (<原因>)` 与 `Journal file <文件> has sealing enabled …`
+fn parse_verify_output(output: &str) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    for line in output.lines().map(str::trim) {
+        if let Some(path) = line.strip_prefix("PASS: ") {
+            report.passed.push(path.to_string());
+        } else if let Some(rest) = line.strip_prefix("FAIL: ") {
+            let (path, reason) = match rest.rsplit_once(" (") {
+                Some((path, reason)) => (path, reason.trim_end_matches(')')),
+                None => (rest, ""),
+            };
+            report.failed.push((path.to_string(), reason.to_string()));
+        } else if let Some(rest) = line.strip_prefix("Journal file ")
+            && let Some((path, _)) = rest.split_once(" has sealing enabled")
+        {
+            report.sealed.push(path.to_string());
+        }
+    }
+    report
+}
+
+/// 各 journal 目录（含 machine-id 子目录）中最近修改的文件；`.journal~` 是 journald 已判定损坏或未正常关闭后改名的文件
+fn recent_journal_files(dirs: &[&str], limit: usize) -> (Vec<String>, Vec<String>) {
+    let mut files = Vec::new();
+    let mut renamed = Vec::new();
+    let subdirs = dirs.iter().flat_map(|dir| {
+        let children = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir());
+        std::iter::once(Path::new(dir).to_path_buf()).chain(children)
+    });
+    for dir in subdirs {
+        for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".journal~") {
+                renamed.push(path.display().to_string());
+            } else if name.ends_with(".journal")
+                && let Ok(modified) = entry.metadata().and_then(|m| m.modified())
+            {
+                files.push((modified, path.display().to_string()));
+            }
+        }
+    }
+    files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    (
+        files
+            .into_iter()
+            .take(limit)
+            .map(|(_, path)| path)
+            .collect(),
+        renamed,
+    )
+}
+
+/// 运行 journalctl --verify，超时则终止并返回 None
+fn run_journal_verify(files: &[String], timeout: Duration) -> Result<Option<String>, String> {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--verify");
+    for file in files {
+        cmd.arg(format!("--file={file}"));
+    }
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("无法执行 journalctl --verify：{e}"))?;
+    // PASS/FAIL 写在标准错误，两边都收集
+    let readers = [
+        child
+            .stdout
+            .take()
+            .map(|out| Box::new(out) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|err| Box::new(err) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|mut reader| {
+        thread::spawn(move || {
+            let mut text = String::new();
+            let _ = reader.read_to_string(&mut text);
+            text
+        })
+    })
+    .collect::<Vec<_>>();
+
+    let started = std::time::Instant::now();
+    let finished = loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break true,
+            Ok(None) if started.elapsed() < timeout => thread::sleep(Duration::from_millis(50)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break false;
+            }
+            Err(e) => return Err(format!("等待 journalctl --verify 失败：{e}")),
+        }
+    };
+    let output = readers
+        .into_iter()
+        .filter_map(|reader| reader.join().ok())
+        .collect::<String>();
+    Ok(finished.then_some(output))
+}
+
+fn check_journal_integrity() {
+    let (files, renamed) = recent_journal_files(JOURNAL_DIRS, VERIFY_RECENT_FILES);
+    if !renamed.is_empty() {
+        println!(
+            "[WARN] 有 {} 个 journald 因损坏或未正常关闭而改名的文件（*.journal~），其中的日志可能不完整",
+            renamed.len()
+        );
+        for path in renamed.iter().take(VERIFY_RECENT_FILES) {
+            println!("       {path}");
+        }
+    }
+    if files.is_empty() {
+        println!(
+            "[WARN] 未找到可校验的 journal 文件（已查找 {}）",
+            JOURNAL_DIRS.join("、")
+        );
+        return;
+    }
+
+    let output = match run_journal_verify(&files, VERIFY_TIMEOUT) {
+        Ok(Some(output)) => output,
+        Ok(None) => {
+            println!(
+                "[WARN] journalctl --verify 在 {} 秒内未完成，已跳过完整性检查",
+                VERIFY_TIMEOUT.as_secs()
+            );
+            println!("       手动运行：sudo journalctl --verify");
+            return;
+        }
+        Err(err) => {
+            println!("[WARN] {err}");
+            return;
+        }
+    };
+    let report = parse_verify_output(&output);
+    let (denied, corrupted): (Vec<_>, Vec<_>) = report
+        .failed
+        .iter()
+        .partition(|(_, reason)| reason.contains("Permission denied"));
+    if !corrupted.is_empty() {
+        println!(
+            "[WARN] {} 个 journal 文件校验失败，分析窗口可能在损坏处提前截断、显得“缺少”日志",
+            corrupted.len()
+        );
+        for (path, reason) in &corrupted {
+            println!("       {path}（{reason}）");
+        }
+        println!("       运行：sudo journalctl --rotate，之后可把损坏文件移出 journal 目录");
+    }
+    if !denied.is_empty() {
+        println!("[WARN] 无权读取 {} 个 journal 文件，无法校验", denied.len());
+        println!("       运行：sudo logtool doctor");
+    }
+    if report.passed.is_empty() && report.failed.is_empty() {
+        // 打不开文件时 journalctl 直接报错退出，不逐个输出 PASS/FAIL
+        let first = output.lines().map(str::trim).find(|l| !l.is_empty());
+        println!(
+            "[WARN] journalctl --verify 未给出校验结果：{}",
+            first.unwrap_or("无输出")
+        );
+        if output.contains("Permission denied") {
+            println!("       运行：sudo logtool doctor");
+        }
+    } else if report.failed.is_empty() {
+        let sealing = if report.sealed.is_empty() {
+            "未启用前向安全封印（FSS）".to_string()
+        } else {
+            format!(
+                "其中 {} 个已封印，校验封印需 journalctl --verify --verify-key=<密钥>",
+                report.sealed.len()
+            )
+        };
+        println!(
+            "[OK] 最近 {} 个 journal 文件结构校验通过（{sealing}）",
+            report.passed.len()
+        );
+    }
+}
+
 fn check_user_access() {
     let uid_output = Command::new("id").arg("-u").output();
     let uid = uid_output.ok().and_then(|out| {
@@ -401,23 +1739,29 @@ fn check_user_access() {
 }
 
 fn check_socket_status() {
-    match fs::metadata(SOCKET_PATH) {
-        Ok(meta) => {
+    let dirs = socket::env_socket_dirs(None);
+    match socket::find_socket(&dirs, SOCKET_NAME)
+        .and_then(|path| Some((fs::metadata(&path).ok()?, path)))
+    {
+        Some((meta, path)) => {
             let mode = meta.permissions().mode() & 0o777;
             let uid = meta.uid();
             let gid = meta.gid();
             println!(
                 "[OK] 检测到 Socket：{}（mode={:o}, uid={}, gid={}）",
-                SOCKET_PATH, mode, uid, gid
+                path.display(),
+                mode,
+                uid,
+                gid
             );
             if mode != 0o660 {
                 println!("[WARN] Socket 权限建议为 660，当前为 {:o}", mode);
             }
         }
-        Err(_) => {
+        None => {
             println!(
-                "[WARN] 未检测到 Socket：{}（守护进程可能未启动）",
-                SOCKET_PATH
+                "[WARN] 未检测到 Socket：已查找 {}（守护进程可能未启动）",
+                socket::dirs_label(&dirs)
             );
             println!("       运行：sudo systemctl start logtool");
         }
@@ -425,8 +1769,8 @@ fn check_socket_status() {
 }
 
 fn check_daemon_connection() {
-    match UnixStream::connect(SOCKET_PATH) {
-        Ok(_) => println!("[OK] 可连接到守护进程 Socket"),
+    match socket::connect_first(&socket::env_socket_dirs(None), SOCKET_NAME) {
+        Ok((_, path)) => println!("[OK] 可连接到守护进程 Socket：{}", path.display()),
         Err(err) => {
             println!("[WARN] 无法连接守护进程 Socket：{err}");
             println!("       运行：sudo systemctl status logtool --no-pager");
@@ -434,6 +1778,165 @@ fn check_daemon_connection() {
     }
 }
 
+// ── 首次运行向导 ─────────────────────────────────────────────
+
+/// 交互启动、没有任何配置文件且连不上 daemon 时运行向导
+fn maybe_run_first_run_wizard() -> Result<(), String> {
+    if !io::stdin().is_terminal()
+        || settings::existing_settings_path().is_some()
+        || socket::connect_first(&socket::env_socket_dirs(None), SOCKET_NAME).is_ok()
+    {
+        return Ok(());
+    }
+    let Some(path) = settings::user_settings_path() else {
+        return Ok(());
+    };
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut output = io::stdout();
+    let service_installed = SERVICE_UNIT_PATHS.iter().any(|p| Path::new(p).is_file());
+    let settings = run_first_run_wizard(
+        &mut input,
+        &mut output,
+        journal_readable(),
+        service_installed,
+    )?;
+    settings.save_to(&path)?;
+    println!("配置已写入：{}", path.display());
+    println!();
+    Ok(())
+}
+
+fn run_first_run_wizard<R: BufRead, W: Write>(
+    input: &mut R,
+    out: &mut W,
+    journal_ok: bool,
+    service_installed: bool,
+) -> Result<Settings, String> {
+    let io_err = |e: io::Error| format!("向导输出失败：{e}");
+    writeln!(
+        out,
+        "欢迎使用 logtool！未找到配置文件，也无法连接守护进程，先做一次简单设置。"
+    )
+    .map_err(io_err)?;
+    writeln!(out, "Welcome to logtool! A short first-run setup follows.").map_err(io_err)?;
+    writeln!(out).map_err(io_err)?;
+
+    if journal_ok {
+        writeln!(out, "[OK] 当前用户可以读取系统日志").map_err(io_err)?;
+    } else {
+        writeln!(out, "[WARN] 当前用户无法读取完整系统日志").map_err(io_err)?;
+        writeln!(
+            out,
+            "       运行：sudo usermod -aG systemd-journal $USER（重新登录后生效）"
+        )
+        .map_err(io_err)?;
+    }
+
+    let mut settings = Settings::default();
+    let use_daemon = service_installed
+        && prompt_yes_no(
+            input,
+            out,
+            "检测到 logtool 服务，是否现在启用并启动？(sudo systemctl enable --now logtool)",
+            true,
+        )?;
+    if use_daemon {
+        let status = Command::new("sudo")
+            .args(["systemctl", "enable", "--now", "logtool"])
+            .status();
+        if !status.is_ok_and(|s| s.success()) {
+            writeln!(out, "[WARN] 启动服务失败，将改用本地模式").map_err(io_err)?;
+            settings.local_mode = true;
+        }
+    } else {
+        if !service_installed {
+            writeln!(
+                out,
+                "未安装守护进程服务（安装：sudo cp logtool.service /etc/systemd/system/ && sudo systemctl enable --now logtool）"
+            )
+            .map_err(io_err)?;
+        }
+        settings.local_mode = prompt_yes_no(
+            input,
+            out,
+            "是否使用本地模式（不经守护进程，直接以当前用户身份读取日志）？",
+            true,
+        )?;
+    }
+
+    let language = loop {
+        let answer = prompt_line(input, out, "首选语言 / Preferred language [zh/en]", "zh")?;
+        if settings::SUPPORTED_LANGUAGES.contains(&answer.as_str()) {
+            break answer;
+        }
+        writeln!(out, "[WARN] 不支持的语言：{answer}，请输入 zh 或 en").map_err(io_err)?;
+    };
+    settings.language = Some(language);
+
+    // 与 --since 按同一规则解析，写错的值当场重新输入，而不是下次运行时才由 journalctl 报错
+    let since = loop {
+        let answer = prompt_line(input, out, "默认时间窗口", DEFAULT_SINCE)?;
+        if resolve_time_spec(&answer, unix_now_secs(), local_utc_offset_secs()).is_some() {
+            break answer;
+        }
+        writeln!(
+            out,
+            "[WARN] 无法识别的时间：{answer}，可用 2 hours ago、-30min、yesterday、2024-01-03 08:00 等写法"
+        )
+        .map_err(io_err)?;
+    };
+    if since != DEFAULT_SINCE {
+        settings.default_since = Some(since);
+    }
+    Ok(settings)
+}
+
+fn prompt_line<R: BufRead, W: Write>(
+    input: &mut R,
+    out: &mut W,
+    question: &str,
+    default: &str,
+) -> Result<String, String> {
+    write!(out, "{question}（默认：{default}）：").map_err(|e| format!("向导输出失败：{e}"))?;
+    out.flush().map_err(|e| format!("向导输出失败：{e}"))?;
+    let mut line = String::new();
+    input
+        .read_line(&mut line)
+        .map_err(|e| format!("读取输入失败：{e}"))?;
+    let answer = line.trim();
+    Ok(if answer.is_empty() {
+        default.to_string()
+    } else {
+        answer.to_string()
+    })
+}
+
+fn prompt_yes_no<R: BufRead, W: Write>(
+    input: &mut R,
+    out: &mut W,
+    question: &str,
+    default: bool,
+) -> Result<bool, String> {
+    let default_text = if default { "Y/n" } else { "y/N" };
+    let answer = prompt_line(input, out, question, default_text)?;
+    Ok(match answer.to_ascii_lowercase().as_str() {
+        "y" | "yes" | "是" => true,
+        "n" | "no" | "否" => false,
+        _ => default,
+    })
+}
+
+fn journal_readable() -> bool {
+    Command::new("journalctl")
+        .args(["--no-pager", "-q", "-n", "1", "-o", "cat", "-p", "3"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .is_ok_and(|out| out.status.success() && out.stderr.is_empty())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -496,6 +1999,72 @@ mod tests {
         assert!(args.is_empty());
     }
 
+    #[test]
+    fn wizard_defaults_to_local_mode_without_service() {
+        // 写错的语言与时间窗口都重新询问
+        let mut input = io::Cursor::new("\nfr\nen\nlast fortnight\n30 min ago\n");
+        let mut out = Vec::new();
+        let settings = run_first_run_wizard(&mut input, &mut out, true, false).expect("应成功");
+        assert!(settings.local_mode);
+        assert_eq!(settings.language.as_deref(), Some("en"));
+        assert_eq!(settings.default_since.as_deref(), Some("30 min ago"));
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert!(text.contains("未安装守护进程服务"));
+        assert!(text.contains("不支持的语言：fr"));
+        assert!(text.contains("无法识别的时间：last fortnight"));
+
+        // 直接回车取默认值
+        let mut input = io::Cursor::new("\n\n\n");
+        let settings =
+            run_first_run_wizard(&mut input, &mut Vec::new(), true, false).expect("应成功");
+        assert_eq!(settings.language.as_deref(), Some("zh"));
+        assert_eq!(settings.default_since, None);
+    }
+
+    #[test]
+    fn settings_default_since_fills_only_the_builtin_default() {
+        let settings = Settings {
+            default_since: Some("1 day ago".to_string()),
+            max_line_bytes: Some(4096),
+            ..Settings::default()
+        };
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let parsed = |list: &[&str]| match parse_args(&args(list)).expect("解析应成功") {
+            Action::Run(config) => config,
+            _ => panic!("应为分析动作"),
+        };
+
+        let mut config = parsed(&["--top", "5"]);
+        apply_settings(&mut config, &settings);
+        assert_eq!(config.since.as_deref(), Some("1 day ago"));
+        assert_eq!(config.max_line_bytes, Some(4096));
+
+        // 显式给出与内置默认值相同的 --since 也保留
+        let mut config = parsed(&["--since", DEFAULT_SINCE, "--max-line-bytes", "8192"]);
+        apply_settings(&mut config, &settings);
+        assert_eq!(config.since.as_deref(), Some(DEFAULT_SINCE));
+        assert_eq!(config.max_line_bytes, Some(8192));
+
+        let mut config = parsed(&["--no-default-since"]);
+        apply_settings(&mut config, &settings);
+        assert_eq!(config.since, None);
+    }
+
+    #[test]
+    fn prompt_yes_no_falls_back_to_default() {
+        let mut out = Vec::new();
+        let mut input = io::Cursor::new("n\n");
+        assert!(!prompt_yes_no(&mut input, &mut out, "?", true).expect("应成功"));
+        let mut input = io::Cursor::new("maybe\n");
+        assert!(prompt_yes_no(&mut input, &mut out, "?", true).expect("应成功"));
+    }
+
+    #[test]
+    fn parse_stty_rows_reads_first_field() {
+        assert_eq!(parse_stty_rows("50 120\n"), Some(50));
+        assert_eq!(parse_stty_rows(""), None);
+    }
+
     #[test]
     fn format_daemon_error_includes_code_and_hint_when_present() {
         let err = ErrorResponse {
@@ -508,4 +2077,90 @@ mod tests {
         assert!(text.contains("invalid_json"));
         assert!(text.contains("运行 logtool --help"));
     }
+
+    #[test]
+    fn alert_line_names_suspect_and_reason() {
+        let alert = Alert {
+            id: "unit:ssh.service".to_string(),
+            count: 12,
+            priority: 3,
+            reason: logtool::alert::AlertReason::CountDoubled,
+            sample_message: String::new(),
+        };
+        assert_eq!(
+            format_alert_line(&alert, "2026-10-14 09:30"),
+            "[2026-10-14 09:30] 告警 unit:ssh.service | 事件数=12 | 最高严重级别=3(错误) | 原因=事件数翻倍"
+        );
+    }
+
+    #[test]
+    fn format_stream_error_shows_exit_code_and_stderr() {
+        let msg = StreamLine {
+            done: true,
+            error: Some("journalctl 退出状态异常：exit status: 1".to_string()),
+            error_kind: Some(StreamErrorKind::JournalctlExit),
+            exit_code: Some(1),
+            stderr: Some("Failed to parse timestamp: garbage\n".to_string()),
+            ..StreamLine::default()
+        };
+        let text = format_stream_error(&msg);
+        assert!(text.starts_with("journalctl 异常退出（退出码 1）"));
+        assert!(text.contains("  Failed to parse timestamp: garbage"));
+
+        // 旧版 daemon 只有 error 文本
+        let legacy = StreamLine {
+            done: true,
+            error: Some("boom".to_string()),
+            ..StreamLine::default()
+        };
+        assert_eq!(format_stream_error(&legacy), "流式请求失败：boom");
+    }
+
+    #[test]
+    fn format_package_map_aligns_sections() {
+        let mut map = PackageMap::default();
+        map.units
+            .insert("ssh.service".to_string(), "openssh-server".to_string());
+        map.units
+            .insert("cron.service".to_string(), "cron".to_string());
+        assert_eq!(
+            format_package_map(&map),
+            "服务单元（2）：\n  cron.service  cron\n  ssh.service   openssh-server\n"
+        );
+    }
+
+    #[test]
+    fn verify_output_separates_corrupted_and_sealed_files() {
+        let output = "PASS: /var/log/journal/abc/system.journal\n\
+            Journal file /var/log/journal/abc/user-1000.journal has sealing enabled but verification key has not been passed using --verify-key=.\n\
+            PASS: /var/log/journal/abc/user-1000.journal\n\
+            File corruption detected at /var/log/journal/abc/system@x.journal:1234 (of 8388608 bytes, 0%).\n\
+            FAIL: /var/log/journal/abc/system@x.journal (Bad message)\n";
+        let report = parse_verify_output(output);
+        assert_eq!(report.passed.len(), 2);
+        assert_eq!(
+            report.failed,
+            [(
+                "/var/log/journal/abc/system@x.journal".to_string(),
+                "Bad message".to_string()
+            )]
+        );
+        assert_eq!(report.sealed, ["/var/log/journal/abc/user-1000.journal"]);
+
+        let root = env::temp_dir().join(format!("logtool-verify-{}", process::id()));
+        let machine = root.join("abc");
+        fs::create_dir_all(&machine).expect("创建目录应成功");
+        for name in ["old.journal", "new.journal", "broken.journal~", "notes.txt"] {
+            fs::write(machine.join(name), "x").expect("写入应成功");
+            thread::sleep(Duration::from_millis(20));
+        }
+        let root_str = root.display().to_string();
+        let (files, renamed) = recent_journal_files(&[&root_str], 1);
+        assert_eq!(files, [machine.join("new.journal").display().to_string()]);
+        assert_eq!(
+            renamed,
+            [machine.join("broken.journal~").display().to_string()]
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
 }
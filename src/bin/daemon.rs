@@ -6,17 +6,31 @@
 // 使用方式：
 //   sudo logtool-daemon              # 前台运行（systemd 管理）
 //   sudo logtool-daemon --foreground # 同上（显式前台）
+//   sudo logtool-daemon --archive-dir /var/log/logtool/sessions --archive-all  # 归档全部流会话
+//   logtool-daemon --socket-dir /tmp/ci    # /run 不可写时（容器、CI）改用其他目录
+//   sudo logtool-daemon --prefetch 300  # 每 5 分钟预先计算默认分析，裸 logtool 直接返回
 
+use logtool::rules::RuleSet;
+use logtool::rusage::{ResourceUsage, process_max_rss_kib, thread_cpu_us};
+use logtool::sink::FanOut;
+use logtool::socket::{self, SOCKET_NAME};
+use logtool::timeutil::unix_now_secs;
+use logtool::viewer::{VIEWER_SOCKET_NAME, ViewerCache};
 use logtool::{
-    Config, ErrorResponse, RunMode, SOCKET_PATH, analyze_journal, daemon_error_with_details,
-    stream_journal_to_writer, validate_config, write_json_line,
+    AnalysisCost, AnalyzeResponse, Config, DaemonStats, ErrorResponse, LiveFilters, ResourceStats,
+    RotatingFileWriter, RuleHit, RuleStats, RunMode, STREAM_OUTPUT_ROTATE_BYTES,
+    analysis_command_line, analyze_journal, daemon_error_with_details, fit_response,
+    spawn_stream_control_reader, stream_journal_live_to_writer, stream_journal_to_writer,
+    validate_config, write_json_line,
 };
+use std::collections::HashMap;
 use std::io::{self, BufRead, BufReader, Read};
+use std::net::Shutdown;
 use std::os::unix::net::{UnixListener, UnixStream};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::{
-    Arc,
+    Arc, Condvar, Mutex,
     atomic::{AtomicU64, AtomicUsize, Ordering},
 };
 use std::thread;
@@ -27,10 +41,279 @@ const MAX_ACTIVE_CLIENTS: usize = 64;
 const SOCKET_GROUP: &str = "logtool";
 const REQUEST_LINE_MAX_BYTES: usize = 64 * 1024;
 const REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// 查看 socket 只写一行快照；读得太慢的客户端直接放弃，不拖住后续连接
+const VIEWER_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
 const INCOMING_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+/// logtool stats 列出的最耗 CPU 的分析条数
+const MAX_HEAVIEST_ANALYSES: usize = 5;
+/// --prefetch 的最短间隔，避免持续扫描 journal
+const PREFETCH_MIN_SECS: u64 = 30;
 
 static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
 
+/// 进程内累计的运行统计，daemon 重启后清零
+struct DaemonState {
+    started_at: u64,
+    requests: AtomicU64,
+    analyses: AtomicU64,
+    rule_counters: Mutex<HashMap<String, RuleCounter>>,
+    resources: Mutex<ResourceStats>,
+    viewer: Mutex<ViewerCache>,
+    archive: Option<ArchivePolicy>,
+    prefetch: Option<Prefetch>,
+}
+
+/// --archive-dir：流会话（客户端收到的全部 JSON 行）另存为 `<目录>/stream-<时间>-<请求号>.ndjson`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ArchivePolicy {
+    dir: PathBuf,
+    /// --archive-all：不论请求是否带 --archive 都归档，用于审计
+    all: bool,
+}
+
+/// --prefetch：后台每 interval_secs 秒重算一次默认分析，与之相同的请求直接返回结果
+struct Prefetch {
+    interval_secs: u64,
+    slot: Mutex<PrefetchSlot>,
+    /// 目标改变时唤醒后台线程立即重算
+    retarget: Condvar,
+}
+
+struct PrefetchSlot {
+    /// 预先计算的配置；遇到带不同维护窗口等设置的裸请求时改为该请求的配置
+    target: Config,
+    /// (计算完成时间, 结果)
+    result: Option<(u64, AnalyzeResponse)>,
+}
+
+impl Prefetch {
+    fn new(interval_secs: u64) -> Self {
+        Self {
+            interval_secs,
+            slot: Mutex::new(PrefetchSlot {
+                target: Config::default(),
+                result: None,
+            }),
+            retarget: Condvar::new(),
+        }
+    }
+
+    /// 结果最多两个周期内有效（重算本身也要时间）
+    fn lookup(&self, config: &Config, now: u64) -> Option<AnalyzeResponse> {
+        if !is_default_query(config) {
+            return None;
+        }
+        // 发起请求的用户只影响 --syslog-file，默认查询对所有用户相同
+        let config = &Config {
+            peer: None,
+            ..config.clone()
+        };
+        let mut slot = self.slot.lock().ok()?;
+        if slot.target != *config {
+            slot.target = config.clone();
+            slot.result = None;
+            self.retarget.notify_one();
+            return None;
+        }
+        let (at, response) = slot.result.as_ref()?;
+        if now.saturating_sub(*at) > 2 * self.interval_secs {
+            return None;
+        }
+        let mut response = response.clone();
+        response.cached_at = Some(*at);
+        Some(response)
+    }
+
+    fn target(&self) -> Option<Config> {
+        self.slot.lock().ok().map(|slot| slot.target.clone())
+    }
+
+    fn store(&self, target: &Config, at: u64, response: AnalyzeResponse) {
+        if let Ok(mut slot) = self.slot.lock()
+            && slot.target == *target
+        {
+            slot.result = Some((at, response));
+        }
+    }
+
+    /// 等满一个周期，或目标不再是刚计算的 computed
+    fn wait_next_round(&self, computed: &Config) {
+        let Ok(slot) = self.slot.lock() else {
+            return;
+        };
+        let _ = self.retarget.wait_timeout_while(
+            slot,
+            Duration::from_secs(self.interval_secs),
+            |slot| slot.target == *computed,
+        );
+    }
+}
+
+/// 裸 `logtool`：除 CLI 配置文件带来的维护窗口与截断上限外都是默认值
+fn is_default_query(config: &Config) -> bool {
+    let normalized = Config {
+        maintenance_windows: Vec::new(),
+        max_message_chars: None,
+        max_line_bytes: None,
+        max_response_bytes: None,
+        peer: None,
+        ..config.clone()
+    };
+    normalized == Config::default()
+}
+
+fn run_prefetch(state: &DaemonState) {
+    let Some(prefetch) = &state.prefetch else {
+        return;
+    };
+    while let Some(target) = prefetch.target() {
+        match analyze_journal(&target) {
+            Ok(response) => {
+                let now = unix_now_secs();
+                if let Ok(mut viewer) = state.viewer.lock() {
+                    viewer.record(&target, &response, now);
+                }
+                prefetch.store(&target, now, response);
+            }
+            Err(err) => eprintln!("event=prefetch_failed error={}", sanitize_log_field(&err)),
+        }
+        prefetch.wait_next_round(&target);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct RuleCounter {
+    hits: u64,
+    analyses: u64,
+    last_hit_at: u64,
+}
+
+impl DaemonState {
+    fn new(archive: Option<ArchivePolicy>, prefetch: Option<Prefetch>) -> Self {
+        Self {
+            started_at: unix_now_secs(),
+            requests: AtomicU64::new(0),
+            analyses: AtomicU64::new(0),
+            rule_counters: Mutex::new(HashMap::new()),
+            resources: Mutex::new(ResourceStats::default()),
+            viewer: Mutex::new(ViewerCache::default()),
+            archive,
+            prefetch,
+        }
+    }
+
+    fn record_usage(&self, cost: AnalysisCost) {
+        let Ok(mut resources) = self.resources.lock() else {
+            return;
+        };
+        resources.measured += 1;
+        resources.cpu_us_total += cost.cpu_us;
+        resources.cpu_us_max = resources.cpu_us_max.max(cost.cpu_us);
+        resources.journalctl_max_rss_kib = resources
+            .journalctl_max_rss_kib
+            .max(cost.journalctl_max_rss_kib);
+        // 同等 CPU 时保留较早的记录
+        let index = resources
+            .heaviest
+            .partition_point(|existing| existing.cpu_us >= cost.cpu_us);
+        if index < MAX_HEAVIEST_ANALYSES {
+            resources.heaviest.insert(index, cost);
+            resources.heaviest.truncate(MAX_HEAVIEST_ANALYSES);
+        }
+    }
+
+    fn record_analysis(&self, rule_hits: &[RuleHit], now: u64) {
+        self.analyses.fetch_add(1, Ordering::Relaxed);
+        let Ok(mut counters) = self.rule_counters.lock() else {
+            return;
+        };
+        for hit in rule_hits {
+            let counter = counters.entry(hit.id.clone()).or_default();
+            counter.hits += hit.count;
+            counter.analyses += 1;
+            counter.last_hit_at = now;
+        }
+    }
+
+    /// `loaded` 为当前已加载规则的 (id, 文件)，未命中的规则以 hits=0 列出
+    fn snapshot(&self, loaded: &[(String, String)]) -> DaemonStats {
+        let counters = self
+            .rule_counters
+            .lock()
+            .map(|c| c.clone())
+            .unwrap_or_default();
+
+        let mut rules = loaded
+            .iter()
+            .map(|(id, file)| RuleStats {
+                id: id.clone(),
+                file: Some(file.clone()),
+                ..RuleStats::default()
+            })
+            .collect::<Vec<_>>();
+        for (id, counter) in &counters {
+            let index = match rules.iter().position(|r| &r.id == id) {
+                Some(index) => index,
+                None => {
+                    rules.push(RuleStats {
+                        id: id.clone(),
+                        ..RuleStats::default()
+                    });
+                    rules.len() - 1
+                }
+            };
+            let entry = &mut rules[index];
+            entry.hits = counter.hits;
+            entry.analyses = counter.analyses;
+            entry.last_hit_at = Some(counter.last_hit_at);
+        }
+        rules.sort_by(|a, b| b.hits.cmp(&a.hits).then_with(|| a.id.cmp(&b.id)));
+
+        let resources = process_max_rss_kib().map(|daemon_max_rss_kib| ResourceStats {
+            daemon_max_rss_kib,
+            ..self.resources.lock().map(|r| r.clone()).unwrap_or_default()
+        });
+
+        DaemonStats {
+            started_at: self.started_at,
+            requests: self.requests.load(Ordering::Relaxed),
+            analyses: self.analyses.load(Ordering::Relaxed),
+            rules,
+            resources,
+        }
+    }
+}
+
+fn analysis_cost(
+    config: &Config,
+    at: u64,
+    daemon_cpu_us: u64,
+    journalctl: ResourceUsage,
+) -> AnalysisCost {
+    AnalysisCost {
+        at,
+        cpu_us: daemon_cpu_us + journalctl.cpu_us,
+        daemon_cpu_us,
+        journalctl_cpu_us: journalctl.cpu_us,
+        journalctl_max_rss_kib: journalctl.max_rss_kib,
+        command: analysis_command_line(config),
+    }
+}
+
+fn loaded_rule_ids() -> Vec<(String, String)> {
+    match RuleSet::load_system() {
+        Ok(set) => set
+            .rules
+            .iter()
+            .map(|r| (r.rule.id.clone(), r.file.display().to_string()))
+            .collect(),
+        Err(err) => {
+            eprintln!("警告：统计时加载规则失败：{}", sanitize_log_field(&err));
+            Vec::new()
+        }
+    }
+}
+
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
 
@@ -46,18 +329,91 @@ fn main() {
         eprintln!("提示：守护进程以前台模式启动（使用 systemd 管理时无需 --foreground）");
     }
 
-    if let Err(err) = run_daemon() {
+    let parsed = parse_archive_args(&args).and_then(|archive| {
+        let socket_dir =
+            daemon_option_value(&args, "--socket-dir", "修复：如 --socket-dir /tmp/logtool")?;
+        Ok((archive, parse_prefetch_args(&args)?, socket_dir))
+    });
+    let (archive, prefetch, socket_dir) = match parsed {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("错误：{err}");
+            process::exit(2);
+        }
+    };
+    let socket_dirs = socket::env_socket_dirs(socket_dir.map(PathBuf::from));
+
+    if let Err(err) = run_daemon(&socket_dirs, archive, prefetch) {
         eprintln!("错误：{err}");
         process::exit(1);
     }
 }
 
-fn run_daemon() -> Result<(), String> {
-    // 清理可能残留的 socket 文件
-    let _ = fs::remove_file(SOCKET_PATH);
+/// `--flag 值` 或 `--flag=值`；出现多次时取最后一个
+fn daemon_option_value(args: &[String], flag: &str, hint: &str) -> Result<Option<String>, String> {
+    let mut value = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if let Some(inline) = arg
+            .strip_prefix(flag)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            value = Some(inline.to_string());
+        } else if arg == flag {
+            value = Some(
+                iter.next()
+                    .ok_or_else(|| format!("缺少 {flag} 的参数值\n{hint}"))?
+                    .clone(),
+            );
+        }
+    }
+    Ok(value)
+}
+
+fn parse_archive_args(args: &[String]) -> Result<Option<ArchivePolicy>, String> {
+    let dir = daemon_option_value(
+        args,
+        "--archive-dir",
+        "修复：如 --archive-dir /var/log/logtool/sessions",
+    )?;
+    let all = args.iter().any(|a| a == "--archive-all");
+    match dir.filter(|dir| !dir.trim().is_empty()) {
+        Some(dir) => Ok(Some(ArchivePolicy {
+            dir: PathBuf::from(dir),
+            all,
+        })),
+        None if all => Err(
+            "--archive-all 需要同时指定 --archive-dir\n修复：如 --archive-dir /var/log/logtool/sessions --archive-all"
+                .to_string(),
+        ),
+        None => Ok(None),
+    }
+}
 
-    let listener = UnixListener::bind(SOCKET_PATH).map_err(|err| {
-        format!("无法绑定 Unix Socket {SOCKET_PATH}：{err}\n提示：可能需要 sudo 权限")
+fn parse_prefetch_args(args: &[String]) -> Result<Option<Prefetch>, String> {
+    let hint = format!("修复：如 --prefetch 300（秒，至少 {PREFETCH_MIN_SECS}）");
+    let Some(value) = daemon_option_value(args, "--prefetch", &hint)? else {
+        return Ok(None);
+    };
+    match value.trim().parse::<u64>() {
+        Ok(secs) if secs >= PREFETCH_MIN_SECS => Ok(Some(Prefetch::new(secs))),
+        _ => Err(format!(
+            "--prefetch 需要不小于 {PREFETCH_MIN_SECS} 的秒数，实际输入：{value}\n{hint}"
+        )),
+    }
+}
+
+fn run_daemon(
+    socket_dirs: &[PathBuf],
+    archive: Option<ArchivePolicy>,
+    prefetch: Option<Prefetch>,
+) -> Result<(), String> {
+    // /run 不可写时依次回退（见 socket 模块），绑定前清理残留的 socket 文件
+    let (listener, socket_path) = socket::bind_first(socket_dirs, SOCKET_NAME).map_err(|err| {
+        format!(
+            "无法绑定 Unix Socket（{err}）\n修复：使用 sudo 启动，或以 --socket-dir / ${} 指定可写目录",
+            socket::SOCKET_DIR_ENV
+        )
     })?;
 
     // 设置 socket 权限：仅 owner(root) 和同组用户可访问
@@ -66,21 +422,63 @@ fn run_daemon() -> Result<(), String> {
     {
         use std::os::unix::fs::PermissionsExt;
         let perms = fs::Permissions::from_mode(0o660);
-        let _ = fs::set_permissions(SOCKET_PATH, perms);
+        let _ = fs::set_permissions(&socket_path, perms);
     }
 
-    if let Err(err) = try_set_socket_group(SOCKET_GROUP) {
+    if let Err(err) = try_set_socket_group(SOCKET_GROUP, &socket_path) {
         eprintln!("提示：{err}");
         eprintln!("   将回退为仅 root/当前组用户可访问 Socket。");
     }
 
-    eprintln!("🚀 logtool 守护进程已启动，监听：{SOCKET_PATH}");
+    eprintln!("🚀 logtool 守护进程已启动，监听：{}", socket_path.display());
     eprintln!("   Socket 权限：0660（owner + group）");
     eprintln!("   Socket 组：{SOCKET_GROUP}（若存在）");
     eprintln!("   最大并发请求：{MAX_ACTIVE_CLIENTS}");
     warn_if_journal_not_persistent();
 
     let active_clients = Arc::new(AtomicUsize::new(0));
+    if let Some(policy) = &archive {
+        // 归档里是完整的日志内容，只让 owner 和同组用户读取
+        use std::os::unix::fs::DirBuilderExt;
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o750)
+            .create(&policy.dir)
+            .map_err(|e| format!("无法创建归档目录 {}：{e}", policy.dir.display()))?;
+        eprintln!(
+            "   会话归档目录：{}（{}）",
+            policy.dir.display(),
+            if policy.all {
+                "全部流会话"
+            } else {
+                "仅带 --archive 的请求"
+            }
+        );
+    }
+    if let Some(prefetch) = &prefetch {
+        eprintln!(
+            "   预先计算默认分析：每 {} 秒（--prefetch）",
+            prefetch.interval_secs
+        );
+    }
+    let state = Arc::new(DaemonState::new(archive, prefetch));
+    if state.prefetch.is_some() {
+        let state = Arc::clone(&state);
+        thread::spawn(move || run_prefetch(&state));
+    }
+
+    // 查看 socket 是可选的：绑定失败只提示，主 socket 照常服务
+    match bind_viewer_socket(&socket::socket_dir_of(&socket_path)) {
+        Ok((viewer, viewer_path)) => {
+            eprintln!(
+                "   只读查看 Socket：{}（0666，仅提供缓存的健康摘要）",
+                viewer_path.display()
+            );
+            let state = Arc::clone(&state);
+            thread::spawn(move || serve_viewer(&viewer, &state));
+        }
+        Err(err) => eprintln!("提示：{err}"),
+    }
 
     for stream in listener.incoming() {
         match stream {
@@ -100,6 +498,7 @@ fn run_daemon() -> Result<(), String> {
 
                 let request_id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
                 let active_clients = Arc::clone(&active_clients);
+                let state = Arc::clone(&state);
                 // 每个连接在独立线程中处理，避免慢请求阻塞其他客户端
                 thread::spawn(move || {
                     let _guard = ActiveClientGuard {
@@ -107,7 +506,7 @@ fn run_daemon() -> Result<(), String> {
                     };
                     let started = Instant::now();
                     let mut mode_for_log = None;
-                    let result = handle_client(request_id, stream, &mut mode_for_log);
+                    let result = handle_client(request_id, stream, &state, &mut mode_for_log);
                     let duration_ms = started.elapsed().as_millis();
                     let mode = mode_for_log
                         .as_ref()
@@ -139,15 +538,54 @@ fn run_daemon() -> Result<(), String> {
     Ok(())
 }
 
+fn bind_viewer_socket(dir: &Path) -> Result<(UnixListener, PathBuf), String> {
+    let (listener, path) =
+        socket::bind_first(&[dir.to_path_buf()], VIEWER_SOCKET_NAME).map_err(|err| {
+            format!("无法绑定只读查看 Socket（{err}），状态栏等组件将无法读取健康摘要")
+        })?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(&path, fs::Permissions::from_mode(0o666));
+    }
+    Ok((listener, path))
+}
+
+/// 每个连接写出一行快照后关闭；不读取请求、不启动 journalctl，因此无需权限检查
+fn serve_viewer(listener: &UnixListener, state: &DaemonState) {
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("查看 Socket 接受连接失败：{err}");
+                thread::sleep(INCOMING_ERROR_BACKOFF);
+                continue;
+            }
+        };
+        let snapshot = state
+            .viewer
+            .lock()
+            .map(|viewer| viewer.snapshot(state.started_at));
+        let Ok(snapshot) = snapshot else {
+            continue;
+        };
+        let _ = stream.set_write_timeout(Some(VIEWER_WRITE_TIMEOUT));
+        let _ = write_json_line(&mut stream, &snapshot, "查看快照");
+    }
+}
+
 fn handle_client(
     request_id: u64,
     stream: UnixStream,
+    state: &DaemonState,
     mode_for_log: &mut Option<RunMode>,
 ) -> Result<(), String> {
     stream
         .set_read_timeout(Some(REQUEST_READ_TIMEOUT))
         .map_err(|e| format!("设置读取超时失败：{e}"))?;
 
+    // 取不到身份时只按“其他用户”的权限代为读取文件
+    let peer = socket::peer_of(&stream).unwrap_or_else(socket::Peer::anonymous);
     let read_stream = stream.try_clone().map_err(|e| e.to_string())?;
     let mut write_stream = stream;
 
@@ -172,7 +610,7 @@ fn handle_client(
     };
 
     // 解析配置
-    let config: Config = match serde_json::from_str(&request_line) {
+    let mut config: Config = match serde_json::from_str(&request_line) {
         Ok(config) => config,
         Err(err) => {
             let msg = format!("解析请求 JSON 失败：{err}");
@@ -185,7 +623,9 @@ fn handle_client(
             return Err(msg);
         }
     };
+    config.peer = Some(peer);
     *mode_for_log = Some(config.mode.clone());
+    state.requests.fetch_add(1, Ordering::Relaxed);
 
     // 服务端参数校验，防止非法/恶意请求
     if let Err(err) = validate_config(&config) {
@@ -208,23 +648,178 @@ fn handle_client(
 
     // 执行分析并返回结果
     let run_result = match config.mode {
-        RunMode::Analyze => analyze_journal(&config)
-            .and_then(|response| write_json_line(&mut write_stream, &response, "分析响应")),
+        RunMode::Analyze => {
+            let cpu_before = thread_cpu_us();
+            let now = unix_now_secs();
+            let prefetched = state
+                .prefetch
+                .as_ref()
+                .and_then(|prefetch| prefetch.lookup(&config, now));
+            let result = match prefetched {
+                Some(response) => {
+                    eprintln!(
+                        "request_id={request_id} event=prefetch_hit age_secs={}",
+                        now.saturating_sub(response.cached_at.unwrap_or(now))
+                    );
+                    Ok(response)
+                }
+                None => analyze_journal(&config).inspect(|response| {
+                    record_fresh_analysis(request_id, state, &config, response, cpu_before);
+                }),
+            };
+            result.and_then(|mut response| {
+                // 统计与查看缓存已用完整结果记录，只压缩发给客户端的部分
+                if let Some(limit) = config.max_response_bytes {
+                    fit_response(&mut response, limit);
+                    if let Some(truncation) = &response.truncated {
+                        eprintln!(
+                            "request_id={request_id} event=response_truncated limit_bytes={limit} omitted_suspects={}",
+                            truncation.omitted_suspects
+                        );
+                    }
+                }
+                write_json_line(&mut write_stream, &response, "分析响应")
+            })
+        }
+        RunMode::Stream if config.live => {
+            // 请求行之后的内容是控制消息；会话可能持续很久，取消读取超时
+            buf_reader
+                .get_ref()
+                .set_read_timeout(None)
+                .map_err(|e| format!("设置读取超时失败：{e}"))?;
+            let mut sink = stream_sink(request_id, &mut write_stream, state, &config)?;
+            let live = Arc::new(Mutex::new(LiveFilters::from_config(&config)));
+            let controls = spawn_stream_control_reader(buf_reader, Arc::clone(&live));
+            let result = stream_journal_live_to_writer(&config, &mut sink, Some(&live));
+            // 唤醒仍阻塞在读取上的控制线程
+            let _ = write_stream.shutdown(Shutdown::Read);
+            let _ = controls.join();
+            log_dropped_sinks(request_id, &sink);
+            result
+        }
         RunMode::Stream => {
-            // 直接将 socket 作为 writer 传入，实现边读边发的真正流式输出
-            stream_journal_to_writer(&config, &mut write_stream)
+            // socket（以及归档文件）作为 writer 传入，实现边读边发的真正流式输出
+            let mut sink = stream_sink(request_id, &mut write_stream, state, &config)?;
+            let result = stream_journal_to_writer(&config, &mut sink);
+            log_dropped_sinks(request_id, &sink);
+            result
+        }
+        RunMode::Stats => {
+            let stats = state.snapshot(&loaded_rule_ids());
+            write_json_line(&mut write_stream, &stats, "统计响应")
         }
+        RunMode::Compare => logtool::compare::compare_units(&config)
+            .and_then(|response| write_json_line(&mut write_stream, &response, "对比响应")),
+        RunMode::PackageMap => write_json_line(
+            &mut write_stream,
+            &logtool::packages::learned_map(),
+            "包映射响应",
+        ),
     };
 
     if let Err(err) = run_result {
-        let (code, hint) = runtime_error_metadata(&err);
-        let _ = send_error_response(&mut write_stream, &err, code, hint.as_deref());
+        // 流模式的失败已在流内以带 error_kind 的结束行回报
+        if config.mode != RunMode::Stream {
+            let (code, hint) = runtime_error_metadata(&err);
+            let _ = send_error_response(&mut write_stream, &err, code, hint.as_deref());
+        }
         return Err(err);
     }
 
     Ok(())
 }
 
+/// 实际执行的分析计入规则命中、查看缓存与资源统计（预先计算的结果不重复计入）
+fn record_fresh_analysis(
+    request_id: u64,
+    state: &DaemonState,
+    config: &Config,
+    response: &AnalyzeResponse,
+    cpu_before: Option<u64>,
+) {
+    let now = unix_now_secs();
+    state.record_analysis(&response.rule_hits, now);
+    if let Ok(mut viewer) = state.viewer.lock() {
+        viewer.record(config, response, now);
+    }
+    if let (Some(before), Some(after)) = (cpu_before, thread_cpu_us()) {
+        let cost = analysis_cost(
+            config,
+            now,
+            after.saturating_sub(before),
+            response.journalctl_usage.unwrap_or_default(),
+        );
+        eprintln!(
+            "request_id={request_id} event=analysis_usage cpu_us={} journalctl_cpu_us={} journalctl_max_rss_kib={}",
+            cost.cpu_us, cost.journalctl_cpu_us, cost.journalctl_max_rss_kib
+        );
+        state.record_usage(cost);
+    }
+}
+
+/// 客户端连接为主目标；需要归档时加上归档文件。打开失败时已向客户端回报错误
+fn stream_sink(
+    request_id: u64,
+    stream: &mut UnixStream,
+    state: &DaemonState,
+    config: &Config,
+) -> Result<FanOut, String> {
+    let fail = |stream: &mut UnixStream, msg: String, hint: &str| {
+        let _ = send_error_response(stream, &msg, None, Some(hint));
+        Err(msg)
+    };
+    let client = match stream.try_clone() {
+        Ok(client) => client,
+        Err(err) => {
+            return fail(
+                stream,
+                format!("复制客户端连接失败：{err}"),
+                "修复：重试请求",
+            );
+        }
+    };
+    let sink = FanOut::new(Box::new(client));
+    let policy = match &state.archive {
+        Some(policy) if policy.all || config.archive => policy,
+        Some(_) => return Ok(sink),
+        None if config.archive => {
+            return fail(
+                stream,
+                "守护进程未启用会话归档".to_string(),
+                "修复：以 logtool-daemon --archive-dir /var/log/logtool/sessions 启动，或去掉 --archive",
+            );
+        }
+        None => return Ok(sink),
+    };
+    let path = policy
+        .dir
+        .join(format!("stream-{}-{request_id}.ndjson", unix_now_secs()));
+    match RotatingFileWriter::open(&path, STREAM_OUTPUT_ROTATE_BYTES) {
+        Ok(archive) => {
+            eprintln!(
+                "request_id={request_id} event=stream_archived path={}",
+                sanitize_log_field(&archive.path().display().to_string())
+            );
+            Ok(sink.with(Box::new(archive)))
+        }
+        Err(err) => fail(
+            stream,
+            err,
+            "修复：检查守护进程的 --archive-dir 目录权限与剩余空间",
+        ),
+    }
+}
+
+/// 归档等附加目标写入失败不影响客户端，只记录在 daemon 日志中
+fn log_dropped_sinks(request_id: u64, sink: &FanOut) {
+    for dropped in sink.dropped() {
+        eprintln!(
+            "request_id={request_id} event=sink_dropped error={}",
+            sanitize_log_field(dropped)
+        );
+    }
+}
+
 fn send_error_response(
     stream: &mut UnixStream,
     message: &str,
@@ -257,6 +852,9 @@ fn run_mode_label(mode: &RunMode) -> &'static str {
     match mode {
         RunMode::Analyze => "analyze",
         RunMode::Stream => "stream",
+        RunMode::Stats => "stats",
+        RunMode::PackageMap => "package_map",
+        RunMode::Compare => "compare",
     }
 }
 
@@ -349,10 +947,10 @@ impl Drop for ActiveClientGuard {
     }
 }
 
-fn try_set_socket_group(group: &str) -> Result<(), String> {
+fn try_set_socket_group(group: &str, socket_path: &Path) -> Result<(), String> {
     let status = Command::new("chgrp")
         .arg(group)
-        .arg(SOCKET_PATH)
+        .arg(socket_path)
         .status()
         .map_err(|e| format!("设置 Socket 组为 {group} 失败：{e}"))?;
 
@@ -374,9 +972,16 @@ fn daemon_help_text() -> &'static str {
 选项：
   -h, --help          显示此帮助信息
   -F, --foreground    前台运行（调试用，默认即前台）
+  --archive-dir <目录>  流会话另存到该目录（请求带 --archive 时）
+  --archive-all       归档全部流会话（审计用，需 --archive-dir）
+  --prefetch <秒>     定期预先计算默认分析（最近 2 小时、级别 3），
+                      裸 logtool 请求直接返回结果（至少 30 秒）
+  --socket-dir <目录>  只在该目录下创建 Socket（默认 /run，不可用时
+                      回退到 $XDG_RUNTIME_DIR；也可用 $LOGTOOL_SOCKET_DIR）
 
 说明：
-  守护进程监听 Unix Socket（/run/logtool.sock），
+  守护进程监听 Unix Socket（/run/logtool.sock；/run 不可写时为
+  $XDG_RUNTIME_DIR/logtool.sock，CLI 按同样顺序查找），
   接收来自 logtool CLI 的分析请求并返回结果。
   每个连接在独立线程中处理，互不阻塞。
 
@@ -404,6 +1009,7 @@ fn warn_if_journal_not_persistent() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use logtool::AnalyzeMetrics;
     use std::io::Cursor;
 
     #[test]
@@ -452,6 +1058,180 @@ mod tests {
         assert!(payload.hint.is_some());
     }
 
+    #[test]
+    fn snapshot_merges_loaded_rules_with_hit_counters() {
+        let state = DaemonState::new(None, None);
+        let hit = |id: &str, count| RuleHit {
+            id: id.to_string(),
+            count,
+        };
+        state.record_analysis(&[hit("acpi", 5), hit("gone", 1)], 100);
+        state.record_analysis(&[hit("acpi", 2)], 200);
+
+        let loaded = [
+            (
+                "acpi".to_string(),
+                "/etc/logtool/rules.d/a.json".to_string(),
+            ),
+            (
+                "dead".to_string(),
+                "/etc/logtool/rules.d/a.json".to_string(),
+            ),
+        ];
+        let stats = state.snapshot(&loaded);
+        assert_eq!(stats.analyses, 2);
+        let view = stats
+            .rules
+            .iter()
+            .map(|r| (r.id.as_str(), r.hits, r.analyses, r.file.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            view,
+            vec![
+                ("acpi", 7, 2, true),
+                ("gone", 1, 1, false),
+                ("dead", 0, 0, true)
+            ]
+        );
+        assert_eq!(stats.rules[0].last_hit_at, Some(200));
+    }
+
+    #[test]
+    fn usage_keeps_heaviest_analyses_in_cpu_order() {
+        let state = DaemonState::new(None, None);
+        for (at, cpu) in [
+            (1, 300),
+            (2, 100),
+            (3, 900),
+            (4, 300),
+            (5, 50),
+            (6, 700),
+            (7, 10),
+        ] {
+            state.record_usage(analysis_cost(
+                &Config::default(),
+                at,
+                cpu,
+                ResourceUsage {
+                    cpu_us: 1,
+                    max_rss_kib: at * 1024,
+                },
+            ));
+        }
+        let resources = state.resources.lock().expect("锁").clone();
+        assert_eq!(resources.measured, 7);
+        assert_eq!(resources.cpu_us_total, 2367);
+        assert_eq!(resources.cpu_us_max, 901);
+        assert_eq!(resources.journalctl_max_rss_kib, 7 * 1024);
+        assert_eq!(
+            resources.heaviest.iter().map(|c| c.at).collect::<Vec<_>>(),
+            vec![3, 6, 1, 4, 2]
+        );
+        assert!(resources.heaviest[0].command.starts_with("journalctl "));
+    }
+
+    #[test]
+    fn archive_args_require_directory() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(parse_archive_args(&args(&["--foreground"])), Ok(None));
+        assert_eq!(
+            parse_archive_args(&args(&["--archive-dir", "/srv/a", "--archive-all"])),
+            Ok(Some(ArchivePolicy {
+                dir: PathBuf::from("/srv/a"),
+                all: true,
+            }))
+        );
+        assert_eq!(
+            parse_archive_args(&args(&["--archive-dir=/srv/b"])).map(|p| p.map(|p| p.all)),
+            Ok(Some(false))
+        );
+        assert!(parse_archive_args(&args(&["--archive-all"])).is_err());
+        assert!(parse_archive_args(&args(&["--archive-dir"])).is_err());
+    }
+
+    fn empty_analysis() -> AnalyzeResponse {
+        AnalyzeResponse {
+            metrics: AnalyzeMetrics::default(),
+            suspects: Vec::new(),
+            top: 10,
+            environment: None,
+            rule_hits: Vec::new(),
+            namespace: None,
+            directory: None,
+            files: Vec::new(),
+            syslog_files: Vec::new(),
+            kernel_fallback: None,
+            hosts: Vec::new(),
+            boots: Vec::new(),
+            timeline: Vec::new(),
+            histogram: None,
+            bursts: Vec::new(),
+            trend: None,
+            baseline: None,
+            reboots: Vec::new(),
+            crashes: Vec::new(),
+            hangs: Vec::new(),
+            network: Vec::new(),
+            hardware: Vec::new(),
+            audit: Vec::new(),
+            failed_units: Vec::new(),
+            root_causes: Vec::new(),
+            smart: Vec::new(),
+            by_package: None,
+            by_user: None,
+            per_boot: None,
+            journalctl_usage: None,
+            truncated: None,
+            cached_at: None,
+            evidence: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn prefetch_serves_only_the_default_query_while_fresh() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert!(parse_prefetch_args(&args(&[])).unwrap().is_none());
+        assert_eq!(
+            parse_prefetch_args(&args(&["--prefetch=300"]))
+                .unwrap()
+                .map(|p| p.interval_secs),
+            Some(300)
+        );
+        assert!(parse_prefetch_args(&args(&["--prefetch", "5"])).is_err());
+        assert!(parse_prefetch_args(&args(&["--prefetch"])).is_err());
+
+        let prefetch = Prefetch::new(60);
+        let bare = Config {
+            max_response_bytes: Some(4096),
+            ..Config::default()
+        };
+        assert!(is_default_query(&bare));
+        assert!(!is_default_query(&Config {
+            fresh: true,
+            ..Config::default()
+        }));
+        assert!(!is_default_query(&Config {
+            dedup: true,
+            ..Config::default()
+        }));
+
+        // 第一次遇到带配置文件设置的裸请求：改为预先计算它
+        assert!(prefetch.lookup(&bare, 1_000).is_none());
+        let target = prefetch.target().expect("应有预计算目标");
+        assert_eq!(target, bare);
+        prefetch.store(&target, 1_000, empty_analysis());
+        let hit = prefetch.lookup(&bare, 1_090).expect("两个周期内应命中");
+        assert_eq!(hit.cached_at, Some(1_000));
+        // daemon 为每个请求填写的对端身份不影响命中
+        let from_user = Config {
+            peer: Some(socket::Peer::anonymous()),
+            ..bare.clone()
+        };
+        assert!(prefetch.lookup(&from_user, 1_090).is_some());
+        assert!(prefetch.lookup(&bare, 1_121).is_none());
+        assert!(prefetch.lookup(&Config::default(), 1_010).is_none());
+    }
+
     #[test]
     fn runtime_error_metadata_maps_journalctl_failure() {
         let (code, hint) = runtime_error_metadata("启动 journalctl 失败：missing");
@@ -0,0 +1,243 @@
+// 突发检测 — `--bursts` 找出每分钟事件数远超自身近期基线的时段，避免间歇性的错误风暴被平均值掩盖
+//
+// 基线为该来源此前 BASELINE_MINUTES 分钟（不早于窗口内第一条事件）的事件数；某分钟至少
+// BURST_MIN_PER_MINUTE 条、且每分钟速率达到基线速率的 BURST_FACTOR 倍时视为突发（基线为 0 时按
+// 1 条计）。窗口开头不足 MIN_HISTORY_MINUTES 分钟的历史无从比较，不判定突发。
+// 相邻的突发分钟合并为一段，段内沿用起始分钟的基线，以免风暴自己抬高基线。
+
+use crate::timeutil::format_local_minute;
+use crate::{SourceKind, source_label_cn, suspect_id};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+pub const BASELINE_MINUTES: u64 = 30;
+pub const MIN_HISTORY_MINUTES: u64 = 10;
+pub const BURST_MIN_PER_MINUTE: u64 = 5;
+pub const BURST_FACTOR: u64 = 5;
+/// 报告中最多列出的突发段数，按倍数取最显著的
+pub const MAX_BURSTS: usize = 10;
+
+const MINUTE_US: u64 = 60_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Burst {
+    pub id: String,
+    pub source_kind: SourceKind,
+    pub source: String,
+    /// 突发段的起始分钟与结束分钟（含，UTC 微秒）
+    pub start_us: u64,
+    pub end_us: u64,
+    /// 段内的事件数
+    pub count: u64,
+    /// 段内单分钟最多的事件数
+    pub peak_per_minute: u64,
+    /// 起始分钟之前 baseline_minutes 分钟内的事件数
+    pub baseline_events: u64,
+    pub baseline_minutes: u64,
+    /// 峰值速率 / 基线速率（取整）
+    pub magnitude: u64,
+}
+
+/// 分析过程中按分钟累计各来源的事件数
+#[derive(Debug, Default)]
+pub struct BurstTracker {
+    first_minute: Option<u64>,
+    minutes: HashMap<(SourceKind, String), BTreeMap<u64, u64>>,
+}
+
+impl BurstTracker {
+    pub fn record(&mut self, kind: SourceKind, source: &str, timestamp_us: u64) {
+        let minute = timestamp_us / MINUTE_US;
+        self.first_minute = Some(self.first_minute.map_or(minute, |first| first.min(minute)));
+        *self
+            .minutes
+            .entry((kind, source.to_string()))
+            .or_default()
+            .entry(minute)
+            .or_insert(0) += 1;
+    }
+
+    /// 所有来源的突发段：按倍数取前 MAX_BURSTS 段，再按时间排序
+    pub fn build(&self) -> Vec<Burst> {
+        let Some(first_minute) = self.first_minute else {
+            return Vec::new();
+        };
+        let mut bursts = Vec::new();
+        for ((kind, source), minutes) in &self.minutes {
+            let mut current: Option<Burst> = None;
+            for (&minute, &count) in minutes {
+                if let Some(burst) = &mut current {
+                    let end = burst.end_us / MINUTE_US;
+                    if minute == end + 1
+                        && exceeds(count, burst.baseline_events, burst.baseline_minutes)
+                    {
+                        burst.end_us = minute * MINUTE_US;
+                        burst.count += count;
+                        burst.peak_per_minute = burst.peak_per_minute.max(count);
+                        continue;
+                    }
+                    bursts.extend(current.take());
+                }
+                let history = (minute - first_minute).min(BASELINE_MINUTES);
+                if history < MIN_HISTORY_MINUTES {
+                    continue;
+                }
+                let baseline_events = minutes
+                    .range(minute - history..minute)
+                    .map(|(_, c)| c)
+                    .sum();
+                if exceeds(count, baseline_events, history) {
+                    current = Some(Burst {
+                        id: suspect_id(*kind, source),
+                        source_kind: *kind,
+                        source: source.clone(),
+                        start_us: minute * MINUTE_US,
+                        end_us: minute * MINUTE_US,
+                        count,
+                        peak_per_minute: count,
+                        baseline_events,
+                        baseline_minutes: history,
+                        magnitude: 0,
+                    });
+                }
+            }
+            bursts.extend(current);
+        }
+        for burst in &mut bursts {
+            burst.magnitude =
+                burst.peak_per_minute * burst.baseline_minutes / burst.baseline_events.max(1);
+        }
+        bursts.sort_by(|a, b| {
+            b.magnitude
+                .cmp(&a.magnitude)
+                .then(b.count.cmp(&a.count))
+                .then(a.start_us.cmp(&b.start_us))
+        });
+        bursts.truncate(MAX_BURSTS);
+        bursts.sort_by(|a, b| a.start_us.cmp(&b.start_us).then_with(|| a.id.cmp(&b.id)));
+        bursts
+    }
+}
+
+fn exceeds(count: u64, baseline_events: u64, baseline_minutes: u64) -> bool {
+    count >= BURST_MIN_PER_MINUTE
+        && count * baseline_minutes >= BURST_FACTOR * baseline_events.max(1)
+}
+
+pub fn write_bursts_section<W: Write>(
+    out: &mut W,
+    bursts: &[Burst],
+    offset_secs: i64,
+) -> io::Result<()> {
+    for burst in bursts {
+        writeln!(
+            out,
+            "  {} 至 {}  [{}] {} ×{}，峰值 {} 条/分钟，约为基线的 {} 倍（此前 {} 分钟共 {} 条）",
+            format_local_minute(burst.start_us, offset_secs),
+            end_clock(burst, offset_secs),
+            source_label_cn(burst.source_kind),
+            burst.source,
+            burst.count,
+            burst.peak_per_minute,
+            burst.magnitude,
+            burst.baseline_minutes,
+            burst.baseline_events
+        )?;
+    }
+    Ok(())
+}
+
+/// 逐句版本（--linear）
+pub fn burst_sentences(bursts: &[Burst], offset_secs: i64) -> Vec<String> {
+    bursts
+        .iter()
+        .map(|burst| {
+            format!(
+                "{} 至 {}，{} {} 突发 {} 条事件，峰值每分钟 {} 条，约为此前基线的 {} 倍。",
+                format_local_minute(burst.start_us, offset_secs),
+                end_clock(burst, offset_secs),
+                source_label_cn(burst.source_kind),
+                burst.source,
+                burst.count,
+                burst.peak_per_minute,
+                burst.magnitude
+            )
+        })
+        .collect()
+}
+
+/// 结束分钟本身也包含在内，故 +1 分钟；同一天只显示 `HH:MM`
+fn end_clock(burst: &Burst, offset_secs: i64) -> String {
+    let start = format_local_minute(burst.start_us, offset_secs);
+    let end = format_local_minute(burst.end_us + MINUTE_US, offset_secs);
+    if start[..10] == end[..10] {
+        end[11..].to_string()
+    } else {
+        end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spikes_above_the_rolling_baseline_are_reported() {
+        let base = 1_704_069_000_000_000; // 2024-01-01 00:30 UTC
+        let mut tracker = BurstTracker::default();
+        for minute in 0..40 {
+            // 稳定的每分钟 6 条：不算突发
+            for i in 0..6 {
+                tracker.record(
+                    SourceKind::Unit,
+                    "steady.service",
+                    base + minute * MINUTE_US + i,
+                );
+            }
+            // 平时每 5 分钟 1 条，00:50–00:51 两分钟内 30 + 12 条
+            let count = match minute {
+                20 => 30,
+                21 => 12,
+                m if m % 5 == 0 => 1,
+                _ => 0,
+            };
+            for i in 0..count {
+                tracker.record(
+                    SourceKind::Unit,
+                    "db.service",
+                    base + minute * MINUTE_US + i,
+                );
+            }
+        }
+        // 窗口开头的历史不足以比较
+        for i in 0..50 {
+            tracker.record(SourceKind::Kernel, "kernel", base + 2 * MINUTE_US + i);
+        }
+
+        let bursts = tracker.build();
+        assert_eq!(bursts.len(), 1, "{bursts:#?}");
+        let burst = &bursts[0];
+        assert_eq!(burst.id, "unit:db.service");
+        assert_eq!(burst.start_us, base + 20 * MINUTE_US);
+        assert_eq!(burst.end_us, base + 21 * MINUTE_US);
+        assert_eq!((burst.count, burst.peak_per_minute), (42, 30));
+        // 基线 20 分钟内 4 条：30 / (4/20) = 150 倍
+        assert_eq!((burst.baseline_events, burst.baseline_minutes), (4, 20));
+        assert_eq!(burst.magnitude, 150);
+
+        let mut out = Vec::new();
+        write_bursts_section(&mut out, &bursts, 0).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert_eq!(
+            text,
+            "  2024-01-01 00:50 至 00:52  [服务单元] db.service ×42，峰值 30 条/分钟，约为基线的 150 倍（此前 20 分钟共 4 条）\n"
+        );
+        assert_eq!(
+            burst_sentences(&bursts, 0),
+            [
+                "2024-01-01 00:50 至 00:52，服务单元 db.service 突发 42 条事件，峰值每分钟 30 条，约为此前基线的 150 倍。"
+            ]
+        );
+    }
+}
@@ -0,0 +1,199 @@
+// 消息聚类 — --clusters：把同一来源中只差数字、地址、路径的消息归为同一模板并计数
+//
+// 按空白切分后逐词归一：路径 → `<path>`，IPv4（可带端口）→ `<ip>`，0x 十六进制 → `<hex>`，
+// UUID 与长十六进制串 → `<id>`，其余词中的数字串 → `<n>`；`key=value`、`open("…")` 只归一分隔符之后的部分。
+// `connection to 10.0.0.7 failed` 与 `connection to 10.0.0.9 failed` 因此同属
+// `connection to <ip> failed`。--redact 的 `<ip>`、`<user>` 等遮盖标记不含数字，归一后保持不变。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 单个来源最多跟踪的模板数；之后出现的新模板不再计入聚类（来源总数不受影响）
+const MAX_TEMPLATES: usize = 512;
+
+/// 某一消息模板在来源中的出现次数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageCluster {
+    pub template: String,
+    pub count: u64,
+    /// 最近一条属于该模板的原始消息
+    pub example: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ClusterCounter {
+    clusters: HashMap<String, MessageCluster>,
+}
+
+impl ClusterCounter {
+    /// template 为 message_template 的结果（可截断），example 为展示用的原始消息
+    pub fn record(&mut self, template: String, example: &str) {
+        if let Some(cluster) = self.clusters.get_mut(&template) {
+            cluster.count += 1;
+            cluster.example = example.to_string();
+            return;
+        }
+        if self.clusters.len() < MAX_TEMPLATES {
+            self.clusters.insert(
+                template.clone(),
+                MessageCluster {
+                    template,
+                    count: 1,
+                    example: example.to_string(),
+                },
+            );
+        }
+    }
+
+    /// 按次数降序、同次数按模板排序，取前 limit 类
+    pub fn top(self, limit: usize) -> Vec<MessageCluster> {
+        let mut clusters = self.clusters.into_values().collect::<Vec<_>>();
+        clusters.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.template.cmp(&b.template))
+        });
+        clusters.truncate(limit);
+        clusters
+    }
+}
+
+pub fn message_template(message: &str) -> String {
+    message
+        .split_whitespace()
+        .map(normalize_word)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn normalize_word(word: &str) -> String {
+    // 保留两侧的括号、引号与句末标点
+    let start = word
+        .find(|c: char| !matches!(c, '(' | '[' | '{' | '"' | '\'' | '<'))
+        .unwrap_or(word.len());
+    let end = word
+        .rfind(|c: char| {
+            !matches!(
+                c,
+                ')' | ']' | '}' | '"' | '\'' | '>' | ',' | ';' | '.' | ':'
+            )
+        })
+        .map_or(start, |i| {
+            i + word[i..].chars().next().map_or(1, char::len_utf8)
+        });
+    if start >= end {
+        return word.to_string();
+    }
+    let core = &word[start..end];
+    // `pid=42`、`open("/tmp/x")`：只归一分隔符之后的部分
+    let normalized = match core.find(['=', '(', '"', '\'']) {
+        Some(pos) => format!("{}{}", &core[..=pos], normalize_word(&core[pos + 1..])),
+        None => normalize_token(core),
+    };
+    format!("{}{normalized}{}", &word[..start], &word[end..])
+}
+
+fn normalize_token(token: &str) -> String {
+    if token.len() > 1 && token.starts_with('/') {
+        return "<path>".to_string();
+    }
+    if is_ipv4(token)
+        || token
+            .rsplit_once(':')
+            .is_some_and(|(addr, port)| is_ipv4(addr) && is_digits(port))
+    {
+        return "<ip>".to_string();
+    }
+    if let Some(hex) = token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+        && !hex.is_empty()
+        && hex.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return "<hex>".to_string();
+    }
+    if is_uuid(token) || is_long_hex(token) {
+        return "<id>".to_string();
+    }
+    let mut out = String::with_capacity(token.len());
+    let mut in_digits = false;
+    for c in token.chars() {
+        if c.is_ascii_digit() {
+            if !in_digits {
+                out.push_str("<n>");
+            }
+            in_digits = true;
+        } else {
+            out.push(c);
+            in_digits = false;
+        }
+    }
+    out
+}
+
+fn is_digits(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_ipv4(text: &str) -> bool {
+    let parts = text.split('.').collect::<Vec<_>>();
+    parts.len() == 4 && parts.iter().all(|p| p.len() <= 3 && is_digits(p))
+}
+
+fn is_uuid(text: &str) -> bool {
+    let groups = text.split('-').map(str::len).collect::<Vec<_>>();
+    groups == [8, 4, 4, 4, 12] && text.chars().all(|c| c == '-' || c.is_ascii_hexdigit())
+}
+
+/// 容器 ID、哈希、machine-id 等：至少 12 位，且同时含数字与字母，避免把普通单词当成 ID
+fn is_long_hex(text: &str) -> bool {
+    text.len() >= 12
+        && text.chars().all(|c| c.is_ascii_hexdigit())
+        && text.chars().any(|c| c.is_ascii_digit())
+        && text.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similar_messages_share_a_template() {
+        assert_eq!(
+            message_template("connection to 10.0.0.7 failed"),
+            "connection to <ip> failed"
+        );
+        assert_eq!(
+            message_template("upstream 192.168.1.20:8080 timed out after 30s (attempt 3)"),
+            "upstream <ip> timed out after <n>s (attempt <n>)"
+        );
+        assert_eq!(
+            message_template("segfault at 0x7f3a2c ip 00007f3a2c10b3d1 in libc.so.6"),
+            "segfault at <hex> ip <id> in libc.so.<n>"
+        );
+        assert_eq!(
+            message_template("open(\"/var/lib/app/cache/1234.db\") failed: pid=4242, user=<user>."),
+            "open(\"<path>\") failed: pid=<n>, user=<user>."
+        );
+        assert_eq!(
+            message_template("job 3f2c9a1e-0b7d-4c1e-9f00-1a2b3c4d5e6f finished"),
+            "job <id> finished"
+        );
+        // 普通单词不视为十六进制 ID
+        assert_eq!(message_template("deadbeefcafe added"), "deadbeefcafe added");
+
+        let mut counter = ClusterCounter::default();
+        for message in [
+            "connection to 10.0.0.7 failed",
+            "disk full",
+            "connection to 10.0.0.9 failed",
+        ] {
+            counter.record(message_template(message), message);
+        }
+        let clusters = counter.top(5);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].template, "connection to <ip> failed");
+        assert_eq!(clusters[0].count, 2);
+        assert_eq!(clusters[0].example, "connection to 10.0.0.9 failed");
+    }
+}
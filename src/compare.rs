@@ -0,0 +1,386 @@
+// 单元对比 — `logtool compare --unit a --unit b`
+//
+// 两个单元分别查询（journalctl --unit 同时匹配 systemd 关于该单元的消息），
+// 各自统计事件数、级别分布与常见消息，再按分钟对齐两条事件序列，给出
+// 相关系数、活跃分钟的重合比例与首次出现的先后，回答“A 的失败是不是 B 引起的”。
+
+use crate::options::display_width;
+use crate::redact::Redactor;
+use crate::timeutil::{format_duration_cn, format_local_second};
+use crate::{
+    Config, JournalEvent, event_is_excluded, event_matches_filters, priority_label_cn,
+    read_journal_events, truncate_for_display,
+};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+const MINUTE_US: u64 = 60_000_000;
+/// 每个单元列出的常见消息条数
+pub const TOP_MESSAGES: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageCount {
+    pub message: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnitProfile {
+    pub unit: String,
+    pub count: u64,
+    /// 没有事件或事件都没有 PRIORITY 时为 None
+    pub worst_priority: Option<u8>,
+    /// 下标即级别 0-7
+    pub priority_counts: Vec<u64>,
+    pub top_messages: Vec<MessageCount>,
+    pub first_us: Option<u64>,
+    pub last_us: Option<u64>,
+    /// 分钟序号 → 事件数
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub minutes: BTreeMap<u64, u64>,
+}
+
+/// 两个单元在时间上的关联
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Correlation {
+    /// 两者覆盖时间段内每分钟事件数的皮尔逊相关系数；任一方无变化时为 None
+    pub pearson: Option<f64>,
+    /// A 的活跃分钟中，±1 分钟内 B 也有事件的比例（0-1）
+    pub a_with_b: Option<f64>,
+    pub b_with_a: Option<f64>,
+    /// B 首次出现减 A 首次出现（秒），负数表示 B 先出现
+    pub first_seen_delta_secs: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompareResponse {
+    pub a: UnitProfile,
+    pub b: UnitProfile,
+    pub correlation: Correlation,
+}
+
+/// daemon 侧入口：config.units 由 validate_config 保证恰好两个
+pub fn compare_units(config: &Config) -> Result<CompareResponse, String> {
+    let [a, b] = config.units.as_slice() else {
+        return Err("compare 需要恰好两个 --unit".to_string());
+    };
+    let redactor = config.redact.then(Redactor::from_system);
+    let profile = |unit: &String| -> Result<UnitProfile, String> {
+        let single = Config {
+            units: vec![unit.clone()],
+            ..config.clone()
+        };
+        let events = read_journal_events(&single)?
+            .into_iter()
+            .filter(|event| {
+                event_matches_filters(event, config) && !event_is_excluded(event, config)
+            })
+            .collect::<Vec<_>>();
+        Ok(build_profile(unit, &events, redactor.as_ref()))
+    };
+    let a = profile(a)?;
+    let b = profile(b)?;
+    let correlation = correlate(&a, &b);
+    Ok(CompareResponse { a, b, correlation })
+}
+
+/// redactor 为 Some（--redact）时先遮盖再归并常见消息，只差 IP、主机名的消息计为同一条
+pub fn build_profile(
+    unit: &str,
+    events: &[JournalEvent],
+    redactor: Option<&Redactor>,
+) -> UnitProfile {
+    let mut profile = UnitProfile {
+        unit: unit.to_string(),
+        priority_counts: vec![0; 8],
+        ..UnitProfile::default()
+    };
+    let mut messages: HashMap<Cow<str>, u64> = HashMap::new();
+    for event in events {
+        profile.count += 1;
+        if let Some(p) = event.priority.filter(|p| *p < 8) {
+            profile.priority_counts[usize::from(p)] += 1;
+            profile.worst_priority = Some(profile.worst_priority.map_or(p, |w| w.min(p)));
+        }
+        if !event.message.is_empty() {
+            let message = match redactor {
+                Some(redactor) => Cow::Owned(redactor.redact(&event.message)),
+                None => Cow::Borrowed(event.message.as_str()),
+            };
+            *messages.entry(message).or_insert(0) += 1;
+        }
+        if let Some(ts) = event.timestamp_us {
+            profile.first_us = Some(profile.first_us.map_or(ts, |f| f.min(ts)));
+            profile.last_us = Some(profile.last_us.map_or(ts, |l| l.max(ts)));
+            *profile.minutes.entry(ts / MINUTE_US).or_insert(0) += 1;
+        }
+    }
+    let mut messages = messages.into_iter().collect::<Vec<_>>();
+    messages.sort_by(|x, y| y.1.cmp(&x.1).then_with(|| x.0.cmp(&y.0)));
+    profile.top_messages = messages
+        .into_iter()
+        .take(TOP_MESSAGES)
+        .map(|(message, count)| MessageCount {
+            message: truncate_for_display(&message, 120),
+            count,
+        })
+        .collect();
+    profile
+}
+
+pub fn correlate(a: &UnitProfile, b: &UnitProfile) -> Correlation {
+    let first_seen_delta_secs = match (a.first_us, b.first_us) {
+        (Some(a), Some(b)) => Some((b as i64 - a as i64) / 1_000_000),
+        _ => None,
+    };
+    Correlation {
+        pearson: pearson(&a.minutes, &b.minutes),
+        a_with_b: overlap(&a.minutes, &b.minutes),
+        b_with_a: overlap(&b.minutes, &a.minutes),
+        first_seen_delta_secs,
+    }
+}
+
+/// 在两者共同覆盖的分钟区间上计算（没有事件的分钟计为 0）
+fn pearson(a: &BTreeMap<u64, u64>, b: &BTreeMap<u64, u64>) -> Option<f64> {
+    let start = (*a.keys().next()?).min(*b.keys().next()?);
+    let end = (*a.keys().next_back()?).max(*b.keys().next_back()?);
+    let n = (end - start + 1) as f64;
+    let series = |m: &BTreeMap<u64, u64>, minute| m.get(&minute).copied().unwrap_or(0) as f64;
+    let mean_a = a.values().sum::<u64>() as f64 / n;
+    let mean_b = b.values().sum::<u64>() as f64 / n;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for minute in start..=end {
+        let da = series(a, minute) - mean_a;
+        let db = series(b, minute) - mean_b;
+        cov += da * db;
+        var_a += da * da;
+        var_b += db * db;
+    }
+    (var_a > 0.0 && var_b > 0.0).then(|| cov / (var_a.sqrt() * var_b.sqrt()))
+}
+
+fn overlap(from: &BTreeMap<u64, u64>, other: &BTreeMap<u64, u64>) -> Option<f64> {
+    if from.is_empty() {
+        return None;
+    }
+    let hits = from
+        .keys()
+        .filter(|minute| {
+            other
+                .range(minute.saturating_sub(1)..=*minute + 1)
+                .next()
+                .is_some()
+        })
+        .count();
+    Some(hits as f64 / from.len() as f64)
+}
+
+fn strength_cn(r: f64) -> String {
+    let level = match r.abs() {
+        x if x >= 0.7 => "强",
+        x if x >= 0.4 => "中等",
+        x if x >= 0.2 => "弱",
+        _ => return "几乎无关".to_string(),
+    };
+    format!("{level}{}相关", if r < 0.0 { "负" } else { "正" })
+}
+
+fn pad(text: &str, width: usize) -> String {
+    let fill = width.saturating_sub(display_width(text));
+    format!("{text}{}", " ".repeat(fill))
+}
+
+pub fn write_compare_report<W: Write>(
+    out: &mut W,
+    response: &CompareResponse,
+    offset_secs: i64,
+) -> io::Result<()> {
+    let (a, b) = (&response.a, &response.b);
+    let rule = "═══════════════════════════════════════════════════════════════";
+    writeln!(out, "{rule}")?;
+    writeln!(out, "          ⚖️  单元对比：{} ↔ {}", a.unit, b.unit)?;
+    writeln!(out, "{rule}")?;
+
+    let column = display_width(&a.unit).max(24) + 2;
+    let time =
+        |ts: Option<u64>| ts.map_or("-".to_string(), |ts| format_local_second(ts, offset_secs));
+    let worst = |p: Option<u8>| {
+        p.map_or("-".to_string(), |p| {
+            format!("{p}({})", priority_label_cn(p))
+        })
+    };
+    let levels = |profile: &UnitProfile| {
+        let parts = profile
+            .priority_counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(level, count)| format!("{count}×{}", crate::priority_keyword(level as u8)))
+            .collect::<Vec<_>>();
+        if parts.is_empty() {
+            "-".to_string()
+        } else {
+            parts.join(", ")
+        }
+    };
+    let rows = [
+        ("", a.unit.clone(), b.unit.clone()),
+        ("事件数", a.count.to_string(), b.count.to_string()),
+        (
+            "最高严重级别",
+            worst(a.worst_priority),
+            worst(b.worst_priority),
+        ),
+        ("级别分布", levels(a), levels(b)),
+        ("首次出现", time(a.first_us), time(b.first_us)),
+        ("最后出现", time(a.last_us), time(b.last_us)),
+        (
+            "活跃分钟数",
+            a.minutes.len().to_string(),
+            b.minutes.len().to_string(),
+        ),
+    ];
+    for (label, left, right) in rows {
+        writeln!(out, "  {}{}{right}", pad(label, 14), pad(&left, column))?;
+    }
+
+    let c = &response.correlation;
+    writeln!(out)?;
+    writeln!(out, "  时间关联：")?;
+    match c.pearson {
+        Some(r) => writeln!(
+            out,
+            "     每分钟事件数相关系数 r = {r:.2}（{}）",
+            strength_cn(r)
+        )?,
+        None => writeln!(
+            out,
+            "     相关系数：无法计算（至少一方没有事件或每分钟事件数没有变化）"
+        )?,
+    }
+    for (from, to, ratio) in [(a, b, c.a_with_b), (b, a, c.b_with_a)] {
+        if let Some(ratio) = ratio {
+            writeln!(
+                out,
+                "     {} 的活跃分钟中 {:.0}% 在 ±1 分钟内也有 {} 的事件",
+                from.unit,
+                ratio * 100.0,
+                to.unit
+            )?;
+        }
+    }
+    if let Some(delta) = c.first_seen_delta_secs {
+        let (earlier, later) = if delta < 0 { (b, a) } else { (a, b) };
+        if delta == 0 {
+            writeln!(out, "     两者在同一秒首次出现")?;
+        } else {
+            writeln!(
+                out,
+                "     {} 比 {} 早 {} 首次出现",
+                earlier.unit,
+                later.unit,
+                format_duration_cn(delta.unsigned_abs())
+            )?;
+        }
+    }
+
+    for profile in [a, b] {
+        writeln!(out)?;
+        writeln!(out, "  {} 常见消息：", profile.unit)?;
+        if profile.top_messages.is_empty() {
+            writeln!(out, "     （无）")?;
+        }
+        for message in &profile.top_messages {
+            writeln!(out, "     ×{} {}", message.count, message.message)?;
+        }
+    }
+    writeln!(out, "{rule}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(minute: u64, message: &str, priority: u8) -> JournalEvent {
+        JournalEvent {
+            message: message.to_string(),
+            priority: Some(priority),
+            timestamp_us: Some(1_704_069_000_000_000 + minute * MINUTE_US),
+            ..JournalEvent::default()
+        }
+    }
+
+    #[test]
+    fn profiles_and_correlates_two_units() {
+        let a_events = [0, 0, 5, 5, 5, 9]
+            .iter()
+            .map(|m| event(*m, "connection refused", 3))
+            .collect::<Vec<_>>();
+        let mut b_events = [0, 5, 5, 6]
+            .iter()
+            .map(|m| event(*m, "upstream timeout", 4))
+            .collect::<Vec<_>>();
+        b_events.push(event(6, "restarting", 2));
+
+        let a = build_profile("web.service", &a_events, None);
+        let b = build_profile("db.service", &b_events, None);
+        assert_eq!(a.count, 6);
+        assert_eq!(b.worst_priority, Some(2));
+        assert_eq!(
+            b.top_messages[0],
+            MessageCount {
+                message: "upstream timeout".to_string(),
+                count: 4
+            }
+        );
+
+        let c = correlate(&a, &b);
+        assert!(c.pearson.is_some_and(|r| r > 0.5), "{:?}", c.pearson);
+        // web 的活跃分钟 0、5、9 中 0 与 5 附近有 db 事件
+        assert_eq!(c.a_with_b.map(|r| (r * 100.0).round()), Some(67.0));
+        assert_eq!(c.b_with_a, Some(1.0));
+        assert_eq!(c.first_seen_delta_secs, Some(0));
+
+        let response = CompareResponse {
+            a,
+            b,
+            correlation: c,
+        };
+        let mut out = Vec::new();
+        write_compare_report(&mut out, &response, 0).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert!(text.contains("  事件数        6"));
+        assert!(text.contains("×4 upstream timeout"));
+        assert!(text.contains("两者在同一秒首次出现"));
+
+        let empty = build_profile("idle.service", &[], None);
+        assert_eq!(correlate(&response.a, &empty).pearson, None);
+    }
+
+    #[test]
+    fn redacted_profiles_hide_and_merge_top_messages() {
+        let events = [
+            event(0, "login from 10.0.0.1 on web1 for alice", 4),
+            event(1, "login from 10.0.0.2 on web1 for alice", 4),
+        ];
+        let redactor = Redactor::with_identity(vec!["web1".to_string()], vec!["alice".to_string()]);
+        let profile = build_profile("sshd.service", &events, Some(&redactor));
+        let [top] = profile.top_messages.as_slice() else {
+            panic!("遮盖后应归并为一条：{:?}", profile.top_messages);
+        };
+        assert_eq!(top.count, 2);
+        for secret in ["10.0.0.", "web1", "alice"] {
+            assert!(!top.message.contains(secret), "{}", top.message);
+        }
+        assert_eq!(
+            build_profile("sshd.service", &events, None)
+                .top_messages
+                .len(),
+            2
+        );
+    }
+}
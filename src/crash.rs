@@ -0,0 +1,262 @@
+// 程序崩溃 — 从内核的 `segfault at … in libfoo.so[…]` 与 `traps: … general protection fault …`
+// 记录中提取崩溃的进程与出错的二进制/库，按 (进程, 出错对象) 归并后单独成节，不再都算在 kernel 名下
+//
+// 这类记录的级别是 info（6），通常不在 --priority 范围内，因此另用一次
+// `journalctl _TRANSPORT=kernel --grep` 查询同一窗口；journalctl 不支持 --grep 或查询失败时不列出。
+// 内核只给出出错对象的文件名，所属包按 `dpkg-query -S '*/文件名'` 反查。
+
+use crate::redact::Redactor;
+use crate::timeutil::format_local_minute;
+use crate::{Config, add_window_args, parse_json_event};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// 报告中最多列出的崩溃类别，按次数取最多的
+pub const MAX_CRASHES: usize = 10;
+const CRASH_PATTERN: &str = "segfault at |traps: ";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Crash {
+    /// 崩溃进程名（内核记录的 comm，最长 15 个字符）
+    pub process: String,
+    /// 出错指令所在的二进制或库的文件名；不在任何映射内时为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object: Option<String>,
+    /// `segfault`、`general protection fault`、`trap invalid opcode` 等
+    pub fault: String,
+    pub count: u64,
+    /// 最近一次崩溃的进程号与时间（UTC 微秒）
+    pub last_pid: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_us: Option<u64>,
+    /// 出错对象所属的包
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+}
+
+impl Crash {
+    /// --redact：进程名与出错对象取自原始消息，同样遮盖
+    pub fn redact(&mut self, redactor: &Redactor) {
+        self.process = redactor.redact(&self.process);
+        self.object = self.object.as_deref().map(|object| redactor.redact(object));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segfault {
+    pub process: String,
+    pub pid: u32,
+    pub object: Option<String>,
+    pub fault: String,
+}
+
+/// 解析一条内核崩溃记录：
+/// `app[1234]: segfault at 0 ip 00007f… sp 00007ff… error 4 in libfoo.so.1[7f12+2000] likely on CPU 1`
+/// `traps: app[1234] general protection fault ip:7f… sp:7ff… error:0 in libc.so.6[7f…+195000]`
+pub fn parse_segfault(message: &str) -> Option<Segfault> {
+    let (head, fault, rest) = if let Some(at) = message.find("]: segfault at ") {
+        (&message[..at], "segfault".to_string(), &message[at + 2..])
+    } else {
+        let body = message.strip_prefix("traps: ")?;
+        let close = body.find("] ")?;
+        let rest = &body[close + 2..];
+        let fault = rest.split(" ip:").next()?.trim();
+        (&body[..close], fault.to_string(), rest)
+    };
+    let (process, pid) = head.rsplit_once('[')?;
+    let pid = pid.parse().ok()?;
+    let object = rest
+        .rfind(" in ")
+        .map(|at| &rest[at + 4..])
+        .and_then(|tail| tail.split_once('['))
+        .map(|(name, _)| name.to_string())
+        .filter(|name| !name.is_empty());
+    (!process.is_empty() && !fault.is_empty()).then(|| Segfault {
+        process: process.to_string(),
+        pid,
+        object,
+        fault,
+    })
+}
+
+/// 窗口内的崩溃，按次数降序取前 MAX_CRASHES 类；任何一步失败都按“没有崩溃”处理
+pub fn collect_crashes(config: &Config) -> Vec<Crash> {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager").arg("--quiet");
+    add_window_args(&mut cmd, config);
+    let Some(output) = cmd
+        .arg("_TRANSPORT=kernel")
+        .arg(format!("--grep={CRASH_PATTERN}"))
+        .arg("--output=json")
+        .arg("--output-fields=MESSAGE")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+    else {
+        return Vec::new();
+    };
+    let crashes = group_crashes(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| parse_json_event(line).ok())
+            .filter_map(|event| Some((parse_segfault(&event.message)?, event.timestamp_us))),
+    );
+    #[cfg(feature = "packages")]
+    let crashes = crate::packages::resolve_packages_for_crashes(crashes);
+    crashes
+}
+
+/// 按 (进程, 出错对象, 类型) 归并，按次数降序、再按最近出现排序
+pub fn group_crashes(events: impl IntoIterator<Item = (Segfault, Option<u64>)>) -> Vec<Crash> {
+    let mut groups: HashMap<(String, Option<String>, String), Crash> = HashMap::new();
+    for (segfault, timestamp_us) in events {
+        let key = (
+            segfault.process.clone(),
+            segfault.object.clone(),
+            segfault.fault.clone(),
+        );
+        let crash = groups.entry(key).or_insert_with(|| Crash {
+            process: segfault.process,
+            object: segfault.object,
+            fault: segfault.fault,
+            count: 0,
+            last_pid: segfault.pid,
+            last_us: None,
+            package: None,
+        });
+        crash.count += 1;
+        if timestamp_us >= crash.last_us {
+            crash.last_us = timestamp_us;
+            crash.last_pid = segfault.pid;
+        }
+    }
+    let mut crashes = groups.into_values().collect::<Vec<_>>();
+    crashes.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then(b.last_us.cmp(&a.last_us))
+            .then_with(|| a.process.cmp(&b.process))
+    });
+    crashes.truncate(MAX_CRASHES);
+    crashes
+}
+
+fn location(crash: &Crash) -> String {
+    let object = crash.object.as_deref().unwrap_or("未知位置");
+    match &crash.package {
+        Some(package) => format!("{object}（{package}）"),
+        None => object.to_string(),
+    }
+}
+
+fn last_seen(crash: &Crash, offset_secs: i64) -> String {
+    match crash.last_us {
+        Some(at) => format!(
+            "最近 {}，pid {}",
+            format_local_minute(at, offset_secs),
+            crash.last_pid
+        ),
+        None => format!("最近 pid {}", crash.last_pid),
+    }
+}
+
+pub fn write_crashes_section<W: Write>(
+    out: &mut W,
+    crashes: &[Crash],
+    offset_secs: i64,
+) -> io::Result<()> {
+    for crash in crashes {
+        writeln!(
+            out,
+            "  ×{} {}  {}，出错位置 {}  {}",
+            crash.count,
+            crash.process,
+            crash.fault,
+            location(crash),
+            last_seen(crash, offset_secs)
+        )?;
+    }
+    Ok(())
+}
+
+/// 逐句版本（--linear）
+pub fn crash_sentences(crashes: &[Crash], offset_secs: i64) -> Vec<String> {
+    crashes
+        .iter()
+        .map(|crash| {
+            format!(
+                "进程 {} 崩溃 {} 次，类型为 {}，出错位置 {}，{}。",
+                crash.process,
+                crash.count,
+                crash.fault,
+                location(crash),
+                last_seen(crash, offset_secs)
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_groups_kernel_crash_lines() {
+        let segfault = parse_segfault(
+            "php-fpm8.3[4121]: segfault at 0 ip 00007f3a2c10b3d1 sp 00007ffd5e1c8a40 error 4 in libfoo.so.1[7f3a2c100000+20000] likely on CPU 1 (core 1, socket 0)",
+        )
+        .expect("应识别 segfault");
+        assert_eq!(
+            segfault,
+            Segfault {
+                process: "php-fpm8.3".to_string(),
+                pid: 4121,
+                object: Some("libfoo.so.1".to_string()),
+                fault: "segfault".to_string(),
+            }
+        );
+        let gpf = parse_segfault(
+            "traps: my worker[88] general protection fault ip:7f01 sp:7ffe error:0 in libc.so.6[7f00+195000]",
+        )
+        .expect("应识别 traps");
+        assert_eq!(gpf.process, "my worker");
+        assert_eq!(gpf.fault, "general protection fault");
+        assert_eq!(gpf.object.as_deref(), Some("libc.so.6"));
+        // 出错地址不在任何映射内
+        let wild = parse_segfault("app[9]: segfault at 8 ip 0000000000000008 sp 00007ffd error 14")
+            .expect("应识别 segfault");
+        assert_eq!(wild.object, None);
+        assert!(parse_segfault("EXT4-fs error (device sda1): htree_dirblock_to_tree").is_none());
+
+        let base = 1_704_069_000_000_000; // 2024-01-01 00:30 UTC
+        let mut crashes = group_crashes([
+            (wild.clone(), Some(base)),
+            (segfault.clone(), Some(base + 1)),
+            (
+                Segfault {
+                    pid: 4200,
+                    ..segfault.clone()
+                },
+                Some(base + 120_000_000),
+            ),
+        ]);
+        assert_eq!(crashes.len(), 2);
+        assert_eq!((crashes[0].count, crashes[0].last_pid), (2, 4200));
+        crashes[0].package = Some("libfoo1".to_string());
+        let mut out = Vec::new();
+        write_crashes_section(&mut out, &crashes, 0).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert_eq!(
+            text,
+            "  ×2 php-fpm8.3  segfault，出错位置 libfoo.so.1（libfoo1）  最近 2024-01-01 00:32，pid 4200\n  ×1 app  segfault，出错位置 未知位置  最近 2024-01-01 00:30，pid 9\n"
+        );
+        assert_eq!(
+            crash_sentences(&crashes, 0)[0],
+            "进程 php-fpm8.3 崩溃 2 次，类型为 segfault，出错位置 libfoo.so.1（libfoo1），最近 2024-01-01 00:32，pid 4200。"
+        );
+    }
+}
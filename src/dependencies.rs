@@ -0,0 +1,171 @@
+// 依赖根因 — 前列来源中有多个单元同时报错时，用 `systemctl list-dependencies` 查出它们之间的依赖，
+// 标出“A 依赖的 B 也在报错”：B 更可能是根因、应先排查，A 的错误多半是连带的，不再当作互不相关的并列来源
+//
+// list-dependencies 默认只展开 target 的下级，得到的是单元的直接依赖（Requires=、Wants=、BindsTo= 等）
+// 与经由 target 拉起的单元；互相依赖的一对单元无法判断先后，不标注。查看的是本机当前的单元配置，
+// 读取 --directory / --file 时不查询；systemctl 不可用或调用失败时不标注。
+
+use crate::restarts::suspect_unit;
+use crate::{Config, SourceStats};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootCauseHint {
+    /// 受影响的单元
+    pub unit: String,
+    /// 它所依赖、同样在报错的单元，按来源排名
+    pub causes: Vec<String>,
+}
+
+/// 前 top 个来源所属单元之间的依赖；不足两个单元时不查询
+pub fn find_root_causes(suspects: &[SourceStats], config: &Config) -> Vec<RootCauseHint> {
+    if config.directory.is_some() || !config.files.is_empty() {
+        return Vec::new();
+    }
+    let mut units: Vec<&str> = Vec::new();
+    for unit in suspects.iter().take(config.top).filter_map(suspect_unit) {
+        if !units.contains(&unit) {
+            units.push(unit);
+        }
+    }
+    if units.len() < 2 {
+        return Vec::new();
+    }
+    let dependencies = units
+        .iter()
+        .filter_map(|unit| Some((*unit, list_dependencies(unit)?)))
+        .collect::<HashMap<_, _>>();
+    link_units(&units, &dependencies)
+}
+
+fn list_dependencies(unit: &str) -> Option<HashSet<String>> {
+    let output = Command::new("systemctl")
+        .arg("list-dependencies")
+        .arg("--plain")
+        .arg("--no-legend")
+        .arg("--no-pager")
+        .arg(unit)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())?;
+    Some(parse_dependencies(
+        unit,
+        &String::from_utf8_lossy(&output.stdout),
+    ))
+}
+
+/// 第一行是单元自身，之后每行一个依赖（按层级缩进）；旧版本不认 --plain 时带 `●`/`○` 与树形线
+pub fn parse_dependencies(unit: &str, output: &str) -> HashSet<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            line.trim_start_matches(|c: char| {
+                c.is_whitespace() || matches!(c, '●' | '○' | '│' | '├' | '└' | '─')
+            })
+            .split_whitespace()
+            .next()
+        })
+        .filter(|dependency| dependency.contains('.') && *dependency != unit)
+        .map(str::to_string)
+        .collect()
+}
+
+/// units 按来源排名；只保留单向的依赖
+pub fn link_units(
+    units: &[&str],
+    dependencies: &HashMap<&str, HashSet<String>>,
+) -> Vec<RootCauseHint> {
+    let depends = |unit: &str, other: &str| {
+        dependencies
+            .get(unit)
+            .is_some_and(|deps| deps.contains(other))
+    };
+    units
+        .iter()
+        .filter_map(|unit| {
+            let causes = units
+                .iter()
+                .filter(|other| other != &unit && depends(unit, other) && !depends(other, unit))
+                .map(|other| other.to_string())
+                .collect::<Vec<_>>();
+            (!causes.is_empty()).then(|| RootCauseHint {
+                unit: unit.to_string(),
+                causes,
+            })
+        })
+        .collect()
+}
+
+/// 某个来源所属单元的依赖标注：它依赖的报错单元、依赖它的报错单元
+pub fn suspect_links<'a>(
+    hints: &'a [RootCauseHint],
+    suspect: &SourceStats,
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let Some(unit) = suspect_unit(suspect) else {
+        return (Vec::new(), Vec::new());
+    };
+    let causes = hints
+        .iter()
+        .filter(|hint| hint.unit == unit)
+        .flat_map(|hint| hint.causes.iter().map(String::as_str))
+        .collect();
+    let dependents = hints
+        .iter()
+        .filter(|hint| hint.causes.iter().any(|cause| cause == unit))
+        .map(|hint| hint.unit.as_str())
+        .collect();
+    (causes, dependents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failing_dependencies_are_marked_as_root_causes() {
+        let app = parse_dependencies(
+            "app.service",
+            "app.service\n  postgresql.service\n  system.slice\n  sysinit.target\n    systemd-journald.service\n",
+        );
+        assert!(app.contains("postgresql.service"));
+        assert!(!app.contains("app.service"));
+        let web = parse_dependencies(
+            "web.service",
+            "web.service\n● ├─app.service\n● └─network-online.target\n",
+        );
+        assert!(web.contains("app.service"));
+
+        let dependencies = HashMap::from([
+            ("web.service", web),
+            ("app.service", app),
+            ("postgresql.service", HashSet::new()),
+            // 互相依赖的一对不标注
+            ("a.service", HashSet::from(["b.service".to_string()])),
+            ("b.service", HashSet::from(["a.service".to_string()])),
+        ]);
+        let units = [
+            "web.service",
+            "postgresql.service",
+            "app.service",
+            "a.service",
+            "b.service",
+        ];
+        assert_eq!(
+            link_units(&units, &dependencies),
+            [
+                RootCauseHint {
+                    unit: "web.service".to_string(),
+                    causes: vec!["app.service".to_string()],
+                },
+                RootCauseHint {
+                    unit: "app.service".to_string(),
+                    causes: vec!["postgresql.service".to_string()],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+// 快照对比 — `logtool diff <旧.json> <新.json>` 比较两份以 `--format json` 保存的分析结果，
+// 列出新出现、已消失与事件数有变化的来源，用于修复前后的验证
+//
+// 只比较两份文件中已有的来源与事件数，不重新查询 journal；两份快照应使用相同的过滤条件，
+// 窗口长度不同时事件数的增减没有可比性。
+
+use crate::trend::{self, SuspectTrend, TrendKind};
+use crate::{AnalyzeResponse, suspect_id};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotSummary {
+    pub path: String,
+    pub matched: usize,
+    pub sources: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    pub old: SnapshotSummary,
+    pub new: SnapshotSummary,
+    /// 新出现、增加、减少、已消失，各组内按变化量降序
+    pub changes: Vec<SuspectTrend>,
+    /// 两份快照中事件数相同的来源数
+    pub unchanged: usize,
+}
+
+pub fn load_snapshot(path: &Path) -> Result<AnalyzeResponse, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("读取快照 {} 失败：{err}", path.display()))?;
+    serde_json::from_str(&text).map_err(|err| {
+        format!(
+            "快照 {} 不是分析结果：{err}\n修复：用 logtool --format json --output <文件> 保存快照",
+            path.display()
+        )
+    })
+}
+
+pub fn diff_snapshots(
+    old: &AnalyzeResponse,
+    old_path: &str,
+    new: &AnalyzeResponse,
+    new_path: &str,
+) -> SnapshotDiff {
+    let all = trend::compare_suspects(&new.suspects, &old.suspects, usize::MAX);
+    let unchanged = all
+        .iter()
+        .filter(|change| change.kind == TrendKind::Unchanged)
+        .count();
+    let group = |kind: TrendKind| match kind {
+        TrendKind::New => 0,
+        TrendKind::Increased => 1,
+        TrendKind::Decreased => 2,
+        TrendKind::Resolved => 3,
+        TrendKind::Unchanged => 4,
+    };
+    let mut changes = all
+        .into_iter()
+        .filter(|change| change.kind != TrendKind::Unchanged)
+        .collect::<Vec<_>>();
+    changes.sort_by(|a, b| {
+        group(a.kind)
+            .cmp(&group(b.kind))
+            .then(
+                b.current
+                    .abs_diff(b.previous)
+                    .cmp(&a.current.abs_diff(a.previous)),
+            )
+            .then_with(|| {
+                suspect_id(a.source_kind, &a.source).cmp(&suspect_id(b.source_kind, &b.source))
+            })
+    });
+    let summary = |response: &AnalyzeResponse, path: &str| SnapshotSummary {
+        path: path.to_string(),
+        matched: response.metrics.matched,
+        sources: response.suspects.len(),
+    };
+    SnapshotDiff {
+        old: summary(old, old_path),
+        new: summary(new, new_path),
+        changes,
+        unchanged,
+    }
+}
+
+pub fn write_diff<W: Write>(out: &mut W, diff: &SnapshotDiff) -> io::Result<()> {
+    for (label, snapshot) in [("旧快照", &diff.old), ("新快照", &diff.new)] {
+        writeln!(
+            out,
+            "{label}：{}，共 {} 条、{} 个来源",
+            snapshot.path, snapshot.matched, snapshot.sources
+        )?;
+    }
+    writeln!(out)?;
+    if diff.changes.is_empty() {
+        writeln!(out, "两份快照的来源与事件数相同")?;
+        return Ok(());
+    }
+    for change in &diff.changes {
+        trend::write_change_line(out, change)?;
+    }
+    if diff.unchanged > 0 {
+        writeln!(out)?;
+        writeln!(out, "另有 {} 个来源事件数不变", diff.unchanged)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(sources: &[(&str, u64)]) -> AnalyzeResponse {
+        let suspects = sources
+            .iter()
+            .map(|(source, count)| {
+                serde_json::json!({
+                    "kind": "Unit",
+                    "source": source,
+                    "count": count,
+                    "worst_priority": 3,
+                    "sample_message": "",
+                })
+            })
+            .collect::<Vec<_>>();
+        let matched = sources.iter().map(|(_, count)| count).sum::<u64>();
+        serde_json::from_value(serde_json::json!({
+            "metrics": {"lines_read": matched, "parsed_ok": matched, "matched": matched, "parse_errors": 0},
+            "suspects": suspects,
+            "top": 10,
+        }))
+        .expect("快照应能解析")
+    }
+
+    #[test]
+    fn lists_new_resolved_and_changed_sources() {
+        let old = snapshot(&[
+            ("db.service", 9),
+            ("old.service", 7),
+            ("cron.service", 2),
+            ("web.service", 1),
+        ]);
+        let new = snapshot(&[
+            ("web.service", 40),
+            ("fresh.service", 4),
+            ("db.service", 3),
+            ("cron.service", 2),
+        ]);
+        let diff = diff_snapshots(&old, "before.json", &new, "after.json");
+        let summary = diff
+            .changes
+            .iter()
+            .map(|c| (c.source.as_str(), c.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("fresh.service", TrendKind::New),
+                ("web.service", TrendKind::Increased),
+                ("db.service", TrendKind::Decreased),
+                ("old.service", TrendKind::Resolved),
+            ]
+        );
+        assert_eq!(diff.unchanged, 1);
+
+        let mut out = Vec::new();
+        write_diff(&mut out, &diff).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert!(text.starts_with(
+            "旧快照：before.json，共 19 条、4 个来源\n新快照：after.json，共 49 条、4 个来源\n"
+        ));
+        assert!(text.contains("  新出现  [服务单元] fresh.service  0 → 4（+4）"));
+        assert!(text.contains("  已消失  [服务单元] old.service  7 → 0（-7）"));
+        assert!(text.ends_with("另有 1 个来源事件数不变\n"));
+    }
+}
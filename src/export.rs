@@ -0,0 +1,116 @@
+// 导出格式 — `--format ndjson` / `--format logfmt`
+//
+// 每个可疑来源一行，字段与 JSON 报告中的 SourceStats 对应，便于 jq、Loki、Splunk 等直接摄取；
+// 流模式 logfmt 行也在这里生成。由 `exporters` 特性控制。
+
+use crate::{
+    AnalyzeResponse, JournalEvent, SourceStats, priority_keyword, quote_kv_value, source_kind_slug,
+    suspect_id, timeutil, write_json_line,
+};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// NDJSON 中的单个可疑来源记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuspectRecord {
+    pub id: String,
+    pub rank: usize,
+    #[serde(flatten)]
+    pub stats: SourceStats,
+}
+
+/// 按排名输出前 top 个可疑来源，每行一个 JSON 对象
+pub fn write_ndjson_suspects<W: Write>(
+    out: &mut W,
+    response: &AnalyzeResponse,
+) -> Result<(), String> {
+    for (index, suspect) in response.suspects.iter().take(response.top).enumerate() {
+        let record = SuspectRecord {
+            id: suspect_id(suspect.kind, &suspect.source),
+            rank: index + 1,
+            stats: suspect.clone(),
+        };
+        write_json_line(out, &record, "可疑来源记录")?;
+    }
+    Ok(())
+}
+
+/// --format logfmt：每个可疑来源一行 key=value，字段与 NDJSON 记录对应
+pub fn write_logfmt_suspects<W: Write>(
+    out: &mut W,
+    response: &AnalyzeResponse,
+) -> Result<(), String> {
+    for (index, suspect) in response.suspects.iter().take(response.top).enumerate() {
+        let mut pairs = vec![
+            ("rank", (index + 1).to_string()),
+            ("id", suspect_id(suspect.kind, &suspect.source)),
+            ("kind", source_kind_slug(suspect.kind).to_string()),
+            ("source", suspect.source.clone()),
+            ("count", suspect.count.to_string()),
+            ("worst_priority", suspect.worst_priority.to_string()),
+            (
+                "level",
+                priority_keyword(suspect.worst_priority).to_string(),
+            ),
+        ];
+        let optional = [
+            ("package", suspect.package.as_ref()),
+            ("unit", suspect.sample_unit.as_ref()),
+            ("exe", suspect.sample_exe.as_ref()),
+        ];
+        pairs.extend(
+            optional
+                .into_iter()
+                .filter_map(|(key, value)| value.map(|v| (key, v.clone()))),
+        );
+        if let Some(version) = &suspect.package_version {
+            pairs.push(("package_version", version.version.clone()));
+            if let Some(origin) = &version.origin {
+                pairs.push(("package_origin", origin.clone()));
+            }
+        }
+        if suspect.maintenance_count > 0 {
+            pairs.push(("maintenance", suspect.maintenance_count.to_string()));
+        }
+        if let Some(note) = &suspect.note {
+            pairs.push(("note", note.text.clone()));
+        }
+        pairs.push(("message", suspect.sample_message.clone()));
+        writeln!(out, "{}", format_logfmt_pairs(&pairs))
+            .map_err(|e| format!("写入 logfmt 记录失败：{e}"))?;
+    }
+    Ok(())
+}
+
+/// 流模式 logfmt 行：`ts=... priority=3 level=err unit=... message="..."`
+pub fn format_logfmt_event(event: &JournalEvent) -> String {
+    let mut pairs = Vec::new();
+    if let Some(ts) = event.timestamp_us {
+        pairs.push(("ts", timeutil::format_utc_rfc3339(ts)));
+    }
+    if let Some(p) = event.priority {
+        pairs.push(("priority", p.to_string()));
+        pairs.push(("level", priority_keyword(p).to_string()));
+    }
+    let optional = [
+        ("unit", &event.unit),
+        ("identifier", &event.identifier),
+        ("comm", &event.comm),
+        ("exe", &event.exe),
+    ];
+    pairs.extend(
+        optional
+            .into_iter()
+            .filter_map(|(key, value)| value.as_ref().map(|v| (key, v.clone()))),
+    );
+    pairs.push(("message", event.message.clone()));
+    format_logfmt_pairs(&pairs)
+}
+
+fn format_logfmt_pairs(pairs: &[(&str, String)]) -> String {
+    pairs
+        .iter()
+        .map(|(key, value)| format!("{key}={}", quote_kv_value(value)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
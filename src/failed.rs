@@ -0,0 +1,129 @@
+// 失败单元 — `--with-failed-units` 对照 `systemctl list-units --state=failed`：标出所属单元当前处于
+// failed 状态的来源，并列出当前失败、但窗口内没有任何匹配日志的单元（如启动即退出、只写 stdout 的服务）
+//
+// 查看的是本机此刻的状态，与分析窗口无关；读取 --directory / --file 等离线 journal 时没有意义，
+// 解析参数时拒绝。systemctl 不可用或调用失败时不标注。
+
+use crate::SourceStats;
+use crate::restarts::suspect_unit;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// 报告中最多列出的“没有日志的失败单元”
+pub const MAX_QUIET_UNITS: usize = 20;
+
+/// `foo.service loaded failed failed Foo daemon`（--plain --no-legend）每行第一列；旧版本不认 --plain 时行首带 `●`
+pub fn parse_failed_units(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            line.trim_start()
+                .trim_start_matches('●')
+                .split_whitespace()
+                .next()
+        })
+        .filter(|unit| unit.contains('.'))
+        .map(str::to_string)
+        .collect()
+}
+
+fn list_failed_units() -> Option<Vec<String>> {
+    let output = Command::new("systemctl")
+        .arg("list-units")
+        .arg("--state=failed")
+        .arg("--all")
+        .arg("--plain")
+        .arg("--no-legend")
+        .arg("--no-pager")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())?;
+    Some(parse_failed_units(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// 标注 unit_failed，返回没有出现在任何来源中的失败单元
+pub fn annotate_failed_units(suspects: &mut [SourceStats]) -> Vec<String> {
+    let Some(failed) = list_failed_units() else {
+        return Vec::new();
+    };
+    merge_failed_units(suspects, failed)
+}
+
+pub fn merge_failed_units(suspects: &mut [SourceStats], failed: Vec<String>) -> Vec<String> {
+    let failed_set = failed.iter().map(String::as_str).collect::<HashSet<_>>();
+    let mut seen = HashSet::new();
+    for suspect in suspects.iter_mut() {
+        if let Some(unit) = suspect_unit(suspect)
+            && failed_set.contains(unit)
+        {
+            seen.insert(unit.to_string());
+            suspect.unit_failed = true;
+        }
+    }
+    let mut quiet = failed
+        .into_iter()
+        .filter(|unit| !seen.contains(unit))
+        .collect::<Vec<_>>();
+    quiet.sort();
+    quiet.dedup();
+    quiet.truncate(MAX_QUIET_UNITS);
+    quiet
+}
+
+pub fn write_failed_units_section<W: Write>(out: &mut W, units: &[String]) -> io::Result<()> {
+    writeln!(out, "  以下单元当前处于失败状态，但窗口内没有匹配的日志：")?;
+    for unit in units {
+        writeln!(out, "  {}", unit)?;
+    }
+    writeln!(
+        out,
+        "  修复：运行 systemctl status <单元> 或 journalctl -u <单元> 查看原因"
+    )?;
+    Ok(())
+}
+
+/// 逐句版本（--linear）
+pub fn failed_units_sentence(units: &[String]) -> String {
+    format!(
+        "以下单元当前处于失败状态，但窗口内没有匹配的日志：{}。",
+        units.join("、")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SourceKind;
+    use crate::test_support::sample_suspect;
+
+    #[test]
+    fn marks_failed_suspects_and_lists_quiet_units() {
+        let output = "web.service   loaded failed failed Web frontend\n\
+                      ● backup.timer loaded failed failed Nightly backup\n\
+                      cron.service  loaded failed failed Regular background jobs\n";
+        let failed = parse_failed_units(output);
+        assert_eq!(failed, ["web.service", "backup.timer", "cron.service"]);
+
+        let mut suspects = vec![
+            sample_suspect(SourceKind::Unit, "web.service", 1, 3),
+            SourceStats {
+                sample_unit: Some("cron.service".to_string()),
+                ..sample_suspect(SourceKind::Executable, "/usr/sbin/cron", 1, 3)
+            },
+            sample_suspect(SourceKind::Unit, "db.service", 1, 3),
+        ];
+        let quiet = merge_failed_units(&mut suspects, failed);
+        assert_eq!(
+            suspects.iter().map(|s| s.unit_failed).collect::<Vec<_>>(),
+            [true, true, false]
+        );
+        assert_eq!(quiet, ["backup.timer"]);
+        assert_eq!(
+            failed_units_sentence(&quiet),
+            "以下单元当前处于失败状态，但窗口内没有匹配的日志：backup.timer。"
+        );
+    }
+}
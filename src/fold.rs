@@ -0,0 +1,270 @@
+// 关键词匹配的文本折叠 — --grep / --grep-any / --exclude 的关键词与日志文本按同一规则处理后做子串比较
+//
+// 默认按 Unicode 规则转小写（纯 ASCII 文本原地转换），本地化服务日志中的 É、Ä、Ж 等字母也能按
+// 不区分大小写匹配；--case-sensitive 不转换大小写。--fold-accents 另把带变音符号的拉丁字母还原为
+// 基本字母（é → e、ß → ss、ł → l）并去掉组合变音符号（U+0300–U+036F），便于用 ASCII 关键词搜索。
+
+use crate::Config;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextFold {
+    pub case_sensitive: bool,
+    pub accents: bool,
+}
+
+impl TextFold {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            case_sensitive: config.case_sensitive,
+            accents: config.fold_accents,
+        }
+    }
+
+    pub fn apply(self, mut text: String) -> String {
+        if !self.case_sensitive {
+            if text.is_ascii() {
+                text.make_ascii_lowercase();
+            } else {
+                text = text.to_lowercase();
+            }
+        }
+        if self.accents && !text.is_ascii() {
+            text = strip_accents(&text);
+        }
+        text
+    }
+}
+
+fn strip_accents(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if ('\u{0300}'..='\u{036f}').contains(&ch) {
+            continue;
+        }
+        match LATIN_BASE.binary_search_by_key(&ch, |(accented, _)| *accented) {
+            Ok(index) => out.push_str(LATIN_BASE[index].1),
+            Err(_) => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Latin-1 补充与拉丁扩展 A 中带变音符号的字母（按码位排序）→ 基本字母
+const LATIN_BASE: &[(char, &str)] = &[
+    ('À', "A"),
+    ('Á', "A"),
+    ('Â', "A"),
+    ('Ã', "A"),
+    ('Ä', "A"),
+    ('Å', "A"),
+    ('Æ', "AE"),
+    ('Ç', "C"),
+    ('È', "E"),
+    ('É', "E"),
+    ('Ê', "E"),
+    ('Ë', "E"),
+    ('Ì', "I"),
+    ('Í', "I"),
+    ('Î', "I"),
+    ('Ï', "I"),
+    ('Ð', "D"),
+    ('Ñ', "N"),
+    ('Ò', "O"),
+    ('Ó', "O"),
+    ('Ô', "O"),
+    ('Õ', "O"),
+    ('Ö', "O"),
+    ('Ø', "O"),
+    ('Ù', "U"),
+    ('Ú', "U"),
+    ('Û', "U"),
+    ('Ü', "U"),
+    ('Ý', "Y"),
+    ('Þ', "TH"),
+    ('ß', "ss"),
+    ('à', "a"),
+    ('á', "a"),
+    ('â', "a"),
+    ('ã', "a"),
+    ('ä', "a"),
+    ('å', "a"),
+    ('æ', "ae"),
+    ('ç', "c"),
+    ('è', "e"),
+    ('é', "e"),
+    ('ê', "e"),
+    ('ë', "e"),
+    ('ì', "i"),
+    ('í', "i"),
+    ('î', "i"),
+    ('ï', "i"),
+    ('ð', "d"),
+    ('ñ', "n"),
+    ('ò', "o"),
+    ('ó', "o"),
+    ('ô', "o"),
+    ('õ', "o"),
+    ('ö', "o"),
+    ('ø', "o"),
+    ('ù', "u"),
+    ('ú', "u"),
+    ('û', "u"),
+    ('ü', "u"),
+    ('ý', "y"),
+    ('þ', "th"),
+    ('ÿ', "y"),
+    ('Ā', "A"),
+    ('ā', "a"),
+    ('Ă', "A"),
+    ('ă', "a"),
+    ('Ą', "A"),
+    ('ą', "a"),
+    ('Ć', "C"),
+    ('ć', "c"),
+    ('Ĉ', "C"),
+    ('ĉ', "c"),
+    ('Ċ', "C"),
+    ('ċ', "c"),
+    ('Č', "C"),
+    ('č', "c"),
+    ('Ď', "D"),
+    ('ď', "d"),
+    ('Đ', "D"),
+    ('đ', "d"),
+    ('Ē', "E"),
+    ('ē', "e"),
+    ('Ĕ', "E"),
+    ('ĕ', "e"),
+    ('Ė', "E"),
+    ('ė', "e"),
+    ('Ę', "E"),
+    ('ę', "e"),
+    ('Ě', "E"),
+    ('ě', "e"),
+    ('Ĝ', "G"),
+    ('ĝ', "g"),
+    ('Ğ', "G"),
+    ('ğ', "g"),
+    ('Ġ', "G"),
+    ('ġ', "g"),
+    ('Ģ', "G"),
+    ('ģ', "g"),
+    ('Ĥ', "H"),
+    ('ĥ', "h"),
+    ('Ħ', "H"),
+    ('ħ', "h"),
+    ('Ĩ', "I"),
+    ('ĩ', "i"),
+    ('Ī', "I"),
+    ('ī', "i"),
+    ('Ĭ', "I"),
+    ('ĭ', "i"),
+    ('Į', "I"),
+    ('į', "i"),
+    ('İ', "I"),
+    ('ı', "i"),
+    ('Ĵ', "J"),
+    ('ĵ', "j"),
+    ('Ķ', "K"),
+    ('ķ', "k"),
+    ('ĸ', "k"),
+    ('Ĺ', "L"),
+    ('ĺ', "l"),
+    ('Ļ', "L"),
+    ('ļ', "l"),
+    ('Ľ', "L"),
+    ('ľ', "l"),
+    ('Ł', "L"),
+    ('ł', "l"),
+    ('Ń', "N"),
+    ('ń', "n"),
+    ('Ņ', "N"),
+    ('ņ', "n"),
+    ('Ň', "N"),
+    ('ň', "n"),
+    ('ŉ', "n"),
+    ('Ō', "O"),
+    ('ō', "o"),
+    ('Ŏ', "O"),
+    ('ŏ', "o"),
+    ('Ő', "O"),
+    ('ő', "o"),
+    ('Œ', "OE"),
+    ('œ', "oe"),
+    ('Ŕ', "R"),
+    ('ŕ', "r"),
+    ('Ŗ', "R"),
+    ('ŗ', "r"),
+    ('Ř', "R"),
+    ('ř', "r"),
+    ('Ś', "S"),
+    ('ś', "s"),
+    ('Ŝ', "S"),
+    ('ŝ', "s"),
+    ('Ş', "S"),
+    ('ş', "s"),
+    ('Š', "S"),
+    ('š', "s"),
+    ('Ţ', "T"),
+    ('ţ', "t"),
+    ('Ť', "T"),
+    ('ť', "t"),
+    ('Ŧ', "T"),
+    ('ŧ', "t"),
+    ('Ũ', "U"),
+    ('ũ', "u"),
+    ('Ū', "U"),
+    ('ū', "u"),
+    ('Ŭ', "U"),
+    ('ŭ', "u"),
+    ('Ů', "U"),
+    ('ů', "u"),
+    ('Ű', "U"),
+    ('ű', "u"),
+    ('Ų', "U"),
+    ('ų', "u"),
+    ('Ŵ', "W"),
+    ('ŵ', "w"),
+    ('Ŷ', "Y"),
+    ('ŷ', "y"),
+    ('Ÿ', "Y"),
+    ('Ź', "Z"),
+    ('ź', "z"),
+    ('Ż', "Z"),
+    ('ż', "z"),
+    ('Ž', "Z"),
+    ('ž', "z"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_unicode_case_and_optionally_accents() {
+        let fold = TextFold::default();
+        assert_eq!(
+            fold.apply("ÉCHEC du Service".to_string()),
+            "échec du service"
+        );
+        assert_eq!(fold.apply("ОШИБКА диска".to_string()), "ошибка диска");
+
+        let accents = TextFold {
+            accents: true,
+            ..TextFold::default()
+        };
+        assert_eq!(
+            accents.apply("Échec: Größe łącze".to_string()),
+            "echec: grosse lacze"
+        );
+        // 组合变音符号（e + U+0301）
+        assert_eq!(accents.apply("Cafe\u{301}".to_string()), "cafe");
+
+        let exact = TextFold {
+            case_sensitive: true,
+            accents: true,
+        };
+        assert_eq!(exact.apply("Échec".to_string()), "Echec");
+        assert!(LATIN_BASE.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+}
@@ -0,0 +1,134 @@
+// 显卡驱动故障 — 识别内核记录的 GPU 挂起、复位与驱动报错（i915/xe 的 GPU HANG 与 Resetting chip、
+// amdgpu 的 ring timeout 与 GPU reset、nouveau 的 fault/TRAP、NVIDIA 专有驱动的 Xid），按驱动归为
+// SourceKind::Gpu 来源，报告中单独标出，不再都算在 kernel 名下
+//
+// 来源名为内核模块名（i915、amdgpu、nvidia…），所属包按模块文件反查（见 packages.rs）。
+// 只认带设备前缀（`i915 0000:00:02.0: …`）、`[drm:函数 [驱动]] *ERROR*` 或 `NVRM: Xid` 形式的消息，
+// 驱动加载、显示模式设置等普通信息不算故障。
+
+/// 报告中对驱动风险的说明
+pub const DRIVER_RISK: &str = "显卡驱动报告 GPU 挂起、复位或故障，桌面卡死、黑屏、花屏多与此有关";
+
+/// 识别的显卡驱动（内核模块名）
+const GPU_DRIVERS: &[&str] = &["i915", "xe", "amdgpu", "radeon", "nouveau", "nvidia"];
+
+/// 带设备前缀的消息中表示故障的片段
+const FAULT_PATTERNS: &[&str] = &[
+    "GPU HANG",
+    "Resetting chip",
+    "reset",
+    "timeout",
+    "timed out",
+    "*ERROR*",
+    "page fault",
+    ": fault",
+    "GPU fault",
+    "GPU lockup",
+    "TRAP",
+    "fallen off the bus",
+];
+
+/// 内核消息对应的显卡驱动；不是显卡驱动故障时为 None
+pub fn gpu_driver(message: &str) -> Option<&'static str> {
+    nvidia_xid(message)
+        .or_else(|| drm_error(message))
+        .or_else(|| device_fault(message))
+}
+
+/// `NVRM: Xid (PCI:0000:01:00): 79, pid=1234, GPU has fallen off the bus.`、
+/// `NVRM: GPU at PCI:0000:01:00: GPU-… has fallen off the bus.`
+fn nvidia_xid(message: &str) -> Option<&'static str> {
+    let rest = message.strip_prefix("NVRM: ")?;
+    (rest.starts_with("Xid ") || rest.contains("fallen off the bus")).then_some("nvidia")
+}
+
+/// `[drm:amdgpu_job_timedout [amdgpu]] *ERROR* ring gfx_0.0.0 timeout, …`，前面可能带设备前缀
+fn drm_error(message: &str) -> Option<&'static str> {
+    let rest = &message[message.find("[drm:")? + 5..];
+    let (function, detail) = rest.split_once("]] ")?;
+    let (_, driver) = function.rsplit_once(" [")?;
+    if !detail.starts_with("*ERROR*") {
+        return None;
+    }
+    known_driver(driver)
+}
+
+/// `i915 0000:00:02.0: [drm] GPU HANG: ecode 9:1:85dffffb, in gnome-shell [1534]`、
+/// `amdgpu 0000:03:00.0: amdgpu: GPU reset begin!`、`nouveau 0000:01:00.0: fifo: fault 00 [READ] …`
+fn device_fault(message: &str) -> Option<&'static str> {
+    let (driver, rest) = message.split_once(' ')?;
+    let driver = known_driver(driver)?;
+    let (device, detail) = rest.split_once(": ")?;
+    let pci_address = device.contains(':')
+        && device
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() || c == ':' || c == '.');
+    (pci_address
+        && FAULT_PATTERNS
+            .iter()
+            .any(|pattern| detail.contains(pattern)))
+    .then_some(driver)
+}
+
+fn known_driver(name: &str) -> Option<&'static str> {
+    GPU_DRIVERS.iter().copied().find(|driver| *driver == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_gpu_faults_by_driver() {
+        let cases = [
+            (
+                "i915 0000:00:02.0: [drm] GPU HANG: ecode 9:1:85dffffb, in gnome-shell [1534]",
+                Some("i915"),
+            ),
+            (
+                "i915 0000:00:02.0: [drm] Resetting chip for stopped heartbeat on rcs0",
+                Some("i915"),
+            ),
+            (
+                "i915 0000:00:02.0: [drm] Finished loading DMC firmware i915/adlp_dmc.bin",
+                None,
+            ),
+            (
+                "[drm:amdgpu_job_timedout [amdgpu]] *ERROR* ring gfx_0.0.0 timeout, signaled seq=123, emitted seq=125",
+                Some("amdgpu"),
+            ),
+            (
+                "amdgpu 0000:03:00.0: amdgpu: GPU reset begin!",
+                Some("amdgpu"),
+            ),
+            (
+                "amdgpu 0000:03:00.0: amdgpu: [gfxhub] page fault (src_id:0 ring:24 vmid:3 pasid:32769)",
+                Some("amdgpu"),
+            ),
+            (
+                "amdgpu 0000:03:00.0: amdgpu: SMU is initialized successfully!",
+                None,
+            ),
+            (
+                "nouveau 0000:01:00.0: fifo: fault 00 [READ] at 0000000000000000 engine 00 [GR]",
+                Some("nouveau"),
+            ),
+            (
+                "NVRM: Xid (PCI:0000:01:00): 79, pid=1234, name=Xorg, GPU has fallen off the bus.",
+                Some("nvidia"),
+            ),
+            (
+                "NVRM: loading NVIDIA UNIX x86_64 Kernel Module  550.107.02",
+                None,
+            ),
+            (
+                "[drm] Initialized i915 1.6.0 for 0000:00:02.0 on minor 0",
+                None,
+            ),
+            ("xhci_hcd 0000:00:14.0: timeout waiting for device", None),
+        ];
+        for (message, driver) in cases {
+            assert_eq!(gpu_driver(message), driver, "{message}");
+        }
+    }
+}
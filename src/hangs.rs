@@ -0,0 +1,398 @@
+// 内核挂起 — 从 `INFO: task … blocked for more than N seconds`（hung task）、`BUG: soft lockup`、
+// `hard LOCKUP` 与 RCU 停滞记录中提取被卡住的任务与调用栈开头，按 (类型, 任务) 归并后单独成节，
+// 不再淹没在其他内核消息里
+//
+// 调用栈是紧随其后的若干条内核记录（`Call Trace:`、逐行的 `函数+0x…/0x…`、`</TASK>`），因此另用一次
+// `journalctl _TRANSPORT=kernel --grep` 查询标题行与各帧，按顺序把调用栈归到前面最近的一条标题行。
+// 调度相关的帧（__schedule、schedule_timeout 等）对所有阻塞任务都一样，跳过；`? ` 开头的不可靠帧也跳过。
+// journalctl 不支持 --grep 或查询失败时不列出。
+
+use crate::redact::Redactor;
+use crate::timeutil::format_local_minute;
+use crate::{Config, add_window_args, parse_json_event};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// 报告中最多列出的挂起类别，按次数取最多的
+pub const MAX_HANGS: usize = 10;
+/// 每类保留的调用栈帧数
+pub const MAX_TRACE_FRAMES: usize = 3;
+const HANG_PATTERN: &str = r"blocked for more than|soft lockup|hard LOCKUP|detected stalls? on|Call Trace|</TASK>|RIP: |\+0x[0-9a-f]+/0x";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HangKind {
+    /// 任务处于不可中断睡眠（D 状态）超过 hung_task_timeout_secs
+    HungTask,
+    /// CPU 在内核态长时间不调度（watchdog 软锁死）
+    SoftLockup,
+    /// CPU 长时间不响应中断（NMI watchdog 硬锁死）
+    HardLockup,
+    /// RCU 宽限期迟迟不能结束
+    RcuStall,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hang {
+    pub kind: HangKind,
+    /// 被卡住的任务（comm）；硬锁死为 `CPU n`，RCU 停滞为 rcu_sched / rcu_preempt
+    pub task: String,
+    pub count: u64,
+    /// 记录中最长的阻塞或卡住秒数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_secs: Option<u64>,
+    /// 最近一次的进程号与时间（UTC 微秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_pid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_us: Option<u64>,
+    /// 最近一次记录的调用栈开头，最多 MAX_TRACE_FRAMES 个函数
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trace: Vec<String>,
+}
+
+impl Hang {
+    /// --redact：任务名取自原始消息，同样遮盖
+    pub fn redact(&mut self, redactor: &Redactor) {
+        self.task = redactor.redact(&self.task);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HangEvent {
+    pub kind: HangKind,
+    pub task: String,
+    pub pid: Option<u32>,
+    pub secs: Option<u64>,
+    pub trace: Vec<String>,
+}
+
+/// `kworker/u8:2:123` → (`kworker/u8:2`, 123)：任务名本身可能含冒号，取最后一个
+fn split_task_pid(text: &str) -> Option<(String, Option<u32>)> {
+    let (task, pid) = text.rsplit_once(':')?;
+    (!task.is_empty()).then(|| (task.to_string(), pid.parse().ok()))
+}
+
+/// 解析挂起记录的标题行：
+/// `INFO: task jbd2/sda1-8:312 blocked for more than 120 seconds.`
+/// `watchdog: BUG: soft lockup - CPU#3 stuck for 23s! [kworker/3:1:1234]`
+/// `watchdog: Watchdog detected hard LOCKUP on cpu 2`
+/// `rcu: INFO: rcu_sched self-detected stall on CPU`
+pub fn parse_hang_header(message: &str) -> Option<HangEvent> {
+    let event = |kind, task: String, pid, secs| HangEvent {
+        kind,
+        task,
+        pid,
+        secs,
+        trace: Vec::new(),
+    };
+    if let Some(at) = message.find(" blocked for more than ") {
+        let head = &message[..at];
+        let task = &head[head.find("task ")? + 5..];
+        let (task, pid) = split_task_pid(task)?;
+        let secs = message[at + 23..]
+            .split_whitespace()
+            .next()
+            .and_then(|n| n.parse().ok());
+        return Some(event(HangKind::HungTask, task, pid, secs));
+    }
+    if let Some(at) = message.find("soft lockup - CPU#") {
+        let rest = &message[at + 18..];
+        let secs = rest
+            .split_once("stuck for ")
+            .and_then(|(_, tail)| tail.split_once('s'))
+            .and_then(|(n, _)| n.parse().ok());
+        let (_, bracket) = rest.split_once('[')?;
+        let (task, pid) = split_task_pid(bracket.trim_end().trim_end_matches(']'))?;
+        return Some(event(HangKind::SoftLockup, task, pid, secs));
+    }
+    if let Some(at) = message.find("hard LOCKUP on cpu ") {
+        let cpu = message[at + 19..].split_whitespace().next()?;
+        return Some(event(
+            HangKind::HardLockup,
+            format!("CPU {cpu}"),
+            None,
+            None,
+        ));
+    }
+    if message.contains("detected stall on") || message.contains("detected stalls on") {
+        let flavor = message
+            .split_whitespace()
+            .find(|word| word.starts_with("rcu_"))
+            .unwrap_or("rcu");
+        return Some(event(HangKind::RcuStall, flavor.to_string(), None, None));
+    }
+    None
+}
+
+/// 调用栈中的一帧：` jbd2_journal_commit_transaction+0x1a2/0x1c50 [jbd2]` → 函数名；`? ` 开头的不可靠帧为 None
+fn parse_frame(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    if line.starts_with("? ") {
+        return None;
+    }
+    let (name, offset) = line.split_once('+')?;
+    let valid = !name.is_empty()
+        && offset.starts_with("0x")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.'));
+    valid.then_some(name)
+}
+
+/// 所有阻塞任务都会经过的调度函数
+fn is_scheduler_frame(name: &str) -> bool {
+    name == "__schedule"
+        || name.starts_with("schedule")
+        || name.starts_with("io_schedule")
+        || name == "preempt_schedule_common"
+}
+
+/// 按时间顺序的内核记录 → 挂起事件，调用栈归到前面最近的一条标题行
+pub fn hang_events(
+    messages: impl IntoIterator<Item = (String, Option<u64>)>,
+) -> Vec<(HangEvent, Option<u64>)> {
+    let mut events: Vec<(HangEvent, Option<u64>)> = Vec::new();
+    // 最近的标题行是否还在等待调用栈，以及是否已进入 Call Trace
+    let mut open = false;
+    let mut in_trace = false;
+    for (message, timestamp_us) in messages {
+        if let Some(event) = parse_hang_header(&message) {
+            events.push((event, timestamp_us));
+            open = true;
+            in_trace = false;
+            continue;
+        }
+        let Some((event, _)) = events.last_mut().filter(|_| open) else {
+            continue;
+        };
+        let trimmed = message.trim();
+        if let Some(rip) = trimmed.split_once("RIP: ").map(|(_, tail)| tail) {
+            // 软锁死时出错指令所在的函数，`RIP: 0010:native_queued_spin_lock_slowpath+0x2d/0x40`
+            let function = rip.split_once(':').map_or(rip, |(_, f)| f);
+            if let Some(name) = parse_frame(function)
+                && event.trace.is_empty()
+            {
+                event.trace.push(name.to_string());
+            }
+        } else if trimmed == "Call Trace:" {
+            in_trace = true;
+        } else if trimmed == "</TASK>" {
+            open = false;
+        } else if in_trace
+            && event.trace.len() < MAX_TRACE_FRAMES
+            && let Some(name) = parse_frame(trimmed)
+            && !is_scheduler_frame(name)
+            && event.trace.last().map(String::as_str) != Some(name)
+        {
+            event.trace.push(name.to_string());
+        }
+    }
+    events
+}
+
+/// 窗口内的内核挂起，按次数降序取前 MAX_HANGS 类；任何一步失败都按“没有挂起”处理
+pub fn collect_hangs(config: &Config) -> Vec<Hang> {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager").arg("--quiet");
+    add_window_args(&mut cmd, config);
+    let Some(output) = cmd
+        .arg("_TRANSPORT=kernel")
+        .arg(format!("--grep={HANG_PATTERN}"))
+        .arg("--output=json")
+        .arg("--output-fields=MESSAGE")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+    else {
+        return Vec::new();
+    };
+    group_hangs(hang_events(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| parse_json_event(line).ok())
+            .map(|event| (event.message, event.timestamp_us)),
+    ))
+}
+
+/// 按 (类型, 任务) 归并，按次数降序、再按最近出现排序；调用栈取最近一次有调用栈的记录
+pub fn group_hangs(events: impl IntoIterator<Item = (HangEvent, Option<u64>)>) -> Vec<Hang> {
+    let mut groups: HashMap<(HangKind, String), Hang> = HashMap::new();
+    for (event, timestamp_us) in events {
+        let hang = groups
+            .entry((event.kind, event.task.clone()))
+            .or_insert_with(|| Hang {
+                kind: event.kind,
+                task: event.task,
+                count: 0,
+                max_secs: None,
+                last_pid: None,
+                last_us: None,
+                trace: Vec::new(),
+            });
+        hang.count += 1;
+        hang.max_secs = hang.max_secs.max(event.secs);
+        if timestamp_us >= hang.last_us {
+            hang.last_us = timestamp_us;
+            hang.last_pid = event.pid.or(hang.last_pid);
+            if !event.trace.is_empty() {
+                hang.trace = event.trace;
+            }
+        } else if hang.trace.is_empty() {
+            hang.trace = event.trace;
+        }
+    }
+    let mut hangs = groups.into_values().collect::<Vec<_>>();
+    hangs.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then(b.last_us.cmp(&a.last_us))
+            .then_with(|| a.task.cmp(&b.task))
+    });
+    hangs.truncate(MAX_HANGS);
+    hangs
+}
+
+/// `任务阻塞超过 120 秒` / `软锁死，卡住 23 秒`
+fn description(hang: &Hang) -> String {
+    match (hang.kind, hang.max_secs) {
+        (HangKind::HungTask, Some(secs)) => format!("任务阻塞（D 状态）超过 {secs} 秒"),
+        (HangKind::HungTask, None) => "任务阻塞（D 状态）".to_string(),
+        (HangKind::SoftLockup, Some(secs)) => format!("软锁死，CPU 卡住 {secs} 秒"),
+        (HangKind::SoftLockup, None) => "软锁死".to_string(),
+        (HangKind::HardLockup, _) => "硬锁死".to_string(),
+        (HangKind::RcuStall, _) => "RCU 停滞".to_string(),
+    }
+}
+
+fn last_seen(hang: &Hang, offset_secs: i64) -> String {
+    let at = hang
+        .last_us
+        .map(|at| format!("最近 {}", format_local_minute(at, offset_secs)));
+    let pid = hang.last_pid.map(|pid| format!("pid {pid}"));
+    [at, pid]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("，")
+}
+
+pub fn write_hangs_section<W: Write>(
+    out: &mut W,
+    hangs: &[Hang],
+    offset_secs: i64,
+) -> io::Result<()> {
+    for hang in hangs {
+        let mut line = format!("  ×{} {}  {}", hang.count, hang.task, description(hang));
+        if !hang.trace.is_empty() {
+            line.push_str(&format!("  调用栈：{}", hang.trace.join(" ← ")));
+        }
+        let seen = last_seen(hang, offset_secs);
+        if !seen.is_empty() {
+            line.push_str(&format!("  {seen}"));
+        }
+        writeln!(out, "{line}")?;
+    }
+    Ok(())
+}
+
+/// 逐句版本（--linear）
+pub fn hang_sentences(hangs: &[Hang], offset_secs: i64) -> Vec<String> {
+    hangs
+        .iter()
+        .map(|hang| {
+            let mut sentence = format!("{} {} 次：{}", hang.task, hang.count, description(hang));
+            if !hang.trace.is_empty() {
+                sentence.push_str(&format!("，卡在 {}", hang.trace.join(" ← ")));
+            }
+            let seen = last_seen(hang, offset_secs);
+            if !seen.is_empty() {
+                sentence.push_str(&format!("，{seen}"));
+            }
+            sentence.push('。');
+            sentence
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hung_tasks_and_lockups_keep_their_backtrace_head() {
+        let base = 1_704_069_000_000_000; // 2024-01-01 00:30 UTC
+        let lines = [
+            "INFO: task jbd2/sda1-8:312 blocked for more than 120 seconds.",
+            "task:jbd2/sda1-8     state:D stack:0     pid:312   ppid:2      flags:0x00004000",
+            "Call Trace:",
+            " <TASK>",
+            " __schedule+0x27c/0x6b0",
+            " schedule+0x33/0x110",
+            " io_schedule+0x46/0x80",
+            " ? __pfx_wbt_inflight_cb+0x10/0x10",
+            " bit_wait_io+0x11/0x70",
+            " __wait_on_bit+0x4a/0x120",
+            " jbd2_journal_commit_transaction+0x1a2/0x1c50 [jbd2]",
+            " kjournald2+0xaf/0x280 [jbd2]",
+            " </TASK>",
+            // 不属于任何挂起记录的调用栈
+            " dump_stack_lvl+0x48/0x70",
+            "INFO: task kworker/u8:2:123 blocked for more than 241 seconds.",
+            "INFO: task jbd2/sda1-8:312 blocked for more than 241 seconds.",
+            "watchdog: BUG: soft lockup - CPU#3 stuck for 23s! [kworker/3:1:1234]",
+            "RIP: 0010:native_queued_spin_lock_slowpath+0x2d/0x40",
+            "Call Trace:",
+            " _raw_spin_lock+0x29/0x30",
+            "rcu: INFO: rcu_sched self-detected stall on CPU",
+        ];
+        let events = hang_events(
+            lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| (line.to_string(), Some(base + i as u64 * 1_000_000))),
+        );
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[1].0.task, "kworker/u8:2");
+        assert_eq!(events[1].0.pid, Some(123));
+        assert_eq!(
+            events[3].0.trace,
+            ["native_queued_spin_lock_slowpath", "_raw_spin_lock"]
+        );
+        assert!(parse_hang_header("EXT4-fs error (device sda1): bad block").is_none());
+        assert_eq!(
+            parse_hang_header("watchdog: Watchdog detected hard LOCKUP on cpu 2")
+                .map(|event| (event.kind, event.task)),
+            Some((HangKind::HardLockup, "CPU 2".to_string()))
+        );
+
+        let hangs = group_hangs(events);
+        assert_eq!(hangs.len(), 4);
+        let jbd2 = &hangs[0];
+        assert_eq!((jbd2.count, jbd2.max_secs), (2, Some(241)));
+        // 第二次记录没有调用栈，保留第一次的
+        assert_eq!(
+            jbd2.trace,
+            [
+                "bit_wait_io",
+                "__wait_on_bit",
+                "jbd2_journal_commit_transaction"
+            ]
+        );
+
+        let mut out = Vec::new();
+        write_hangs_section(&mut out, &hangs[..1], 0).expect("写入应成功");
+        assert_eq!(
+            String::from_utf8(out).expect("UTF-8"),
+            "  ×2 jbd2/sda1-8  任务阻塞（D 状态）超过 241 秒  调用栈：bit_wait_io ← __wait_on_bit ← jbd2_journal_commit_transaction  最近 2024-01-01 00:30，pid 312\n"
+        );
+        assert_eq!(
+            hang_sentences(&hangs, 0)[1],
+            "rcu_sched 1 次：RCU 停滞，最近 2024-01-01 00:30。"
+        );
+    }
+}
@@ -0,0 +1,397 @@
+// 硬件警告 — 从内核记录中识别机器检查异常（MCE）、EDAC 内存错误与 CPU 过热降频，按 (类型, 部件) 归并后
+// 单独成节，并在报告开头给出醒目的提示：这些记录说明硬件（CPU、内存、散热）正在出问题，而不是软件缺陷
+//
+// 不可纠正的内存错误（UE）、致命的机器检查与临界温度标为严重。Intel 平台上可纠正的机器检查通常只记一条
+// `Machine check events logged`，详情在 mcelog / rasdaemon 中，这时部件只能记为未指明的 CPU。
+// 这些记录不少是 warning 级别，另用一次 `journalctl _TRANSPORT=kernel --grep` 查询，不受 --priority 影响。
+
+use crate::timeutil::format_local_minute;
+use crate::{Config, add_window_args, parse_json_event};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// 报告中最多列出的条目，严重的优先，再按次数取最多的
+pub const MAX_HARDWARE_WARNINGS: usize = 10;
+const HARDWARE_PATTERN: &str = r"Machine [Cc]heck|EDAC MC[0-9]+: [0-9]+ [CU]E |temperature above threshold|critical temperature";
+/// 只有 `Machine check events logged` 时的部件名
+const UNSPECIFIED_CPU: &str = "CPU";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HardwareKind {
+    /// CPU 报告的机器检查异常（缓存、总线、内存控制器等）
+    MachineCheck,
+    /// EDAC 报告的内存错误（CE 可纠正、UE 不可纠正）
+    MemoryError,
+    /// 温度超过阈值后 CPU 降频，或达到临界温度
+    Thermal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareWarning {
+    pub kind: HardwareKind,
+    /// `CPU 2 bank 5`、`MC0 CPU_SrcID#0_Ha#0_Chan#1_DIMM#0`、`CPU3`、`thermal_zone0`
+    pub component: String,
+    /// 事件数；EDAC 记录按其中报告的错误数累计
+    pub count: u64,
+    /// 出现过不可纠正的内存错误、致命的机器检查或临界温度
+    pub severe: bool,
+    /// 最近一次的时间（UTC 微秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_us: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HardwareEvent {
+    pub kind: HardwareKind,
+    pub component: String,
+    pub count: u64,
+    pub severe: bool,
+}
+
+/// 窗口内的硬件警告；任何一步失败都按“没有警告”处理
+pub fn collect_hardware_warnings(config: &Config) -> Vec<HardwareWarning> {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager").arg("--quiet");
+    add_window_args(&mut cmd, config);
+    let Some(output) = cmd
+        .arg("_TRANSPORT=kernel")
+        .arg(format!("--grep={HARDWARE_PATTERN}"))
+        .arg("--output=json")
+        .arg("--output-fields=MESSAGE")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+    else {
+        return Vec::new();
+    };
+    group_hardware_warnings(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| parse_json_event(line).ok())
+            .filter_map(|event| Some((parse_hardware_event(&event.message)?, event.timestamp_us))),
+    )
+}
+
+/// 识别一条硬件相关的内核消息；`Core temperature/speed normal` 等恢复记录与 MCE 的寄存器明细为 None
+pub fn parse_hardware_event(message: &str) -> Option<HardwareEvent> {
+    edac_error(message)
+        .or_else(|| thermal_event(message))
+        .or_else(|| machine_check(message))
+}
+
+/// `mce: [Hardware Error]: CPU 2: Machine Check: 0 Bank 5: be00000000800400`、
+/// `mce: [Hardware Error]: Machine check events logged`、`Kernel panic - not syncing: Fatal machine check`
+fn machine_check(message: &str) -> Option<HardwareEvent> {
+    let event = |component: String, severe| HardwareEvent {
+        kind: HardwareKind::MachineCheck,
+        component,
+        count: 1,
+        severe,
+    };
+    if message.contains("Fatal machine check") || message.contains("Fatal Machine check") {
+        return Some(event(UNSPECIFIED_CPU.to_string(), true));
+    }
+    if message.contains("Machine check events logged") {
+        return Some(event(UNSPECIFIED_CPU.to_string(), false));
+    }
+    let rest = &message[message.find("CPU ")? + 4..];
+    let (cpu, rest) = rest.split_once(": Machine Check")?;
+    if cpu.is_empty() || !cpu.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let component = match rest.split_once("Bank ") {
+        Some((_, bank)) => {
+            let bank = bank.split(':').next().unwrap_or_default().trim();
+            format!("CPU {cpu} bank {bank}")
+        }
+        None => format!("CPU {cpu}"),
+    };
+    Some(event(component, false))
+}
+
+/// `EDAC MC0: 1 CE memory read error on CPU_SrcID#0_Ha#0_Chan#1_DIMM#0 (channel:1 slot:0 …)`、
+/// `EDAC MC1: 2 UE … on mc#1csrow#2channel#0 (…)`
+fn edac_error(message: &str) -> Option<HardwareEvent> {
+    let rest = &message[message.find("EDAC MC")? + 5..];
+    let (controller, rest) = rest.split_once(": ")?;
+    let mut words = rest.splitn(3, ' ');
+    let count = words.next()?.parse().ok()?;
+    let severe = match words.next()? {
+        "CE" => false,
+        "UE" => true,
+        _ => return None,
+    };
+    let location = words
+        .next()
+        .and_then(|rest| rest.split_once(" on "))
+        .map(|(_, location)| location.split(" (").next().unwrap_or(location).trim())
+        .filter(|location| !location.is_empty());
+    Some(HardwareEvent {
+        kind: HardwareKind::MemoryError,
+        component: match location {
+            Some(location) => format!("{controller} {location}"),
+            None => controller.to_string(),
+        },
+        count,
+        severe,
+    })
+}
+
+/// `mce: CPU3: Core temperature above threshold, cpu clock throttled (total events = 12)`、
+/// `mce: CPU0: Package temperature above threshold, …`、
+/// `thermal thermal_zone0: critical temperature reached (105 C), shutting down`
+fn thermal_event(message: &str) -> Option<HardwareEvent> {
+    let message = message.strip_prefix("mce: ").unwrap_or(message);
+    if let Some((cpu, detail)) = message.split_once(": ")
+        && cpu.starts_with("CPU")
+        && detail.contains("temperature above threshold")
+    {
+        let component = if detail.starts_with("Package") {
+            format!("{cpu} 封装")
+        } else {
+            cpu.to_string()
+        };
+        return Some(HardwareEvent {
+            kind: HardwareKind::Thermal,
+            component,
+            count: 1,
+            severe: false,
+        });
+    }
+    let (device, detail) = message.split_once(": ")?;
+    if !detail.contains("critical temperature") {
+        return None;
+    }
+    Some(HardwareEvent {
+        kind: HardwareKind::Thermal,
+        component: device.rsplit(' ').next().unwrap_or(device).to_string(),
+        count: 1,
+        severe: true,
+    })
+}
+
+/// 按 (类型, 部件) 归并；严重的排在前面，再按次数降序、最近出现排序
+pub fn group_hardware_warnings(
+    events: impl IntoIterator<Item = (HardwareEvent, Option<u64>)>,
+) -> Vec<HardwareWarning> {
+    let mut groups: HashMap<(HardwareKind, String), HardwareWarning> = HashMap::new();
+    for (event, timestamp_us) in events {
+        let warning = groups
+            .entry((event.kind, event.component.clone()))
+            .or_insert_with(|| HardwareWarning {
+                kind: event.kind,
+                component: event.component,
+                count: 0,
+                severe: false,
+                last_us: None,
+            });
+        warning.count += event.count;
+        warning.severe |= event.severe;
+        warning.last_us = warning.last_us.max(timestamp_us);
+    }
+    let mut warnings = groups.into_values().collect::<Vec<_>>();
+    warnings.sort_by(|a, b| {
+        b.severe
+            .cmp(&a.severe)
+            .then(b.count.cmp(&a.count))
+            .then(b.last_us.cmp(&a.last_us))
+            .then(a.kind.cmp(&b.kind))
+            .then_with(|| a.component.cmp(&b.component))
+    });
+    warnings.truncate(MAX_HARDWARE_WARNINGS);
+    warnings
+}
+
+fn kind_label(kind: HardwareKind) -> &'static str {
+    match kind {
+        HardwareKind::MachineCheck => "机器检查异常",
+        HardwareKind::MemoryError => "内存错误",
+        HardwareKind::Thermal => "过热降频",
+    }
+}
+
+/// `机器检查异常（MCE）` / `内存错误（EDAC，含不可纠正错误）` / `达到临界温度`
+fn description(warning: &HardwareWarning) -> String {
+    match (warning.kind, warning.severe) {
+        (HardwareKind::MachineCheck, false) => "机器检查异常（MCE）".to_string(),
+        (HardwareKind::MachineCheck, true) => "机器检查异常（MCE，含致命错误）".to_string(),
+        (HardwareKind::MemoryError, false) => "可纠正的内存错误（EDAC）".to_string(),
+        (HardwareKind::MemoryError, true) => "内存错误（EDAC，含不可纠正错误）".to_string(),
+        (HardwareKind::Thermal, false) => "过热降频".to_string(),
+        (HardwareKind::Thermal, true) => "达到临界温度".to_string(),
+    }
+}
+
+/// 报告开头的提示；没有硬件警告时为 None
+pub fn hardware_banner(warnings: &[HardwareWarning]) -> Option<String> {
+    if warnings.is_empty() {
+        return None;
+    }
+    let mut totals: Vec<(HardwareKind, u64)> = Vec::new();
+    for warning in warnings {
+        match totals.iter_mut().find(|(kind, _)| *kind == warning.kind) {
+            Some((_, total)) => *total += warning.count,
+            None => totals.push((warning.kind, warning.count)),
+        }
+    }
+    totals.sort();
+    let counts = totals
+        .iter()
+        .map(|(kind, total)| format!("{} {total} 次", kind_label(*kind)))
+        .collect::<Vec<_>>()
+        .join("、");
+    let heading = if warnings.iter().any(|w| w.severe) {
+        "检测到严重硬件故障"
+    } else {
+        "检测到硬件故障迹象"
+    };
+    Some(format!(
+        "{heading}：{counts}；这通常是 CPU、内存或散热出了问题，而不是软件缺陷"
+    ))
+}
+
+fn last_seen(warning: &HardwareWarning, offset_secs: i64) -> Option<String> {
+    warning
+        .last_us
+        .map(|at| format!("最近 {}", format_local_minute(at, offset_secs)))
+}
+
+pub fn write_hardware_section<W: Write>(
+    out: &mut W,
+    warnings: &[HardwareWarning],
+    offset_secs: i64,
+) -> io::Result<()> {
+    for warning in warnings {
+        let mut line = format!(
+            "  ×{} {}  {}",
+            warning.count,
+            warning.component,
+            description(warning)
+        );
+        if warning.severe {
+            line.push_str("  【严重】");
+        }
+        if let Some(seen) = last_seen(warning, offset_secs) {
+            line.push_str(&format!("  {seen}"));
+        }
+        writeln!(out, "{line}")?;
+    }
+    Ok(())
+}
+
+/// 逐句版本（--linear）
+pub fn hardware_sentences(warnings: &[HardwareWarning], offset_secs: i64) -> Vec<String> {
+    warnings
+        .iter()
+        .map(|warning| {
+            let mut sentence = format!(
+                "{} {} 次：{}",
+                warning.component,
+                warning.count,
+                description(warning)
+            );
+            if warning.severe {
+                sentence.push_str("，属于严重故障");
+            }
+            if let Some(seen) = last_seen(warning, offset_secs) {
+                sentence.push_str(&format!("，{seen}"));
+            }
+            sentence.push('。');
+            sentence
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn machine_checks_memory_errors_and_throttling_are_flagged() {
+        let base = 1_704_069_000_000_000; // 2024-01-01 00:30 UTC
+        let lines = [
+            "mce: [Hardware Error]: CPU 2: Machine Check: 0 Bank 5: be00000000800400",
+            "mce: [Hardware Error]: TSC 0 ADDR fef1a140 MISC 38a0000086",
+            "mce: [Hardware Error]: CPU 2: Machine Check: 0 Bank 5: be00000000800400",
+            "mce: [Hardware Error]: Machine check events logged",
+            "EDAC MC0: 1 CE memory read error on CPU_SrcID#0_Ha#0_Chan#1_DIMM#0 (channel:1 slot:0 page:0x12345 offset:0x0 grain:32 syndrome:0x0)",
+            "EDAC MC0: 2 CE memory read error on CPU_SrcID#0_Ha#0_Chan#1_DIMM#0 (channel:1 slot:0 page:0x12346 offset:0x0 grain:32 syndrome:0x0)",
+            "EDAC MC1: 1 UE memory read error on mc#1csrow#2channel#0 (page:0x0 offset:0x0 grain:8)",
+            "EDAC sbridge MC0: HANDLING MCE MEMORY ERROR",
+            "mce: CPU3: Core temperature above threshold, cpu clock throttled (total events = 12)",
+            "mce: CPU3: Core temperature/speed normal",
+            "mce: CPU0: Package temperature above threshold, cpu clock throttled (total events = 1)",
+            "thermal thermal_zone0: critical temperature reached (105 C), shutting down",
+        ];
+        let events = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                Some((
+                    parse_hardware_event(line)?,
+                    Some(base + i as u64 * 1_000_000),
+                ))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 9);
+        assert_eq!(
+            events[0].0,
+            HardwareEvent {
+                kind: HardwareKind::MachineCheck,
+                component: "CPU 2 bank 5".to_string(),
+                count: 1,
+                severe: false,
+            }
+        );
+
+        let warnings = group_hardware_warnings(events);
+        assert_eq!(
+            warnings
+                .iter()
+                .map(|w| (w.kind, w.component.as_str(), w.count, w.severe))
+                .collect::<Vec<_>>(),
+            [
+                (HardwareKind::Thermal, "thermal_zone0", 1, true),
+                (
+                    HardwareKind::MemoryError,
+                    "MC1 mc#1csrow#2channel#0",
+                    1,
+                    true
+                ),
+                (
+                    HardwareKind::MemoryError,
+                    "MC0 CPU_SrcID#0_Ha#0_Chan#1_DIMM#0",
+                    3,
+                    false
+                ),
+                (HardwareKind::MachineCheck, "CPU 2 bank 5", 2, false),
+                (HardwareKind::Thermal, "CPU0 封装", 1, false),
+                (HardwareKind::Thermal, "CPU3", 1, false),
+                (HardwareKind::MachineCheck, "CPU", 1, false),
+            ]
+        );
+        assert_eq!(
+            hardware_banner(&warnings).as_deref(),
+            Some(
+                "检测到严重硬件故障：机器检查异常 3 次、内存错误 4 次、过热降频 3 次；这通常是 CPU、内存或散热出了问题，而不是软件缺陷"
+            )
+        );
+        assert!(hardware_banner(&[]).is_none());
+
+        let mut out = Vec::new();
+        write_hardware_section(&mut out, &warnings[1..2], 0).expect("写入应成功");
+        assert_eq!(
+            String::from_utf8(out).expect("UTF-8"),
+            "  ×1 MC1 mc#1csrow#2channel#0  内存错误（EDAC，含不可纠正错误）  【严重】  最近 2024-01-01 00:30\n"
+        );
+        assert_eq!(
+            hardware_sentences(&warnings, 0)[5],
+            "CPU3 1 次：过热降频，最近 2024-01-01 00:30。"
+        );
+    }
+}
@@ -0,0 +1,255 @@
+// 事件分布 — `--histogram <间隔>` 按 journal 时间戳把匹配事件分桶，为全部事件与排行前列的来源
+// 各画一行火花线，一眼看出错误风暴从何时开始
+//
+// 桶按 UTC 对齐到间隔的整数倍，范围从最早到最晚的事件；桶数超过 MAX_BUCKETS 时把间隔放大为
+// 整数倍，保证一行放得下（报告中显示实际间隔）。没有时间戳的事件不计入分布。
+
+use crate::options::display_width;
+use crate::timeutil::format_local_minute;
+use crate::{SourceKind, SourceStats, truncate_for_display};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+pub const MAX_BUCKETS: u64 = 60;
+/// 火花线的八级方块；空桶显示为空格
+const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const LABEL_CHARS: usize = 24;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Histogram {
+    /// 实际使用的桶宽（秒），可能大于 --histogram 指定值
+    pub interval_secs: u64,
+    /// 第一个桶的起点（UTC 微秒）
+    pub start_us: u64,
+    /// 全部匹配事件在各桶中的数量；各来源的 SourceStats::histogram 与之等长
+    pub counts: Vec<u64>,
+}
+
+/// 分析过程中按桶累计
+#[derive(Debug)]
+pub struct HistogramTracker {
+    interval_us: u64,
+    overall: BTreeMap<u64, u64>,
+    sources: HashMap<(SourceKind, String), BTreeMap<u64, u64>>,
+}
+
+impl HistogramTracker {
+    pub fn new(interval_secs: u64) -> Self {
+        Self {
+            interval_us: interval_secs.max(1) * 1_000_000,
+            overall: BTreeMap::new(),
+            sources: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, kind: SourceKind, source: &str, timestamp_us: u64) {
+        let bucket = timestamp_us / self.interval_us;
+        *self.overall.entry(bucket).or_insert(0) += 1;
+        *self
+            .sources
+            .entry((kind, source.to_string()))
+            .or_default()
+            .entry(bucket)
+            .or_insert(0) += 1;
+    }
+
+    /// 填入前 top 个来源的 histogram，返回整体分布；没有带时间戳的事件时为 None
+    pub fn build(self, suspects: &mut [SourceStats], top: usize) -> Option<Histogram> {
+        let first = *self.overall.keys().next()?;
+        let last = *self.overall.keys().next_back()?;
+        let factor = (last - first) / MAX_BUCKETS + 1;
+        let start = first / factor * factor;
+        let len = ((last / factor - start / factor) + 1) as usize;
+        let spread = |buckets: &BTreeMap<u64, u64>| {
+            let mut counts = vec![0; len];
+            for (&bucket, &count) in buckets {
+                counts[((bucket - start) / factor) as usize] += count;
+            }
+            counts
+        };
+        for suspect in suspects.iter_mut().take(top) {
+            if let Some(buckets) = self.sources.get(&(suspect.kind, suspect.source.clone())) {
+                suspect.histogram = spread(buckets);
+            }
+        }
+        Some(Histogram {
+            interval_secs: self.interval_us * factor / 1_000_000,
+            start_us: start * self.interval_us,
+            counts: spread(&self.overall),
+        })
+    }
+}
+
+impl Histogram {
+    pub fn bucket_start_us(&self, index: usize) -> u64 {
+        self.start_us + index as u64 * self.interval_secs * 1_000_000
+    }
+
+    /// 最多事件的桶：(起点, 数量)，并列时取最早的
+    pub fn peak(&self, counts: &[u64]) -> Option<(u64, u64)> {
+        let (index, &count) = counts
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, count)| **count)?;
+        (count > 0).then(|| (self.bucket_start_us(index), count))
+    }
+}
+
+/// 以整体最大值为满格，各行共用同一刻度，便于上下比较
+pub fn sparkline(counts: &[u64], max: u64) -> String {
+    counts
+        .iter()
+        .map(|&count| {
+            if count == 0 || max == 0 {
+                ' '
+            } else {
+                let level = ((count * LEVELS.len() as u64).div_ceil(max)).max(1) as usize;
+                LEVELS[level.min(LEVELS.len()) - 1]
+            }
+        })
+        .collect()
+}
+
+pub fn write_histogram_section<W: Write>(
+    out: &mut W,
+    histogram: &Histogram,
+    suspects: &[SourceStats],
+    offset_secs: i64,
+) -> io::Result<()> {
+    let end_us = histogram.bucket_start_us(histogram.counts.len());
+    writeln!(
+        out,
+        "  {} 至 {}，每格 {}",
+        format_local_minute(histogram.start_us, offset_secs),
+        format_local_minute(end_us, offset_secs),
+        interval_label(histogram.interval_secs)
+    )?;
+    let max = histogram.counts.iter().copied().max().unwrap_or(0);
+    let rows = std::iter::once(("全部".to_string(), &histogram.counts)).chain(
+        suspects
+            .iter()
+            .filter(|s| !s.histogram.is_empty())
+            .enumerate()
+            .map(|(i, s)| {
+                let label = format!("{}. {}", i + 1, s.source);
+                (truncate_for_display(&label, LABEL_CHARS), &s.histogram)
+            }),
+    );
+    for (label, counts) in rows {
+        let pad = LABEL_CHARS.saturating_sub(display_width(&label));
+        let peak = match histogram.peak(counts) {
+            Some((at, count)) => format!("峰值 {count}（{}）", clock(at, offset_secs)),
+            None => String::new(),
+        };
+        writeln!(
+            out,
+            "  {label}{} │{}│ {peak}",
+            " ".repeat(pad),
+            sparkline(counts, max)
+        )?;
+    }
+    Ok(())
+}
+
+/// 逐句版本（--linear）：火花线对读屏软件没有意义，改为说明开始时间与峰值
+pub fn histogram_sentences(
+    histogram: &Histogram,
+    suspects: &[SourceStats],
+    offset_secs: i64,
+) -> Vec<String> {
+    let end_us = histogram.bucket_start_us(histogram.counts.len());
+    let mut sentences = vec![format!(
+        "事件分布，自 {} 至 {}，每格 {}。",
+        format_local_minute(histogram.start_us, offset_secs),
+        format_local_minute(end_us, offset_secs),
+        interval_label(histogram.interval_secs)
+    )];
+    let rows = std::iter::once(("全部事件".to_string(), &histogram.counts)).chain(
+        suspects
+            .iter()
+            .filter(|s| !s.histogram.is_empty())
+            .map(|s| (s.source.clone(), &s.histogram)),
+    );
+    for (label, counts) in rows {
+        let first = counts.iter().position(|&c| c > 0);
+        if let (Some(first), Some((at, count))) = (first, histogram.peak(counts)) {
+            sentences.push(format!(
+                "{label} 从 {} 开始出现，{} 最多，为 {count} 条。",
+                clock(histogram.bucket_start_us(first), offset_secs),
+                clock(at, offset_secs)
+            ));
+        }
+    }
+    sentences
+}
+
+fn clock(timestamp_us: u64, offset_secs: i64) -> String {
+    format_local_minute(timestamp_us, offset_secs)[11..].to_string()
+}
+
+/// `5 分钟`、`2 小时`、`90 秒`
+fn interval_label(secs: u64) -> String {
+    match secs {
+        s if s % 3600 == 0 => format!("{} 小时", s / 3600),
+        s if s % 60 == 0 => format!("{} 分钟", s / 60),
+        s => format!("{s} 秒"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_suspect;
+
+    #[test]
+    fn buckets_events_and_draws_shared_scale_sparklines() {
+        let minute = 60_000_000;
+        let base = 1_704_069_000_000_000; // 2024-01-01 00:30 UTC
+        let mut tracker = HistogramTracker::new(300);
+        tracker.record(SourceKind::Unit, "db.service", base + minute);
+        for i in 0..8 {
+            tracker.record(SourceKind::Unit, "web.service", base + 10 * minute + i);
+        }
+        tracker.record(SourceKind::Unit, "db.service", base + 16 * minute);
+
+        let mut suspects = vec![
+            sample_suspect(SourceKind::Unit, "web.service", 0, 3),
+            sample_suspect(SourceKind::Unit, "db.service", 0, 3),
+        ];
+        let histogram = tracker.build(&mut suspects, 1).expect("应有分布");
+        assert_eq!(histogram.interval_secs, 300);
+        assert_eq!(histogram.start_us, base);
+        assert_eq!(histogram.counts, [1, 0, 8, 1]);
+        assert_eq!(suspects[0].histogram, [0, 0, 8, 0]);
+        // 只为前 top 个来源填写
+        assert!(suspects[1].histogram.is_empty());
+        assert_eq!(sparkline(&histogram.counts, 8), "▁ █▁");
+        assert_eq!(
+            histogram.peak(&histogram.counts),
+            Some((base + 10 * minute, 8))
+        );
+
+        let mut out = Vec::new();
+        write_histogram_section(&mut out, &histogram, &suspects, 0).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert!(text.contains("2024-01-01 00:30 至 2024-01-01 00:50，每格 5 分钟"));
+        assert!(text.contains("  全部                     │▁ █▁│ 峰值 8（00:40）"));
+        assert!(text.contains("  1. web.service           │  █ │ 峰值 8（00:40）"));
+        let sentences = histogram_sentences(&histogram, &suspects, 0);
+        assert_eq!(
+            sentences[1],
+            "全部事件 从 00:30 开始出现，00:40 最多，为 8 条。"
+        );
+
+        // 跨度过长时放大间隔，桶数不超过 MAX_BUCKETS
+        let mut wide = HistogramTracker::new(60);
+        wide.record(SourceKind::Kernel, "kernel", base);
+        wide.record(SourceKind::Kernel, "kernel", base + 600 * minute);
+        let histogram = wide.build(&mut [], 0).expect("应有分布");
+        assert!(histogram.counts.len() as u64 <= MAX_BUCKETS);
+        assert_eq!(histogram.interval_secs, 11 * 60);
+        assert_eq!(histogram.counts.iter().sum::<u64>(), 2);
+    }
+}
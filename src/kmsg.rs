@@ -0,0 +1,303 @@
+// 内核日志兜底 — 没有 journalctl，或 journalctl 没有任何输出且本机没有 journal 文件（未启用持久化、
+// 精简容器）时，改读内核环形缓冲区：先用 `dmesg --json`，不支持或失败时直接读 /dev/kmsg。
+// 每条记录转成与 `journalctl --output=json` 相同字段的一行，照常走同一套归类、排行流程
+//
+// 内核缓冲区只有本次启动的内核消息，没有单元、进程号等字段：指定了 --unit、--pid 等字段过滤、
+// 其他启动周期或 --directory 等日志来源时不兜底。缓冲区中的时间是启动后的单调时间，按
+// /proc/uptime 推算的启动时刻换算；--since / --until 无法换算（如 `last monday`）时也不兜底。
+
+use crate::timeutil::{local_utc_offset_secs, resolve_time_spec, unix_now_secs};
+use crate::{BootFilter, Config};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+pub const KMSG_PATH: &str = "/dev/kmsg";
+/// journald 的持久化与运行时存储目录
+const JOURNAL_DIRS: &[&str] = &["/var/log/journal", "/run/log/journal"];
+/// Linux 各主流架构上的 O_NONBLOCK；读完缓冲区时 read 返回 EAGAIN 而不是等待新消息
+const O_NONBLOCK: i32 = 0o4000;
+/// 读取期间最旧的记录被新消息覆盖
+const EPIPE: i32 = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FallbackReason {
+    /// 找不到或无法运行 journalctl
+    NoJournalctl,
+    /// journalctl 没有输出，且 /var/log/journal 与 /run/log/journal 中没有 journal 文件
+    NoJournalFiles,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KernelFallback {
+    pub reason: FallbackReason,
+    /// `dmesg --json` 或 /dev/kmsg
+    pub source: String,
+}
+
+/// 当前条件下内核缓冲区能否代替 journal：只有本次启动、没有单元等字段
+pub fn can_fall_back(config: &Config) -> bool {
+    let current_boot = match &config.boot {
+        BootFilter::Disabled | BootFilter::Current => true,
+        BootFilter::Value(value) => value == "0",
+        BootFilter::Several(_) => false,
+    };
+    current_boot
+        && config.namespace.is_none()
+        && config.directory.is_none()
+        && config.files.is_empty()
+        && !config.merge
+        && config.units.is_empty()
+        && config.comms.is_empty()
+        && config.pids.is_empty()
+        && config.uids.is_empty()
+        && config.cgroups.is_empty()
+        && config.containers.is_empty()
+        && config.matches.is_empty()
+        && config.identifiers.iter().all(|id| id == "kernel")
+}
+
+/// 本机的 journal 存储目录中是否有 journal 文件（`<machine-id>/*.journal`、`*.journal~`）
+pub fn journal_files_present() -> bool {
+    JOURNAL_DIRS
+        .iter()
+        .any(|dir| has_journal_file(Path::new(dir), 2))
+}
+
+fn has_journal_file(dir: &Path, depth: u8) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let path = entry.path();
+        if path.is_dir() {
+            return depth > 1 && has_journal_file(&path, depth - 1);
+        }
+        entry.file_name().to_string_lossy().contains(".journal")
+    })
+}
+
+/// 读取内核缓冲区并按级别、时间窗口过滤，返回 journal JSON 行；条件不允许兜底或两种方式都读不到时为 None
+pub fn read_kernel_log(
+    config: &Config,
+    reason: FallbackReason,
+) -> Option<(KernelFallback, String)> {
+    if !can_fall_back(config) {
+        return None;
+    }
+    let now_secs = unix_now_secs();
+    let offset_secs = local_utc_offset_secs();
+    let resolve = |spec: &Option<String>| match spec {
+        Some(spec) => resolve_time_spec(spec, now_secs, offset_secs).map(Some),
+        None => Some(None),
+    };
+    let since = resolve(&config.since)?;
+    let until = resolve(&config.until)?;
+    let boot_us = boot_time_us(now_secs)?;
+
+    let (source, records) = match read_dmesg_json() {
+        Some(records) => ("dmesg --json".to_string(), records),
+        None => (KMSG_PATH.to_string(), read_kmsg()?),
+    };
+    let mut lines = String::new();
+    for record in records {
+        let timestamp_us = boot_us + record.uptime_us;
+        let secs = timestamp_us / 1_000_000;
+        if !config.priority.contains(record.priority)
+            || since.is_some_and(|since| secs < since)
+            || until.is_some_and(|until| secs >= until)
+        {
+            continue;
+        }
+        lines.push_str(&journal_line(&record, timestamp_us));
+        lines.push('\n');
+    }
+    Some((KernelFallback { reason, source }, lines))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernelRecord {
+    pub priority: u8,
+    /// 启动后的微秒数
+    pub uptime_us: u64,
+    pub message: String,
+}
+
+/// 与 journald 记录内核消息时相同的字段
+fn journal_line(record: &KernelRecord, timestamp_us: u64) -> String {
+    serde_json::json!({
+        "MESSAGE": record.message,
+        "PRIORITY": record.priority.to_string(),
+        "SYSLOG_IDENTIFIER": "kernel",
+        "_TRANSPORT": "kernel",
+        "__REALTIME_TIMESTAMP": timestamp_us.to_string(),
+    })
+    .to_string()
+}
+
+/// 启动时刻（UTC 微秒）= 现在 - /proc/uptime
+fn boot_time_us(now_secs: u64) -> Option<u64> {
+    let uptime = fs::read_to_string("/proc/uptime").ok()?;
+    let uptime_us = parse_seconds_us(uptime.split_whitespace().next()?)?;
+    (now_secs * 1_000_000).checked_sub(uptime_us)
+}
+
+/// `12.345678` 秒 → 微秒
+fn parse_seconds_us(value: &str) -> Option<u64> {
+    let (secs, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let fraction = format!("{fraction:0<6}");
+    Some(secs.parse::<u64>().ok()? * 1_000_000 + fraction.get(..6)?.parse::<u64>().ok()?)
+}
+
+fn read_dmesg_json() -> Option<Vec<KernelRecord>> {
+    let output = Command::new("dmesg")
+        .arg("--json")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())?;
+    parse_dmesg_json(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// `{"dmesg":[{"pri":3,"time":12.345678,"msg":"…"}]}`；pri 可能带设施位，只取级别
+pub fn parse_dmesg_json(text: &str) -> Option<Vec<KernelRecord>> {
+    // 缓冲区为空时 dmesg 不输出任何内容
+    if text.trim().is_empty() {
+        return Some(Vec::new());
+    }
+    let value: Value = serde_json::from_str(text).ok()?;
+    let records = value
+        .get("dmesg")?
+        .as_array()?
+        .iter()
+        .filter_map(|record| {
+            let priority = record.get("pri")?.as_u64()? & 7;
+            // 按十进制写法换算，避免 f64 乘法的舍入误差
+            let uptime_us = parse_seconds_us(&record.get("time")?.to_string())?;
+            Some(KernelRecord {
+                priority: priority as u8,
+                uptime_us,
+                message: record.get("msg")?.as_str()?.to_string(),
+            })
+        })
+        .collect();
+    Some(records)
+}
+
+/// 非阻塞读取 /dev/kmsg：每次 read 得到一条记录，读完时返回 EAGAIN
+fn read_kmsg() -> Option<Vec<KernelRecord>> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .custom_flags(O_NONBLOCK)
+        .open(KMSG_PATH)
+        .ok()?;
+    let mut records = Vec::new();
+    let mut buf = vec![0u8; 8192];
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => records.extend(parse_kmsg_record(&String::from_utf8_lossy(&buf[..n]))),
+            Err(err) if err.raw_os_error() == Some(EPIPE) => continue,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+            Err(_) if records.is_empty() => return None,
+            Err(_) => break,
+        }
+    }
+    Some(records)
+}
+
+/// `6,339,5140900,-;NET: Registered protocol family 10`，后面可能跟 ` KEY=value` 续行
+pub fn parse_kmsg_record(record: &str) -> Option<KernelRecord> {
+    let (header, body) = record.split_once(';')?;
+    let mut fields = header.split(',');
+    let priority = fields.next()?.parse::<u32>().ok()? & 7;
+    let _sequence = fields.next()?;
+    let uptime_us = fields.next()?.parse().ok()?;
+    let message = body.lines().next().unwrap_or_default();
+    Some(KernelRecord {
+        priority: priority as u8,
+        uptime_us,
+        message: unescape_kmsg(message),
+    })
+}
+
+/// /dev/kmsg 把不可打印字符写成 `\x0a` 形式
+fn unescape_kmsg(message: &str) -> String {
+    let mut bytes = Vec::with_capacity(message.len());
+    let raw = message.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'\\'
+            && raw.get(i + 1) == Some(&b'x')
+            && let Some(byte) = raw
+                .get(i + 2..i + 4)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            bytes.push(byte);
+            i += 4;
+            continue;
+        }
+        bytes.push(raw[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+pub fn reason_label(reason: FallbackReason) -> &'static str {
+    match reason {
+        FallbackReason::NoJournalctl => "找不到可用的 journalctl",
+        FallbackReason::NoJournalFiles => "本机没有 journal 文件",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json_event;
+
+    #[test]
+    fn kernel_records_become_journal_events() {
+        let records = parse_dmesg_json(
+            r#"{"dmesg":[{"pri":6,"time":0.000000,"msg":"Linux version 6.8.0"},{"pri":11,"time":12.5,"msg":"EXT4-fs error (device sda1): bad block"}]}"#,
+        )
+        .expect("应能解析 dmesg --json");
+        assert_eq!(
+            records[1],
+            KernelRecord {
+                priority: 3,
+                uptime_us: 12_500_000,
+                message: "EXT4-fs error (device sda1): bad block".to_string(),
+            }
+        );
+        assert_eq!(parse_dmesg_json(""), Some(Vec::new()));
+        assert!(parse_dmesg_json("dmesg: unrecognized option").is_none());
+
+        let record = parse_kmsg_record(
+            "3,812,5140900,-;usb 1-1: device descriptor read\\x2c error -71\n SUBSYSTEM=usb\n",
+        )
+        .expect("应能解析 /dev/kmsg 记录");
+        assert_eq!(record.priority, 3);
+        assert_eq!(record.uptime_us, 5_140_900);
+        assert_eq!(record.message, "usb 1-1: device descriptor read, error -71");
+
+        let event = parse_json_event(&journal_line(&record, 1_704_069_000_000_000))
+            .expect("转换结果应是 journal JSON");
+        assert_eq!(event.identifier.as_deref(), Some("kernel"));
+        assert_eq!(event.priority, Some(3));
+        assert_eq!(event.timestamp_us, Some(1_704_069_000_000_000));
+
+        let config = Config {
+            units: vec!["nginx.service".to_string()],
+            ..Config::default()
+        };
+        assert!(can_fall_back(&Config::default()));
+        assert!(!can_fall_back(&config));
+    }
+}
@@ -1,19 +1,131 @@
 // logtool 核心库 — 日志分析引擎
 //
 // 提供 journalctl 日志的解析、归因分析、包反查等功能。
-// 被 daemon 和 CLI 共用。
-
+// 被 daemon 和 CLI 共用；包反查（packages）、规则（rules）与导出格式（exporters）
+// 可通过 Cargo 特性关闭，只保留核心解析/聚合部分。
+
+pub mod alert;
+pub mod audit;
+pub mod baseline;
+pub mod burst;
+pub mod cluster;
+pub mod compare;
+pub mod crash;
+pub mod dependencies;
+pub mod diff;
+#[cfg(feature = "exporters")]
+pub mod export;
+pub mod failed;
+pub mod fold;
+pub mod gpu;
+pub mod hangs;
+pub mod hardware;
+pub mod histogram;
+pub mod kmsg;
+pub mod network;
+pub mod options;
+pub mod owners;
+#[cfg(feature = "packages")]
+pub mod packages;
+pub mod perboot;
+pub mod reboots;
+pub mod redact;
+pub mod reliability;
+pub mod restarts;
+#[cfg(feature = "rules")]
+pub mod rules;
+pub mod rusage;
+pub mod settings;
+pub mod sink;
+pub mod smart;
+pub mod socket;
+pub mod state;
+pub mod storage;
+pub mod syslog;
+#[cfg(feature = "exporters")]
+pub mod template;
+#[cfg(test)]
+mod test_support;
+pub mod timeline;
+pub mod timeutil;
+pub mod trend;
+pub mod users;
+pub mod viewer;
+pub mod wildcard;
+
+#[cfg(feature = "exporters")]
+pub use export::{
+    SuspectRecord, format_logfmt_event, write_logfmt_suspects, write_ndjson_suspects,
+};
+
+use alert::WatchOptions;
+use audit::AuditDenial;
+use baseline::BaselineReport;
+use burst::{Burst, BurstTracker};
+use cluster::{ClusterCounter, MessageCluster};
+use compare::MessageCount;
+use crash::Crash;
+use dependencies::RootCauseHint;
+use fold::TextFold;
+use hangs::Hang;
+use hardware::HardwareWarning;
+use histogram::{Histogram, HistogramTracker};
+use kmsg::{FallbackReason, KernelFallback};
+use network::NetworkIssue;
+use options::{OptionKind, ParseState};
+use owners::PackageView;
+use perboot::{BootBreakdown, BootTracker};
+use reboots::{BootSpan, Reboot};
+use redact::Redactor;
+use reliability::Reliability;
+use restarts::UnitLifecycle;
+use rusage::ResourceUsage;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
+use settings::{MaintenanceMatcher, MaintenanceWindow};
+use smart::SmartHealth;
+use state::Note;
 use std::cmp::Ordering;
-use std::collections::HashMap;
-use std::io::{self, BufRead, BufReader, Write};
-use std::process::{Command, Stdio};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use timeline::{TimelineEntry, TimelineTracker};
+use trend::TrendReport;
+use users::{UserTracker, UserView};
 
 pub const DEFAULT_SINCE: &str = "2 hours ago";
-pub const DEFAULT_PRIORITY: &str = "3";
+pub const DEFAULT_PRIORITY: Priority = Priority::up_to(3);
 pub const DEFAULT_TOP: usize = 10;
+/// 默认位置；/run 不可用时的回退顺序见 socket 模块
 pub const SOCKET_PATH: &str = "/run/logtool.sock";
+/// `logtool last-words` 默认输出的条数
+pub const DEFAULT_LAST_WORDS_LINES: usize = 50;
+/// alert(1) 与 emerg(0)：过滤条件挡掉这些事件时报告顶部给出警告
+pub const EMERGENCY_MAX_PRIORITY: u8 = 1;
+/// --boot-history 回溯检查的最近启动周期数
+pub const RECENT_BOOTS_CHECKED: usize = 5;
+/// `--boot -3..0` 等一次最多选中的启动周期数
+pub const MAX_SELECTED_BOOTS: usize = 20;
+/// --output 流模式文件达到该大小后轮转为 `<path>.1`
+pub const STREAM_OUTPUT_ROTATE_BYTES: u64 = 64 * 1024 * 1024;
+/// 流模式回报错误时附带的 journalctl 标准错误上限
+pub const STREAM_STDERR_TAIL_BYTES: usize = 4096;
+/// 示例消息默认保留的字符数（--max-msg-chars）
+pub const DEFAULT_MESSAGE_CHARS: usize = 180;
+/// 三项截断上限各自允许的最小值：再小就放不下截断标记或不含来源的基本响应
+pub const MIN_MESSAGE_CHARS: usize = 16;
+pub const MIN_LINE_BYTES: usize = 64;
+pub const MIN_RESPONSE_BYTES: usize = 4096;
+/// 报告中每个来源列出的最常见消息条数
+pub const TOP_MESSAGES: usize = 3;
+/// 每个来源最多计数的不同消息数；之后出现的新消息不再计入（来源总数不受影响）
+const MAX_TRACKED_MESSAGES: usize = 64;
+/// 报告中每个来源列出的主机数（多主机日志）
+pub const HOSTS_SHOWN: usize = 3;
 
 // ── 配置与枚举 ─────────────────────────────────────────────
 
@@ -21,23 +133,239 @@ pub const SOCKET_PATH: &str = "/run/logtool.sock";
 pub struct Config {
     pub mode: RunMode,
     pub since: Option<String>,
+    /// 命令行给出了 --since / --last；配置文件的 default_since 只替换未显式指定的内置默认值。
+    /// 仅在 CLI 侧使用，不随请求发送
+    #[serde(skip)]
+    pub since_explicit: bool,
     pub until: Option<String>,
     pub units: Vec<String>,
+    /// --identifier/-t：按 SYSLOG_IDENTIFIER 匹配（journalctl --identifier，可重复，多个值为“或”）
+    #[serde(default)]
+    pub identifiers: Vec<String>,
+    /// --comm：按 _COMM= 匹配（进程名，可重复，多个值为“或”）
+    #[serde(default)]
+    pub comms: Vec<String>,
+    /// --pid：按 _PID= 匹配（可重复，多个值为“或”）
+    #[serde(default)]
+    pub pids: Vec<u32>,
+    /// --user-filter：按 _UID= 匹配（用户名在 CLI 侧解析为 UID，可重复，多个值为“或”）
+    #[serde(default)]
+    pub uids: Vec<u32>,
+    /// --cgroup：按 _SYSTEMD_CGROUP= 匹配（可重复，多个值为“或”）
+    #[serde(default)]
+    pub cgroups: Vec<String>,
+    /// --container：按 CONTAINER_NAME= 匹配（docker/podman 的 journald 日志驱动，可重复，多个值为“或”）
+    #[serde(default)]
+    pub containers: Vec<String>,
+    /// --match：任意 `FIELD=value` 字段匹配，原样追加给 journalctl；单独的 `+` 分隔“或”的条件组
+    #[serde(default)]
+    pub matches: Vec<String>,
+    /// journal 命名空间（--namespace），用于 LogNamespace= 配置的服务
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// --directory：改为读取该目录下的 journal 文件（journalctl -D），绝对路径
+    #[serde(default)]
+    pub directory: Option<String>,
+    /// --file：改为读取这些 journal 文件（journalctl --file，可重复），绝对路径
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// --syslog-file：改为读取这些纯文本 syslog 文件（不经 journalctl，可重复），绝对路径
+    #[serde(default)]
+    pub syslog_files: Vec<String>,
+    /// 发起请求的用户（daemon 在收到请求后填写）；daemon 按其权限检查 syslog 文件，本地运行时为 None。
+    /// 不随请求发送，客户端无法自行声明
+    #[serde(skip)]
+    pub peer: Option<socket::Peer>,
+    /// --merge：合并读取所有可用 journal，包括 systemd-journal-remote 收到的远程日志
+    #[serde(default)]
+    pub merge: bool,
     pub grep_terms: Vec<String>,
+    /// --grep-any：命中其中任一关键词即可，与 grep_terms 同时生效
+    #[serde(default)]
+    pub grep_any: Vec<String>,
+    /// --exclude：含任一关键词的事件被丢弃
+    #[serde(default)]
+    pub exclude_terms: Vec<String>,
+    /// --invert-match：只保留不满足 --grep / --grep-any 的事件（与 grep -v 相同）
+    #[serde(default)]
+    pub invert_match: bool,
+    /// --journal-grep：唯一的 --grep 关键词同时交给 journalctl --grep，在 journald 内先过滤
+    #[serde(default)]
+    pub journal_grep: bool,
+    /// --case-sensitive：关键词按原样区分大小写匹配；否则三组关键词在解析结束时统一转为小写
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// --fold-accents：关键词与日志文本都去掉变音符号后再匹配（见 fold::TextFold）
+    #[serde(default)]
+    pub fold_accents: bool,
+    /// --dedup：与上一条事件来源、内容都相同的事件折叠；流模式输出 `… repeated N times`，
+    /// 分析时不计入匹配条数，单独计入 metrics.duplicates
+    #[serde(default)]
+    pub dedup: bool,
+    /// --exclude-unit：来自这些单元（支持 `*` / `?` 通配）的事件被丢弃
+    #[serde(default)]
+    pub exclude_units: Vec<String>,
     pub boot: BootFilter,
     pub follow: bool,
     pub kernel_only: bool,
     pub output_json: bool,
     pub max_lines: Option<usize>,
-    pub priority: String,
+    pub priority: Priority,
     pub show_command: bool,
     pub top: usize,
+    #[serde(default)]
+    pub output_path: Option<String>,
+    #[serde(default)]
+    pub boot_history: bool,
+    /// --reliability：为前 N 个来源统计近 30 天的无错误天数与平均故障间隔
+    #[serde(default)]
+    pub reliability: bool,
+    #[serde(default)]
+    pub summary_only: bool,
+    /// 来自配置文件的维护窗口，窗口内事件照常计数但单独标记
+    #[serde(default)]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    /// 流模式下额外转发匹配事件（JSON 行）的 Unix Socket 或 FIFO
+    #[serde(default)]
+    pub forward_path: Option<String>,
+    /// --archive：请 daemon 把本次流会话另存到其归档目录（logtool-daemon --archive-dir）
+    #[serde(default)]
+    pub archive: bool,
+    /// --fresh：不使用 daemon 预先计算的结果（logtool-daemon --prefetch）
+    #[serde(default)]
+    pub fresh: bool,
+    #[serde(default)]
+    pub format: ReportFormat,
+    /// 来源的归并维度（--group-by）
+    #[serde(default)]
+    pub group_by: GroupBy,
+    #[serde(default)]
+    pub env_snapshot: bool,
+    /// 自定义报告模板文件（--template），由 CLI 读取渲染
+    #[serde(default)]
+    pub template_path: Option<String>,
+    /// 每个可疑来源保留的不同示例消息条数（--samples），None 时只保留最近一条
+    #[serde(default)]
+    pub samples: Option<usize>,
+    /// 每个可疑来源按消息模板聚类后列出的类别数（--clusters）
+    #[serde(default)]
+    pub clusters: Option<usize>,
+    /// 把前列来源计入统计的原始条目写入该目录（--evidence），由 CLI 写文件
+    #[serde(default)]
+    pub evidence_dir: Option<String>,
+    /// 示例消息保留的最大字符数（--max-msg-chars），None 为 DEFAULT_MESSAGE_CHARS
+    #[serde(default)]
+    pub max_message_chars: Option<usize>,
+    /// 流模式每行输出的最大字节数（--max-line-bytes），超出部分以截断标记代替；None 不限
+    #[serde(default)]
+    pub max_line_bytes: Option<usize>,
+    /// 分析响应 JSON 的最大字节数（--max-resp-bytes），超出时按 fit_response 省略内容；None 不限
+    #[serde(default)]
+    pub max_response_bytes: Option<usize>,
+    /// 输出前遮盖 IP、MAC、主机名与用户名（--redact）
+    #[serde(default)]
+    pub redact: bool,
+    /// 禁止通过分页器显示长报告（--no-pager），由 CLI 处理
+    #[serde(default)]
+    pub no_pager: bool,
+    /// 发现该级别或更严重的事件时 CLI 以退出码 2 结束（--fail-on）
+    #[serde(default)]
+    pub fail_on: Option<u8>,
+    /// 触发 --fail-on 所需的最少事件数（--fail-on-count，默认 1）
+    #[serde(default)]
+    pub fail_on_count: Option<usize>,
+    /// 无框线、无对齐的逐句报告（--linear），适合读屏软件与盲文终端
+    #[serde(default)]
+    pub linear: bool,
+    /// 在报告中按时间交错列出前列来源的首次出现与集中爆发（--timeline）
+    #[serde(default)]
+    pub timeline: bool,
+    /// 按该间隔（秒）统计事件分布并画火花线（--histogram）
+    #[serde(default)]
+    pub histogram: Option<u64>,
+    /// 列出每分钟事件数远超近期基线的突发时段（--bursts）
+    #[serde(default)]
+    pub bursts: bool,
+    /// 再查一次上一个等长窗口，报告前列来源的增减（--compare-previous）
+    #[serde(default)]
+    pub compare_previous: bool,
+    /// 对照 systemctl 当前失败的单元（--with-failed-units）
+    #[serde(default)]
+    pub with_failed_units: bool,
+    /// 对报错的存储设备运行 smartctl -H，报告磁盘健康结论（--smart）
+    #[serde(default)]
+    pub smart: bool,
+    /// 以下各节各需一次额外的 journalctl 查询，按需开启：程序崩溃（--crashes）
+    #[serde(default)]
+    pub crashes: bool,
+    /// 内核挂起（--hangs）
+    #[serde(default)]
+    pub hangs: bool,
+    /// 网络状况（--network）
+    #[serde(default)]
+    pub network: bool,
+    /// 硬件警告（--hardware）
+    #[serde(default)]
+    pub hardware: bool,
+    /// 审计拒绝（--audit）
+    #[serde(default)]
+    pub audit: bool,
+    /// 把同一 dpkg 包的来源合并汇总（--by-package）
+    #[serde(default)]
+    pub by_package: bool,
+    /// 按事件的 _UID 汇总各用户的事件数与主要来源（--by-user）
+    #[serde(default)]
+    pub by_user: bool,
+    /// 按事件的 _BOOT_ID 分列各启动周期的事件数与首要来源（--per-boot）
+    #[serde(default)]
+    pub per_boot: bool,
+    /// 与 `logtool baseline save` 保存的基线对比，标出速率明显高于基线的来源（--against-baseline）
+    #[serde(default)]
+    pub against_baseline: Option<String>,
+    /// --live：流会话中接受 StreamControl 控制消息，不重启 journalctl 即可调整关键词与优先级
+    #[serde(default)]
+    pub live: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RunMode {
     Analyze,
     Stream,
+    /// 查询 daemon 运行统计（logtool stats）
+    Stats,
+    /// 导出 daemon 已学到的单元/可执行文件 → 包映射（logtool map）
+    PackageMap,
+    /// 对比两个单元（logtool compare）
+    Compare,
+}
+
+/// 来源的归并维度（--group-by）：Auto 为默认次序（单元 → 可执行文件 → 标识符 → 进程名），
+/// 其余按指定字段归并，事件缺少该字段时退回默认次序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    #[default]
+    Auto,
+    Unit,
+    Exe,
+    Identifier,
+    /// 按可执行文件或单元反查所属 dpkg 包
+    Package,
+    Comm,
+}
+
+/// 分析结果输出格式（--format）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportFormat {
+    /// 中文装饰报告
+    #[default]
+    Text,
+    /// 完整 AnalyzeResponse 单个 JSON 文档
+    Json,
+    /// 每个可疑来源一行 JSON（NDJSON）
+    Ndjson,
+    /// 每个可疑来源（流模式下每条日志）一行 key=value，便于 Loki 等直接解析
+    Logfmt,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -45,8 +373,13 @@ pub enum BootFilter {
     Disabled,
     Current,
     Value(String),
+    /// `--boot -3..0` / `--boot -2,0,<ID>`：多个启动周期（偏移或启动 ID），
+    /// 查询前由 resolve_boot_selection 换成启动 ID，以 `_BOOT_ID=` 匹配一次读出
+    Several(Vec<String>),
 }
 
+// Action 每次调用只构造一次，Config 变大也无需装箱
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Action {
     Run(Config),
@@ -54,19 +387,94 @@ pub enum Action {
     Version,
     Doctor,
     ListBoots,
+    /// `logtool --force-unlock`：移除状态文件的锁
+    ForceUnlock,
+    /// `logtool rules test [样本文件] [--rules <文件|目录>]`
+    RulesTest {
+        sample: Option<String>,
+        rules_path: Option<String>,
+    },
+    /// `logtool rules lint [--rules <文件|目录>]`
+    RulesLint {
+        rules_path: Option<String>,
+    },
+    /// `logtool stats [--rules]`
+    Stats {
+        rules: bool,
+    },
+    /// `logtool status [--json]`：读取查看 socket 上缓存的健康状况
+    Status {
+        json: bool,
+    },
+    /// `logtool last-words [--boot <偏移|ID>] [--lines N]`
+    LastWords {
+        boot: String,
+        lines: usize,
+    },
+    /// `logtool map [--scan] [--format text|json]`
+    Map {
+        scan: bool,
+        json: bool,
+    },
+    /// `logtool watch [选项]`
+    Watch {
+        config: Config,
+        options: WatchOptions,
+    },
+    /// `logtool diff <旧.json> <新.json> [--format text|json]`
+    Diff {
+        old: String,
+        new: String,
+        json: bool,
+    },
+    /// `logtool baseline save <文件> [选项]`
+    BaselineSave {
+        config: Config,
+        path: String,
+    },
+    /// `logtool ack <id>`：确认 watch 告警
+    Ack {
+        id: String,
+    },
+    /// `logtool note [<id> <文本>|<id> --clear]`
+    Note {
+        id: Option<String>,
+        /// None 表示清除
+        text: Option<String>,
+    },
+    /// `logtool man`：输出 roff 手册页
+    Man,
+    /// `logtool completions <bash|zsh|fish>`
+    Completions {
+        shell: String,
+    },
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// 声明顺序即排序时的次序（同事件数、同级别、同名称时的最终平局裁决）
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
 pub enum SourceKind {
+    #[default]
     Unit,
     Executable,
     Identifier,
     Comm,
     Kernel,
+    /// docker/podman 容器（CONTAINER_NAME，或 cgroup 中的容器 ID）
+    Container,
+    /// 由规则 merge_as 归并的来源
+    Rule,
+    /// 内核记录的磁盘错误，来源为设备名（sda、nvme0n1、ata1）
+    Storage,
+    /// 内核记录的显卡驱动故障，来源为驱动模块名（i915、amdgpu、nvidia）
+    Gpu,
+    /// --group-by package：来源为 dpkg 包名
+    Package,
     Unknown,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct JournalEvent {
     pub message: String,
     pub priority: Option<u8>,
@@ -74,9 +482,53 @@ pub struct JournalEvent {
     pub exe: Option<String>,
     pub comm: Option<String>,
     pub identifier: Option<String>,
+    /// __REALTIME_TIMESTAMP（UTC 微秒）
+    #[serde(default)]
+    pub timestamp_us: Option<u64>,
+    /// _HOSTNAME：汇总多台机器的 journal 时区分来源主机
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// _SYSTEMD_CGROUP
+    #[serde(default)]
+    pub cgroup: Option<String>,
+    /// CONTAINER_NAME：docker/podman 的 journald 日志驱动写入
+    #[serde(default)]
+    pub container: Option<String>,
+    /// _UID：发送进程的用户
+    #[serde(default)]
+    pub uid: Option<u32>,
+    /// _BOOT_ID：事件所在的启动周期
+    #[serde(default)]
+    pub boot_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl JournalEvent {
+    /// 容器名；日志驱动没有写入 CONTAINER_NAME 时取 cgroup 中的容器 ID
+    pub fn container_source(&self) -> Option<String> {
+        self.container
+            .clone()
+            .or_else(|| self.cgroup.as_deref().and_then(container_id_from_cgroup))
+    }
+}
+
+/// systemd scope（`docker-<id>.scope`、`libpod-<id>.scope`）或 cgroupfs 路径（`/docker/<id>`）中的
+/// 容器 ID，取前 12 位（与 docker ps 一致）；conmon 等其他 scope 不算
+pub fn container_id_from_cgroup(cgroup: &str) -> Option<String> {
+    let segments = cgroup.split('/').collect::<Vec<_>>();
+    segments.iter().enumerate().find_map(|(i, segment)| {
+        let id = segment
+            .strip_suffix(".scope")
+            .and_then(|scope| {
+                scope
+                    .strip_prefix("docker-")
+                    .or_else(|| scope.strip_prefix("libpod-"))
+            })
+            .or_else(|| (i > 0 && segments[i - 1] == "docker").then_some(*segment))?;
+        (id.len() == 64 && id.chars().all(|c| c.is_ascii_hexdigit())).then(|| id[..12].to_string())
+    })
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SourceStats {
     pub kind: SourceKind,
     pub source: String,
@@ -86,6 +538,166 @@ pub struct SourceStats {
     pub sample_unit: Option<String>,
     pub sample_exe: Option<String>,
     pub package: Option<String>,
+    /// 前列来源所属 dpkg 包的安装版本与来源仓库
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_version: Option<PackageVersion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot_presence: Option<BootPresence>,
+    /// --reliability 的长期统计
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reliability: Option<Reliability>,
+    /// 落在维护窗口内的事件数（已计入 count）
+    #[serde(default)]
+    pub maintenance_count: u64,
+    /// --samples 保留的不同消息，按严重级别、再按最近出现排序
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub samples: Vec<SampleMessage>,
+    /// --clusters：按消息模板归并后次数最多的几类
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clusters: Vec<MessageCluster>,
+    /// 出现次数最多的不同消息（最多 TOP_MESSAGES 条），按次数降序
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top_messages: Vec<MessageCount>,
+    /// 最早与最晚一条带时间戳事件的时间（UTC 微秒），用于估算事件频率
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_us: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_us: Option<u64>,
+    /// 窗口内有重启时，首末事件跨越的启动周期
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot_span: Option<BootSpan>,
+    /// --histogram：各桶中的事件数，与 AnalyzeResponse::histogram 的桶对齐
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub histogram: Vec<u64>,
+    /// 窗口内 systemd 对该来源所属单元的重启与失败记录；没有记录时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit_events: Option<UnitLifecycle>,
+    /// --with-failed-units：所属单元当前处于 failed 状态
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unit_failed: bool,
+    /// 规则 severity 调整过该来源的事件时记录原始数据；worst_priority 为调整后的值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity_override: Option<SeverityOverride>,
+    /// 已知问题库（带 hint 的规则）给出的说明与建议
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<Hint>,
+    /// 按有效优先级 0-7 统计的事件数（8 个槽位；事件都没有 PRIORITY 时为空）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub priority_counts: Vec<u64>,
+    /// `logtool note` 留下的备注，由 CLI 在渲染前从本地状态填入
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<Note>,
+    /// 各主机上的事件数，按事件数从多到少；整个窗口只有一台主机时为空
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<HostCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageVersion {
+    /// `1:9.6p1-3ubuntu13.5`
+    pub version: String,
+    /// 已安装版本所在的软件源（`noble-security`、`ppa:owner/name`），不来自任何源时为 `local`；
+    /// apt-cache 不可用时为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostCount {
+    pub host: String,
+    pub count: u64,
+}
+
+impl SourceStats {
+    /// `openssh-server 1:9.6p1-3ubuntu13.5（noble-security）`；没有版本信息时只有包名
+    pub fn package_label(&self) -> Option<String> {
+        let package = self.package.as_deref()?;
+        let Some(version) = &self.package_version else {
+            return Some(package.to_string());
+        };
+        Some(match version.origin.as_deref() {
+            Some("local") => format!(
+                "{package} {}（本地安装，不来自任何软件源）",
+                version.version
+            ),
+            Some(origin) => format!("{package} {}（{origin}）", version.version),
+            None => format!("{package} {}", version.version),
+        })
+    }
+
+    /// `3×crit, 40×err, 120×warn`，从高到低只列出出现过的级别
+    pub fn priority_breakdown(&self) -> Option<String> {
+        let parts = self
+            .priority_counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(level, count)| format!("{count}×{}", priority_keyword(level as u8)))
+            .collect::<Vec<_>>();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+
+    /// 按首末事件时间估算的频率：`约 12 条/分钟（3 分 5 秒内 37 条）`；
+    /// 高于每秒 2 条时按秒、低于每分钟 1 条时给出平均间隔。少于 2 条或没有时间戳时为 None
+    pub fn event_rate(&self) -> Option<String> {
+        let (Some(first), Some(last)) = (self.first_us, self.last_us) else {
+            return None;
+        };
+        if self.count < 2 {
+            return None;
+        }
+        let span_us = (last - first).max(1_000_000);
+        let intervals = self.count - 1;
+        let per_minute = intervals * 60_000_000 / span_us;
+        let rate = if per_minute >= 120 {
+            format!("约 {} 条/秒", intervals * 1_000_000 / span_us)
+        } else if per_minute >= 1 {
+            format!("约 {per_minute} 条/分钟")
+        } else {
+            format!(
+                "约每 {} 1 条",
+                timeutil::format_duration_cn(span_us / intervals / 1_000_000)
+            )
+        };
+        Some(format!(
+            "{rate}（{}内 {} 条）",
+            timeutil::format_duration_cn(span_us / 1_000_000),
+            self.count
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeverityOverride {
+    pub rule_id: String,
+    /// 被调整事件的原始最高严重级别（无 PRIORITY 字段时为 None）
+    pub raw_worst_priority: Option<u8>,
+    pub events: u64,
+}
+
+/// 来源的第一条带 hint 的命中规则
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hint {
+    pub rule_id: String,
+    /// 规则的 description：可能的原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<String>,
+    pub suggestion: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SampleMessage {
+    pub message: String,
+    pub priority: Option<u8>,
+    /// 该消息在本次分析中出现的次数
+    pub count: u64,
+}
+
+/// 可疑来源在最近若干次启动周期中的出现情况
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BootPresence {
+    pub seen: usize,
+    pub checked: usize,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -94,6 +706,33 @@ pub struct AnalyzeMetrics {
     pub parsed_ok: usize,
     pub matched: usize,
     pub parse_errors: usize,
+    #[serde(default)]
+    pub in_maintenance: usize,
+    /// 被 --exclude 丢弃的事件数（不计入 matched）
+    #[serde(default)]
+    pub excluded: usize,
+    /// 按有效严重级别统计的事件数，下标即级别 0-7
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub priority_counts: Vec<usize>,
+    /// 同一时间窗口内被当前过滤条件挡掉的紧急级别（0-1）事件数，见 count_emergency_outside_filter
+    #[serde(default)]
+    pub emergency_outside_filter: usize,
+    /// 分析开始前一小时内的匹配事件数（logtool status 显示）
+    #[serde(default)]
+    pub last_hour: usize,
+    /// --dedup 折叠掉的连续重复事件数（不计入 matched）
+    #[serde(default)]
+    pub duplicates: usize,
+}
+
+impl AnalyzeMetrics {
+    /// 严重级别不低于 `level`（数值 ≤ level）的事件数
+    pub fn events_at_or_above(&self, level: u8) -> usize {
+        self.priority_counts
+            .iter()
+            .take(usize::from(level) + 1)
+            .sum()
+    }
 }
 
 /// daemon → CLI 的响应
@@ -102,15 +741,267 @@ pub struct AnalyzeResponse {
     pub metrics: AnalyzeMetrics,
     pub suspects: Vec<SourceStats>,
     pub top: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<EnvironmentSnapshot>,
+    /// 本次分析中各规则（第一条命中）的生效次数
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rule_hits: Vec<RuleHit>,
+    /// 分析的 journal 命名空间（--namespace）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// 读取的 journal 目录（--directory）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    /// 读取的 journal 文件（--file）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+    /// 读取的 syslog 文件（--syslog-file）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub syslog_files: Vec<String>,
+    /// 没有可读的 journal、改读内核缓冲区时的原因与来源
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kernel_fallback: Option<KernelFallback>,
+    /// 窗口内出现的全部主机（_HOSTNAME），不止一台时才填写
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
+    /// 多个 --boot 时实际读取的启动周期（相对偏移，由旧到新）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub boots: Vec<i64>,
+    /// --timeline：按时间排序的首次出现与爆发
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timeline: Vec<TimelineEntry>,
+    /// --histogram：全部匹配事件的时间分布
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub histogram: Option<Histogram>,
+    /// --bursts：事件速率远超基线的时段，按时间排序
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bursts: Vec<Burst>,
+    /// --compare-previous：与上一个等长窗口的对比
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trend: Option<TrendReport>,
+    /// --against-baseline：明显高于基线的来源，由 CLI 读取基线文件后填入
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<BaselineReport>,
+    /// 窗口内发生的重启（未指定 --boot 时检查）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reboots: Vec<Reboot>,
+    /// 内核记录的程序崩溃（segfault 等），按次数降序
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub crashes: Vec<Crash>,
+    /// 内核记录的任务阻塞、软/硬锁死与 RCU 停滞，按次数降序
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hangs: Vec<Hang>,
+    /// NetworkManager / networkd / resolved 记录的断网、DHCP 失败与 DNS 降级，按次数降序
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network: Vec<NetworkIssue>,
+    /// 内核记录的机器检查异常、EDAC 内存错误与过热降频，严重的在前
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hardware: Vec<HardwareWarning>,
+    /// 审计记录中的 AppArmor / SELinux 拒绝与 seccomp 拦截，按次数降序
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audit: Vec<AuditDenial>,
+    /// --with-failed-units：当前失败、但窗口内没有匹配日志的单元
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed_units: Vec<String>,
+    /// 前列来源的单元之间的依赖：所依赖的单元同样在报错时，它更可能是根因
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub root_causes: Vec<RootCauseHint>,
+    /// --smart：前列存储设备的 SMART 健康评估
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub smart: Vec<SmartHealth>,
+    /// --by-package：同一 dpkg 包的来源合并后的汇总
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by_package: Option<PackageView>,
+    /// --by-user：按 _UID 汇总的各用户事件数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by_user: Option<UserView>,
+    /// --per-boot：按 _BOOT_ID 分列的各启动周期事件数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_boot: Option<BootBreakdown>,
+    /// 主 journalctl 子进程的资源用量，只供 daemon 统计，不随响应发送
+    #[serde(skip)]
+    pub journalctl_usage: Option<ResourceUsage>,
+    /// 响应超过 --max-resp-bytes 时省略了哪些内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<ResponseTruncation>,
+    /// 由 daemon 预先计算（logtool-daemon --prefetch）时的计算时间（Unix 秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_at: Option<u64>,
+    /// --evidence：前 top 个来源的原始条目，按排名排列；CLI 写入文件后从报告中去掉
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evidence: Vec<SuspectEvidence>,
+}
+
+/// 某个来源计入统计的全部原始 journal 条目（journalctl JSON 行）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuspectEvidence {
+    pub id: String,
+    pub entries: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseTruncation {
+    pub limit_bytes: usize,
+    /// 各来源的多条示例消息（--samples）已去掉
+    #[serde(default)]
+    pub samples_dropped: bool,
+    /// 各来源的常见消息已去掉
+    #[serde(default)]
+    pub top_messages_dropped: bool,
+    #[serde(default)]
+    pub timeline_dropped: bool,
+    /// --histogram 的事件分布已去掉
+    #[serde(default)]
+    pub histogram_dropped: bool,
+    /// --bursts 的突发时段已去掉
+    #[serde(default)]
+    pub bursts_dropped: bool,
+    /// --evidence 的原始条目已去掉
+    #[serde(default)]
+    pub evidence_dropped: bool,
+    /// --clusters 的消息聚类已去掉
+    #[serde(default)]
+    pub clusters_dropped: bool,
+    /// 从排名末尾省略的来源数
+    #[serde(default)]
+    pub omitted_suspects: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleHit {
+    pub id: String,
+    pub count: u64,
+}
+
+/// daemon 自启动以来的运行统计（logtool stats）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaemonStats {
+    pub started_at: u64,
+    pub requests: u64,
+    pub analyses: u64,
+    /// 当前已加载规则及历史命中过的规则，按命中次数降序
+    #[serde(default)]
+    pub rules: Vec<RuleStats>,
+    /// 分析请求的 CPU / 内存用量；平台不支持 getrusage 时为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceStats>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceStats {
+    /// daemon 进程自启动以来的峰值常驻内存（KiB）
+    pub daemon_max_rss_kib: u64,
+    /// 计入统计的分析次数
+    pub measured: u64,
+    /// 所有分析的 CPU 时间合计（daemon 线程 + journalctl，微秒）
+    pub cpu_us_total: u64,
+    pub cpu_us_max: u64,
+    /// 单次分析中 journalctl 的最高峰值内存（KiB）
+    pub journalctl_max_rss_kib: u64,
+    /// 最耗 CPU 的几次分析，按 CPU 时间降序
+    #[serde(default)]
+    pub heaviest: Vec<AnalysisCost>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalysisCost {
+    pub at: u64,
+    /// daemon 线程与 journalctl 的 CPU 时间之和（微秒）
+    pub cpu_us: u64,
+    pub daemon_cpu_us: u64,
+    pub journalctl_cpu_us: u64,
+    pub journalctl_max_rss_kib: u64,
+    /// 本次分析执行的 journalctl 命令行，用于找出代价高的查询
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleStats {
+    pub id: String,
+    /// 规则所在文件；规则已被删除时为 None
+    pub file: Option<String>,
+    pub hits: u64,
+    /// 有命中的分析次数
+    pub analyses: u64,
+    pub last_hit_at: Option<u64>,
+}
+
+/// 分析时的运行环境快照（--env-snapshot），便于事后或异地复核报告
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvironmentSnapshot {
+    pub captured_at: u64,
+    pub logtool_version: String,
+    pub kernel: Option<String>,
+    pub os_release: Option<String>,
+    pub systemd_version: Option<String>,
+    /// 启用的内置规则（已知问题库）ID
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub presets: Vec<String>,
+    /// 启用的自定义规则（/etc/logtool/rules.d）ID，按加载顺序
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<String>,
 }
 
 /// stream 模式下 daemon → CLI 的逐行消息
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct StreamLine {
     pub line: String,
     pub done: bool,
     #[serde(default)]
     pub error: Option<String>,
+    /// 出错时的错误类别；旧版 daemon 不发送
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<StreamErrorKind>,
+    /// journalctl 的退出码（被信号终止时为空）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// journalctl 标准错误输出的末尾部分
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamErrorKind {
+    /// journalctl 不存在或无法启动
+    JournalctlUnavailable,
+    /// journalctl 中途以非零状态退出（如 --until 格式无效）
+    JournalctlExit,
+    /// 读取 journalctl 输出失败
+    Read,
+    /// 向客户端写出失败（客户端已断开，不再回报）
+    Output,
+}
+
+/// 流模式的失败信息，除 Output 外都会以 StreamLine 错误行发送给客户端
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamFailure {
+    pub kind: StreamErrorKind,
+    pub message: String,
+    pub exit_code: Option<i32>,
+    pub stderr: Option<String>,
+}
+
+impl StreamFailure {
+    fn new(kind: StreamErrorKind, message: String) -> Self {
+        Self {
+            kind,
+            message,
+            exit_code: None,
+            stderr: None,
+        }
+    }
+
+    pub fn to_line(&self) -> StreamLine {
+        StreamLine {
+            done: true,
+            error: Some(self.message.clone()),
+            error_kind: Some(self.kind),
+            exit_code: self.exit_code,
+            stderr: self.stderr.clone(),
+            ..StreamLine::default()
+        }
+    }
 }
 
 /// daemon → CLI 的统一错误响应
@@ -128,18 +1019,79 @@ impl Default for Config {
         Self {
             mode: RunMode::Analyze,
             since: Some(DEFAULT_SINCE.to_string()),
+            since_explicit: false,
             until: None,
             units: Vec::new(),
+            identifiers: Vec::new(),
+            comms: Vec::new(),
+            pids: Vec::new(),
+            uids: Vec::new(),
+            cgroups: Vec::new(),
+            containers: Vec::new(),
+            matches: Vec::new(),
+            namespace: None,
+            directory: None,
+            files: Vec::new(),
+            syslog_files: Vec::new(),
+            peer: None,
+            merge: false,
             grep_terms: Vec::new(),
+            grep_any: Vec::new(),
+            exclude_terms: Vec::new(),
+            invert_match: false,
+            journal_grep: false,
+            case_sensitive: false,
+            fold_accents: false,
+            dedup: false,
+            exclude_units: Vec::new(),
             // 默认跨启动周期查询，避免“异常后重启就看不到”的常见排障盲区。
             boot: BootFilter::Disabled,
             follow: false,
             kernel_only: false,
             output_json: false,
             max_lines: Some(1500),
-            priority: DEFAULT_PRIORITY.to_string(),
+            priority: DEFAULT_PRIORITY,
             show_command: false,
             top: DEFAULT_TOP,
+            output_path: None,
+            boot_history: false,
+            reliability: false,
+            summary_only: false,
+            maintenance_windows: Vec::new(),
+            forward_path: None,
+            archive: false,
+            fresh: false,
+            format: ReportFormat::Text,
+            group_by: GroupBy::Auto,
+            env_snapshot: false,
+            template_path: None,
+            samples: None,
+            clusters: None,
+            evidence_dir: None,
+            max_message_chars: None,
+            max_line_bytes: None,
+            max_response_bytes: None,
+            redact: false,
+            no_pager: false,
+            fail_on: None,
+            fail_on_count: None,
+            linear: false,
+            timeline: false,
+            histogram: None,
+            bursts: false,
+            compare_previous: false,
+            with_failed_units: false,
+            smart: false,
+            crashes: false,
+            hangs: false,
+            network: false,
+            hardware: false,
+            audit: false,
+            by_package: false,
+            by_user: false,
+            per_boot: false,
+            against_baseline: None,
+            live: false,
         }
     }
 }
@@ -147,1158 +1099,6911 @@ impl Default for Config {
 // ── 参数解析 ─────────────────────────────────────────────
 
 pub fn parse_args(args: &[String]) -> Result<Action, String> {
-    let mut config = Config::default();
+    match args.first().map(String::as_str) {
+        Some("rules") => return parse_rules_args(&args[1..]),
+        Some("stats") => return parse_stats_args(&args[1..]),
+        Some("status") => {
+            return match &args[1..] {
+                [] => Ok(Action::Status { json: false }),
+                [flag] if flag == "--json" => Ok(Action::Status { json: true }),
+                _ => Err("用法：logtool status [--json]\n修复：去掉其他参数".to_string()),
+            };
+        }
+        Some("map") => return parse_map_args(&args[1..]),
+        Some("last-words") => return parse_last_words_args(&args[1..]),
+        Some("man") => return standalone_action(args, "man", Action::Man),
+        Some("completions") => return parse_completions_args(&args[1..]),
+        Some("watch") => return parse_watch_args(&args[1..]),
+        Some("compare") => return parse_compare_args(&args[1..]),
+        Some("note") => return parse_note_args(&args[1..]),
+        Some("baseline") => return parse_baseline_args(&args[1..]),
+        Some("diff") => return parse_diff_args(&args[1..]),
+        Some("ack") => {
+            return match &args[1..] {
+                [id] if !id.starts_with('-') => Ok(Action::Ack { id: id.clone() }),
+                _ => Err(
+                    "用法：logtool ack <id>\n修复：id 为告警行中的来源，如 unit:ssh.service"
+                        .to_string(),
+                ),
+            };
+        }
+        _ => {}
+    }
+
+    let state = match parse_options(args)? {
+        ParsedArgs::Action(action) => return Ok(action),
+        ParsedArgs::Options(state) => state,
+    };
+    if let Some(flag) = state.watch_flag {
+        return Err(format!(
+            "{flag} 只能用于 logtool watch\n修复：运行 logtool watch {flag} <值>"
+        ));
+    }
+    finish_config(state).map(Action::Run)
+}
+
+/// 选项解析的结果：立即返回的动作（--help 等），或待校验的配置
+enum ParsedArgs {
+    Action(Action),
+    Options(ParseState),
+}
+
+fn parse_options(args: &[String]) -> Result<ParsedArgs, String> {
+    let mut state = ParseState::default();
     let mut i = 0usize;
-    let mut max_lines_explicit = false;
 
     while i < args.len() {
         let arg = &args[i];
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        let Some(spec) = options::find_option(name) else {
+            return Err(unknown_option_error(arg));
+        };
 
-        match arg.as_str() {
-            "--help" | "-h" | "help" => return Ok(Action::Help),
-            "--version" | "-V" | "-v" | "version" => {
-                return standalone_action(args, arg, Action::Version);
+        match (&spec.kind, inline) {
+            (OptionKind::Immediate(action), None) => return Ok(ParsedArgs::Action(action())),
+            (OptionKind::Standalone(action), None) => {
+                return standalone_action(args, arg, action()).map(ParsedArgs::Action);
             }
-            "--doctor" | "doctor" => return standalone_action(args, arg, Action::Doctor),
-            "--list-boots" | "boots" => {
-                return standalone_action(args, arg, Action::ListBoots);
+            (OptionKind::Flag(apply), None) => apply(&mut state),
+            (OptionKind::FlagUnlessAlone(_, action), None)
+                if args.len() == 1 && spec.is_short(name) =>
+            {
+                return Ok(ParsedArgs::Action(action()));
             }
-            "--analyze" => config.mode = RunMode::Analyze,
-            "--stream" => config.mode = RunMode::Stream,
-            "--all-boots" => config.boot = BootFilter::Disabled,
-            "--follow" | "-f" => config.follow = true,
-            "--kernel" | "-k" => config.kernel_only = true,
-            "--json" => config.output_json = true,
-            "--show-command" => config.show_command = true,
-            "--no-default-since" => config.since = None,
-            "--since" => {
-                let value = get_next_value(args, &mut i, "--since")?;
-                config.since = Some(value);
+            (OptionKind::FlagUnlessAlone(apply, _), None) => apply(&mut state),
+            (OptionKind::Value(apply), Some(value)) => apply(&mut state, value)?,
+            (OptionKind::Value(apply), None) => {
+                let value = get_next_value(args, &mut i, spec.long)?;
+                apply(&mut state, &value)?;
             }
-            "--until" => {
-                let value = get_next_value(args, &mut i, "--until")?;
-                config.until = Some(value);
+            (OptionKind::OptionalValue(apply), Some(value)) => {
+                apply(&mut state, (!value.is_empty()).then_some(value))?;
             }
-            "--unit" | "-u" => {
-                let value = get_next_value(args, &mut i, "--unit")?;
-                config.units.push(value);
-            }
-            "--grep" | "-g" => {
-                let value = get_next_value(args, &mut i, "--grep")?;
-                if !value.is_empty() {
-                    config.grep_terms.push(value.to_ascii_lowercase());
-                }
-            }
-            "--priority" | "-p" => {
-                let value = get_next_value(args, &mut i, "--priority")?;
-                config.priority = normalize_priority(value)?;
-            }
-            "--max-lines" | "-n" => {
-                let value = get_next_value(args, &mut i, "--max-lines")?;
-                config.max_lines = Some(parse_positive_usize(&value, "--max-lines")?);
-                max_lines_explicit = true;
-            }
-            "--top" => {
-                let value = get_next_value(args, &mut i, "--top")?;
-                config.top = parse_positive_usize(&value, "--top")?;
-            }
-            "--boot" | "-b" => {
+            (OptionKind::OptionalValue(apply), None) => {
                 if has_next_boot_value(args, i) {
                     i += 1;
-                    config.boot = BootFilter::Value(args[i].clone());
-                } else {
-                    config.boot = BootFilter::Current;
-                }
-            }
-            _ => {
-                if let Some(value) = arg.strip_prefix("--since=") {
-                    config.since = Some(value.to_string());
-                } else if let Some(value) = arg.strip_prefix("--until=") {
-                    config.until = Some(value.to_string());
-                } else if let Some(value) = arg.strip_prefix("--unit=") {
-                    config.units.push(value.to_string());
-                } else if let Some(value) = arg.strip_prefix("--grep=") {
-                    if !value.is_empty() {
-                        config.grep_terms.push(value.to_ascii_lowercase());
-                    }
-                } else if let Some(value) = arg.strip_prefix("--priority=") {
-                    config.priority = normalize_priority(value.to_string())?;
-                } else if let Some(value) = arg.strip_prefix("--max-lines=") {
-                    config.max_lines = Some(parse_positive_usize(value, "--max-lines")?);
-                    max_lines_explicit = true;
-                } else if let Some(value) = arg.strip_prefix("--top=") {
-                    config.top = parse_positive_usize(value, "--top")?;
-                } else if let Some(value) = arg.strip_prefix("--boot=") {
-                    if value.is_empty() {
-                        config.boot = BootFilter::Current;
-                    } else {
-                        config.boot = BootFilter::Value(value.to_string());
-                    }
+                    apply(&mut state, Some(&args[i]))?;
                 } else {
-                    return Err(format!(
-                        "未知选项：{arg}\n修复：运行 logtool --help 查看可用参数。\n\n{}",
-                        help_text()
-                    ));
+                    apply(&mut state, None)?;
                 }
             }
+            // 开关类选项不接受 `=值`
+            (_, Some(_)) => return Err(unknown_option_error(arg)),
         }
 
         i += 1;
     }
 
+    Ok(ParsedArgs::Options(state))
+}
+
+fn finish_config(state: ParseState) -> Result<Config, String> {
+    let ParseState {
+        mut config,
+        max_lines_explicit,
+        since_explicit,
+        ..
+    } = state;
+
+    // 拷贝来的日志通常早于“2 小时前”，未指定 --since 时分析目录或文件中的全部日志
+    if (config.directory.is_some() || !config.files.is_empty() || !config.syslog_files.is_empty())
+        && !since_explicit
+    {
+        config.since = None;
+    }
+    config.since_explicit = since_explicit;
+    // 轮转文件从旧到新排列，报告中的文件列表即时间先后
+    syslog::sort_rotations(&mut config.syslog_files);
+
     // 流模式跟随输出在未显式指定 --max-lines 时默认不截断。
     if config.mode == RunMode::Stream && config.follow && !max_lines_explicit {
         config.max_lines = None;
     }
 
-    validate_config(&config)?;
-    Ok(Action::Run(config))
-}
-
-fn standalone_action(args: &[String], arg: &str, action: Action) -> Result<Action, String> {
-    if args.len() != 1 {
-        return Err(format!("{arg} 不能与其他参数同时使用"));
+    // --case-sensitive / --fold-accents 可以出现在 --grep 之后，统一在这里折叠
+    let fold = TextFold::from_config(&config);
+    for term in config
+        .grep_terms
+        .iter_mut()
+        .chain(&mut config.grep_any)
+        .chain(&mut config.exclude_terms)
+    {
+        *term = fold.apply(std::mem::take(term));
     }
-    Ok(action)
+
+    validate_config(&config)?;
+    Ok(config)
 }
 
-pub fn validate_config(config: &Config) -> Result<(), String> {
-    if config.follow && config.mode == RunMode::Analyze {
+/// --syslog-file 不经 journalctl：只能用于分析报告，journal 独有的来源与字段过滤不适用
+fn validate_syslog_files(config: &Config) -> Result<(), String> {
+    if config.mode != RunMode::Analyze {
         return Err(
-            "--follow 只能搭配 --stream 使用\n修复：运行 logtool --stream --follow".to_string(),
+            "--syslog-file 只能用于分析报告\n修复：去掉 --stream，要实时跟随请用 tail -F"
+                .to_string(),
         );
     }
-
-    if config.output_json && config.mode == RunMode::Analyze {
-        return Err(
-            "--json 只能搭配 --stream 使用\n修复：运行 logtool --stream --json".to_string(),
-        );
+    let sources = [
+        (config.directory.is_some(), "--directory"),
+        (!config.files.is_empty(), "--file"),
+        (config.namespace.is_some(), "--namespace"),
+        (config.merge, "--merge"),
+        (config.boot != BootFilter::Disabled, "--boot"),
+    ];
+    if let Some((_, flag)) = sources.iter().find(|(given, _)| *given) {
+        return Err(format!(
+            "--syslog-file 不能与 {flag} 同时使用\n修复：只保留其中一种日志来源"
+        ));
     }
-
-    Ok(())
+    let fields = [
+        (!config.units.is_empty(), "--unit"),
+        (!config.comms.is_empty(), "--comm"),
+        (!config.pids.is_empty(), "--pid"),
+        (!config.uids.is_empty(), "--user-filter"),
+        (config.by_user, "--by-user"),
+        (config.per_boot, "--per-boot"),
+        (!config.cgroups.is_empty(), "--cgroup"),
+        (!config.containers.is_empty(), "--container"),
+        (!config.matches.is_empty(), "--match"),
+        (config.with_failed_units, "--with-failed-units"),
+        (config.crashes, "--crashes"),
+        (config.hangs, "--hangs"),
+        (config.network, "--network"),
+        (config.hardware, "--hardware"),
+        (config.audit, "--audit"),
+        (config.boot_history, "--boot-history"),
+        (config.reliability, "--reliability"),
+    ];
+    if let Some((_, flag)) = fields.iter().find(|(given, _)| *given) {
+        return Err(format!(
+            "syslog 文件中没有 {flag} 所需的 journal 字段\n修复：去掉 {flag}，按程序名过滤请用 --identifier"
+        ));
+    }
+    for path in &config.syslog_files {
+        recheck_path("--syslog-file", path, parse_syslog_file)?;
+    }
+    Ok(())
 }
 
-fn get_next_value(args: &[String], index: &mut usize, flag: &str) -> Result<String, String> {
-    if *index + 1 >= args.len() {
+/// daemon 收到的请求不一定来自官方 CLI：路径在 CLI 侧已解析为绝对路径，这里按同一检查再做一次
+fn recheck_path(
+    flag: &str,
+    value: &str,
+    check: fn(&str) -> Result<String, String>,
+) -> Result<(), String> {
+    if !Path::new(value).is_absolute() {
         return Err(format!(
-            "缺少 {flag} 的参数值\n修复：运行 logtool --help 查看参数示例"
+            "{flag} 需要绝对路径：{value}\n修复：使用官方 CLI 发起请求，路径会自动解析为绝对路径"
         ));
     }
-    *index += 1;
-    Ok(args[*index].clone())
+    check(value).map(drop)
 }
 
-fn has_next_boot_value(args: &[String], index: usize) -> bool {
-    if index + 1 >= args.len() {
-        return false;
+/// `logtool watch [选项]`：周期性分析并按去重规则输出告警
+fn parse_watch_args(args: &[String]) -> Result<Action, String> {
+    let state = match parse_options(args)? {
+        ParsedArgs::Action(Action::Help) => return Ok(Action::Help),
+        ParsedArgs::Action(_) => {
+            return Err("watch 只能搭配分析选项使用\n修复：运行 logtool watch --help".to_string());
+        }
+        ParsedArgs::Options(state) => state,
+    };
+    let options = state.watch.clone();
+    let config = finish_config(state)?;
+
+    let unsupported = [
+        (config.mode == RunMode::Stream, "--stream"),
+        (config.summary_only, "--summary-only"),
+        (config.format != ReportFormat::Text, "--format"),
+        (config.template_path.is_some(), "--template"),
+        (config.output_path.is_some(), "--output"),
+        (config.evidence_dir.is_some(), "--evidence"),
+        (config.linear, "--linear"),
+        (config.fail_on.is_some(), "--fail-on"),
+        (config.fail_on_count.is_some(), "--fail-on-count"),
+        (config.against_baseline.is_some(), "--against-baseline"),
+        (!config.syslog_files.is_empty(), "--syslog-file"),
+    ];
+    if let Some((_, flag)) = unsupported.iter().find(|(given, _)| *given) {
+        return Err(format!(
+            "watch 只输出告警，不支持 {flag}\n修复：去掉 {flag}，或用 --on-alert 处理告警"
+        ));
     }
+    Ok(Action::Watch { config, options })
+}
 
-    let next = &args[index + 1];
-    if !next.starts_with('-') {
-        return true;
+fn parse_compare_args(args: &[String]) -> Result<Action, String> {
+    let usage =
+        "修复：运行 logtool compare --unit a.service --unit b.service [--since …] [--format json]";
+    let mut state = match parse_options(args)? {
+        ParsedArgs::Action(Action::Help) => return Ok(Action::Help),
+        ParsedArgs::Action(_) => return Err(format!("compare 只能搭配分析选项使用\n{usage}")),
+        ParsedArgs::Options(state) => state,
+    };
+    if let Some(flag) = state.watch_flag {
+        return Err(format!("{flag} 只能用于 logtool watch"));
     }
-
-    is_boot_offset(next)
+    let config = &state.config;
+    let unsupported = [
+        (config.mode != RunMode::Analyze, "--stream"),
+        (config.summary_only, "--summary-only"),
+        (
+            !matches!(config.format, ReportFormat::Text | ReportFormat::Json),
+            "--format",
+        ),
+        (config.template_path.is_some(), "--template"),
+        (config.output_path.is_some(), "--output"),
+        (config.linear, "--linear"),
+        (config.timeline, "--timeline"),
+        (config.histogram.is_some(), "--histogram"),
+        (config.bursts, "--bursts"),
+        (config.compare_previous, "--compare-previous"),
+        (config.with_failed_units, "--with-failed-units"),
+        (config.smart, "--smart"),
+        (config.crashes, "--crashes"),
+        (config.hangs, "--hangs"),
+        (config.network, "--network"),
+        (config.hardware, "--hardware"),
+        (config.audit, "--audit"),
+        (config.against_baseline.is_some(), "--against-baseline"),
+        (config.group_by != GroupBy::Auto, "--group-by"),
+        (config.by_package, "--by-package"),
+        (config.by_user, "--by-user"),
+        (config.per_boot, "--per-boot"),
+        (!config.syslog_files.is_empty(), "--syslog-file"),
+        (config.samples.is_some(), "--samples"),
+        (config.clusters.is_some(), "--clusters"),
+        (config.evidence_dir.is_some(), "--evidence"),
+        (config.boot_history, "--boot-history"),
+        (config.reliability, "--reliability"),
+        (
+            config.fail_on.is_some() || config.fail_on_count.is_some(),
+            "--fail-on",
+        ),
+    ];
+    if let Some((_, flag)) = unsupported.iter().find(|(given, _)| *given) {
+        return Err(format!("compare 不支持 {flag}\n{usage}"));
+    }
+    state.config.mode = RunMode::Compare;
+    finish_config(state).map(Action::Run)
 }
 
-fn is_boot_offset(value: &str) -> bool {
-    let digits = value.strip_prefix('-').unwrap_or(value);
-    !digits.is_empty() && digits.chars().all(|ch| ch.is_ascii_digit())
-}
+fn parse_rules_args(args: &[String]) -> Result<Action, String> {
+    let usage = "修复：运行 logtool rules test [样本文件] [--rules <文件|目录>] 或 logtool rules lint [--rules <文件|目录>]";
+    let lint = match args.first().map(String::as_str) {
+        Some("test") => false,
+        Some("lint") => true,
+        Some(other) => return Err(format!("未知 rules 子命令：{other}\n{usage}")),
+        None => return Err(format!("缺少 rules 子命令\n{usage}")),
+    };
 
-fn parse_positive_usize(value: &str, flag: &str) -> Result<usize, String> {
-    let parsed = value
-        .parse::<usize>()
-        .map_err(|_| format!("{flag} 需要一个正整数，实际输入：{value}\n修复：示例 {flag} 50"))?;
-    if parsed == 0 {
-        return Err(format!("{flag} 必须大于 0\n修复：示例 {flag} 50"));
+    let mut sample = None;
+    let mut rules_path = None;
+    let mut i = 1usize;
+    while i < args.len() {
+        let arg = &args[i];
+        if arg == "--rules" {
+            rules_path = Some(get_next_value(args, &mut i, "--rules")?);
+        } else if let Some(value) = arg.strip_prefix("--rules=") {
+            rules_path = Some(value.to_string());
+        } else if arg.starts_with('-') || sample.is_some() || lint {
+            return Err(format!("rules 无法识别参数：{arg}\n{usage}"));
+        } else {
+            sample = Some(arg.clone());
+        }
+        i += 1;
     }
-    Ok(parsed)
+
+    if lint {
+        return Ok(Action::RulesLint { rules_path });
+    }
+    Ok(Action::RulesTest { sample, rules_path })
 }
 
-fn normalize_priority(value: String) -> Result<String, String> {
-    let raw = value.trim().to_ascii_lowercase();
-    let normalized = match raw.as_str() {
-        "0" | "emerg" | "emergency" | "panic" => "0",
-        "1" | "alert" => "1",
-        "2" | "crit" | "critical" => "2",
-        "3" | "err" | "error" => "3",
-        "4" | "warning" | "warn" => "4",
-        "5" | "notice" => "5",
-        "6" | "info" | "informational" | "information" => "6",
-        "7" | "debug" => "7",
-        _ => {
-            return Err(format!(
-                "无效优先级：{value}\n修复：使用 0-7 或 err/warning/info/debug（可运行：logtool --help）"
-            ));
+fn parse_stats_args(args: &[String]) -> Result<Action, String> {
+    let mut rules = false;
+    for arg in args {
+        match arg.as_str() {
+            "--rules" => rules = true,
+            _ => {
+                return Err(format!(
+                    "stats 无法识别参数：{arg}\n修复：运行 logtool stats [--rules]"
+                ));
+            }
         }
-    };
-
-    Ok(normalized.to_string())
+    }
+    Ok(Action::Stats { rules })
 }
 
-// ── 日志分析核心 ─────────────────────────────────────────────
-
-pub fn analyze_journal(config: &Config) -> Result<AnalyzeResponse, String> {
-    ensure_journalctl_exists()?;
-
-    let mut cmd = build_journalctl_command_for_analysis(config);
-    if config.show_command {
-        eprintln!("执行命令：{}", render_command(&cmd));
+fn parse_last_words_args(args: &[String]) -> Result<Action, String> {
+    let usage = "修复：运行 logtool last-words [--boot -1] [--lines 50]";
+    let mut boot = "-1".to_string();
+    let mut lines = DEFAULT_LAST_WORDS_LINES;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut take_value = || {
+            inline
+                .clone()
+                .or_else(|| iter.next().cloned())
+                .ok_or_else(|| format!("{flag} 缺少参数\n{usage}"))
+        };
+        match flag {
+            "--boot" | "-b" => {
+                let value = take_value()?;
+                if value.parse::<i64>().is_ok_and(|offset| offset > 0) {
+                    return Err(format!(
+                        "last-words 的 --boot 使用 0 或负偏移（-1 为上一次启动），或启动 ID：{value}\n{usage}"
+                    ));
+                }
+                boot = value;
+            }
+            "--lines" | "-n" => lines = parse_positive_usize(&take_value()?, "--lines")?,
+            _ => return Err(format!("last-words 无法识别参数：{arg}\n{usage}")),
+        }
     }
+    Ok(Action::LastWords { boot, lines })
+}
 
-    let mut child = cmd
-        .stdout(Stdio::piped())
-        .stderr(Stdio::inherit())
-        .spawn()
-        .map_err(|err| format!("启动 journalctl 失败：{err}"))?;
-
-    let stdout = child
-        .stdout
-        .take()
-        .ok_or_else(|| "无法获取 journalctl 标准输出".to_string())?;
-
-    let reader = BufReader::new(stdout);
-    let mut stats: HashMap<(SourceKind, String), SourceStats> = HashMap::new();
-    let mut metrics = AnalyzeMetrics::default();
-
-    let mut loop_error: Option<String> = None;
-    for maybe_line in reader.lines() {
-        let line = match maybe_line {
-            Ok(line) => line,
-            Err(err) => {
-                loop_error = Some(io_error_to_string(err));
-                break;
+fn parse_map_args(args: &[String]) -> Result<Action, String> {
+    let usage = "修复：运行 logtool map [--scan] [--format text|json]";
+    let mut scan = false;
+    let mut json = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let format = match arg.as_str() {
+            "--scan" => {
+                scan = true;
+                continue;
             }
+            "--format" => iter
+                .next()
+                .ok_or_else(|| format!("--format 缺少参数\n{usage}"))?,
+            other => match other.strip_prefix("--format=") {
+                Some(value) => value,
+                None => return Err(format!("map 无法识别参数：{arg}\n{usage}")),
+            },
         };
-        if line.trim().is_empty() {
-            continue;
-        }
+        json = match parse_report_format(format)? {
+            ReportFormat::Text => false,
+            ReportFormat::Json => true,
+            _ => return Err(format!("map 只支持 text 和 json 格式：{format}\n{usage}")),
+        };
+    }
+    Ok(Action::Map { scan, json })
+}
 
-        metrics.lines_read += 1;
-        let event = match parse_json_event(&line) {
-            Ok(event) => {
-                metrics.parsed_ok += 1;
-                event
-            }
-            Err(_) => {
-                metrics.parse_errors += 1;
+fn parse_diff_args(args: &[String]) -> Result<Action, String> {
+    let usage = "修复：运行 logtool diff <旧.json> <新.json> [--format text|json]";
+    let mut files = Vec::new();
+    let mut json = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let format = match arg.as_str() {
+            "--format" => iter
+                .next()
+                .ok_or_else(|| format!("--format 缺少参数\n{usage}"))?,
+            other if other.starts_with('-') => match other.strip_prefix("--format=") {
+                Some(value) => value,
+                None => return Err(format!("diff 无法识别参数：{arg}\n{usage}")),
+            },
+            _ => {
+                files.push(arg.clone());
                 continue;
             }
         };
+        json = match parse_report_format(format)? {
+            ReportFormat::Text => false,
+            ReportFormat::Json => true,
+            _ => return Err(format!("diff 只支持 text 和 json 格式：{format}\n{usage}")),
+        };
+    }
+    match <[String; 2]>::try_from(files) {
+        Ok([old, new]) => Ok(Action::Diff { old, new, json }),
+        Err(files) => Err(format!(
+            "diff 需要两个快照文件，实际给出 {} 个\n{usage}",
+            files.len()
+        )),
+    }
+}
 
-        if !event_matches_terms(&event, &config.grep_terms) {
-            continue;
+fn parse_note_args(args: &[String]) -> Result<Action, String> {
+    let usage = "用法：logtool note <id> <文本> | logtool note <id> --clear | logtool note";
+    match args {
+        [] => Ok(Action::Note {
+            id: None,
+            text: None,
+        }),
+        [id, flag] if flag == "--clear" => Ok(Action::Note {
+            id: Some(id.clone()),
+            text: None,
+        }),
+        [id, rest @ ..] if !id.starts_with('-') && !rest.is_empty() => {
+            let text = rest.join(" ");
+            if text.trim().is_empty() {
+                return Err(format!("备注内容不能为空\n{usage}"));
+            }
+            Ok(Action::Note {
+                id: Some(id.clone()),
+                text: Some(text),
+            })
         }
+        _ => Err(format!(
+            "{usage}\n修复：id 为报告中的来源，如 unit:ssh.service（可用 --format ndjson 查看）"
+        )),
+    }
+}
 
-        metrics.matched += 1;
-        let (kind, source) = classify_source(&event);
-        let key = (kind, source.clone());
+/// `logtool baseline save <文件> [选项]`：按分析选项取各来源的事件数写入基线文件
+fn parse_baseline_args(args: &[String]) -> Result<Action, String> {
+    let usage = "修复：运行 logtool baseline save <文件> [--last 24h] [--priority …]";
+    let path = match args {
+        [command, path, ..] if command == "save" && !path.starts_with('-') => path.clone(),
+        [command, ..] if command == "save" => return Err(format!("缺少基线文件路径\n{usage}")),
+        [command, ..] => return Err(format!("未知 baseline 子命令：{command}\n{usage}")),
+        [] => return Err(format!("缺少 baseline 子命令\n{usage}")),
+    };
+    let state = match parse_options(&args[2..])? {
+        ParsedArgs::Action(Action::Help) => return Ok(Action::Help),
+        ParsedArgs::Action(_) => {
+            return Err(format!("baseline save 只能搭配分析选项使用\n{usage}"));
+        }
+        ParsedArgs::Options(state) => state,
+    };
+    if let Some(flag) = state.watch_flag {
+        return Err(format!("{flag} 只能用于 logtool watch"));
+    }
+    let config = finish_config(state)?;
+    let unsupported = [
+        (config.mode == RunMode::Stream, "--stream"),
+        (config.summary_only, "--summary-only"),
+        (config.format != ReportFormat::Text, "--format"),
+        (config.template_path.is_some(), "--template"),
+        (config.output_path.is_some(), "--output"),
+        (config.evidence_dir.is_some(), "--evidence"),
+        (config.linear, "--linear"),
+        (config.compare_previous, "--compare-previous"),
+        (config.against_baseline.is_some(), "--against-baseline"),
+        (config.boot != BootFilter::Disabled, "--boot"),
+    ];
+    if let Some((_, flag)) = unsupported.iter().find(|(given, _)| *given) {
+        return Err(format!(
+            "baseline save 只写入基线文件，不支持 {flag}\n{usage}"
+        ));
+    }
+    trend::current_window(
+        &config,
+        "baseline save",
+        timeutil::unix_now_secs(),
+        timeutil::local_utc_offset_secs(),
+    )?;
+    Ok(Action::BaselineSave { config, path })
+}
 
-        let entry = stats.entry(key).or_insert_with(|| SourceStats {
-            kind,
-            source,
-            count: 0,
-            worst_priority: 7,
-            sample_message: String::new(),
-            sample_unit: None,
-            sample_exe: None,
-            package: None,
-        });
+fn parse_completions_args(args: &[String]) -> Result<Action, String> {
+    let usage = "修复：运行 logtool completions bash|zsh|fish";
+    match args {
+        [shell] if options::COMPLETION_SHELLS.contains(&shell.as_str()) => {
+            Ok(Action::Completions {
+                shell: shell.clone(),
+            })
+        }
+        [shell] => Err(format!("不支持的 shell：{shell}\n{usage}")),
+        [] => Err(format!("缺少 shell 名称\n{usage}")),
+        [_, extra, ..] => Err(format!("completions 无法识别参数：{extra}\n{usage}")),
+    }
+}
 
-        entry.count += 1;
+fn unknown_option_error(arg: &str) -> String {
+    format!(
+        "未知选项：{arg}\n修复：运行 logtool --help 查看可用参数。\n\n{}",
+        help_text()
+    )
+}
 
-        if let Some(p) = event.priority
-            && p < entry.worst_priority
-        {
-            entry.worst_priority = p;
-        }
+fn standalone_action(args: &[String], arg: &str, action: Action) -> Result<Action, String> {
+    if args.len() != 1 {
+        return Err(format!("{arg} 不能与其他参数同时使用"));
+    }
+    Ok(action)
+}
 
-        if !event.message.is_empty() {
-            entry.sample_message = truncate_for_display(&event.message, 180);
+pub fn validate_config(config: &Config) -> Result<(), String> {
+    if config.mode == RunMode::Compare {
+        let [a, b] = config.units.as_slice() else {
+            return Err(format!(
+                "compare 需要恰好两个 --unit（实际 {} 个）\n修复：运行 logtool compare --unit a.service --unit b.service",
+                config.units.len()
+            ));
+        };
+        if a == b {
+            return Err(format!("compare 的两个单元相同：{a}"));
         }
+    }
 
-        if entry.sample_unit.is_none() {
-            entry.sample_unit = event.unit.clone();
+    if config.matches.iter().any(|m| m == "+") {
+        let dangling = config.matches.first().is_some_and(|m| m == "+")
+            || config.matches.last().is_some_and(|m| m == "+")
+            || config
+                .matches
+                .windows(2)
+                .any(|pair| pair[0] == "+" && pair[1] == "+");
+        if dangling {
+            return Err(
+                "--match + 两侧都需要字段条件\n修复：如 --match _PID=1 --match + --match _COMM=sshd"
+                    .to_string(),
+            );
         }
-
-        if entry.sample_exe.is_none() {
-            entry.sample_exe = event.exe.clone();
+        // `+` 会把前面所有位置参数都并入第一组，--comm 等条件只约束其中一组
+        if !config.comms.is_empty()
+            || !config.pids.is_empty()
+            || !config.uids.is_empty()
+            || !config.cgroups.is_empty()
+            || !config.containers.is_empty()
+            || matches!(config.boot, BootFilter::Several(_))
+        {
+            return Err(
+                "--match + 不能与 --comm / --pid / --user-filter / --cgroup / --container / 多个 --boot 同时使用\n修复：把这些条件也写成 --match，如 --match _PID=1234"
+                    .to_string(),
+            );
         }
+    }
 
-        if reached_limit(metrics.matched, config.max_lines) {
-            break;
-        }
+    if config.directory.is_some() && config.namespace.is_some() {
+        return Err(
+            "--directory 不能与 --namespace 同时使用\n修复：目录中的日志已按所在目录选定，去掉 --namespace"
+                .to_string(),
+        );
     }
 
-    let reached_max_lines = reached_limit(metrics.matched, config.max_lines);
-    if reached_max_lines || loop_error.is_some() {
-        let _ = child.kill();
+    validate_limits(
+        config.max_message_chars,
+        config.max_line_bytes,
+        config.max_response_bytes,
+    )?;
+
+    if !config.files.is_empty() && (config.directory.is_some() || config.namespace.is_some()) {
+        return Err(
+            "--file 不能与 --directory / --namespace 同时使用\n修复：只保留其中一种日志来源"
+                .to_string(),
+        );
     }
 
-    let status = child.wait().map_err(io_error_to_string)?;
-    if let Some(err) = loop_error {
-        return Err(err);
+    if let Some(directory) = &config.directory {
+        recheck_path("--directory", directory, parse_directory)?;
     }
-    if !status.success() && !status_killed_by_limit(metrics.matched, config.max_lines) {
-        return Err(format!("journalctl 退出状态异常：{status}"));
+    for file in &config.files {
+        recheck_path("--file", file, parse_journal_file)?;
     }
 
-    let mut suspects = stats.into_values().collect::<Vec<_>>();
-    suspects.sort_by(compare_suspects);
-
-    resolve_packages_for_top(&mut suspects, config.top);
+    if !config.syslog_files.is_empty() {
+        validate_syslog_files(config)?;
+    }
 
-    Ok(AnalyzeResponse {
-        metrics,
-        suspects,
-        top: config.top,
-    })
-}
+    if config.invert_match && config.grep_terms.is_empty() && config.grep_any.is_empty() {
+        return Err(
+            "--invert-match 需要配合 --grep 或 --grep-any\n修复：如 --grep timeout --invert-match（排除已知问题可直接用 --exclude）"
+                .to_string(),
+        );
+    }
 
-/// 流模式：边读边写，每匹配一行立即通过 writer 发送 JSON StreamLine
-///
-/// 这是真正的流式实现——不缓冲到内存，支持 --follow 实时输出。
-/// writer 通常是 Unix Socket stream 或 stdout。
-pub fn stream_journal_to_writer<W: Write>(config: &Config, mut writer: W) -> Result<(), String> {
-    ensure_journalctl_exists()?;
+    if config.journal_grep
+        && (config.grep_terms.len() != 1
+            || !config.grep_any.is_empty()
+            || config.invert_match
+            || config.fold_accents
+            || config.live)
+    {
+        return Err(
+            "--journal-grep 只支持单个 --grep 关键词，且不能与 --grep-any / --invert-match / --fold-accents / --live 同时使用\n修复：如 --grep timeout --journal-grep，或去掉 --journal-grep 在本地过滤"
+                .to_string(),
+        );
+    }
 
-    let mut cmd = build_journalctl_command_for_stream(config);
-    if config.show_command {
-        eprintln!("执行命令：{}", render_command(&cmd));
+    if config.live && !(config.mode == RunMode::Stream && config.follow) {
+        return Err(
+            "--live 只能用于持续流\n修复：运行 logtool --stream --follow --live".to_string(),
+        );
     }
 
-    let mut child = cmd
-        .stdout(Stdio::piped())
-        .stderr(Stdio::inherit())
-        .spawn()
-        .map_err(|err| format!("启动 journalctl 失败：{err}"))?;
+    if config.follow && config.mode == RunMode::Analyze {
+        return Err(
+            "--follow 只能搭配 --stream 使用\n修复：运行 logtool --stream --follow".to_string(),
+        );
+    }
 
-    let stdout = child
-        .stdout
-        .take()
-        .ok_or_else(|| "无法获取 journalctl 标准输出".to_string())?;
+    if config.output_json && config.mode == RunMode::Analyze {
+        return Err(
+            "--json 只能搭配 --stream 使用\n修复：运行 logtool --stream --json".to_string(),
+        );
+    }
 
-    let reader = BufReader::new(stdout);
-    let mut lines_written = 0usize;
-    let mut stream_error: Option<String> = None;
+    if config.summary_only && config.mode == RunMode::Stream {
+        return Err(
+            "--summary-only 只能用于分析模式\n修复：运行 logtool --summary-only".to_string(),
+        );
+    }
 
-    for maybe_line in reader.lines() {
-        let line = match maybe_line {
-            Ok(line) => line,
-            Err(err) => {
-                stream_error = Some(io_error_to_string(err));
-                break;
-            }
-        };
-        if !matches_filters(&line, &config.grep_terms) {
-            continue;
+    if config.format != ReportFormat::Text {
+        if config.mode == RunMode::Stream && config.format != ReportFormat::Logfmt {
+            return Err(
+                "流模式下 --format 只支持 logfmt\n修复：流模式 JSON 请使用 --json".to_string(),
+            );
         }
-
-        let msg = StreamLine {
-            line,
-            done: false,
-            error: None,
-        };
-        if let Err(err) = write_json_line(&mut writer, &msg, "流消息") {
-            stream_error = Some(err);
-            break;
+        if config.output_json {
+            return Err("--json 不能与 --format 同时使用".to_string());
         }
-
-        lines_written += 1;
-
-        if reached_limit(lines_written, config.max_lines) {
-            break;
+        if config.summary_only {
+            return Err("--summary-only 不能与 --format 同时使用".to_string());
         }
     }
 
-    let reached_max_lines = reached_limit(lines_written, config.max_lines);
-    let mut killed_by_tool = false;
-    if (reached_max_lines || stream_error.is_some()) && child.kill().is_ok() {
-        killed_by_tool = true;
+    if config.linear
+        && (config.mode == RunMode::Stream
+            || config.summary_only
+            || config.format != ReportFormat::Text
+            || config.template_path.is_some())
+    {
+        return Err(
+            "--linear 只能用于文本分析报告\n修复：去掉 --stream / --summary-only / --format / --template"
+                .to_string(),
+        );
     }
 
-    let status = child.wait().map_err(io_error_to_string)?;
-    if let Some(err) = stream_error {
-        return Err(err);
+    if config.template_path.is_some() {
+        if config.mode == RunMode::Stream {
+            return Err("--template 只能用于分析模式".to_string());
+        }
+        if config.summary_only || config.format != ReportFormat::Text {
+            return Err("--template 不能与 --summary-only 或 --format 同时使用".to_string());
+        }
     }
 
-    if !status.success()
-        && !killed_by_tool
-        && !status_killed_by_limit(lines_written, config.max_lines)
+    if (config.fail_on.is_some() || config.fail_on_count.is_some())
+        && config.mode != RunMode::Analyze
     {
-        return Err(format!("journalctl 退出状态异常：{status}"));
+        return Err("--fail-on / --fail-on-count 只能用于分析模式".to_string());
     }
 
-    let done_msg = StreamLine {
-        line: String::new(),
-        done: true,
-        error: None,
-    };
-    write_json_line(&mut writer, &done_msg, "结束标记")?;
-
-    Ok(())
+    if !config.exclude_units.is_empty()
+        && config.mode == RunMode::Stream
+        && !config.output_json
+        && config.format != ReportFormat::Logfmt
+        && !config.live
+    {
+        return Err(
+            "流模式文本输出不含单元字段，无法按 --exclude-unit 过滤\n修复：加 --json 或 --format logfmt"
+                .to_string(),
+        );
+    }
+
+    if config.timeline
+        && (config.mode == RunMode::Stream
+            || config.summary_only
+            || matches!(config.format, ReportFormat::Ndjson | ReportFormat::Logfmt))
+    {
+        return Err(
+            "--timeline 只能用于文本、--linear、--template 或 --format json 分析报告\n修复：去掉 --stream / --summary-only，或改用 --format json"
+                .to_string(),
+        );
+    }
+
+    if config.histogram.is_some()
+        && (config.mode == RunMode::Stream
+            || config.summary_only
+            || matches!(config.format, ReportFormat::Ndjson | ReportFormat::Logfmt))
+    {
+        return Err(
+            "--histogram 只能用于文本、--linear、--template 或 --format json 分析报告\n修复：去掉 --stream / --summary-only，或改用 --format json"
+                .to_string(),
+        );
+    }
+
+    if config.bursts
+        && (config.mode == RunMode::Stream
+            || config.summary_only
+            || matches!(config.format, ReportFormat::Ndjson | ReportFormat::Logfmt))
+    {
+        return Err(
+            "--bursts 只能用于文本、--linear、--template 或 --format json 分析报告\n修复：去掉 --stream / --summary-only，或改用 --format json"
+                .to_string(),
+        );
+    }
+
+    if config.samples.is_some() && (config.mode == RunMode::Stream || config.summary_only) {
+        return Err("--samples 只能用于分析报告\n修复：去掉 --stream / --summary-only".to_string());
+    }
+
+    if config.clusters.is_some() && (config.mode == RunMode::Stream || config.summary_only) {
+        return Err(
+            "--clusters 只能用于分析报告\n修复：去掉 --stream / --summary-only".to_string(),
+        );
+    }
+
+    if let Some(dir) = &config.evidence_dir
+        && (config.mode != RunMode::Analyze || dir.trim().is_empty())
+    {
+        return Err(
+            "--evidence 只能用于分析模式，且需要一个目录\n修复：如 logtool --evidence /tmp/evidence"
+                .to_string(),
+        );
+    }
+
+    if config.compare_previous {
+        if config.mode != RunMode::Analyze
+            || config.summary_only
+            || matches!(config.format, ReportFormat::Ndjson | ReportFormat::Logfmt)
+        {
+            return Err(
+                "--compare-previous 只能用于文本、--linear、--template 或 --format json 分析报告\n修复：去掉 --stream / --summary-only，或改用 --format json"
+                    .to_string(),
+            );
+        }
+        if config.boot != BootFilter::Disabled {
+            return Err(
+                "--compare-previous 按时间窗口对比，不能与 --boot 同时使用\n修复：改用 --last 或 --since 指定窗口"
+                    .to_string(),
+            );
+        }
+        trend::current_window(
+            config,
+            "--compare-previous",
+            timeutil::unix_now_secs(),
+            timeutil::local_utc_offset_secs(),
+        )?;
+    }
+
+    if let Some(path) = &config.against_baseline {
+        if config.mode != RunMode::Analyze
+            || config.summary_only
+            || matches!(config.format, ReportFormat::Ndjson | ReportFormat::Logfmt)
+        {
+            return Err(
+                "--against-baseline 只能用于文本、--linear、--template 或 --format json 分析报告\n修复：去掉 --stream / --summary-only，或改用 --format json"
+                    .to_string(),
+            );
+        }
+        if path.trim().is_empty() {
+            return Err(
+                "--against-baseline 需要基线文件\n修复：先运行 logtool baseline save <文件>"
+                    .to_string(),
+            );
+        }
+        if config.boot != BootFilter::Disabled {
+            return Err(
+                "--against-baseline 按每小时事件数对比，不能与 --boot 同时使用\n修复：改用 --last 或 --since 指定窗口"
+                    .to_string(),
+            );
+        }
+        trend::current_window(
+            config,
+            "--against-baseline",
+            timeutil::unix_now_secs(),
+            timeutil::local_utc_offset_secs(),
+        )?;
+    }
+
+    if config.by_user && (config.mode != RunMode::Analyze || config.summary_only) {
+        return Err("--by-user 只能用于分析报告\n修复：去掉 --stream / --summary-only".to_string());
+    }
+    if config.per_boot && (config.mode != RunMode::Analyze || config.summary_only) {
+        return Err(
+            "--per-boot 只能用于分析报告\n修复：去掉 --stream / --summary-only".to_string(),
+        );
+    }
+
+    if config.by_package {
+        if config.mode != RunMode::Analyze || config.summary_only {
+            return Err(
+                "--by-package 只能用于分析报告\n修复：去掉 --stream / --summary-only".to_string(),
+            );
+        }
+        if cfg!(not(feature = "packages")) {
+            return Err(
+                "本构建未启用 packages 特性，不支持 --by-package\n修复：以默认特性重新编译"
+                    .to_string(),
+            );
+        }
+    }
+
+    if config.with_failed_units {
+        if config.mode != RunMode::Analyze || config.summary_only {
+            return Err(
+                "--with-failed-units 只能用于分析报告\n修复：去掉 --stream / --summary-only"
+                    .to_string(),
+            );
+        }
+        if config.directory.is_some() || !config.files.is_empty() {
+            return Err(
+                "--with-failed-units 查看的是本机当前的单元状态，不能与 --directory / --file 同时使用\n修复：去掉 --with-failed-units，或在日志所属的机器上运行"
+                    .to_string(),
+            );
+        }
+    }
+
+    let sections = [
+        (config.crashes, "--crashes"),
+        (config.hangs, "--hangs"),
+        (config.network, "--network"),
+        (config.hardware, "--hardware"),
+        (config.audit, "--audit"),
+    ];
+    if let Some((_, flag)) = sections.iter().find(|(given, _)| *given)
+        && (config.mode != RunMode::Analyze || config.summary_only)
+    {
+        return Err(format!(
+            "{flag} 只能用于分析报告\n修复：去掉 --stream / --summary-only"
+        ));
+    }
+
+    if config.smart {
+        if config.mode != RunMode::Analyze || config.summary_only {
+            return Err(
+                "--smart 只能用于分析报告\n修复：去掉 --stream / --summary-only".to_string(),
+            );
+        }
+        if config.directory.is_some() || !config.files.is_empty() {
+            return Err(
+                "--smart 检查的是本机当前的磁盘，不能与 --directory / --file 同时使用\n修复：去掉 --smart，或在日志所属的机器上运行"
+                    .to_string(),
+            );
+        }
+    }
+
+    if let Some(path) = &config.forward_path {
+        if config.mode != RunMode::Stream {
+            return Err(
+                "--forward 只能搭配 --stream 使用\n修复：运行 logtool --stream --forward <路径>"
+                    .to_string(),
+            );
+        }
+        if path.trim().is_empty() {
+            return Err("--forward 需要 Unix Socket 或 FIFO 路径".to_string());
+        }
+    }
+
+    if config.archive && config.mode != RunMode::Stream {
+        return Err(
+            "--archive 只能搭配 --stream 使用\n修复：运行 logtool --stream --archive".to_string(),
+        );
+    }
+
+    for window in &config.maintenance_windows {
+        window.validate()?;
+    }
+
+    if let Some(path) = &config.output_path
+        && path.trim().is_empty()
+    {
+        return Err("--output 需要一个文件路径\n修复：示例 --output /tmp/report.txt".to_string());
+    }
+
+    Ok(())
 }
 
-// ── JSON 解析 ─────────────────────────────────────────────
+fn get_next_value(args: &[String], index: &mut usize, flag: &str) -> Result<String, String> {
+    if *index + 1 >= args.len() {
+        return Err(format!(
+            "缺少 {flag} 的参数值\n修复：运行 logtool --help 查看参数示例"
+        ));
+    }
+    *index += 1;
+    Ok(args[*index].clone())
+}
 
-pub fn parse_json_event(line: &str) -> Result<JournalEvent, String> {
-    let value: Value = serde_json::from_str(line).map_err(|err| err.to_string())?;
-    let object = value
-        .as_object()
-        .ok_or_else(|| "日志 JSON 行不是对象".to_string())?;
+fn has_next_boot_value(args: &[String], index: usize) -> bool {
+    if index + 1 >= args.len() {
+        return false;
+    }
 
-    let message = field_as_string(object, "MESSAGE").unwrap_or_default();
-    let priority = field_as_string(object, "PRIORITY").and_then(|p| p.parse::<u8>().ok());
-    let unit = field_as_string(object, "_SYSTEMD_UNIT");
-    let exe = field_as_string(object, "_EXE");
-    let comm = field_as_string(object, "_COMM");
-    let identifier = field_as_string(object, "SYSLOG_IDENTIFIER");
+    let next = &args[index + 1];
+    if !next.starts_with('-') {
+        return true;
+    }
 
-    Ok(JournalEvent {
-        message,
-        priority,
-        unit,
-        exe,
-        comm,
-        identifier,
-    })
+    is_boot_offset(next) || looks_like_boot_range(next)
 }
 
-fn field_as_string(map: &Map<String, Value>, key: &str) -> Option<String> {
-    let raw = map.get(key)?;
-    value_to_string(raw).and_then(normalize_optional)
+fn is_boot_offset(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    !digits.is_empty() && digits.chars().all(|ch| ch.is_ascii_digit())
 }
 
-fn value_to_string(value: &Value) -> Option<String> {
-    match value {
-        Value::String(s) => Some(s.clone()),
-        Value::Number(n) => Some(n.to_string()),
-        Value::Bool(b) => Some(b.to_string()),
-        Value::Array(arr) => decode_byte_array(arr),
-        _ => None,
+/// `-3..0`、`-2,-1`：以 `-` 开头时只有这种形式才被当作 --boot 的值
+fn looks_like_boot_range(value: &str) -> bool {
+    (value.contains("..") || value.contains(','))
+        && value
+            .chars()
+            .all(|ch| ch.is_ascii_digit() || matches!(ch, '-' | '.' | ','))
+}
+
+fn is_boot_id(value: &str) -> bool {
+    value.len() == 32 && value.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+/// container_id_from_cgroup 给出的 12 位短 ID
+fn is_container_id(value: &str) -> bool {
+    value.len() == 12 && value.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+/// --boot 的取值：单个偏移或启动 ID，或逗号分隔、可含 `a..b` 范围的多个启动周期
+fn parse_boot_value(value: &str) -> Result<BootFilter, String> {
+    let value = value.trim();
+    if !value.contains("..") && !value.contains(',') {
+        return Ok(BootFilter::Value(value.to_string()));
+    }
+    let hint =
+        "修复：如 --boot -3..0、--boot -2,0 或 --boot -1,<启动 ID>（运行 logtool boots 查看）";
+    let mut boots: Vec<String> = Vec::new();
+    for part in value.split(',').map(str::trim) {
+        if let Some((first, last)) = part.split_once("..") {
+            let (Ok(first), Ok(last)) = (first.parse::<i64>(), last.parse::<i64>()) else {
+                return Err(format!("--boot 范围的两端需要是启动偏移：{part}\n{hint}"));
+            };
+            if first > last {
+                return Err(format!("--boot 范围的起点大于终点：{part}\n{hint}"));
+            }
+            if last - first >= MAX_SELECTED_BOOTS as i64 {
+                return Err(format!(
+                    "--boot 最多选择 {MAX_SELECTED_BOOTS} 个启动周期：{part}\n{hint}"
+                ));
+            }
+            boots.extend((first..=last).map(|offset| offset.to_string()));
+        } else if is_boot_offset(part) || is_boot_id(part) {
+            boots.push(part.to_string());
+        } else {
+            return Err(format!("--boot 无法识别的启动周期：{part:?}\n{hint}"));
+        }
+    }
+    let mut seen = HashSet::new();
+    boots.retain(|boot| seen.insert(boot.to_ascii_lowercase()));
+    if boots.len() > MAX_SELECTED_BOOTS {
+        return Err(format!(
+            "--boot 最多选择 {MAX_SELECTED_BOOTS} 个启动周期（实际 {} 个）\n{hint}",
+            boots.len()
+        ));
     }
+    Ok(BootFilter::Several(boots))
 }
 
-fn decode_byte_array(arr: &[Value]) -> Option<String> {
-    let mut bytes = Vec::with_capacity(arr.len());
-    for item in arr {
-        let n = item.as_u64()?;
-        let byte = u8::try_from(n).ok()?;
-        bytes.push(byte);
+fn parse_positive_usize(value: &str, flag: &str) -> Result<usize, String> {
+    let parsed = value
+        .parse::<usize>()
+        .map_err(|_| format!("{flag} 需要一个正整数，实际输入：{value}\n修复：示例 {flag} 50"))?;
+    if parsed == 0 {
+        return Err(format!("{flag} 必须大于 0\n修复：示例 {flag} 50"));
     }
+    Ok(parsed)
+}
 
-    String::from_utf8(bytes).ok().and_then(normalize_optional)
+/// `--last 30m` → `30 min ago`（单位 s/m/h/d/w），交给 journalctl --since
+fn parse_last(value: &str) -> Result<String, String> {
+    let hint = "修复：如 --last 30m、--last 2h、--last 1d（单位 s/m/h/d/w）";
+    let value = value.trim();
+    let split = value
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (digits, unit) = value.split_at(split);
+    let count = digits
+        .parse::<u32>()
+        .map_err(|_| format!("--last 需要“数字+单位”，实际输入：{value}\n{hint}"))?;
+    if count == 0 {
+        return Err(format!("--last 必须大于 0\n{hint}"));
+    }
+    let plural = if count == 1 { "" } else { "s" };
+    let unit = match unit {
+        "s" => "sec".to_string(),
+        "m" => "min".to_string(),
+        "h" => format!("hour{plural}"),
+        "d" => format!("day{plural}"),
+        "w" => format!("week{plural}"),
+        _ => return Err(format!("--last 不支持的单位：{value}\n{hint}")),
+    };
+    Ok(format!("{count} {unit} ago"))
 }
 
-fn normalize_optional(value: String) -> Option<String> {
-    let trimmed = value.trim();
-    if trimmed.is_empty() {
-        return None;
+/// `--histogram 5m` → 300 秒（单位 s/m/h/d，不带单位按秒）
+fn parse_histogram_interval(value: &str) -> Result<u64, String> {
+    let hint = "修复：如 --histogram 5m、--histogram 1h（单位 s/m/h/d）";
+    let value = value.trim();
+    let split = value
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (digits, unit) = value.split_at(split);
+    let count = digits
+        .parse::<u64>()
+        .map_err(|_| format!("--histogram 需要“数字+单位”，实际输入：{value}\n{hint}"))?;
+    if count == 0 {
+        return Err(format!("--histogram 间隔必须大于 0\n{hint}"));
     }
-    Some(trimmed.to_string())
+    let unit_secs = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => return Err(format!("--histogram 不支持的单位：{value}\n{hint}")),
+    };
+    count
+        .checked_mul(unit_secs)
+        .ok_or_else(|| format!("--histogram 间隔过大：{value}\n{hint}"))
 }
 
-// ── 过滤与分类 ─────────────────────────────────────────────
+fn parse_identifier(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("--identifier 缺少标识符\n修复：如 -t sshd 或 -t kernel（即日志中的 SYSLOG_IDENTIFIER）".to_string());
+    }
+    Ok(value.to_string())
+}
 
-pub fn event_matches_terms(event: &JournalEvent, terms: &[String]) -> bool {
-    if terms.is_empty() {
-        return true;
+/// 内核记录的进程名最多 15 字节（TASK_COMM_LEN - 1），更长的名称按同样规则截断，否则永远匹配不上
+fn parse_comm(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(
+            "--comm 缺少进程名\n修复：如 --comm python3（即 ps -o comm 显示的名称）".to_string(),
+        );
+    }
+    let mut end = value.len().min(15);
+    while !value.is_char_boundary(end) {
+        end -= 1;
     }
+    Ok(value[..end].to_string())
+}
 
-    let mut text = String::new();
-    text.push_str(&event.message);
-    if let Some(unit) = &event.unit {
-        text.push(' ');
-        text.push_str(unit);
+/// journal 字段名只含大写字母、数字和下划线，且不以数字开头；`+` 原样保留
+fn parse_match(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value == "+" {
+        return Ok(value.to_string());
     }
-    if let Some(exe) = &event.exe {
-        text.push(' ');
-        text.push_str(exe);
+    let hint = "修复：如 --match _TRANSPORT=kernel，或在两组条件间写 --match + 表示“或”";
+    let Some((field, _)) = value.split_once('=') else {
+        return Err(format!("--match 需要 FIELD=value 形式：{value}\n{hint}"));
+    };
+    let valid = !field.is_empty()
+        && !field.starts_with(|ch: char| ch.is_ascii_digit())
+        && field
+            .chars()
+            .all(|ch| ch.is_ascii_uppercase() || ch.is_ascii_digit() || ch == '_');
+    if !valid {
+        return Err(format!(
+            "--match 字段名无效：{field}\n修复：journal 字段名为大写字母、数字和下划线，如 _SYSTEMD_USER_UNIT、CODE_FILE"
+        ));
     }
-    if let Some(comm) = &event.comm {
-        text.push(' ');
-        text.push_str(comm);
+    Ok(value.to_string())
+}
+
+fn parse_pid(value: &str) -> Result<u32, String> {
+    let pid = parse_positive_usize(value.trim(), "--pid")?;
+    u32::try_from(pid).map_err(|_| format!("--pid 超出范围：{value}"))
+}
+
+/// 数字按 UID 原样使用；用户名通过 `id -u` 解析（与 NSS/LDAP 一致）
+fn resolve_user_filter(value: &str) -> Result<u32, String> {
+    let value = value.trim();
+    if let Ok(uid) = value.parse::<u32>() {
+        return Ok(uid);
     }
-    if let Some(id) = &event.identifier {
-        text.push(' ');
-        text.push_str(id);
+    let not_found = || {
+        format!(
+            "找不到用户：{value}\n修复：用 getent passwd 确认用户名，或直接传 UID，如 --user-filter 1000"
+        )
+    };
+    if value.is_empty() || value.starts_with('-') {
+        return Err(not_found());
     }
+    let output = Command::new("id")
+        .arg("-u")
+        .arg(value)
+        .output()
+        .map_err(|e| format!("执行 id 失败：{e}"))?;
+    if !output.status.success() {
+        return Err(not_found());
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|_| not_found())
+}
 
-    let lower = text.to_ascii_lowercase();
-    terms.iter().all(|term| lower.contains(term))
+/// 接受 /proc/<pid>/cgroup 中的整行（`0::/system.slice/ssh.service`）或路径本身
+fn normalize_cgroup_path(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    let path = value.strip_prefix("0::").unwrap_or(value);
+    if !path.starts_with('/') {
+        return Err(format!(
+            "无效 cgroup 路径：{value}\n修复：使用绝对路径，如 --cgroup /system.slice/ssh.service（可从 /proc/<pid>/cgroup 获得）"
+        ));
+    }
+    Ok(path.to_string())
 }
 
-pub fn classify_source(event: &JournalEvent) -> (SourceKind, String) {
-    if let Some(id) = &event.identifier
-        && id == "kernel"
-    {
-        return (SourceKind::Kernel, "kernel".to_string());
+fn parse_fail_on(value: &str) -> Result<u8, String> {
+    parse_priority_level(value)
+        .ok_or_else(|| format!("无效优先级：{value}\n修复：示例 --fail-on err 或 --fail-on 2"))
+}
+
+/// --fail-on 的判定：返回 (命中事件数, 阈值级别)；未触发时为 None
+///
+/// 只给出 --fail-on-count 时以 --priority 查询级别为阈值。
+pub fn fail_on_triggered(config: &Config, metrics: &AnalyzeMetrics) -> Option<(usize, u8)> {
+    if config.fail_on.is_none() && config.fail_on_count.is_none() {
+        return None;
     }
+    let level = config
+        .fail_on
+        .unwrap_or_else(|| config.priority.threshold());
+    let hits = metrics.events_at_or_above(level);
+    (hits >= config.fail_on_count.unwrap_or(1)).then_some((hits, level))
+}
 
-    if let Some(unit) = &event.unit {
-        return (SourceKind::Unit, unit.clone());
+pub(crate) fn parse_group_by(value: &str) -> Result<GroupBy, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "unit" => Ok(GroupBy::Unit),
+        "exe" => Ok(GroupBy::Exe),
+        "identifier" => Ok(GroupBy::Identifier),
+        #[cfg(feature = "packages")]
+        "package" => Ok(GroupBy::Package),
+        #[cfg(not(feature = "packages"))]
+        "package" => Err(
+            "本构建未启用 packages 特性，不支持 --group-by package\n修复：改用其他维度，或以默认特性重新编译"
+                .to_string(),
+        ),
+        "comm" => Ok(GroupBy::Comm),
+        _ => Err(format!(
+            "无效归并维度：{value}\n修复：使用 --group-by unit|exe|identifier|package|comm"
+        )),
     }
+}
 
-    if let Some(exe) = &event.exe {
-        return (SourceKind::Executable, exe.clone());
+fn parse_report_format(value: &str) -> Result<ReportFormat, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "text" => Ok(ReportFormat::Text),
+        "json" => Ok(ReportFormat::Json),
+        #[cfg(feature = "exporters")]
+        "ndjson" | "jsonl" => Ok(ReportFormat::Ndjson),
+        #[cfg(feature = "exporters")]
+        "logfmt" => Ok(ReportFormat::Logfmt),
+        #[cfg(not(feature = "exporters"))]
+        "ndjson" | "jsonl" | "logfmt" => Err(format!(
+            "本构建未启用 exporters 特性，不支持 --format {value}\n修复：使用 --format text|json，或以默认特性重新编译"
+        )),
+        _ => Err(format!(
+            "无效输出格式：{value}\n修复：使用 --format text|json|ndjson|logfmt"
+        )),
     }
+}
 
-    if let Some(identifier) = &event.identifier {
-        return (SourceKind::Identifier, identifier.clone());
+/// daemon 的工作目录与 CLI 不同，因此在 CLI 侧解析为绝对路径并确认是目录；daemon 侧由 validate_config 再检查一次
+fn parse_directory(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    let hint = "修复：指定包含 *.journal 文件的目录，如 --directory /mnt/broken/var/log/journal";
+    if value.is_empty() {
+        return Err(format!("--directory 缺少目录\n{hint}"));
     }
+    let path = fs::canonicalize(value).map_err(|e| format!("无法访问目录 {value}：{e}\n{hint}"))?;
+    if !path.is_dir() {
+        return Err(format!("--directory 需要目录：{value}\n{hint}"));
+    }
+    Ok(path.to_string_lossy().into_owned())
+}
 
-    if let Some(comm) = &event.comm {
-        return (SourceKind::Comm, comm.clone());
+/// 同 parse_directory；只接受 journal 文件本身，`journalctl -o export` 的导出流需先转换
+fn parse_journal_file(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    let hint = "修复：指定 .journal 文件，如 --file /tmp/case-1234/system.journal；\
+                export 格式需先用 systemd-journal-remote -o <文件>.journal 转换";
+    if value.is_empty() {
+        return Err(format!("--file 缺少文件\n{hint}"));
+    }
+    let path = fs::canonicalize(value).map_err(|e| format!("无法访问文件 {value}：{e}\n{hint}"))?;
+    if !path.is_file() {
+        return Err(format!("--file 需要普通文件：{value}\n{hint}"));
     }
+    Ok(path.to_string_lossy().into_owned())
+}
 
-    (SourceKind::Unknown, "unknown".to_string())
+/// 同 parse_journal_file；syslog 文件由分析进程直接读取
+fn parse_syslog_file(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    let hint =
+        "修复：指定 rsyslog 写出的文本文件或其 .gz 轮转文件，如 --syslog-file /var/log/syslog";
+    if value.is_empty() {
+        return Err(format!("--syslog-file 缺少文件\n{hint}"));
+    }
+    let path = fs::canonicalize(value).map_err(|e| format!("无法访问文件 {value}：{e}\n{hint}"))?;
+    if !path.is_file() {
+        return Err(format!("--syslog-file 需要普通文件：{value}\n{hint}"));
+    }
+    Ok(path.to_string_lossy().into_owned())
 }
 
-fn compare_suspects(left: &SourceStats, right: &SourceStats) -> Ordering {
-    right
-        .count
-        .cmp(&left.count)
-        .then(left.worst_priority.cmp(&right.worst_priority))
-        .then_with(|| left.source.cmp(&right.source))
+/// journalctl --namespace 的取值：命名空间名、`*`（全部）或 `+名称`（含默认命名空间）
+fn parse_namespace(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    let name = value.strip_prefix('+').unwrap_or(value);
+    let valid = value == "*"
+        || (!name.is_empty()
+            && name.len() <= 64
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')));
+    if valid {
+        Ok(value.to_string())
+    } else {
+        Err(format!(
+            "无效命名空间：{value}\n修复：使用服务 LogNamespace= 中的名称（字母、数字、_ - .），`*` 表示全部，`+名称` 同时包含默认命名空间"
+        ))
+    }
 }
 
-// ── 包反查 ─────────────────────────────────────────────
+/// --priority 查询级别：闭区间 from..=to（数值越小越严重）。
+///
+/// 单个级别 `N` 即 0..=N，与 journalctl 一致。序列化为 journalctl 接受的字符串
+/// （`3` / `2..4`），反序列化时校验，daemon 不会收到无效级别。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Priority {
+    pub from: u8,
+    pub to: u8,
+}
 
-fn resolve_packages_for_top(suspects: &mut [SourceStats], top: usize) {
-    let mut resolver = PackageResolver::new();
-    let limit = suspects.len().min(top);
+impl Priority {
+    pub const fn up_to(level: u8) -> Self {
+        Self { from: 0, to: level }
+    }
 
-    for suspect in suspects.iter_mut().take(limit) {
-        suspect.package = resolver.resolve(suspect);
+    /// 最不严重的一端：报告排序与 watch/--fail-on 的默认阈值
+    pub fn threshold(self) -> u8 {
+        self.to
     }
-}
 
-#[derive(Default)]
-struct PackageResolver {
-    dpkg_available: bool,
-    systemctl_available: bool,
-    path_cache: HashMap<String, Option<String>>,
-    unit_cache: HashMap<String, Option<String>>,
+    pub fn contains(self, level: u8) -> bool {
+        (self.from..=self.to).contains(&level)
+    }
 }
 
-impl PackageResolver {
-    fn new() -> Self {
-        Self {
-            dpkg_available: command_exists("dpkg-query"),
-            systemctl_available: command_exists("systemctl"),
-            path_cache: HashMap::new(),
-            unit_cache: HashMap::new(),
+impl std::str::FromStr for Priority {
+    type Err = String;
+
+    /// 数字 0-7、名称（err、warning、crit…）或范围 `A..B`；范围两端按严重程度排序
+    fn from_str(value: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "无效优先级：{value}\n修复：使用 0-7、err/warning/info/debug 等名称，或范围如 2..4、crit..warning（可运行：logtool --help）"
+            )
+        };
+        match value.split_once("..") {
+            Some((from, to)) => {
+                let from = parse_priority_level(from).ok_or_else(invalid)?;
+                let to = parse_priority_level(to).ok_or_else(invalid)?;
+                Ok(Self {
+                    from: from.min(to),
+                    to: from.max(to),
+                })
+            }
+            None => parse_priority_level(value)
+                .map(Self::up_to)
+                .ok_or_else(invalid),
         }
     }
+}
 
-    fn resolve(&mut self, suspect: &SourceStats) -> Option<String> {
-        if !self.dpkg_available {
-            return None;
+impl std::fmt::Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.from == 0 {
+            write!(f, "{}", self.to)
+        } else {
+            write!(f, "{}..{}", self.from, self.to)
         }
+    }
+}
 
-        if let Some(exe) = &suspect.sample_exe
-            && let Some(pkg) = self.package_by_path(exe)
-        {
-            return Some(pkg);
-        }
+impl TryFrom<String> for Priority {
+    type Error = String;
 
-        if suspect.kind == SourceKind::Executable
-            && let Some(pkg) = self.package_by_path(&suspect.source)
-        {
-            return Some(pkg);
-        }
+    fn try_from(value: String) -> Result<Self, String> {
+        value.parse()
+    }
+}
 
-        if let Some(unit) = &suspect.sample_unit {
-            return self.package_by_unit(unit);
+impl From<Priority> for String {
+    fn from(priority: Priority) -> Self {
+        priority.to_string()
+    }
+}
+
+/// 解析 syslog 优先级（数字 0-7 或名称，大小写不敏感）
+pub fn parse_priority_level(value: &str) -> Option<u8> {
+    let level = match value.trim().to_ascii_lowercase().as_str() {
+        "0" | "emerg" | "emergency" | "panic" => 0,
+        "1" | "alert" => 1,
+        "2" | "crit" | "critical" => 2,
+        "3" | "err" | "error" => 3,
+        "4" | "warning" | "warn" => 4,
+        "5" | "notice" => 5,
+        "6" | "info" | "informational" | "information" => 6,
+        "7" | "debug" => 7,
+        _ => return None,
+    };
+    Some(level)
+}
+
+// ── 日志分析核心 ─────────────────────────────────────────────
+
+pub fn analyze_journal(config: &Config) -> Result<AnalyzeResponse, String> {
+    if !config.compare_previous {
+        return analyze_window(config);
+    }
+    let (since, until) = trend::current_window(
+        config,
+        "--compare-previous",
+        timeutil::unix_now_secs(),
+        timeutil::local_utc_offset_secs(),
+    )?;
+    let previous_since = since.saturating_sub(until - since);
+    let previous = analyze_window(&trend::previous_window_config(
+        config,
+        previous_since,
+        since,
+    ))?;
+    let mut response = analyze_window(config)?;
+    response.trend = Some(TrendReport {
+        previous_since_us: previous_since * 1_000_000,
+        previous_until_us: since * 1_000_000,
+        previous_matched: previous.metrics.matched,
+        changes: trend::compare_suspects(&response.suspects, &previous.suspects, config.top),
+    });
+    Ok(response)
+}
+
+/// 分析读取的事件流：journalctl 的 JSON 输出，或由 syslog 文件、兜底时的内核缓冲区转换成的同样格式的行
+struct EventStream {
+    lines: Box<dyn Iterator<Item = io::Result<String>>>,
+    /// 读取 journal 时的 journalctl 子进程
+    child: Option<Child>,
+    kernel_fallback: Option<KernelFallback>,
+}
+
+impl EventStream {
+    fn kernel(fallback: KernelFallback, lines: String) -> Self {
+        EventStream {
+            lines: Box::new(io::Cursor::new(lines).lines()),
+            child: None,
+            kernel_fallback: Some(fallback),
+        }
+    }
+}
+
+/// --syslog-file 时读取文件；journalctl 不可用，或没有任何输出且本机没有 journal 文件时，改读内核缓冲区（见 kmsg 模块）
+fn open_event_stream(
+    config: &Config,
+    journalctl: Result<(), String>,
+) -> Result<EventStream, String> {
+    if !config.syslog_files.is_empty() {
+        return Ok(EventStream {
+            lines: syslog::read_syslog_files(config)?,
+            child: None,
+            kernel_fallback: None,
+        });
+    }
+    if let Err(err) = journalctl {
+        let (fallback, lines) =
+            kmsg::read_kernel_log(config, FallbackReason::NoJournalctl).ok_or(err)?;
+        return Ok(EventStream::kernel(fallback, lines));
+    }
+
+    let mut cmd = build_journalctl_command_for_analysis(config);
+    if config.show_command {
+        eprintln!("执行命令：{}", render_command(&cmd));
+    }
+
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|err| format!("启动 journalctl 失败：{err}"))?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| "无法获取 journalctl 标准输出".to_string())?;
+
+    let mut reader = BufReader::new(stdout);
+    // 没有任何输出：journalctl 正常退出且本机没有 journal 文件时才兜底，出错照常报告
+    if reader.fill_buf().is_ok_and(|buf| buf.is_empty())
+        && kmsg::can_fall_back(config)
+        && !kmsg::journal_files_present()
+    {
+        let (status, _) = rusage::wait_child(&mut child).map_err(io_error_to_string)?;
+        if !status.success() {
+            return Err(format!("journalctl 退出状态异常：{status}"));
+        }
+        return Ok(
+            match kmsg::read_kernel_log(config, FallbackReason::NoJournalFiles) {
+                Some((fallback, lines)) => EventStream::kernel(fallback, lines),
+                None => EventStream {
+                    lines: Box::new(std::iter::empty()),
+                    child: None,
+                    kernel_fallback: None,
+                },
+            },
+        );
+    }
+    Ok(EventStream {
+        lines: Box::new(reader.lines()),
+        child: Some(child),
+        kernel_fallback: None,
+    })
+}
+
+fn analyze_window(config: &Config) -> Result<AnalyzeResponse, String> {
+    // syslog 文件不经 journalctl，崩溃、审计、重启等按本机 journal 的附加查询也不做
+    let from_journal = config.syslog_files.is_empty();
+    let journalctl = if from_journal {
+        ensure_journalctl_exists()
+    } else {
+        Ok(())
+    };
+    let resolved = match journalctl {
+        Ok(()) => resolve_boot_selection(config)?,
+        Err(_) => None,
+    };
+    let (config, boots) = match &resolved {
+        Some((resolved, offsets)) => (resolved, offsets.clone()),
+        None => (config, Vec::new()),
+    };
+
+    let EventStream {
+        lines,
+        mut child,
+        kernel_fallback,
+    } = open_event_stream(config, journalctl)?;
+    let mut stats: HashMap<(SourceKind, String), SourceStats> = HashMap::new();
+    let mut metrics = AnalyzeMetrics::default();
+    let offset_secs = if config.maintenance_windows.is_empty() {
+        0
+    } else {
+        timeutil::local_utc_offset_secs()
+    };
+    let mut maintenance = MaintenanceMatcher::new(&config.maintenance_windows, offset_secs);
+    let mut timeline = config.timeline.then(TimelineTracker::default);
+    let mut histogram = config.histogram.map(HistogramTracker::new);
+    let mut bursts = config.bursts.then(BurstTracker::default);
+    let mut users = config.by_user.then(UserTracker::default);
+    let mut per_boot = config.per_boot.then(BootTracker::default);
+    #[cfg(feature = "rules")]
+    let rule_set = rules::RuleSet::load_system().map_err(|err| {
+        format!(
+            "{err}\n修复：运行 logtool rules test --rules {} 检查规则文件",
+            rules::SYSTEM_RULES_DIR
+        )
+    })?;
+    #[cfg(feature = "rules")]
+    let known_issues = rules::RuleSet::known_issues();
+    let mut rule_hits: HashMap<String, u64> = HashMap::new();
+    let mut grouper = SourceGrouper::new(config.group_by);
+    let mut evidence: HashMap<(SourceKind, String), Vec<String>> = HashMap::new();
+    let mut clusters: HashMap<(SourceKind, String), ClusterCounter> = HashMap::new();
+    let mut message_counts: HashMap<(SourceKind, String), HashMap<String, u64>> = HashMap::new();
+    let redactor = config.redact.then(Redactor::from_system);
+    let message_chars = config.max_message_chars.unwrap_or(DEFAULT_MESSAGE_CHARS);
+
+    let mut emergency_included = 0usize;
+    let hour_ago_us = timeutil::unix_now_secs().saturating_sub(3600) * 1_000_000;
+
+    let mut previous_key: Option<String> = None;
+
+    let mut loop_error: Option<String> = None;
+    for maybe_line in lines {
+        let line = match maybe_line {
+            Ok(line) => line,
+            Err(err) => {
+                loop_error = Some(io_error_to_string(err));
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        metrics.lines_read += 1;
+        let event = match parse_json_event(&line) {
+            Ok(event) => {
+                metrics.parsed_ok += 1;
+                event
+            }
+            Err(_) => {
+                metrics.parse_errors += 1;
+                continue;
+            }
+        };
+
+        if !event_matches_filters(&event, config) {
+            continue;
+        }
+        if event_is_excluded(&event, config) {
+            metrics.excluded += 1;
+            continue;
+        }
+        if config.dedup {
+            let key = dedup_key(&event);
+            if previous_key.as_ref() == Some(&key) {
+                metrics.duplicates += 1;
+                continue;
+            }
+            previous_key = Some(key);
+        }
+
+        metrics.matched += 1;
+        if event.timestamp_us.is_some_and(|ts| ts >= hour_ago_us) {
+            metrics.last_hour += 1;
+        }
+        if event.priority.is_some_and(|p| p <= EMERGENCY_MAX_PRIORITY) {
+            emergency_included += 1;
+        }
+        // 第一条命中的规则：(规则 id, merge_as, 调整后的级别)
+        #[cfg(feature = "rules")]
+        let matched_rule = rule_set.first_match(&event).map(|rule| {
+            (
+                rule.rule.id.as_str(),
+                rule.rule.merge_as.as_deref(),
+                rule.severity,
+            )
+        });
+        #[cfg(not(feature = "rules"))]
+        let matched_rule: Option<(&str, Option<&str>, Option<u8>)> = None;
+        let (kind, source) = match matched_rule {
+            Some((rule_id, merge_as, _)) => {
+                *rule_hits.entry(rule_id.to_string()).or_default() += 1;
+                match merge_as {
+                    Some(name) => (SourceKind::Rule, name.to_string()),
+                    None => grouper.classify(&event),
+                }
+            }
+            None => grouper.classify(&event),
+        };
+        let key = (kind, source.clone());
+        if config.evidence_dir.is_some() {
+            let entry = match &redactor {
+                Some(redactor) => redact_evidence_line(&line, redactor),
+                None => line,
+            };
+            evidence.entry(key.clone()).or_default().push(entry);
+        }
+
+        let entry = stats.entry(key).or_insert_with(|| SourceStats {
+            kind,
+            source,
+            worst_priority: 7,
+            ..SourceStats::default()
+        });
+
+        entry.count += 1;
+
+        #[cfg(feature = "rules")]
+        if entry.hint.is_none()
+            && let Some(rule) = rule_set
+                .first_hint(&event)
+                .or_else(|| known_issues.first_hint(&event))
+            && let Some(suggestion) = &rule.rule.hint
+        {
+            entry.hint = Some(Hint {
+                rule_id: rule.rule.id.clone(),
+                explanation: rule.rule.description.clone(),
+                suggestion: suggestion.clone(),
+            });
+        }
+
+        if !maintenance.is_empty()
+            && let Some(ts) = event.timestamp_us
+            && maintenance.contains(ts)
+        {
+            entry.maintenance_count += 1;
+            metrics.in_maintenance += 1;
+        }
+
+        let mut priority = event.priority;
+        if let Some((rule_id, _, Some(level))) = matched_rule {
+            let adjusted = entry
+                .severity_override
+                .get_or_insert_with(|| SeverityOverride {
+                    rule_id: rule_id.to_string(),
+                    raw_worst_priority: None,
+                    events: 0,
+                });
+            adjusted.events += 1;
+            if let Some(raw) = event.priority {
+                adjusted.raw_worst_priority =
+                    Some(adjusted.raw_worst_priority.map_or(raw, |p| p.min(raw)));
+            }
+            priority = Some(level);
+        }
+
+        if let Some(p) = priority {
+            if p < entry.worst_priority {
+                entry.worst_priority = p;
+            }
+            if entry.priority_counts.is_empty() {
+                entry.priority_counts = vec![0; 8];
+            }
+            if let Some(slot) = entry.priority_counts.get_mut(usize::from(p)) {
+                *slot += 1;
+            }
+            if metrics.priority_counts.is_empty() {
+                metrics.priority_counts = vec![0; 8];
+            }
+            if let Some(slot) = metrics.priority_counts.get_mut(usize::from(p)) {
+                *slot += 1;
+            }
+        }
+
+        if let Some(tracker) = &mut users {
+            tracker.record(event.uid, entry.kind, &entry.source, priority);
+        }
+        if let Some(tracker) = &mut per_boot {
+            tracker.record(
+                event.boot_id.as_deref(),
+                entry.kind,
+                &entry.source,
+                priority,
+                event.timestamp_us,
+            );
+        }
+
+        if !event.message.is_empty() {
+            let message = match &redactor {
+                Some(redactor) => std::borrow::Cow::Owned(redactor.redact(&event.message)),
+                None => std::borrow::Cow::Borrowed(event.message.as_str()),
+            };
+            entry.sample_message = truncate_for_display(&message, message_chars);
+            let counts = message_counts
+                .entry((entry.kind, entry.source.clone()))
+                .or_default();
+            if let Some(count) = counts.get_mut(&entry.sample_message) {
+                *count += 1;
+            } else if counts.len() < MAX_TRACKED_MESSAGES {
+                counts.insert(entry.sample_message.clone(), 1);
+            }
+            if config.clusters.is_some() {
+                let template =
+                    truncate_for_display(&cluster::message_template(&message), message_chars);
+                clusters
+                    .entry((entry.kind, entry.source.clone()))
+                    .or_default()
+                    .record(template, &entry.sample_message);
+            }
+            if let Some(limit) = config.samples {
+                record_sample(
+                    &mut entry.samples,
+                    &entry.sample_message,
+                    event.priority,
+                    limit,
+                );
+            }
+        }
+
+        if let Some(tracker) = &mut timeline
+            && let Some(ts) = event.timestamp_us
+        {
+            tracker.record(
+                entry.kind,
+                &entry.source,
+                ts,
+                &entry.sample_message,
+                priority,
+            );
+        }
+
+        if let Some(ts) = event.timestamp_us {
+            entry.first_us = Some(entry.first_us.map_or(ts, |first| first.min(ts)));
+            entry.last_us = Some(entry.last_us.map_or(ts, |last| last.max(ts)));
+        }
+
+        if let Some(tracker) = &mut histogram
+            && let Some(ts) = event.timestamp_us
+        {
+            tracker.record(entry.kind, &entry.source, ts);
+        }
+
+        if let Some(tracker) = &mut bursts
+            && let Some(ts) = event.timestamp_us
+        {
+            tracker.record(entry.kind, &entry.source, ts);
+        }
+
+        if entry.sample_unit.is_none() {
+            entry.sample_unit = event.unit.clone();
+        }
+
+        if entry.sample_exe.is_none() {
+            entry.sample_exe = event.exe.clone();
+        }
+
+        if let Some(host) = &event.hostname {
+            // 来源与报告头部的主机列表都由这里汇总，--redact 时在此遮盖
+            let host = match &redactor {
+                Some(redactor) => redactor.redact(host),
+                None => host.clone(),
+            };
+            match entry.hosts.iter_mut().find(|h| h.host == host) {
+                Some(existing) => existing.count += 1,
+                None => entry.hosts.push(HostCount { host, count: 1 }),
+            }
+        }
+
+        if reached_limit(metrics.matched, config.max_lines) {
+            break;
+        }
+    }
+
+    let reached_max_lines = reached_limit(metrics.matched, config.max_lines);
+    let mut journalctl_usage = None;
+    if let Some(child) = &mut child {
+        if reached_max_lines || loop_error.is_some() {
+            let _ = child.kill();
+        }
+        let (status, usage) = rusage::wait_child(child).map_err(io_error_to_string)?;
+        journalctl_usage = usage;
+        if loop_error.is_none()
+            && !status.success()
+            && !status_killed_by_limit(metrics.matched, config.max_lines)
+        {
+            return Err(format!("journalctl 退出状态异常：{status}"));
+        }
+    }
+    if let Some(err) = loop_error {
+        return Err(err);
+    }
+
+    // 读到 --max-lines 为止时已纳入的条数不完整，无法比较；内核缓冲区兜底时没有 journal 可查
+    if !reached_max_lines
+        && from_journal
+        && kernel_fallback.is_none()
+        && filters_narrow_window(config)
+    {
+        metrics.emergency_outside_filter =
+            count_emergency_outside_filter(config, emergency_included);
+    }
+
+    let mut suspects = stats.into_values().collect::<Vec<_>>();
+    rank_suspects(&mut suspects, config.priority.threshold());
+    for suspect in &mut suspects {
+        order_samples(&mut suspect.samples);
+        if let Some(counts) = message_counts.remove(&(suspect.kind, suspect.source.clone())) {
+            suspect.top_messages = top_message_counts(counts);
+        }
+        if let Some(limit) = config.clusters
+            && let Some(counter) = clusters.remove(&(suspect.kind, suspect.source.clone()))
+        {
+            suspect.clusters = counter.top(limit);
+        }
+    }
+    let hosts = collect_hosts(&mut suspects);
+
+    #[cfg(feature = "packages")]
+    packages::resolve_packages_for_top(
+        &mut suspects,
+        if config.by_package {
+            config.top.max(owners::MAX_RESOLVED_SOURCES)
+        } else {
+            config.top
+        },
+    );
+    #[cfg(feature = "packages")]
+    packages::resolve_package_versions(&mut suspects, config.top);
+    restarts::annotate_restarts(&mut suspects, config);
+    let root_causes = dependencies::find_root_causes(&suspects, config);
+    let smart = if config.smart {
+        smart::check_storage_health(&suspects, config.top)
+    } else {
+        Vec::new()
+    };
+    if config.boot_history {
+        annotate_boot_presence(&mut suspects, config);
+    }
+    if config.reliability {
+        reliability::annotate_reliability(&mut suspects, config, timeutil::unix_now_secs());
+    }
+
+    let failed_units = if config.with_failed_units {
+        failed::annotate_failed_units(&mut suspects)
+    } else {
+        Vec::new()
+    };
+    let reboots = if from_journal {
+        reboots::detect_reboots(config)
+    } else {
+        Vec::new()
+    };
+    reboots::annotate_boot_spans(&mut suspects, &reboots, config.top);
+
+    let timeline = timeline
+        .map(|tracker| tracker.build(&suspects, config.top))
+        .unwrap_or_default();
+    let histogram = histogram.and_then(|tracker| tracker.build(&mut suspects, config.top));
+    let evidence = suspects
+        .iter()
+        .take(config.top)
+        .filter_map(|suspect| {
+            let entries = evidence.remove(&(suspect.kind, suspect.source.clone()))?;
+            Some(SuspectEvidence {
+                id: suspect_id(suspect.kind, &suspect.source),
+                entries,
+            })
+        })
+        .collect();
+    let by_package = config
+        .by_package
+        .then(|| owners::summarize_by_package(&suspects, config.top));
+    Ok(AnalyzeResponse {
+        metrics,
+        suspects,
+        top: config.top,
+        environment: config.env_snapshot.then(|| EnvironmentSnapshot {
+            #[cfg(feature = "rules")]
+            presets: known_issues.ids(),
+            #[cfg(feature = "rules")]
+            rules: rule_set.ids(),
+            ..collect_environment_snapshot()
+        }),
+        rule_hits: sorted_rule_hits(rule_hits),
+        namespace: config.namespace.clone(),
+        directory: config.directory.clone(),
+        files: config.files.clone(),
+        syslog_files: config.syslog_files.clone(),
+        kernel_fallback,
+        hosts,
+        boots,
+        timeline,
+        histogram,
+        bursts: bursts.map(|tracker| tracker.build()).unwrap_or_default(),
+        trend: None,
+        baseline: None,
+        reboots,
+        crashes: redact_section(
+            if from_journal && config.crashes {
+                crash::collect_crashes(config)
+            } else {
+                Vec::new()
+            },
+            redactor.as_ref(),
+            crash::Crash::redact,
+        ),
+        hangs: redact_section(
+            if from_journal && config.hangs {
+                hangs::collect_hangs(config)
+            } else {
+                Vec::new()
+            },
+            redactor.as_ref(),
+            hangs::Hang::redact,
+        ),
+        network: redact_section(
+            if from_journal && config.network {
+                network::collect_network_issues(config)
+            } else {
+                Vec::new()
+            },
+            redactor.as_ref(),
+            network::NetworkIssue::redact,
+        ),
+        hardware: if from_journal && config.hardware {
+            hardware::collect_hardware_warnings(config)
+        } else {
+            Vec::new()
+        },
+        audit: redact_section(
+            if from_journal && config.audit {
+                audit::collect_denials(config)
+            } else {
+                Vec::new()
+            },
+            redactor.as_ref(),
+            audit::AuditDenial::redact,
+        ),
+        failed_units,
+        root_causes,
+        smart,
+        by_package,
+        by_user: users.map(|tracker| {
+            let mut view = tracker.finish(config.top);
+            if let Some(redactor) = &redactor {
+                view.redact(redactor);
+            }
+            view
+        }),
+        per_boot: per_boot
+            .map(|tracker| tracker.finish(&reboots::list_boot_records(config).unwrap_or_default())),
+        journalctl_usage,
+        truncated: None,
+        cached_at: None,
+        evidence,
+    })
+}
+
+/// --redact：附加各节直接取自 journalctl 的原始消息，与主消息一样遮盖
+fn redact_section<T>(
+    mut items: Vec<T>,
+    redactor: Option<&Redactor>,
+    redact: impl Fn(&mut T, &Redactor),
+) -> Vec<T> {
+    if let Some(redactor) = redactor {
+        for item in &mut items {
+            redact(item, redactor);
+        }
+    }
+    items
+}
+
+/// 只遮盖字段值，保证写出的仍是合法 JSON
+fn redact_evidence_line(line: &str, redactor: &Redactor) -> String {
+    match serde_json::from_str::<Map<String, Value>>(line) {
+        Ok(mut fields) => {
+            for value in fields.values_mut() {
+                if let Value::String(text) = value {
+                    *text = redactor.redact(text);
+                }
+            }
+            Value::Object(fields).to_string()
+        }
+        Err(_) => redactor.redact(line),
+    }
+}
+
+/// 当前条件是否可能把同一时间窗口内的部分事件挡在外面
+pub(crate) fn filters_narrow_window(config: &Config) -> bool {
+    config.kernel_only
+        || !config.units.is_empty()
+        || !config.identifiers.is_empty()
+        || !config.comms.is_empty()
+        || !config.pids.is_empty()
+        || !config.uids.is_empty()
+        || !config.cgroups.is_empty()
+        || !config.containers.is_empty()
+        || !config.matches.is_empty()
+        || !config.grep_terms.is_empty()
+        || !config.grep_any.is_empty()
+        || !config.exclude_terms.is_empty()
+        || !config.exclude_units.is_empty()
+        || (0..=EMERGENCY_MAX_PRIORITY).any(|p| !config.priority.contains(p))
+}
+
+/// 同一窗口（时间、启动周期、命名空间）但不带任何来源/关键词过滤的紧急级别查询
+fn emergency_check_command(config: &Config) -> Command {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager").arg("--quiet");
+    add_window_args(&mut cmd, config);
+    cmd.arg(format!("--priority=0..{EMERGENCY_MAX_PRIORITY}"))
+        .arg("--output=json")
+        .arg("--output-fields=PRIORITY");
+    cmd
+}
+
+/// 读取哪些日志：--namespace 命名空间、--directory 目录、--file 文件、--merge；每个 journalctl 调用都要带上
+pub(crate) fn add_journal_source_args(cmd: &mut Command, config: &Config) {
+    if let Some(namespace) = &config.namespace {
+        cmd.arg(format!("--namespace={namespace}"));
+    }
+    if let Some(directory) = &config.directory {
+        cmd.arg(format!("--directory={directory}"));
+    }
+    for file in &config.files {
+        cmd.arg(format!("--file={file}"));
+    }
+    if config.merge {
+        cmd.arg("--merge");
+    }
+}
+
+/// 只限定窗口本身：时间范围、日志来源与启动周期
+pub(crate) fn add_window_args(cmd: &mut Command, config: &Config) {
+    if let Some(since) = &config.since {
+        cmd.arg("--since").arg(since);
+    }
+    if let Some(until) = &config.until {
+        cmd.arg("--until").arg(until);
+    }
+    add_journal_source_args(cmd, config);
+    add_boot_args(cmd, config);
+}
+
+/// BootFilter::Several 此时应已解析为启动 ID；同一字段的多个匹配之间为“或”
+fn add_boot_args(cmd: &mut Command, config: &Config) {
+    match &config.boot {
+        BootFilter::Disabled => {}
+        BootFilter::Current => {
+            cmd.arg("--boot");
+        }
+        BootFilter::Value(value) => {
+            cmd.arg("--boot").arg(value);
+        }
+        BootFilter::Several(boot_ids) => {
+            for boot_id in boot_ids {
+                cmd.arg(format!("_BOOT_ID={boot_id}"));
+            }
+        }
+    }
+}
+
+/// 多个 --boot：按 `journalctl --list-boots` 把偏移和启动 ID 换成启动 ID，返回改写后的配置与
+/// 实际选中的偏移；不存在的启动周期跳过，一个都没有时报错。其他 BootFilter 返回 None
+fn resolve_boot_selection(config: &Config) -> Result<Option<(Config, Vec<i64>)>, String> {
+    let BootFilter::Several(wanted) = &config.boot else {
+        return Ok(None);
+    };
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager").arg("--list-boots");
+    add_journal_source_args(&mut cmd, config);
+    let output = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| format!("执行 journalctl --list-boots 失败：{e}"))?;
+    let selected = select_boots(
+        &parse_boot_list(&String::from_utf8_lossy(&output.stdout)),
+        wanted,
+    );
+    if selected.is_empty() {
+        return Err(format!(
+            "--boot {} 没有对应的启动周期\n修复：运行 logtool boots 查看可用的启动周期",
+            wanted.join(",")
+        ));
+    }
+    let mut resolved = config.clone();
+    resolved.boot = BootFilter::Several(selected.iter().map(|(_, id)| id.clone()).collect());
+    let offsets = selected.into_iter().map(|(offset, _)| offset).collect();
+    Ok(Some((resolved, offsets)))
+}
+
+/// wanted 中各项（偏移或启动 ID；与 journalctl 一致，正偏移从最早的启动数起，1 为第一次）
+/// 对应的启动周期，由旧到新
+pub fn select_boots(boots: &[(i64, String)], wanted: &[String]) -> Vec<(i64, String)> {
+    let mut boots = boots.to_vec();
+    boots.sort_by_key(|(offset, _)| *offset);
+    boots
+        .iter()
+        .enumerate()
+        .filter(|(position, (offset, id))| {
+            wanted.iter().any(|want| match want.parse::<i64>() {
+                Ok(n) if n > 0 => n as usize == position + 1,
+                Ok(n) => n == *offset,
+                Err(_) => id.eq_ignore_ascii_case(want),
+            })
+        })
+        .map(|(_, boot)| boot.clone())
+        .collect()
+}
+
+/// 紧急级别事件通常极少，这次额外查询开销很小；查询失败时按 0 处理，不影响主报告
+fn count_emergency_outside_filter(config: &Config, included: usize) -> usize {
+    let mut cmd = emergency_check_command(config);
+    if config.show_command {
+        eprintln!("执行命令：{}", render_command(&cmd));
+    }
+    let total = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map_or(0, |out| {
+            out.stdout
+                .split(|b| *b == b'\n')
+                .filter(|line| !line.trim_ascii().is_empty())
+                .count()
+        });
+    total.saturating_sub(included)
+}
+
+pub fn emergency_banner(count: usize) -> String {
+    format!(
+        "🚨 窗口内存在 {count} 条紧急级别事件未包含在当前过滤中\n   修复：去掉 --unit/--grep 等过滤，运行 logtool --priority {EMERGENCY_MAX_PRIORITY} 查看"
+    )
+}
+
+/// 记录一条示例消息。`samples` 按最近出现排列（末尾最新）；
+/// 超出上限时淘汰严重级别最低的条目，同级淘汰最早出现的。
+fn record_sample(
+    samples: &mut Vec<SampleMessage>,
+    message: &str,
+    priority: Option<u8>,
+    limit: usize,
+) {
+    if let Some(pos) = samples.iter().position(|s| s.message == message) {
+        let mut existing = samples.remove(pos);
+        existing.count += 1;
+        existing.priority = match (existing.priority, priority) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        samples.push(existing);
+        return;
+    }
+
+    samples.push(SampleMessage {
+        message: message.to_string(),
+        priority,
+        count: 1,
+    });
+    if samples.len() > limit {
+        let evict = samples
+            .iter()
+            .enumerate()
+            .max_by(|(ia, a), (ib, b)| {
+                a.priority
+                    .unwrap_or(7)
+                    .cmp(&b.priority.unwrap_or(7))
+                    .then_with(|| ib.cmp(ia))
+            })
+            .map(|(index, _)| index)
+            .unwrap_or(0);
+        samples.remove(evict);
+    }
+}
+
+/// 次数降序、同次数按消息排序，取前 TOP_MESSAGES 条
+fn top_message_counts(counts: HashMap<String, u64>) -> Vec<MessageCount> {
+    let mut messages = counts
+        .into_iter()
+        .map(|(message, count)| MessageCount { message, count })
+        .collect::<Vec<_>>();
+    messages.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.message.cmp(&b.message))
+    });
+    messages.truncate(TOP_MESSAGES);
+    messages
+}
+
+/// 输出顺序：严重级别高的在前，同级最近出现的在前
+fn order_samples(samples: &mut [SampleMessage]) {
+    samples.reverse();
+    samples.sort_by_key(|s| s.priority.unwrap_or(7));
+}
+
+fn sorted_rule_hits(hits: HashMap<String, u64>) -> Vec<RuleHit> {
+    let mut out = hits
+        .into_iter()
+        .map(|(id, count)| RuleHit { id, count })
+        .collect::<Vec<_>>();
+    out.sort_by(|a, b| a.id.cmp(&b.id));
+    out
+}
+
+/// 流模式：边读边写，每匹配一行立即通过 writer 发送 JSON StreamLine
+///
+/// 这是真正的流式实现——不缓冲到内存，支持 --follow 实时输出。
+/// writer 通常是 Unix Socket stream 或 stdout。
+/// 失败时先在流内发送带 error_kind 的结束行，调用方无需再回报错误。
+pub fn stream_journal_to_writer<W: Write>(config: &Config, writer: W) -> Result<(), String> {
+    stream_journal_live_to_writer(config, writer, None)
+}
+
+/// 同 stream_journal_to_writer；live 为 Some 时（--live）每行按其当前内容过滤，
+/// 控制消息由 spawn_stream_control_reader 在另一线程写入
+pub fn stream_journal_live_to_writer<W: Write>(
+    config: &Config,
+    mut writer: W,
+    live: Option<&Mutex<LiveFilters>>,
+) -> Result<(), String> {
+    match stream_journal(config, &mut writer, live) {
+        Ok(()) => Ok(()),
+        Err(failure) => {
+            if failure.kind != StreamErrorKind::Output {
+                let _ = write_json_line(&mut writer, &failure.to_line(), "流错误");
+            }
+            Err(failure.message)
+        }
+    }
+}
+
+fn stream_journal<W: Write>(
+    config: &Config,
+    writer: &mut W,
+    live: Option<&Mutex<LiveFilters>>,
+) -> Result<(), StreamFailure> {
+    let unavailable = |message| StreamFailure::new(StreamErrorKind::JournalctlUnavailable, message);
+    ensure_journalctl_exists().map_err(unavailable)?;
+    let resolved = resolve_boot_selection(config).map_err(unavailable)?;
+    let config = resolved.as_ref().map_or(config, |(resolved, _)| resolved);
+
+    let mut cmd = build_journalctl_command_for_stream(config);
+    if config.show_command {
+        eprintln!("执行命令：{}", render_command(&cmd));
+    }
+
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| unavailable(format!("启动 journalctl 失败：{err}")))?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| unavailable("无法获取 journalctl 标准输出".to_string()))?;
+    // 标准错误在后台线程中读取，避免管道写满阻塞 journalctl
+    let stderr_reader = child
+        .stderr
+        .take()
+        .map(|stderr| thread::spawn(move || read_stderr_tail(stderr, STREAM_STDERR_TAIL_BYTES)));
+
+    let reader = BufReader::new(stdout);
+    let mut lines_written = 0usize;
+    let mut stream_error: Option<StreamFailure> = None;
+    let redactor = config.redact.then(Redactor::from_system);
+    let offset_secs = if live.is_some() {
+        timeutil::local_utc_offset_secs()
+    } else {
+        0
+    };
+    // --dedup：上一条输出行的键，以及其后被折叠的行数
+    let mut previous_key: Option<String> = None;
+    let mut repeats = 0usize;
+
+    for maybe_line in reader.lines() {
+        let line = match maybe_line {
+            Ok(line) => line,
+            Err(err) => {
+                stream_error = Some(StreamFailure::new(
+                    StreamErrorKind::Read,
+                    format!("读取 journalctl 输出失败：{}", io_error_to_string(err)),
+                ));
+                break;
+            }
+        };
+        // 转换为 logfmt 等格式之前取键：原始行为 JSON 或 short-iso 文本
+        let key = config.dedup.then(|| stream_dedup_key(&line));
+        let line = match live {
+            Some(live) => filter_live_stream_line(line, config, live, offset_secs),
+            None => filter_stream_line(line, config),
+        };
+        let Some(line) = line else {
+            continue;
+        };
+        if let Some(key) = key {
+            if previous_key.as_ref() == Some(&key) {
+                repeats += 1;
+                continue;
+            }
+            previous_key = Some(key);
+            if let Err(err) = flush_repeats(writer, &mut repeats, config) {
+                stream_error = Some(err);
+                break;
+            }
+        }
+
+        let line = match &redactor {
+            Some(redactor) => redactor.redact(&line),
+            None => line,
+        };
+        let line = match config.max_line_bytes {
+            Some(limit) => truncate_stream_line(line, limit, config.output_json),
+            None => line,
+        };
+        let msg = StreamLine {
+            line,
+            ..StreamLine::default()
+        };
+        if let Err(err) = write_json_line(writer, &msg, "流消息") {
+            stream_error = Some(StreamFailure::new(StreamErrorKind::Output, err));
+            break;
+        }
+
+        lines_written += 1;
+
+        if reached_limit(lines_written, config.max_lines) {
+            break;
+        }
+    }
+
+    let reached_max_lines = reached_limit(lines_written, config.max_lines);
+    let mut killed_by_tool = false;
+    if (reached_max_lines || stream_error.is_some()) && child.kill().is_ok() {
+        killed_by_tool = true;
+    }
+
+    let status = child
+        .wait()
+        .map_err(|err| StreamFailure::new(StreamErrorKind::Read, io_error_to_string(err)))?;
+    let stderr = stderr_reader
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default();
+    if let Some(err) = stream_error {
+        return Err(err);
+    }
+    flush_repeats(writer, &mut repeats, config)?;
+
+    if !status.success()
+        && !killed_by_tool
+        && !status_killed_by_limit(lines_written, config.max_lines)
+    {
+        let stderr = stderr.trim();
+        let mut message = format!("journalctl 退出状态异常：{status}");
+        if let Some(last) = stderr.lines().rev().find(|l| !l.trim().is_empty()) {
+            message.push_str(&format!("：{}", last.trim()));
+        }
+        return Err(StreamFailure {
+            kind: StreamErrorKind::JournalctlExit,
+            message,
+            exit_code: status.code(),
+            stderr: (!stderr.is_empty()).then(|| stderr.to_string()),
+        });
+    }
+
+    let done_msg = StreamLine {
+        done: true,
+        ..StreamLine::default()
+    };
+    write_json_line(writer, &done_msg, "结束标记")
+        .map_err(|err| StreamFailure::new(StreamErrorKind::Output, err))?;
+
+    Ok(())
+}
+
+/// 比较连续重复用的键：主机、来源字段与消息相同即视为重复
+fn dedup_key(event: &JournalEvent) -> String {
+    let fields = [&event.hostname, &event.unit, &event.identifier, &event.comm];
+    let mut key = String::new();
+    for field in fields {
+        key.push_str(field.as_deref().unwrap_or_default());
+        key.push('\u{1f}');
+    }
+    key.push_str(&event.message);
+    key
+}
+
+/// JSON 行按字段取键；short-iso 文本行去掉开头的时间戳
+fn stream_dedup_key(line: &str) -> String {
+    if line.starts_with('{')
+        && let Ok(event) = parse_json_event(line)
+    {
+        return dedup_key(&event);
+    }
+    line.split_once(' ')
+        .map_or(line, |(_, rest)| rest)
+        .to_string()
+}
+
+/// 有被折叠的行时先输出一条 `… repeated N times`（JSON / logfmt 输出保持同样的行格式）
+fn flush_repeats<W: Write>(
+    writer: &mut W,
+    repeats: &mut usize,
+    config: &Config,
+) -> Result<(), StreamFailure> {
+    if *repeats == 0 {
+        return Ok(());
+    }
+    let msg = StreamLine {
+        line: repeat_marker(*repeats, config),
+        ..StreamLine::default()
+    };
+    *repeats = 0;
+    write_json_line(writer, &msg, "流消息")
+        .map_err(|err| StreamFailure::new(StreamErrorKind::Output, err))
+}
+
+fn repeat_marker(repeats: usize, config: &Config) -> String {
+    let text = format!("… repeated {repeats} times");
+    #[cfg(feature = "exporters")]
+    if config.format == ReportFormat::Logfmt {
+        return format_logfmt_event(&JournalEvent {
+            message: text,
+            ..JournalEvent::default()
+        });
+    }
+    if config.output_json {
+        return serde_json::json!({ "MESSAGE": text, "LOGTOOL_REPEATED": repeats.to_string() })
+            .to_string();
+    }
+    text
+}
+
+/// 按启动时的条件过滤一行 journalctl 输出并转换为目标格式；None 表示丢弃
+fn filter_stream_line(line: String, config: &Config) -> Option<String> {
+    if !matches_filters(&line, config) {
+        return None;
+    }
+    // validate_config 保证此时输出为 JSON
+    if !config.exclude_units.is_empty()
+        && parse_json_event(&line)
+            .is_ok_and(|event| unit_is_excluded(event.unit.as_deref(), config))
+    {
+        return None;
+    }
+    #[cfg(feature = "exporters")]
+    if config.format == ReportFormat::Logfmt {
+        return parse_json_event(&line)
+            .ok()
+            .map(|event| format_logfmt_event(&event));
+    }
+    Some(line)
+}
+
+/// --live：journalctl 以 JSON 输出全部级别，关键词与优先级按 live 的当前值过滤；
+/// --grep-any、--exclude、--exclude-unit 仍按启动时的条件
+fn filter_live_stream_line(
+    line: String,
+    config: &Config,
+    live: &Mutex<LiveFilters>,
+    offset_secs: i64,
+) -> Option<String> {
+    let event = parse_json_event(&line).ok()?;
+    let text = event_search_text(&event, TextFold::from_config(config));
+    let passes = live
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .passes(&event, &text);
+    if !passes || is_excluded(&text, config) || unit_is_excluded(event.unit.as_deref(), config) {
+        return None;
+    }
+    if config.output_json {
+        return Some(line);
+    }
+    #[cfg(feature = "exporters")]
+    if config.format == ReportFormat::Logfmt {
+        return Some(format_logfmt_event(&event));
+    }
+    Some(format_last_words_line(&event, offset_secs))
+}
+
+// ── 流会话控制（--live）─────────────────────────────────────────────
+
+/// 客户端在请求行之后发送的控制消息，每行一个，如 `{"control":"add_grep","term":"timeout"}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "control", rename_all = "snake_case")]
+pub enum StreamControl {
+    AddGrep { term: String },
+    RemoveGrep { term: String },
+    SetPriority { priority: Priority },
+}
+
+/// --live 会话中可被控制消息修改的过滤条件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveFilters {
+    /// 全部命中才输出（已按 fold 折叠）
+    pub grep_terms: Vec<String>,
+    pub priority: Priority,
+    pub fold: TextFold,
+    /// 启动时的 --grep-any 与 --invert-match，与 grep_terms 一起判断
+    pub grep_any: Vec<String>,
+    pub invert: bool,
+}
+
+impl LiveFilters {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            grep_terms: config.grep_terms.clone(),
+            priority: config.priority,
+            fold: TextFold::from_config(config),
+            grep_any: config.grep_any.clone(),
+            invert: config.invert_match,
+        }
+    }
+
+    pub fn apply(&mut self, control: StreamControl) {
+        match control {
+            StreamControl::AddGrep { term } => {
+                let term = self.fold.apply(term);
+                if !term.is_empty() && !self.grep_terms.contains(&term) {
+                    self.grep_terms.push(term);
+                }
+            }
+            StreamControl::RemoveGrep { term } => {
+                let term = self.fold.apply(term);
+                self.grep_terms.retain(|t| *t != term);
+            }
+            StreamControl::SetPriority { priority } => self.priority = priority,
+        }
+    }
+
+    /// 与 journalctl --priority 一致：没有 PRIORITY 字段的事件不输出；text 为 event_search_text
+    fn passes(&self, event: &JournalEvent, text: &str) -> bool {
+        event.priority.is_some_and(|p| self.priority.contains(p))
+            && terms_pass(text, &self.grep_terms, &self.grep_any, self.invert)
+    }
+}
+
+/// 控制消息单行上限；超出或无法解析时结束读取，不影响正在进行的流
+const STREAM_CONTROL_MAX_BYTES: usize = 4096;
+
+/// 在后台线程中逐行读取控制消息并应用到 live；读到 EOF、出错或无效消息时退出
+pub fn spawn_stream_control_reader<R: BufRead + Send + 'static>(
+    mut reader: R,
+    live: Arc<Mutex<LiveFilters>>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        loop {
+            let mut line = Vec::new();
+            let read = (&mut reader)
+                .take(STREAM_CONTROL_MAX_BYTES as u64 + 1)
+                .read_until(b'\n', &mut line);
+            match read {
+                Ok(0) | Err(_) => return,
+                Ok(_) if line.len() > STREAM_CONTROL_MAX_BYTES => return,
+                Ok(_) => {}
+            }
+            let line = String::from_utf8_lossy(&line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Ok(control) = serde_json::from_str::<StreamControl>(line.trim()) else {
+                return;
+            };
+            live.lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .apply(control);
+        }
+    })
+}
+
+/// 终端输入 → 控制消息：`+关键词` 添加、`-关键词` 移除、`p <级别>` 修改优先级（如 `p 4`、`p 2..4`）
+pub fn parse_live_command(input: &str) -> Result<StreamControl, String> {
+    let input = input.trim();
+    let usage = "修复：输入 +关键词 添加、-关键词 移除、p <级别> 修改优先级（如 p 4 或 p 2..4）";
+    if let Some(term) = input.strip_prefix('+') {
+        let term = term.trim();
+        if term.is_empty() {
+            return Err(format!("缺少关键词\n{usage}"));
+        }
+        return Ok(StreamControl::AddGrep {
+            term: term.to_string(),
+        });
+    }
+    if let Some(term) = input.strip_prefix('-') {
+        let term = term.trim();
+        if term.is_empty() {
+            return Err(format!("缺少关键词\n{usage}"));
+        }
+        return Ok(StreamControl::RemoveGrep {
+            term: term.to_string(),
+        });
+    }
+    if let Some(level) = input
+        .strip_prefix("p ")
+        .or_else(|| input.strip_prefix("priority "))
+    {
+        return Ok(StreamControl::SetPriority {
+            priority: level.trim().parse()?,
+        });
+    }
+    Err(format!("无法识别的指令：{input}\n{usage}"))
+}
+
+/// 读完子进程标准错误，只保留末尾 limit 字节
+fn read_stderr_tail<R: Read>(mut reader: R, limit: usize) -> String {
+    let mut tail: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                tail.extend_from_slice(&chunk[..n]);
+                if tail.len() > limit {
+                    tail.drain(..tail.len() - limit);
+                }
+            }
+        }
+    }
+    String::from_utf8_lossy(&tail).into_owned()
+}
+
+// ── JSON 解析 ─────────────────────────────────────────────
+
+pub fn parse_json_event(line: &str) -> Result<JournalEvent, String> {
+    let value: Value = serde_json::from_str(line).map_err(|err| err.to_string())?;
+    let object = value
+        .as_object()
+        .ok_or_else(|| "日志 JSON 行不是对象".to_string())?;
+
+    let message = field_as_string(object, "MESSAGE").unwrap_or_default();
+    let priority = field_as_string(object, "PRIORITY").and_then(|p| p.parse::<u8>().ok());
+    let unit = field_as_string(object, "_SYSTEMD_UNIT");
+    let exe = field_as_string(object, "_EXE");
+    let comm = field_as_string(object, "_COMM");
+    let identifier = field_as_string(object, "SYSLOG_IDENTIFIER");
+    let timestamp_us =
+        field_as_string(object, "__REALTIME_TIMESTAMP").and_then(|t| t.parse::<u64>().ok());
+    let hostname = field_as_string(object, "_HOSTNAME");
+    let cgroup = field_as_string(object, "_SYSTEMD_CGROUP");
+    let container = field_as_string(object, "CONTAINER_NAME");
+    let uid = field_as_string(object, "_UID").and_then(|u| u.parse::<u32>().ok());
+    let boot_id = field_as_string(object, "_BOOT_ID");
+
+    Ok(JournalEvent {
+        message,
+        priority,
+        unit,
+        exe,
+        comm,
+        identifier,
+        timestamp_us,
+        hostname,
+        cgroup,
+        container,
+        uid,
+        boot_id,
+    })
+}
+
+/// 解析样本文件中的一行：journal JSON 对象按字段解析，其余按纯文本消息处理
+pub fn parse_sample_line(line: &str) -> Option<JournalEvent> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if trimmed.starts_with('{')
+        && let Ok(event) = parse_json_event(trimmed)
+    {
+        return Some(event);
+    }
+    Some(JournalEvent {
+        message: trimmed.to_string(),
+        ..JournalEvent::default()
+    })
+}
+
+/// 按分析参数读取最近的 journal 事件（供规则测试等本地命令使用）
+pub fn read_journal_events(config: &Config) -> Result<Vec<JournalEvent>, String> {
+    ensure_journalctl_exists()?;
+    let mut cmd = build_journalctl_command_for_analysis(config);
+    if let Some(max) = config.max_lines {
+        cmd.arg(format!("--lines={max}"));
+    }
+    let output = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|err| format!("启动 journalctl 失败：{err}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(format!("journalctl 执行失败：{stderr}"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| parse_json_event(line).ok())
+        .collect())
+}
+
+fn field_as_string(map: &Map<String, Value>, key: &str) -> Option<String> {
+    let raw = map.get(key)?;
+    value_to_string(raw).and_then(normalize_optional)
+}
+
+fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Array(arr) => decode_byte_array(arr),
+        _ => None,
+    }
+}
+
+fn decode_byte_array(arr: &[Value]) -> Option<String> {
+    let mut bytes = Vec::with_capacity(arr.len());
+    for item in arr {
+        let n = item.as_u64()?;
+        let byte = u8::try_from(n).ok()?;
+        bytes.push(byte);
+    }
+
+    String::from_utf8(bytes).ok().and_then(normalize_optional)
+}
+
+fn normalize_optional(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    Some(trimmed.to_string())
+}
+
+// ── 过滤与分类 ─────────────────────────────────────────────
+
+/// 关键词过滤在消息与来源字段上进行（除非 --case-sensitive，否则大小写不敏感）
+pub fn event_matches_filters(event: &JournalEvent, config: &Config) -> bool {
+    if config.grep_terms.is_empty() && config.grep_any.is_empty() {
+        return true;
+    }
+    term_filters_pass(
+        &event_search_text(event, TextFold::from_config(config)),
+        config,
+    )
+}
+
+/// --exclude：与 --grep 在同样的字段上匹配；--exclude-unit：按 _SYSTEMD_UNIT 通配匹配
+pub fn event_is_excluded(event: &JournalEvent, config: &Config) -> bool {
+    unit_is_excluded(event.unit.as_deref(), config)
+        || (!config.exclude_terms.is_empty()
+            && is_excluded(
+                &event_search_text(event, TextFold::from_config(config)),
+                config,
+            ))
+}
+
+fn unit_is_excluded(unit: Option<&str>, config: &Config) -> bool {
+    unit.is_some_and(|unit| {
+        config
+            .exclude_units
+            .iter()
+            .any(|pattern| wildcard::wildcard_match(pattern, unit))
+    })
+}
+
+/// 与 journalctl --unit 一致：不含 `.` 与通配符的名称补全为 `.service`
+fn normalize_unit_pattern(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("--exclude-unit 缺少单元名\n修复：如 --exclude-unit snapd.service 或 --exclude-unit 'snap.*'".to_string());
+    }
+    if value.contains(['.', '*', '?']) {
+        Ok(value.to_string())
+    } else {
+        Ok(format!("{value}.service"))
+    }
+}
+
+fn event_search_text(event: &JournalEvent, fold: TextFold) -> String {
+    let mut text = String::new();
+    text.push_str(&event.message);
+    for field in [&event.unit, &event.exe, &event.comm, &event.identifier]
+        .into_iter()
+        .flatten()
+    {
+        text.push(' ');
+        text.push_str(field);
+    }
+    fold.apply(text)
+}
+
+fn is_excluded(text: &str, config: &Config) -> bool {
+    config.exclude_terms.iter().any(|term| text.contains(term))
+}
+
+fn term_filters_pass(text: &str, config: &Config) -> bool {
+    terms_pass(
+        text,
+        &config.grep_terms,
+        &config.grep_any,
+        config.invert_match,
+    )
+}
+
+/// all 全部命中，且 any 为空或至少命中一个；invert 时取反。两组都为空时不过滤。
+/// text 已按 --case-sensitive / --fold-accents 折叠
+fn terms_pass(text: &str, all: &[String], any: &[String], invert: bool) -> bool {
+    if all.is_empty() && any.is_empty() {
+        return true;
+    }
+    let matched = all.iter().all(|term| text.contains(term))
+        && (any.is_empty() || any.iter().any(|term| text.contains(term)));
+    matched != invert
+}
+
+pub fn classify_source(event: &JournalEvent) -> (SourceKind, String) {
+    if let Some(id) = &event.identifier
+        && id == "kernel"
+    {
+        if let Some(device) = storage::storage_device(&event.message) {
+            return (SourceKind::Storage, device);
+        }
+        if let Some(driver) = gpu::gpu_driver(&event.message) {
+            return (SourceKind::Gpu, driver.to_string());
+        }
+        return (SourceKind::Kernel, "kernel".to_string());
+    }
+
+    // 经 journald 日志驱动转发的容器日志，_SYSTEMD_UNIT / _EXE 是 docker.service / dockerd 等运行时
+    if let Some(container) = event.container_source() {
+        return (SourceKind::Container, container);
+    }
+
+    if let Some(unit) = &event.unit {
+        return (SourceKind::Unit, unit.clone());
+    }
+
+    if let Some(exe) = &event.exe {
+        return (SourceKind::Executable, exe.clone());
+    }
+
+    if let Some(identifier) = &event.identifier {
+        return (SourceKind::Identifier, identifier.clone());
+    }
+
+    if let Some(comm) = &event.comm {
+        return (SourceKind::Comm, comm.clone());
+    }
+
+    (SourceKind::Unknown, "unknown".to_string())
+}
+
+/// 按 --group-by 指定的字段归并；事件缺少该字段时按默认次序。内核事件始终归为内核或存储设备，
+/// package 维度由 SourceGrouper 先行反查
+pub fn classify_source_by(event: &JournalEvent, group_by: GroupBy) -> (SourceKind, String) {
+    let chosen = match group_by {
+        GroupBy::Auto | GroupBy::Package => None,
+        GroupBy::Unit => event.unit.clone().map(|unit| (SourceKind::Unit, unit)),
+        GroupBy::Exe => event.exe.clone().map(|exe| (SourceKind::Executable, exe)),
+        GroupBy::Identifier => event
+            .identifier
+            .clone()
+            .filter(|id| id != "kernel")
+            .map(|id| (SourceKind::Identifier, id)),
+        GroupBy::Comm => event.comm.clone().map(|comm| (SourceKind::Comm, comm)),
+    };
+    chosen.unwrap_or_else(|| classify_source(event))
+}
+
+/// 分析循环中的来源归并；package 维度时持有包反查缓存，同一可执行文件或单元只查一次
+struct SourceGrouper {
+    group_by: GroupBy,
+    #[cfg(feature = "packages")]
+    packages: Option<packages::EventPackages>,
+}
+
+impl SourceGrouper {
+    fn new(group_by: GroupBy) -> Self {
+        Self {
+            group_by,
+            #[cfg(feature = "packages")]
+            packages: (group_by == GroupBy::Package).then(packages::EventPackages::new),
+        }
+    }
+
+    fn classify(&mut self, event: &JournalEvent) -> (SourceKind, String) {
+        #[cfg(feature = "packages")]
+        if let Some(packages) = &mut self.packages
+            && let Some(package) = packages.package_of(event)
+        {
+            return (SourceKind::Package, package);
+        }
+        classify_source_by(event, self.group_by)
+    }
+}
+
+/// 全序：事件数降序 → 级别 → 名称 → 类型 → 包名，保证每次运行输出顺序一致
+fn compare_suspects(left: &SourceStats, right: &SourceStats) -> Ordering {
+    right
+        .count
+        .cmp(&left.count)
+        .then(left.worst_priority.cmp(&right.worst_priority))
+        .then_with(|| left.source.cmp(&right.source))
+        .then(left.kind.cmp(&right.kind))
+        .then_with(|| left.package.cmp(&right.package))
+}
+
+/// 被规则降级到查询级别之外的来源排到最后（稳定排序，组内顺序不变）
+/// 窗口内全部主机；只有一台（或事件都没有 _HOSTNAME）时清空各来源的主机统计，
+/// 单机报告与 JSON 保持原样
+fn collect_hosts(suspects: &mut [SourceStats]) -> Vec<String> {
+    let hosts = suspects
+        .iter()
+        .flat_map(|s| s.hosts.iter().map(|h| h.host.clone()))
+        .collect::<BTreeSet<_>>();
+    for suspect in suspects.iter_mut() {
+        if hosts.len() <= 1 {
+            suspect.hosts.clear();
+        } else {
+            suspect
+                .hosts
+                .sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.host.cmp(&b.host)));
+        }
+    }
+    if hosts.len() <= 1 {
+        Vec::new()
+    } else {
+        hosts.into_iter().collect()
+    }
+}
+
+/// `守护进程 3 分 20 秒前预先计算的结果（--prefetch），加 --fresh 重新分析`
+fn cached_summary(at: u64, now: u64) -> String {
+    let age = now.saturating_sub(at);
+    let age = if age < 60 {
+        "刚刚".to_string()
+    } else {
+        format!(" {} 前", timeutil::format_duration_cn(age))
+    };
+    format!("守护进程{age}预先计算的结果（--prefetch），加 --fresh 重新分析")
+}
+
+/// `-3、-2、-1、0`
+fn boot_offsets_label(offsets: &[i64]) -> String {
+    offsets
+        .iter()
+        .map(i64::to_string)
+        .collect::<Vec<_>>()
+        .join("、")
+}
+
+/// `web-1 ×30、web-2 ×10 等 5 台`
+pub fn host_breakdown(hosts: &[HostCount]) -> String {
+    let mut text = hosts
+        .iter()
+        .take(HOSTS_SHOWN)
+        .map(|h| format!("{} ×{}", h.host, h.count))
+        .collect::<Vec<_>>()
+        .join("、");
+    if hosts.len() > HOSTS_SHOWN {
+        text.push_str(&format!(" 等 {} 台", hosts.len()));
+    }
+    text
+}
+
+fn rank_suspects(suspects: &mut [SourceStats], threshold: u8) {
+    suspects.sort_by(compare_suspects);
+    suspects.sort_by_key(|s| s.worst_priority > threshold);
+}
+
+// ── 跨启动周期出现情况 ─────────────────────────────────────────────
+
+fn annotate_boot_presence(suspects: &mut [SourceStats], config: &Config) {
+    let offsets = match list_recent_boot_offsets(config, RECENT_BOOTS_CHECKED) {
+        Some(offsets) if !offsets.is_empty() => offsets,
+        _ => return,
+    };
+
+    for suspect in suspects.iter_mut().take(config.top) {
+        let Some(field_match) = suspect_journal_match(suspect.kind, &suspect.source) else {
+            continue;
+        };
+
+        let mut seen = 0usize;
+        for offset in &offsets {
+            let mut cmd = boot_presence_command(config, *offset, &field_match);
+            let produced = cmd
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .output()
+                .map(|out| out.status.success() && !out.stdout.trim_ascii().is_empty())
+                .unwrap_or(false);
+            if produced {
+                seen += 1;
+            }
+        }
+
+        suspect.boot_presence = Some(BootPresence {
+            seen,
+            checked: offsets.len(),
+        });
+    }
+}
+
+fn list_recent_boot_offsets(config: &Config, limit: usize) -> Option<Vec<i64>> {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager").arg("--list-boots");
+    add_journal_source_args(&mut cmd, config);
+    let output = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let mut offsets = parse_boot_offsets(&String::from_utf8_lossy(&output.stdout));
+    // journalctl 按时间正序输出，最近的启动在末尾
+    offsets.sort_unstable_by(|a, b| b.cmp(a));
+    offsets.truncate(limit);
+    Some(offsets)
+}
+
+/// 解析 `journalctl --list-boots` 输出中的相对偏移列（0、-1、-2…），忽略表头。
+pub fn parse_boot_offsets(output: &str) -> Vec<i64> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter_map(|token| token.parse::<i64>().ok())
+        .filter(|offset| *offset <= 0)
+        .collect()
+}
+
+/// 解析 `journalctl --list-boots` 输出中的 (偏移, 启动 ID)，忽略表头
+pub fn parse_boot_list(output: &str) -> Vec<(i64, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace();
+            let offset = columns.next()?.parse::<i64>().ok()?;
+            let id = columns.next()?;
+            Some((offset, id.to_string()))
+        })
+        .collect()
+}
+
+// ── 最后的日志（last-words） ─────────────────────────────────────────────
+
+/// 正常关机/重启时 journald 写下的最后几类消息
+const CLEAN_SHUTDOWN_MARKERS: &[&str] = &[
+    "journal stopped",
+    "systemd-shutdown",
+    "reached target power-off",
+    "reached target system power off",
+    "reached target system reboot",
+    "reached target reboot",
+    "reached target system halt",
+    "reached target final step",
+];
+
+/// 最后几条日志中是否出现正常关机的迹象；没有时更可能是死机、断电或内核崩溃
+pub fn looks_like_clean_shutdown(events: &[JournalEvent]) -> bool {
+    events.iter().rev().take(20).any(|event| {
+        let lower = event.message.to_ascii_lowercase();
+        CLEAN_SHUTDOWN_MARKERS
+            .iter()
+            .any(|marker| lower.contains(marker))
+            || event.identifier.as_deref() == Some("systemd-shutdown")
+    })
+}
+
+/// `2026-10-13 23:58:01 [err   ] NetworkManager: message`
+pub fn format_last_words_line(event: &JournalEvent, offset_secs: i64) -> String {
+    let time = event
+        .timestamp_us
+        .map(|ts| timeutil::format_local_second(ts, offset_secs))
+        .unwrap_or_else(|| "????-??-?? ??:??:??".to_string());
+    let level = event.priority.map_or("-", priority_keyword);
+    let source = event
+        .identifier
+        .as_deref()
+        .or(event.comm.as_deref())
+        .or(event.unit.as_deref())
+        .unwrap_or("?");
+    format!("{time} [{level:<6}] {source}: {}", event.message)
+}
+
+/// 可疑来源对应的 journal 字段匹配（用于按启动周期回查）
+pub fn suspect_journal_match(kind: SourceKind, source: &str) -> Option<String> {
+    match kind {
+        SourceKind::Unit => Some(format!("_SYSTEMD_UNIT={source}")),
+        SourceKind::Executable => Some(format!("_EXE={source}")),
+        SourceKind::Identifier => Some(format!("SYSLOG_IDENTIFIER={source}")),
+        SourceKind::Comm => Some(format!("_COMM={source}")),
+        SourceKind::Kernel => Some("_TRANSPORT=kernel".to_string()),
+        // 只从 cgroup 得到容器 ID 的来源没有可匹配的字段
+        SourceKind::Container if is_container_id(source) => None,
+        SourceKind::Container => Some(format!("CONTAINER_NAME={source}")),
+        // 设备名、驱动名只出现在消息文本中
+        SourceKind::Rule
+        | SourceKind::Storage
+        | SourceKind::Gpu
+        | SourceKind::Package
+        | SourceKind::Unknown => None,
+    }
+}
+
+fn boot_presence_command(config: &Config, offset: i64, field_match: &str) -> Command {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager")
+        .arg("--quiet")
+        .arg(format!("--boot={offset}"))
+        .arg(format!("--priority={}", config.priority))
+        .arg("--lines=1")
+        .arg("--output=cat");
+    add_journal_source_args(&mut cmd, config);
+    cmd.arg(field_match);
+    cmd
+}
+
+// ── 环境快照 ─────────────────────────────────────────────
+
+pub fn collect_environment_snapshot() -> EnvironmentSnapshot {
+    let captured_at = timeutil::unix_now_secs();
+
+    let kernel = fs::read_to_string("/proc/sys/kernel/osrelease")
+        .ok()
+        .and_then(normalize_optional);
+    let os_release = fs::read_to_string("/etc/os-release")
+        .ok()
+        .and_then(|text| parse_os_release_pretty_name(&text));
+    let systemd_version = Command::new("systemctl")
+        .arg("--version")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| {
+            String::from_utf8_lossy(&out.stdout)
+                .lines()
+                .next()
+                .map(|line| line.to_string())
+        })
+        .and_then(normalize_optional);
+
+    EnvironmentSnapshot {
+        captured_at,
+        logtool_version: env!("CARGO_PKG_VERSION").to_string(),
+        kernel,
+        os_release,
+        systemd_version,
+        ..EnvironmentSnapshot::default()
+    }
+}
+
+/// `已知问题库 24 条；自定义规则 nginx-upstream、disk-full`
+fn enabled_rules_cn(env: &EnvironmentSnapshot) -> String {
+    let presets = if env.presets.is_empty() {
+        "未启用已知问题库".to_string()
+    } else {
+        format!("已知问题库 {} 条", env.presets.len())
+    };
+    let rules = if env.rules.is_empty() {
+        "无自定义规则".to_string()
+    } else {
+        format!("自定义规则 {}", env.rules.join("、"))
+    };
+    format!("{presets}；{rules}")
+}
+
+fn parse_os_release_pretty_name(text: &str) -> Option<String> {
+    let value = text
+        .lines()
+        .find_map(|line| line.strip_prefix("PRETTY_NAME="))?;
+    normalize_optional(value.trim_matches('"').to_string())
+}
+
+// ── 中文输出格式化 ─────────────────────────────────────────────
+
+const REPORT_RULE: &str = "═══════════════════════════════════════════════════════════════";
+
+pub fn print_analysis_report(response: &AnalyzeResponse) {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let _ = write_analysis_report(&mut out, response);
+}
+
+/// 将中文分析报告写入任意 writer（stdout、文件缓冲区等）
+pub fn write_analysis_report<W: Write>(out: &mut W, response: &AnalyzeResponse) -> io::Result<()> {
+    let metrics = &response.metrics;
+    let suspects = &response.suspects;
+    let top = response.top;
+
+    if metrics.emergency_outside_filter > 0 {
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(
+            out,
+            "{}",
+            emergency_banner(metrics.emergency_outside_filter)
+        )?;
+    }
+    if let Some(banner) = hardware::hardware_banner(&response.hardware) {
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "🔥 {banner}")?;
+    }
+    writeln!(out, "{REPORT_RULE}")?;
+    writeln!(out, "                      📋 事件摘要")?;
+    writeln!(out, "{REPORT_RULE}")?;
+    if let Some(namespace) = &response.namespace {
+        writeln!(out, "  命名空间    ：{namespace}")?;
+    }
+    if let Some(directory) = &response.directory {
+        writeln!(out, "  日志目录    ：{directory}")?;
+    }
+    if !response.files.is_empty() {
+        writeln!(out, "  日志文件    ：{}", response.files.join("、"))?;
+    }
+    if !response.syslog_files.is_empty() {
+        writeln!(out, "  syslog 文件 ：{}", response.syslog_files.join("、"))?;
+    }
+    if let Some(fallback) = &response.kernel_fallback {
+        writeln!(
+            out,
+            "  日志来源    ：内核缓冲区（{}），{}",
+            fallback.source,
+            kmsg::reason_label(fallback.reason)
+        )?;
+    }
+    if !response.boots.is_empty() {
+        writeln!(
+            out,
+            "  启动周期    ：{} 个（{}）",
+            response.boots.len(),
+            boot_offsets_label(&response.boots)
+        )?;
+    }
+    if !response.hosts.is_empty() {
+        writeln!(
+            out,
+            "  主机        ：{} 台（{}）",
+            response.hosts.len(),
+            response.hosts.join("、")
+        )?;
+    }
+    if let Some(truncation) = &response.truncated {
+        writeln!(out, "  ✂️ {}", truncation_summary(truncation))?;
+    }
+    if let Some(at) = response.cached_at {
+        writeln!(
+            out,
+            "  ⏱️ {}",
+            cached_summary(at, timeutil::unix_now_secs())
+        )?;
+    }
+    if !response.reboots.is_empty() {
+        writeln!(
+            out,
+            "  🔁 {}",
+            reboots::reboot_summary(&response.reboots, timeutil::local_utc_offset_secs())
+        )?;
+    }
+    writeln!(out, "  读取行数    ：{}", metrics.lines_read)?;
+    writeln!(out, "  解析成功    ：{}", metrics.parsed_ok)?;
+    writeln!(out, "  匹配条数    ：{}", metrics.matched)?;
+    writeln!(out, "  解析错误    ：{}", metrics.parse_errors)?;
+    writeln!(out, "  独立来源    ：{}", suspects.len())?;
+    if metrics.in_maintenance > 0 {
+        writeln!(
+            out,
+            "  维护窗口内  ：{}（已计入匹配条数）",
+            metrics.in_maintenance
+        )?;
+    }
+    if metrics.excluded > 0 {
+        writeln!(
+            out,
+            "  已排除      ：{}（--exclude / --exclude-unit，未计入匹配条数）",
+            metrics.excluded
+        )?;
+    }
+    if metrics.duplicates > 0 {
+        writeln!(
+            out,
+            "  连续重复    ：{}（--dedup 已折叠，未计入匹配条数）",
+            metrics.duplicates
+        )?;
+    }
+
+    if suspects.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "  ✅ 当前过滤条件下未发现可疑来源。")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        return Ok(());
+    }
+
+    writeln!(out)?;
+    writeln!(out, "{REPORT_RULE}")?;
+    writeln!(out, "                    🔍 可疑来源排行")?;
+    writeln!(out, "{REPORT_RULE}")?;
+
+    for (index, suspect) in suspects.iter().take(top).enumerate() {
+        let label = source_label_cn(suspect.kind);
+        let priority_text = priority_label_cn(suspect.worst_priority);
+
+        writeln!(out)?;
+        writeln!(
+            out,
+            "  {}. [{}] {} | 事件数={} | 最高严重级别={}({})",
+            index + 1,
+            label,
+            suspect.source,
+            suspect.count,
+            suspect.worst_priority,
+            priority_text
+        )?;
+        if suspect.kind == SourceKind::Storage {
+            writeln!(out, "     硬件风险：{}", storage::HARDWARE_RISK)?;
+        }
+        if let Some(health) = smart::health_for(&response.smart, suspect) {
+            writeln!(out, "     SMART   ：{}", health.verdict())?;
+        }
+        if suspect.kind == SourceKind::Gpu {
+            writeln!(out, "     驱动风险：{}", gpu::DRIVER_RISK)?;
+        }
+        if suspect.unit_failed {
+            writeln!(
+                out,
+                "     当前状态：所属单元处于失败状态（systemctl --failed）"
+            )?;
+        }
+        let (causes, dependents) = dependencies::suspect_links(&response.root_causes, suspect);
+        if !causes.is_empty() {
+            writeln!(
+                out,
+                "     可能根因：依赖的 {} 同样在报错，本单元的错误可能是连带的",
+                causes.join("、")
+            )?;
+        }
+        if !dependents.is_empty() {
+            writeln!(
+                out,
+                "     影响范围：依赖它的 {} 同样在报错，宜优先排查本单元",
+                dependents.join("、")
+            )?;
+        }
+
+        if let Some(pkg) = suspect.package_label() {
+            writeln!(out, "     所属包  ：{pkg}")?;
+        } else {
+            writeln!(out, "     所属包  ：未知")?;
+        }
+
+        if let Some(exe) = &suspect.sample_exe {
+            writeln!(out, "     可执行文件：{exe}")?;
+        }
+        if let Some(unit) = &suspect.sample_unit {
+            writeln!(out, "     服务单元：{unit}")?;
+        }
+        if !suspect.hosts.is_empty() {
+            writeln!(out, "     主机    ：{}", host_breakdown(&suspect.hosts))?;
+        }
+
+        if let Some(breakdown) = suspect.priority_breakdown() {
+            writeln!(out, "     级别分布：{breakdown}")?;
+        }
+        if let Some(rate) = suspect.event_rate() {
+            writeln!(out, "     事件频率：{rate}")?;
+        }
+        if let Some(lifecycle) = &suspect.unit_events {
+            writeln!(
+                out,
+                "     服务状态：{}",
+                lifecycle.describe(timeutil::local_utc_offset_secs())
+            )?;
+        }
+
+        if let Some(note) = &suspect.note {
+            writeln!(out, "     备注    ：{}", format_note(note))?;
+        }
+        if let Some(hint) = &suspect.hint {
+            if let Some(explanation) = &hint.explanation {
+                writeln!(out, "     可能原因：{explanation}")?;
+            }
+            writeln!(out, "     建议    ：{}", hint.suggestion)?;
+        }
+
+        if let Some(adjusted) = &suspect.severity_override {
+            let raw = adjusted
+                .raw_worst_priority
+                .map(|p| format!("{p}({})", priority_label_cn(p)))
+                .unwrap_or_else(|| "未知".to_string());
+            writeln!(
+                out,
+                "     级别调整：规则 {} 调整了 {} 条事件，原始最高级别={raw}",
+                adjusted.rule_id, adjusted.events
+            )?;
+        }
+
+        if suspect.maintenance_count > 0 {
+            writeln!(
+                out,
+                "     维护窗口：{} 条事件发生在维护窗口内",
+                suspect.maintenance_count
+            )?;
+        }
+
+        if let Some(presence) = &suspect.boot_presence {
+            writeln!(
+                out,
+                "     启动周期：在最近 {} 次启动中出现 {} 次",
+                presence.checked, presence.seen
+            )?;
+        }
+        if let Some(span) = &suspect.boot_span {
+            writeln!(
+                out,
+                "     重启前后：{}",
+                span.describe(timeutil::local_utc_offset_secs())
+            )?;
+        }
+
+        if let Some(reliability) = &suspect.reliability {
+            writeln!(out, "     可靠性  ：{}", reliability.summary())?;
+        }
+
+        if !suspect.samples.is_empty() {
+            writeln!(
+                out,
+                "     示例消息（{} 条不同消息）：",
+                suspect.samples.len()
+            )?;
+            for sample in &suspect.samples {
+                let priority = sample
+                    .priority
+                    .map(|p| p.to_string())
+                    .unwrap_or_else(|| "-".to_string());
+                writeln!(
+                    out,
+                    "       [{priority}] ×{} {}",
+                    sample.count, sample.message
+                )?;
+            }
+        } else if !suspect.sample_message.is_empty() {
+            writeln!(out, "     示例消息：{}", suspect.sample_message)?;
+        }
+
+        // 只有一种消息时与示例消息相同；--samples 已带次数
+        if suspect.top_messages.len() > 1 && suspect.samples.is_empty() {
+            writeln!(
+                out,
+                "     常见消息（前 {} 条）：",
+                suspect.top_messages.len()
+            )?;
+            for message in &suspect.top_messages {
+                writeln!(out, "       ×{} {}", message.count, message.message)?;
+            }
+        }
+
+        if !suspect.clusters.is_empty() {
+            writeln!(out, "     消息聚类（前 {} 类）：", suspect.clusters.len())?;
+            for cluster in &suspect.clusters {
+                writeln!(out, "       ×{} {}", cluster.count, cluster.template)?;
+            }
+        }
+    }
+
+    if let Some(view) = &response.by_package {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      📦 按软件包汇总")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        owners::write_package_section(out, view)?;
+    }
+
+    if let Some(view) = &response.by_user {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      👤 按用户汇总")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        users::write_user_section(out, view)?;
+    }
+
+    if let Some(view) = &response.per_boot {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      🔁 按启动周期分列")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        perboot::write_boot_section(out, view, timeutil::local_utc_offset_secs())?;
+    }
+
+    if !response.crashes.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      💥 程序崩溃")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        crash::write_crashes_section(out, &response.crashes, timeutil::local_utc_offset_secs())?;
+    }
+
+    if !response.hangs.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      ⏳ 内核挂起")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        hangs::write_hangs_section(out, &response.hangs, timeutil::local_utc_offset_secs())?;
+    }
+
+    if !response.network.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      🌐 网络状况")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        network::write_network_section(out, &response.network, timeutil::local_utc_offset_secs())?;
+    }
+
+    if !response.hardware.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      🔥 硬件警告")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        hardware::write_hardware_section(
+            out,
+            &response.hardware,
+            timeutil::local_utc_offset_secs(),
+        )?;
+    }
+
+    if !response.audit.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      🛡 审计拒绝")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        audit::write_audit_section(out, &response.audit, timeutil::local_utc_offset_secs())?;
+    }
+
+    if !response.failed_units.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      ⛔ 失败的单元")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        failed::write_failed_units_section(out, &response.failed_units)?;
+    }
+
+    if !response.timeline.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      🕒 时间线")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        timeline::write_timeline_section(
+            out,
+            &response.timeline,
+            timeutil::local_utc_offset_secs(),
+        )?;
+    }
+
+    if let Some(histogram) = &response.histogram {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      📊 事件分布")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        histogram::write_histogram_section(
+            out,
+            histogram,
+            &response.suspects,
+            timeutil::local_utc_offset_secs(),
+        )?;
+    }
+
+    if !response.bursts.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      ⚡ 突发时段")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        burst::write_bursts_section(out, &response.bursts, timeutil::local_utc_offset_secs())?;
+    }
+
+    if let Some(report) = &response.trend {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      📈 与上一窗口对比")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        trend::write_trend_section(
+            out,
+            report,
+            response.metrics.matched,
+            timeutil::local_utc_offset_secs(),
+        )?;
+    }
+
+    if let Some(report) = &response.baseline {
+        writeln!(out)?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out, "                      📏 与基线对比")?;
+        writeln!(out, "{REPORT_RULE}")?;
+        writeln!(out)?;
+        baseline::write_baseline_section(out, report, timeutil::local_utc_offset_secs())?;
+    }
+
+    if let Some(env) = &response.environment {
+        writeln!(out)?;
+        writeln!(out, "  运行环境：")?;
+        writeln!(out, "     logtool ：{}", env.logtool_version)?;
+        writeln!(
+            out,
+            "     系统    ：{}",
+            env.os_release.as_deref().unwrap_or("未知")
+        )?;
+        writeln!(
+            out,
+            "     内核    ：{}",
+            env.kernel.as_deref().unwrap_or("未知")
+        )?;
+        writeln!(
+            out,
+            "     systemd ：{}",
+            env.systemd_version.as_deref().unwrap_or("未知")
+        )?;
+        writeln!(out, "     规则    ：{}", enabled_rules_cn(env))?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "{REPORT_RULE}")?;
+    Ok(())
+}
+
+/// 逐句报告（--linear）：每行一句或一组完整句子，不使用框线、表格与列对齐
+pub fn write_linear_report<W: Write>(out: &mut W, response: &AnalyzeResponse) -> io::Result<()> {
+    let metrics = &response.metrics;
+    let suspects = &response.suspects;
+
+    if metrics.emergency_outside_filter > 0 {
+        writeln!(
+            out,
+            "警告：窗口内存在 {} 条紧急级别事件未包含在当前过滤中，去掉单元或关键词过滤后运行 logtool --priority {EMERGENCY_MAX_PRIORITY} 可查看。",
+            metrics.emergency_outside_filter
+        )?;
+    }
+    if let Some(banner) = hardware::hardware_banner(&response.hardware) {
+        writeln!(out, "硬件警告：{banner}。")?;
+    }
+    writeln!(
+        out,
+        "事件摘要：读取 {} 行，解析成功 {} 行，解析错误 {} 行，匹配 {} 条事件，来自 {} 个独立来源。",
+        metrics.lines_read,
+        metrics.parsed_ok,
+        metrics.parse_errors,
+        metrics.matched,
+        suspects.len()
+    )?;
+    if let Some(namespace) = &response.namespace {
+        writeln!(out, "分析的日志命名空间为 {namespace}。")?;
+    }
+    if let Some(directory) = &response.directory {
+        writeln!(out, "分析的是目录 {directory} 中的日志。")?;
+    }
+    if !response.files.is_empty() {
+        writeln!(out, "分析的是日志文件 {}。", response.files.join("、"))?;
+    }
+    if !response.syslog_files.is_empty() {
+        writeln!(
+            out,
+            "分析的是 syslog 文件 {}。",
+            response.syslog_files.join("、")
+        )?;
+    }
+    if let Some(fallback) = &response.kernel_fallback {
+        writeln!(
+            out,
+            "{}，分析的是内核缓冲区（{}）中的消息。",
+            kmsg::reason_label(fallback.reason),
+            fallback.source
+        )?;
+    }
+    if !response.boots.is_empty() {
+        writeln!(
+            out,
+            "合并了 {} 个启动周期（{}）的日志。",
+            response.boots.len(),
+            boot_offsets_label(&response.boots)
+        )?;
+    }
+    if !response.hosts.is_empty() {
+        writeln!(
+            out,
+            "日志来自 {} 台主机：{}。",
+            response.hosts.len(),
+            response.hosts.join("、")
+        )?;
+    }
+    if let Some(truncation) = &response.truncated {
+        writeln!(out, "{}。", truncation_summary(truncation))?;
+    }
+    if let Some(at) = response.cached_at {
+        writeln!(out, "{}。", cached_summary(at, timeutil::unix_now_secs()))?;
+    }
+    if !response.reboots.is_empty() {
+        writeln!(
+            out,
+            "{}。",
+            reboots::reboot_summary(&response.reboots, timeutil::local_utc_offset_secs())
+        )?;
+    }
+    if metrics.in_maintenance > 0 {
+        writeln!(
+            out,
+            "其中 {} 条事件发生在维护窗口内，已计入匹配数。",
+            metrics.in_maintenance
+        )?;
+    }
+    if metrics.excluded > 0 {
+        writeln!(
+            out,
+            "另有 {} 条事件被排除，未计入匹配数。",
+            metrics.excluded
+        )?;
+    }
+    if metrics.duplicates > 0 {
+        writeln!(
+            out,
+            "另有 {} 条与上一条完全相同的连续重复事件已折叠，未计入匹配数。",
+            metrics.duplicates
+        )?;
+    }
+    if suspects.is_empty() {
+        writeln!(out, "当前过滤条件下未发现可疑来源。")?;
+        return Ok(());
+    }
+
+    let shown = suspects.len().min(response.top);
+    writeln!(out, "以下按事件数列出前 {shown} 个可疑来源。")?;
+    for (index, suspect) in suspects.iter().take(response.top).enumerate() {
+        let mut line = format!(
+            "第 {} 名：{} {}，共 {} 条事件，最高严重级别为{}（{}）。",
+            index + 1,
+            source_label_cn(suspect.kind),
+            suspect.source,
+            suspect.count,
+            priority_label_cn(suspect.worst_priority),
+            suspect.worst_priority
+        );
+        if suspect.kind == SourceKind::Storage {
+            line.push_str(&format!("属于硬件风险：{}。", storage::HARDWARE_RISK));
+        }
+        if let Some(health) = smart::health_for(&response.smart, suspect) {
+            line.push_str(&format!("SMART 检查：{}。", health.verdict()));
+        }
+        if suspect.kind == SourceKind::Gpu {
+            line.push_str(&format!("属于驱动风险：{}。", gpu::DRIVER_RISK));
+        }
+        if suspect.unit_failed {
+            line.push_str("所属单元当前处于失败状态。");
+        }
+        let (causes, dependents) = dependencies::suspect_links(&response.root_causes, suspect);
+        if !causes.is_empty() {
+            line.push_str(&format!(
+                "它依赖的 {} 同样在报错，这里的错误可能是连带的。",
+                causes.join("、")
+            ));
+        }
+        if !dependents.is_empty() {
+            line.push_str(&format!(
+                "依赖它的 {} 同样在报错，宜优先排查。",
+                dependents.join("、")
+            ));
+        }
+        match suspect.package_label() {
+            Some(pkg) => line.push_str(&format!("所属包为 {pkg}。")),
+            None => line.push_str("所属包未知。"),
+        }
+        if let Some(exe) = &suspect.sample_exe {
+            line.push_str(&format!("可执行文件为 {exe}。"));
+        }
+        if let Some(unit) = &suspect.sample_unit {
+            line.push_str(&format!("服务单元为 {unit}。"));
+        }
+        if !suspect.hosts.is_empty() {
+            line.push_str(&format!("出现在主机 {}。", host_breakdown(&suspect.hosts)));
+        }
+        let levels = suspect
+            .priority_counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(level, count)| format!("{count} 条{}", priority_label_cn(level as u8)))
+            .collect::<Vec<_>>();
+        if !levels.is_empty() {
+            line.push_str(&format!("级别分布为{}。", levels.join("、")));
+        }
+        if let Some(rate) = suspect.event_rate() {
+            line.push_str(&format!("事件频率{rate}。"));
+        }
+        if let Some(lifecycle) = &suspect.unit_events {
+            line.push_str(&format!(
+                "服务状态：{}。",
+                lifecycle.describe(timeutil::local_utc_offset_secs())
+            ));
+        }
+        if let Some(note) = &suspect.note {
+            line.push_str(&format!("备注：{}。", format_note(note)));
+        }
+        if let Some(hint) = &suspect.hint {
+            if let Some(explanation) = &hint.explanation {
+                line.push_str(&format!("可能原因：{explanation}。"));
+            }
+            line.push_str(&format!("建议：{}。", hint.suggestion));
+        }
+        if let Some(adjusted) = &suspect.severity_override {
+            let raw = adjusted
+                .raw_worst_priority
+                .map(|p| format!("{}（{p}）", priority_label_cn(p)))
+                .unwrap_or_else(|| "未知".to_string());
+            line.push_str(&format!(
+                "规则 {} 调整了其中 {} 条事件的级别，原始最高级别为{raw}。",
+                adjusted.rule_id, adjusted.events
+            ));
+        }
+        if suspect.maintenance_count > 0 {
+            line.push_str(&format!(
+                "其中 {} 条发生在维护窗口内。",
+                suspect.maintenance_count
+            ));
+        }
+        if let Some(presence) = &suspect.boot_presence {
+            line.push_str(&format!(
+                "在最近 {} 次启动中出现了 {} 次。",
+                presence.checked, presence.seen
+            ));
+        }
+        if let Some(span) = &suspect.boot_span {
+            line.push_str(&format!(
+                "重启前后：{}。",
+                span.describe(timeutil::local_utc_offset_secs())
+            ));
+        }
+        if let Some(reliability) = &suspect.reliability {
+            line.push_str(&format!("可靠性：{}。", reliability.summary()));
+        }
+        if !suspect.samples.is_empty() {
+            for (n, sample) in suspect.samples.iter().enumerate() {
+                line.push_str(&format!(
+                    "示例消息 {}，出现 {} 次：{}。",
+                    n + 1,
+                    sample.count,
+                    sample.message
+                ));
+            }
+        } else if !suspect.sample_message.is_empty() {
+            line.push_str(&format!("示例消息：{}。", suspect.sample_message));
+        }
+        if suspect.top_messages.len() > 1 && suspect.samples.is_empty() {
+            for (n, message) in suspect.top_messages.iter().enumerate() {
+                line.push_str(&format!(
+                    "常见消息 {}，出现 {} 次：{}。",
+                    n + 1,
+                    message.count,
+                    message.message
+                ));
+            }
+        }
+        for (n, cluster) in suspect.clusters.iter().enumerate() {
+            line.push_str(&format!(
+                "消息类别 {}，出现 {} 次：{}。",
+                n + 1,
+                cluster.count,
+                cluster.template
+            ));
+        }
+        writeln!(out, "{line}")?;
+    }
+
+    if let Some(view) = &response.by_package {
+        writeln!(out, "按软件包汇总，按事件数从多到少：")?;
+        for sentence in owners::package_sentences(view) {
+            writeln!(out, "{sentence}")?;
+        }
+    }
+
+    if let Some(view) = &response.by_user {
+        writeln!(out, "按用户汇总，按事件数从多到少：")?;
+        for sentence in users::user_sentences(view) {
+            writeln!(out, "{sentence}")?;
+        }
+    }
+
+    if let Some(view) = &response.per_boot {
+        writeln!(out, "按启动周期分列，按开机时间先后：")?;
+        for sentence in perboot::boot_sentences(view, timeutil::local_utc_offset_secs()) {
+            writeln!(out, "{sentence}")?;
+        }
+    }
+
+    if !response.crashes.is_empty() {
+        writeln!(out, "程序崩溃，按次数从多到少：")?;
+        let offset_secs = timeutil::local_utc_offset_secs();
+        for sentence in crash::crash_sentences(&response.crashes, offset_secs) {
+            writeln!(out, "{sentence}")?;
+        }
+    }
+
+    if !response.hangs.is_empty() {
+        writeln!(out, "内核挂起，按次数从多到少：")?;
+        let offset_secs = timeutil::local_utc_offset_secs();
+        for sentence in hangs::hang_sentences(&response.hangs, offset_secs) {
+            writeln!(out, "{sentence}")?;
+        }
+    }
+
+    if !response.network.is_empty() {
+        writeln!(out, "网络状况，按次数从多到少：")?;
+        let offset_secs = timeutil::local_utc_offset_secs();
+        for sentence in network::network_sentences(&response.network, offset_secs) {
+            writeln!(out, "{sentence}")?;
+        }
+    }
+
+    if !response.hardware.is_empty() {
+        writeln!(out, "硬件警告，严重的在前，再按次数从多到少：")?;
+        let offset_secs = timeutil::local_utc_offset_secs();
+        for sentence in hardware::hardware_sentences(&response.hardware, offset_secs) {
+            writeln!(out, "{sentence}")?;
+        }
+    }
+
+    if !response.audit.is_empty() {
+        writeln!(out, "审计拒绝，按次数从多到少：")?;
+        let offset_secs = timeutil::local_utc_offset_secs();
+        for sentence in audit::audit_sentences(&response.audit, offset_secs) {
+            writeln!(out, "{sentence}")?;
+        }
+    }
+
+    if !response.failed_units.is_empty() {
+        writeln!(
+            out,
+            "{}",
+            failed::failed_units_sentence(&response.failed_units)
+        )?;
+    }
+
+    if !response.timeline.is_empty() {
+        writeln!(out, "时间线，按时间先后：")?;
+        let offset_secs = timeutil::local_utc_offset_secs();
+        for sentence in timeline::timeline_sentences(&response.timeline, offset_secs) {
+            writeln!(out, "{sentence}")?;
+        }
+    }
+
+    if let Some(histogram) = &response.histogram {
+        let offset_secs = timeutil::local_utc_offset_secs();
+        for sentence in histogram::histogram_sentences(histogram, &response.suspects, offset_secs) {
+            writeln!(out, "{sentence}")?;
+        }
+    }
+
+    if !response.bursts.is_empty() {
+        writeln!(out, "突发时段，按时间先后：")?;
+        let offset_secs = timeutil::local_utc_offset_secs();
+        for sentence in burst::burst_sentences(&response.bursts, offset_secs) {
+            writeln!(out, "{sentence}")?;
+        }
+    }
+
+    if let Some(report) = &response.trend {
+        let offset_secs = timeutil::local_utc_offset_secs();
+        for sentence in trend::trend_sentences(report, response.metrics.matched, offset_secs) {
+            writeln!(out, "{sentence}")?;
+        }
+    }
+
+    if let Some(report) = &response.baseline {
+        let offset_secs = timeutil::local_utc_offset_secs();
+        for sentence in baseline::baseline_sentences(report, offset_secs) {
+            writeln!(out, "{sentence}")?;
+        }
+    }
+
+    if let Some(env) = &response.environment {
+        writeln!(
+            out,
+            "运行环境：logtool {}，系统 {}，内核 {}，systemd {}，规则：{}。",
+            env.logtool_version,
+            env.os_release.as_deref().unwrap_or("未知"),
+            env.kernel.as_deref().unwrap_or("未知"),
+            env.systemd_version.as_deref().unwrap_or("未知"),
+            enabled_rules_cn(env)
+        )?;
+    }
+    Ok(())
+}
+
+/// 单行摘要（--summary-only），键值对以空格分隔，便于脚本解析：
+/// `matched=12 sources=3 worst_priority=3 top=unit:ssh.service`
+pub fn format_summary_line(response: &AnalyzeResponse) -> String {
+    let worst = response.suspects.iter().map(|s| s.worst_priority).min();
+    let top = response
+        .suspects
+        .first()
+        .map(|s| suspect_id(s.kind, &s.source));
+
+    format!(
+        "matched={} sources={} worst_priority={} top={}",
+        response.metrics.matched,
+        response.suspects.len(),
+        worst.map_or_else(|| "none".to_string(), |p| p.to_string()),
+        top.map_or_else(|| "none".to_string(), |id| quote_kv_value(&id)),
+    )
+}
+
+/// 可疑来源的稳定标识，如 `unit:ssh.service`、`exe:/usr/bin/foo`
+pub fn suspect_id(kind: SourceKind, source: &str) -> String {
+    format!("{}:{source}", source_kind_slug(kind))
+}
+
+pub fn source_kind_slug(kind: SourceKind) -> &'static str {
+    match kind {
+        SourceKind::Unit => "unit",
+        SourceKind::Executable => "exe",
+        SourceKind::Identifier => "identifier",
+        SourceKind::Comm => "comm",
+        SourceKind::Kernel => "kernel",
+        SourceKind::Container => "container",
+        SourceKind::Rule => "rule",
+        SourceKind::Storage => "storage",
+        SourceKind::Gpu => "gpu",
+        SourceKind::Package => "package",
+        SourceKind::Unknown => "unknown",
+    }
+}
+
+/// 键值对输出的值转义：含空白、引号或 `=` 时加双引号
+pub(crate) fn quote_kv_value(value: &str) -> String {
+    if !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '=' | '\\'))
+    {
+        return value.to_string();
+    }
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+pub fn source_label_cn(kind: SourceKind) -> &'static str {
+    match kind {
+        SourceKind::Unit => "服务单元",
+        SourceKind::Executable => "可执行文件",
+        SourceKind::Identifier => "标识符",
+        SourceKind::Comm => "进程名",
+        SourceKind::Kernel => "内核",
+        SourceKind::Container => "容器",
+        SourceKind::Rule => "规则归并",
+        SourceKind::Storage => "存储设备",
+        SourceKind::Gpu => "显卡驱动",
+        SourceKind::Package => "软件包",
+        SourceKind::Unknown => "未知",
+    }
+}
+
+/// `known issue（alice，2024-01-03 08:05）`
+pub fn format_note(note: &Note) -> String {
+    let when = timeutil::format_local_minute(
+        note.updated_at.saturating_mul(1_000_000),
+        timeutil::local_utc_offset_secs(),
+    );
+    match &note.author {
+        Some(author) => format!("{}（{author}，{when}）", note.text),
+        None => format!("{}（{when}）", note.text),
+    }
+}
+
+/// syslog 级别关键字的简写，用于紧凑的级别分布
+pub fn priority_keyword(priority: u8) -> &'static str {
+    match priority {
+        0 => "emerg",
+        1 => "alert",
+        2 => "crit",
+        3 => "err",
+        4 => "warn",
+        5 => "notice",
+        6 => "info",
+        7 => "debug",
+        _ => "unknown",
+    }
+}
+
+pub fn priority_label_cn(priority: u8) -> &'static str {
+    match priority {
+        0 => "紧急",
+        1 => "警报",
+        2 => "严重",
+        3 => "错误",
+        4 => "警告",
+        5 => "通知",
+        6 => "信息",
+        7 => "调试",
+        _ => "未知",
+    }
+}
+
+// ── journalctl 命令构建 ─────────────────────────────────────────────
+
+fn ensure_journalctl_exists() -> Result<(), String> {
+    let status = Command::new("journalctl")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+
+    match status {
+        Ok(exit) if exit.success() => Ok(()),
+        Ok(_) => Err("journalctl 存在但不可用".to_string()),
+        Err(err) => Err(format!("找不到 journalctl：{err}")),
+    }
+}
+
+fn build_journalctl_command_for_stream(config: &Config) -> Command {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager");
+
+    if config.follow {
+        cmd.arg("--follow");
+    }
+
+    if config.live {
+        // 优先级可能被放宽，journalctl 侧先不过滤级别
+        let all_levels = Config {
+            priority: Priority::up_to(7),
+            ..config.clone()
+        };
+        add_common_query_args(&mut cmd, &all_levels);
+    } else {
+        add_common_query_args(&mut cmd, config);
+    }
+
+    // logfmt 与 --live 由结构化字段转换而来
+    if config.output_json || config.format == ReportFormat::Logfmt || config.live {
+        cmd.arg("--output=json");
+    } else {
+        cmd.arg("--output=short-iso");
+    }
+
+    cmd
+}
+
+/// 分析模式实际执行的 journalctl 命令行（已转义，可直接粘贴到 shell）
+pub fn analysis_command_line(config: &Config) -> String {
+    render_command(&build_journalctl_command_for_analysis(config))
+}
+
+fn build_journalctl_command_for_analysis(config: &Config) -> Command {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager");
+    add_common_query_args(&mut cmd, config);
+    cmd.arg("--output=json");
+    // --evidence 要保留完整条目，供事后用 jq 等工具查看全部字段
+    if config.evidence_dir.is_none() {
+        cmd.arg(
+            "--output-fields=PRIORITY,MESSAGE,_SYSTEMD_UNIT,_EXE,_COMM,SYSLOG_IDENTIFIER,_HOSTNAME,_SYSTEMD_CGROUP,CONTAINER_NAME,_UID,_BOOT_ID",
+        );
+    }
+    cmd
+}
+
+fn add_common_query_args(cmd: &mut Command, config: &Config) {
+    if config.kernel_only {
+        cmd.arg("--dmesg");
+    }
+
+    if let Some(since) = &config.since {
+        cmd.arg("--since").arg(since);
+    }
+
+    if let Some(until) = &config.until {
+        cmd.arg("--until").arg(until);
+    }
+
+    add_journal_source_args(cmd, config);
+
+    for unit in &config.units {
+        cmd.arg("--unit").arg(unit);
+    }
+
+    for identifier in &config.identifiers {
+        cmd.arg(format!("--identifier={identifier}"));
+    }
+
+    add_boot_args(cmd, config);
+
+    cmd.arg(format!("--priority={}", config.priority));
+
+    // journald 只在 MESSAGE 上匹配，得到的是本地过滤（还匹配单元名、程序名）结果的子集，本地照常复核
+    if config.journal_grep
+        && let [term] = config.grep_terms.as_slice()
+    {
+        cmd.arg(format!("--grep={}", escape_pcre(term)));
+    }
+
+    // 字段匹配：同字段多值为“或”，不同字段之间为“且”
+    for comm in &config.comms {
+        cmd.arg(format!("_COMM={comm}"));
+    }
+    for pid in &config.pids {
+        cmd.arg(format!("_PID={pid}"));
+    }
+    for uid in &config.uids {
+        cmd.arg(format!("_UID={uid}"));
+    }
+    for cgroup in &config.cgroups {
+        cmd.arg(format!("_SYSTEMD_CGROUP={cgroup}"));
+    }
+    for container in &config.containers {
+        cmd.arg(format!("CONTAINER_NAME={container}"));
+    }
+    cmd.args(&config.matches);
+}
+
+/// 按字面匹配：转义 PCRE2 元字符。关键词已折叠为小写时 journalctl 不区分大小写
+fn escape_pcre(term: &str) -> String {
+    let mut escaped = String::with_capacity(term.len());
+    for c in term.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+pub fn render_command(cmd: &Command) -> String {
+    let mut rendered = cmd.get_program().to_string_lossy().to_string();
+    for arg in cmd.get_args() {
+        rendered.push(' ');
+        rendered.push_str(&shell_escape(arg.to_string_lossy().as_ref()));
+    }
+    rendered
+}
+
+pub fn write_json_line<W: Write, T: Serialize>(
+    writer: &mut W,
+    payload: &T,
+    label: &str,
+) -> Result<(), String> {
+    let json = serde_json::to_string(payload).map_err(|e| format!("序列化{label}失败：{e}"))?;
+    writer
+        .write_all(json.as_bytes())
+        .map_err(|e| format!("发送{label}失败：{e}"))?;
+    writer
+        .write_all(b"\n")
+        .map_err(|e| format!("发送换行符失败：{e}"))?;
+    writer.flush().map_err(|e| format!("刷新输出失败：{e}"))?;
+
+    Ok(())
+}
+
+pub fn stream_error_line(message: String) -> StreamLine {
+    StreamLine {
+        done: true,
+        error: Some(message),
+        ..StreamLine::default()
+    }
+}
+
+pub fn daemon_error(message: String) -> ErrorResponse {
+    daemon_error_with_details(message, None, None)
+}
+
+pub fn daemon_error_with_details(
+    message: String,
+    code: Option<&str>,
+    hint: Option<String>,
+) -> ErrorResponse {
+    ErrorResponse {
+        error: message,
+        code: code.map(|v| v.to_string()),
+        hint,
+    }
+}
+
+fn shell_escape(value: &str) -> String {
+    if value.is_empty() {
+        return "''".to_string();
+    }
+    if value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | ':' | '+'))
+    {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', "'\"'\"'"))
+}
+
+fn io_error_to_string(err: io::Error) -> String {
+    err.to_string()
+}
+
+pub fn truncate_for_display(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(limit + 3);
+    for (idx, ch) in text.chars().enumerate() {
+        if idx >= limit {
+            break;
+        }
+        out.push(ch);
+    }
+    out.push_str("...");
+    out
+}
+
+/// 三项截断上限的下限检查；配置文件中的同名字段也经过这里
+pub fn validate_limits(
+    message_chars: Option<usize>,
+    line_bytes: Option<usize>,
+    response_bytes: Option<usize>,
+) -> Result<(), String> {
+    let checks = [
+        ("--max-msg-chars", message_chars, MIN_MESSAGE_CHARS),
+        ("--max-line-bytes", line_bytes, MIN_LINE_BYTES),
+        ("--max-resp-bytes", response_bytes, MIN_RESPONSE_BYTES),
+    ];
+    for (flag, value, min) in checks {
+        if let Some(value) = value
+            && value < min
+        {
+            return Err(format!(
+                "{flag} 不能小于 {min}（实际 {value}）\n修复：示例 {flag} {}",
+                min * 4
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn truncation_marker(dropped_bytes: usize) -> String {
+    format!("…[截断 {dropped_bytes} 字节]")
+}
+
+/// 按字节上限截断（不拆开 UTF-8 字符），末尾的标记注明丢弃的字节数，标记计入上限
+pub fn truncate_with_marker(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    // 丢弃的字节数不超过原长，按原长的位数预留标记长度
+    let mut end = max_bytes.saturating_sub(truncation_marker(text.len()).len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", &text[..end], truncation_marker(text.len() - end))
+}
+
+/// 流模式的一行：文本行直接截断；JSON 行只缩短 MESSAGE 字段以保持 JSON 有效，
+/// 其余字段过长时整行仍可能超出上限
+pub fn truncate_stream_line(line: String, max_bytes: usize, json: bool) -> String {
+    if line.len() <= max_bytes {
+        return line;
+    }
+    if !json {
+        return truncate_with_marker(&line, max_bytes);
+    }
+    let Ok(serde_json::Value::Object(mut fields)) = serde_json::from_str(&line) else {
+        return line;
+    };
+    let Some(serde_json::Value::String(message)) = fields.remove("MESSAGE") else {
+        return line;
+    };
+    // 转义会改变长度，缩短后按实际结果再校正；每轮至少少一个字节，必然结束
+    let mut budget = message.len().saturating_sub(line.len() - max_bytes);
+    loop {
+        fields.insert(
+            "MESSAGE".to_string(),
+            serde_json::Value::String(truncate_with_marker(&message, budget)),
+        );
+        let shortened = serde_json::Value::Object(fields.clone()).to_string();
+        if shortened.len() <= max_bytes || budget == 0 {
+            return shortened;
+        }
+        budget = budget.saturating_sub((shortened.len() - max_bytes).max(1));
+    }
+}
+
+/// 把分析响应压缩到 max_bytes 以内（单行 JSON，不含换行）：依次去掉多条示例消息、时间线，
+/// 再从排名末尾省略来源；metrics 等计数始终完整，连同省略记录写入 truncated
+pub fn fit_response(response: &mut AnalyzeResponse, max_bytes: usize) {
+    let size =
+        |response: &AnalyzeResponse| serde_json::to_vec(response).map_or(0, |json| json.len());
+    if size(response) <= max_bytes {
+        return;
+    }
+    let mut truncation = ResponseTruncation {
+        limit_bytes: max_bytes,
+        ..ResponseTruncation::default()
+    };
+    let fits = |response: &mut AnalyzeResponse, truncation: &ResponseTruncation| {
+        response.truncated = Some(truncation.clone());
+        size(response) <= max_bytes
+    };
+
+    if !response.evidence.is_empty() {
+        response.evidence.clear();
+        truncation.evidence_dropped = true;
+        if fits(response, &truncation) {
+            return;
+        }
+    }
+    if response.suspects.iter().any(|s| !s.clusters.is_empty()) {
+        for suspect in &mut response.suspects {
+            suspect.clusters.clear();
+        }
+        truncation.clusters_dropped = true;
+        if fits(response, &truncation) {
+            return;
+        }
+    }
+    if response.suspects.iter().any(|s| !s.samples.is_empty()) {
+        for suspect in &mut response.suspects {
+            suspect.samples.clear();
+        }
+        truncation.samples_dropped = true;
+        if fits(response, &truncation) {
+            return;
+        }
+    }
+    if response.suspects.iter().any(|s| !s.top_messages.is_empty()) {
+        for suspect in &mut response.suspects {
+            suspect.top_messages.clear();
+        }
+        truncation.top_messages_dropped = true;
+        if fits(response, &truncation) {
+            return;
+        }
+    }
+    if !response.timeline.is_empty() {
+        response.timeline.clear();
+        truncation.timeline_dropped = true;
+        if fits(response, &truncation) {
+            return;
+        }
+    }
+    if response.histogram.is_some() {
+        response.histogram = None;
+        for suspect in &mut response.suspects {
+            suspect.histogram.clear();
+        }
+        truncation.histogram_dropped = true;
+        if fits(response, &truncation) {
+            return;
+        }
+    }
+    if !response.bursts.is_empty() {
+        response.bursts.clear();
+        truncation.bursts_dropped = true;
+        if fits(response, &truncation) {
+            return;
+        }
+    }
+
+    // 每个来源约占自身 JSON 长度加一个逗号：先按估计一次省略，再逐个校正
+    let mut excess = size(response).saturating_sub(max_bytes);
+    while excess > 0 {
+        let Some(last) = response.suspects.pop() else {
+            break;
+        };
+        excess = excess.saturating_sub(serde_json::to_vec(&last).map_or(0, |json| json.len()) + 1);
+        truncation.omitted_suspects += 1;
+    }
+    while !fits(response, &truncation) && response.suspects.pop().is_some() {
+        truncation.omitted_suspects += 1;
+    }
+}
+
+/// `省略了多条示例消息、时间线和排名最后的 12 个来源`
+pub fn truncation_summary(truncation: &ResponseTruncation) -> String {
+    let mut parts = Vec::new();
+    if truncation.evidence_dropped {
+        parts.push("--evidence 原始条目".to_string());
+    }
+    if truncation.clusters_dropped {
+        parts.push("消息聚类".to_string());
+    }
+    if truncation.samples_dropped {
+        parts.push("多条示例消息".to_string());
+    }
+    if truncation.top_messages_dropped {
+        parts.push("常见消息".to_string());
+    }
+    if truncation.timeline_dropped {
+        parts.push("时间线".to_string());
+    }
+    if truncation.histogram_dropped {
+        parts.push("事件分布".to_string());
+    }
+    if truncation.bursts_dropped {
+        parts.push("突发时段".to_string());
+    }
+    if truncation.omitted_suspects > 0 {
+        parts.push(format!("排名最后的 {} 个来源", truncation.omitted_suspects));
+    }
+    let omitted = match parts.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{}和{last}", rest.join("、")),
+        None => "部分内容".to_string(),
+    };
+    format!(
+        "响应超过 {} 字节上限（--max-resp-bytes），省略了{omitted}",
+        truncation.limit_bytes
+    )
+}
+
+fn reached_limit(count: usize, max: Option<usize>) -> bool {
+    match max {
+        Some(max) => count >= max,
+        None => false,
+    }
+}
+
+fn status_killed_by_limit(count: usize, max: Option<usize>) -> bool {
+    reached_limit(count, max)
+}
+
+fn matches_filters(line: &str, config: &Config) -> bool {
+    if config.grep_terms.is_empty() && config.grep_any.is_empty() && config.exclude_terms.is_empty()
+    {
+        return true;
+    }
+    let text = TextFold::from_config(config).apply(line.to_string());
+    term_filters_pass(&text, config) && !is_excluded(&text, config)
+}
+
+// ── 文件输出 ─────────────────────────────────────────────
+
+/// 原子写入：先写同目录临时文件并 fsync，再 rename 覆盖目标，
+/// 避免读者看到写了一半的报告。
+pub fn write_file_atomically(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("输出路径无效：{}", path.display()))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(format!(".tmp-{}", std::process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = (|| {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    })();
+
+    if let Err(err) = result {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(format!("写入输出文件 {} 失败：{err}", path.display()));
+    }
+    Ok(())
+}
+
+/// --evidence：每个来源一个 `<排名>-<来源 ID>.jsonl`，ID 中的路径分隔符等替换为 `_`；返回写入的文件
+pub fn write_evidence(dir: &Path, evidence: &[SuspectEvidence]) -> Result<Vec<PathBuf>, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("创建证据目录 {} 失败：{e}", dir.display()))?;
+    let mut written = Vec::new();
+    for (index, suspect) in evidence.iter().enumerate() {
+        let name = suspect
+            .id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '@') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        let path = dir.join(format!("{:02}-{name}.jsonl", index + 1));
+        let mut contents = suspect.entries.join("\n");
+        contents.push('\n');
+        write_file_atomically(&path, contents.as_bytes())?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// 追加写入的文件 writer，超过阈值时将当前文件轮转为 `<path>.1` 后重新打开。
+/// 只在行首轮转：分几次写入的一行不会被拆到两个文件里，当前文件最多超出阈值一行。
+pub struct RotatingFileWriter {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_bytes: u64,
+    /// 当前文件末尾停在一行中间（最后写入的字节不是换行）
+    mid_line: bool,
+}
+
+impl RotatingFileWriter {
+    pub fn open(path: &Path, max_bytes: u64) -> Result<Self, String> {
+        let file = open_append(path)?;
+        let written = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            written,
+            max_bytes,
+            mid_line: written > 0 && !ends_with_newline(path),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        fs::rename(&self.path, PathBuf::from(rotated))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.mid_line && self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        if let Some(last) = buf[..n].last() {
+            self.mid_line = *last != b'\n';
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn ends_with_newline(path: &Path) -> bool {
+    use std::io::{Seek, SeekFrom};
+    let mut last = [0u8; 1];
+    File::open(path)
+        .and_then(|mut file| {
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)
+        })
+        .is_ok_and(|()| last[0] == b'\n')
+}
+
+fn open_append(path: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("打开输出文件 {} 失败：{e}", path.display()))
+}
+
+// ── 帮助文本 ─────────────────────────────────────────────
+
+/// 由选项表生成（见 options.rs），首次调用时渲染
+pub fn help_text() -> &'static str {
+    static HELP: OnceLock<String> = OnceLock::new();
+    HELP.get_or_init(options::render_help)
+}
+
+// ── 单元测试 ─────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_suspect;
+    use std::env;
+
+    fn parse(input: &[&str]) -> Result<Action, String> {
+        let args = input.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn default_mode_is_analyze() {
+        let action = parse(&[]).expect("解析应成功");
+        let Action::Run(config) = action else {
+            panic!("应为 Action::Run");
+        };
+
+        assert_eq!(config.mode, RunMode::Analyze);
+        assert_eq!(config.boot, BootFilter::Disabled);
+        assert_eq!(config.since, Some(DEFAULT_SINCE.to_string()));
+    }
+
+    #[test]
+    fn stream_mode_allows_follow() {
+        let action = parse(&["--stream", "--follow"]).expect("解析应成功");
+        let Action::Run(config) = action else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(config.mode, RunMode::Stream);
+        assert!(config.follow);
+        assert_eq!(config.max_lines, None);
+    }
+
+    #[test]
+    fn help_subcommand_works() {
+        let action = parse(&["help"]).expect("解析应成功");
+        assert_eq!(action, Action::Help);
+    }
+
+    #[test]
+    fn version_flag_returns_version_action() {
+        let action = parse(&["--version"]).expect("解析应成功");
+        assert_eq!(action, Action::Version);
+    }
+
+    #[test]
+    fn version_short_flag_lowercase_returns_version_action() {
+        let action = parse(&["-v"]).expect("解析应成功");
+        assert_eq!(action, Action::Version);
+    }
+
+    #[test]
+    fn doctor_command_returns_doctor_action() {
+        let action = parse(&["doctor"]).expect("解析应成功");
+        assert_eq!(action, Action::Doctor);
+    }
+
+    #[test]
+    fn list_boots_flag_returns_action() {
+        let action = parse(&["--list-boots"]).expect("解析应成功");
+        assert_eq!(action, Action::ListBoots);
+    }
+
+    #[test]
+    fn doctor_rejects_mixed_arguments() {
+        let err = parse(&["--doctor", "--stream"]).expect_err("解析应失败");
+        assert!(err.contains("--doctor"));
+    }
+
+    #[test]
+    fn version_rejects_mixed_arguments() {
+        let err = parse(&["--version", "--stream"]).expect_err("解析应失败");
+        assert!(err.contains("--version"));
+    }
+
+    #[test]
+    fn all_boots_disables_boot_filter() {
+        let action = parse(&["--all-boots"]).expect("解析应成功");
+        let Action::Run(config) = action else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(config.boot, BootFilter::Disabled);
+    }
+
+    #[test]
+    fn boot_accepts_negative_offset() {
+        let action = parse(&["--boot", "-1"]).expect("解析应成功");
+        let Action::Run(config) = action else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(config.boot, BootFilter::Value("-1".to_string()));
+    }
+
+    #[test]
+    fn boot_accepts_ranges_and_lists() {
+        let Action::Run(config) =
+            parse(&["--boot", "-3..-1", "--grep", "oom"]).expect("解析应成功")
+        else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(
+            config.boot,
+            BootFilter::Several(vec!["-3".into(), "-2".into(), "-1".into()])
+        );
+        let id = "0123456789abcdef0123456789ABCDEF";
+        let Action::Run(config) = parse(&[&format!("--boot=-2,0,{id},0")]).expect("解析应成功")
+        else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(
+            config.boot,
+            BootFilter::Several(vec!["-2".into(), "0".into(), id.into()])
+        );
+        for bad in ["0..-3", "-30..0", "-1..x", "-1,nope"] {
+            assert!(parse(&["--boot", bad]).is_err(), "{bad} 应被拒绝");
+        }
+        assert!(
+            parse(&[
+                "--boot", "-1..0", "--match", "A=1", "--match", "+", "--match", "B=2"
+            ])
+            .is_err()
+        );
+
+        let boots = parse_boot_list(&format!(
+            "IDX BOOT ID FIRST ENTRY LAST ENTRY\n -2 aaaa Mon\n -1 {} Tue\n  0 cccc Wed\n",
+            id.to_ascii_lowercase()
+        ));
+        let wanted = [
+            "1".to_string(),
+            id.to_string(),
+            "0".to_string(),
+            "-9".to_string(),
+        ];
+        assert_eq!(
+            select_boots(&boots, &wanted)
+                .iter()
+                .map(|(offset, _)| *offset)
+                .collect::<Vec<_>>(),
+            vec![-2, -1, 0]
+        );
+
+        let resolved = Config {
+            boot: BootFilter::Several(vec!["aaaa".into(), "cccc".into()]),
+            ..Config::default()
+        };
+        let rendered = render_command(&build_journalctl_command_for_analysis(&resolved));
+        assert!(rendered.contains("_BOOT_ID=aaaa") && rendered.contains("_BOOT_ID=cccc"));
+        assert!(!rendered.contains("--boot"));
+    }
+
+    #[test]
+    fn analyze_mode_rejects_follow() {
+        let err = parse(&["--follow"]).expect_err("解析应失败");
+        assert!(err.contains("--follow"));
+    }
+
+    #[test]
+    fn top_must_be_positive() {
+        let err = parse(&["--top", "0"]).expect_err("解析应失败");
+        assert!(err.contains("--top"));
+    }
+
+    #[test]
+    fn priority_alias_warning_normalizes_to_numeric() {
+        let action = parse(&["--priority", "warning"]).expect("解析应成功");
+        let Action::Run(config) = action else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(config.priority, Priority::up_to(4));
+        assert_eq!(config.priority.to_string(), "4");
+    }
+
+    #[test]
+    fn priority_invalid_value_is_rejected() {
+        let err = parse(&["--priority", "verbose"]).expect_err("解析应失败");
+        assert!(err.contains("无效优先级"));
+    }
+
+    #[test]
+    fn priority_range_is_normalized_and_validated() {
+        let Action::Run(config) = parse(&["--priority", "warning..CRIT"]).expect("解析应成功")
+        else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(config.priority, Priority { from: 2, to: 4 });
+        assert_eq!(config.priority.threshold(), 4);
+        assert!(config.priority.contains(3) && !config.priority.contains(1));
+        assert!(
+            render_command(&build_journalctl_command_for_analysis(&config))
+                .contains("--priority=2..4")
+        );
+        assert_eq!("0..3".parse::<Priority>(), Ok(Priority::up_to(3)));
+        assert_eq!(
+            "2..2".parse::<Priority>().map(|p| p.to_string()),
+            Ok("2..2".to_string())
+        );
+        for bad in ["..4", "2..", "2..9", "1..2..3", "err.."] {
+            assert!(parse(&["--priority", bad]).is_err(), "{bad}");
+        }
+
+        // 请求也可能不经 CLI 直接发给 daemon：反序列化即校验
+        let mut raw = serde_json::to_value(Config::default()).expect("序列化应成功");
+        assert_eq!(raw["priority"], "3");
+        raw["priority"] = Value::from("3; rm");
+        assert!(serde_json::from_value::<Config>(raw).is_err());
+    }
+
+    #[test]
+    fn stream_follow_honors_explicit_max_lines() {
+        let action = parse(&["--stream", "--follow", "--max-lines", "20"]).expect("解析应成功");
+        let Action::Run(config) = action else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(config.max_lines, Some(20));
+    }
+
+    #[test]
+    fn output_path_is_parsed() {
+        let action = parse(&["--output=/tmp/report.txt"]).expect("解析应成功");
+        let Action::Run(config) = action else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(config.output_path.as_deref(), Some("/tmp/report.txt"));
+    }
+
+    #[test]
+    fn output_path_rejects_empty_value() {
+        let err = parse(&["--output="]).expect_err("解析应失败");
+        assert!(err.contains("--output"));
+    }
+
+    #[test]
+    fn forward_requires_stream_mode() {
+        let err = parse(&["--forward", "/run/user/1000/feed"]).expect_err("解析应失败");
+        assert!(err.contains("--forward"));
+
+        let action = parse(&["--stream", "--forward=/tmp/feed"]).expect("解析应成功");
+        let Action::Run(config) = action else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(config.forward_path.as_deref(), Some("/tmp/feed"));
+    }
+
+    #[test]
+    fn rotating_writer_rotates_after_limit() {
+        let dir = env::temp_dir().join(format!("logtool-rotate-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("创建临时目录应成功");
+        let path = dir.join("stream.log");
+        let _ = fs::remove_file(&path);
+
+        let mut writer = RotatingFileWriter::open(&path, 8).expect("打开应成功");
+        writer.write_all(b"12345\n").expect("写入应成功");
+        writer.write_all(b"67890\n").expect("写入应成功");
+        writer.flush().expect("刷新应成功");
+
+        let rotated = fs::read_to_string(dir.join("stream.log.1")).expect("应生成轮转文件");
+        let current = fs::read_to_string(&path).expect("应存在当前文件");
+        assert_eq!(rotated, "12345\n");
+        assert_eq!(current, "67890\n");
+
+        // 分两次写入的一行越过阈值时不拆开，下一行开头再轮转
+        writer.write_all(b"ab").expect("写入应成功");
+        writer.write_all(b"cdef\n").expect("写入应成功");
+        writer.write_all(b"gh\n").expect("写入应成功");
+        writer.flush().expect("刷新应成功");
+        let rotated = fs::read_to_string(dir.join("stream.log.1")).expect("应生成轮转文件");
+        let current = fs::read_to_string(&path).expect("应存在当前文件");
+        assert_eq!(rotated, "67890\nabcdef\n");
+        assert_eq!(current, "gh\n");
+
+        // 重新打开时沿用文件末尾是否停在行中间
+        fs::write(&path, "partial").expect("写入应成功");
+        let mut writer = RotatingFileWriter::open(&path, 8).expect("打开应成功");
+        writer.write_all(b" line\n").expect("写入应成功");
+        writer.flush().expect("刷新应成功");
+        assert_eq!(
+            fs::read_to_string(&path).expect("应存在当前文件"),
+            "partial line\n"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn parses_boot_offsets_and_skips_header() {
+        let out = "IDX BOOT ID                          FIRST ENTRY                 LAST ENTRY\n\
+                    -2 1b2c... Mon 2024-01-01 10:00:00 CST Mon 2024-01-01 12:00:00 CST\n\
+                    -1 2c3d... Tue 2024-01-02 09:00:00 CST Tue 2024-01-02 18:00:00 CST\n\
+                     0 3d4e... Wed 2024-01-03 08:00:00 CST Wed 2024-01-03 09:30:00 CST\n";
+        assert_eq!(parse_boot_offsets(out), vec![-2, -1, 0]);
+    }
+
+    #[test]
+    fn compare_requires_two_distinct_units() {
+        let Ok(Action::Run(config)) = parse(&["compare", "-u", "a", "--unit", "b.socket"]) else {
+            panic!("compare 应解析为运行动作");
+        };
+        assert_eq!(config.mode, RunMode::Compare);
+        assert_eq!(config.units, vec!["a", "b.socket"]);
+        assert!(parse(&["compare", "-u", "a", "-u", "b", "--format", "json"]).is_ok());
+
+        assert!(parse(&["compare", "-u", "a"]).is_err());
+        assert!(parse(&["compare", "-u", "a", "-u", "a"]).is_err());
+        assert!(parse(&["compare", "-u", "a", "-u", "b", "-u", "c"]).is_err());
+        assert!(parse(&["compare", "-u", "a", "-u", "b", "--stream"]).is_err());
+        assert!(parse(&["compare", "-u", "a", "-u", "b", "--timeline"]).is_err());
+    }
+
+    #[test]
+    fn last_words_parses_args_and_detects_clean_shutdown() {
+        assert_eq!(
+            parse(&["last-words"]),
+            Ok(Action::LastWords {
+                boot: "-1".to_string(),
+                lines: DEFAULT_LAST_WORDS_LINES
+            })
+        );
+        assert_eq!(
+            parse(&["last-words", "--boot=-3", "-n", "20"]),
+            Ok(Action::LastWords {
+                boot: "-3".to_string(),
+                lines: 20
+            })
+        );
+        assert!(parse(&["last-words", "--boot", "1"]).is_err());
+        assert!(parse(&["last-words", "--lines", "0"]).is_err());
+
+        let boots =
+            parse_boot_list("IDX BOOT ID FIRST ENTRY LAST ENTRY\n -1 2c3d Tue\n  0 3d4e Wed\n");
+        assert_eq!(
+            boots,
+            vec![(-1, "2c3d".to_string()), (0, "3d4e".to_string())]
+        );
+
+        let event = |identifier: &str, message: &str| JournalEvent {
+            message: message.to_string(),
+            priority: Some(6),
+            identifier: Some(identifier.to_string()),
+            timestamp_us: Some(1_704_069_009_000_000),
+            ..JournalEvent::default()
+        };
+        let froze = vec![
+            event("kernel", "usb 1-1: reset"),
+            event("cron", "job started"),
+        ];
+        assert!(!looks_like_clean_shutdown(&froze));
+        let clean = vec![event("systemd-journald", "Journal stopped")];
+        assert!(looks_like_clean_shutdown(&clean));
+        assert_eq!(
+            format_last_words_line(&froze[1], 8 * 3600),
+            "2024-01-01 08:30:09 [info  ] cron: job started"
+        );
+    }
+
+    #[test]
+    fn boot_presence_command_targets_suspect_field() {
+        let config = Config::default();
+        let field = suspect_journal_match(SourceKind::Unit, "ssh.service").expect("应有匹配");
+        let cmd = boot_presence_command(&config, -1, &field);
+        let rendered = render_command(&cmd);
+        assert!(rendered.contains("--boot=-1"));
+        assert!(rendered.contains("--priority=3"));
+        assert!(rendered.ends_with("'_SYSTEMD_UNIT=ssh.service'"));
+        assert_eq!(suspect_journal_match(SourceKind::Unknown, "unknown"), None);
+    }
+
+    #[test]
+    fn namespace_is_validated_and_passed_to_every_query() {
+        let Action::Run(config) = parse(&["--namespace", "+billing"]).expect("解析应成功")
+        else {
+            panic!("应为 Run");
+        };
+        assert_eq!(config.namespace.as_deref(), Some("+billing"));
+        assert!(
+            render_command(&build_journalctl_command_for_analysis(&config))
+                .contains("--namespace=+billing")
+        );
+        let cmd = boot_presence_command(&config, -1, "_SYSTEMD_UNIT=a.service");
+        assert!(render_command(&cmd).contains("--namespace=+billing"));
+
+        assert!(parse(&["--namespace", "*"]).is_ok());
+        assert!(parse(&["--namespace", "a b"]).is_err());
+        assert!(parse(&["--namespace", "+"]).is_err());
+    }
+
+    #[test]
+    fn directory_is_absolute_and_reads_everything_by_default() {
+        let dir = env::temp_dir();
+        let dir_str = dir.to_string_lossy().into_owned();
+        let Action::Run(config) = parse(&["-D", &dir_str]).expect("解析应成功") else {
+            panic!("应为 Run");
+        };
+        let expected = fs::canonicalize(&dir).expect("临时目录");
+        assert_eq!(config.directory.as_deref(), expected.to_str());
+        assert_eq!(config.since, None);
+        let rendered = render_command(&build_journalctl_command_for_analysis(&config));
+        assert!(rendered.contains(&format!("--directory={}", expected.display())));
+        assert!(!rendered.contains("--since"));
+        let cmd = boot_presence_command(&config, -1, "_SYSTEMD_UNIT=a.service");
+        assert!(render_command(&cmd).contains("--directory="));
+
+        let Action::Run(config) =
+            parse(&["--directory", &dir_str, "--since", "yesterday"]).expect("解析应成功")
+        else {
+            panic!("应为 Run");
+        };
+        assert_eq!(config.since.as_deref(), Some("yesterday"));
+
+        assert!(parse(&["--directory", "/nonexistent/journal"]).is_err());
+        assert!(parse(&["--directory", "/etc/hostname"]).is_err());
+        assert!(parse(&["--directory", &dir_str, "--namespace", "x"]).is_err());
+    }
+
+    #[test]
+    fn journal_files_repeat_and_read_everything_by_default() {
+        let dir = env::temp_dir().join(format!("logtool-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("创建临时目录");
+        let (a, b) = (dir.join("a.journal"), dir.join("b.journal"));
+        fs::write(&a, b"").expect("写入");
+        fs::write(&b, b"").expect("写入");
+        let (a_str, b_str) = (
+            a.to_string_lossy().into_owned(),
+            b.to_string_lossy().into_owned(),
+        );
+
+        let Action::Run(config) =
+            parse(&["--file", &a_str, &format!("--file={b_str}")]).expect("解析应成功")
+        else {
+            panic!("应为 Run");
+        };
+        let expected = [&a, &b].map(|p| fs::canonicalize(p).expect("规范化").display().to_string());
+        assert_eq!(config.files, expected);
+        assert_eq!(config.since, None);
+        let rendered = render_command(&build_journalctl_command_for_analysis(&config));
+        assert!(rendered.contains(&format!("--file={}", expected[0])));
+        assert!(rendered.contains(&format!("--file={}", expected[1])));
+
+        let dir_str = dir.to_string_lossy().into_owned();
+        assert!(parse(&["--file", &dir_str]).is_err());
+        assert!(parse(&["--file", "/nonexistent/system.journal"]).is_err());
+        assert!(parse(&["--file", &a_str, "--directory", &dir_str]).is_err());
+        assert!(parse(&["--file", &a_str, "--namespace", "x"]).is_err());
+        fs::remove_dir_all(&dir).expect("清理");
+    }
+
+    #[test]
+    fn validate_config_rechecks_journal_paths_from_raw_requests() {
+        let file = env::temp_dir().join(format!("logtool-recheck-{}.journal", std::process::id()));
+        fs::write(&file, b"").expect("写入");
+        let file_str = file.to_string_lossy().into_owned();
+        let tmp = env::temp_dir().to_string_lossy().into_owned();
+
+        let directory = |dir: &str| Config {
+            directory: Some(dir.to_string()),
+            ..Config::default()
+        };
+        assert!(validate_config(&directory(&tmp)).is_ok());
+        for dir in ["/nonexistent/journal", "var/log/journal", file_str.as_str()] {
+            assert!(validate_config(&directory(dir)).is_err(), "{dir}");
+        }
+        let files = |path: &str| Config {
+            files: vec![path.to_string()],
+            ..Config::default()
+        };
+        assert!(validate_config(&files(&file_str)).is_ok());
+        for path in [
+            "/dev/zero",
+            "/nonexistent/system.journal",
+            "system.journal",
+            &tmp,
+        ] {
+            assert!(validate_config(&files(path)).is_err(), "{path}");
+        }
+        fs::remove_file(&file).expect("清理");
+    }
+
+    #[test]
+    fn validate_config_rechecks_syslog_paths_from_raw_requests() {
+        for path in ["/dev/zero", "/nonexistent/syslog", "var/log/syslog", "/tmp"] {
+            let config = Config {
+                syslog_files: vec![path.to_string()],
+                since: None,
+                ..Config::default()
+            };
+            assert!(validate_config(&config).is_err(), "{path}");
+        }
+    }
+
+    #[test]
+    fn syslog_files_reject_journal_only_filters() {
+        let dir = env::temp_dir().join(format!("logtool-syslog-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("创建临时目录");
+        let path = dir.join("syslog");
+        fs::write(&path, b"").expect("写入");
+        let path_str = path.to_string_lossy().into_owned();
+
+        let Action::Run(config) = parse(&["--syslog-file", &path_str]).expect("解析应成功")
+        else {
+            panic!("应为 Run");
+        };
+        assert_eq!(config.syslog_files.len(), 1);
+        assert_eq!(config.since, None);
+        assert!(parse(&["--syslog-file", &path_str, "--identifier", "sshd"]).is_ok());
+        for extra in [
+            &["--unit", "nginx.service"][..],
+            &["--boot"],
+            &["--stream"],
+            &["--file", &path_str],
+            &["--with-failed-units"],
+        ] {
+            let mut args = vec!["--syslog-file", path_str.as_str()];
+            args.extend_from_slice(extra);
+            assert!(parse(&args).is_err(), "{extra:?} 应被拒绝");
+        }
+        // 报错中给出的是实际存在的选项名
+        let err =
+            parse(&["--syslog-file", &path_str, "--user-filter", "1000"]).expect_err("应被拒绝");
+        assert!(err.contains("--user-filter 所需"), "{err}");
+        assert!(parse(&["watch", "--syslog-file", &path_str]).is_err());
+        fs::remove_dir_all(&dir).expect("清理");
+    }
+
+    #[test]
+    fn match_expressions_pass_through_with_or_groups() {
+        let Action::Run(config) = parse(&[
+            "--match",
+            "_TRANSPORT=kernel",
+            "--match",
+            "+",
+            "--match",
+            "_SYSTEMD_USER_UNIT=pipewire.service",
+        ])
+        .expect("解析应成功") else {
+            panic!("应为 Run");
+        };
+        let rendered = render_command(&build_journalctl_command_for_analysis(&config));
+        assert!(rendered.contains(" '_TRANSPORT=kernel' + '_SYSTEMD_USER_UNIT=pipewire.service'"));
+        assert!(filters_narrow_window(&config));
+
+        assert!(parse(&["--match", "transport=kernel"]).is_err());
+        assert!(parse(&["--match", "1X=y"]).is_err());
+        assert!(parse(&["--match", "MESSAGE"]).is_err());
+        assert!(parse(&["--match", "+", "--match", "A=1"]).is_err());
+        assert!(
+            parse(&[
+                "--match", "A=1", "--match", "+", "--match", "+", "--match", "B=2"
+            ])
+            .is_err()
+        );
+        assert!(
+            parse(&[
+                "--pid", "1", "--match", "A=1", "--match", "+", "--match", "B=2"
+            ])
+            .is_err()
+        );
+        // 不含 `+` 时与专用参数一起都是“且”
+        assert!(parse(&["--pid", "1", "--match", "A="]).is_ok());
+    }
+
+    #[test]
+    fn pid_and_cgroup_become_journal_matches() {
+        let Action::Run(config) =
+            parse(&["--pid", "1234", "--cgroup", "0::/system.slice/ssh.service"])
+                .expect("解析应成功")
+        else {
+            panic!("应为 Run");
+        };
+        assert_eq!(config.pids, vec![1234]);
+        assert_eq!(
+            config.cgroups,
+            vec!["/system.slice/ssh.service".to_string()]
+        );
+        let rendered = render_command(&build_journalctl_command_for_analysis(&config));
+        assert!(rendered.contains(" '_PID=1234'"));
+        assert!(rendered.contains(" '_SYSTEMD_CGROUP=/system.slice/ssh.service'"));
+
+        assert!(parse(&["--pid", "0"]).is_err());
+        assert!(parse(&["--pid", "abc"]).is_err());
+        assert!(parse(&["--cgroup", "system.slice"]).is_err());
+    }
+
+    #[test]
+    fn identifier_maps_to_journalctl_identifier() {
+        let Action::Run(config) =
+            parse(&["-t", "sshd", "--identifier", "kernel"]).expect("解析应成功")
+        else {
+            panic!("应为 Run");
+        };
+        assert_eq!(config.identifiers, vec!["sshd", "kernel"]);
+        let rendered = render_command(&build_journalctl_command_for_analysis(&config));
+        assert!(rendered.contains(" '--identifier=sshd' '--identifier=kernel'"));
+        assert!(filters_narrow_window(&config));
+        assert!(parse(&["-t", " "]).is_err());
+    }
+
+    #[test]
+    fn comm_matches_kernel_truncated_name() {
+        let Action::Run(config) =
+            parse(&["--comm", "python3", "--comm", "systemd-journald-extra"]).expect("解析应成功")
+        else {
+            panic!("应为 Run");
+        };
+        assert_eq!(config.comms, vec!["python3", "systemd-journal"]);
+        let rendered = render_command(&build_journalctl_command_for_analysis(&config));
+        assert!(rendered.contains(" '_COMM=python3' '_COMM=systemd-journal'"));
+        assert!(parse(&["--comm", ""]).is_err());
+    }
+
+    #[test]
+    fn user_filter_resolves_names_to_uid_matches() {
+        let Action::Run(config) =
+            parse(&["--user-filter", "1000", "--user-filter", "root"]).expect("解析应成功")
+        else {
+            panic!("应为 Run");
+        };
+        assert_eq!(config.uids, vec![1000, 0]);
+        let rendered = render_command(&build_journalctl_command_for_analysis(&config));
+        assert!(rendered.contains(" '_UID=1000' '_UID=0'"));
+
+        assert!(parse(&["--user-filter", "no-such-user-logtool"]).is_err());
+        assert!(parse(&["--user-filter", "-1"]).is_err());
+    }
+
+    fn sample_response(suspects: Vec<SourceStats>) -> AnalyzeResponse {
+        let matched = suspects.iter().map(|s| s.count as usize).sum();
+        AnalyzeResponse {
+            metrics: AnalyzeMetrics {
+                lines_read: matched,
+                parsed_ok: matched,
+                matched,
+                parse_errors: 0,
+                in_maintenance: 0,
+                excluded: 0,
+                priority_counts: Vec::new(),
+                emergency_outside_filter: 0,
+                last_hour: 0,
+                duplicates: 0,
+            },
+            suspects,
+            top: DEFAULT_TOP,
+            environment: None,
+            rule_hits: Vec::new(),
+            namespace: None,
+            directory: None,
+            files: Vec::new(),
+            syslog_files: Vec::new(),
+            kernel_fallback: None,
+            hosts: Vec::new(),
+            boots: Vec::new(),
+            timeline: Vec::new(),
+            histogram: None,
+            bursts: Vec::new(),
+            trend: None,
+            baseline: None,
+            reboots: Vec::new(),
+            crashes: Vec::new(),
+            hangs: Vec::new(),
+            network: Vec::new(),
+            hardware: Vec::new(),
+            audit: Vec::new(),
+            failed_units: Vec::new(),
+            root_causes: Vec::new(),
+            smart: Vec::new(),
+            by_package: None,
+            by_user: None,
+            per_boot: None,
+            journalctl_usage: None,
+            truncated: None,
+            cached_at: None,
+            evidence: Vec::new(),
+        }
+    }
+
+    /// 与 tests/golden/ 下的文件逐字比较；设置 LOGTOOL_UPDATE_GOLDEN=1 运行测试即重新生成
+    fn assert_golden(name: &str, actual: &[u8]) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(name);
+        if env::var_os("LOGTOOL_UPDATE_GOLDEN").is_some() {
+            fs::write(&path, actual).expect("写入 golden 文件应成功");
         }
+        let expected = fs::read(&path).unwrap_or_else(|e| {
+            panic!(
+                "读取 {} 失败：{e}（运行 LOGTOOL_UPDATE_GOLDEN=1 cargo test 生成）",
+                path.display()
+            )
+        });
+        assert!(
+            actual == expected.as_slice(),
+            "{name} 与 golden 文件不一致，输出变化是有意的话运行 LOGTOOL_UPDATE_GOLDEN=1 cargo test 更新：\n{}",
+            String::from_utf8_lossy(actual)
+        );
+    }
+
+    /// 覆盖各渲染器大部分分支的固定响应；来源按乱序给出，由 rank_suspects 排定
+    fn golden_response() -> AnalyzeResponse {
+        let mut nginx = sample_suspect(SourceKind::Unit, "nginx.service", 40, 2);
+        nginx.sample_message = "upstream timed out (110: Connection timed out)".to_string();
+        nginx.sample_unit = Some("nginx.service".to_string());
+        nginx.sample_exe = Some("/usr/sbin/nginx".to_string());
+        nginx.package = Some("nginx-core".to_string());
+        nginx.boot_presence = Some(BootPresence {
+            seen: 3,
+            checked: 5,
+        });
+        nginx.maintenance_count = 4;
+        nginx.first_us = Some(1_709_214_310_000_000);
+        nginx.last_us = Some(1_709_214_310_000_000 + 13 * 60_000_000);
+        nginx.unit_failed = true;
+        nginx.boot_span = Some(BootSpan {
+            boots: 2,
+            after_reboot_us: None,
+        });
+        // 最近失败时间随本机时区变化，golden 中不填
+        nginx.unit_events = Some(UnitLifecycle {
+            unit: "nginx.service".to_string(),
+            starts: 3,
+            restarts: 2,
+            failures: 3,
+            ..UnitLifecycle::default()
+        });
+        nginx.priority_counts = vec![0, 0, 2, 38, 0, 0, 0, 0];
+        nginx.samples = vec![
+            SampleMessage {
+                message: "worker process 812 exited on signal 11".to_string(),
+                priority: Some(2),
+                count: 2,
+            },
+            SampleMessage {
+                message: nginx.sample_message.clone(),
+                priority: Some(3),
+                count: 38,
+            },
+        ];
+
+        // 与 kernel 同事件数、同级别：按名称排在前面
+        let mut php = sample_suspect(SourceKind::Executable, "/usr/bin/php8.3", 12, 3);
+        php.sample_message = "PHP Fatal error:  Allowed memory size exhausted".to_string();
+        php.sample_exe = Some("/usr/bin/php8.3".to_string());
+        php.package = Some("php8.3-cli".to_string());
+        php.priority_counts = vec![0, 0, 0, 12, 0, 0, 0, 0];
+        php.top_messages = vec![
+            MessageCount {
+                message: php.sample_message.clone(),
+                count: 9,
+            },
+            MessageCount {
+                message: "PHP Warning:  Undefined array key \"id\"".to_string(),
+                count: 3,
+            },
+        ];
+
+        let mut disk = sample_suspect(SourceKind::Storage, "sda", 12, 3);
+        disk.sample_message = "EXT4-fs error (device sda1): htree_dirblock_to_tree".to_string();
+        disk.hint = Some(Hint {
+            rule_id: "kb-filesystem-error".to_string(),
+            explanation: Some("EXT4 文件系统检测到元数据损坏".to_string()),
+            suggestion: "卸载后运行 fsck -f <设备>".to_string(),
+        });
+        disk.priority_counts = vec![0, 0, 0, 12, 0, 0, 0, 0];
+
+        // 规则把 err 降级为 debug，排到查询级别之内的来源之后
+        let mut cron = sample_suspect(SourceKind::Identifier, "CRON", 30, 7);
+        cron.sample_message = "pam_unix(cron:session): session opened".to_string();
+        cron.severity_override = Some(SeverityOverride {
+            rule_id: "cron-noise".to_string(),
+            raw_worst_priority: Some(3),
+            events: 30,
+        });
+        cron.priority_counts = vec![0, 0, 0, 0, 0, 0, 0, 30];
+
+        let mut suspects = vec![cron, disk, php, nginx];
+        rank_suspects(&mut suspects, DEFAULT_PRIORITY.threshold());
+
+        let mut response = sample_response(suspects);
+        response.metrics.lines_read = 97;
+        response.metrics.parsed_ok = 96;
+        response.metrics.parse_errors = 1;
+        response.metrics.excluded = 2;
+        response.metrics.in_maintenance = 4;
+        response.metrics.priority_counts = vec![0, 0, 2, 62, 0, 0, 0, 30];
+        response.rule_hits = vec![RuleHit {
+            id: "cron-noise".to_string(),
+            count: 30,
+        }];
+        response.failed_units = vec!["backup.timer".to_string()];
+        // 崩溃时间随本机时区变化，golden 中不填
+        response.crashes = vec![Crash {
+            process: "php-fpm8.3".to_string(),
+            object: Some("libfoo.so.1".to_string()),
+            fault: "segfault".to_string(),
+            count: 2,
+            last_pid: 4121,
+            last_us: None,
+            package: Some("libfoo1".to_string()),
+        }];
+        response
+    }
+
+    #[test]
+    fn renderers_match_golden_files() {
+        let response = golden_response();
+        let order = response
+            .suspects
+            .iter()
+            .map(|s| suspect_id(s.kind, &s.source))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![
+                "unit:nginx.service",
+                "exe:/usr/bin/php8.3",
+                "storage:sda",
+                "identifier:CRON"
+            ]
+        );
+
+        let mut text = Vec::new();
+        write_analysis_report(&mut text, &response).expect("写入应成功");
+        assert_golden("report.txt", &text);
 
-        if suspect.kind == SourceKind::Unit {
-            return self.package_by_unit(&suspect.source);
-        }
+        let mut linear = Vec::new();
+        write_linear_report(&mut linear, &response).expect("写入应成功");
+        assert_golden("report-linear.txt", &linear);
 
-        None
-    }
+        let mut json = Vec::new();
+        write_json_line(&mut json, &response, "分析结果").expect("写入应成功");
+        assert_golden("report.json", &json);
 
-    fn package_by_path(&mut self, path: &str) -> Option<String> {
-        if path.is_empty() || !path.starts_with('/') {
-            return None;
-        }
+        #[cfg(feature = "exporters")]
+        {
+            let mut ndjson = Vec::new();
+            write_ndjson_suspects(&mut ndjson, &response).expect("写入应成功");
+            assert_golden("suspects.ndjson", &ndjson);
 
-        if let Some(cached) = self.path_cache.get(path) {
-            return cached.clone();
+            let mut logfmt = Vec::new();
+            write_logfmt_suspects(&mut logfmt, &response).expect("写入应成功");
+            assert_golden("suspects.logfmt", &logfmt);
         }
 
-        let output = Command::new("dpkg-query")
-            .arg("-S")
-            .arg(path)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .output();
+        let summary = format_summary_line(&response) + "\n";
+        assert_golden("summary.txt", summary.as_bytes());
+    }
 
-        let resolved = match output {
-            Ok(out) if out.status.success() => {
-                parse_dpkg_search_output(&String::from_utf8_lossy(&out.stdout))
-            }
-            _ => None,
+    #[test]
+    fn truncation_keeps_utf8_and_json_valid() {
+        let text = "磁盘错误".repeat(40);
+        let cut = truncate_with_marker(&text, 64);
+        assert!(cut.len() <= 64);
+        assert!(cut.ends_with(&format!(
+            "[截断 {} 字节]",
+            text.len() - cut.find('…').unwrap()
+        )));
+        assert_eq!(truncate_with_marker("short", 64), "short");
+
+        let line = format!(
+            r#"{{"MESSAGE":"{}\"quoted\"","PRIORITY":"3","_SYSTEMD_UNIT":"a.service"}}"#,
+            "x".repeat(500)
+        );
+        let cut = truncate_stream_line(line, 200, true);
+        assert!(cut.len() <= 200);
+        let event = parse_json_event(&cut).expect("截断后仍是有效 JSON");
+        assert_eq!(event.unit.as_deref(), Some("a.service"));
+        assert!(event.message.contains("[截断 "));
+        assert!(truncate_stream_line("a".repeat(100), 80, false).len() <= 80);
+
+        assert!(parse(&["--stream", "--max-line-bytes", "32"]).is_err());
+        assert!(parse(&["--max-resp-bytes", "100"]).is_err());
+        let Action::Run(config) = parse(&["--max-msg-chars", "40"]).expect("解析应成功")
+        else {
+            panic!("应为 Run");
         };
+        assert_eq!(config.max_message_chars, Some(40));
+    }
+
+    #[test]
+    fn fit_response_drops_detail_then_tail_suspects() {
+        let suspects = (0..200)
+            .map(|i| {
+                let mut suspect =
+                    sample_suspect(SourceKind::Unit, &format!("u{i:03}.service"), 5, 3);
+                suspect.sample_message = "m".repeat(150);
+                suspect.samples = vec![SampleMessage {
+                    message: "s".repeat(150),
+                    priority: Some(3),
+                    count: 5,
+                }];
+                suspect
+            })
+            .collect();
+        let mut response = sample_response(suspects);
+        let matched = response.metrics.matched;
+        fit_response(&mut response, 8192);
+
+        let json = serde_json::to_string(&response).expect("序列化");
+        assert!(json.len() <= 8192);
+        let truncation = response.truncated.clone().expect("应注明截断");
+        assert!(truncation.samples_dropped && !truncation.timeline_dropped);
+        assert_eq!(truncation.omitted_suspects + response.suspects.len(), 200);
+        assert_eq!(response.suspects[0].source, "u000.service");
+        assert_eq!(response.metrics.matched, matched);
+        assert!(truncation_summary(&truncation).ends_with(&format!(
+            "多条示例消息和排名最后的 {} 个来源",
+            truncation.omitted_suspects
+        )));
+
+        let mut small = sample_response(vec![sample_suspect(SourceKind::Kernel, "kernel", 1, 3)]);
+        fit_response(&mut small, MIN_RESPONSE_BYTES);
+        assert_eq!(small.truncated, None);
+
+        // 原始条目最先去掉
+        small.evidence = vec![SuspectEvidence {
+            id: "kernel:kernel".to_string(),
+            entries: vec!["{}".repeat(MIN_RESPONSE_BYTES)],
+        }];
+        fit_response(&mut small, MIN_RESPONSE_BYTES);
+        let truncation = small.truncated.clone().expect("应注明截断");
+        assert!(truncation.evidence_dropped && !truncation.samples_dropped);
+        assert_eq!(small.suspects.len(), 1);
+        assert!(truncation_summary(&truncation).ends_with("省略了--evidence 原始条目"));
+    }
 
-        self.path_cache.insert(path.to_string(), resolved.clone());
+    #[test]
+    fn merged_journals_are_counted_per_host() {
+        let Action::Run(config) = parse(&["--merge"]).expect("解析应成功") else {
+            panic!("应为 Run");
+        };
+        let rendered = render_command(&build_journalctl_command_for_analysis(&config));
+        assert!(rendered.contains("--merge") && rendered.contains("_HOSTNAME"));
+        let event = parse_json_event(r#"{"MESSAGE":"x","_HOSTNAME":"web-1"}"#).expect("解析");
+        assert_eq!(event.hostname.as_deref(), Some("web-1"));
+
+        let host = |host: &str, count| HostCount {
+            host: host.to_string(),
+            count,
+        };
+        let mut nginx = sample_suspect(SourceKind::Unit, "nginx.service", 46, 3);
+        nginx.hosts = vec![
+            host("web-2", 10),
+            host("web-1", 30),
+            host("web-4", 1),
+            host("web-3", 5),
+        ];
+        let mut sshd = sample_suspect(SourceKind::Unit, "ssh.service", 2, 3);
+        sshd.hosts = vec![host("web-1", 2)];
+        let mut suspects = vec![nginx, sshd];
+        assert_eq!(
+            collect_hosts(&mut suspects),
+            vec!["web-1", "web-2", "web-3", "web-4"]
+        );
+        assert_eq!(
+            host_breakdown(&suspects[0].hosts),
+            "web-1 ×30、web-2 ×10、web-3 ×5 等 4 台"
+        );
 
-        resolved
+        let mut response = sample_response(suspects);
+        response.hosts = vec!["web-1".to_string(), "web-2".to_string()];
+        let mut text = Vec::new();
+        write_analysis_report(&mut text, &response).expect("写入应成功");
+        let text = String::from_utf8(text).expect("UTF-8");
+        assert!(text.contains("主机        ：2 台（web-1、web-2）"));
+        assert!(text.contains("     主机    ：web-1 ×2"));
+
+        // 单台主机：不显示主机信息，JSON 与单机日志一致
+        let mut single = vec![sample_suspect(SourceKind::Kernel, "kernel", 1, 3)];
+        single[0].hosts = vec![host("laptop", 1)];
+        assert!(collect_hosts(&mut single).is_empty());
+        assert!(single[0].hosts.is_empty());
     }
 
-    fn package_by_unit(&mut self, unit: &str) -> Option<String> {
-        if !self.systemctl_available {
-            return None;
-        }
+    #[test]
+    fn ranking_breaks_ties_by_kind_then_package() {
+        let mut suspects = vec![
+            sample_suspect(SourceKind::Comm, "sshd", 5, 3),
+            sample_suspect(SourceKind::Identifier, "sshd", 5, 3),
+            sample_suspect(SourceKind::Unit, "sshd", 5, 3),
+        ];
+        suspects[1].package = Some("openssh-server".to_string());
+        rank_suspects(&mut suspects, 3);
+        let kinds = suspects.iter().map(|s| s.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![SourceKind::Unit, SourceKind::Identifier, SourceKind::Comm]
+        );
 
-        if let Some(cached) = self.unit_cache.get(unit) {
-            return cached.clone();
-        }
+        let mut a = sample_suspect(SourceKind::Rule, "ssh", 5, 3);
+        let mut b = a.clone();
+        a.package = Some("openssh-server".to_string());
+        b.package = Some("openssh-client".to_string());
+        assert_eq!(compare_suspects(&b, &a), Ordering::Less);
+    }
 
-        let fragment_path = Command::new("systemctl")
-            .arg("show")
-            .arg("--property=FragmentPath")
-            .arg("--value")
-            .arg(unit)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .output();
+    #[test]
+    fn samples_keep_distinct_messages_preferring_severity() {
+        let mut samples = Vec::new();
+        record_sample(&mut samples, "a", Some(3), 2);
+        record_sample(&mut samples, "b", Some(6), 2);
+        record_sample(&mut samples, "a", Some(2), 2);
+        record_sample(&mut samples, "c", Some(4), 2);
+        // b 级别最低被淘汰；同级别时保留较新的
+        record_sample(&mut samples, "d", Some(4), 2);
+        order_samples(&mut samples);
+
+        let view = samples
+            .iter()
+            .map(|s| (s.message.as_str(), s.priority, s.count))
+            .collect::<Vec<_>>();
+        assert_eq!(view, vec![("a", Some(2), 2), ("d", Some(4), 1)]);
+    }
 
-        let resolved = match fragment_path {
-            Ok(out) if out.status.success() => {
-                let path = String::from_utf8_lossy(&out.stdout).trim().to_string();
-                if path.is_empty() {
-                    None
-                } else {
-                    self.package_by_path(&path)
-                }
-            }
-            _ => None,
+    #[test]
+    fn samples_flag_requires_analysis_report() {
+        let action = parse(&["--samples", "3"]).expect("应成功");
+        let Action::Run(config) = action else {
+            panic!("应为 Run");
         };
+        assert_eq!(config.samples, Some(3));
+        assert!(parse(&["--stream", "--samples=3"]).is_err());
+        assert!(parse(&["--samples", "0"]).is_err());
+    }
 
-        self.unit_cache.insert(unit.to_string(), resolved.clone());
-        resolved
+    #[test]
+    fn clusters_are_listed_in_text_and_linear_reports() {
+        let Ok(Action::Run(config)) = parse(&["--clusters", "2"]) else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(config.clusters, Some(2));
+        assert!(parse(&["--summary-only", "--clusters=2"]).is_err());
+        assert!(parse(&["compare", "--unit", "a", "--unit", "b", "--clusters", "2"]).is_err());
+
+        let mut suspect = sample_suspect(SourceKind::Unit, "app.service", 7, 3);
+        suspect.clusters = vec![
+            MessageCluster {
+                template: "connection to <ip> failed".to_string(),
+                count: 5,
+                example: "connection to 10.0.0.9 failed".to_string(),
+            },
+            MessageCluster {
+                template: "retry <n> of <n>".to_string(),
+                count: 2,
+                example: "retry 2 of 5".to_string(),
+            },
+        ];
+        let response = sample_response(vec![suspect]);
+        let mut text = Vec::new();
+        write_analysis_report(&mut text, &response).expect("写入应成功");
+        let text = String::from_utf8(text).expect("UTF-8");
+        assert!(text.contains("     消息聚类（前 2 类）：\n       ×5 connection to <ip> failed\n       ×2 retry <n> of <n>\n"), "{text}");
+
+        let mut linear = Vec::new();
+        write_linear_report(&mut linear, &response).expect("写入应成功");
+        let linear = String::from_utf8(linear).expect("UTF-8");
+        assert!(linear.contains("消息类别 1，出现 5 次：connection to <ip> failed。"));
     }
-}
 
-fn parse_dpkg_search_output(output: &str) -> Option<String> {
-    let line = output.lines().find(|line| line.contains(':'))?.trim();
-    let mut split = line.splitn(2, ':');
-    let pkg = split.next()?.trim();
-    if pkg.is_empty() {
-        return None;
+    #[test]
+    fn report_marks_rule_severity_adjustment() {
+        let mut suspect = sample_suspect(SourceKind::Rule, "acpi-noise", 40, 6);
+        suspect.severity_override = Some(SeverityOverride {
+            rule_id: "acpi-benign".to_string(),
+            raw_worst_priority: Some(3),
+            events: 40,
+        });
+        let mut out = Vec::new();
+        write_analysis_report(&mut out, &sample_response(vec![suspect])).expect("写入应成功");
+        let text = String::from_utf8(out).expect("应为 UTF-8");
+        assert!(text.contains("最高严重级别=6(信息)"));
+        assert!(text.contains("规则 acpi-benign 调整了 40 条事件，原始最高级别=3(错误)"));
     }
-    Some(pkg.to_string())
-}
 
-fn command_exists(command: &str) -> bool {
-    let status = Command::new(command)
-        .arg("--version")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status();
+    #[test]
+    fn event_rate_uses_first_and_last_timestamps() {
+        let minute = 60_000_000;
+        let mut suspect = sample_suspect(SourceKind::Unit, "a.service", 3, 3);
+        assert_eq!(suspect.event_rate(), None);
+        suspect.first_us = Some(1_704_069_000_000_000);
+        suspect.last_us = Some(1_704_069_000_000_000 + 2 * 3600 * 1_000_000);
+        assert_eq!(
+            suspect.event_rate().as_deref(),
+            Some("约每 1 小时 1 条（2 小时内 3 条）")
+        );
+        suspect.count = 61;
+        suspect.last_us = suspect.first_us.map(|first| first + 10 * minute);
+        assert_eq!(
+            suspect.event_rate().as_deref(),
+            Some("约 6 条/分钟（10 分内 61 条）")
+        );
+        // 同一秒内的 3 条按 1 秒计
+        suspect.count = 3;
+        suspect.last_us = suspect.first_us.map(|first| first + 300_000);
+        assert_eq!(
+            suspect.event_rate().as_deref(),
+            Some("约 2 条/秒（1 秒内 3 条）")
+        );
+        suspect.count = 1;
+        assert_eq!(suspect.event_rate(), None);
+    }
 
-    matches!(status, Ok(exit) if exit.success())
-}
+    #[test]
+    fn report_shows_priority_breakdown() {
+        let mut suspect = sample_suspect(SourceKind::Unit, "flappy.service", 163, 2);
+        suspect.priority_counts = vec![0, 0, 3, 40, 120, 0, 0, 0];
+        assert_eq!(
+            suspect.priority_breakdown().as_deref(),
+            Some("3×crit, 40×err, 120×warn")
+        );
+        let response = sample_response(vec![suspect]);
 
-// ── 中文输出格式化 ─────────────────────────────────────────────
+        let mut out = Vec::new();
+        write_analysis_report(&mut out, &response).expect("写入应成功");
+        let text = String::from_utf8(out).expect("应为 UTF-8");
+        assert!(text.contains("     级别分布：3×crit, 40×err, 120×warn"));
 
-pub fn print_analysis_report(response: &AnalyzeResponse) {
-    let metrics = &response.metrics;
-    let suspects = &response.suspects;
-    let top = response.top;
+        let mut out = Vec::new();
+        write_linear_report(&mut out, &response).expect("写入应成功");
+        let text = String::from_utf8(out).expect("应为 UTF-8");
+        assert!(text.contains("级别分布为3 条严重、40 条错误、120 条警告。"));
 
-    println!("═══════════════════════════════════════════════════════════════");
-    println!("                      📋 事件摘要");
-    println!("═══════════════════════════════════════════════════════════════");
-    println!("  读取行数    ：{}", metrics.lines_read);
-    println!("  解析成功    ：{}", metrics.parsed_ok);
-    println!("  匹配条数    ：{}", metrics.matched);
-    println!("  解析错误    ：{}", metrics.parse_errors);
-    println!("  独立来源    ：{}", suspects.len());
+        assert_eq!(
+            sample_suspect(SourceKind::Kernel, "kernel", 1, 7).priority_breakdown(),
+            None
+        );
+    }
 
-    if suspects.is_empty() {
-        println!();
-        println!("  ✅ 当前过滤条件下未发现可疑来源。");
-        println!("═══════════════════════════════════════════════════════════════");
-        return;
+    #[test]
+    fn emergency_check_ignores_source_filters_and_warns_in_reports() {
+        let config = Config {
+            units: vec!["nginx.service".to_string()],
+            grep_terms: vec!["timeout".to_string()],
+            since: Some("1 hour ago".to_string()),
+            ..Config::default()
+        };
+        assert!(filters_narrow_window(&config));
+        assert!(!filters_narrow_window(&Config::default()));
+        assert!(filters_narrow_window(&Config {
+            priority: "2..4".parse().expect("有效范围"),
+            ..Config::default()
+        }));
+        let rendered = render_command(&emergency_check_command(&config));
+        assert!(rendered.contains("--since '1 hour ago'"));
+        assert!(rendered.contains("--priority=0..1"));
+        assert!(!rendered.contains("nginx"));
+
+        let mut response = sample_response(Vec::new());
+        response.metrics.emergency_outside_filter = 2;
+        let mut out = Vec::new();
+        write_analysis_report(&mut out, &response).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert!(text.contains("🚨 窗口内存在 2 条紧急级别事件未包含在当前过滤中"));
+        assert!(text.find("🚨").expect("横幅") < text.find("📋 事件摘要").expect("摘要"));
+
+        let mut out = Vec::new();
+        write_linear_report(&mut out, &response).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert!(text.starts_with("警告：窗口内存在 2 条紧急级别事件"));
     }
 
-    println!();
-    println!("═══════════════════════════════════════════════════════════════");
-    println!("                    🔍 可疑来源排行");
-    println!("═══════════════════════════════════════════════════════════════");
+    #[test]
+    fn fail_on_counts_events_at_or_above_level() {
+        let metrics = AnalyzeMetrics {
+            priority_counts: vec![0, 0, 1, 4, 0, 0, 0, 0],
+            ..AnalyzeMetrics::default()
+        };
+        let mut config = Config {
+            fail_on: Some(2),
+            ..Config::default()
+        };
+        assert_eq!(fail_on_triggered(&config, &metrics), Some((1, 2)));
 
-    for (index, suspect) in suspects.iter().take(top).enumerate() {
-        let label = source_label_cn(suspect.kind);
-        let priority_text = priority_label_cn(suspect.worst_priority);
+        config.fail_on_count = Some(2);
+        assert_eq!(fail_on_triggered(&config, &metrics), None);
 
-        println!();
-        println!(
-            "  {}. [{}] {} | 事件数={} | 最高严重级别={}({})",
-            index + 1,
-            label,
-            suspect.source,
-            suspect.count,
-            suspect.worst_priority,
-            priority_text
-        );
+        // 只有 --fail-on-count 时以查询级别 err(3) 为阈值
+        config.fail_on = None;
+        assert_eq!(fail_on_triggered(&config, &metrics), Some((5, 3)));
+        assert_eq!(fail_on_triggered(&Config::default(), &metrics), None);
 
-        if let Some(pkg) = &suspect.package {
-            println!("     所属包  ：{pkg}");
-        } else {
-            println!("     所属包  ：未知");
-        }
+        assert!(parse(&["--stream", "--fail-on", "err"]).is_err());
+        assert!(parse(&["--fail-on", "loud"]).is_err());
+    }
 
-        if let Some(exe) = &suspect.sample_exe {
-            println!("     可执行文件：{exe}");
-        }
-        if let Some(unit) = &suspect.sample_unit {
-            println!("     服务单元：{unit}");
-        }
+    #[test]
+    fn linear_report_uses_one_sentence_line_per_suspect() {
+        let mut suspect = sample_suspect(SourceKind::Unit, "ssh.service", 3, 3);
+        suspect.package = Some("openssh-server".to_string());
+        suspect.sample_message = "Connection reset".to_string();
+        let response = sample_response(vec![
+            suspect,
+            sample_suspect(SourceKind::Kernel, "kernel", 1, 4),
+        ]);
+
+        let mut out = Vec::new();
+        write_linear_report(&mut out, &response).expect("写入应成功");
+        let text = String::from_utf8(out).expect("应为 UTF-8");
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[2],
+            "第 1 名：服务单元 ssh.service，共 3 条事件，最高严重级别为错误（3）。所属包为 openssh-server。示例消息：Connection reset。"
+        );
+        assert!(!text.contains('═') && !text.contains("  "));
+        assert!(parse(&["--linear", "--format", "json"]).is_err());
+    }
 
-        if !suspect.sample_message.is_empty() {
-            println!("     示例消息：{}", suspect.sample_message);
-        }
+    #[test]
+    fn summary_line_reports_top_suspect() {
+        let response = sample_response(vec![
+            sample_suspect(SourceKind::Unit, "ssh.service", 5, 4),
+            sample_suspect(SourceKind::Kernel, "kernel", 2, 2),
+        ]);
+        assert_eq!(
+            format_summary_line(&response),
+            "matched=7 sources=2 worst_priority=2 top=unit:ssh.service"
+        );
     }
 
-    println!();
-    println!("═══════════════════════════════════════════════════════════════");
-}
+    #[test]
+    fn summary_line_handles_empty_result_and_quoting() {
+        let empty = sample_response(Vec::new());
+        assert_eq!(
+            format_summary_line(&empty),
+            "matched=0 sources=0 worst_priority=none top=none"
+        );
 
-pub fn source_label_cn(kind: SourceKind) -> &'static str {
-    match kind {
-        SourceKind::Unit => "服务单元",
-        SourceKind::Executable => "可执行文件",
-        SourceKind::Identifier => "标识符",
-        SourceKind::Comm => "进程名",
-        SourceKind::Kernel => "内核",
-        SourceKind::Unknown => "未知",
+        let spaced = sample_response(vec![sample_suspect(SourceKind::Comm, "my app", 1, 3)]);
+        assert!(format_summary_line(&spaced).ends_with("top=\"comm:my app\""));
     }
-}
 
-pub fn priority_label_cn(priority: u8) -> &'static str {
-    match priority {
-        0 => "紧急",
-        1 => "警报",
-        2 => "严重",
-        3 => "错误",
-        4 => "警告",
-        5 => "通知",
-        6 => "信息",
-        7 => "调试",
-        _ => "未知",
+    #[test]
+    #[cfg(feature = "exporters")]
+    fn ndjson_emits_one_record_per_top_suspect() {
+        let mut response = sample_response(vec![
+            sample_suspect(SourceKind::Unit, "a.service", 5, 3),
+            sample_suspect(SourceKind::Unit, "b.service", 3, 3),
+            sample_suspect(SourceKind::Unit, "c.service", 1, 3),
+        ]);
+        response.top = 2;
+
+        let mut buffer = Vec::new();
+        write_ndjson_suspects(&mut buffer, &response).expect("写入应成功");
+        let text = String::from_utf8(buffer).expect("应为 UTF-8");
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+
+        let first: Value = serde_json::from_str(lines[0]).expect("应为 JSON");
+        assert_eq!(first["id"], "unit:a.service");
+        assert_eq!(first["rank"], 1);
+        assert_eq!(first["count"], 5);
     }
-}
 
-// ── journalctl 命令构建 ─────────────────────────────────────────────
+    #[test]
+    #[cfg(feature = "exporters")]
+    fn logfmt_emits_key_value_lines() {
+        let mut suspect = sample_suspect(SourceKind::Unit, "ssh.service", 5, 3);
+        suspect.package = Some("openssh-server".to_string());
+        suspect.sample_message = "Connection reset by \"peer\"".to_string();
+        let mut buffer = Vec::new();
+        write_logfmt_suspects(&mut buffer, &sample_response(vec![suspect])).expect("写入应成功");
+        assert_eq!(
+            String::from_utf8(buffer).expect("应为 UTF-8"),
+            "rank=1 id=unit:ssh.service kind=unit source=ssh.service count=5 worst_priority=3 level=err package=openssh-server message=\"Connection reset by \\\"peer\\\"\"\n"
+        );
 
-fn ensure_journalctl_exists() -> Result<(), String> {
-    let status = Command::new("journalctl")
-        .arg("--version")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status();
+        let event = parse_json_event(
+            r#"{"MESSAGE":"disk full","PRIORITY":"2","_SYSTEMD_UNIT":"a.service","__REALTIME_TIMESTAMP":"1709214310000000"}"#,
+        )
+        .expect("应解析");
+        assert_eq!(
+            format_logfmt_event(&event),
+            "ts=2024-02-29T13:45:10Z priority=2 level=crit unit=a.service message=\"disk full\""
+        );
 
-    match status {
-        Ok(exit) if exit.success() => Ok(()),
-        Ok(_) => Err("journalctl 存在但不可用".to_string()),
-        Err(err) => Err(format!("找不到 journalctl：{err}")),
+        let Ok(Action::Run(config)) = parse(&["--stream", "--format", "logfmt"]) else {
+            panic!("流模式应接受 logfmt");
+        };
+        assert!(
+            build_journalctl_command_for_stream(&config)
+                .get_args()
+                .any(|a| a == "--output=json")
+        );
+        assert!(parse(&["--stream", "--json", "--format=logfmt"]).is_err());
     }
-}
 
-fn build_journalctl_command_for_stream(config: &Config) -> Command {
-    let mut cmd = Command::new("journalctl");
-    cmd.arg("--no-pager");
+    #[test]
+    #[cfg(not(feature = "exporters"))]
+    fn export_formats_need_exporters_feature() {
+        let err = parse(&["--format", "ndjson"]).expect_err("解析应失败");
+        assert!(err.contains("exporters"));
+        assert!(parse(&["--format", "json"]).is_ok());
+    }
 
-    if config.follow {
-        cmd.arg("--follow");
+    #[test]
+    fn live_stream_applies_control_messages() {
+        let Ok(Action::Run(config)) = parse(&["--stream", "--follow", "--live", "-g", "oom"])
+        else {
+            panic!("--live 应可用于持续流");
+        };
+        assert!(config.live);
+        let rendered = render_command(&build_journalctl_command_for_stream(&config));
+        assert!(rendered.contains("--priority=7"));
+        assert!(rendered.contains("--output=json"));
+        assert!(parse(&["--stream", "--live"]).is_err());
+        assert!(parse(&["--live"]).is_err());
+
+        // 线上格式
+        let add = parse_live_command("+ Timeout ").expect("应解析");
+        assert_eq!(
+            serde_json::to_string(&add).expect("序列化"),
+            r#"{"control":"add_grep","term":"Timeout"}"#
+        );
+        assert!(parse_live_command("p 2..4").is_ok());
+        assert!(parse_live_command("p loud").is_err());
+        assert!(parse_live_command("?").is_err());
+
+        let live = Arc::new(Mutex::new(LiveFilters::from_config(&config)));
+        let controls = concat!(
+            r#"{"control":"remove_grep","term":"oom"}"#,
+            "\n",
+            r#"{"control":"add_grep","term":"Timeout"}"#,
+            "\n",
+            r#"{"control":"set_priority","priority":"4"}"#,
+            "\n",
+        );
+        spawn_stream_control_reader(io::Cursor::new(controls), Arc::clone(&live))
+            .join()
+            .expect("控制线程应正常结束");
+        let filters = live.lock().expect("锁").clone();
+        assert_eq!(filters.grep_terms, vec!["timeout"]);
+        assert_eq!(filters.priority, Priority::up_to(4));
+
+        let line = |priority: u8, message: &str| {
+            format!(
+                r#"{{"MESSAGE":"{message}","PRIORITY":"{priority}","SYSLOG_IDENTIFIER":"nginx","__REALTIME_TIMESTAMP":"1709214310000000"}}"#
+            )
+        };
+        assert_eq!(
+            filter_live_stream_line(line(4, "upstream timeout"), &config, &live, 0).as_deref(),
+            Some("2024-02-29 13:45:10 [warn  ] nginx: upstream timeout")
+        );
+        assert_eq!(
+            filter_live_stream_line(line(6, "upstream timeout"), &config, &live, 0),
+            None
+        );
+        assert_eq!(
+            filter_live_stream_line(line(3, "oom-killer"), &config, &live, 0),
+            None
+        );
     }
 
-    add_common_query_args(&mut cmd, config);
+    #[test]
+    fn format_is_rejected_in_stream_mode() {
+        let err = parse(&["--stream", "--format", "ndjson"]).expect_err("解析应失败");
+        assert!(err.contains("--format"));
+        let err = parse(&["--format", "yaml"]).expect_err("解析应失败");
+        assert!(err.contains("无效输出格式"));
+    }
 
-    if config.output_json {
-        cmd.arg("--output=json");
-    } else {
-        cmd.arg("--output=short-iso");
+    #[test]
+    fn parses_os_release_pretty_name() {
+        let text = "NAME=\"Ubuntu\"\nPRETTY_NAME=\"Ubuntu 24.04.1 LTS\"\nID=ubuntu\n";
+        assert_eq!(
+            parse_os_release_pretty_name(text).as_deref(),
+            Some("Ubuntu 24.04.1 LTS")
+        );
+        assert_eq!(parse_os_release_pretty_name("ID=ubuntu\n"), None);
     }
 
-    cmd
-}
+    #[test]
+    fn environment_snapshot_is_omitted_unless_requested() {
+        let response = sample_response(Vec::new());
+        let json = serde_json::to_string(&response).expect("序列化应成功");
+        assert!(!json.contains("environment"));
+    }
 
-fn build_journalctl_command_for_analysis(config: &Config) -> Command {
-    let mut cmd = Command::new("journalctl");
-    cmd.arg("--no-pager");
-    add_common_query_args(&mut cmd, config);
-    cmd.arg("--output=json");
-    cmd.arg("--output-fields=PRIORITY,MESSAGE,_SYSTEMD_UNIT,_EXE,_COMM,SYSLOG_IDENTIFIER");
-    cmd
-}
+    #[cfg(feature = "rules")]
+    #[test]
+    fn environment_snapshot_records_enabled_rules() {
+        let custom = rules::RuleSet {
+            rules: vec![rules::LoadedRule::new(
+                rules::Rule {
+                    id: "disk-full".to_string(),
+                    pattern: "*No space left*".to_string(),
+                    source: None,
+                    merge_as: None,
+                    description: None,
+                    severity: None,
+                    hint: None,
+                },
+                PathBuf::from("/etc/logtool/rules.d/disk.json"),
+            )],
+        };
+        let known_issues = rules::RuleSet::known_issues();
+        let environment = EnvironmentSnapshot {
+            presets: known_issues.ids(),
+            rules: custom.ids(),
+            ..EnvironmentSnapshot::default()
+        };
+        assert_eq!(environment.rules, vec!["disk-full"]);
+        assert_eq!(environment.presets.len(), known_issues.rules.len());
+        assert!(!environment.presets.is_empty());
+
+        let mut response =
+            sample_response(vec![sample_suspect(SourceKind::Unit, "a.service", 1, 3)]);
+        response.environment = Some(environment);
+        let json: serde_json::Value = serde_json::to_value(&response).expect("序列化应成功");
+        assert_eq!(
+            json["environment"]["rules"],
+            serde_json::json!(["disk-full"])
+        );
+        assert_eq!(
+            json["environment"]["presets"][0],
+            known_issues.rules[0].rule.id.as_str()
+        );
 
-fn add_common_query_args(cmd: &mut Command, config: &Config) {
-    if config.kernel_only {
-        cmd.arg("--dmesg");
+        let mut out = Vec::new();
+        write_analysis_report(&mut out, &response).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        let expected = format!(
+            "规则    ：已知问题库 {} 条；自定义规则 disk-full",
+            known_issues.rules.len()
+        );
+        assert!(text.contains(&expected), "{text}");
     }
 
-    if let Some(since) = &config.since {
-        cmd.arg("--since").arg(since);
+    #[test]
+    #[cfg(feature = "exporters")]
+    fn template_renders_top_suspects_with_derived_fields() {
+        let mut response = sample_response(vec![
+            sample_suspect(SourceKind::Unit, "a.service", 5, 3),
+            sample_suspect(SourceKind::Kernel, "kernel", 2, 2),
+        ]);
+        response.top = 1;
+        let context = template::template_context(&response);
+        let out = template::render_template(
+            "{{metrics.matched}}/{{source_count}}{{#suspects}} #{{rank}} {{id}} {{label}} {{priority_label}}{{/suspects}}",
+            &context,
+        )
+        .expect("渲染应成功");
+        assert_eq!(out, "7/2 #1 unit:a.service 服务单元 错误");
     }
 
-    if let Some(until) = &config.until {
-        cmd.arg("--until").arg(until);
+    #[test]
+    fn template_conflicts_with_format() {
+        let err = parse(&["--template", "t.txt", "--format", "json"]).expect_err("应失败");
+        assert!(err.contains("--template"));
     }
 
-    for unit in &config.units {
-        cmd.arg("--unit").arg(unit);
+    #[test]
+    fn summary_only_rejects_stream_mode() {
+        let err = parse(&["--stream", "--summary-only"]).expect_err("解析应失败");
+        assert!(err.contains("--summary-only"));
     }
 
-    match &config.boot {
-        BootFilter::Disabled => {}
-        BootFilter::Current => {
-            cmd.arg("--boot");
-        }
-        BootFilter::Value(value) => {
-            cmd.arg("--boot").arg(value);
-        }
+    #[test]
+    fn rules_test_command_parses_sample_and_rules_path() {
+        let action = parse(&["rules", "test", "sample.log", "--rules", "my.json"]).expect("应成功");
+        assert_eq!(
+            action,
+            Action::RulesTest {
+                sample: Some("sample.log".to_string()),
+                rules_path: Some("my.json".to_string()),
+            }
+        );
+        let action = parse(&["rules", "lint"]).expect("应成功");
+        assert_eq!(action, Action::RulesLint { rules_path: None });
+        let err = parse(&["rules", "lint", "extra"]).expect_err("应失败");
+        assert!(err.contains("extra"));
+        let err = parse(&["rules", "frobnicate"]).expect_err("应失败");
+        assert!(err.contains("rules"));
     }
 
-    cmd.arg(format!("--priority={}", config.priority));
-}
-
-pub fn render_command(cmd: &Command) -> String {
-    let mut rendered = cmd.get_program().to_string_lossy().to_string();
-    for arg in cmd.get_args() {
-        rendered.push(' ');
-        rendered.push_str(&shell_escape(arg.to_string_lossy().as_ref()));
+    #[test]
+    fn stats_command_parses_rules_flag() {
+        assert_eq!(parse(&["stats"]), Ok(Action::Stats { rules: false }));
+        assert_eq!(
+            parse(&["stats", "--rules"]),
+            Ok(Action::Stats { rules: true })
+        );
+        assert!(parse(&["stats", "--top"]).is_err());
     }
-    rendered
-}
 
-pub fn write_json_line<W: Write, T: Serialize>(
-    writer: &mut W,
-    payload: &T,
-    label: &str,
-) -> Result<(), String> {
-    let json = serde_json::to_string(payload).map_err(|e| format!("序列化{label}失败：{e}"))?;
-    writer
-        .write_all(json.as_bytes())
-        .map_err(|e| format!("发送{label}失败：{e}"))?;
-    writer
-        .write_all(b"\n")
-        .map_err(|e| format!("发送换行符失败：{e}"))?;
-    writer.flush().map_err(|e| format!("刷新输出失败：{e}"))?;
+    #[test]
+    fn status_command_accepts_only_json_flag() {
+        assert_eq!(parse(&["status"]), Ok(Action::Status { json: false }));
+        assert_eq!(
+            parse(&["status", "--json"]),
+            Ok(Action::Status { json: true })
+        );
+        assert!(parse(&["status", "--rules"]).is_err());
+        assert!(parse(&["status", "--json", "--json"]).is_err());
+    }
 
-    Ok(())
-}
+    #[test]
+    fn watch_command_parses_watch_options() {
+        let Ok(Action::Watch { config, options }) = parse(&[
+            "watch",
+            "--interval",
+            "30",
+            "--cooldown=600",
+            "--unit",
+            "ssh",
+        ]) else {
+            panic!("应为 Action::Watch");
+        };
+        assert_eq!(config.units, vec!["ssh".to_string()]);
+        assert_eq!(options.interval_secs, 30);
+        assert_eq!(options.cooldown_secs, 600);
+        assert_eq!(options.on_alert, None);
+
+        assert!(parse(&["--interval", "30"]).is_err());
+        assert!(parse(&["watch", "--format", "json"]).is_err());
+        assert!(parse(&["watch", "--interval", "0"]).is_err());
+        assert_eq!(parse(&["watch", "--help"]), Ok(Action::Help));
+    }
 
-pub fn stream_error_line(message: String) -> StreamLine {
-    StreamLine {
-        line: String::new(),
-        done: true,
-        error: Some(message),
+    #[test]
+    fn ack_command_takes_one_id() {
+        assert_eq!(
+            parse(&["ack", "unit:ssh.service"]),
+            Ok(Action::Ack {
+                id: "unit:ssh.service".to_string()
+            })
+        );
+        assert!(parse(&["ack"]).is_err());
+        assert!(parse(&["ack", "a", "b"]).is_err());
     }
-}
 
-pub fn daemon_error(message: String) -> ErrorResponse {
-    daemon_error_with_details(message, None, None)
-}
+    #[test]
+    fn note_command_sets_clears_and_lists() {
+        assert_eq!(
+            parse(&["note", "unit:ssh.service", "known", "issue"]),
+            Ok(Action::Note {
+                id: Some("unit:ssh.service".to_string()),
+                text: Some("known issue".to_string()),
+            })
+        );
+        assert_eq!(
+            parse(&["note", "unit:ssh.service", "--clear"]),
+            Ok(Action::Note {
+                id: Some("unit:ssh.service".to_string()),
+                text: None,
+            })
+        );
+        assert_eq!(
+            parse(&["note"]),
+            Ok(Action::Note {
+                id: None,
+                text: None
+            })
+        );
+        assert!(parse(&["note", "unit:ssh.service"]).is_err());
+        assert!(parse(&["note", "unit:ssh.service", " "]).is_err());
+    }
 
-pub fn daemon_error_with_details(
-    message: String,
-    code: Option<&str>,
-    hint: Option<String>,
-) -> ErrorResponse {
-    ErrorResponse {
-        error: message,
-        code: code.map(|v| v.to_string()),
-        hint,
+    #[test]
+    fn map_command_accepts_scan_and_format() {
+        assert_eq!(
+            parse(&["map"]),
+            Ok(Action::Map {
+                scan: false,
+                json: false
+            })
+        );
+        assert_eq!(
+            parse(&["map", "--format", "json", "--scan"]),
+            Ok(Action::Map {
+                scan: true,
+                json: true
+            })
+        );
+        assert!(parse(&["map", "--format=ndjson"]).is_err());
+        assert!(parse(&["map", "--format"]).is_err());
+        assert!(parse(&["map", "--top", "3"]).is_err());
     }
-}
 
-fn shell_escape(value: &str) -> String {
-    if value.is_empty() {
-        return "''".to_string();
-    }
-    if value
-        .chars()
-        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | ':' | '+'))
-    {
-        return value.to_string();
+    #[test]
+    fn report_shows_suspect_note() {
+        let mut suspect = sample_suspect(SourceKind::Unit, "ssh.service", 3, 3);
+        suspect.note = Some(Note {
+            text: "known issue, fix scheduled".to_string(),
+            author: Some("alice".to_string()),
+            updated_at: 0,
+        });
+        let mut out = Vec::new();
+        write_analysis_report(&mut out, &sample_response(vec![suspect])).expect("写入应成功");
+        let text = String::from_utf8(out).expect("应为 UTF-8");
+        assert!(text.contains("     备注    ：known issue, fix scheduled（alice，"));
     }
-    format!("'{}'", value.replace('\'', "'\"'\"'"))
-}
 
-fn io_error_to_string(err: io::Error) -> String {
-    err.to_string()
-}
+    #[test]
+    fn man_and_completions_commands_parse() {
+        assert_eq!(parse(&["man"]), Ok(Action::Man));
+        assert!(parse(&["man", "--top"]).is_err());
+        assert_eq!(
+            parse(&["completions", "zsh"]),
+            Ok(Action::Completions {
+                shell: "zsh".to_string()
+            })
+        );
+        assert!(parse(&["completions", "tcsh"]).is_err());
+        // 开关类选项不接受 `=值`
+        assert!(parse(&["--json=yes"]).is_err());
+    }
 
-pub fn truncate_for_display(text: &str, limit: usize) -> String {
-    if text.chars().count() <= limit {
-        return text.to_string();
+    #[test]
+    fn sample_line_falls_back_to_plain_message() {
+        let event = parse_sample_line("  ACPI Error: AE_NOT_FOUND ").expect("应有事件");
+        assert_eq!(event.message, "ACPI Error: AE_NOT_FOUND");
+        assert!(parse_sample_line("   ").is_none());
+        let json =
+            parse_sample_line(r#"{"MESSAGE":"m","_SYSTEMD_UNIT":"a.service"}"#).expect("应有事件");
+        assert_eq!(json.unit.as_deref(), Some("a.service"));
     }
 
-    let mut out = String::with_capacity(limit + 3);
-    for (idx, ch) in text.chars().enumerate() {
-        if idx >= limit {
-            break;
-        }
-        out.push(ch);
+    #[test]
+    fn parses_json_event() {
+        let line = r#"{"MESSAGE":"segfault at 0 ip ...","PRIORITY":"3","_SYSTEMD_UNIT":"foo.service","_EXE":"/usr/bin/foo","_COMM":"foo","SYSLOG_IDENTIFIER":"foo"}"#;
+        let event = parse_json_event(line).expect("JSON 应解析成功");
+
+        assert_eq!(event.message, "segfault at 0 ip ...");
+        assert_eq!(event.priority, Some(3));
+        assert_eq!(event.unit.as_deref(), Some("foo.service"));
+        assert_eq!(event.exe.as_deref(), Some("/usr/bin/foo"));
+        assert_eq!(event.identifier.as_deref(), Some("foo"));
+        assert_eq!(event.timestamp_us, None);
     }
-    out.push_str("...");
-    out
-}
 
-fn reached_limit(count: usize, max: Option<usize>) -> bool {
-    match max {
-        Some(max) => count >= max,
-        None => false,
+    #[test]
+    fn parses_realtime_timestamp() {
+        let line = r#"{"MESSAGE":"x","__REALTIME_TIMESTAMP":"1704074400000000"}"#;
+        let event = parse_json_event(line).expect("JSON 应解析成功");
+        assert_eq!(event.timestamp_us, Some(1_704_074_400_000_000));
     }
-}
 
-fn status_killed_by_limit(count: usize, max: Option<usize>) -> bool {
-    reached_limit(count, max)
-}
+    #[test]
+    fn classify_prefers_kernel_identifier() {
+        let event = JournalEvent {
+            priority: Some(3),
+            unit: Some("x.service".to_string()),
+            exe: Some("/usr/bin/x".to_string()),
+            comm: Some("x".to_string()),
+            identifier: Some("kernel".to_string()),
+            ..JournalEvent::default()
+        };
 
-fn matches_filters(line: &str, filters: &[String]) -> bool {
-    if filters.is_empty() {
-        return true;
+        let (kind, source) = classify_source(&event);
+        assert_eq!(kind, SourceKind::Kernel);
+        assert_eq!(source, "kernel");
     }
 
-    let lower = line.to_ascii_lowercase();
-    filters.iter().all(|term| lower.contains(term))
-}
+    #[test]
+    fn group_by_picks_the_requested_field_and_falls_back() {
+        let event = parse_json_event(
+            r#"{"MESSAGE":"x","PRIORITY":"3","_SYSTEMD_UNIT":"session-4.scope","_EXE":"/usr/bin/python3","_COMM":"worker","SYSLOG_IDENTIFIER":"app"}"#,
+        )
+        .expect("解析应成功");
+        let cases = [
+            (GroupBy::Auto, SourceKind::Unit, "session-4.scope"),
+            (GroupBy::Unit, SourceKind::Unit, "session-4.scope"),
+            (GroupBy::Exe, SourceKind::Executable, "/usr/bin/python3"),
+            (GroupBy::Identifier, SourceKind::Identifier, "app"),
+            (GroupBy::Comm, SourceKind::Comm, "worker"),
+        ];
+        for (group_by, kind, source) in cases {
+            assert_eq!(
+                classify_source_by(&event, group_by),
+                (kind, source.to_string()),
+                "{group_by:?}"
+            );
+        }
+        // 缺少所选字段时按默认次序；内核事件不按标识符归并
+        let bare = parse_json_event(r#"{"MESSAGE":"x","PRIORITY":"3","SYSLOG_IDENTIFIER":"cron"}"#)
+            .expect("解析应成功");
+        assert_eq!(
+            classify_source_by(&bare, GroupBy::Exe),
+            (SourceKind::Identifier, "cron".to_string())
+        );
+        let kernel =
+            parse_json_event(r#"{"MESSAGE":"x","PRIORITY":"3","SYSLOG_IDENTIFIER":"kernel"}"#)
+                .expect("解析应成功");
+        assert_eq!(
+            classify_source_by(&kernel, GroupBy::Identifier),
+            (SourceKind::Kernel, "kernel".to_string())
+        );
 
-// ── 帮助文本 ─────────────────────────────────────────────
+        let Ok(Action::Run(config)) = parse(&["--group-by", "exe"]) else {
+            panic!("--group-by exe 应解析成功");
+        };
+        assert_eq!(config.group_by, GroupBy::Exe);
+        assert!(parse(&["--group-by", "host"]).is_err());
+        assert!(parse(&["compare", "-u", "a", "-u", "b", "--group-by", "exe"]).is_err());
+    }
 
-pub fn help_text() -> &'static str {
-    "logtool — Ubuntu 系统异常日志诊断工具
-
-默认模式为 --analyze（归因分析，定位可疑程序/包）。
-
-用法：
-  logtool                    进入交互模式（输入 help/doctor/boots）
-  logtool [命令|选项]        单次执行模式
-
-模式：
-      --analyze             归因分析模式，排列可疑程序/服务（默认）
-      --stream              原始日志流模式（直接输出日志）
-      analyze               归因分析模式别名
-      stream                原始日志流模式别名
-
-命令：
-  help                     显示帮助（等同 --help）
-  version                  显示版本（等同 --version）
-  doctor                   运行环境自检（等同 --doctor）
-  boots                    列出启动周期（等同 --list-boots）
-  run                      按默认分析执行（适合交互模式）
-
-交互模式：
-  exit / quit / q          退出交互模式
-
-选项：
-  -h, --help                显示此帮助信息
-  -v, -V, --version         显示版本信息（需单独使用）
-      --doctor              运行环境自检（需单独使用）
-      --list-boots          列出启动周期（需单独使用）
-  -f, --follow              持续输出新日志（仅 --stream 模式）
-  -k, --kernel              仅查看内核日志（等同 journalctl --dmesg）
-  -u, --unit <名称>         按 systemd 服务单元过滤（可重复）
-  -g, --grep <关键词>       按关键词过滤（可重复，AND 逻辑）
-  -b, --boot [id]           仅当前启动周期日志，或指定启动 ID
-      --all-boots           跨所有启动周期排查（默认）
-  -p, --priority <级别>     优先级过滤（支持 0-7 或 err/warning/info/debug，默认：3）
-  -n, --max-lines <N>       最多扫描/输出的匹配日志行数（--stream --follow 默认不限制）
-      --top <N>             分析报告展示前 N 个可疑来源（默认：10）
-      --since <时间>        开始时间（默认：\"2 hours ago\"）
-      --until <时间>        结束时间
-      --no-default-since    禁用默认时间窗口
-      --json                JSON 输出（仅 --stream 模式）
-      --show-command        显示生成的 journalctl 命令
-
-示例：
-  logtool
-  logtool doctor
-  logtool boots
-  logtool --since \"30 min ago\" --top 15
-  logtool --kernel --priority 4 --grep hang
-  logtool --stream --follow --unit ssh
-"
-}
+    #[test]
+    fn kernel_disk_errors_are_attributed_to_the_device() {
+        let event = parse_json_event(
+            r#"{"MESSAGE":"blk_update_request: I/O error, dev sdb, sector 2048 op 0x0:(READ)","PRIORITY":"3","SYSLOG_IDENTIFIER":"kernel","_TRANSPORT":"kernel"}"#,
+        )
+        .expect("解析应成功");
+        assert_eq!(
+            classify_source(&event),
+            (SourceKind::Storage, "sdb".to_string())
+        );
+        assert_eq!(suspect_id(SourceKind::Storage, "sdb"), "storage:sdb");
+        assert_eq!(suspect_journal_match(SourceKind::Storage, "sdb"), None);
+    }
 
-// ── 单元测试 ─────────────────────────────────────────────
+    #[test]
+    fn kernel_gpu_faults_are_attributed_to_the_driver() {
+        let event = parse_json_event(
+            r#"{"MESSAGE":"i915 0000:00:02.0: [drm] GPU HANG: ecode 9:1:85dffffb, in gnome-shell [1534]","PRIORITY":"3","SYSLOG_IDENTIFIER":"kernel","_TRANSPORT":"kernel"}"#,
+        )
+        .expect("解析应成功");
+        assert_eq!(
+            classify_source(&event),
+            (SourceKind::Gpu, "i915".to_string())
+        );
+        assert_eq!(suspect_id(SourceKind::Gpu, "i915"), "gpu:i915");
+        assert_eq!(suspect_journal_match(SourceKind::Gpu, "i915"), None);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn container_logs_are_attributed_to_the_container() {
+        let id = "3f4e8a1b2c9d".to_string() + &"0".repeat(52);
+        let event = parse_json_event(&format!(
+            r#"{{"MESSAGE":"db timeout","PRIORITY":"3","_SYSTEMD_UNIT":"docker.service","_EXE":"/usr/bin/dockerd","CONTAINER_NAME":"web","_SYSTEMD_CGROUP":"/system.slice/docker-{id}.scope"}}"#
+        ))
+        .expect("解析应成功");
+        assert_eq!(
+            classify_source(&event),
+            (SourceKind::Container, "web".to_string())
+        );
+        assert_eq!(
+            suspect_journal_match(SourceKind::Container, "web").as_deref(),
+            Some("CONTAINER_NAME=web")
+        );
 
-    fn parse(input: &[&str]) -> Result<Action, String> {
-        let args = input.iter().map(|s| s.to_string()).collect::<Vec<_>>();
-        parse_args(&args)
+        // 没有 CONTAINER_NAME 时按 cgroup 中的容器 ID 归因
+        let podman = JournalEvent {
+            unit: Some(format!("libpod-{id}.scope")),
+            cgroup: Some(format!("/machine.slice/libpod-{id}.scope/container")),
+            ..JournalEvent::default()
+        };
+        assert_eq!(
+            classify_source(&podman),
+            (SourceKind::Container, "3f4e8a1b2c9d".to_string())
+        );
+        assert_eq!(
+            container_id_from_cgroup(&format!("/docker/{id}")).as_deref(),
+            Some("3f4e8a1b2c9d")
+        );
+        assert_eq!(
+            container_id_from_cgroup(&format!("/machine.slice/libpod-conmon-{id}.scope")),
+            None
+        );
+        assert_eq!(
+            suspect_journal_match(SourceKind::Container, "3f4e8a1b2c9d"),
+            None
+        );
+
+        let Ok(Action::Run(config)) = parse(&["--container", "web", "--container=db"]) else {
+            panic!("应为 Action::Run");
+        };
+        let command = analysis_command_line(&config);
+        assert!(
+            command.contains("'CONTAINER_NAME=web' 'CONTAINER_NAME=db'"),
+            "{command}"
+        );
+        assert!(command.contains("_SYSTEMD_CGROUP,CONTAINER_NAME"));
+        assert!(filters_narrow_window(&config));
+        assert!(parse(&["--container", " "]).is_err());
+        assert!(
+            parse(&[
+                "--container",
+                "web",
+                "--match",
+                "_PID=1",
+                "--match",
+                "+",
+                "--match",
+                "_PID=2"
+            ])
+            .is_err()
+        );
     }
 
     #[test]
-    fn default_mode_is_analyze() {
-        let action = parse(&[]).expect("解析应成功");
-        let Action::Run(config) = action else {
+    fn grep_any_keeps_events_matching_one_term() {
+        let Ok(Action::Run(config)) = parse(&[
+            "--grep-any",
+            "OOM",
+            "--grep-any=panic",
+            "--grep-any",
+            "",
+            "--grep",
+            "kernel",
+        ]) else {
             panic!("应为 Action::Run");
         };
+        assert_eq!(
+            config.grep_any,
+            vec!["oom".to_string(), "panic".to_string()]
+        );
 
-        assert_eq!(config.mode, RunMode::Analyze);
-        assert_eq!(config.boot, BootFilter::Disabled);
-        assert_eq!(config.since, Some(DEFAULT_SINCE.to_string()));
+        let event = |message: &str| JournalEvent {
+            message: message.to_string(),
+            priority: Some(3),
+            identifier: Some("kernel".to_string()),
+            ..JournalEvent::default()
+        };
+        assert!(event_matches_filters(
+            &event("Out of memory: oom-kill"),
+            &config
+        ));
+        assert!(event_matches_filters(&event("Kernel PANIC"), &config));
+        assert!(!event_matches_filters(&event("usb disconnect"), &config));
+        assert!(matches_filters("kernel: panic", &config));
+        assert!(!matches_filters("sshd: panic", &config));
     }
 
     #[test]
-    fn stream_mode_allows_follow() {
-        let action = parse(&["--stream", "--follow"]).expect("解析应成功");
-        let Action::Run(config) = action else {
+    fn invert_match_keeps_events_without_the_terms() {
+        let Ok(Action::Run(config)) = parse(&["-g", "timeout", "-v", "--exclude", "usb"]) else {
             panic!("应为 Action::Run");
         };
-        assert_eq!(config.mode, RunMode::Stream);
-        assert!(config.follow);
-        assert_eq!(config.max_lines, None);
+        assert!(config.invert_match);
+        let event = |message: &str| JournalEvent {
+            message: message.to_string(),
+            priority: Some(3),
+            unit: Some("app.service".to_string()),
+            ..JournalEvent::default()
+        };
+        assert!(!event_matches_filters(&event("request TIMEOUT"), &config));
+        assert!(event_matches_filters(&event("disk full"), &config));
+        assert!(matches_filters("app: disk full", &config));
+        assert!(!matches_filters("app: timeout", &config));
+        assert!(!matches_filters("usb: disk full", &config));
+
+        // --live 删掉全部关键词后不再过滤
+        let mut live = LiveFilters::from_config(&config);
+        assert!(!live.passes(&event("timeout"), "timeout"));
+        live.apply(StreamControl::RemoveGrep {
+            term: "timeout".to_string(),
+        });
+        assert!(live.passes(&event("timeout"), "timeout"));
+
+        assert!(matches!(parse(&["-v"]), Ok(Action::Version)));
+        // 只有短选项单独使用时才是版本；--invert-match 与 --version 各自不变
+        assert!(matches!(
+            parse(&["-v", "-g", "timeout"]),
+            Ok(Action::Run(Config {
+                invert_match: true,
+                ..
+            }))
+        ));
+        assert!(parse(&["--version", "-g", "timeout"]).is_err());
+        let err = parse(&["--invert-match"]).expect_err("没有关键词时应拒绝");
+        assert!(err.contains("--grep"));
     }
 
     #[test]
-    fn help_subcommand_works() {
-        let action = parse(&["help"]).expect("解析应成功");
-        assert_eq!(action, Action::Help);
-    }
+    fn journal_grep_pushes_single_term_down_escaped() {
+        let Ok(Action::Run(config)) = parse(&["--grep", "Conn.Reset(1)", "--journal-grep"]) else {
+            panic!("应为 Action::Run");
+        };
+        let command = analysis_command_line(&config);
+        assert!(command.contains(r"'--grep=conn\.reset\(1\)'"), "{command}");
+        // 本地过滤仍在消息与来源字段上复核
+        assert!(matches_filters("app: conn.reset(1) by peer", &config));
 
-    #[test]
-    fn version_flag_returns_version_action() {
-        let action = parse(&["--version"]).expect("解析应成功");
-        assert_eq!(action, Action::Version);
+        let Ok(Action::Run(local)) = parse(&["--grep", "timeout"]) else {
+            panic!("应为 Action::Run");
+        };
+        assert!(!analysis_command_line(&local).contains("--grep"));
+        for args in [
+            &["--journal-grep"][..],
+            &["-g", "a", "-g", "b", "--journal-grep"],
+            &["-g", "a", "--grep-any", "b", "--journal-grep"],
+            &["-g", "a", "--invert-match", "--journal-grep"],
+            &["-g", "echec", "--fold-accents", "--journal-grep"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?}");
+        }
     }
 
     #[test]
-    fn version_short_flag_lowercase_returns_version_action() {
-        let action = parse(&["-v"]).expect("解析应成功");
-        assert_eq!(action, Action::Version);
+    fn exclude_drops_matching_events() {
+        let Ok(Action::Run(config)) = parse(&["--exclude", "ACPI", "--exclude=bluetooth"]) else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(
+            config.exclude_terms,
+            vec!["acpi".to_string(), "bluetooth".to_string()]
+        );
+        let event = JournalEvent {
+            message: "ACPI Error: AE_NOT_FOUND".to_string(),
+            priority: Some(3),
+            identifier: Some("kernel".to_string()),
+            ..JournalEvent::default()
+        };
+        assert!(event_is_excluded(&event, &config));
+        assert!(!event_is_excluded(&event, &Config::default()));
+        assert!(!matches_filters("kernel: ACPI Error", &config));
+        assert!(matches_filters("kernel: usb 1-1: reset", &config));
     }
 
     #[test]
-    fn doctor_command_returns_doctor_action() {
-        let action = parse(&["doctor"]).expect("解析应成功");
-        assert_eq!(action, Action::Doctor);
+    fn exclude_unit_matches_globs_and_needs_structured_stream() {
+        let Ok(Action::Run(config)) =
+            parse(&["--exclude-unit", "snapd", "--exclude-unit", "snap.*.scope"])
+        else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(
+            config.exclude_units,
+            vec!["snapd.service".to_string(), "snap.*.scope".to_string()]
+        );
+        let event = |unit: Option<&str>| JournalEvent {
+            message: "noise".to_string(),
+            priority: Some(3),
+            unit: unit.map(str::to_string),
+            ..JournalEvent::default()
+        };
+        assert!(event_is_excluded(&event(Some("snapd.service")), &config));
+        assert!(event_is_excluded(
+            &event(Some("snap.firefox.firefox-1234.scope")),
+            &config
+        ));
+        assert!(!event_is_excluded(&event(Some("ssh.service")), &config));
+        assert!(!event_is_excluded(&event(None), &config));
+
+        assert!(parse(&["--stream", "--exclude-unit", "snapd"]).is_err());
+        assert!(parse(&["--stream", "--json", "--exclude-unit", "snapd"]).is_ok());
     }
 
     #[test]
-    fn list_boots_flag_returns_action() {
-        let action = parse(&["--list-boots"]).expect("解析应成功");
-        assert_eq!(action, Action::ListBoots);
+    fn timeline_requires_a_full_analysis_report() {
+        assert!(parse(&["--timeline", "--format", "json"]).is_ok());
+        assert!(parse(&["--timeline", "--linear"]).is_ok());
+        for bad in [
+            &["--timeline", "--stream"][..],
+            &["--timeline", "--summary-only"],
+            &["--timeline", "--format", "ndjson"],
+        ] {
+            assert!(parse(bad).is_err(), "{bad:?}");
+        }
     }
 
     #[test]
-    fn doctor_rejects_mixed_arguments() {
-        let err = parse(&["--doctor", "--stream"]).expect_err("解析应失败");
-        assert!(err.contains("--doctor"));
+    fn bursts_require_a_full_analysis_report() {
+        assert!(parse(&["--bursts", "--linear"]).is_ok());
+        for bad in [
+            &["--bursts", "--stream"][..],
+            &["--bursts", "--format", "ndjson"],
+            &["compare", "-u", "a", "-u", "b", "--bursts"],
+        ] {
+            assert!(parse(bad).is_err(), "{bad:?}");
+        }
     }
 
     #[test]
-    fn version_rejects_mixed_arguments() {
-        let err = parse(&["--version", "--stream"]).expect_err("解析应失败");
-        assert!(err.contains("--version"));
+    fn compare_previous_needs_a_convertible_time_window() {
+        assert!(parse(&["--compare-previous"]).is_ok());
+        assert!(parse(&["--compare-previous", "--since", "2024-01-03 08:00"]).is_ok());
+        for bad in [
+            &["--compare-previous", "--since", "last monday"][..],
+            &["--compare-previous", "--boot"],
+            &["--compare-previous", "--stream"],
+            &["compare", "-u", "a", "-u", "b", "--compare-previous"],
+        ] {
+            assert!(parse(bad).is_err(), "{bad:?}");
+        }
     }
 
     #[test]
-    fn all_boots_disables_boot_filter() {
-        let action = parse(&["--all-boots"]).expect("解析应成功");
-        let Action::Run(config) = action else {
-            panic!("应为 Action::Run");
-        };
-        assert_eq!(config.boot, BootFilter::Disabled);
+    fn failed_units_cross_check_needs_the_live_system() {
+        assert!(parse(&["--with-failed-units", "--linear"]).is_ok());
+        for bad in [
+            &["--with-failed-units", "--stream"][..],
+            &["--with-failed-units", "--summary-only"],
+            &["--with-failed-units", "--directory", "/mnt/var/log/journal"],
+            &["compare", "-u", "a", "-u", "b", "--with-failed-units"],
+        ] {
+            assert!(parse(bad).is_err(), "{bad:?}");
+        }
     }
 
     #[test]
-    fn boot_accepts_negative_offset() {
-        let action = parse(&["--boot", "-1"]).expect("解析应成功");
-        let Action::Run(config) = action else {
+    fn extra_sections_are_opt_in_analysis_flags() {
+        let Ok(Action::Run(config)) = parse(&["--crashes", "--audit"]) else {
             panic!("应为 Action::Run");
         };
-        assert_eq!(config.boot, BootFilter::Value("-1".to_string()));
+        assert!(config.crashes && config.audit && !config.hangs);
+        assert!(!Config::default().network);
+        for bad in [
+            &["--hangs", "--stream"][..],
+            &["--network", "--summary-only"],
+            &["compare", "-u", "a", "-u", "b", "--hardware"],
+        ] {
+            assert!(parse(bad).is_err(), "{bad:?}");
+        }
     }
 
     #[test]
-    fn analyze_mode_rejects_follow() {
-        let err = parse(&["--follow"]).expect_err("解析应失败");
-        assert!(err.contains("--follow"));
+    fn smart_check_needs_the_live_disks() {
+        assert!(parse(&["--smart", "--linear"]).is_ok());
+        for bad in [
+            &["--smart", "--stream"][..],
+            &["--smart", "--summary-only"],
+            &["--smart", "--file", "/tmp/system.journal"],
+            &["compare", "-u", "a", "-u", "b", "--smart"],
+        ] {
+            assert!(parse(bad).is_err(), "{bad:?}");
+        }
     }
 
     #[test]
-    fn top_must_be_positive() {
-        let err = parse(&["--top", "0"]).expect_err("解析应失败");
-        assert!(err.contains("--top"));
+    fn baseline_save_and_comparison_need_a_time_window() {
+        let Ok(Action::BaselineSave { config, path }) =
+            parse(&["baseline", "save", "/tmp/base.json", "--last", "24h"])
+        else {
+            panic!("应为 Action::BaselineSave");
+        };
+        assert_eq!(path, "/tmp/base.json");
+        assert_eq!(config.since.as_deref(), Some("24 hours ago"));
+        assert!(parse(&["--against-baseline", "/tmp/base.json", "--format", "json"]).is_ok());
+        for bad in [
+            &["baseline"][..],
+            &["baseline", "save"],
+            &["baseline", "load", "/tmp/base.json"],
+            &["baseline", "save", "/tmp/base.json", "--boot"],
+            &["baseline", "save", "/tmp/base.json", "--format", "json"],
+            &[
+                "--against-baseline",
+                "/tmp/base.json",
+                "--since",
+                "last monday",
+            ],
+            &["--against-baseline", "/tmp/base.json", "--summary-only"],
+            &["watch", "--against-baseline", "/tmp/base.json"],
+        ] {
+            assert!(parse(bad).is_err(), "{bad:?}");
+        }
     }
 
     #[test]
-    fn priority_alias_warning_normalizes_to_numeric() {
-        let action = parse(&["--priority", "warning"]).expect("解析应成功");
-        let Action::Run(config) = action else {
-            panic!("应为 Action::Run");
-        };
-        assert_eq!(config.priority, "4");
+    fn diff_takes_two_snapshots() {
+        assert_eq!(
+            parse(&["diff", "before.json", "after.json", "--format", "json"]),
+            Ok(Action::Diff {
+                old: "before.json".to_string(),
+                new: "after.json".to_string(),
+                json: true,
+            })
+        );
+        for bad in [
+            &["diff", "before.json"][..],
+            &["diff", "a.json", "b.json", "c.json"],
+            &["diff", "a.json", "b.json", "--format", "ndjson"],
+            &["diff", "a.json", "b.json", "--top", "3"],
+        ] {
+            assert!(parse(bad).is_err(), "{bad:?}");
+        }
     }
 
     #[test]
-    fn priority_invalid_value_is_rejected() {
-        let err = parse(&["--priority", "verbose"]).expect_err("解析应失败");
-        assert!(err.contains("无效优先级"));
+    fn histogram_interval_accepts_units() {
+        for (arg, secs) in [("90", 90), ("5m", 300), ("2h", 7200), ("1d", 86_400)] {
+            let Ok(Action::Run(config)) = parse(&["--histogram", arg]) else {
+                panic!("--histogram {arg} 应解析成功");
+            };
+            assert_eq!(config.histogram, Some(secs));
+        }
+        for bad in [
+            &["--histogram", "0m"][..],
+            &["--histogram", "5x"],
+            &["--histogram", "m"],
+            &["--histogram", "5m", "--stream"],
+            &["--histogram", "5m", "--format", "logfmt"],
+            &["compare", "-u", "a", "-u", "b", "--histogram", "5m"],
+        ] {
+            assert!(parse(bad).is_err(), "{bad:?}");
+        }
     }
 
     #[test]
-    fn stream_follow_honors_explicit_max_lines() {
-        let action = parse(&["--stream", "--follow", "--max-lines", "20"]).expect("解析应成功");
+    fn grep_terms_are_lowercased() {
+        let action = parse(&["--grep", "FaIled"]).expect("解析应成功");
         let Action::Run(config) = action else {
             panic!("应为 Action::Run");
         };
-        assert_eq!(config.max_lines, Some(20));
+        assert_eq!(config.grep_terms, vec!["failed".to_string()]);
     }
 
     #[test]
-    fn parses_json_event() {
-        let line = r#"{"MESSAGE":"segfault at 0 ip ...","PRIORITY":"3","_SYSTEMD_UNIT":"foo.service","_EXE":"/usr/bin/foo","_COMM":"foo","SYSLOG_IDENTIFIER":"foo"}"#;
-        let event = parse_json_event(line).expect("JSON 应解析成功");
+    fn dedup_collapses_only_identical_consecutive_messages() {
+        let Action::Run(config) = parse(&["--stream", "--dedup"]).expect("解析应成功") else {
+            panic!("应为 Action::Run");
+        };
+        assert!(config.dedup);
+
+        let a = stream_dedup_key("2026-10-14T09:00:00+0800 host app[12]: loop failed");
+        let b = stream_dedup_key("2026-10-14T09:00:01+0800 host app[12]: loop failed");
+        let other_host = stream_dedup_key("2026-10-14T09:00:01+0800 peer app[12]: loop failed");
+        assert_eq!(a, b);
+        assert_ne!(a, other_host);
+        let json = |ts: u64, message: &str| {
+            format!(
+                r#"{{"__REALTIME_TIMESTAMP":"{ts}","SYSLOG_IDENTIFIER":"app","MESSAGE":"{message}"}}"#
+            )
+        };
+        assert_eq!(
+            stream_dedup_key(&json(1, "loop failed")),
+            stream_dedup_key(&json(2, "loop failed"))
+        );
+        assert_ne!(
+            stream_dedup_key(&json(1, "loop failed")),
+            stream_dedup_key(&json(1, "loop failed again"))
+        );
 
-        assert_eq!(event.message, "segfault at 0 ip ...");
-        assert_eq!(event.priority, Some(3));
-        assert_eq!(event.unit.as_deref(), Some("foo.service"));
-        assert_eq!(event.exe.as_deref(), Some("/usr/bin/foo"));
-        assert_eq!(event.identifier.as_deref(), Some("foo"));
+        let mut out = Vec::new();
+        let mut repeats = 0;
+        flush_repeats(&mut out, &mut repeats, &config).expect("无重复时不输出");
+        assert!(out.is_empty());
+        repeats = 41;
+        flush_repeats(&mut out, &mut repeats, &config).expect("写入应成功");
+        let line: StreamLine = serde_json::from_slice(&out).expect("应为一行 StreamLine");
+        assert_eq!(line.line, "… repeated 41 times");
+        assert_eq!(repeats, 0);
+
+        let json_config = Config {
+            output_json: true,
+            ..config
+        };
+        let marker: Value = serde_json::from_str(&repeat_marker(3, &json_config))
+            .expect("JSON 输出时标记应为 JSON");
+        assert_eq!(marker["MESSAGE"], "… repeated 3 times");
+        assert_eq!(marker["LOGTOOL_REPEATED"], "3");
     }
 
     #[test]
-    fn classify_prefers_kernel_identifier() {
-        let event = JournalEvent {
-            message: String::new(),
-            priority: Some(3),
-            unit: Some("x.service".to_string()),
-            exe: Some("/usr/bin/x".to_string()),
-            comm: Some("x".to_string()),
-            identifier: Some("kernel".to_string()),
+    fn last_converts_duration_to_since() {
+        let Action::Run(config) = parse(&["--last", "30m"]).expect("解析应成功") else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(config.since.as_deref(), Some("30 min ago"));
+        assert_eq!(parse_last("1h").as_deref(), Ok("1 hour ago"));
+        assert_eq!(parse_last("2d").as_deref(), Ok("2 days ago"));
+        assert_eq!(parse_last("45s").as_deref(), Ok("45 sec ago"));
+
+        // 读取日志文件时 --last 同样算作显式的开始时间
+        let Action::Run(config) =
+            parse(&["--directory", "/tmp", "--last", "1w"]).expect("解析应成功")
+        else {
+            panic!("应为 Action::Run");
         };
+        assert_eq!(config.since.as_deref(), Some("1 week ago"));
 
-        let (kind, source) = classify_source(&event);
-        assert_eq!(kind, SourceKind::Kernel);
-        assert_eq!(source, "kernel");
+        for bad in ["0h", "h", "30", "2y", "-1d", "1.5h"] {
+            assert!(parse(&["--last", bad]).is_err(), "{bad} 应被拒绝");
+        }
+        let err = parse(&["--since", "yesterday", "--last", "2h"]).expect_err("应冲突");
+        assert!(err.contains("不能同时使用"));
+        assert!(parse(&["--last", "2h", "--since", "yesterday"]).is_err());
     }
 
     #[test]
-    fn parses_dpkg_output() {
-        let out = "openssh-server: /lib/systemd/system/ssh.service\n";
-        let pkg = parse_dpkg_search_output(out);
-        assert_eq!(pkg.as_deref(), Some("openssh-server"));
+    fn keyword_filters_fold_unicode_case_and_accents() {
+        let Action::Run(config) =
+            parse(&["--grep", "FEHLER", "--exclude", "Ошибка"]).expect("解析应成功")
+        else {
+            panic!("应为 Action::Run");
+        };
+        assert_eq!(config.grep_terms, vec!["fehler"]);
+        assert_eq!(config.exclude_terms, vec!["ошибка"]);
+        let event = |message: &str| JournalEvent {
+            message: message.to_string(),
+            ..JournalEvent::default()
+        };
+        assert!(event_matches_filters(&event("Fehler beim Öffnen"), &config));
+        assert!(event_is_excluded(&event("ОШИБКА чтения"), &config));
+
+        let Action::Run(config) =
+            parse(&["--grep", "echec", "--fold-accents"]).expect("解析应成功")
+        else {
+            panic!("应为 Action::Run");
+        };
+        assert!(event_matches_filters(&event("Échec du démarrage"), &config));
+        assert!(matches_filters("app: ÉCHEC", &config));
+        assert!(!event_matches_filters(&event("error"), &config));
+
+        let mut live = LiveFilters::from_config(&config);
+        live.apply(StreamControl::AddGrep {
+            term: "Démarrage".to_string(),
+        });
+        assert_eq!(live.grep_terms, vec!["echec", "demarrage"]);
     }
 
     #[test]
-    fn grep_terms_are_lowercased() {
-        let action = parse(&["--grep", "FaIled"]).expect("解析应成功");
-        let Action::Run(config) = action else {
+    fn case_sensitive_keeps_terms_and_matches_exact_case() {
+        let Action::Run(config) =
+            parse(&["--grep", "Error", "--exclude", "ERROR", "--case-sensitive"])
+                .expect("解析应成功")
+        else {
             panic!("应为 Action::Run");
         };
-        assert_eq!(config.grep_terms, vec!["failed".to_string()]);
+        assert_eq!(config.grep_terms, vec!["Error"]);
+        let event = |message: &str| JournalEvent {
+            message: message.to_string(),
+            ..JournalEvent::default()
+        };
+        assert!(event_matches_filters(&event("Error: disk full"), &config));
+        assert!(!event_matches_filters(&event("error: disk full"), &config));
+        assert!(event_is_excluded(&event("Error ERROR 42"), &config));
+        assert!(matches_filters("app: Error opening", &config));
+        assert!(!matches_filters("app: ERROR 42 Error", &config));
+
+        let mut live = LiveFilters::from_config(&config);
+        live.apply(StreamControl::AddGrep {
+            term: "Disk".to_string(),
+        });
+        assert_eq!(live.grep_terms, vec!["Error", "Disk"]);
     }
 
     #[test]
@@ -1308,6 +8013,31 @@ mod tests {
         assert_eq!(parsed.error, None);
     }
 
+    #[test]
+    fn stream_failure_line_carries_kind_and_exit_code() {
+        let failure = StreamFailure {
+            kind: StreamErrorKind::JournalctlExit,
+            message: "journalctl 退出状态异常".to_string(),
+            exit_code: Some(1),
+            stderr: Some("Failed to parse timestamp: x".to_string()),
+        };
+        let json = serde_json::to_string(&failure.to_line()).expect("应可序列化");
+        assert!(json.contains(r#""error_kind":"journalctl_exit""#));
+        let parsed: StreamLine = serde_json::from_str(&json).expect("应可解析");
+        assert!(parsed.done);
+        assert_eq!(parsed.exit_code, Some(1));
+
+        let plain = serde_json::to_string(&StreamLine::default()).expect("应可序列化");
+        assert!(!plain.contains("error_kind"));
+    }
+
+    #[test]
+    fn stderr_tail_keeps_last_bytes() {
+        let text = "a".repeat(10_000) + "tail";
+        let tail = read_stderr_tail(text.as_bytes(), 8);
+        assert_eq!(tail, "aaaatail");
+    }
+
     #[test]
     fn daemon_error_response_serializes() {
         let payload = daemon_error("bad request".to_string());
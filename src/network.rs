@@ -0,0 +1,367 @@
+// 网络状况 — 从 NetworkManager、systemd-networkd 与 systemd-resolved 的日志中识别断网（设备从 activated
+// 离开、Lost carrier）、连接激活失败、DHCP 失败与 DNS 服务器降级，按 (类型, 接口或 DNS 服务器) 归并后
+// 单独成节，回答“这段时间网络断过几次、卡在哪一步”
+//
+// 这些记录多为 info/warning 级别，不在默认的 --priority 范围内，因此另用一次带 --grep 的 journalctl 查询
+// 这三个单元的日志，不受 --priority 影响。接口与服务器都取自消息文本；journalctl 不支持 --grep 或查询失败时不列出。
+
+use crate::redact::Redactor;
+use crate::timeutil::format_local_minute;
+use crate::{Config, add_window_args, parse_json_event};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// 报告中最多列出的条目，按次数取最多的
+pub const MAX_NETWORK_ISSUES: usize = 10;
+const NETWORK_UNITS: &[&str] = &[
+    "NetworkManager.service",
+    "systemd-networkd.service",
+    "systemd-resolved.service",
+];
+const NETWORK_PATTERN: &str = r"state change: activated -> |Activation: failed|Lost carrier|dhcp[46] \(|DHCP|degraded feature set";
+/// --redact 时代替连接名
+const CONNECTION_MASK: &str = "<connection>";
+/// DHCP 记录中表示失败的片段（租约正常获取、续期的记录不算）
+const DHCP_FAILURES: &[&str] = &["timed out", "fail", "Fail", "no lease", "expired", "lost"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkIssueKind {
+    /// NetworkManager 设备离开 activated 状态，或 networkd 报告 Lost carrier
+    Disconnect,
+    /// NetworkManager 连接激活失败
+    ConnectFailed,
+    /// DHCP 请求超时、租约丢失
+    Dhcp,
+    /// systemd-resolved 因超时或错误回答把 DNS 服务器降级
+    Dns,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkIssue {
+    pub kind: NetworkIssueKind,
+    /// 网络接口（wlp2s0）；Dns 为 DNS 服务器地址
+    pub target: String,
+    pub count: u64,
+    /// 最近一次的详情：断开原因、连接名、DHCP 消息或降级后改用的协议
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// 最近一次的时间（UTC 微秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_us: Option<u64>,
+}
+
+impl NetworkIssue {
+    /// --redact：DNS 服务器地址与详情按通用规则遮盖；连接名多为 Wi-Fi 名称（SSID），整个遮盖
+    pub fn redact(&mut self, redactor: &Redactor) {
+        self.target = redactor.redact(&self.target);
+        self.detail = match self.kind {
+            NetworkIssueKind::ConnectFailed => {
+                self.detail.as_ref().map(|_| CONNECTION_MASK.to_string())
+            }
+            _ => self.detail.as_deref().map(|detail| redactor.redact(detail)),
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkEvent {
+    pub kind: NetworkIssueKind,
+    pub target: String,
+    pub detail: Option<String>,
+}
+
+/// 窗口内的网络问题，按次数降序取前 MAX_NETWORK_ISSUES 条；任何一步失败都按“没有问题”处理
+pub fn collect_network_issues(config: &Config) -> Vec<NetworkIssue> {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager").arg("--quiet");
+    add_window_args(&mut cmd, config);
+    // 同一字段的多个匹配之间为“或”
+    for unit in NETWORK_UNITS {
+        cmd.arg(format!("_SYSTEMD_UNIT={unit}"));
+    }
+    let Some(output) = cmd
+        .arg(format!("--grep={NETWORK_PATTERN}"))
+        .arg("--output=json")
+        .arg("--output-fields=MESSAGE")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+    else {
+        return Vec::new();
+    };
+    group_network_issues(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| parse_json_event(line).ok())
+            .filter_map(|event| Some((parse_network_event(&event.message)?, event.timestamp_us))),
+    )
+}
+
+/// 识别一条网络相关的消息；正常的状态变化与租约记录为 None
+pub fn parse_network_event(message: &str) -> Option<NetworkEvent> {
+    network_manager_event(message)
+        .or_else(|| resolved_event(message))
+        .or_else(|| networkd_event(message))
+}
+
+/// `<info>  [1712345678.1234] device (wlp2s0): state change: activated -> deactivating (reason 'supplicant-disconnect', …)`、
+/// `device (wlp2s0): Activation: failed for connection 'Home'`、`dhcp4 (enp3s0): request timed out`
+fn network_manager_event(message: &str) -> Option<NetworkEvent> {
+    if let Some((interface, detail)) = parenthesized(message, "device (") {
+        if let Some(rest) = detail.strip_prefix("state change: activated -> ") {
+            return Some(NetworkEvent {
+                kind: NetworkIssueKind::Disconnect,
+                target: interface.to_string(),
+                detail: quoted_after(rest, "reason '").map(str::to_string),
+            });
+        }
+        if let Some(rest) = detail.strip_prefix("Activation: failed") {
+            return Some(NetworkEvent {
+                kind: NetworkIssueKind::ConnectFailed,
+                target: interface.to_string(),
+                detail: quoted_after(rest, "connection '").map(str::to_string),
+            });
+        }
+        return None;
+    }
+    let (interface, detail) =
+        parenthesized(message, "dhcp4 (").or_else(|| parenthesized(message, "dhcp6 ("))?;
+    dhcp_failure(interface, detail)
+}
+
+/// `Using degraded feature set UDP instead of UDP+EDNS0 for DNS server 192.168.1.1.`
+fn resolved_event(message: &str) -> Option<NetworkEvent> {
+    let rest = message.strip_prefix("Using degraded feature set ")?;
+    let (feature, rest) = rest.split_once(" instead of ")?;
+    let (_, server) = rest.split_once(" for DNS server ")?;
+    Some(NetworkEvent {
+        kind: NetworkIssueKind::Dns,
+        target: server.trim_end_matches('.').to_string(),
+        detail: Some(feature.to_string()),
+    })
+}
+
+/// `wlan0: Lost carrier`、`eth0: DHCPv4 client: Failed to …`、`eth0: DHCP lease lost`
+fn networkd_event(message: &str) -> Option<NetworkEvent> {
+    let (interface, detail) = message.split_once(": ")?;
+    if interface.is_empty() || interface.contains(char::is_whitespace) {
+        return None;
+    }
+    if detail == "Lost carrier" {
+        return Some(NetworkEvent {
+            kind: NetworkIssueKind::Disconnect,
+            target: interface.to_string(),
+            detail: Some(detail.to_string()),
+        });
+    }
+    if !detail.contains("DHCP") {
+        return None;
+    }
+    dhcp_failure(interface, detail)
+}
+
+fn dhcp_failure(interface: &str, detail: &str) -> Option<NetworkEvent> {
+    DHCP_FAILURES
+        .iter()
+        .any(|pattern| detail.contains(pattern))
+        .then(|| NetworkEvent {
+            kind: NetworkIssueKind::Dhcp,
+            target: interface.to_string(),
+            detail: Some(detail.to_string()),
+        })
+}
+
+/// `prefix` 之后括号内的部分与 `): ` 之后的其余部分
+fn parenthesized<'a>(message: &'a str, prefix: &str) -> Option<(&'a str, &'a str)> {
+    let rest = &message[message.find(prefix)? + prefix.len()..];
+    rest.split_once("): ")
+}
+
+/// `prefix` 之后到下一个单引号为止
+fn quoted_after<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = &text[text.find(prefix)? + prefix.len()..];
+    rest.split_once('\'').map(|(value, _)| value)
+}
+
+/// 按 (类型, 接口或服务器) 归并，按次数降序、再按最近出现排序；原因取最近一次
+pub fn group_network_issues(
+    events: impl IntoIterator<Item = (NetworkEvent, Option<u64>)>,
+) -> Vec<NetworkIssue> {
+    let mut groups: HashMap<(NetworkIssueKind, String), NetworkIssue> = HashMap::new();
+    for (event, timestamp_us) in events {
+        let issue = groups
+            .entry((event.kind, event.target.clone()))
+            .or_insert_with(|| NetworkIssue {
+                kind: event.kind,
+                target: event.target,
+                count: 0,
+                detail: None,
+                last_us: None,
+            });
+        issue.count += 1;
+        if timestamp_us >= issue.last_us {
+            issue.last_us = timestamp_us;
+            issue.detail = event.detail.or(issue.detail.take());
+        }
+    }
+    let mut issues = groups.into_values().collect::<Vec<_>>();
+    issues.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then(b.last_us.cmp(&a.last_us))
+            .then(a.kind.cmp(&b.kind))
+            .then_with(|| a.target.cmp(&b.target))
+    });
+    issues.truncate(MAX_NETWORK_ISSUES);
+    issues
+}
+
+fn kind_label(kind: NetworkIssueKind) -> &'static str {
+    match kind {
+        NetworkIssueKind::Disconnect => "网络断开",
+        NetworkIssueKind::ConnectFailed => "连接失败",
+        NetworkIssueKind::Dhcp => "DHCP 失败",
+        NetworkIssueKind::Dns => "DNS 降级",
+    }
+}
+
+/// `接口 wlp2s0` / `DNS 服务器 192.168.1.1`
+fn target_label(issue: &NetworkIssue) -> String {
+    match issue.kind {
+        NetworkIssueKind::Dns => format!("DNS 服务器 {}", issue.target),
+        _ => format!("接口 {}", issue.target),
+    }
+}
+
+fn last_seen(issue: &NetworkIssue, offset_secs: i64) -> String {
+    let at = issue
+        .last_us
+        .map(|at| format!("最近 {}", format_local_minute(at, offset_secs)));
+    let detail = issue.detail.as_ref().map(|detail| match issue.kind {
+        NetworkIssueKind::Disconnect => format!("原因 {detail}"),
+        NetworkIssueKind::ConnectFailed => format!("连接 {detail}"),
+        NetworkIssueKind::Dhcp => detail.clone(),
+        NetworkIssueKind::Dns => format!("改用 {detail}"),
+    });
+    [at, detail]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("，")
+}
+
+pub fn write_network_section<W: Write>(
+    out: &mut W,
+    issues: &[NetworkIssue],
+    offset_secs: i64,
+) -> io::Result<()> {
+    for issue in issues {
+        let mut line = format!(
+            "  ×{} {}  {}",
+            issue.count,
+            kind_label(issue.kind),
+            target_label(issue)
+        );
+        let seen = last_seen(issue, offset_secs);
+        if !seen.is_empty() {
+            line.push_str(&format!("  {seen}"));
+        }
+        writeln!(out, "{line}")?;
+    }
+    Ok(())
+}
+
+/// 逐句版本（--linear）
+pub fn network_sentences(issues: &[NetworkIssue], offset_secs: i64) -> Vec<String> {
+    issues
+        .iter()
+        .map(|issue| {
+            let mut sentence = format!(
+                "{}：{} {} 次",
+                target_label(issue),
+                kind_label(issue.kind),
+                issue.count
+            );
+            let seen = last_seen(issue, offset_secs);
+            if !seen.is_empty() {
+                sentence.push_str(&format!("，{seen}"));
+            }
+            sentence.push('。');
+            sentence
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_failures_are_grouped_by_interface_and_server() {
+        let base = 1_704_069_000_000_000; // 2024-01-01 00:30 UTC
+        let lines = [
+            "<info>  [1704069000.1234] device (wlp2s0): state change: activated -> deactivating (reason 'supplicant-disconnect', sys-iface-state: 'managed')",
+            "<info>  [1704069001.1234] device (wlp2s0): state change: disconnected -> prepare (reason 'none', sys-iface-state: 'managed')",
+            "<warn>  [1704069002.1234] device (wlp2s0): Activation: failed for connection 'Home'",
+            "<info>  [1704069003.1234] dhcp4 (wlp2s0): request timed out",
+            "<info>  [1704069004.1234] dhcp4 (wlp2s0): state changed new lease, address=192.168.1.20",
+            "<info>  [1704069005.1234] device (wlp2s0): state change: activated -> unavailable (reason 'carrier-changed', sys-iface-state: 'managed')",
+            "Using degraded feature set UDP instead of UDP+EDNS0 for DNS server 192.168.1.1.",
+            "Grace period over, resuming full feature set (UDP+EDNS0) for DNS server 192.168.1.1.",
+            "enp3s0: Lost carrier",
+            "enp3s0: DHCPv4 address 10.0.0.5/24, gateway 10.0.0.1 acquired from 10.0.0.1",
+            "enp3s0: DHCP lease lost",
+        ];
+        let events = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                Some((
+                    parse_network_event(line)?,
+                    Some(base + i as u64 * 1_000_000),
+                ))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 7);
+        assert_eq!(
+            events[1].0,
+            NetworkEvent {
+                kind: NetworkIssueKind::ConnectFailed,
+                target: "wlp2s0".to_string(),
+                detail: Some("Home".to_string()),
+            }
+        );
+
+        let issues = group_network_issues(events);
+        assert_eq!(
+            issues
+                .iter()
+                .map(|issue| (issue.kind, issue.target.as_str(), issue.count))
+                .collect::<Vec<_>>(),
+            [
+                (NetworkIssueKind::Disconnect, "wlp2s0", 2),
+                (NetworkIssueKind::Dhcp, "enp3s0", 1),
+                (NetworkIssueKind::Disconnect, "enp3s0", 1),
+                (NetworkIssueKind::Dns, "192.168.1.1", 1),
+                (NetworkIssueKind::Dhcp, "wlp2s0", 1),
+                (NetworkIssueKind::ConnectFailed, "wlp2s0", 1),
+            ]
+        );
+
+        let mut out = Vec::new();
+        write_network_section(&mut out, &issues[..1], 0).expect("写入应成功");
+        assert_eq!(
+            String::from_utf8(out).expect("UTF-8"),
+            "  ×2 网络断开  接口 wlp2s0  最近 2024-01-01 00:30，原因 carrier-changed\n"
+        );
+        assert_eq!(
+            network_sentences(&issues, 0)[3],
+            "DNS 服务器 192.168.1.1：DNS 降级 1 次，最近 2024-01-01 00:30，改用 UDP。"
+        );
+    }
+}
@@ -0,0 +1,1341 @@
+// 选项表 — 命令行选项的唯一定义
+//
+// parse_args、help_text、`logtool man`（roff）与 `logtool completions`（bash/zsh/fish）
+// 都从 OPTIONS / COMMANDS 生成；新增参数只需在表中加一项。
+
+use crate::alert::WatchOptions;
+use crate::{
+    Action, BootFilter, Config, RunMode, normalize_cgroup_path, normalize_unit_pattern,
+    parse_boot_value, parse_comm, parse_directory, parse_fail_on, parse_group_by,
+    parse_histogram_interval, parse_identifier, parse_journal_file, parse_last, parse_match,
+    parse_namespace, parse_pid, parse_positive_usize, parse_report_format, parse_syslog_file,
+    resolve_user_filter,
+};
+use std::fmt::Write as _;
+
+/// 解析过程中的可变状态
+#[derive(Debug, Default)]
+pub struct ParseState {
+    pub config: Config,
+    pub max_lines_explicit: bool,
+    pub since_explicit: bool,
+    /// --last 已设置 since，与 --since 互斥
+    pub last_explicit: bool,
+    pub watch: WatchOptions,
+    /// 首个出现的 watch 专用选项，非 watch 命令据此报错
+    pub watch_flag: Option<&'static str>,
+}
+
+pub enum OptionKind {
+    /// 立即返回的动作，忽略其余参数（--help）
+    Immediate(fn() -> Action),
+    /// 必须单独使用的动作（--version、--doctor）
+    Standalone(fn() -> Action),
+    Flag(fn(&mut ParseState)),
+    /// 开关；短选项作为唯一参数时改为动作（`-v` 同 grep -v，单独的 `logtool -v` 仍显示版本）
+    FlagUnlessAlone(fn(&mut ParseState), fn() -> Action),
+    /// 需要一个值：`--x v` 或 `--x=v`
+    Value(fn(&mut ParseState, &str) -> Result<(), String>),
+    /// 值可省略（`--boot [id]`）；只有形如启动 ID/偏移的下一个参数才被当作值
+    OptionalValue(fn(&mut ParseState, Option<&str>) -> Result<(), String>),
+}
+
+/// 补全脚本使用的取值提示
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueHint {
+    None,
+    File,
+    Dir,
+    Choices(&'static [&'static str]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelpSection {
+    Mode,
+    Option,
+    /// 仅 `logtool watch` 可用
+    Watch,
+}
+
+pub struct OptionSpec {
+    pub long: &'static str,
+    pub short: Option<char>,
+    /// 其他写法：额外的短选项（`-V`）或命令词（`help`）
+    pub aliases: &'static [&'static str],
+    /// 帮助中显示的值占位符，如 `<N>`、`[id]`
+    pub placeholder: &'static str,
+    pub hint: ValueHint,
+    pub help: &'static str,
+    pub section: HelpSection,
+    pub kind: OptionKind,
+}
+
+impl OptionSpec {
+    pub fn takes_value(&self) -> bool {
+        matches!(
+            self.kind,
+            OptionKind::Value(_) | OptionKind::OptionalValue(_)
+        )
+    }
+
+    pub fn is_short(&self, name: &str) -> bool {
+        self.short
+            .is_some_and(|c| name.len() == 2 && name.starts_with('-') && name.ends_with(c))
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        self.long == name || self.aliases.contains(&name) || self.is_short(name)
+    }
+
+    /// 所有以 `-` 开头的写法：短选项、短别名、长选项
+    pub fn flag_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        if let Some(c) = self.short {
+            names.push(format!("-{c}"));
+        }
+        names.extend(
+            self.aliases
+                .iter()
+                .filter(|a| a.starts_with('-'))
+                .map(|a| a.to_string()),
+        );
+        names.push(self.long.to_string());
+        names
+    }
+}
+
+const PRIORITY_CHOICES: &[&str] = &[
+    "0", "1", "2", "3", "4", "5", "6", "7", "emerg", "alert", "crit", "err", "warning", "notice",
+    "info", "debug",
+];
+
+const SINCE_LAST_CONFLICT: &str =
+    "--last 与 --since 不能同时使用\n修复：保留其一，如 --last 2h 或 --since \"2 hours ago\"";
+
+const fn flag(
+    long: &'static str,
+    short: Option<char>,
+    help: &'static str,
+    apply: fn(&mut ParseState),
+) -> OptionSpec {
+    OptionSpec {
+        long,
+        short,
+        aliases: &[],
+        placeholder: "",
+        hint: ValueHint::None,
+        help,
+        section: HelpSection::Option,
+        kind: OptionKind::Flag(apply),
+    }
+}
+
+const fn value(
+    long: &'static str,
+    short: Option<char>,
+    placeholder: &'static str,
+    hint: ValueHint,
+    help: &'static str,
+    apply: fn(&mut ParseState, &str) -> Result<(), String>,
+) -> OptionSpec {
+    OptionSpec {
+        long,
+        short,
+        aliases: &[],
+        placeholder,
+        hint,
+        help,
+        section: HelpSection::Option,
+        kind: OptionKind::Value(apply),
+    }
+}
+
+const fn watch_value(
+    long: &'static str,
+    placeholder: &'static str,
+    hint: ValueHint,
+    help: &'static str,
+    apply: fn(&mut ParseState, &str) -> Result<(), String>,
+) -> OptionSpec {
+    OptionSpec {
+        section: HelpSection::Watch,
+        ..value(long, None, placeholder, hint, help, apply)
+    }
+}
+
+const fn mode(long: &'static str, help: &'static str, apply: fn(&mut ParseState)) -> OptionSpec {
+    OptionSpec {
+        section: HelpSection::Mode,
+        ..flag(long, None, help, apply)
+    }
+}
+
+pub static OPTIONS: &[OptionSpec] = &[
+    mode(
+        "--analyze",
+        "归因分析模式，排列可疑程序/服务（默认）",
+        |s| s.config.mode = RunMode::Analyze,
+    ),
+    mode(
+        "--stream",
+        "原始日志流模式（直接输出日志）",
+        |s| s.config.mode = RunMode::Stream,
+    ),
+    OptionSpec {
+        aliases: &["help"],
+        kind: OptionKind::Immediate(|| Action::Help),
+        ..flag("--help", Some('h'), "显示此帮助信息", |_| {})
+    },
+    OptionSpec {
+        aliases: &["-V", "version"],
+        kind: OptionKind::Standalone(|| Action::Version),
+        ..flag(
+            "--version",
+            None,
+            "显示版本信息（需单独使用，单独的 -v 同此）",
+            |_| {},
+        )
+    },
+    OptionSpec {
+        aliases: &["doctor"],
+        kind: OptionKind::Standalone(|| Action::Doctor),
+        ..flag("--doctor", None, "运行环境自检（需单独使用）", |_| {})
+    },
+    OptionSpec {
+        kind: OptionKind::Standalone(|| Action::ForceUnlock),
+        ..flag(
+            "--force-unlock",
+            None,
+            "移除状态文件的锁（持锁进程异常卡住时使用，需单独使用）",
+            |_| {},
+        )
+    },
+    OptionSpec {
+        aliases: &["boots"],
+        kind: OptionKind::Standalone(|| Action::ListBoots),
+        ..flag("--list-boots", None, "列出启动周期（需单独使用）", |_| {})
+    },
+    flag(
+        "--follow",
+        Some('f'),
+        "持续输出新日志（仅 --stream 模式）",
+        |s| s.config.follow = true,
+    ),
+    flag(
+        "--live",
+        None,
+        "持续流中可从终端输入 +词 / -词 / p 级别 实时调整过滤",
+        |s| s.config.live = true,
+    ),
+    flag(
+        "--kernel",
+        Some('k'),
+        "仅查看内核日志（等同 journalctl --dmesg）",
+        |s| s.config.kernel_only = true,
+    ),
+    value(
+        "--unit",
+        Some('u'),
+        "<名称>",
+        ValueHint::None,
+        "按 systemd 服务单元过滤（可重复）",
+        |s, v| {
+            s.config.units.push(v.to_string());
+            Ok(())
+        },
+    ),
+    value(
+        "--identifier",
+        Some('t'),
+        "<名称>",
+        ValueHint::None,
+        "按 SYSLOG_IDENTIFIER 过滤（如 sshd、kernel，可重复）",
+        |s, v| {
+            s.config.identifiers.push(parse_identifier(v)?);
+            Ok(())
+        },
+    ),
+    value(
+        "--comm",
+        None,
+        "<进程名>",
+        ValueHint::None,
+        "只看该进程名的日志（_COMM=，可重复）",
+        |s, v| {
+            s.config.comms.push(parse_comm(v)?);
+            Ok(())
+        },
+    ),
+    value(
+        "--pid",
+        None,
+        "<PID>",
+        ValueHint::None,
+        "只看该进程的日志（_PID=，可重复）",
+        |s, v| {
+            s.config.pids.push(parse_pid(v)?);
+            Ok(())
+        },
+    ),
+    value(
+        "--user-filter",
+        None,
+        "<用户>",
+        ValueHint::None,
+        "只看该用户的日志（用户名或 UID → _UID=，可重复）",
+        |s, v| {
+            s.config.uids.push(resolve_user_filter(v)?);
+            Ok(())
+        },
+    ),
+    value(
+        "--cgroup",
+        None,
+        "<路径>",
+        ValueHint::None,
+        "只看该 cgroup 的日志（_SYSTEMD_CGROUP=，可重复）",
+        |s, v| {
+            s.config.cgroups.push(normalize_cgroup_path(v)?);
+            Ok(())
+        },
+    ),
+    value(
+        "--container",
+        None,
+        "<名称>",
+        ValueHint::None,
+        "只看该容器的日志（CONTAINER_NAME=，需 journald 日志驱动，可重复）",
+        |s, v| {
+            let name = v.trim();
+            if name.is_empty() {
+                return Err(
+                    "--container 缺少容器名\n修复：如 --container web（docker ps 中的 NAMES）"
+                        .to_string(),
+                );
+            }
+            s.config.containers.push(name.to_string());
+            Ok(())
+        },
+    ),
+    value(
+        "--match",
+        None,
+        "<字段=值>",
+        ValueHint::None,
+        "任意 journal 字段匹配（可重复，单独的 + 表示“或”）",
+        |s, v| {
+            s.config.matches.push(parse_match(v)?);
+            Ok(())
+        },
+    ),
+    value(
+        "--namespace",
+        None,
+        "<名称>",
+        ValueHint::None,
+        "分析指定 journal 命名空间（LogNamespace=）",
+        |s, v| {
+            s.config.namespace = Some(parse_namespace(v)?);
+            Ok(())
+        },
+    ),
+    value(
+        "--directory",
+        Some('D'),
+        "<目录>",
+        ValueHint::Dir,
+        "分析该目录中的 journal 文件（如从故障机拷出的日志）",
+        |s, v| {
+            s.config.directory = Some(parse_directory(v)?);
+            Ok(())
+        },
+    ),
+    value(
+        "--file",
+        None,
+        "<文件>",
+        ValueHint::File,
+        "分析指定 journal 文件，可重复",
+        |s, v| {
+            s.config.files.push(parse_journal_file(v)?);
+            Ok(())
+        },
+    ),
+    value(
+        "--syslog-file",
+        None,
+        "<文件>",
+        ValueHint::File,
+        "分析 rsyslog 等写出的纯文本 syslog 文件（RFC 3164/5424，可为 .gz 轮转文件），可重复",
+        |s, v| {
+            s.config.syslog_files.push(parse_syslog_file(v)?);
+            Ok(())
+        },
+    ),
+    flag(
+        "--merge",
+        None,
+        "合并读取所有 journal（含 journal-remote 收到的远程日志），报告按主机区分",
+        |s| s.config.merge = true,
+    ),
+    value(
+        "--grep",
+        Some('g'),
+        "<关键词>",
+        ValueHint::None,
+        "按关键词过滤（可重复，AND 逻辑）",
+        |s, v| {
+            if !v.is_empty() {
+                s.config.grep_terms.push(v.to_string());
+            }
+            Ok(())
+        },
+    ),
+    value(
+        "--grep-any",
+        None,
+        "<关键词>",
+        ValueHint::None,
+        "命中任一关键词即保留（可重复，OR 逻辑，可与 --grep 组合）",
+        |s, v| {
+            if !v.is_empty() {
+                s.config.grep_any.push(v.to_string());
+            }
+            Ok(())
+        },
+    ),
+    value(
+        "--exclude",
+        None,
+        "<关键词>",
+        ValueHint::None,
+        "丢弃含该关键词的事件（可重复，分析与流模式均生效）",
+        |s, v| {
+            if !v.is_empty() {
+                s.config.exclude_terms.push(v.to_string());
+            }
+            Ok(())
+        },
+    ),
+    flag(
+        "--dedup",
+        None,
+        "折叠连续重复的消息（刷屏循环只算一次）",
+        |s| s.config.dedup = true,
+    ),
+    value(
+        "--exclude-unit",
+        None,
+        "<名称>",
+        ValueHint::None,
+        "丢弃来自该单元的事件（可重复，支持 * ? 通配）",
+        |s, v| {
+            s.config.exclude_units.push(normalize_unit_pattern(v)?);
+            Ok(())
+        },
+    ),
+    OptionSpec {
+        kind: OptionKind::FlagUnlessAlone(|s| s.config.invert_match = true, || Action::Version),
+        ..flag(
+            "--invert-match",
+            Some('v'),
+            "只保留不匹配 --grep / --grep-any 的事件（单独的 -v 显示版本）",
+            |_| {},
+        )
+    },
+    flag(
+        "--journal-grep",
+        None,
+        "唯一的 --grep 关键词交给 journalctl --grep 先过滤（大日志更快）",
+        |s| s.config.journal_grep = true,
+    ),
+    flag(
+        "--case-sensitive",
+        None,
+        "--grep / --grep-any / --exclude 区分大小写",
+        |s| s.config.case_sensitive = true,
+    ),
+    flag(
+        "--fold-accents",
+        None,
+        "关键词匹配忽略变音符号（é → e、ß → ss）",
+        |s| s.config.fold_accents = true,
+    ),
+    OptionSpec {
+        long: "--boot",
+        short: Some('b'),
+        aliases: &[],
+        placeholder: "[id]",
+        hint: ValueHint::None,
+        help: "仅当前启动周期，或指定启动周期（-1、-3..0、-2,0）",
+        section: HelpSection::Option,
+        kind: OptionKind::OptionalValue(|s, v| {
+            s.config.boot = match v {
+                Some(value) => parse_boot_value(value)?,
+                None => BootFilter::Current,
+            };
+            Ok(())
+        }),
+    },
+    flag(
+        "--all-boots",
+        None,
+        "跨所有启动周期排查（默认）",
+        |s| s.config.boot = BootFilter::Disabled,
+    ),
+    value(
+        "--priority",
+        Some('p'),
+        "<级别>",
+        ValueHint::Choices(PRIORITY_CHOICES),
+        "优先级过滤（0-7、err/warning 等名称或范围 2..4，默认：3）",
+        |s, v| {
+            s.config.priority = v.parse()?;
+            Ok(())
+        },
+    ),
+    value(
+        "--max-lines",
+        Some('n'),
+        "<N>",
+        ValueHint::None,
+        "最多扫描/输出的匹配日志行数（--stream --follow 默认不限制）",
+        |s, v| {
+            s.config.max_lines = Some(parse_positive_usize(v, "--max-lines")?);
+            s.max_lines_explicit = true;
+            Ok(())
+        },
+    ),
+    value(
+        "--top",
+        None,
+        "<N>",
+        ValueHint::None,
+        "分析报告展示前 N 个可疑来源（默认：10）",
+        |s, v| {
+            s.config.top = parse_positive_usize(v, "--top")?;
+            Ok(())
+        },
+    ),
+    value(
+        "--since",
+        None,
+        "<时间>",
+        ValueHint::None,
+        "开始时间（默认：\"2 hours ago\"）",
+        |s, v| {
+            if s.last_explicit {
+                return Err(SINCE_LAST_CONFLICT.to_string());
+            }
+            s.config.since = Some(v.to_string());
+            s.since_explicit = true;
+            Ok(())
+        },
+    ),
+    value(
+        "--last",
+        None,
+        "<时长>",
+        ValueHint::None,
+        "分析最近一段时间（30m、2h、1d），代替 --since",
+        |s, v| {
+            if s.since_explicit && !s.last_explicit {
+                return Err(SINCE_LAST_CONFLICT.to_string());
+            }
+            s.config.since = Some(parse_last(v)?);
+            s.since_explicit = true;
+            s.last_explicit = true;
+            Ok(())
+        },
+    ),
+    value(
+        "--until",
+        None,
+        "<时间>",
+        ValueHint::None,
+        "结束时间",
+        |s, v| {
+            s.config.until = Some(v.to_string());
+            Ok(())
+        },
+    ),
+    flag(
+        "--no-default-since",
+        None,
+        "禁用默认时间窗口",
+        |s| s.config.since = None,
+    ),
+    flag(
+        "--json",
+        None,
+        "JSON 输出（仅 --stream 模式）",
+        |s| s.config.output_json = true,
+    ),
+    flag(
+        "--show-command",
+        None,
+        "显示生成的 journalctl 命令",
+        |s| s.config.show_command = true,
+    ),
+    flag(
+        "--linear",
+        None,
+        "逐句输出报告（无框线与对齐），适合读屏软件与盲文终端",
+        |s| s.config.linear = true,
+    ),
+    flag(
+        "--no-pager",
+        None,
+        "报告超过一屏时不使用分页器（默认使用 $PAGER 或 less）",
+        |s| s.config.no_pager = true,
+    ),
+    flag(
+        "--boot-history",
+        None,
+        "统计每个可疑来源在最近 5 次启动中的出现次数",
+        |s| s.config.boot_history = true,
+    ),
+    flag(
+        "--reliability",
+        None,
+        "统计每个可疑来源近 30 天的无错误天数占比与平均故障间隔",
+        |s| s.config.reliability = true,
+    ),
+    flag(
+        "--timeline",
+        None,
+        "按时间列出前列来源的首次出现与集中爆发",
+        |s| s.config.timeline = true,
+    ),
+    value(
+        "--histogram",
+        None,
+        "<间隔>",
+        ValueHint::None,
+        "按间隔（如 5m、1h）统计事件分布，为全部事件与前列来源各画一行火花线",
+        |s, v| {
+            s.config.histogram = Some(parse_histogram_interval(v)?);
+            Ok(())
+        },
+    ),
+    flag(
+        "--bursts",
+        None,
+        "列出每分钟事件数远超该来源近期基线的突发时段",
+        |s| s.config.bursts = true,
+    ),
+    flag(
+        "--compare-previous",
+        None,
+        "再分析紧挨着的上一个等长窗口，列出前列来源的新出现、增加、减少与已消失",
+        |s| s.config.compare_previous = true,
+    ),
+    flag(
+        "--by-package",
+        None,
+        "把属于同一 dpkg 包的来源合并汇总：合计事件数与各来源分布，回答“该给哪个包报 bug”",
+        |s| s.config.by_package = true,
+    ),
+    flag(
+        "--by-user",
+        None,
+        "按事件的 _UID 汇总各用户的事件数与主要来源，找出多用户服务器上是谁的程序在报错",
+        |s| s.config.by_user = true,
+    ),
+    flag(
+        "--per-boot",
+        None,
+        "按事件的 _BOOT_ID 分列各启动周期的事件数、最高级别与首要来源，看出是哪一次启动出了问题",
+        |s| s.config.per_boot = true,
+    ),
+    flag(
+        "--with-failed-units",
+        None,
+        "对照 systemctl 当前失败的单元：标出所属单元已失败的来源，并列出没有日志的失败单元",
+        |s| s.config.with_failed_units = true,
+    ),
+    flag(
+        "--smart",
+        None,
+        "对前列中报错的存储设备运行 smartctl -H，给出磁盘是否正在损坏的结论（通常需要 root）",
+        |s| s.config.smart = true,
+    ),
+    flag(
+        "--crashes",
+        None,
+        "另查一次内核日志，列出 segfault 与 general protection fault 等程序崩溃及出错的库",
+        |s| s.config.crashes = true,
+    ),
+    flag(
+        "--hangs",
+        None,
+        "另查一次内核日志，列出挂起的任务、软/硬死锁与 RCU stall",
+        |s| s.config.hangs = true,
+    ),
+    flag(
+        "--network",
+        None,
+        "另查一次 NetworkManager / systemd-networkd / systemd-resolved 的日志，列出断网、DHCP 失败与 DNS 降级",
+        |s| s.config.network = true,
+    ),
+    flag(
+        "--hardware",
+        None,
+        "另查一次内核日志，在报告开头标出机器检查异常、EDAC 内存错误与过热降频",
+        |s| s.config.hardware = true,
+    ),
+    flag(
+        "--audit",
+        None,
+        "另查一次审计日志，解码 AppArmor / SELinux / seccomp 拒绝记录并反查所属包",
+        |s| s.config.audit = true,
+    ),
+    value(
+        "--against-baseline",
+        None,
+        "<文件>",
+        ValueHint::File,
+        "与 logtool baseline save 保存的基线对比，标出每小时事件数明显高于基线的来源",
+        |s, v| {
+            s.config.against_baseline = Some(v.to_string());
+            Ok(())
+        },
+    ),
+    flag(
+        "--summary-only",
+        None,
+        "仅输出一行可解析摘要（适合 cron/脚本）",
+        |s| s.config.summary_only = true,
+    ),
+    flag(
+        "--env-snapshot",
+        None,
+        "在结果中附带内核/系统/systemd/logtool 版本快照",
+        |s| s.config.env_snapshot = true,
+    ),
+    value(
+        "--fail-on",
+        None,
+        "<级别>",
+        ValueHint::Choices(PRIORITY_CHOICES),
+        "发现该级别或更严重的事件时以退出码 2 结束（适合健康检查/CI）",
+        |s, v| {
+            s.config.fail_on = Some(parse_fail_on(v)?);
+            Ok(())
+        },
+    ),
+    value(
+        "--fail-on-count",
+        None,
+        "<N>",
+        ValueHint::None,
+        "至少 N 条此类事件才触发 --fail-on（默认 1）",
+        |s, v| {
+            s.config.fail_on_count = Some(parse_positive_usize(v, "--fail-on-count")?);
+            Ok(())
+        },
+    ),
+    value(
+        "--samples",
+        None,
+        "<N>",
+        ValueHint::None,
+        "每个可疑来源保留并展示 N 条不同的示例消息（严重级别优先）",
+        |s, v| {
+            s.config.samples = Some(parse_positive_usize(v, "--samples")?);
+            Ok(())
+        },
+    ),
+    value(
+        "--clusters",
+        None,
+        "<N>",
+        ValueHint::None,
+        "把数字、地址、路径不同的相似消息归为同一模板，每个可疑来源列出次数最多的 N 类",
+        |s, v| {
+            s.config.clusters = Some(parse_positive_usize(v, "--clusters")?);
+            Ok(())
+        },
+    ),
+    value(
+        "--evidence",
+        None,
+        "<目录>",
+        ValueHint::Dir,
+        "把前列来源计入统计的原始 journal 条目（JSON 行）按来源写入该目录",
+        |s, v| {
+            s.config.evidence_dir = Some(v.to_string());
+            Ok(())
+        },
+    ),
+    value(
+        "--max-msg-chars",
+        None,
+        "<N>",
+        ValueHint::None,
+        "示例消息最多保留 N 个字符（默认：180）",
+        |s, v| {
+            s.config.max_message_chars = Some(parse_positive_usize(v, "--max-msg-chars")?);
+            Ok(())
+        },
+    ),
+    value(
+        "--max-line-bytes",
+        None,
+        "<N>",
+        ValueHint::None,
+        "流模式每行最多输出 N 字节，超出部分以截断标记代替",
+        |s, v| {
+            s.config.max_line_bytes = Some(parse_positive_usize(v, "--max-line-bytes")?);
+            Ok(())
+        },
+    ),
+    value(
+        "--max-resp-bytes",
+        None,
+        "<N>",
+        ValueHint::None,
+        "分析响应最多 N 字节，超出时省略示例、时间线与末尾来源并在报告中注明",
+        |s, v| {
+            s.config.max_response_bytes = Some(parse_positive_usize(v, "--max-resp-bytes")?);
+            Ok(())
+        },
+    ),
+    flag(
+        "--redact",
+        None,
+        "遮盖示例消息与流输出中的 IP、MAC、主机名和用户名（便于对外分享）",
+        |s| s.config.redact = true,
+    ),
+    value(
+        "--template",
+        None,
+        "<文件>",
+        ValueHint::File,
+        "使用自定义模板渲染报告（{{变量}}、{{#区块}}…{{/区块}}）",
+        |s, v| {
+            s.config.template_path = Some(v.to_string());
+            Ok(())
+        },
+    ),
+    value(
+        "--group-by",
+        None,
+        "<维度>",
+        ValueHint::Choices(&["unit", "exe", "identifier", "package", "comm"]),
+        "来源的归并维度：unit | exe | identifier | package（所属 dpkg 包）| comm；缺少该字段的事件按默认次序",
+        |s, v| {
+            s.config.group_by = parse_group_by(v)?;
+            Ok(())
+        },
+    ),
+    value(
+        "--format",
+        None,
+        "<格式>",
+        ValueHint::Choices(&["text", "json", "ndjson", "logfmt"]),
+        "结果格式：text（默认）| json | ndjson（每个可疑来源一行）| logfmt（流模式亦可用）",
+        |s, v| {
+            s.config.format = parse_report_format(v)?;
+            Ok(())
+        },
+    ),
+    value(
+        "--output",
+        None,
+        "<文件>",
+        ValueHint::File,
+        "写入文件而非标准输出（报告原子写入，流模式追加并按 64MiB 轮转）",
+        |s, v| {
+            s.config.output_path = Some(v.to_string());
+            Ok(())
+        },
+    ),
+    value(
+        "--forward",
+        None,
+        "<路径>",
+        ValueHint::File,
+        "流模式下将匹配事件以 JSON 行额外写入 Unix Socket 或 FIFO",
+        |s, v| {
+            s.config.forward_path = Some(v.to_string());
+            Ok(())
+        },
+    ),
+    flag(
+        "--fresh",
+        None,
+        "重新分析，不用守护进程预先计算的结果",
+        |s| s.config.fresh = true,
+    ),
+    flag(
+        "--archive",
+        None,
+        "流模式下由守护进程另存本次会话（需 --archive-dir）",
+        |s| s.config.archive = true,
+    ),
+    watch_value(
+        "--interval",
+        "<秒>",
+        ValueHint::None,
+        "两次检查之间的间隔（默认 60）",
+        |s, v| {
+            s.watch.interval_secs = parse_positive_usize(v, "--interval")? as u64;
+            s.watch_flag.get_or_insert("--interval");
+            Ok(())
+        },
+    ),
+    watch_value(
+        "--cooldown",
+        "<秒>",
+        ValueHint::None,
+        "同一来源的告警冷却时间（默认 1800）；期间仅在事件数翻倍或级别升高时再次告警",
+        |s, v| {
+            s.watch.cooldown_secs = parse_positive_usize(v, "--cooldown")? as u64;
+            s.watch_flag.get_or_insert("--cooldown");
+            Ok(())
+        },
+    ),
+    watch_value(
+        "--on-alert",
+        "<命令>",
+        ValueHint::None,
+        "每条告警通过 sh -c 执行的命令（告警信息在 LOGTOOL_ALERT_* 环境变量中）",
+        |s, v| {
+            s.watch.on_alert = Some(v.to_string());
+            s.watch_flag.get_or_insert("--on-alert");
+            Ok(())
+        },
+    ),
+];
+
+/// 子命令：(用法, 说明)；说明中的换行在帮助里缩进续行
+pub static COMMANDS: &[(&str, &str)] = &[
+    ("help", "显示帮助（等同 --help）"),
+    ("version", "显示版本（等同 --version）"),
+    ("doctor", "运行环境自检（等同 --doctor）"),
+    ("boots", "列出启动周期（等同 --list-boots）"),
+    (
+        "last-words [--boot -1]",
+        "死机排查：输出该启动周期最后 N 条日志（全部级别，--lines 默认 50），并标注距下次启动的间隔",
+    ),
+    (
+        "rules test [样本文件]",
+        "用样本文件（journal JSON 或纯文本行）或最近日志测试规则\n可加 --rules <文件|目录> 指定待测规则（默认 /etc/logtool/rules.d）",
+    ),
+    (
+        "rules lint",
+        "检查规则文件（JSON 错误、重复 ID、无效模式、遮蔽与归并冲突）",
+    ),
+    (
+        "watch [选项]",
+        "周期性分析并输出告警（同一来源去重，见“监视选项”）；其余分析选项照常可用",
+    ),
+    (
+        "compare -u A -u B",
+        "对比两个单元的事件数、级别、常见消息与时间关联（“A 失败是不是因为 B？”）",
+    ),
+    (
+        "ack <id>",
+        "确认 watch 告警；此后仅在严重级别升高时再次告警",
+    ),
+    (
+        "note <id> <文本>",
+        "为可疑来源添加备注（显示在之后的报告中）；--clear 清除，不带参数列出全部",
+    ),
+    (
+        "diff <旧.json> <新.json>",
+        "比较两份 --format json 保存的分析结果：新出现、已消失与事件数变化的来源；--format json 输出 JSON",
+    ),
+    (
+        "baseline save <文件>",
+        "按当前分析选项保存各来源的事件数与窗口长度，供之后 --against-baseline 对比",
+    ),
+    (
+        "stats [--rules]",
+        "查看守护进程运行统计；--rules 列出各规则命中次数（找出噪声/失效规则）",
+    ),
+    (
+        "status [--json]",
+        "一行显示缓存的健康状况（不启动 journalctl，适合状态栏轮询）；--json 输出小型 JSON 文档",
+    ),
+    (
+        "map [--scan]",
+        "导出守护进程已学到的单元/可执行文件 → 包映射；--scan 扫描 dpkg 数据库补全，--format json 输出 JSON",
+    ),
+    ("man", "输出 roff 格式的手册页（logtool man | man -l -）"),
+    ("completions <shell>", "输出 bash / zsh / fish 补全脚本"),
+    ("run", "按默认分析执行（适合交互模式）"),
+];
+
+pub const COMPLETION_SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+const EXAMPLES: &[&str] = &[
+    "logtool",
+    "logtool doctor",
+    "logtool boots",
+    "logtool --since \"30 min ago\" --top 15",
+    "logtool --kernel --priority 4 --grep hang",
+    "logtool --stream --follow --unit ssh",
+    "logtool --stream --follow --output /var/tmp/logtool-stream.log",
+    "logtool watch --interval 120 --on-alert 'notify-send \"$LOGTOOL_ALERT_ID\"'",
+];
+
+pub fn find_option(name: &str) -> Option<&'static OptionSpec> {
+    OPTIONS.iter().find(|spec| spec.matches(name))
+}
+
+/// 命令词（首个参数）列表，供补全使用
+fn command_words() -> Vec<&'static str> {
+    let mut words = COMMANDS
+        .iter()
+        .filter_map(|(usage, _)| usage.split_whitespace().next())
+        .collect::<Vec<_>>();
+    words.extend(["analyze", "stream"]);
+    words.dedup();
+    words
+}
+
+// ── 帮助文本 ─────────────────────────────────────────────
+
+/// 帮助说明起始列：选项与模式为 32，命令与交互模式为 27
+const OPTION_COLUMN: usize = 32;
+const COMMAND_COLUMN: usize = 27;
+
+/// 终端显示宽度：中日韩全角字符占两列
+pub(crate) fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115F
+            | 0x2E80..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6 => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+fn push_aligned(out: &mut String, column: usize, left: &str, help: &str) {
+    let left = format!("  {left}");
+    let pad = column.saturating_sub(display_width(&left)).max(1);
+    for (index, line) in help.lines().enumerate() {
+        if index == 0 {
+            let _ = writeln!(out, "{left}{}{line}", " ".repeat(pad));
+        } else {
+            let _ = writeln!(out, "{}{line}", " ".repeat(column));
+        }
+    }
+}
+
+fn option_left(spec: &OptionSpec) -> String {
+    let names = spec.flag_names();
+    let mut left = if spec.short.is_some() {
+        names.join(", ")
+    } else {
+        format!("    {}", names.join(", "))
+    };
+    if !spec.placeholder.is_empty() {
+        left.push(' ');
+        left.push_str(spec.placeholder);
+    }
+    left
+}
+
+pub fn render_help() -> String {
+    let mut out = String::from(
+        "logtool — Ubuntu 系统异常日志诊断工具
+
+默认模式为 --analyze（归因分析，定位可疑程序/包）。
+
+用法：
+  logtool                    进入交互模式（输入 help/doctor/boots）
+  logtool [命令|选项]        单次执行模式
+
+模式：
+",
+    );
+    for spec in OPTIONS.iter().filter(|s| s.section == HelpSection::Mode) {
+        push_aligned(&mut out, OPTION_COLUMN, &option_left(spec), spec.help);
+    }
+    push_aligned(&mut out, OPTION_COLUMN, "    analyze", "归因分析模式别名");
+    push_aligned(&mut out, OPTION_COLUMN, "    stream", "原始日志流模式别名");
+
+    out.push_str("\n命令：\n");
+    for (usage, help) in COMMANDS {
+        push_aligned(&mut out, COMMAND_COLUMN, usage, help);
+    }
+
+    out.push_str("\n交互模式：\n");
+    push_aligned(&mut out, COMMAND_COLUMN, "exit / quit / q", "退出交互模式");
+
+    out.push_str("\n选项：\n");
+    for spec in OPTIONS.iter().filter(|s| s.section == HelpSection::Option) {
+        push_aligned(&mut out, OPTION_COLUMN, &option_left(spec), spec.help);
+    }
+
+    out.push_str("\n监视选项（logtool watch）：\n");
+    for spec in OPTIONS.iter().filter(|s| s.section == HelpSection::Watch) {
+        push_aligned(&mut out, OPTION_COLUMN, &option_left(spec), spec.help);
+    }
+
+    out.push_str("\n示例：\n");
+    for example in EXAMPLES {
+        let _ = writeln!(out, "  {example}");
+    }
+    out
+}
+
+// ── man 手册页 ─────────────────────────────────────────────
+
+fn roff_escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('-', "\\-");
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{escaped}")
+    } else {
+        escaped
+    }
+}
+
+pub fn render_man_page() -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        ".TH LOGTOOL 1 \"\" \"logtool {}\" \"User Commands\"",
+        env!("CARGO_PKG_VERSION")
+    );
+    out.push_str(".SH NAME\nlogtool \\- Ubuntu 系统异常日志诊断工具\n");
+    out.push_str(".SH SYNOPSIS\n.B logtool\n[\\fI命令\\fR|\\fI选项\\fR]\n");
+    out.push_str(".SH DESCRIPTION\n");
+    out.push_str(
+        "通过 logtool\\-daemon 读取 systemd journal，按来源归因并排列可疑程序/服务/包。\n\
+         不带参数时进入交互模式。默认模式为 \\fB\\-\\-analyze\\fR。\n",
+    );
+
+    out.push_str(".SH COMMANDS\n");
+    for (usage, help) in COMMANDS {
+        let _ = writeln!(out, ".TP\n.B {}", roff_escape(usage));
+        for line in help.lines() {
+            let _ = writeln!(out, "{}", roff_escape(line));
+        }
+    }
+
+    for (title, section) in [
+        ("MODES", HelpSection::Mode),
+        ("OPTIONS", HelpSection::Option),
+        ("WATCH OPTIONS", HelpSection::Watch),
+    ] {
+        let _ = writeln!(out, ".SH {title}");
+        for spec in OPTIONS.iter().filter(|s| s.section == section) {
+            let names = spec
+                .flag_names()
+                .iter()
+                .map(|n| format!("\\fB{}\\fR", roff_escape(n)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let placeholder = if spec.placeholder.is_empty() {
+                String::new()
+            } else {
+                format!(" \\fI{}\\fR", roff_escape(spec.placeholder))
+            };
+            let _ = writeln!(out, ".TP\n{names}{placeholder}\n{}", roff_escape(spec.help));
+        }
+    }
+
+    out.push_str(".SH EXAMPLES\n");
+    for example in EXAMPLES {
+        let _ = writeln!(out, ".nf\n{}\n.fi", roff_escape(example));
+    }
+    out.push_str(
+        ".SH FILES\n.TP\n/run/logtool.sock\n守护进程 Unix Socket（/run 不可写时为 $XDG_RUNTIME_DIR/logtool.sock，或 $LOGTOOL_SOCKET_DIR 指定的目录）\n\
+         .TP\n/etc/logtool/config.json, ~/.config/logtool/config.json\n配置文件\n\
+         .TP\n/etc/logtool/rules.d/*.json\n规则文件\n\
+         .TP\n/var/cache/logtool/pkgcache.json, ~/.cache/logtool/pkgcache.json\n包反查缓存（$LOGTOOL_PKGCACHE 可另行指定）\n",
+    );
+    out.push_str(".SH SEE ALSO\n.BR journalctl (1),\n.BR logtool\\-daemon (8)\n");
+    out
+}
+
+// ── 补全脚本 ─────────────────────────────────────────────
+
+pub fn render_completions(shell: &str) -> Result<String, String> {
+    match shell {
+        "bash" => Ok(render_bash_completion()),
+        "zsh" => Ok(render_zsh_completion()),
+        "fish" => Ok(render_fish_completion()),
+        other => Err(format!(
+            "不支持的 shell：{other}\n修复：使用 logtool completions bash|zsh|fish"
+        )),
+    }
+}
+
+fn render_bash_completion() -> String {
+    let mut out = String::from(
+        "# logtool bash 补全（logtool completions bash 生成）\n_logtool() {\n    local cur prev\n    cur=\"${COMP_WORDS[COMP_CWORD]}\"\n    prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n    case \"$prev\" in\n",
+    );
+    for spec in OPTIONS.iter().filter(|s| s.takes_value()) {
+        let names = spec.flag_names().join("|");
+        match spec.hint {
+            ValueHint::File => {
+                let _ = writeln!(
+                    out,
+                    "        {names}) COMPREPLY=($(compgen -f -- \"$cur\")); return ;;"
+                );
+            }
+            ValueHint::Dir => {
+                let _ = writeln!(
+                    out,
+                    "        {names}) COMPREPLY=($(compgen -d -- \"$cur\")); return ;;"
+                );
+            }
+            ValueHint::Choices(choices) => {
+                let _ = writeln!(
+                    out,
+                    "        {names}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return ;;",
+                    choices.join(" ")
+                );
+            }
+            ValueHint::None if matches!(spec.kind, OptionKind::Value(_)) => {
+                let _ = writeln!(out, "        {names}) return ;;");
+            }
+            ValueHint::None => {}
+        }
+    }
+    let mut words = OPTIONS
+        .iter()
+        .flat_map(|s| s.flag_names())
+        .collect::<Vec<_>>();
+    words.extend(command_words().into_iter().map(str::to_string));
+    let _ = write!(
+        out,
+        "    esac\n    COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n}}\ncomplete -F _logtool logtool\n",
+        words.join(" ")
+    );
+    out
+}
+
+fn shell_single_quote(text: &str) -> String {
+    text.replace('\'', "'\\''")
+}
+
+fn render_zsh_completion() -> String {
+    let mut out = String::from(
+        "#compdef logtool\n# logtool zsh 补全（logtool completions zsh 生成）\n_arguments \\\n",
+    );
+    for spec in OPTIONS {
+        let names = spec.flag_names();
+        let action = match spec.hint {
+            ValueHint::File => ":file:_files".to_string(),
+            ValueHint::Dir => ":directory:_files -/".to_string(),
+            ValueHint::Choices(choices) => format!(":value:({})", choices.join(" ")),
+            ValueHint::None if spec.takes_value() => ":value: ".to_string(),
+            ValueHint::None => String::new(),
+        };
+        let optional = matches!(spec.kind, OptionKind::OptionalValue(_));
+        let help = shell_single_quote(&spec.help.replace(['[', ']'], ""));
+        let group = if names.len() > 1 {
+            format!("{{{}}}", names.join(","))
+        } else {
+            names[0].clone()
+        };
+        let action = if optional {
+            action.replacen(':', "::", 1)
+        } else {
+            action
+        };
+        let _ = writeln!(out, "  '{group}[{help}]{action}' \\");
+    }
+    let _ = writeln!(out, "  '1:command:({})'", command_words().join(" "));
+    out
+}
+
+fn render_fish_completion() -> String {
+    let mut out = String::from("# logtool fish 补全（logtool completions fish 生成）\n");
+    for word in command_words() {
+        let _ = writeln!(
+            out,
+            "complete -c logtool -n '__fish_use_subcommand' -f -a {word}"
+        );
+    }
+    for spec in OPTIONS {
+        let mut line = String::from("complete -c logtool");
+        if let Some(c) = spec.short {
+            let _ = write!(line, " -s {c}");
+        }
+        let _ = write!(line, " -l {}", spec.long.trim_start_matches("--"));
+        match spec.hint {
+            ValueHint::File => line.push_str(" -r -F"),
+            ValueHint::Dir => line.push_str(" -x -a '(__fish_complete_directories)'"),
+            ValueHint::Choices(choices) => {
+                let _ = write!(line, " -x -a '{}'", choices.join(" "));
+            }
+            ValueHint::None if matches!(spec.kind, OptionKind::Value(_)) => line.push_str(" -x"),
+            ValueHint::None => {}
+        }
+        let _ = write!(line, " -d '{}'", shell_single_quote(spec.help));
+        let _ = writeln!(out, "{line}");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn option_names_are_unique() {
+        let mut names = OPTIONS
+            .iter()
+            .flat_map(|s| {
+                let mut all = s.flag_names();
+                all.extend(
+                    s.aliases
+                        .iter()
+                        .filter(|a| !a.starts_with('-'))
+                        .map(|a| a.to_string()),
+                );
+                all
+            })
+            .collect::<Vec<_>>();
+        let total = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), total);
+    }
+
+    #[test]
+    fn help_lists_every_option_aligned() {
+        let help = render_help();
+        for spec in OPTIONS {
+            let line = help
+                .lines()
+                .find(|l| l.contains(spec.long) && l.contains(spec.help))
+                .unwrap_or_else(|| panic!("帮助中缺少 {}", spec.long));
+            assert_eq!(
+                line.find(spec.help).map(|i| display_width(&line[..i])),
+                Some(OPTION_COLUMN)
+            );
+        }
+        assert!(help.contains(
+            "      -V, --version             显示版本信息（需单独使用，单独的 -v 同此）"
+        ));
+        assert!(help.contains("  -v, --invert-match            只保留不匹配"));
+    }
+
+    #[test]
+    fn man_and_completions_cover_every_option() {
+        let man = render_man_page();
+        assert!(man.starts_with(".TH LOGTOOL 1"));
+        for shell in COMPLETION_SHELLS {
+            let script = render_completions(shell).expect("应支持");
+            for spec in OPTIONS {
+                let bare = spec.long.trim_start_matches("--");
+                assert!(script.contains(bare), "{shell} 补全缺少 {}", spec.long);
+                assert!(man.contains(&roff_escape(spec.long)));
+            }
+        }
+        assert!(render_completions("tcsh").is_err());
+    }
+}
@@ -0,0 +1,180 @@
+// 按软件包汇总 — `--by-package` 把属于同一 dpkg 包的来源（单元、可执行文件等）合并成一项，
+// 给出合计事件数与各来源的分布，直接回答“该给哪个包报 bug”
+//
+// 各来源的排行与明细不变，汇总单独成节。所属包照常经 dpkg-query 反查，但查询范围从前 top 个
+// 扩大到前 MAX_RESOLVED_SOURCES 个来源；查不到包的来源不计入汇总，只给出其事件数。
+
+use crate::{SourceStats, priority_label_cn, suspect_id};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// --by-package 时反查所属包的来源数上限，避免长尾来源逐个调用 dpkg-query
+pub const MAX_RESOLVED_SOURCES: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageSource {
+    pub id: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageSummary {
+    pub package: String,
+    pub count: u64,
+    pub worst_priority: u8,
+    /// 属于该包的来源，按事件数降序
+    pub sources: Vec<PackageSource>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageView {
+    /// 按合计事件数降序，最多 top 个
+    pub packages: Vec<PackageSummary>,
+    /// 查不到所属包（或超出反查范围）的来源的事件数
+    pub unowned: u64,
+}
+
+pub fn summarize_by_package(suspects: &[SourceStats], top: usize) -> PackageView {
+    let mut packages: HashMap<&str, PackageSummary> = HashMap::new();
+    let mut unowned = 0;
+    for suspect in suspects {
+        let Some(package) = suspect.package.as_deref() else {
+            unowned += suspect.count;
+            continue;
+        };
+        let summary = packages.entry(package).or_insert_with(|| PackageSummary {
+            package: package.to_string(),
+            count: 0,
+            worst_priority: 7,
+            sources: Vec::new(),
+        });
+        summary.count += suspect.count;
+        summary.worst_priority = summary.worst_priority.min(suspect.worst_priority);
+        summary.sources.push(PackageSource {
+            id: suspect_id(suspect.kind, &suspect.source),
+            count: suspect.count,
+        });
+    }
+    let mut packages = packages.into_values().collect::<Vec<_>>();
+    for summary in &mut packages {
+        summary
+            .sources
+            .sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.id.cmp(&b.id)));
+    }
+    packages.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then(a.worst_priority.cmp(&b.worst_priority))
+            .then_with(|| a.package.cmp(&b.package))
+    });
+    packages.truncate(top);
+    PackageView { packages, unowned }
+}
+
+/// `unit:nginx.service（30）、exe:/usr/sbin/nginx（10）`
+fn source_list(summary: &PackageSummary) -> String {
+    summary
+        .sources
+        .iter()
+        .map(|source| format!("{}（{}）", source.id, source.count))
+        .collect::<Vec<_>>()
+        .join("、")
+}
+
+pub fn write_package_section<W: Write>(out: &mut W, view: &PackageView) -> io::Result<()> {
+    for summary in &view.packages {
+        writeln!(
+            out,
+            "  ×{} {}  最高严重级别={}({})  {} 个来源：{}",
+            summary.count,
+            summary.package,
+            summary.worst_priority,
+            priority_label_cn(summary.worst_priority),
+            summary.sources.len(),
+            source_list(summary)
+        )?;
+    }
+    if view.unowned > 0 {
+        writeln!(out, "  另有 {} 条事件的来源不属于已知的包", view.unowned)?;
+    }
+    Ok(())
+}
+
+/// 逐句版本（--linear）
+pub fn package_sentences(view: &PackageView) -> Vec<String> {
+    let mut sentences = view
+        .packages
+        .iter()
+        .map(|summary| {
+            format!(
+                "软件包 {} 共 {} 条事件，最高严重级别为{}（{}），来自 {}。",
+                summary.package,
+                summary.count,
+                priority_label_cn(summary.worst_priority),
+                summary.worst_priority,
+                source_list(summary)
+            )
+        })
+        .collect::<Vec<_>>();
+    if view.unowned > 0 {
+        sentences.push(format!(
+            "另有 {} 条事件的来源不属于已知的包。",
+            view.unowned
+        ));
+    }
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SourceKind;
+    use crate::test_support::sample_suspect;
+
+    fn owned(suspect: SourceStats, package: &str) -> SourceStats {
+        SourceStats {
+            package: Some(package.to_string()),
+            ..suspect
+        }
+    }
+
+    #[test]
+    fn merges_sources_of_the_same_package() {
+        let suspects = [
+            owned(
+                sample_suspect(SourceKind::Unit, "php8.3-fpm.service", 35, 3),
+                "php8.3-fpm",
+            ),
+            owned(
+                sample_suspect(SourceKind::Unit, "nginx.service", 30, 3),
+                "nginx-core",
+            ),
+            sample_suspect(SourceKind::Identifier, "myscript", 12, 3),
+            owned(
+                sample_suspect(SourceKind::Executable, "/usr/sbin/nginx", 10, 2),
+                "nginx-core",
+            ),
+        ];
+        let view = summarize_by_package(&suspects, 10);
+        assert_eq!(view.unowned, 12);
+        let nginx = &view.packages[0];
+        assert_eq!(
+            (nginx.package.as_str(), nginx.count, nginx.worst_priority),
+            ("nginx-core", 40, 2)
+        );
+
+        let mut out = Vec::new();
+        write_package_section(&mut out, &view).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert_eq!(
+            text,
+            "  ×40 nginx-core  最高严重级别=2(严重)  2 个来源：unit:nginx.service（30）、exe:/usr/sbin/nginx（10）\n  ×35 php8.3-fpm  最高严重级别=3(错误)  1 个来源：unit:php8.3-fpm.service（35）\n  另有 12 条事件的来源不属于已知的包\n"
+        );
+        assert_eq!(
+            package_sentences(&view)[1],
+            "软件包 php8.3-fpm 共 35 条事件，最高严重级别为错误（3），来自 unit:php8.3-fpm.service（35）。"
+        );
+        assert_eq!(summarize_by_package(&suspects, 1).packages.len(), 1);
+    }
+}
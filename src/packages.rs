@@ -0,0 +1,984 @@
+// 包映射 — 服务单元/可执行文件 → Debian 包
+//
+// PackageResolver 每解析出一个映射就记入进程内的 LEARNED，daemon 生命周期内跨请求复用；
+// /var/lib/dpkg/status 的修改时间变化（安装/卸载了包）时清空。
+// LEARNED 另存到磁盘缓存（见 cache_path），连同 dpkg 数据库的修改时间一起，供之后的运行与 daemon 重启后
+// 直接读入；修改时间不一致的缓存不读。缓存只是加速，读写失败都不影响反查。
+// 前列来源的反查耗时主要在 dpkg-query / systemctl 的进程启动上，由至多 RESOLVE_WORKERS 个线程分担。
+// snap 应用不经 dpkg：`/snap/<名称>/<修订号>/…` 下的可执行文件与 `snap.<名称>.<应用>.service` 单元
+// 归为 `snap:<名称> (<修订号>)`；路径中是 current 或单元无路径可看时，修订号取自 /snap/<名称>/current 链接
+// 或 `snap list <名称>`。修订号随刷新变化，snap 的归属不记入 LEARNED。
+// `logtool map --scan` 另外直接读取 /var/lib/dpkg/info/*.list 得到完整映射，无需调用 dpkg-query。
+
+use crate::audit::AuditDenial;
+use crate::crash::Crash;
+use crate::state::{LOCK_WAIT, StateLock};
+use crate::{JournalEvent, PackageVersion, SourceKind, SourceStats, write_file_atomically};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DPKG_INFO_DIR: &str = "/var/lib/dpkg/info";
+const DPKG_STATUS_PATH: &str = "/var/lib/dpkg/status";
+const SNAP_MOUNT_DIR: &str = "/snap";
+pub const SYSTEM_CACHE_DIR: &str = "/var/cache/logtool";
+/// 并行反查前列来源的线程数上限
+const RESOLVE_WORKERS: usize = 8;
+
+/// 单元文件所在目录（包内路径）
+const UNIT_DIRS: &[&str] = &[
+    "/lib/systemd/system/",
+    "/usr/lib/systemd/system/",
+    "/lib/systemd/user/",
+    "/usr/lib/systemd/user/",
+    "/etc/systemd/system/",
+];
+/// 可执行文件所在目录；libexec 下允许子目录
+const EXECUTABLE_DIRS: &[&str] = &["/usr/bin/", "/usr/sbin/", "/bin/", "/sbin/"];
+const LIBEXEC_DIRS: &[&str] = &["/usr/libexec/", "/usr/lib/libexec/"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageMap {
+    /// `ssh.service` → `openssh-server`
+    pub units: BTreeMap<String, String>,
+    /// `/usr/sbin/sshd` → `openssh-server`
+    pub executables: BTreeMap<String, String>,
+}
+
+impl PackageMap {
+    pub const fn new() -> Self {
+        Self {
+            units: BTreeMap::new(),
+            executables: BTreeMap::new(),
+        }
+    }
+
+    /// 合并另一份映射；冲突时以 other 为准
+    pub fn merge(&mut self, other: PackageMap) {
+        self.units.extend(other.units);
+        self.executables.extend(other.executables);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.units.is_empty() && self.executables.is_empty()
+    }
+}
+
+struct Learned {
+    dpkg_status_mtime: Option<SystemTime>,
+    map: PackageMap,
+    /// 是否已尝试读入磁盘缓存
+    loaded: bool,
+    /// 有尚未写入磁盘缓存的映射
+    dirty: bool,
+}
+
+static LEARNED: Mutex<Learned> = Mutex::new(Learned {
+    dpkg_status_mtime: None,
+    map: PackageMap::new(),
+    loaded: false,
+    dirty: false,
+});
+/// 同一进程内的多个线程不同时写缓存文件（临时文件名只含进程号）
+static SAVING: Mutex<()> = Mutex::new(());
+
+/// 磁盘缓存的内容；dpkg_status_mtime_ns 与当前 dpkg 数据库不一致时整份作废
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageCache {
+    #[serde(default)]
+    pub dpkg_status_mtime_ns: Option<u64>,
+    #[serde(flatten)]
+    pub map: PackageMap,
+}
+
+fn learned() -> std::sync::MutexGuard<'static, Learned> {
+    LEARNED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// dpkg 数据库变化后已学到的映射可能过时，整体清空；首次调用时读入与之相符的磁盘缓存
+pub fn refresh_learned() {
+    let mtime = fs::metadata(DPKG_STATUS_PATH)
+        .and_then(|m| m.modified())
+        .ok();
+    let mut learned = learned();
+    if learned.dpkg_status_mtime != mtime {
+        learned.dpkg_status_mtime = mtime;
+        learned.map = PackageMap::new();
+        learned.dirty = false;
+    }
+    if !learned.loaded {
+        learned.loaded = true;
+        if let Some(cache) = cache_path().and_then(|path| PackageCache::load_matching(&path, mtime))
+        {
+            let map = std::mem::take(&mut learned.map);
+            learned.map = cache.map;
+            learned.map.merge(map);
+        }
+    }
+}
+
+/// 把新学到的映射写入磁盘缓存；没有新映射时不写
+pub fn save_learned() {
+    let _saving = SAVING
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let cache = {
+        let mut learned = learned();
+        if !learned.dirty {
+            return;
+        }
+        learned.dirty = false;
+        PackageCache {
+            dpkg_status_mtime_ns: mtime_ns(learned.dpkg_status_mtime),
+            map: learned.map.clone(),
+        }
+    };
+    if let Some(path) = cache_path() {
+        let _ = cache.merge_into(&path);
+    }
+}
+
+fn mtime_ns(mtime: Option<SystemTime>) -> Option<u64> {
+    let elapsed = mtime?.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(elapsed.as_nanos()).ok()
+}
+
+impl PackageCache {
+    /// 文件不存在或内容损坏时为 None
+    pub fn load_from(path: &Path) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    /// 只读入与给定 dpkg 数据库修改时间相符的缓存；dpkg 之后安装或卸载过软件包时为 None
+    pub fn load_matching(path: &Path, dpkg_status_mtime: Option<SystemTime>) -> Option<Self> {
+        Self::load_from(path)
+            .filter(|cache| cache.dpkg_status_mtime_ns == mtime_ns(dpkg_status_mtime))
+    }
+
+    /// daemon 与本地模式的 CLI 共用缓存文件：持锁读入对方新写入的映射，与本进程的合并后写回
+    /// （同一条目以本进程的为准）；磁盘上的缓存对应另一个 dpkg 数据库时直接覆盖
+    pub fn merge_into(mut self, path: &Path) -> Result<(), String> {
+        let _lock = StateLock::acquire(path, LOCK_WAIT)?;
+        if let Some(disk) = Self::load_from(path)
+            && disk.dpkg_status_mtime_ns == self.dpkg_status_mtime_ns
+        {
+            let mut map = disk.map;
+            map.merge(self.map);
+            self.map = map;
+        }
+        self.save_to(path)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("创建缓存目录 {} 失败：{e}", parent.display()))?;
+        }
+        let text = serde_json::to_string(self).map_err(|e| format!("序列化包缓存失败：{e}"))?;
+        write_file_atomically(path, text.as_bytes())
+    }
+}
+
+/// $LOGTOOL_PKGCACHE（测试/自定义），否则 /var/cache/logtool/pkgcache.json（目录存在时，供 daemon 与
+/// 同一台机器上的多个用户共享），否则 $XDG_CACHE_HOME/logtool/pkgcache.json（默认 ~/.cache/logtool/）
+pub fn cache_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("LOGTOOL_PKGCACHE").filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let system = Path::new(SYSTEM_CACHE_DIR);
+    if system.is_dir() {
+        return Some(system.join("pkgcache.json"));
+    }
+    if let Some(dir) = env::var_os("XDG_CACHE_HOME").filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(dir).join("logtool").join("pkgcache.json"));
+    }
+    env::var_os("HOME")
+        .filter(|v| !v.is_empty())
+        .map(|home| PathBuf::from(home).join(".cache/logtool/pkgcache.json"))
+}
+
+pub fn learned_unit(unit: &str) -> Option<String> {
+    learned().map.units.get(unit).cloned()
+}
+
+pub fn learned_executable(path: &str) -> Option<String> {
+    learned().map.executables.get(path).cloned()
+}
+
+pub fn learn_unit(unit: &str, package: &str) {
+    let mut learned = learned();
+    learned
+        .map
+        .units
+        .insert(unit.to_string(), package.to_string());
+    learned.dirty = true;
+}
+
+pub fn learn_executable(path: &str, package: &str) {
+    let mut learned = learned();
+    learned
+        .map
+        .executables
+        .insert(path.to_string(), package.to_string());
+    learned.dirty = true;
+}
+
+pub fn learned_map() -> PackageMap {
+    learned().map.clone()
+}
+
+// ── 包反查 ─────────────────────────────────────────────
+
+/// 为排行前 top 的来源填入所属包
+pub(crate) fn resolve_packages_for_top(suspects: &mut [SourceStats], top: usize) {
+    let limit = suspects.len().min(top);
+    for (index, package) in resolve_in_parallel(&suspects[..limit], PackageResolver::resolve) {
+        suspects[index].package = package;
+    }
+    save_learned();
+}
+
+/// 为前 top 个来源所属的 dpkg 包填入安装版本与来源仓库（报 bug 时要写明）；snap 已带修订号，不查
+pub(crate) fn resolve_package_versions(suspects: &mut [SourceStats], top: usize) {
+    let limit = suspects.len().min(top);
+    let resolved = resolve_in_parallel(&suspects[..limit], |resolver, suspect| {
+        resolver.package_version(suspect.package.as_deref()?)
+    });
+    for (index, version) in resolved {
+        suspects[index].package_version = version;
+    }
+}
+
+/// 各线程按序领取来源，单独缓存，学到的映射经 LEARNED 共享；结果按来源下标返回
+fn resolve_in_parallel<T: Send>(
+    targets: &[SourceStats],
+    resolve: impl Fn(&mut PackageResolver, &SourceStats) -> T + Sync,
+) -> Vec<(usize, T)> {
+    let base = PackageResolver::new();
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        let workers = (0..RESOLVE_WORKERS.min(targets.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut resolver = base.worker();
+                    let mut resolved = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(suspect) = targets.get(index) else {
+                            break;
+                        };
+                        resolved.push((index, resolve(&mut resolver, suspect)));
+                    }
+                    resolved
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap_or_default())
+            .collect()
+    })
+}
+
+/// 内核崩溃记录只有出错对象的文件名，按文件名反查
+pub(crate) fn resolve_packages_for_crashes(mut crashes: Vec<Crash>) -> Vec<Crash> {
+    let mut resolver = PackageResolver::new();
+    if !resolver.dpkg_available {
+        return crashes;
+    }
+    for crash in &mut crashes {
+        if let Some(object) = &crash.object {
+            crash.package = resolver.package_by_file_name(object);
+        }
+    }
+    crashes
+}
+
+/// 审计拒绝按可执行文件反查；没有 exe 时，以路径命名的 AppArmor 配置（`/usr/sbin/cupsd`）也是可执行文件
+pub(crate) fn resolve_packages_for_denials(mut denials: Vec<AuditDenial>) -> Vec<AuditDenial> {
+    let mut resolver = PackageResolver::new();
+    if !resolver.dpkg_available {
+        return denials;
+    }
+    for denial in &mut denials {
+        let path = denial
+            .exe
+            .as_deref()
+            .or(denial.profile.as_deref().filter(|p| p.starts_with('/')));
+        if let Some(path) = path {
+            denial.package = resolver.package_by_exe(path);
+        }
+    }
+    save_learned();
+    denials
+}
+
+/// --group-by package：事件的可执行文件或单元所属的包，在整次分析内缓存
+pub(crate) struct EventPackages {
+    resolver: PackageResolver,
+}
+
+impl EventPackages {
+    pub(crate) fn new() -> Self {
+        Self {
+            resolver: PackageResolver::new(),
+        }
+    }
+
+    /// 先按可执行文件、再按单元反查；都查不到（或 dpkg 不可用）时为 None
+    pub(crate) fn package_of(&mut self, event: &JournalEvent) -> Option<String> {
+        if !self.resolver.dpkg_available {
+            return None;
+        }
+        if let Some(exe) = &event.exe
+            && let Some(package) = self.resolver.package_by_exe(exe)
+        {
+            return Some(package);
+        }
+        self.resolver.package_by_unit(event.unit.as_deref()?)
+    }
+}
+
+#[derive(Default)]
+struct PackageResolver {
+    dpkg_available: bool,
+    systemctl_available: bool,
+    path_cache: HashMap<String, Option<String>>,
+    unit_cache: HashMap<String, Option<String>>,
+    /// snap 名称 → 当前修订号
+    snap_revisions: HashMap<String, Option<String>>,
+    version_cache: HashMap<String, Option<PackageVersion>>,
+}
+
+impl PackageResolver {
+    fn new() -> Self {
+        refresh_learned();
+        let (dpkg_available, systemctl_available) = tool_availability();
+        Self {
+            dpkg_available,
+            systemctl_available,
+            path_cache: HashMap::new(),
+            unit_cache: HashMap::new(),
+            snap_revisions: HashMap::new(),
+            version_cache: HashMap::new(),
+        }
+    }
+
+    /// 同样的可用性检测结果、空缓存，供并行反查的线程使用
+    fn worker(&self) -> Self {
+        Self {
+            dpkg_available: self.dpkg_available,
+            systemctl_available: self.systemctl_available,
+            ..Self::default()
+        }
+    }
+
+    fn resolve(&mut self, suspect: &SourceStats) -> Option<String> {
+        if !self.dpkg_available {
+            return None;
+        }
+
+        if suspect.kind == SourceKind::Package {
+            return Some(suspect.source.clone());
+        }
+
+        if suspect.kind == SourceKind::Gpu {
+            return self.package_by_module(&suspect.source);
+        }
+
+        if let Some(exe) = &suspect.sample_exe
+            && let Some(pkg) = self.package_by_exe(exe)
+        {
+            return Some(pkg);
+        }
+
+        if suspect.kind == SourceKind::Executable
+            && let Some(pkg) = self.package_by_exe(&suspect.source)
+        {
+            return Some(pkg);
+        }
+
+        if let Some(unit) = &suspect.sample_unit {
+            return self.package_by_unit(unit);
+        }
+
+        if suspect.kind == SourceKind::Unit {
+            return self.package_by_unit(&suspect.source);
+        }
+
+        None
+    }
+
+    /// 先查进程内已学到的映射，解析成功后记入（`logtool map` 可导出）
+    fn package_by_exe(&mut self, path: &str) -> Option<String> {
+        if let Some((name, revision)) = parse_snap_path(path) {
+            let revision = match revision {
+                Some(revision) => Some(revision.to_string()),
+                None => self.snap_revision(name),
+            };
+            return Some(snap_package(name, revision.as_deref()));
+        }
+        if let Some(pkg) = learned_executable(path) {
+            return Some(pkg);
+        }
+        let resolved = self.package_by_path(path)?;
+        learn_executable(path, &resolved);
+        Some(resolved)
+    }
+
+    fn package_by_path(&mut self, path: &str) -> Option<String> {
+        if path.is_empty() || !path.starts_with('/') {
+            return None;
+        }
+
+        if let Some(cached) = self.path_cache.get(path) {
+            return cached.clone();
+        }
+
+        let output = Command::new("dpkg-query")
+            .arg("-S")
+            .arg(path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .output();
+
+        let resolved = match output {
+            Ok(out) if out.status.success() => {
+                parse_dpkg_search_output(&String::from_utf8_lossy(&out.stdout))
+            }
+            _ => None,
+        };
+
+        self.path_cache.insert(path.to_string(), resolved.clone());
+
+        resolved
+    }
+
+    /// `dpkg-query -S '*/libc.so.6'`：同名文件有多个属主时与 package_by_path 一样取名称最小者
+    fn package_by_file_name(&mut self, name: &str) -> Option<String> {
+        if name.is_empty() || name.contains('/') || name.contains('*') {
+            return None;
+        }
+        let pattern = format!("*/{name}");
+        if let Some(cached) = self.path_cache.get(&pattern) {
+            return cached.clone();
+        }
+        let resolved = Command::new("dpkg-query")
+            .arg("-S")
+            .arg(&pattern)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .and_then(|out| parse_dpkg_search_output(&String::from_utf8_lossy(&out.stdout)));
+        self.path_cache.insert(pattern, resolved.clone());
+        resolved
+    }
+
+    /// 内核模块所属的包：`modinfo -n` 给出当前内核的模块文件（i915 → linux-modules-<版本>）；
+    /// DKMS 编译的模块文件不属于任何包，改查源码目录 `/usr/src/<模块>-<版本>`（nvidia → nvidia-dkms-550）
+    fn package_by_module(&mut self, module: &str) -> Option<String> {
+        let modinfo = |args: &[&str]| {
+            Command::new("modinfo")
+                .args(args)
+                .arg(module)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .output()
+                .ok()
+                .filter(|out| out.status.success())
+                .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        if let Some(path) = modinfo(&["-n"])
+            && let Some(package) = self.package_by_path(&path)
+        {
+            return Some(package);
+        }
+        let version = modinfo(&["-F", "version"])?;
+        self.package_by_path(&format!("/usr/src/{module}-{version}"))
+    }
+
+    fn package_by_unit(&mut self, unit: &str) -> Option<String> {
+        if let Some(name) = snap_unit_name(unit) {
+            let revision = self.snap_revision(name);
+            return Some(snap_package(name, revision.as_deref()));
+        }
+        if !self.systemctl_available {
+            return None;
+        }
+
+        if let Some(cached) = self.unit_cache.get(unit) {
+            return cached.clone();
+        }
+        if let Some(pkg) = learned_unit(unit) {
+            return Some(pkg);
+        }
+
+        let fragment_path = Command::new("systemctl")
+            .arg("show")
+            .arg("--property=FragmentPath")
+            .arg("--value")
+            .arg(unit)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .output();
+
+        let resolved = match fragment_path {
+            Ok(out) if out.status.success() => {
+                let path = String::from_utf8_lossy(&out.stdout).trim().to_string();
+                if path.is_empty() {
+                    None
+                } else {
+                    self.package_by_path(&path)
+                }
+            }
+            _ => None,
+        };
+
+        if let Some(pkg) = &resolved {
+            learn_unit(unit, pkg);
+        }
+        self.unit_cache.insert(unit.to_string(), resolved.clone());
+        resolved
+    }
+
+    /// `dpkg-query -W` 给出安装版本，`apt-cache policy` 给出该版本所在的软件源；apt-cache 不可用时只有版本
+    fn package_version(&mut self, package: &str) -> Option<PackageVersion> {
+        if !self.dpkg_available || package.starts_with("snap:") {
+            return None;
+        }
+        if let Some(cached) = self.version_cache.get(package) {
+            return cached.clone();
+        }
+        let run = |program: &str, args: &[&str]| {
+            Command::new(program)
+                .args(args)
+                .arg(package)
+                .env("LC_ALL", "C")
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .output()
+                .ok()
+                .filter(|out| out.status.success())
+                .map(|out| String::from_utf8_lossy(&out.stdout).into_owned())
+        };
+        let resolved = run("dpkg-query", &["-W", "-f=${Version}\n"])
+            .and_then(|out| {
+                out.lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .map(str::to_string)
+            })
+            .map(|version| PackageVersion {
+                origin: run("apt-cache", &["policy"])
+                    .and_then(|out| parse_apt_policy_origin(&out, &version)),
+                version,
+            });
+        self.version_cache
+            .insert(package.to_string(), resolved.clone());
+        resolved
+    }
+
+    /// `/snap/<名称>/current` 指向当前修订号；读不到时查 `snap list <名称>`
+    fn snap_revision(&mut self, name: &str) -> Option<String> {
+        if let Some(cached) = self.snap_revisions.get(name) {
+            return cached.clone();
+        }
+        let revision = fs::read_link(Path::new(SNAP_MOUNT_DIR).join(name).join("current"))
+            .ok()
+            .map(|target| target.to_string_lossy().into_owned())
+            .filter(|target| !target.is_empty() && !target.contains('/'))
+            .or_else(|| {
+                let output = Command::new("snap")
+                    .arg("list")
+                    .arg(name)
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::null())
+                    .output()
+                    .ok()
+                    .filter(|out| out.status.success())?;
+                parse_snap_list(name, &String::from_utf8_lossy(&output.stdout))
+            });
+        self.snap_revisions
+            .insert(name.to_string(), revision.clone());
+        revision
+    }
+}
+
+/// `/snap/firefox/4848/usr/lib/firefox/firefox` → (firefox, Some(4848))；`/snap/firefox/current/…` 的修订号待查
+fn parse_snap_path(path: &str) -> Option<(&str, Option<&str>)> {
+    let rest = path.strip_prefix(SNAP_MOUNT_DIR)?.strip_prefix('/')?;
+    let mut parts = rest.splitn(3, '/');
+    let name = parts.next().filter(|name| !name.is_empty())?;
+    // /snap/bin/<命令> 是 snap run 的入口，不属于某个 snap
+    if name == "bin" {
+        return None;
+    }
+    let revision = parts.next().filter(|revision| !revision.is_empty())?;
+    parts.next()?;
+    Some((name, (revision != "current").then_some(revision)))
+}
+
+/// `snap.lxd.daemon.service`、`snap.firefox.firefox-1a2b.scope` → 名称
+fn snap_unit_name(unit: &str) -> Option<&str> {
+    let (name, rest) = unit.strip_prefix("snap.")?.split_once('.')?;
+    (!name.is_empty() && rest.contains('.')).then_some(name)
+}
+
+/// `snap list <名称>` 的表格：`Name  Version  Rev  Tracking  Publisher  Notes`
+fn parse_snap_list(name: &str, output: &str) -> Option<String> {
+    output.lines().skip(1).find_map(|line| {
+        let mut columns = line.split_whitespace();
+        (columns.next() == Some(name))
+            .then(|| columns.nth(1))
+            .flatten()
+            .map(str::to_string)
+    })
+}
+
+fn snap_package(name: &str, revision: Option<&str>) -> String {
+    match revision {
+        Some(revision) => format!("snap:{name} ({revision})"),
+        None => format!("snap:{name}"),
+    }
+}
+
+/// `apt-cache policy` 版本表中已安装版本（`***` 行）下列出的软件源：有 `-security` 源时取它，否则取第一个；
+/// PPA 记为 `ppa:<所有者>/<名称>`；只有 /var/lib/dpkg/status 时为 `local`（不来自任何软件源）
+fn parse_apt_policy_origin(output: &str, version: &str) -> Option<String> {
+    let mut lines = output.lines().skip_while(|line| {
+        line.trim_start()
+            .strip_prefix("*** ")
+            .is_none_or(|rest| rest.split_whitespace().next() != Some(version))
+    });
+    lines.next()?;
+    let sources = lines
+        .take_while(|line| line.starts_with("        "))
+        .filter_map(|line| {
+            let mut columns = line.split_whitespace().skip(1);
+            let url = columns.next()?;
+            if url.starts_with('/') {
+                return None;
+            }
+            if let Some(path) = ["ppa.launchpadcontent.net/", "ppa.launchpad.net/"]
+                .iter()
+                .find_map(|host| url.split_once(host).map(|(_, path)| path))
+            {
+                let mut parts = path.split('/');
+                return Some(format!("ppa:{}/{}", parts.next()?, parts.next()?));
+            }
+            let suite = columns.next()?.split('/').next()?;
+            Some(suite.to_string())
+        })
+        .collect::<Vec<_>>();
+    if sources.is_empty() {
+        return Some("local".to_string());
+    }
+    sources
+        .iter()
+        .find(|suite| suite.ends_with("-security"))
+        .or(sources.first())
+        .cloned()
+}
+
+/// `pkg-a, pkg-b:amd64: /path`：共享文件有多个属主时取名称最小者，结果与 dpkg 列出的次序无关；
+/// 跳过 `diversion by … from/to: …` 行
+fn parse_dpkg_search_output(output: &str) -> Option<String> {
+    output
+        .lines()
+        .filter(|line| !line.starts_with("diversion by "))
+        .find_map(|line| line.split_once(": "))
+        .and_then(|(packages, _)| {
+            packages
+                .split(',')
+                .map(|pkg| pkg.trim().split(':').next().unwrap_or_default())
+                .filter(|pkg| !pkg.is_empty())
+                .min()
+        })
+        .map(str::to_string)
+}
+
+/// dpkg-query 与 systemctl 是否可用；每次分析要建好几个 PackageResolver，只在进程内检测一次
+fn tool_availability() -> (bool, bool) {
+    static AVAILABILITY: OnceLock<(bool, bool)> = OnceLock::new();
+    *AVAILABILITY.get_or_init(|| (command_exists("dpkg-query"), command_exists("systemctl")))
+}
+
+fn command_exists(command: &str) -> bool {
+    let status = Command::new(command)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+
+    matches!(status, Ok(exit) if exit.success())
+}
+
+/// 读取 dpkg 的文件清单得到完整映射
+pub fn scan_dpkg_info(dir: &Path) -> Result<PackageMap, String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("读取 dpkg 数据库 {} 失败：{e}", dir.display()))?;
+    let mut lists = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "list"))
+        .collect::<Vec<_>>();
+    lists.sort();
+
+    let mut map = PackageMap::new();
+    for path in lists {
+        let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        // `libc6:amd64.list` → libc6
+        let package = stem.split(':').next().unwrap_or(stem);
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        add_list_entries(&mut map, package, &text);
+    }
+    Ok(map)
+}
+
+fn add_list_entries(map: &mut PackageMap, package: &str, list: &str) {
+    for line in list.lines().map(str::trim) {
+        if let Some(name) = UNIT_DIRS
+            .iter()
+            .find_map(|dir| line.strip_prefix(dir))
+            .filter(|name| is_unit_file_name(name))
+        {
+            map.units.insert(name.to_string(), package.to_string());
+            continue;
+        }
+        let in_bin = EXECUTABLE_DIRS.iter().any(|dir| {
+            line.strip_prefix(dir)
+                .is_some_and(|n| !n.is_empty() && !n.contains('/'))
+        });
+        let in_libexec = LIBEXEC_DIRS.iter().any(|dir| {
+            line.strip_prefix(dir)
+                .is_some_and(|n| !n.is_empty() && !n.ends_with('/'))
+        });
+        if in_bin || (in_libexec && !Path::new(line).is_dir()) {
+            map.executables
+                .insert(line.to_string(), package.to_string());
+        }
+    }
+}
+
+fn is_unit_file_name(name: &str) -> bool {
+    const SUFFIXES: &[&str] = &[
+        ".service", ".socket", ".timer", ".path", ".mount", ".target", ".slice",
+    ];
+    !name.contains('/') && SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn list_entries_map_units_and_executables() {
+        let mut map = PackageMap::new();
+        add_list_entries(
+            &mut map,
+            "openssh-server",
+            "/.\n/usr/sbin\n/usr/sbin/sshd\n/lib/systemd/system/ssh.service\n/lib/systemd/system/ssh.service.d/x.conf\n/usr/share/doc/openssh-server/README\n",
+        );
+        assert_eq!(
+            map.units.get("ssh.service").map(String::as_str),
+            Some("openssh-server")
+        );
+        assert_eq!(map.units.len(), 1);
+        assert_eq!(
+            map.executables.keys().collect::<Vec<_>>(),
+            vec!["/usr/sbin/sshd"]
+        );
+    }
+
+    #[test]
+    fn parses_dpkg_output() {
+        let out = "openssh-server: /lib/systemd/system/ssh.service\n";
+        let pkg = parse_dpkg_search_output(out);
+        assert_eq!(pkg.as_deref(), Some("openssh-server"));
+
+        let shared = "libfoo1:i386, libbar1:amd64: /usr/share/doc/shared\n";
+        assert_eq!(parse_dpkg_search_output(shared).as_deref(), Some("libbar1"));
+        let diverted = "diversion by dash from: /bin/sh\ndiversion by dash to: /bin/sh.distrib\ndash: /bin/sh\n";
+        assert_eq!(parse_dpkg_search_output(diverted).as_deref(), Some("dash"));
+    }
+
+    #[test]
+    fn package_cache_round_trips() {
+        let dir = env::temp_dir().join(format!("logtool-pkgcache-test-{}", std::process::id()));
+        let path = dir.join("nested").join("pkgcache.json");
+        assert_eq!(PackageCache::load_from(&path), None);
+
+        let mut cache = PackageCache {
+            dpkg_status_mtime_ns: Some(1_704_067_200_000_000_000),
+            map: PackageMap::new(),
+        };
+        cache
+            .map
+            .units
+            .insert("ssh.service".to_string(), "openssh-server".to_string());
+        cache
+            .map
+            .executables
+            .insert("/usr/sbin/sshd".to_string(), "openssh-server".to_string());
+        cache.save_to(&path).expect("应写入成功");
+        assert_eq!(PackageCache::load_from(&path), Some(cache.clone()));
+
+        // 另一个进程学到的映射合并进已有缓存
+        let mut other = PackageCache {
+            dpkg_status_mtime_ns: cache.dpkg_status_mtime_ns,
+            map: PackageMap::new(),
+        };
+        other
+            .map
+            .units
+            .insert("cron.service".to_string(), "cron".to_string());
+        other.clone().merge_into(&path).expect("应写入成功");
+        let merged = PackageCache::load_from(&path).expect("应可读取");
+        assert_eq!(merged.map.units.len(), 2);
+        assert_eq!(merged.map.executables, cache.map.executables);
+        assert!(!crate::state::lock_path(&path).exists());
+        // 对应另一个 dpkg 数据库的缓存直接覆盖
+        other.dpkg_status_mtime_ns = Some(1);
+        other.clone().merge_into(&path).expect("应写入成功");
+        assert_eq!(PackageCache::load_from(&path), Some(other));
+
+        fs::write(&path, "{").expect("写入损坏的缓存");
+        assert_eq!(PackageCache::load_from(&path), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn cache_is_discarded_after_the_dpkg_database_changes() {
+        let dir = env::temp_dir().join(format!("logtool-pkgcache-mtime-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("创建临时目录");
+        let status = dir.join("status");
+        let path = dir.join("pkgcache.json");
+        fs::write(&status, "Package: cron\n").expect("写入 status");
+        let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        let mut cache = PackageCache {
+            dpkg_status_mtime_ns: mtime_ns(modified(&status)),
+            map: PackageMap::new(),
+        };
+        cache
+            .map
+            .units
+            .insert("cron.service".to_string(), "cron".to_string());
+        cache.save_to(&path).expect("应写入成功");
+        assert_eq!(
+            PackageCache::load_matching(&path, modified(&status)),
+            Some(cache)
+        );
+
+        // 缓存写入后 dpkg 数据库又被修改（安装、卸载软件包）
+        fs::File::options()
+            .write(true)
+            .open(&status)
+            .and_then(|file| file.set_modified(SystemTime::now() + Duration::from_secs(60)))
+            .expect("修改 status 时间");
+        assert_eq!(PackageCache::load_matching(&path, modified(&status)), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn snap_paths_and_units_are_recognized() {
+        assert_eq!(
+            parse_snap_path("/snap/firefox/4848/usr/lib/firefox/firefox"),
+            Some(("firefox", Some("4848")))
+        );
+        assert_eq!(
+            parse_snap_path("/snap/firefox/current/usr/lib/firefox/firefox"),
+            Some(("firefox", None))
+        );
+        assert_eq!(parse_snap_path("/snap/bin/firefox"), None);
+        assert_eq!(parse_snap_path("/snapshot/bin/app"), None);
+        assert_eq!(parse_snap_path("/usr/bin/firefox"), None);
+
+        assert_eq!(snap_unit_name("snap.lxd.daemon.service"), Some("lxd"));
+        assert_eq!(
+            snap_unit_name("snap.firefox.firefox-1a2b.scope"),
+            Some("firefox")
+        );
+        assert_eq!(snap_unit_name("snapd.service"), None);
+
+        let list = "Name     Version  Rev   Tracking       Publisher  Notes\nfirefox  130.0    4848  latest/stable  mozilla✓   -\n";
+        assert_eq!(parse_snap_list("firefox", list).as_deref(), Some("4848"));
+        assert_eq!(parse_snap_list("lxd", list), None);
+        assert_eq!(snap_package("firefox", Some("4848")), "snap:firefox (4848)");
+    }
+
+    #[test]
+    fn apt_policy_gives_the_origin_of_the_installed_version() {
+        let policy = "openssh-server:
+  Installed: 1:9.6p1-3ubuntu13.5
+  Candidate: 1:9.6p1-3ubuntu13.5
+  Version table:
+ *** 1:9.6p1-3ubuntu13.5 500
+        500 http://archive.ubuntu.com/ubuntu noble-updates/main amd64 Packages
+        500 http://security.ubuntu.com/ubuntu noble-security/main amd64 Packages
+        100 /var/lib/dpkg/status
+     1:9.6p1-3ubuntu13 500
+        500 http://archive.ubuntu.com/ubuntu noble/main amd64 Packages
+";
+        assert_eq!(
+            parse_apt_policy_origin(policy, "1:9.6p1-3ubuntu13.5").as_deref(),
+            Some("noble-security")
+        );
+        // dpkg 与 apt 的已安装版本不一致（刚升级、尚未刷新）时不猜
+        assert_eq!(parse_apt_policy_origin(policy, "1:9.6p1-3ubuntu13"), None);
+
+        let ppa = "foo:
+  Installed: 2.0-1~ppa1
+  Version table:
+ *** 2.0-1~ppa1 500
+        500 https://ppa.launchpadcontent.net/owner/foo/ubuntu noble/main amd64 Packages
+        100 /var/lib/dpkg/status
+";
+        assert_eq!(
+            parse_apt_policy_origin(ppa, "2.0-1~ppa1").as_deref(),
+            Some("ppa:owner/foo")
+        );
+
+        let local = "bar:
+  Installed: 0.1
+  Version table:
+ *** 0.1 100
+        100 /var/lib/dpkg/status
+";
+        assert_eq!(
+            parse_apt_policy_origin(local, "0.1").as_deref(),
+            Some("local")
+        );
+        assert_eq!(parse_apt_policy_origin("", "0.1"), None);
+    }
+
+    #[test]
+    fn merge_prefers_other() {
+        let mut base = PackageMap::new();
+        base.units
+            .insert("a.service".to_string(), "old".to_string());
+        let mut other = PackageMap::new();
+        other
+            .units
+            .insert("a.service".to_string(), "new".to_string());
+        base.merge(other);
+        assert_eq!(base.units["a.service"], "new");
+    }
+}
@@ -0,0 +1,350 @@
+// 按启动周期分列 — `--per-boot` 按事件的 _BOOT_ID 分组，列出每个启动周期的事件数、最高级别与首要来源，
+// 一眼看出“哪一次启动出了问题”
+//
+// 统计在分析循环中逐条进行，来源排行不变。启动周期的偏移（0 为本次、-1 为上一次…）与开机时间来自
+// `journalctl --list-boots --output=json`；查询失败或该周期已不在列表中时只给出启动 ID。
+// 没有 _BOOT_ID 的事件（如内核缓冲区兜底时）单独计数。
+
+use crate::options::display_width;
+use crate::reboots::BootRecord;
+use crate::timeutil::format_local_minute;
+use crate::{SourceKind, priority_label_cn, suspect_id};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// 最多列出的启动周期数，超出时只保留最近的
+pub const MAX_BOOT_ROWS: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BootSuspect {
+    pub id: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BootRow {
+    pub boot_id: String,
+    /// `journalctl --boot` 的相对偏移；不在 --list-boots 中时为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+    /// 该启动周期第一条日志的时间（UTC 微秒），即开机时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_us: Option<u64>,
+    pub count: u64,
+    pub worst_priority: u8,
+    /// 该启动周期内事件最多的来源
+    pub top_suspect: BootSuspect,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BootBreakdown {
+    /// 按开机时间先后
+    pub boots: Vec<BootRow>,
+    /// 超出 MAX_BOOT_ROWS 未列出的较早启动周期数
+    #[serde(default)]
+    pub omitted: usize,
+    /// 没有 _BOOT_ID 的事件数
+    pub without_boot_id: u64,
+}
+
+struct BootCounter {
+    count: u64,
+    worst_priority: u8,
+    first_us: Option<u64>,
+    sources: HashMap<(SourceKind, String), u64>,
+}
+
+/// 分析循环中逐条记录事件所属的启动周期与来源
+#[derive(Default)]
+pub struct BootTracker {
+    boots: HashMap<String, BootCounter>,
+    without_boot_id: u64,
+}
+
+impl BootTracker {
+    pub fn record(
+        &mut self,
+        boot_id: Option<&str>,
+        kind: SourceKind,
+        source: &str,
+        priority: Option<u8>,
+        timestamp_us: Option<u64>,
+    ) {
+        let Some(boot_id) = boot_id else {
+            self.without_boot_id += 1;
+            return;
+        };
+        let counter = self
+            .boots
+            .entry(boot_id.to_string())
+            .or_insert_with(|| BootCounter {
+                count: 0,
+                worst_priority: 7,
+                first_us: None,
+                sources: HashMap::new(),
+            });
+        counter.count += 1;
+        if let Some(p) = priority {
+            counter.worst_priority = counter.worst_priority.min(p);
+        }
+        if let Some(ts) = timestamp_us {
+            counter.first_us = Some(counter.first_us.map_or(ts, |first| first.min(ts)));
+        }
+        *counter
+            .sources
+            .entry((kind, source.to_string()))
+            .or_default() += 1;
+    }
+
+    /// 与 --list-boots 的记录对照，填上偏移与开机时间；records 为空时只给出启动 ID
+    pub fn finish(self, records: &[BootRecord]) -> BootBreakdown {
+        let records = records
+            .iter()
+            .map(|record| (record.boot_id.as_str(), record))
+            .collect::<HashMap<_, _>>();
+        let mut rows = self
+            .boots
+            .into_iter()
+            .map(|(boot_id, counter)| {
+                let record = records.get(boot_id.as_str());
+                let top_suspect = counter
+                    .sources
+                    .iter()
+                    .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+                    .map(|((kind, source), count)| BootSuspect {
+                        id: suspect_id(*kind, source),
+                        count: *count,
+                    })
+                    .unwrap_or(BootSuspect {
+                        id: String::new(),
+                        count: 0,
+                    });
+                let row = BootRow {
+                    offset: record.map(|r| r.offset),
+                    started_us: record.map(|r| r.first_us),
+                    boot_id,
+                    count: counter.count,
+                    worst_priority: counter.worst_priority,
+                    top_suspect,
+                };
+                (row.started_us.or(counter.first_us).unwrap_or(0), row)
+            })
+            .collect::<Vec<_>>();
+        rows.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.boot_id.cmp(&b.1.boot_id)));
+        let omitted = rows.len().saturating_sub(MAX_BOOT_ROWS);
+        BootBreakdown {
+            boots: rows.into_iter().skip(omitted).map(|(_, row)| row).collect(),
+            omitted,
+            without_boot_id: self.without_boot_id,
+        }
+    }
+}
+
+/// 偏移（`-1`）；不在 --list-boots 中时为启动 ID 的前 8 位
+fn boot_label(row: &BootRow) -> String {
+    match row.offset {
+        Some(offset) => offset.to_string(),
+        None => format!("{}…", row.boot_id.get(..8).unwrap_or(&row.boot_id)),
+    }
+}
+
+/// 事件最多的启动周期；只有一个启动周期时不标注
+fn worst_boot(view: &BootBreakdown) -> Option<&str> {
+    if view.boots.len() < 2 {
+        return None;
+    }
+    view.boots
+        .iter()
+        .max_by_key(|row| (row.count, std::cmp::Reverse(row.worst_priority)))
+        .map(|row| row.boot_id.as_str())
+}
+
+fn pad(text: &str, width: usize) -> String {
+    format!(
+        "{text}{}",
+        " ".repeat(width.saturating_sub(display_width(text)))
+    )
+}
+
+pub fn write_boot_section<W: Write>(
+    out: &mut W,
+    view: &BootBreakdown,
+    offset_secs: i64,
+) -> io::Result<()> {
+    let worst = worst_boot(view);
+    writeln!(
+        out,
+        "  {}{}{}{}首要来源",
+        pad("启动", 11),
+        pad("开机时间", 18),
+        pad("事件数", 8),
+        pad("最高级别", 10)
+    )?;
+    for row in &view.boots {
+        let started = row
+            .started_us
+            .map(|us| format_local_minute(us, offset_secs))
+            .unwrap_or_else(|| "-".to_string());
+        let level = format!(
+            "{}({})",
+            row.worst_priority,
+            priority_label_cn(row.worst_priority)
+        );
+        writeln!(
+            out,
+            "  {}{}{}{}{}（{}）{}",
+            pad(&boot_label(row), 11),
+            pad(&started, 18),
+            pad(&row.count.to_string(), 8),
+            pad(&level, 10),
+            row.top_suspect.id,
+            row.top_suspect.count,
+            if worst == Some(row.boot_id.as_str()) {
+                "  ◀ 最多"
+            } else {
+                ""
+            }
+        )?;
+    }
+    if view.omitted > 0 {
+        writeln!(out, "  更早的 {} 个启动周期未列出", view.omitted)?;
+    }
+    if view.without_boot_id > 0 {
+        writeln!(out, "  另有 {} 条事件没有启动 ID", view.without_boot_id)?;
+    }
+    Ok(())
+}
+
+/// 逐句版本（--linear）
+pub fn boot_sentences(view: &BootBreakdown, offset_secs: i64) -> Vec<String> {
+    let worst = worst_boot(view);
+    let mut sentences = view
+        .boots
+        .iter()
+        .map(|row| {
+            let started = row
+                .started_us
+                .map(|us| format!("（{} 开机）", format_local_minute(us, offset_secs)))
+                .unwrap_or_default();
+            format!(
+                "启动周期 {}{started}，共 {} 条事件，最高严重级别为{}（{}），首要来源 {}（{} 条）{}。",
+                boot_label(row),
+                row.count,
+                priority_label_cn(row.worst_priority),
+                row.worst_priority,
+                row.top_suspect.id,
+                row.top_suspect.count,
+                if worst == Some(row.boot_id.as_str()) {
+                    "，事件最多"
+                } else {
+                    ""
+                }
+            )
+        })
+        .collect::<Vec<_>>();
+    if view.omitted > 0 {
+        sentences.push(format!("更早的 {} 个启动周期未列出。", view.omitted));
+    }
+    if view.without_boot_id > 0 {
+        sentences.push(format!("另有 {} 条事件没有启动 ID。", view.without_boot_id));
+    }
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "0f1e2d3c4b5a69788796a5b4c3d2e1f0";
+    const NEW: &str = "a1b2c3d4e5f60718293a4b5c6d7e8f90";
+
+    #[test]
+    fn splits_events_by_boot() {
+        let mut tracker = BootTracker::default();
+        for _ in 0..3 {
+            tracker.record(
+                Some(OLD),
+                SourceKind::Kernel,
+                "kernel",
+                Some(2),
+                Some(1_704_070_000_000_000),
+            );
+        }
+        tracker.record(
+            Some(OLD),
+            SourceKind::Unit,
+            "nginx.service",
+            Some(3),
+            Some(1_704_070_100_000_000),
+        );
+        tracker.record(
+            Some(NEW),
+            SourceKind::Unit,
+            "nginx.service",
+            Some(3),
+            Some(1_704_080_000_000_000),
+        );
+        tracker.record(None, SourceKind::Kernel, "kernel", Some(3), None);
+
+        // 2024-01-01 00:30 UTC 与 03:00 UTC 开机
+        let records = [
+            BootRecord {
+                offset: -1,
+                boot_id: OLD.to_string(),
+                first_us: 1_704_069_000_000_000,
+                last_us: 1_704_075_000_000_000,
+            },
+            BootRecord {
+                offset: 0,
+                boot_id: NEW.to_string(),
+                first_us: 1_704_078_000_000_000,
+                last_us: 1_704_080_000_000_000,
+            },
+        ];
+        let view = tracker.finish(&records);
+        assert_eq!(view.without_boot_id, 1);
+        assert_eq!(
+            view.boots
+                .iter()
+                .map(|row| (row.offset, row.count, row.worst_priority))
+                .collect::<Vec<_>>(),
+            [(Some(-1), 4, 2), (Some(0), 1, 3)]
+        );
+
+        let mut out = Vec::new();
+        write_boot_section(&mut out, &view, 0).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert_eq!(
+            text,
+            "  启动       开机时间          事件数  最高级别  首要来源\n  -1         2024-01-01 00:30  4       2(严重)   kernel:kernel（3）  ◀ 最多\n  0          2024-01-01 03:00  1       3(错误)   unit:nginx.service（1）\n  另有 1 条事件没有启动 ID\n"
+        );
+
+        // --list-boots 不可用时只给出启动 ID，按事件时间排序
+        let view = {
+            let mut tracker = BootTracker::default();
+            tracker.record(
+                Some(NEW),
+                SourceKind::Unit,
+                "a.service",
+                Some(3),
+                Some(2_000_000),
+            );
+            tracker.record(
+                Some(OLD),
+                SourceKind::Unit,
+                "b.service",
+                Some(4),
+                Some(1_000_000),
+            );
+            tracker.finish(&[])
+        };
+        assert_eq!(
+            boot_sentences(&view, 0),
+            [
+                "启动周期 0f1e2d3c…，共 1 条事件，最高严重级别为警告（4），首要来源 unit:b.service（1 条）。",
+                "启动周期 a1b2c3d4…，共 1 条事件，最高严重级别为错误（3），首要来源 unit:a.service（1 条），事件最多。",
+            ]
+        );
+    }
+}
@@ -0,0 +1,334 @@
+// 重启标注 — 分析窗口跨越重启时，在报告中注明重启次数和没有日志的时段
+//
+// 启动记录来自 `journalctl --list-boots --output=json`（每个启动周期第一条与最后一条日志的时间）；
+// 在窗口内开始的每个启动周期算一次重启，上一周期最后一条日志到本周期第一条日志之间没有日志。
+// 上一周期末尾没有正常关机记录时另作标注，这类重启更可能是死机、断电或内核崩溃。
+
+use crate::timeutil::format_local_minute;
+use crate::{
+    BootFilter, Config, SourceStats, add_journal_source_args, add_window_args,
+    looks_like_clean_shutdown, parse_json_event,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+/// 最多为这么多次重启回查上一周期的关机记录，每次一个 journalctl 调用
+pub const MAX_SHUTDOWN_CHECKS: usize = 5;
+/// 回查上一周期末尾的日志条数（与 looks_like_clean_shutdown 检查的范围一致）
+const SHUTDOWN_TAIL_LINES: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootRecord {
+    pub offset: i64,
+    pub boot_id: String,
+    /// 第一条与最后一条日志时间（UTC 微秒）
+    pub first_us: u64,
+    pub last_us: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reboot {
+    /// 重启后的启动周期
+    pub boot_id: String,
+    /// 上一启动周期最后一条日志（UTC 微秒）
+    pub down_at_us: u64,
+    /// 本启动周期第一条日志
+    pub up_at_us: u64,
+    /// 上一周期末尾是否有正常关机记录；超出 MAX_SHUTDOWN_CHECKS 或读取失败时为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clean_shutdown: Option<bool>,
+}
+
+/// 窗口内有重启时，来源的事件落在几个启动周期里
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BootSpan {
+    /// 首末事件之间的启动周期数（含两端所在周期）
+    pub boots: u64,
+    /// 首次事件之前最近一次重启的开机时间；来源在窗口内第一次重启之前就已出现时为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after_reboot_us: Option<u64>,
+}
+
+impl BootSpan {
+    /// `跨越 3 个启动周期，重启后仍在出现` / `只在 2024-01-01 08:00 的重启之后出现`
+    pub fn describe(&self, offset_secs: i64) -> String {
+        match (self.boots, self.after_reboot_us) {
+            (1, Some(at)) => format!(
+                "只在 {} 的重启之后出现",
+                format_local_minute(at, offset_secs)
+            ),
+            (1, None) => "重启之后未再出现".to_string(),
+            (boots, Some(at)) => format!(
+                "首次出现在 {} 的重启之后，跨越 {boots} 个启动周期",
+                format_local_minute(at, offset_secs)
+            ),
+            (boots, None) => format!("跨越 {boots} 个启动周期，重启后仍在出现"),
+        }
+    }
+}
+
+/// 按首末事件时间为前 top 个来源填写 boot_span；没有重启或缺少时间戳时不填
+pub fn annotate_boot_spans(suspects: &mut [SourceStats], reboots: &[Reboot], top: usize) {
+    if reboots.is_empty() {
+        return;
+    }
+    for suspect in suspects.iter_mut().take(top) {
+        let (Some(first), Some(last)) = (suspect.first_us, suspect.last_us) else {
+            continue;
+        };
+        let crossed = reboots
+            .iter()
+            .filter(|reboot| reboot.up_at_us > first && reboot.up_at_us <= last)
+            .count() as u64;
+        let after_reboot_us = reboots
+            .iter()
+            .map(|reboot| reboot.up_at_us)
+            .filter(|&up| up <= first)
+            .max();
+        suspect.boot_span = Some(BootSpan {
+            boots: crossed + 1,
+            after_reboot_us,
+        });
+    }
+}
+
+/// `[{"index":-1,"boot_id":"…","first_entry":…,"last_entry":…}, …]`；字段缺失的条目跳过
+pub fn parse_boot_records(json: &str) -> Vec<BootRecord> {
+    let Ok(Value::Array(items)) = serde_json::from_str::<Value>(json) else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            Some(BootRecord {
+                offset: item.get("index")?.as_i64()?,
+                boot_id: item.get("boot_id")?.as_str()?.to_string(),
+                first_us: item.get("first_entry")?.as_u64()?,
+                last_us: item.get("last_entry")?.as_u64()?,
+            })
+        })
+        .collect()
+}
+
+/// 在 (start_us, end_us] 内开始的启动周期，按时间先后
+pub fn reboots_in_window(boots: &[BootRecord], start_us: u64, end_us: u64) -> Vec<Reboot> {
+    let mut boots = boots.iter().collect::<Vec<_>>();
+    boots.sort_by_key(|boot| boot.first_us);
+    boots
+        .windows(2)
+        .filter(|pair| pair[1].first_us > start_us && pair[1].first_us <= end_us)
+        .map(|pair| Reboot {
+            boot_id: pair[1].boot_id.clone(),
+            down_at_us: pair[0].last_us,
+            up_at_us: pair[1].first_us,
+            clean_shutdown: None,
+        })
+        .collect()
+}
+
+/// 按当前日志来源列出全部启动周期（`journalctl --list-boots --output=json`）；查询失败时为 None
+pub fn list_boot_records(config: &Config) -> Option<Vec<BootRecord>> {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager")
+        .arg("--list-boots")
+        .arg("--output=json");
+    add_journal_source_args(&mut cmd, config);
+    let output = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())?;
+    Some(parse_boot_records(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// 指定了 --boot 时窗口只在一个启动周期内，不做检查；任何一步失败都按“没有重启”处理
+pub fn detect_reboots(config: &Config) -> Vec<Reboot> {
+    if config.boot != BootFilter::Disabled {
+        return Vec::new();
+    }
+    let (Some(start_us), Some(end_us)) = (window_edge(config, false), window_edge(config, true))
+    else {
+        return Vec::new();
+    };
+
+    let Some(boots) = list_boot_records(config) else {
+        return Vec::new();
+    };
+
+    let mut reboots = reboots_in_window(&boots, start_us, end_us);
+    for reboot in reboots.iter_mut().take(MAX_SHUTDOWN_CHECKS) {
+        let previous = boots
+            .iter()
+            .filter(|boot| boot.first_us < reboot.up_at_us)
+            .max_by_key(|boot| boot.first_us);
+        reboot.clean_shutdown = previous.and_then(|boot| shut_down_cleanly(config, &boot.boot_id));
+    }
+    reboots
+}
+
+/// 窗口内（不带任何过滤）最早或最晚一条日志的时间：只读一行后结束 journalctl
+fn window_edge(config: &Config, newest: bool) -> Option<u64> {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager").arg("--quiet");
+    add_window_args(&mut cmd, config);
+    if newest {
+        cmd.arg("--reverse");
+    }
+    let mut child = cmd
+        .arg("--output=json")
+        .arg("--output-fields=MESSAGE")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut line = String::new();
+    let read = child
+        .stdout
+        .take()
+        .map(|stdout| BufReader::new(stdout).read_line(&mut line));
+    let _ = child.kill();
+    let _ = child.wait();
+    read?.ok()?;
+    parse_json_event(&line).ok()?.timestamp_us
+}
+
+fn shut_down_cleanly(config: &Config, boot_id: &str) -> Option<bool> {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager")
+        .arg("--quiet")
+        .arg(format!("--boot={boot_id}"))
+        .arg(format!("--lines={SHUTDOWN_TAIL_LINES}"))
+        .arg("--output=json")
+        .arg("--output-fields=MESSAGE,SYSLOG_IDENTIFIER");
+    add_journal_source_args(&mut cmd, config);
+    let output = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())?;
+    let events = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| parse_json_event(line).ok())
+        .collect::<Vec<_>>();
+    (!events.is_empty()).then(|| looks_like_clean_shutdown(&events))
+}
+
+/// `00:14–00:21`；跨日时两端都带日期
+fn gap_label(reboot: &Reboot, offset_secs: i64) -> String {
+    let down = format_local_minute(reboot.down_at_us, offset_secs);
+    let up = format_local_minute(reboot.up_at_us, offset_secs);
+    if down[..10] == up[..10] {
+        format!("{}–{}", &down[11..], &up[11..])
+    } else {
+        format!("{down}–{up}")
+    }
+}
+
+/// `窗口内重启 2 次，缺失 00:14–00:21、03:02–03:05 的日志`，以及未正常关机的次数
+pub fn reboot_summary(reboots: &[Reboot], offset_secs: i64) -> String {
+    let gaps = reboots
+        .iter()
+        .map(|reboot| gap_label(reboot, offset_secs))
+        .collect::<Vec<_>>()
+        .join("、");
+    let mut summary = format!("窗口内重启 {} 次，缺失 {gaps} 的日志", reboots.len());
+    let unclean = reboots
+        .iter()
+        .filter(|reboot| reboot.clean_shutdown == Some(false))
+        .count();
+    if unclean > 0 {
+        summary.push_str(&format!(
+            "；其中 {unclean} 次之前没有正常关机记录（可能是死机、断电或内核崩溃，可运行 logtool last-words --boot -1 查看）"
+        ));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_suspect;
+
+    const MINUTE_US: u64 = 60_000_000;
+
+    #[test]
+    fn reboots_inside_window_report_gaps() {
+        let base = 1_704_067_200_000_000; // 2024-01-01 00:00 UTC
+        let json = format!(
+            r#"[{{"index":-2,"boot_id":"aa","first_entry":{},"last_entry":{}}},
+                {{"index":-1,"boot_id":"bb","first_entry":{},"last_entry":{}}},
+                {{"index":0,"boot_id":"cc","first_entry":{},"last_entry":{}}},
+                {{"index":1,"boot_id":"broken"}}]"#,
+            base - 600 * MINUTE_US,
+            base + 14 * MINUTE_US,
+            base + 21 * MINUTE_US,
+            base + 182 * MINUTE_US,
+            base + 185 * MINUTE_US,
+            base + 300 * MINUTE_US,
+        );
+        let boots = parse_boot_records(&json);
+        assert_eq!(boots.len(), 3);
+
+        let mut reboots = reboots_in_window(&boots, base, base + 240 * MINUTE_US);
+        assert_eq!(
+            reboots
+                .iter()
+                .map(|r| r.boot_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["bb", "cc"]
+        );
+        reboots[1].clean_shutdown = Some(false);
+        let summary = reboot_summary(&reboots, 0);
+        assert!(summary.starts_with("窗口内重启 2 次，缺失 00:14–00:21、03:02–03:05 的日志"));
+        assert!(summary.contains("其中 1 次之前没有正常关机记录"));
+
+        // 窗口完全落在一个启动周期内
+        assert!(reboots_in_window(&boots, base + 30 * MINUTE_US, base + 60 * MINUTE_US).is_empty());
+        assert!(parse_boot_records("No journal files were found.").is_empty());
+    }
+
+    #[test]
+    fn suspects_are_placed_relative_to_reboots() {
+        let base = 1_704_067_200_000_000; // 2024-01-01 00:00 UTC
+        let reboot = |up_minute: u64| Reboot {
+            boot_id: format!("b{up_minute}"),
+            down_at_us: base + (up_minute - 5) * MINUTE_US,
+            up_at_us: base + up_minute * MINUTE_US,
+            clean_shutdown: None,
+        };
+        let reboots = [reboot(60), reboot(180)];
+        let suspect = |first: u64, last: u64| SourceStats {
+            first_us: Some(base + first * MINUTE_US),
+            last_us: Some(base + last * MINUTE_US),
+            ..sample_suspect(crate::SourceKind::Unit, "app.service", 2, 3)
+        };
+        let mut suspects = vec![
+            suspect(10, 200),
+            suspect(70, 100),
+            suspect(90, 190),
+            suspect(10, 20),
+        ];
+        annotate_boot_spans(&mut suspects, &reboots, 4);
+        let spans = suspects
+            .iter()
+            .map(|s| s.boot_span.as_ref().expect("应有标注").describe(0))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [
+                "跨越 3 个启动周期，重启后仍在出现",
+                "只在 2024-01-01 01:00 的重启之后出现",
+                "首次出现在 2024-01-01 01:00 的重启之后，跨越 2 个启动周期",
+                "重启之后未再出现",
+            ]
+        );
+
+        let mut quiet = vec![suspect(10, 20)];
+        annotate_boot_spans(&mut quiet, &[], 1);
+        assert!(quiet[0].boot_span.is_none());
+    }
+}
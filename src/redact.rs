@@ -0,0 +1,304 @@
+// 脱敏 — 在输出前遮盖可识别信息（--redact）
+//
+// 遮盖对象：IPv4/IPv6 地址、MAC 地址、本机主机名、用户名。
+// 用户名来源：/etc/passwd 中 uid 1000–65533 的普通用户、`user=`/`rhost=` 等键值、
+// `user <名称>`、sshd 的 `for <名称> from`、以及 `/home/<名称>/` 路径。
+// 按“词”扫描（字母数字与 `.:-_`），不依赖正则。
+
+use std::fs;
+
+const IP_MASK: &str = "<ip>";
+const MAC_MASK: &str = "<mac>";
+const HOST_MASK: &str = "<host>";
+pub const USER_MASK: &str = "<user>";
+
+/// 其后的词视为用户名的键（`user=alice`）
+const USER_KEYS: &[&str] = &["user", "ruser", "username", "user_name", "acct", "logname"];
+/// 其后的词视为主机名的键（`rhost=example.org`）
+const HOST_KEYS: &[&str] = &["host", "rhost", "hostname", "_hostname"];
+
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    hostnames: Vec<String>,
+    usernames: Vec<String>,
+}
+
+/// 消息中的一个词：`start..end` 为字节范围
+struct Word<'a> {
+    text: &'a str,
+    start: usize,
+    end: usize,
+}
+
+impl Redactor {
+    /// 读取本机主机名与普通用户列表
+    pub fn from_system() -> Self {
+        let mut hostnames = Vec::new();
+        for path in ["/proc/sys/kernel/hostname", "/etc/hostname"] {
+            if let Ok(text) = fs::read_to_string(path) {
+                let name = text.trim();
+                if !name.is_empty() {
+                    hostnames.push(name.to_string());
+                }
+            }
+        }
+        let usernames = fs::read_to_string("/etc/passwd")
+            .map(|text| parse_passwd_users(&text))
+            .unwrap_or_default();
+        Self::with_identity(hostnames, usernames)
+    }
+
+    pub fn with_identity(hostnames: Vec<String>, usernames: Vec<String>) -> Self {
+        let mut hostnames = hostnames
+            .into_iter()
+            .filter(|h| !h.is_empty() && h != "localhost")
+            .map(|h| h.to_ascii_lowercase())
+            .collect::<Vec<_>>();
+        // FQDN 的短名同样需要遮盖
+        let short_names = hostnames
+            .iter()
+            .filter_map(|h| h.split_once('.').map(|(short, _)| short.to_string()))
+            .collect::<Vec<_>>();
+        hostnames.extend(short_names);
+        hostnames.sort();
+        hostnames.dedup();
+
+        let mut usernames = usernames
+            .into_iter()
+            .filter(|u| !u.is_empty())
+            .collect::<Vec<_>>();
+        usernames.sort();
+        usernames.dedup();
+        Self {
+            hostnames,
+            usernames,
+        }
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let words = split_words(text);
+        let mut out = String::with_capacity(text.len());
+        let mut cursor = 0usize;
+
+        for (index, word) in words.iter().enumerate() {
+            let Some(replacement) = self.classify(text, &words, index) else {
+                continue;
+            };
+            out.push_str(&text[cursor..word.start]);
+            out.push_str(&replacement);
+            cursor = word.end;
+        }
+        out.push_str(&text[cursor..]);
+        out
+    }
+
+    fn classify(&self, text: &str, words: &[Word<'_>], index: usize) -> Option<String> {
+        let word = &words[index];
+        if is_mac_address(word.text) {
+            return Some(MAC_MASK.to_string());
+        }
+        if is_ipv4(word.text) {
+            return Some(IP_MASK.to_string());
+        }
+        // `1.2.3.4:22` 保留端口
+        if let Some((addr, port)) = word.text.rsplit_once(':')
+            && is_ipv4(addr)
+            && !port.is_empty()
+            && port.chars().all(|c| c.is_ascii_digit())
+        {
+            return Some(format!("{IP_MASK}:{port}"));
+        }
+        if is_ipv6(word.text) {
+            return Some(IP_MASK.to_string());
+        }
+
+        let lower = word.text.to_ascii_lowercase();
+        if self.hostnames.contains(&lower) {
+            return Some(HOST_MASK.to_string());
+        }
+        if self.usernames.iter().any(|u| u == word.text) {
+            return Some(USER_MASK.to_string());
+        }
+
+        let prev = index.checked_sub(1).map(|i| &words[i]);
+        let separator = prev.map(|p| &text[p.end..word.start]).unwrap_or("");
+        if let Some(prev) = prev {
+            let key = prev.text.to_ascii_lowercase();
+            let keyed = matches!(separator, "=" | "=\"" | "='");
+            if keyed && USER_KEYS.contains(&key.as_str()) {
+                return Some(USER_MASK.to_string());
+            }
+            // `invalid user admin`；大写的 `User Manager` 不算
+            if separator == " " && prev.text == "user" {
+                return Some(USER_MASK.to_string());
+            }
+            if separator.starts_with('=') && HOST_KEYS.contains(&key.as_str()) {
+                return Some(HOST_MASK.to_string());
+            }
+            // `/home/alice/...`
+            if separator == "/" && key == "home" && preceded_by_slash(text, prev) {
+                return Some(USER_MASK.to_string());
+            }
+            // sshd：`Accepted publickey for alice from 1.2.3.4`
+            if key == "for"
+                && separator == " "
+                && words
+                    .get(index + 1)
+                    .is_some_and(|next| next.text == "from" && &text[word.end..next.start] == " ")
+            {
+                return Some(USER_MASK.to_string());
+            }
+        }
+        None
+    }
+}
+
+fn preceded_by_slash(text: &str, word: &Word<'_>) -> bool {
+    text[..word.start].ends_with('/')
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '.' | ':' | '-' | '_')
+}
+
+/// 拆分出词，并去掉词尾的标点（如句末的 `.`、`:`）
+fn split_words(text: &str) -> Vec<Word<'_>> {
+    let mut words = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for (pos, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (start, pos < text.len() && is_word_char(c)) {
+            (None, true) => start = Some(pos),
+            (Some(s), false) => {
+                let trimmed = text[s..pos].trim_end_matches(['.', ':', '-', '_']);
+                // IPv6 的 `::` 结尾需要保留
+                let trimmed = if text[s..pos].ends_with("::") && is_ipv6(&text[s..pos]) {
+                    &text[s..pos]
+                } else {
+                    trimmed
+                };
+                if !trimmed.is_empty() {
+                    words.push(Word {
+                        text: trimmed,
+                        start: s,
+                        end: s + trimmed.len(),
+                    });
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+fn is_ipv4(text: &str) -> bool {
+    let parts = text.split('.').collect::<Vec<_>>();
+    parts.len() == 4
+        && parts
+            .iter()
+            .all(|p| !p.is_empty() && p.len() <= 3 && p.chars().all(|c| c.is_ascii_digit()))
+        && parts
+            .iter()
+            .all(|p| p.parse::<u16>().is_ok_and(|v| v <= 255))
+}
+
+fn is_mac_address(text: &str) -> bool {
+    for sep in [':', '-'] {
+        let parts = text.split(sep).collect::<Vec<_>>();
+        if parts.len() == 6
+            && parts
+                .iter()
+                .all(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// 保守判断：只含十六进制与冒号，且含 `::` 或恰好 8 组；
+/// 纯数字的 `12:34:56` 时间不会被误判
+fn is_ipv6(text: &str) -> bool {
+    if !text.contains(':') || !text.chars().all(|c| c.is_ascii_hexdigit() || c == ':') {
+        return false;
+    }
+    let groups = text.split(':').collect::<Vec<_>>();
+    if groups.iter().any(|g| g.len() > 4) || text.contains(":::") {
+        return false;
+    }
+    let compressed = text.matches("::").count();
+    if compressed > 1 {
+        return false;
+    }
+    if compressed == 1 {
+        // 至少要有一个非空组，且不是单独的 `::`
+        return groups.iter().filter(|g| !g.is_empty()).count() >= 1;
+    }
+    groups.len() == 8
+}
+
+/// 普通登录用户：uid 1000–65533
+pub fn parse_passwd_users(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse::<u32>().ok()?;
+            ((1000..65534).contains(&uid) && !name.is_empty()).then(|| name.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor() -> Redactor {
+        Redactor::with_identity(
+            vec!["web01.example.com".to_string()],
+            vec!["alice".to_string()],
+        )
+    }
+
+    #[test]
+    fn masks_addresses() {
+        let r = redactor();
+        assert_eq!(
+            r.redact("Failed from 192.168.1.20 port 22, peer 10.0.0.1:443."),
+            "Failed from <ip> port 22, peer <ip>:443."
+        );
+        assert_eq!(
+            r.redact("link fe80::1ff:fe23:4567:890a up, mac 00:1A:2b:3c:4D:5e"),
+            "link <ip> up, mac <mac>"
+        );
+        // 时间与版本号不应被误判
+        assert_eq!(
+            r.redact("at 12:34:56 version 1.2.3"),
+            "at 12:34:56 version 1.2.3"
+        );
+    }
+
+    #[test]
+    fn masks_hostnames_and_users() {
+        let r = redactor();
+        assert_eq!(
+            r.redact("web01 sshd: Accepted publickey for bob from 1.2.3.4"),
+            "<host> sshd: Accepted publickey for <user> from <ip>"
+        );
+        assert_eq!(
+            r.redact("pam_unix(sudo:auth): user=carol rhost=gw.lan open /home/alice/.ssh by alice"),
+            "pam_unix(sudo:auth): user=<user> rhost=<host> open /home/<user>/.ssh by <user>"
+        );
+        assert_eq!(
+            r.redact("Started User Manager, waited for 5 seconds; invalid user admin"),
+            "Started User Manager, waited for 5 seconds; invalid user <user>"
+        );
+    }
+
+    #[test]
+    fn parses_regular_users_from_passwd() {
+        let text = "root:x:0:0:root:/root:/bin/bash\nalice:x:1000:1000::/home/alice:/bin/bash\nnobody:x:65534:65534::/:/usr/sbin/nologin\n";
+        assert_eq!(parse_passwd_users(text), vec!["alice".to_string()]);
+    }
+}
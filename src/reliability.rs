@@ -0,0 +1,187 @@
+// 长期可靠性 — 可疑来源近 RELIABILITY_DAYS 天的无错误天数占比与平均故障间隔（--reliability）
+//
+// 数据直接取自 journal：每个来源一次 journalctl 查询（同一优先级条件，仅输出时间戳），
+// 按本地日期统计有错误的天数；相隔超过 FAILURE_GAP_SECS 的两批事件算两次故障。
+// journal 保留不足 RELIABILITY_DAYS 天时，统计区间从最早一条日志开始。
+
+use crate::timeutil::{format_duration_cn, local_utc_offset_secs};
+use crate::{Config, SourceStats, add_journal_source_args, suspect_journal_match};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+pub const RELIABILITY_DAYS: u64 = 30;
+/// 同一来源的事件间隔超过该秒数时视为新的一次故障
+pub const FAILURE_GAP_SECS: u64 = 30 * 60;
+const DAY_SECS: u64 = 86_400;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reliability {
+    /// 统计区间覆盖的本地日期数
+    pub days: u64,
+    pub error_free_days: u64,
+    pub failures: u64,
+    /// 统计区间长度 / 故障次数；没有故障时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtbf_secs: Option<u64>,
+}
+
+impl Reliability {
+    /// timestamps 为 [start, end] 内的事件时间（Unix 秒，任意顺序）
+    pub fn from_timestamps(timestamps: &[u64], start: u64, end: u64, offset_secs: i64) -> Self {
+        let local_day = |secs: u64| (secs as i64 + offset_secs).div_euclid(DAY_SECS as i64);
+        let days = (local_day(end) - local_day(start) + 1).max(1) as u64;
+        let mut sorted = timestamps.to_vec();
+        sorted.sort_unstable();
+        let error_days = sorted
+            .iter()
+            .map(|ts| local_day(*ts))
+            .collect::<BTreeSet<_>>()
+            .len() as u64;
+        let failures = sorted
+            .iter()
+            .enumerate()
+            .filter(|(i, ts)| *i == 0 || **ts - sorted[i - 1] > FAILURE_GAP_SECS)
+            .count() as u64;
+        Self {
+            days,
+            error_free_days: days.saturating_sub(error_days),
+            failures,
+            mtbf_secs: (failures > 0).then(|| end.saturating_sub(start) / failures),
+        }
+    }
+
+    /// `近 30 天 24/30 天无错误（80%），3 次故障，约每 10 天一次`
+    pub fn summary(&self) -> String {
+        let percent = self.error_free_days * 100 / self.days.max(1);
+        let mut text = format!(
+            "近 {} 天 {}/{} 天无错误（{percent}%）",
+            self.days, self.error_free_days, self.days
+        );
+        match self.mtbf_secs {
+            Some(mtbf) => text.push_str(&format!(
+                "，{} 次故障，约每 {}一次",
+                self.failures,
+                format_duration_cn(mtbf)
+            )),
+            None => text.push_str("，没有故障"),
+        }
+        text
+    }
+}
+
+/// 为前 config.top 个来源补充可靠性；查询失败的来源不标注
+pub fn annotate_reliability(suspects: &mut [SourceStats], config: &Config, now: u64) {
+    let since = now.saturating_sub(RELIABILITY_DAYS * DAY_SECS);
+    let Some(start) = oldest_entry_secs(config, since) else {
+        return;
+    };
+    let offset_secs = local_utc_offset_secs();
+    for suspect in suspects.iter_mut().take(config.top) {
+        let Some(field_match) = suspect_journal_match(suspect.kind, &suspect.source) else {
+            continue;
+        };
+        let mut cmd = event_times_command(config, start, &field_match);
+        let Some(output) = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+        else {
+            continue;
+        };
+        let timestamps = parse_unix_timestamps(&String::from_utf8_lossy(&output.stdout));
+        suspect.reliability = Some(Reliability::from_timestamps(
+            &timestamps,
+            start,
+            now,
+            offset_secs,
+        ));
+    }
+}
+
+fn event_times_command(config: &Config, start: u64, field_match: &str) -> Command {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager")
+        .arg("--quiet")
+        .arg(format!("--since=@{start}"))
+        .arg(format!("--priority={}", config.priority))
+        .arg("--output=short-unix");
+    add_journal_source_args(&mut cmd, config);
+    cmd.arg(field_match);
+    cmd
+}
+
+/// 统计区间起点：since 之后（不带过滤）的第一条日志；只读一行后结束 journalctl
+fn oldest_entry_secs(config: &Config, since: u64) -> Option<u64> {
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager")
+        .arg("--quiet")
+        .arg(format!("--since=@{since}"))
+        .arg("--output=short-unix");
+    add_journal_source_args(&mut cmd, config);
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut line = String::new();
+    let read = child
+        .stdout
+        .take()
+        .map(|stdout| BufReader::new(stdout).read_line(&mut line));
+    let _ = child.kill();
+    let _ = child.wait();
+    read?.ok()?;
+    parse_unix_timestamps(&line).first().copied()
+}
+
+/// `--output=short-unix` 每行以 `1704067200.123456` 开头；多行消息的续行跳过
+pub fn parse_unix_timestamps(output: &str) -> Vec<u64> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter_map(|token| token.split('.').next()?.parse::<u64>().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 3600;
+
+    #[test]
+    fn reliability_counts_error_days_and_failure_episodes() {
+        let start = 1_704_067_200; // 2024-01-01 00:00 UTC
+        let end = start + 10 * DAY_SECS - 1;
+        let output = format!(
+            "{}.000100 host app[1]: a\n    continued line\n{}.5 host app[1]: b\n{}.0 host app[1]: c\n{}.0 host app[1]: d\n",
+            start + HOUR,
+            start + HOUR + 600,
+            start + 5 * HOUR,
+            start + 3 * DAY_SECS,
+        );
+        let timestamps = parse_unix_timestamps(&output);
+        assert_eq!(timestamps.len(), 4);
+
+        let reliability = Reliability::from_timestamps(&timestamps, start, end, 0);
+        assert_eq!(reliability.days, 10);
+        assert_eq!(reliability.error_free_days, 8);
+        assert_eq!(reliability.failures, 3);
+        assert_eq!(
+            reliability.summary(),
+            "近 10 天 8/10 天无错误（80%），3 次故障，约每 3 天 7 小时一次"
+        );
+
+        // 时区偏移把 23:00 UTC 的事件算到次日
+        let late = Reliability::from_timestamps(&[start + 23 * HOUR], start, end, 2 * HOUR as i64);
+        assert_eq!((late.days, late.error_free_days), (11, 10));
+
+        let clean = Reliability::from_timestamps(&[], start, end, 0);
+        assert_eq!((clean.error_free_days, clean.mtbf_secs), (10, None));
+        assert!(clean.summary().ends_with("没有故障"));
+    }
+}
@@ -0,0 +1,233 @@
+// 重启关联 — 把前列来源与同一窗口内 systemd 对其单元的启动、停止、自动重启与失败记录对照，
+// 标出“报错之后服务真的挂了”的来源
+//
+// 一次查询取回所有相关单元的 systemd（_PID=1）消息：`journalctl _PID=1 UNIT=a UNIT=b`，
+// 同一字段的多个匹配为“或”。按 MESSAGE_ID（systemd 消息目录）分类，旧版本或缺少 MESSAGE_ID 时
+// 退回按消息文本判断。只覆盖系统服务，用户会话中的单元（USER_UNIT）不检查。
+
+use crate::timeutil::format_local_minute;
+use crate::{Config, SourceKind, SourceStats, add_window_args};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+
+const MESSAGE_UNIT_STARTED: &str = "39f53479d3a045ac8e11786248231fbf";
+const MESSAGE_UNIT_STOPPED: &str = "9d1aaa27d60140bd96365438aad20286";
+const MESSAGE_UNIT_FAILED: &str = "be02cf6855d2428ba40df7e9d022f03d";
+const MESSAGE_UNIT_RESULT: &str = "d9b373ed55a64feb8242e02dbe79a49c";
+const MESSAGE_RESTART_SCHEDULED: &str = "5eb03494b6584870a536b337290809b3";
+
+/// 窗口内 systemd 对某单元的操作次数
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnitLifecycle {
+    pub unit: String,
+    #[serde(default)]
+    pub starts: u64,
+    #[serde(default)]
+    pub stops: u64,
+    /// Restart= 触发的自动重启
+    #[serde(default)]
+    pub restarts: u64,
+    /// 进入 failed 状态（含启动失败）
+    #[serde(default)]
+    pub failures: u64,
+    /// 最近一次失败（UTC 微秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_failure_us: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transition {
+    Started,
+    Stopped,
+    RestartScheduled,
+    Failed,
+}
+
+impl UnitLifecycle {
+    /// `自动重启 3 次、失败 1 次、启动 4 次`，只列出非零项
+    pub fn summary(&self) -> String {
+        [
+            (self.restarts, "自动重启"),
+            (self.failures, "失败"),
+            (self.stops, "停止"),
+            (self.starts, "启动"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, label)| format!("{label} {count} 次"))
+        .collect::<Vec<_>>()
+        .join("、")
+    }
+
+    /// `web.service 在窗口内自动重启 2 次、失败 1 次，最近一次失败于 2024-01-01 00:30`
+    pub fn describe(&self, offset_secs: i64) -> String {
+        let mut text = format!("{} 在窗口内{}", self.unit, self.summary());
+        if let Some(at) = self.last_failure_us {
+            text.push_str(&format!(
+                "，最近一次失败于 {}",
+                format_local_minute(at, offset_secs)
+            ));
+        }
+        text
+    }
+}
+
+/// 来源对应的 systemd 单元：单元来源本身，或事件所属的单元
+pub(crate) fn suspect_unit(suspect: &SourceStats) -> Option<&str> {
+    match suspect.kind {
+        SourceKind::Unit => Some(suspect.source.as_str()),
+        _ => suspect.sample_unit.as_deref(),
+    }
+    .filter(|unit| unit.contains('.') && !unit.ends_with(".scope") && !unit.ends_with(".slice"))
+}
+
+/// 为前 top 个来源填写 unit_events；journalctl 失败时不标注
+pub fn annotate_restarts(suspects: &mut [SourceStats], config: &Config) {
+    let mut units = suspects
+        .iter()
+        .take(config.top)
+        .filter_map(suspect_unit)
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    units.sort();
+    units.dedup();
+    if units.is_empty() {
+        return;
+    }
+
+    let mut cmd = Command::new("journalctl");
+    cmd.arg("--no-pager").arg("--quiet");
+    add_window_args(&mut cmd, config);
+    cmd.arg("_PID=1");
+    for unit in &units {
+        cmd.arg(format!("UNIT={unit}"));
+    }
+    let Some(output) = cmd
+        .arg("--output=json")
+        .arg("--output-fields=MESSAGE,MESSAGE_ID,UNIT")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+    else {
+        return;
+    };
+    let lifecycles = tally_transitions(&String::from_utf8_lossy(&output.stdout));
+    for suspect in suspects.iter_mut().take(config.top) {
+        if let Some(unit) = suspect_unit(suspect).map(str::to_string)
+            && let Some(lifecycle) = lifecycles.get(&unit)
+        {
+            suspect.unit_events = Some(lifecycle.clone());
+        }
+    }
+}
+
+/// journalctl JSON 行 → 各单元的操作次数；认不出的消息忽略
+pub fn tally_transitions(json_lines: &str) -> HashMap<String, UnitLifecycle> {
+    let mut lifecycles: HashMap<String, UnitLifecycle> = HashMap::new();
+    for line in json_lines.lines() {
+        let Ok(Value::Object(entry)) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        let text = |key: &str| entry.get(key).and_then(Value::as_str).unwrap_or_default();
+        let unit = text("UNIT");
+        let Some(transition) = classify(text("MESSAGE_ID"), text("MESSAGE")) else {
+            continue;
+        };
+        if unit.is_empty() {
+            continue;
+        }
+        let lifecycle = lifecycles
+            .entry(unit.to_string())
+            .or_insert_with(|| UnitLifecycle {
+                unit: unit.to_string(),
+                ..UnitLifecycle::default()
+            });
+        match transition {
+            Transition::Started => lifecycle.starts += 1,
+            Transition::Stopped => lifecycle.stops += 1,
+            Transition::RestartScheduled => lifecycle.restarts += 1,
+            Transition::Failed => {
+                lifecycle.failures += 1;
+                let at = text("__REALTIME_TIMESTAMP").parse::<u64>().ok();
+                lifecycle.last_failure_us = lifecycle.last_failure_us.max(at);
+            }
+        }
+    }
+    lifecycles
+}
+
+/// 一次失败 systemd 会先记 `Failed with result`（RESULT），有时再记 `Failed to start`（FAILED）；
+/// 只按前者计数，没有 MESSAGE_ID 时按 `Failed with result` / `entered failed state` 判断
+fn classify(message_id: &str, message: &str) -> Option<Transition> {
+    match message_id {
+        MESSAGE_UNIT_STARTED => return Some(Transition::Started),
+        MESSAGE_UNIT_STOPPED => return Some(Transition::Stopped),
+        MESSAGE_RESTART_SCHEDULED => return Some(Transition::RestartScheduled),
+        MESSAGE_UNIT_RESULT => return Some(Transition::Failed),
+        MESSAGE_UNIT_FAILED => return None,
+        "" => {}
+        _ => return None,
+    }
+    if message.contains("Scheduled restart job") {
+        Some(Transition::RestartScheduled)
+    } else if message.contains("Failed with result") || message.contains("entered failed state") {
+        Some(Transition::Failed)
+    } else if message.starts_with("Started ") {
+        Some(Transition::Started)
+    } else if message.starts_with("Stopped ") {
+        Some(Transition::Stopped)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tallies_restarts_and_failures_per_unit() {
+        let lines = [
+            format!(
+                r#"{{"UNIT":"web.service","MESSAGE_ID":"{MESSAGE_UNIT_RESULT}","MESSAGE":"web.service: Failed with result 'exit-code'.","__REALTIME_TIMESTAMP":"1704069000000000"}}"#
+            ),
+            format!(
+                r#"{{"UNIT":"web.service","MESSAGE_ID":"{MESSAGE_UNIT_FAILED}","MESSAGE":"Failed to start web.service."}}"#
+            ),
+            format!(
+                r#"{{"UNIT":"web.service","MESSAGE_ID":"{MESSAGE_RESTART_SCHEDULED}","MESSAGE":"web.service: Scheduled restart job, restart counter is at 1."}}"#
+            ),
+            format!(
+                r#"{{"UNIT":"web.service","MESSAGE_ID":"{MESSAGE_UNIT_STARTED}","MESSAGE":"Started web.service."}}"#
+            ),
+            // 没有 MESSAGE_ID 的旧格式
+            r#"{"UNIT":"db.service","MESSAGE":"db.service: Failed with result 'timeout'.","__REALTIME_TIMESTAMP":"1704069600000000"}"#.to_string(),
+            r#"{"UNIT":"db.service","MESSAGE":"Stopped db.service."}"#.to_string(),
+            r#"{"UNIT":"db.service","MESSAGE":"db.service: Main process exited, code=exited"}"#
+                .to_string(),
+            "not json".to_string(),
+        ]
+        .join("\n");
+        let lifecycles = tally_transitions(&lines);
+        let web = &lifecycles["web.service"];
+        assert_eq!(
+            (web.starts, web.stops, web.restarts, web.failures),
+            (1, 0, 1, 1)
+        );
+        assert_eq!(web.last_failure_us, Some(1_704_069_000_000_000));
+        assert_eq!(
+            web.describe(0),
+            "web.service 在窗口内自动重启 1 次、失败 1 次、启动 1 次，最近一次失败于 2024-01-01 00:30"
+        );
+        let db = &lifecycles["db.service"];
+        assert_eq!(
+            (db.starts, db.stops, db.restarts, db.failures),
+            (0, 1, 0, 1)
+        );
+        assert_eq!(db.summary(), "失败 1 次、停止 1 次");
+    }
+}
@@ -0,0 +1,654 @@
+// 规则 — 基于消息通配模式的事件归类
+//
+// 规则文件为 JSON（`{"rules":[...]}` 或直接数组），默认从 /etc/logtool/rules.d/*.json
+// 按文件名顺序加载，同一事件以第一条命中的规则为准。
+// 模式使用通配语法：`*` 匹配任意串，`?` 匹配单个字符，大小写不敏感。
+//
+// 带 hint 的规则同时是已知问题库：来源的第一条带 hint 的命中规则（先查 rules.d，再查内置的
+// known_issues.json）给出说明与处理建议，与归并、级别调整所用的“第一条命中”分开判断。
+
+pub use crate::wildcard::wildcard_match;
+use crate::wildcard::wildcard_match_lower;
+use crate::{JournalEvent, parse_priority_level};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const SYSTEM_RULES_DIR: &str = "/etc/logtool/rules.d";
+/// 内置的已知问题库
+const KNOWN_ISSUES: &str = include_str!("known_issues.json");
+pub const KNOWN_ISSUES_FILE: &str = "<内置已知问题库>";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    pub id: String,
+    /// 消息通配模式
+    #[serde(rename = "match")]
+    pub pattern: String,
+    /// 可选：限定来源（匹配 unit / exe / identifier / comm 任一）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// 命中后将事件归并到该名称的可疑来源下
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merge_as: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 命中事件的有效严重级别（0-7 或 err/warning/info 等），用于调整排序
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<SeverityValue>,
+    /// 已知问题的处理建议，与 description（原因说明）一起附在来源上
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SeverityValue {
+    Level(u8),
+    Name(String),
+}
+
+impl SeverityValue {
+    pub fn level(&self) -> Option<u8> {
+        match self {
+            Self::Level(level) => (*level <= 7).then_some(*level),
+            Self::Name(name) => parse_priority_level(name),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RuleFileContent {
+    Wrapped { rules: Vec<Rule> },
+    Bare(Vec<Rule>),
+}
+
+/// 已加载的规则（附带来源文件，便于报错定位）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedRule {
+    pub rule: Rule,
+    pub file: PathBuf,
+    /// 解析后的 severity；无效值在读取文件时已报错
+    pub severity: Option<u8>,
+    pattern_lower: String,
+    source_lower: Option<String>,
+}
+
+impl LoadedRule {
+    pub fn new(rule: Rule, file: PathBuf) -> Self {
+        let pattern_lower = rule.pattern.to_ascii_lowercase();
+        let source_lower = rule.source.as_ref().map(|s| s.to_ascii_lowercase());
+        let severity = rule.severity.as_ref().and_then(SeverityValue::level);
+        Self {
+            rule,
+            file,
+            severity,
+            pattern_lower,
+            source_lower,
+        }
+    }
+
+    pub fn matches(&self, event: &JournalEvent) -> bool {
+        if let Some(source) = &self.source_lower {
+            let candidates = [&event.unit, &event.exe, &event.identifier, &event.comm];
+            let source_hit = candidates
+                .iter()
+                .filter_map(|value| value.as_deref())
+                .any(|value| wildcard_match_lower(source, &value.to_ascii_lowercase()));
+            if !source_hit {
+                return false;
+            }
+        }
+        wildcard_match_lower(&self.pattern_lower, &event.message.to_ascii_lowercase())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleSet {
+    pub rules: Vec<LoadedRule>,
+}
+
+impl RuleSet {
+    /// 加载系统规则目录；目录不存在时返回空规则集
+    pub fn load_system() -> Result<Self, String> {
+        let dir = Path::new(SYSTEM_RULES_DIR);
+        if !dir.is_dir() {
+            return Ok(Self::default());
+        }
+        Self::load_path(dir)
+    }
+
+    /// 加载单个规则文件或目录下的全部 *.json（按文件名排序）
+    pub fn load_path(path: &Path) -> Result<Self, String> {
+        let mut set = Self::default();
+        for file in rule_files(path)? {
+            for rule in read_rule_file(&file)? {
+                set.rules.push(LoadedRule::new(rule, file.clone()));
+            }
+        }
+        Ok(set)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// 按加载顺序列出规则 ID
+    pub fn ids(&self) -> Vec<String> {
+        self.rules.iter().map(|rule| rule.rule.id.clone()).collect()
+    }
+
+    /// 第一条命中的规则
+    pub fn first_match(&self, event: &JournalEvent) -> Option<&LoadedRule> {
+        self.rules.iter().find(|rule| rule.matches(event))
+    }
+
+    /// 第一条带 hint 且命中的规则
+    pub fn first_hint(&self, event: &JournalEvent) -> Option<&LoadedRule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.rule.hint.is_some())
+            .find(|rule| rule.matches(event))
+    }
+
+    /// 内置的已知问题库
+    pub fn known_issues() -> Self {
+        let content: RuleFileContent =
+            serde_json::from_str(KNOWN_ISSUES).expect("内置已知问题库应为有效 JSON");
+        let rules = match content {
+            RuleFileContent::Wrapped { rules } => rules,
+            RuleFileContent::Bare(rules) => rules,
+        };
+        Self {
+            rules: rules
+                .into_iter()
+                .map(|rule| LoadedRule::new(rule, PathBuf::from(KNOWN_ISSUES_FILE)))
+                .collect(),
+        }
+    }
+}
+
+pub fn rule_files(path: &Path) -> Result<Vec<PathBuf>, String> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let entries =
+        fs::read_dir(path).map_err(|e| format!("读取规则目录 {} 失败：{e}", path.display()))?;
+    let mut files = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+pub fn read_rule_file(path: &Path) -> Result<Vec<Rule>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("读取规则文件 {} 失败：{e}", path.display()))?;
+    let content: RuleFileContent = serde_json::from_str(&text)
+        .map_err(|e| format!("解析规则文件 {} 失败：{e}", path.display()))?;
+    let rules = match content {
+        RuleFileContent::Wrapped { rules } => rules,
+        RuleFileContent::Bare(rules) => rules,
+    };
+    for rule in &rules {
+        if let Some(severity) = &rule.severity
+            && severity.level().is_none()
+        {
+            return Err(format!(
+                "规则文件 {} 中规则 {} 的 severity 无效：{severity:?}\n修复：使用 0-7 或 err/warning/info/debug",
+                path.display(),
+                rule.id
+            ));
+        }
+    }
+    Ok(rules)
+}
+
+/// 模式 `a` 是否覆盖模式 `b`：凡 `b` 能匹配的消息 `a` 都能匹配（保守判断）
+///
+/// 把 `b` 当作文本逐字比对，`b` 中的 `*` 只能被 `a` 的 `*` 吸收，
+/// `b` 中的 `?` 只能对上 `a` 的 `?` 或 `*`。
+pub fn pattern_covers(a: &str, b: &str) -> bool {
+    let p = a.to_ascii_lowercase().chars().collect::<Vec<_>>();
+    let t = b.to_ascii_lowercase().chars().collect::<Vec<_>>();
+    let (mut pi, mut ti) = (0usize, 0usize);
+    let mut star: Option<usize> = None;
+    let mut star_ti = 0usize;
+
+    while ti < t.len() {
+        if pi < p.len() && p[pi] == '*' {
+            star = Some(pi);
+            star_ti = ti;
+            pi += 1;
+        } else if pi < p.len() && t[ti] != '*' && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if let Some(star_pi) = star {
+            pi = star_pi + 1;
+            star_ti += 1;
+            ti = star_ti;
+        } else {
+            return false;
+        }
+    }
+
+    while pi < p.len() && p[pi] == '*' {
+        pi += 1;
+    }
+    pi == p.len()
+}
+
+/// 是否存在同时被 `a` 与 `b` 匹配的消息
+///
+/// 两个模式各自看作状态机（位置 i / j），从 (0, 0) 出发同时读入同一个字符：
+/// `*` 可以不读字符直接跳过，也可以读任意字符留在原地；`?` 读任意字符；其余字符只读自己。
+/// 能同时走到两个模式末尾即有交集。
+pub fn patterns_overlap(a: &str, b: &str) -> bool {
+    let p = a.to_ascii_lowercase().chars().collect::<Vec<_>>();
+    let q = b.to_ascii_lowercase().chars().collect::<Vec<_>>();
+    let mut seen = vec![vec![false; q.len() + 1]; p.len() + 1];
+    let mut pending = vec![(0usize, 0usize)];
+
+    while let Some((i, j)) = pending.pop() {
+        if seen[i][j] {
+            continue;
+        }
+        seen[i][j] = true;
+        if i == p.len() && j == q.len() {
+            return true;
+        }
+        if i < p.len() && p[i] == '*' {
+            pending.push((i + 1, j));
+        }
+        if j < q.len() && q[j] == '*' {
+            pending.push((i, j + 1));
+        }
+        if i < p.len() && j < q.len() {
+            let compatible = matches!(p[i], '*' | '?') || matches!(q[j], '*' | '?') || p[i] == q[j];
+            let next = (i + usize::from(p[i] != '*'), j + usize::from(q[j] != '*'));
+            if compatible && next != (i, j) {
+                pending.push(next);
+            }
+        }
+    }
+    false
+}
+
+// ── 规则检查 ─────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintIssue {
+    pub level: LintLevel,
+    pub file: PathBuf,
+    pub rule_id: Option<String>,
+    pub message: String,
+}
+
+/// 检查规则文件：JSON 错误、空/重复 ID、无效模式、被前序规则完全遮蔽、模式有重叠的规则 merge 目标冲突
+pub fn lint_rules(path: &Path) -> Result<Vec<LintIssue>, String> {
+    let mut issues = Vec::new();
+    let mut loaded: Vec<LoadedRule> = Vec::new();
+
+    for file in rule_files(path)? {
+        match read_rule_file(&file) {
+            Ok(rules) => loaded.extend(rules.into_iter().map(|r| LoadedRule::new(r, file.clone()))),
+            Err(err) => issues.push(LintIssue {
+                level: LintLevel::Error,
+                file: file.clone(),
+                rule_id: None,
+                message: err,
+            }),
+        }
+    }
+
+    for (index, current) in loaded.iter().enumerate() {
+        let rule = &current.rule;
+        let mut issue = |level, message: String| {
+            issues.push(LintIssue {
+                level,
+                file: current.file.clone(),
+                rule_id: Some(rule.id.clone()).filter(|id| !id.is_empty()),
+                message,
+            })
+        };
+
+        if rule.id.trim().is_empty() {
+            issue(LintLevel::Error, "规则缺少 id".to_string());
+        }
+        if let Some(message) = pattern_problem(&rule.pattern) {
+            issue(LintLevel::Error, format!("match 模式无效：{message}"));
+        } else if rule.pattern.chars().all(|c| c == '*') {
+            issue(
+                LintLevel::Warning,
+                "match 模式会匹配所有消息，其后的规则将全部失效".to_string(),
+            );
+        }
+        if let Some(source) = &rule.source
+            && let Some(message) = pattern_problem(source)
+        {
+            issue(LintLevel::Error, format!("source 模式无效：{message}"));
+        }
+        if rule
+            .merge_as
+            .as_deref()
+            .is_some_and(|m| m.trim().is_empty())
+        {
+            issue(LintLevel::Error, "merge_as 不能为空字符串".to_string());
+        }
+
+        for earlier in &loaded[..index] {
+            if !earlier.rule.id.is_empty() && earlier.rule.id == rule.id {
+                issue(
+                    LintLevel::Error,
+                    format!("重复的规则 id（首次定义于 {}）", earlier.file.display()),
+                );
+                continue;
+            }
+
+            if rule_covers(earlier, current) {
+                issue(
+                    LintLevel::Warning,
+                    format!("被前面的规则 {} 完全遮蔽，永远不会生效", earlier.rule.id),
+                );
+            } else if let (Some(a), Some(b)) = (&earlier.rule.merge_as, &rule.merge_as)
+                && a != b
+                && rules_overlap(earlier, current)
+            {
+                issue(
+                    LintLevel::Warning,
+                    format!(
+                        "与规则 {} 的模式重叠但归并目标不同（{a} / {b}），结果取决于加载顺序",
+                        earlier.rule.id
+                    ),
+                );
+            }
+        }
+    }
+
+    Ok(issues)
+}
+
+/// `a` 是否覆盖 `b` 的全部命中范围（模式与来源限定都要覆盖）
+fn rule_covers(a: &LoadedRule, b: &LoadedRule) -> bool {
+    let source_covered = match (&a.rule.source, &b.rule.source) {
+        (None, _) => true,
+        (Some(_), None) => false,
+        (Some(x), Some(y)) => pattern_covers(x, y),
+    };
+    source_covered && pattern_covers(&a.rule.pattern, &b.rule.pattern)
+}
+
+/// 是否存在两条规则都会命中的事件（来源限定缺省时匹配任意来源）
+fn rules_overlap(a: &LoadedRule, b: &LoadedRule) -> bool {
+    let sources_overlap = match (&a.rule.source, &b.rule.source) {
+        (Some(x), Some(y)) => patterns_overlap(x, y),
+        _ => true,
+    };
+    sources_overlap && patterns_overlap(&a.rule.pattern, &b.rule.pattern)
+}
+
+fn pattern_problem(pattern: &str) -> Option<String> {
+    if pattern.trim().is_empty() {
+        return Some("模式不能为空".to_string());
+    }
+    if pattern.chars().any(|c| c.is_control()) {
+        return Some("模式包含控制字符".to_string());
+    }
+    None
+}
+
+// ── 规则测试 ─────────────────────────────────────────────
+
+/// 单条规则在样本上的测试结果
+#[derive(Debug, Clone)]
+pub struct RuleTestResult {
+    pub id: String,
+    pub file: PathBuf,
+    /// 规则自身命中的事件数（不考虑先后顺序）
+    pub matched: usize,
+    /// 作为第一条命中规则而实际生效的事件数
+    pub effective: usize,
+    pub elapsed: Duration,
+    pub examples: Vec<String>,
+}
+
+pub const RULE_TEST_EXAMPLES: usize = 3;
+
+/// 对样本事件逐条评估全部规则，统计命中数、生效数与耗时
+pub fn test_rules(rules: &RuleSet, events: &[JournalEvent]) -> Vec<RuleTestResult> {
+    let mut results = rules
+        .rules
+        .iter()
+        .map(|rule| RuleTestResult {
+            id: rule.rule.id.clone(),
+            file: rule.file.clone(),
+            matched: 0,
+            effective: 0,
+            elapsed: Duration::ZERO,
+            examples: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+
+    for event in events {
+        let mut claimed = false;
+        for (rule, result) in rules.rules.iter().zip(results.iter_mut()) {
+            let started = Instant::now();
+            let hit = rule.matches(event);
+            result.elapsed += started.elapsed();
+
+            if !hit {
+                continue;
+            }
+            result.matched += 1;
+            if !claimed {
+                result.effective += 1;
+                claimed = true;
+            }
+            if result.examples.len() < RULE_TEST_EXAMPLES {
+                result.examples.push(event.message.clone());
+            }
+        }
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(message: &str, unit: Option<&str>) -> JournalEvent {
+        JournalEvent {
+            message: message.to_string(),
+            priority: Some(3),
+            unit: unit.map(|u| u.to_string()),
+            ..JournalEvent::default()
+        }
+    }
+
+    fn rule(id: &str, pattern: &str) -> LoadedRule {
+        LoadedRule::new(
+            Rule {
+                id: id.to_string(),
+                pattern: pattern.to_string(),
+                source: None,
+                merge_as: None,
+                description: None,
+                severity: None,
+                hint: None,
+            },
+            PathBuf::from("test.json"),
+        )
+    }
+
+    #[test]
+    fn rule_source_filter_restricts_matches() {
+        let mut scoped = rule("snapd-noise", "*cannot*");
+        scoped = LoadedRule::new(
+            Rule {
+                source: Some("snapd*".to_string()),
+                ..scoped.rule
+            },
+            scoped.file,
+        );
+        assert!(scoped.matches(&event("cannot refresh", Some("snapd.service"))));
+        assert!(!scoped.matches(&event("cannot refresh", Some("ssh.service"))));
+    }
+
+    #[test]
+    fn test_rules_counts_effective_hits_in_order() {
+        let set = RuleSet {
+            rules: vec![rule("broad", "*error*"), rule("specific", "*acpi error*")],
+        };
+        let events = vec![event("ACPI Error: x", None), event("disk error", None)];
+        let results = test_rules(&set, &events);
+
+        assert_eq!(results[0].matched, 2);
+        assert_eq!(results[0].effective, 2);
+        assert_eq!(results[1].matched, 1);
+        assert_eq!(results[1].effective, 0);
+    }
+
+    #[test]
+    fn known_issues_give_hints_independently_of_classification() {
+        let known = RuleSet::known_issues();
+        assert!(known.rules.iter().all(|rule| rule.rule.hint.is_some()));
+        let hit = known
+            .first_hint(&event(
+                "Out of memory: Killed process 4121 (php-fpm8.3) total-vm:2048kB",
+                None,
+            ))
+            .expect("应命中内置规则");
+        assert_eq!(hit.rule.id, "kb-oom-killer");
+        assert!(known.first_hint(&event("all good", None)).is_none());
+
+        // 没有 hint 的规则只参与归类，不影响建议
+        let user = RuleSet {
+            rules: vec![
+                rule("merge-all", "*"),
+                LoadedRule::new(
+                    Rule {
+                        hint: Some("重启 foo".to_string()),
+                        ..rule("foo-stuck", "*foo stuck*").rule
+                    },
+                    PathBuf::from("test.json"),
+                ),
+            ],
+        };
+        let stuck = event("worker foo stuck", None);
+        assert_eq!(
+            user.first_match(&stuck).map(|r| r.rule.id.as_str()),
+            Some("merge-all")
+        );
+        assert_eq!(
+            user.first_hint(&stuck).map(|r| r.rule.id.as_str()),
+            Some("foo-stuck")
+        );
+    }
+
+    #[test]
+    fn severity_accepts_levels_and_names() {
+        let rules: Vec<Rule> = serde_json::from_str(
+            r#"[{"id":"a","match":"*","severity":"info"},{"id":"b","match":"*","severity":2}]"#,
+        )
+        .expect("解析应成功");
+        let levels = rules
+            .into_iter()
+            .map(|r| LoadedRule::new(r, PathBuf::new()).severity)
+            .collect::<Vec<_>>();
+        assert_eq!(levels, vec![Some(6), Some(2)]);
+        assert_eq!(SeverityValue::Level(9).level(), None);
+        assert_eq!(SeverityValue::Name("loud".to_string()).level(), None);
+    }
+
+    #[test]
+    fn pattern_covers_is_conservative() {
+        assert!(pattern_covers("*error*", "*acpi error*"));
+        assert!(pattern_covers("*", "ata?.00*"));
+        assert!(!pattern_covers("acpi*", "*acpi*"));
+        assert!(!pattern_covers("a?c", "a*c"));
+        assert!(!pattern_covers("abc", "a?c"));
+    }
+
+    #[test]
+    fn patterns_overlap_finds_shared_messages() {
+        // “upstream timeout” 两者都能匹配，但谁也不覆盖谁
+        assert!(patterns_overlap("*timeout*", "upstream*"));
+        assert!(patterns_overlap("disk*", "*error"));
+        assert!(patterns_overlap("a?c", "*B*"));
+        assert!(patterns_overlap("*", ""));
+        assert!(!patterns_overlap("disk full*", "net*"));
+        assert!(!patterns_overlap("*error", "*warning"));
+        assert!(!patterns_overlap("a?c", "abcd"));
+    }
+
+    #[test]
+    fn lint_reports_duplicates_shadowing_and_bad_files() {
+        let dir = std::env::temp_dir().join(format!("logtool-lint-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("创建临时目录应成功");
+        fs::write(
+            dir.join("10-a.json"),
+            r#"[{"id":"broad","match":"*error*","merge_as":"A"},{"id":"narrow","match":"*acpi error*","merge_as":"B"},{"id":"broad","match":""},{"id":"upstream","match":"upstream ?????","merge_as":"C"},{"id":"disk","match":"disk*","source":"smartd","merge_as":"D"}]"#,
+        )
+        .expect("写入应成功");
+        fs::write(dir.join("20-bad.json"), "{not json").expect("写入应成功");
+
+        let issues = lint_rules(&dir).expect("检查应成功");
+        let has = |level: LintLevel, id: Option<&str>, needle: &str| {
+            issues.iter().any(|i| {
+                i.level == level && i.rule_id.as_deref() == id && i.message.contains(needle)
+            })
+        };
+        assert!(has(LintLevel::Warning, Some("narrow"), "遮蔽"));
+        // 只部分重叠（“upstream error” 两者都命中）也提示归并目标冲突；5 个字符放不下 “acpi error”
+        assert!(has(
+            LintLevel::Warning,
+            Some("upstream"),
+            "与规则 broad 的模式重叠"
+        ));
+        assert!(!has(LintLevel::Warning, Some("upstream"), "与规则 narrow"));
+        assert!(has(
+            LintLevel::Warning,
+            Some("disk"),
+            "与规则 broad 的模式重叠"
+        ));
+        assert!(!has(LintLevel::Warning, Some("disk"), "与规则 upstream"));
+        assert!(has(LintLevel::Error, Some("broad"), "重复"));
+        assert!(has(LintLevel::Error, Some("broad"), "模式不能为空"));
+        assert!(has(LintLevel::Error, None, "解析规则文件"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reads_wrapped_and_bare_rule_files() {
+        let dir = std::env::temp_dir().join(format!("logtool-rules-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("创建临时目录应成功");
+        fs::write(
+            dir.join("10-a.json"),
+            r#"{"rules":[{"id":"a","match":"*a*","merge_as":"A"}]}"#,
+        )
+        .expect("写入应成功");
+        fs::write(dir.join("20-b.json"), r#"[{"id":"b","match":"*b*"}]"#).expect("写入应成功");
+        fs::write(dir.join("README"), "ignored").expect("写入应成功");
+
+        let set = RuleSet::load_path(&dir).expect("加载应成功");
+        let ids = set
+            .rules
+            .iter()
+            .map(|r| r.rule.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert_eq!(set.rules[0].rule.merge_as.as_deref(), Some("A"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,132 @@
+// 资源用量 — 每次分析的 CPU 时间与峰值内存（logtool stats）
+//
+// 只依赖 std：直接调用 libc 的 getrusage(2) / wait4(2)（std 本身已链接 libc），
+// 结构体布局按 64 位 Linux 声明，其他平台一律返回 None，统计中不出现资源字段。
+// wait4 在回收 journalctl 子进程的同时取得它的 CPU 时间与峰值 RSS；
+// daemon 每个连接一个线程，RUSAGE_THREAD 的差值即该请求在 daemon 内消耗的 CPU。
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::process::{Child, ExitStatus};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// 用户态 + 内核态 CPU 时间（微秒）
+    pub cpu_us: u64,
+    /// 峰值常驻内存（KiB）
+    pub max_rss_kib: u64,
+}
+
+#[cfg(all(target_os = "linux", target_pointer_width = "64"))]
+mod sys {
+    use super::ResourceUsage;
+
+    pub const RUSAGE_SELF: i32 = 0;
+    pub const RUSAGE_THREAD: i32 = 1;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct Timeval {
+        tv_sec: i64,
+        tv_usec: i64,
+    }
+
+    /// `struct rusage`：两个 timeval 之后是 14 个 long，第一个为 ru_maxrss（KiB）
+    #[repr(C)]
+    #[derive(Default)]
+    pub struct RawUsage {
+        ru_utime: Timeval,
+        ru_stime: Timeval,
+        ru_maxrss: i64,
+        _rest: [i64; 13],
+    }
+
+    impl RawUsage {
+        pub fn usage(&self) -> ResourceUsage {
+            let micros = |tv: &Timeval| {
+                u64::try_from(tv.tv_sec).unwrap_or(0) * 1_000_000
+                    + u64::try_from(tv.tv_usec).unwrap_or(0)
+            };
+            ResourceUsage {
+                cpu_us: micros(&self.ru_utime) + micros(&self.ru_stime),
+                max_rss_kib: u64::try_from(self.ru_maxrss).unwrap_or(0),
+            }
+        }
+    }
+
+    unsafe extern "C" {
+        pub fn getrusage(who: i32, usage: *mut RawUsage) -> i32;
+        pub fn wait4(pid: i32, status: *mut i32, options: i32, usage: *mut RawUsage) -> i32;
+    }
+
+    pub fn getrusage_for(who: i32) -> Option<ResourceUsage> {
+        let mut raw = RawUsage::default();
+        // SAFETY: raw 按 64 位 Linux 的 struct rusage 布局声明，指针在调用期间有效
+        let ret = unsafe { getrusage(who, &mut raw) };
+        (ret == 0).then(|| raw.usage())
+    }
+}
+
+/// 当前线程累计的 CPU 时间（微秒）
+pub fn thread_cpu_us() -> Option<u64> {
+    #[cfg(all(target_os = "linux", target_pointer_width = "64"))]
+    return sys::getrusage_for(sys::RUSAGE_THREAD).map(|usage| usage.cpu_us);
+    #[cfg(not(all(target_os = "linux", target_pointer_width = "64")))]
+    None
+}
+
+/// 本进程自启动以来的峰值常驻内存（KiB）
+pub fn process_max_rss_kib() -> Option<u64> {
+    #[cfg(all(target_os = "linux", target_pointer_width = "64"))]
+    return sys::getrusage_for(sys::RUSAGE_SELF).map(|usage| usage.max_rss_kib);
+    #[cfg(not(all(target_os = "linux", target_pointer_width = "64")))]
+    None
+}
+
+/// 等待子进程退出并取得它的资源用量；回收后不能再对 child 调用 wait/kill
+pub fn wait_child(child: &mut Child) -> io::Result<(ExitStatus, Option<ResourceUsage>)> {
+    #[cfg(all(target_os = "linux", target_pointer_width = "64"))]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        let pid = i32::try_from(child.id())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "子进程 PID 超出范围"))?;
+        let mut status = 0i32;
+        let mut raw = sys::RawUsage::default();
+        loop {
+            // SAFETY: pid 是尚未回收的子进程；status 与 raw 在调用期间有效
+            let ret = unsafe { sys::wait4(pid, &mut status, 0, &mut raw) };
+            if ret == pid {
+                return Ok((ExitStatus::from_raw(status), Some(raw.usage())));
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+    #[cfg(not(all(target_os = "linux", target_pointer_width = "64")))]
+    child.wait().map(|status| (status, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn wait_child_reaps_and_reports_usage() {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg("exit 3")
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("sh 应可启动");
+        let (status, usage) = wait_child(&mut child).expect("等待应成功");
+        assert_eq!(status.code(), Some(3));
+        if cfg!(all(target_os = "linux", target_pointer_width = "64")) {
+            assert!(usage.is_some_and(|u| u.max_rss_kib > 0));
+            assert!(process_max_rss_kib().is_some_and(|kib| kib > 0));
+            assert!(thread_cpu_us().is_some());
+        }
+    }
+}
@@ -0,0 +1,418 @@
+// 配置文件 — 用户级 / 系统级 JSON 配置
+//
+// 查找顺序：$XDG_CONFIG_HOME/logtool/config.json（或 ~/.config/logtool/config.json），
+// 然后 /etc/logtool/config.json；找到的第一个文件生效。
+// 配置由 CLI 读取，需要 daemon 参与的部分随请求 Config 一并发送。
+
+use crate::timeutil::{CivilTime, local_civil_from_micros};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SYSTEM_SETTINGS_PATH: &str = "/etc/logtool/config.json";
+pub const SUPPORTED_LANGUAGES: &[&str] = &["zh", "en"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+    /// 本地模式：不连接 daemon，CLI 直接调用 journalctl（需当前用户可读 journal）
+    #[serde(default)]
+    pub local_mode: bool,
+    /// 首选语言（zh / en），首次运行向导写入
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// 未指定 --since 时使用的默认时间窗口
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_since: Option<String>,
+    /// 未指定对应参数时使用的截断上限（--max-msg-chars / --max-line-bytes / --max-resp-bytes）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_message_chars: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_line_bytes: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_bytes: Option<usize>,
+    /// 读取时跳过的无效项（如 schedule 写错的维护窗口）；由 CLI 输出警告，不写回文件
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+/// 维护窗口：按 cron 表达式（分 时 日 月 周）开始，持续 duration_minutes 分钟
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub name: String,
+    pub schedule: String,
+    pub duration_minutes: u32,
+}
+
+impl Settings {
+    /// 加载第一个存在的配置文件；都不存在时返回默认配置
+    pub fn load() -> Result<Self, String> {
+        match existing_settings_path() {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// 写入配置文件（必要时创建目录）
+    pub fn save_to(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("创建配置目录 {} 失败：{e}", dir.display()))?;
+        }
+        let mut text =
+            serde_json::to_string_pretty(self).map_err(|e| format!("序列化配置失败：{e}"))?;
+        text.push('\n');
+        fs::write(path, text).map_err(|e| format!("写入配置文件 {} 失败：{e}", path.display()))
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("读取配置文件 {} 失败：{e}", path.display()))?;
+        let mut settings: Settings = serde_json::from_str(&text).map_err(|e| {
+            format!(
+                "解析配置文件 {} 失败：{e}\n修复：检查 JSON 格式",
+                path.display()
+            )
+        })?;
+        // 一个写错的维护窗口不应让每次运行都失败：报告后跳过该窗口
+        let mut warnings = Vec::new();
+        settings.maintenance_windows.retain(|window| match window.validate() {
+            Ok(()) => true,
+            Err(err) => {
+                warnings.push(format!(
+                    "配置文件 {}：{err}，已忽略该窗口\n修复：按“分 时 日 月 周”改正 schedule，duration_minutes 须大于 0",
+                    path.display()
+                ));
+                false
+            }
+        });
+        settings.warnings = warnings;
+        crate::validate_limits(
+            settings.max_message_chars,
+            settings.max_line_bytes,
+            settings.max_response_bytes,
+        )
+        .map_err(|e| format!("配置文件 {}：{e}", path.display()))?;
+        if let Some(language) = &settings.language
+            && !SUPPORTED_LANGUAGES.contains(&language.as_str())
+        {
+            return Err(format!(
+                "配置文件 {}：不支持的 language：{language}\n修复：使用 zh 或 en",
+                path.display()
+            ));
+        }
+        Ok(settings)
+    }
+}
+
+pub fn user_settings_path() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(dir).join("logtool").join("config.json"));
+    }
+    env::var_os("HOME")
+        .filter(|v| !v.is_empty())
+        .map(|home| PathBuf::from(home).join(".config/logtool/config.json"))
+}
+
+/// 第一个存在的配置文件路径
+pub fn existing_settings_path() -> Option<PathBuf> {
+    settings_candidates()
+        .into_iter()
+        .find(|path| path.is_file())
+}
+
+fn settings_candidates() -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    if let Some(path) = user_settings_path() {
+        out.push(path);
+    }
+    out.push(PathBuf::from(SYSTEM_SETTINGS_PATH));
+    out
+}
+
+// ── 维护窗口 ─────────────────────────────────────────────
+
+impl MaintenanceWindow {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.duration_minutes == 0 {
+            return Err(format!(
+                "维护窗口 {} 的 duration_minutes 必须大于 0",
+                self.name
+            ));
+        }
+        CronSchedule::parse(&self.schedule)
+            .map(|_| ())
+            .map_err(|e| format!("维护窗口 {} 的 schedule 无效：{e}", self.name))
+    }
+}
+
+/// 判断事件是否落在任一维护窗口内，按分钟缓存结果；无效的窗口（读取配置时已警告）被跳过
+pub struct MaintenanceMatcher {
+    windows: Vec<(CronSchedule, i64)>,
+    offset_secs: i64,
+    cache: HashMap<i64, bool>,
+}
+
+impl MaintenanceMatcher {
+    pub fn new(windows: &[MaintenanceWindow], offset_secs: i64) -> Self {
+        let windows = windows
+            .iter()
+            .filter(|window| window.duration_minutes > 0)
+            .filter_map(|window| {
+                let schedule = CronSchedule::parse(&window.schedule).ok()?;
+                Some((schedule, i64::from(window.duration_minutes)))
+            })
+            .collect();
+        Self {
+            windows,
+            offset_secs,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    pub fn contains(&mut self, timestamp_us: u64) -> bool {
+        if self.windows.is_empty() {
+            return false;
+        }
+        let minute = (timestamp_us / 60_000_000) as i64;
+        if let Some(hit) = self.cache.get(&minute) {
+            return *hit;
+        }
+
+        let hit = self.windows.iter().any(|(schedule, duration)| {
+            // 向前回看 duration 分钟，寻找一个满足 cron 的开始分钟
+            (0..*duration).any(|back| {
+                let start_us = ((minute - back) * 60_000_000).max(0) as u64;
+                schedule.matches(&local_civil_from_micros(start_us, self.offset_secs))
+            })
+        });
+        self.cache.insert(minute, hit);
+        hit
+    }
+}
+
+/// 五段式 cron 表达式：分 时 日 月 周，支持 `*`、`a-b`、`a,b`、`*/n`、`a-b/n`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    /// 日 / 周字段不以 `*` 开头（`*/2` 与 `*` 一样不算限定），与 cron 的判断一致
+    day_restricted: bool,
+    weekday_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields = expr.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 5 {
+            return Err(format!(
+                "需要 5 个字段（分 时 日 月 周），实际为 {}：{expr}",
+                fields.len()
+            ));
+        }
+
+        let mut weekdays = parse_cron_field(fields[4], 0, 7)?;
+        // 0 与 7 均表示周日
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+        weekdays.truncate(7);
+
+        Ok(Self {
+            minutes: parse_cron_field(fields[0], 0, 59)?,
+            hours: parse_cron_field(fields[1], 0, 23)?,
+            days: parse_cron_field(fields[2], 1, 31)?,
+            months: parse_cron_field(fields[3], 1, 12)?,
+            weekdays,
+            day_restricted: !fields[2].starts_with('*'),
+            weekday_restricted: !fields[4].starts_with('*'),
+        })
+    }
+
+    pub fn matches(&self, t: &CivilTime) -> bool {
+        if !self.minutes[t.minute as usize]
+            || !self.hours[t.hour as usize]
+            || !self.months[t.month as usize]
+        {
+            return false;
+        }
+
+        let day_hit = self.days[t.day as usize];
+        let weekday_hit = self.weekdays[t.weekday as usize];
+        // 与 cron 一致：日与周同时受限时满足任一即可
+        if self.day_restricted && self.weekday_restricted {
+            day_hit || weekday_hit
+        } else {
+            day_hit && weekday_hit
+        }
+    }
+}
+
+/// 解析单个 cron 字段，返回下标 0..=max 的命中表
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>, String> {
+    let mut table = vec![false; max as usize + 1];
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("步长无效：{part}"))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (
+                parse_cron_number(a, min, max)?,
+                parse_cron_number(b, min, max)?,
+            )
+        } else {
+            let value = parse_cron_number(range, min, max)?;
+            // `5/10` 表示从 5 开始每 10 个单位
+            if step > 1 {
+                (value, max)
+            } else {
+                (value, value)
+            }
+        };
+
+        if start > end {
+            return Err(format!("范围起点大于终点：{part}"));
+        }
+
+        let mut value = start;
+        while value <= end {
+            table[value as usize] = true;
+            value += step;
+        }
+    }
+
+    Ok(table)
+}
+
+fn parse_cron_number(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    let parsed = value
+        .parse::<u32>()
+        .map_err(|_| format!("不是有效数字：{value}"))?;
+    if parsed < min || parsed > max {
+        return Err(format!("{value} 超出范围 {min}-{max}"));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeutil::civil_from_unix;
+
+    fn window(schedule: &str, duration_minutes: u32) -> MaintenanceWindow {
+        MaintenanceWindow {
+            name: "nightly".to_string(),
+            schedule: schedule.to_string(),
+            duration_minutes,
+        }
+    }
+
+    #[test]
+    fn cron_rejects_malformed_expressions() {
+        assert!(CronSchedule::parse("0 2 * *").is_err());
+        assert!(CronSchedule::parse("61 2 * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("0 5-3 * * *").is_err());
+    }
+
+    #[test]
+    fn cron_matches_weekday_ranges_and_steps() {
+        let schedule = CronSchedule::parse("*/15 2 * * 1-5").expect("应解析成功");
+        // 2024-01-01 02:30 UTC 为周一
+        assert!(schedule.matches(&civil_from_unix(1_704_076_200)));
+        // 2024-01-01 02:31 不在 */15 上
+        assert!(!schedule.matches(&civil_from_unix(1_704_076_260)));
+        // 2024-01-06 02:30 为周六
+        assert!(!schedule.matches(&civil_from_unix(1_704_508_200)));
+    }
+
+    #[test]
+    fn maintenance_matcher_covers_window_duration() {
+        let mut matcher = MaintenanceMatcher::new(&[window("0 2 * * *", 60)], 0);
+        let base_us = 1_704_074_400u64 * 1_000_000; // 2024-01-01 02:00 UTC
+        assert!(matcher.contains(base_us));
+        assert!(matcher.contains(base_us + 59 * 60_000_000));
+        assert!(!matcher.contains(base_us + 60 * 60_000_000));
+        assert!(!matcher.contains(base_us - 60_000_000));
+    }
+
+    #[test]
+    fn cron_day_and_weekday_steps_from_star_are_unrestricted() {
+        // 2024-01-01 02:00 UTC 为周一，01-02 为周二
+        let monday = civil_from_unix(1_704_074_400);
+        let tuesday = civil_from_unix(1_704_160_800);
+        let schedule = CronSchedule::parse("0 2 */1 * 1").expect("应解析成功");
+        assert!(schedule.matches(&monday));
+        assert!(!schedule.matches(&tuesday));
+        // 日与周都给出具体值时满足任一即可
+        let schedule = CronSchedule::parse("0 2 2 * 1").expect("应解析成功");
+        assert!(schedule.matches(&monday) && schedule.matches(&tuesday));
+        let schedule = CronSchedule::parse("0 2 1 * */1").expect("应解析成功");
+        assert!(schedule.matches(&monday));
+        assert!(!schedule.matches(&tuesday));
+    }
+
+    #[test]
+    fn settings_skip_invalid_windows_with_a_warning() {
+        let dir = env::temp_dir().join(format!("logtool-settings-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("创建临时目录应成功");
+        let path = dir.join("config.json");
+        fs::write(
+            &path,
+            r#"{"maintenance_windows":[{"name":"bad","schedule":"0 25 * * *","duration_minutes":30},{"name":"nightly","schedule":"0 2 * * *","duration_minutes":60}]}"#,
+        )
+        .expect("写入应成功");
+        let settings = Settings::load_from(&path).expect("无效窗口不应导致读取失败");
+        assert_eq!(settings.maintenance_windows, vec![window("0 2 * * *", 60)]);
+        assert_eq!(settings.warnings.len(), 1);
+        assert!(settings.warnings[0].contains("bad") && settings.warnings[0].contains("修复："));
+
+        // 直接传入的无效窗口同样被跳过
+        let mut matcher = MaintenanceMatcher::new(&[window("0 25 * * *", 30)], 0);
+        assert!(matcher.is_empty());
+        assert!(!matcher.contains(1_704_074_400u64 * 1_000_000));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn settings_round_trip_through_save() {
+        let dir = env::temp_dir().join(format!("logtool-settings-save-{}", std::process::id()));
+        let path = dir.join("nested/config.json");
+        let settings = Settings {
+            local_mode: true,
+            language: Some("en".to_string()),
+            default_since: Some("1 hour ago".to_string()),
+            ..Settings::default()
+        };
+        settings.save_to(&path).expect("写入应成功");
+        assert_eq!(Settings::load_from(&path).expect("读取应成功"), settings);
+
+        fs::write(&path, r#"{"language":"fr"}"#).expect("写入应成功");
+        assert!(Settings::load_from(&path).is_err());
+        fs::write(&path, r#"{"max_line_bytes":10}"#).expect("写入应成功");
+        assert!(Settings::load_from(&path).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
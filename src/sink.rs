@@ -0,0 +1,231 @@
+// 流输出目标 — 流模式逐行写出的 JSON 行可以同时送往多个目标
+//
+// StreamSink 是带名称的 Write：Unix Socket、（可轮转的）文件、FIFO 与内存缓冲都实现了它。
+// FanOut 把一次会话写到一个主目标和若干附加目标：主目标（daemon 的客户端连接）失败即结束流，
+// 附加目标（如 daemon 侧的会话归档）失败时只被移除并记下原因，不影响客户端。
+
+use crate::RotatingFileWriter;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+pub trait StreamSink: Write + Send {
+    /// 日志与错误信息中的名称，如 `socket`、`file:/var/log/logtool/…`
+    fn label(&self) -> String;
+}
+
+impl StreamSink for UnixStream {
+    fn label(&self) -> String {
+        "socket".to_string()
+    }
+}
+
+impl StreamSink for RotatingFileWriter {
+    fn label(&self) -> String {
+        format!("file:{}", self.path().display())
+    }
+}
+
+impl StreamSink for Vec<u8> {
+    fn label(&self) -> String {
+        "memory".to_string()
+    }
+}
+
+pub struct FifoSink {
+    path: PathBuf,
+    file: File,
+}
+
+impl Write for FifoSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl StreamSink for FifoSink {
+    fn label(&self) -> String {
+        format!("fifo:{}", self.path.display())
+    }
+}
+
+/// FIFO 在没有读取端时 open 会阻塞，放到后台线程并设置超时
+pub fn open_fifo(path: &Path, timeout: Duration) -> Result<FifoSink, String> {
+    let (tx, rx) = mpsc::channel();
+    let fifo_path = path.to_path_buf();
+    thread::spawn(move || {
+        let _ = tx.send(OpenOptions::new().write(true).open(fifo_path));
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(Ok(file)) => Ok(FifoSink {
+            path: path.to_path_buf(),
+            file,
+        }),
+        Ok(Err(err)) => Err(format!("打开转发 FIFO {} 失败：{err}", path.display())),
+        Err(_) => Err(format!(
+            "打开转发 FIFO {} 超时：{} 秒内没有读取端\n修复：先启动消费端（例如 cat {}）",
+            path.display(),
+            timeout.as_secs(),
+            path.display()
+        )),
+    }
+}
+
+/// --forward 目标：Unix Socket 直接连接，FIFO 以写方式打开
+pub fn open_forward_sink(path: &Path, timeout: Duration) -> Result<Box<dyn StreamSink>, String> {
+    let meta =
+        fs::metadata(path).map_err(|e| format!("无法访问转发目标 {}：{e}", path.display()))?;
+    let file_type = meta.file_type();
+
+    if file_type.is_socket() {
+        let stream = UnixStream::connect(path)
+            .map_err(|e| format!("无法连接转发 Socket {}：{e}", path.display()))?;
+        return Ok(Box::new(stream));
+    }
+
+    if file_type.is_fifo() {
+        return Ok(Box::new(open_fifo(path, timeout)?));
+    }
+
+    Err(format!(
+        "转发目标 {} 既不是 Unix Socket 也不是 FIFO\n修复：使用 mkfifo 创建，或指向监听中的 Socket",
+        path.display()
+    ))
+}
+
+/// 一个主目标加若干附加目标；每次写入按顺序写完整个缓冲区
+pub struct FanOut {
+    primary: Box<dyn StreamSink>,
+    extra: Vec<Box<dyn StreamSink>>,
+    /// 写入失败后被移除的附加目标：`名称：错误`
+    dropped: Vec<String>,
+}
+
+impl FanOut {
+    pub fn new(primary: Box<dyn StreamSink>) -> Self {
+        Self {
+            primary,
+            extra: Vec::new(),
+            dropped: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, sink: Box<dyn StreamSink>) -> Self {
+        self.extra.push(sink);
+        self
+    }
+
+    /// 主目标之后的各目标名称（仍在写入的）
+    pub fn extra_labels(&self) -> Vec<String> {
+        self.extra.iter().map(|sink| sink.label()).collect()
+    }
+
+    pub fn dropped(&self) -> &[String] {
+        &self.dropped
+    }
+
+    fn for_each_extra(&mut self, mut op: impl FnMut(&mut dyn StreamSink) -> io::Result<()>) {
+        let dropped = &mut self.dropped;
+        self.extra.retain_mut(|sink| match op(sink.as_mut()) {
+            Ok(()) => true,
+            Err(err) => {
+                dropped.push(format!("{}：{err}", sink.label()));
+                false
+            }
+        });
+    }
+}
+
+impl Write for FanOut {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.primary.write_all(buf)?;
+        self.for_each_extra(|sink| sink.write_all(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.primary.flush()?;
+        self.for_each_extra(|sink| sink.flush());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    /// 第一次写入之后就失败的附加目标
+    struct Broken(usize);
+
+    impl Write for Broken {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += 1;
+            if self.0 > 1 {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "gone"));
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl StreamSink for Broken {
+        fn label(&self) -> String {
+            "broken".to_string()
+        }
+    }
+
+    #[test]
+    fn fan_out_copies_to_every_sink_and_drops_failed_extras() {
+        let (client, peer) = UnixStream::pair().expect("创建 socketpair 应成功");
+        let path = env::temp_dir().join(format!("logtool-sink-{}.ndjson", process::id()));
+        let _ = fs::remove_file(&path);
+        let archive = RotatingFileWriter::open(&path, 1024).expect("打开文件应成功");
+
+        let mut out = FanOut::new(Box::new(client))
+            .with(Box::new(archive))
+            .with(Box::new(Broken(0)));
+        assert_eq!(out.extra_labels()[0], format!("file:{}", path.display()));
+        writeln!(out, "{{\"line\":\"a\"}}").expect("写入应成功");
+        writeln!(out, "{{\"line\":\"b\"}}").expect("附加目标失败不应中断写入");
+        out.flush().expect("刷新应成功");
+        assert_eq!(out.dropped(), ["broken：gone"]);
+        drop(out);
+
+        let expected = "{\"line\":\"a\"}\n{\"line\":\"b\"}\n";
+        assert_eq!(fs::read_to_string(&path).expect("读取应成功"), expected);
+        let mut received = String::new();
+        io::Read::read_to_string(&mut &peer, &mut received).expect("读取 socket 应成功");
+        assert_eq!(received, expected);
+        let _ = fs::remove_file(&path);
+
+        // 主目标失败时整个写入失败
+        let mut out = FanOut::new(Box::new(Broken(1))).with(Box::new(Vec::new()));
+        assert!(out.write_all(b"x").is_err());
+    }
+
+    #[test]
+    fn open_forward_target_rejects_regular_file() {
+        let path = env::temp_dir().join(format!("logtool-forward-{}", process::id()));
+        fs::write(&path, b"").expect("写入应成功");
+        let err = match open_forward_sink(&path, Duration::from_secs(1)) {
+            Ok(_) => panic!("普通文件不应作为转发目标"),
+            Err(err) => err,
+        };
+        assert!(err.contains("FIFO"));
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,172 @@
+// SMART 健康检查 — `--smart` 对前列中报错的存储设备运行 `smartctl -H -j`，把“ata3.00 exception”变成
+// “磁盘 /dev/sda 健康检查未通过，正在损坏”这样可以直接行动的结论
+//
+// ATA 端口（ata3）经 /sys/block/*/device 的实际路径对应到挂在该端口上的磁盘；NVMe 控制器（nvme0）直接检查。
+// smartctl 的退出码是位掩码，磁盘故障时同样非零，因此只看 JSON 中的 smart_status；读取设备通常需要 root，
+// 无权限或设备不支持时给出 smartctl 的提示。查看的是本机当前的磁盘，读取 --directory / --file 时解析参数即拒绝。
+
+use crate::{SourceKind, SourceStats};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SmartHealth {
+    /// 对应的存储设备来源（sda、ata3、nvme0n1）
+    pub source: String,
+    /// 实际检查的设备（/dev/sda）
+    pub device: String,
+    /// SMART 总体健康评估；smartctl 给不出结论时为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passed: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// 给不出结论时 smartctl 的提示（如 Permission denied）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 对前 top 个来源中的存储设备逐一检查；找不到对应磁盘或无法运行 smartctl 的设备不列出
+pub fn check_storage_health(suspects: &[SourceStats], top: usize) -> Vec<SmartHealth> {
+    suspects
+        .iter()
+        .take(top)
+        .filter(|suspect| suspect.kind == SourceKind::Storage)
+        .filter_map(|suspect| {
+            let device = block_device(&suspect.source)?;
+            run_smartctl(&suspect.source, &device)
+        })
+        .collect()
+}
+
+/// 存储来源 → 设备节点；ATA 端口按 sysfs 找到其上的磁盘
+fn block_device(source: &str) -> Option<String> {
+    if source.starts_with("ata") {
+        return ata_disk(source).map(|disk| format!("/dev/{disk}"));
+    }
+    // device-mapper、md 等虚拟设备没有 SMART
+    if source.starts_with("dm-") || source.starts_with("md") || source.starts_with("loop") {
+        return None;
+    }
+    Some(format!("/dev/{source}"))
+}
+
+/// `/sys/block/sda/device` → `…/ata3/host2/target2:0:0/2:0:0:0`
+fn ata_disk(port: &str) -> Option<String> {
+    let marker = format!("/{port}/");
+    let mut disks = fs::read_dir("/sys/block")
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            fs::canonicalize(entry.path().join("device"))
+                .is_ok_and(|path| path.to_string_lossy().contains(&marker))
+        })
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    disks.sort();
+    disks.into_iter().next()
+}
+
+fn run_smartctl(source: &str, device: &str) -> Option<SmartHealth> {
+    let output = Command::new("smartctl")
+        .arg("-H")
+        .arg("-j")
+        .arg(device)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    parse_smartctl_json(source, device, &String::from_utf8_lossy(&output.stdout))
+}
+
+/// `{"model_name":"…","smart_status":{"passed":false},"smartctl":{"messages":[{"string":"…"}]}}`
+pub fn parse_smartctl_json(source: &str, device: &str, text: &str) -> Option<SmartHealth> {
+    let value: Value = serde_json::from_str(text).ok()?;
+    let passed = value
+        .get("smart_status")
+        .and_then(|status| status.get("passed"))
+        .and_then(Value::as_bool);
+    let error = passed.is_none().then(|| {
+        value
+            .pointer("/smartctl/messages")
+            .and_then(Value::as_array)
+            .and_then(|messages| messages.first())
+            .and_then(|message| message.get("string"))
+            .and_then(Value::as_str)
+            .unwrap_or("smartctl 没有给出健康评估")
+            .to_string()
+    });
+    Some(SmartHealth {
+        source: source.to_string(),
+        device: device.to_string(),
+        passed,
+        model: value
+            .get("model_name")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        error,
+    })
+}
+
+impl SmartHealth {
+    /// `/dev/sda（Samsung SSD 860）健康检查未通过，磁盘正在损坏，尽快备份并更换`
+    pub fn verdict(&self) -> String {
+        let device = match &self.model {
+            Some(model) => format!("{}（{model}）", self.device),
+            None => format!("{} ", self.device),
+        };
+        match (self.passed, &self.error) {
+            (Some(false), _) => format!("{device}健康检查未通过，磁盘正在损坏，尽快备份并更换"),
+            (Some(true), _) => {
+                format!("{device}健康检查通过，错误更可能来自线缆、接口或控制器")
+            }
+            (None, Some(error)) => format!("{device}无法读取 SMART 状态（{error}）"),
+            (None, None) => format!("{device}无法读取 SMART 状态"),
+        }
+    }
+}
+
+/// 某个存储来源的检查结果
+pub fn health_for<'a>(health: &'a [SmartHealth], suspect: &SourceStats) -> Option<&'a SmartHealth> {
+    if suspect.kind != SourceKind::Storage {
+        return None;
+    }
+    health.iter().find(|h| h.source == suspect.source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smartctl_verdicts_are_parsed() {
+        let failing = parse_smartctl_json(
+            "ata3",
+            "/dev/sda",
+            r#"{"smartctl":{"exit_status":8},"device":{"name":"/dev/sda"},"model_name":"ST2000DM008","smart_status":{"passed":false}}"#,
+        )
+        .expect("应能解析 smartctl 输出");
+        assert_eq!(failing.passed, Some(false));
+        assert_eq!(
+            failing.verdict(),
+            "/dev/sda（ST2000DM008）健康检查未通过，磁盘正在损坏，尽快备份并更换"
+        );
+
+        let denied = parse_smartctl_json(
+            "sda",
+            "/dev/sda",
+            r#"{"smartctl":{"messages":[{"string":"Smartctl open device: /dev/sda failed: Permission denied","severity":"error"}],"exit_status":2}}"#,
+        )
+        .expect("应能解析 smartctl 输出");
+        assert_eq!(denied.passed, None);
+        assert_eq!(
+            denied.verdict(),
+            "/dev/sda 无法读取 SMART 状态（Smartctl open device: /dev/sda failed: Permission denied）"
+        );
+        assert!(parse_smartctl_json("sda", "/dev/sda", "smartctl: command not found").is_none());
+
+        assert_eq!(block_device("nvme0n1").as_deref(), Some("/dev/nvme0n1"));
+        assert_eq!(block_device("dm-0"), None);
+    }
+}
@@ -0,0 +1,329 @@
+// Socket 位置 — daemon 与 CLI 共用同一查找顺序
+//
+// 指定了目录（$LOGTOOL_SOCKET_DIR，或 daemon 的 --socket-dir）时只用该目录；否则依次为
+// /run 与 $XDG_RUNTIME_DIR。daemon 绑定第一个可用的目录，CLI 连接第一个有 daemon 监听的目录，
+// 因此 /run 只读或不存在时（容器、CI），两者以普通用户身份也能配对使用。
+
+use std::env;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_SOCKET_DIR: &str = "/run";
+pub const SOCKET_DIR_ENV: &str = "LOGTOOL_SOCKET_DIR";
+pub const SOCKET_NAME: &str = "logtool.sock";
+
+/// configured 为显式指定的目录；runtime_dir 为 $XDG_RUNTIME_DIR
+pub fn socket_dirs(configured: Option<PathBuf>, runtime_dir: Option<PathBuf>) -> Vec<PathBuf> {
+    if let Some(dir) = configured {
+        return vec![dir];
+    }
+    let default = PathBuf::from(DEFAULT_SOCKET_DIR);
+    let mut dirs = vec![default.clone()];
+    dirs.extend(runtime_dir.filter(|dir| *dir != default));
+    dirs
+}
+
+/// 按环境变量给出候选目录；configured（daemon 的 --socket-dir）优先于 $LOGTOOL_SOCKET_DIR
+pub fn env_socket_dirs(configured: Option<PathBuf>) -> Vec<PathBuf> {
+    let env_dir = |key: &str| {
+        env::var_os(key)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    socket_dirs(
+        configured.or_else(|| env_dir(SOCKET_DIR_ENV)),
+        env_dir("XDG_RUNTIME_DIR"),
+    )
+}
+
+/// 第一个已存在的 socket 文件（logtool doctor 用）
+pub fn find_socket(dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+    dirs.iter()
+        .map(|dir| dir.join(name))
+        .find(|path| path.exists())
+}
+
+/// 依次绑定各目录下的 name，先清理残留的 socket 文件；全部失败时列出每个位置的原因
+pub fn bind_first(dirs: &[PathBuf], name: &str) -> Result<(UnixListener, PathBuf), String> {
+    let mut failures = Vec::new();
+    for dir in dirs {
+        let path = dir.join(name);
+        if !dir.is_dir() {
+            failures.push(format!("{}：目录不存在", path.display()));
+            continue;
+        }
+        let _ = fs::remove_file(&path);
+        match UnixListener::bind(&path) {
+            Ok(listener) => return Ok((listener, path)),
+            Err(err) => failures.push(format!("{}：{err}", path.display())),
+        }
+    }
+    Err(failures.join("；"))
+}
+
+/// 依次连接各目录下的 name；全部失败时列出每个位置的原因
+pub fn connect_first(dirs: &[PathBuf], name: &str) -> Result<(UnixStream, PathBuf), String> {
+    let mut failures = Vec::new();
+    for dir in dirs {
+        let path = dir.join(name);
+        match UnixStream::connect(&path) {
+            Ok(stream) => return Ok((stream, path)),
+            Err(err) => failures.push(format!("{}：{err}", path.display())),
+        }
+    }
+    Err(failures.join("；"))
+}
+
+/// `/run`、`/run/user/1000`
+pub fn dirs_label(dirs: &[PathBuf]) -> String {
+    dirs.iter()
+        .map(|dir| dir.display().to_string())
+        .collect::<Vec<_>>()
+        .join("、")
+}
+
+/// socket 所在目录，用于把查看 socket 放在主 socket 旁边
+pub fn socket_dir_of(path: &Path) -> PathBuf {
+    path.parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SOCKET_DIR))
+}
+
+/// 连接对端的身份：root daemon 代为读取客户端指定的文件前，按对端自己的权限检查
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Peer {
+    pub uid: u32,
+    /// 主组与附加组
+    pub groups: Vec<u32>,
+}
+
+impl Peer {
+    /// 无法取得对端身份时按此处理：只有“其他用户”权限
+    pub fn anonymous() -> Self {
+        Peer {
+            uid: u32::MAX,
+            groups: Vec::new(),
+        }
+    }
+
+    /// 对端能否读取 path（已解析的绝对路径）：各级目录需有搜索权限，file 为已打开文件的元数据；
+    /// 不考虑 ACL，从严处理
+    pub fn may_read(&self, path: &Path, file: &fs::Metadata) -> bool {
+        if self.uid == 0 {
+            return true;
+        }
+        path.ancestors()
+            .skip(1)
+            .all(|dir| fs::metadata(dir).is_ok_and(|meta| self.allows(&meta, 0o1)))
+            && self.allows(file, 0o4)
+    }
+
+    /// 与内核相同：属主只看属主位，同组只看组位
+    fn allows(&self, meta: &fs::Metadata, bit: u32) -> bool {
+        let shift = if meta.uid() == self.uid {
+            6
+        } else if self.groups.contains(&meta.gid()) {
+            3
+        } else {
+            0
+        };
+        (meta.mode() >> shift) & bit != 0
+    }
+}
+
+/// SO_PEERCRED 与 SO_PEERGROUPS：内核记录的是连接时的身份，对端之后退出或 exec 也不受影响。
+/// 取不到附加组（Linux 4.13 之前）时只算主组
+#[cfg(target_os = "linux")]
+pub fn peer_of(stream: &UnixStream) -> Option<Peer> {
+    use std::ffi::c_void;
+    use std::os::fd::AsRawFd;
+
+    const SOL_SOCKET: i32 = 1;
+    #[cfg(any(target_arch = "powerpc", target_arch = "powerpc64"))]
+    const SO_PEERCRED: i32 = 21;
+    #[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+    const SO_PEERCRED: i32 = 18;
+    #[cfg(not(any(
+        target_arch = "powerpc",
+        target_arch = "powerpc64",
+        target_arch = "mips",
+        target_arch = "mips64"
+    )))]
+    const SO_PEERCRED: i32 = 17;
+    const SO_PEERGROUPS: i32 = 59;
+    const MAX_GROUPS: usize = 65536;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct Ucred {
+        pid: i32,
+        uid: u32,
+        gid: u32,
+    }
+
+    unsafe extern "C" {
+        fn getsockopt(fd: i32, level: i32, name: i32, value: *mut c_void, len: *mut u32) -> i32;
+    }
+
+    let fd = stream.as_raw_fd();
+    let mut cred = Ucred::default();
+    let mut len = size_of::<Ucred>() as u32;
+    // SAFETY: cred 按 struct ucred 布局声明，len 为其大小，指针在调用期间有效
+    let ret = unsafe {
+        getsockopt(
+            fd,
+            SOL_SOCKET,
+            SO_PEERCRED,
+            (&raw mut cred).cast(),
+            &mut len,
+        )
+    };
+    if ret != 0 || len as usize != size_of::<Ucred>() {
+        return None;
+    }
+
+    let mut groups = vec![cred.gid];
+    let mut buf = vec![0u32; 64];
+    loop {
+        let mut len = (buf.len() * size_of::<u32>()) as u32;
+        // SAFETY: buf 有 len 字节可写；缓冲区不够时内核返回 ERANGE 并在 len 中给出所需大小
+        let ret = unsafe {
+            getsockopt(
+                fd,
+                SOL_SOCKET,
+                SO_PEERGROUPS,
+                buf.as_mut_ptr().cast(),
+                &mut len,
+            )
+        };
+        let needed = len as usize / size_of::<u32>();
+        if ret == 0 {
+            groups.extend_from_slice(&buf[..needed.min(buf.len())]);
+            break;
+        }
+        if needed <= buf.len() || needed > MAX_GROUPS {
+            break;
+        }
+        buf.resize(needed, 0);
+    }
+    Some(Peer {
+        uid: cred.uid,
+        groups,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn peer_of(_stream: &UnixStream) -> Option<Peer> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    #[test]
+    fn socket_falls_back_to_next_usable_directory() {
+        let runtime = PathBuf::from("/run/user/1000");
+        assert_eq!(
+            socket_dirs(None, Some(runtime.clone())),
+            [PathBuf::from("/run"), runtime.clone()]
+        );
+        assert_eq!(socket_dirs(None, Some(PathBuf::from("/run"))).len(), 1);
+        assert_eq!(
+            socket_dirs(Some(PathBuf::from("/srv/sock")), Some(runtime)),
+            [PathBuf::from("/srv/sock")]
+        );
+
+        let missing = env::temp_dir().join(format!("logtool-no-such-dir-{}", process::id()));
+        let usable = env::temp_dir().join(format!("logtool-sock-test-{}", process::id()));
+        fs::create_dir_all(&usable).expect("创建目录应成功");
+        let dirs = [missing.clone(), usable.clone()];
+
+        let (_listener, bound) = bind_first(&dirs, SOCKET_NAME).expect("应回退到可用目录");
+        assert_eq!(bound, usable.join(SOCKET_NAME));
+        assert_eq!(find_socket(&dirs, SOCKET_NAME), Some(bound.clone()));
+        let (_stream, connected) = connect_first(&dirs, SOCKET_NAME).expect("应连接到回退目录");
+        assert_eq!(connected, bound);
+
+        let err = match connect_first(std::slice::from_ref(&missing), SOCKET_NAME) {
+            Ok(_) => panic!("不存在的目录不应连接成功"),
+            Err(err) => err,
+        };
+        assert!(err.starts_with(&missing.join(SOCKET_NAME).display().to_string()));
+        assert!(
+            bind_first(&[missing], SOCKET_NAME)
+                .unwrap_err()
+                .contains("目录不存在")
+        );
+        let _ = fs::remove_dir_all(&usable);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn peer_reports_the_connecting_user() {
+        let dir = env::temp_dir().join(format!("logtool-peer-{}", process::id()));
+        fs::create_dir_all(&dir).expect("创建临时目录");
+        let own = fs::metadata(&dir).expect("读取目录");
+
+        let (a, _b) = UnixStream::pair().expect("创建 socketpair");
+        let peer = peer_of(&a).expect("应取得对端身份");
+        assert_eq!(peer.uid, own.uid());
+        assert!(peer.groups.contains(&own.gid()));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn peer_may_read_follows_owner_group_and_other_bits() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = env::temp_dir().join(format!("logtool-peer-mode-{}", process::id()));
+        fs::create_dir_all(&dir).expect("创建临时目录");
+        let path = dir.join("syslog");
+        fs::write(&path, "").expect("写入");
+        let meta = |mode: u32| {
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).expect("设置权限");
+            fs::metadata(&path).expect("读取元数据")
+        };
+        let owner = meta(0o600);
+        let same_user = Peer {
+            uid: owner.uid(),
+            groups: Vec::new(),
+        };
+        let same_group = Peer {
+            uid: owner.uid().wrapping_add(1),
+            groups: vec![owner.gid()],
+        };
+        let other = Peer::anonymous();
+
+        assert!(same_user.may_read(&path, &owner));
+        assert!(!same_group.may_read(&path, &owner));
+        assert!(!other.may_read(&path, &owner));
+
+        let group_readable = meta(0o640);
+        assert!(same_group.may_read(&path, &group_readable));
+        assert!(!other.may_read(&path, &group_readable));
+        assert!(other.may_read(&path, &meta(0o644)));
+        // 属主位没有读权限时，其他位允许也不算（root 不受权限位限制）
+        if owner.uid() != 0 {
+            assert!(!same_user.may_read(&path, &meta(0o044)));
+        }
+        // 所在目录不可搜索时，文件本身可读也不行
+        let readable = meta(0o644);
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700)).expect("设置目录权限");
+        assert!(!other.may_read(&path, &readable));
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).expect("设置目录权限");
+        assert!(other.may_read(&path, &readable));
+        assert!(
+            Peer {
+                uid: 0,
+                groups: Vec::new()
+            }
+            .may_read(&path, &meta(0o000))
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
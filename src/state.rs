@@ -0,0 +1,387 @@
+// 本地状态 — watch 告警记录、可疑来源备注等需要跨进程重启保留的数据
+//
+// 位置：$LOGTOOL_STATE（测试/自定义），否则 /var/lib/logtool/state.json（目录存在时，
+// 供同一台机器上的多个用户共享），否则 $XDG_STATE_HOME/logtool/state.json
+// （默认 ~/.local/state/logtool/state.json）。以原子替换方式写入。
+//
+// 读-改-写（watch 每轮、note、ack）经 State::update 在 `<状态文件>.lock` 锁文件下进行，
+// 避免 cron 触发的多个 watch 与交互使用互相覆盖。锁文件内容为持有者进程号：进程已退出的锁
+// 视为残留并自动清除；持有者仍在运行但迟迟不释放时报错，可用 `logtool --force-unlock` 移除。
+// 清除残留锁在 `<锁文件>.break` 下进行并重新判断一次，两个进程同时发现同一把残留锁时，
+// 后到者不会删掉先到者刚建立的新锁。同一把锁也保护基线文件与包缓存的写入。
+
+use crate::alert::AlertState;
+use crate::write_file_atomically;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+pub const SYSTEM_STATE_DIR: &str = "/var/lib/logtool";
+/// 等待其他进程释放状态锁的上限
+pub const LOCK_WAIT: Duration = Duration::from_secs(5);
+const LOCK_POLL: Duration = Duration::from_millis(20);
+/// 锁文件刚创建、尚未写入进程号的宽限期；超过后仍读不到进程号视为残留
+const LOCK_EMPTY_GRACE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct State {
+    #[serde(default)]
+    pub alerts: AlertState,
+    /// `logtool note`：以 suspect_id 为键，显示在之后的报告中
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub notes: BTreeMap<String, Note>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    pub updated_at: u64,
+}
+
+/// 备注作者：sudo 执行时取原用户
+pub fn current_user() -> Option<String> {
+    ["SUDO_USER", "USER", "LOGNAME"]
+        .iter()
+        .filter_map(|key| env::var(key).ok())
+        .find(|name| !name.is_empty())
+}
+
+impl State {
+    /// 文件不存在时返回空状态
+    pub fn load_from(path: &Path) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(format!("读取状态文件 {} 失败：{err}", path.display())),
+        };
+        serde_json::from_str(&text).map_err(|e| {
+            format!(
+                "解析状态文件 {} 失败：{e}\n修复：检查 JSON 格式，或删除该文件以重置告警状态",
+                path.display()
+            )
+        })
+    }
+
+    /// 持锁读取、修改并写回；change 返回错误时不写回
+    pub fn update<T>(
+        path: &Path,
+        change: impl FnOnce(&mut Self) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let _lock = StateLock::acquire(path, LOCK_WAIT)?;
+        let mut state = Self::load_from(path)?;
+        let result = change(&mut state)?;
+        state.save_to(path)?;
+        Ok(result)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("创建状态目录 {} 失败：{e}", dir.display()))?;
+        }
+        let mut text =
+            serde_json::to_string_pretty(self).map_err(|e| format!("序列化状态失败：{e}"))?;
+        text.push('\n');
+        write_file_atomically(path, text.as_bytes())
+    }
+}
+
+/// 状态文件的锁，离开作用域时删除锁文件
+#[derive(Debug)]
+pub struct StateLock {
+    path: PathBuf,
+}
+
+impl StateLock {
+    pub fn acquire(state_path: &Path, wait: Duration) -> Result<Self, String> {
+        let path = lock_path(state_path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("创建状态目录 {} 失败：{e}", dir.display()))?;
+        }
+        let started = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    writeln!(file, "{}", process::id())
+                        .map_err(|e| format!("写入锁文件 {} 失败：{e}", path.display()))?;
+                    return Ok(Self { path });
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(err) => return Err(format!("创建锁文件 {} 失败：{err}", path.display())),
+            }
+            let holder = fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| content.trim().parse::<u32>().ok());
+            if lock_is_stale(&path) && clear_stale_lock(&path) {
+                continue;
+            }
+            if started.elapsed() >= wait {
+                let holder =
+                    holder.map_or("另一个进程".to_string(), |pid| format!("进程 {pid} "));
+                return Err(format!(
+                    "状态文件 {} 正被{holder}使用（已等待 {} 秒）\n修复：稍后重试；若确认该进程已不在运行，执行 logtool --force-unlock",
+                    state_path.display(),
+                    wait.as_secs()
+                ));
+            }
+            thread::sleep(LOCK_POLL);
+        }
+    }
+}
+
+impl Drop for StateLock {
+    /// 被 --force-unlock 移除后可能已有其他进程重新加锁，只删除自己的锁
+    fn drop(&mut self) {
+        let ours = fs::read_to_string(&self.path)
+            .is_ok_and(|content| content.trim() == process::id().to_string());
+        if ours {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// 持有者进程已退出，或锁文件超过宽限期仍没有进程号；锁文件不存在时为 false
+fn lock_is_stale(path: &Path) -> bool {
+    let Ok(content) = fs::read_to_string(path) else {
+        return false;
+    };
+    match content.trim().parse::<u32>() {
+        Ok(pid) => !process_alive(pid),
+        Err(_) => lock_age(path).is_some_and(|age| age > LOCK_EMPTY_GRACE),
+    }
+}
+
+/// 在 `<锁文件>.break` 下重新确认锁仍是残留后删除；break 文件已被占用时返回 false，由调用方稍后重试
+fn clear_stale_lock(path: &Path) -> bool {
+    let breaker = with_suffix(path, ".break");
+    match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&breaker)
+    {
+        Ok(_) => {}
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            // 清除过程中异常退出留下的 break 文件
+            if lock_age(&breaker).is_some_and(|age| age > LOCK_EMPTY_GRACE) {
+                let _ = fs::remove_file(&breaker);
+            }
+            return false;
+        }
+        Err(_) => return false,
+    }
+    if lock_is_stale(path) {
+        let _ = fs::remove_file(path);
+    }
+    let _ = fs::remove_file(&breaker);
+    true
+}
+
+/// `state.json` → `state.json.lock`
+pub fn lock_path(state_path: &Path) -> PathBuf {
+    with_suffix(state_path, ".lock")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// --force-unlock：删除锁文件；没有锁时返回 None，否则返回 (持有者进程号, 是否仍在运行)
+pub fn force_unlock(state_path: &Path) -> Result<Option<(Option<u32>, bool)>, String> {
+    let path = lock_path(state_path);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(format!("读取锁文件 {} 失败：{err}", path.display())),
+    };
+    fs::remove_file(&path).map_err(|e| format!("删除锁文件 {} 失败：{e}", path.display()))?;
+    let holder = content.trim().parse::<u32>().ok();
+    Ok(Some((holder, holder.is_some_and(process_alive))))
+}
+
+fn lock_age(path: &Path) -> Option<Duration> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    SystemTime::now().duration_since(modified).ok()
+}
+
+/// kill(pid, 0)：进程存在（包括属于其他用户、EPERM）即视为仍在运行
+#[cfg(target_os = "linux")]
+fn process_alive(pid: u32) -> bool {
+    const ESRCH: i32 = 3;
+    unsafe extern "C" {
+        fn kill(pid: i32, sig: i32) -> i32;
+    }
+    let Ok(pid) = i32::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: 信号 0 只做存在性与权限检查，不会投递信号
+    let ret = unsafe { kill(pid, 0) };
+    ret == 0 || io::Error::last_os_error().raw_os_error() != Some(ESRCH)
+}
+
+/// 无法判断时保守地视为仍在运行
+#[cfg(not(target_os = "linux"))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+pub fn state_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("LOGTOOL_STATE").filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let system = Path::new(SYSTEM_STATE_DIR);
+    if system.is_dir() {
+        return Some(system.join("state.json"));
+    }
+    if let Some(dir) = env::var_os("XDG_STATE_HOME").filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(dir).join("logtool").join("state.json"));
+    }
+    env::var_os("HOME")
+        .filter(|v| !v.is_empty())
+        .map(|home| PathBuf::from(home).join(".local/state/logtool/state.json"))
+}
+
+pub fn require_state_path() -> Result<PathBuf, String> {
+    state_path().ok_or_else(|| {
+        "无法确定状态文件位置\n修复：设置 HOME，或通过 LOGTOOL_STATE 指定状态文件路径".to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::AlertRecord;
+
+    #[test]
+    fn state_round_trips_and_missing_file_is_empty() {
+        let dir = env::temp_dir().join(format!("logtool-state-test-{}", std::process::id()));
+        let path = dir.join("nested").join("state.json");
+        assert_eq!(State::load_from(&path), Ok(State::default()));
+
+        let mut state = State::default();
+        state.alerts.records.insert(
+            "unit:ssh.service".to_string(),
+            AlertRecord {
+                last_alert_at: 10,
+                count: 4,
+                priority: 3,
+                last_seen_at: 20,
+                acknowledged_at: Some(15),
+            },
+        );
+        state.notes.insert(
+            "unit:ssh.service".to_string(),
+            Note {
+                text: "known issue, fix scheduled".to_string(),
+                author: Some("alice".to_string()),
+                updated_at: 30,
+            },
+        );
+        state.save_to(&path).expect("应写入成功");
+        assert_eq!(State::load_from(&path), Ok(state));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn state_lock_serializes_updates_and_clears_stale_locks() {
+        let dir = env::temp_dir().join(format!("logtool-lock-test-{}", process::id()));
+        let path = dir.join("state.json");
+        let note = |text: String| Note {
+            text,
+            author: None,
+            updated_at: 0,
+        };
+
+        // 并发更新互不覆盖
+        let writers = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    State::update(&path, |state| {
+                        state
+                            .notes
+                            .insert(format!("unit:u{i}.service"), note(i.to_string()));
+                        Ok(())
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        for writer in writers {
+            writer.join().expect("线程应正常结束").expect("更新应成功");
+        }
+        assert_eq!(State::load_from(&path).expect("读取").notes.len(), 8);
+        assert!(!lock_path(&path).exists());
+
+        // change 出错时不写回
+        let err = State::update(&path, |state| {
+            state.notes.clear();
+            Err::<(), _>("没有备注".to_string())
+        });
+        assert_eq!(err, Err("没有备注".to_string()));
+        assert_eq!(State::load_from(&path).expect("读取").notes.len(), 8);
+
+        // 已退出进程留下的锁自动清除
+        let mut child = process::Command::new("true").spawn().expect("启动 true");
+        let dead_pid = child.id();
+        child.wait().expect("等待 true");
+        fs::write(lock_path(&path), format!("{dead_pid}\n")).expect("写入锁文件");
+        StateLock::acquire(&path, Duration::ZERO).expect("残留锁应被清除");
+
+        // 仍在运行的持有者：超时报错，--force-unlock 可移除
+        let held = StateLock::acquire(&path, Duration::ZERO).expect("应取得锁");
+        let err = StateLock::acquire(&path, Duration::ZERO).expect_err("锁应被占用");
+        assert!(err.contains(&format!("进程 {}", process::id())) && err.contains("--force-unlock"));
+        assert_eq!(force_unlock(&path), Ok(Some((Some(process::id()), true))));
+        assert_eq!(force_unlock(&path), Ok(None));
+        drop(held);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn concurrent_acquirers_clear_a_stale_lock_only_once() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let dir = env::temp_dir().join(format!("logtool-lock-race-{}", process::id()));
+        fs::create_dir_all(&dir).expect("创建临时目录");
+        let path = dir.join("state.json");
+        let mut child = process::Command::new("true").spawn().expect("启动 true");
+        let dead_pid = child.id();
+        child.wait().expect("等待 true");
+        fs::write(lock_path(&path), format!("{dead_pid}\n")).expect("写入锁文件");
+        // 清除过程中异常退出留下的 break 文件超过宽限期后同样作废
+        let breaker = with_suffix(&lock_path(&path), ".break");
+        fs::File::create(&breaker)
+            .and_then(|file| file.set_modified(SystemTime::now() - 2 * LOCK_EMPTY_GRACE))
+            .expect("写入 break 文件");
+
+        let inside = Arc::new(AtomicUsize::new(0));
+        let holders = (0..8)
+            .map(|_| {
+                let (path, inside) = (path.clone(), Arc::clone(&inside));
+                thread::spawn(move || {
+                    let _lock = StateLock::acquire(&path, LOCK_WAIT).expect("应取得锁");
+                    assert_eq!(inside.fetch_add(1, Ordering::SeqCst), 0, "锁应互斥");
+                    thread::sleep(Duration::from_millis(5));
+                    inside.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect::<Vec<_>>();
+        for holder in holders {
+            holder.join().expect("线程应正常结束");
+        }
+        assert!(!lock_path(&path).exists());
+        assert!(!breaker.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
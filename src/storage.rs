@@ -0,0 +1,201 @@
+// 存储错误 — 识别内核记录的磁盘相关错误（ATA 异常、块设备 I/O 错误、EXT4/Btrfs/XFS 错误、NVMe
+// 超时与复位），按设备归为 SourceKind::Storage 来源，报告中作为硬件风险单独标出，不再都算在 kernel 名下
+//
+// 分区归并到整块磁盘（sda1 → sda、nvme0n1p2 → nvme0n1）；ATA 错误只给出端口（ata1），
+// NVMe 控制器错误只给出控制器（nvme0），无法对应到具体磁盘，按原样作为设备名。
+
+/// 报告中对硬件风险的说明
+pub const HARDWARE_RISK: &str = "存储设备报错，可能是磁盘、线缆或控制器故障";
+
+/// 内核消息涉及的存储设备；不是存储错误时为 None
+pub fn storage_device(message: &str) -> Option<String> {
+    let device = ata_port(message)
+        .or_else(|| after(message, "I/O error, dev ", &[',', ' ']))
+        .or_else(|| after(message, "critical medium error, dev ", &[',', ' ']))
+        .or_else(|| after(message, "Buffer I/O error on dev ", &[',', ' ']))
+        .or_else(|| filesystem_error(message))
+        .or_else(|| scsi_failure(message))
+        .or_else(|| nvme_error(message))?;
+    (!device.is_empty()).then(|| whole_disk(device))
+}
+
+/// `ata1.00: exception Emask 0x0 …`、`ata3: hard resetting link`、`ata1.00: failed command: READ FPDMA QUEUED`
+fn ata_port(message: &str) -> Option<&str> {
+    let rest = message.strip_prefix("ata")?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+    if digits == 0 {
+        return None;
+    }
+    let (head, tail) = message.split_once(": ")?;
+    let storage_error = [
+        "exception Emask",
+        "failed command",
+        "hard resetting link",
+        "SError",
+        "status: {",
+        "error: {",
+    ]
+    .iter()
+    .any(|pattern| tail.starts_with(pattern));
+    storage_error.then(|| &head[..3 + digits])
+}
+
+/// `EXT4-fs error (device sda1): …`、`BTRFS error (device dm-0): …`、`BTRFS: error (device sda2) in …`、
+/// `XFS (sdb1): Metadata corruption detected …`
+fn filesystem_error(message: &str) -> Option<&str> {
+    for prefix in [
+        "EXT4-fs error (device ",
+        "EXT3-fs error (device ",
+        "BTRFS error (device ",
+        "BTRFS: error (device ",
+        "BTRFS critical (device ",
+    ] {
+        if let Some(device) = after(message, prefix, &[')', ' ', ':']) {
+            return Some(device);
+        }
+    }
+    let rest = message.strip_prefix("XFS (")?;
+    let (device, detail) = rest.split_once("): ")?;
+    ["corruption", "Corruption", "I/O error"]
+        .iter()
+        .any(|pattern| detail.contains(pattern))
+        .then_some(device)
+}
+
+/// `sd 2:0:0:0: [sdb] tag#0 FAILED Result: …`、`sd 0:0:0:0: [sda] Sense Key : Medium Error`
+fn scsi_failure(message: &str) -> Option<&str> {
+    if !message.starts_with("sd ") {
+        return None;
+    }
+    let device = after(message, "[", &[']'])?;
+    [
+        "FAILED Result",
+        "Medium Error",
+        "Hardware Error",
+        "Unrecovered read error",
+        "timing out command",
+    ]
+    .iter()
+    .any(|pattern| message.contains(pattern))
+    .then_some(device)
+}
+
+/// `nvme nvme0: I/O 123 QID 4 timeout, reset controller`、`nvme nvme0: controller is down; will reset`、
+/// `nvme0n1: I/O Cmd(0x2) @ LBA 1000, 8 blocks, I/O Error (sct 0x2 / sc 0x81)`
+fn nvme_error(message: &str) -> Option<&str> {
+    if let Some(rest) = message.strip_prefix("nvme ") {
+        let (device, detail) = rest.split_once(": ")?;
+        return [
+            "timeout",
+            "reset",
+            "controller is down",
+            "Device not ready",
+            "Removing after probe failure",
+        ]
+        .iter()
+        .any(|pattern| detail.contains(pattern))
+        .then_some(device);
+    }
+    let (device, detail) = message.split_once(": ")?;
+    (device.starts_with("nvme") && detail.contains("I/O Error")).then_some(device)
+}
+
+/// prefix 之后到任一结束字符为止的部分
+fn after<'a>(message: &'a str, prefix: &str, ends: &[char]) -> Option<&'a str> {
+    let start = message.find(prefix)? + prefix.len();
+    let rest = &message[start..];
+    let end = rest.find(|c| ends.contains(&c)).unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+/// 分区 → 所在磁盘：`sda1` → `sda`、`nvme0n1p2` → `nvme0n1`、`mmcblk0p1` → `mmcblk0`
+fn whole_disk(device: &str) -> String {
+    let trimmed = device.trim_end_matches(|c: char| c.is_ascii_digit());
+    let partition = &device[trimmed.len()..];
+    if partition.is_empty() {
+        return device.to_string();
+    }
+    if ["sd", "vd", "xvd", "hd"].iter().any(|prefix| {
+        trimmed.starts_with(prefix)
+            && trimmed[prefix.len()..]
+                .chars()
+                .all(|c| c.is_ascii_lowercase())
+    }) && trimmed.len() > 2
+    {
+        return trimmed.to_string();
+    }
+    if (trimmed.starts_with("nvme") || trimmed.starts_with("mmcblk"))
+        && let Some(disk) = trimmed.strip_suffix('p')
+        && disk.ends_with(|c: char| c.is_ascii_digit())
+    {
+        return disk.to_string();
+    }
+    device.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_disk_errors_by_device() {
+        let cases = [
+            (
+                "ata1.00: exception Emask 0x0 SAct 0x1 SErr 0x0 action 0x0",
+                Some("ata1"),
+            ),
+            ("ata3: hard resetting link", Some("ata3")),
+            ("ata1.00: configured for UDMA/133", None),
+            (
+                "blk_update_request: I/O error, dev sda, sector 2048 op 0x0:(READ)",
+                Some("sda"),
+            ),
+            (
+                "I/O error, dev nvme0n1, sector 1000 op 0x1:(WRITE) flags 0x0",
+                Some("nvme0n1"),
+            ),
+            (
+                "Buffer I/O error on dev sdb1, logical block 0, async page read",
+                Some("sdb"),
+            ),
+            (
+                "EXT4-fs error (device sda1): htree_dirblock_to_tree:1080: inode #2",
+                Some("sda"),
+            ),
+            (
+                "BTRFS error (device dm-0): bdev /dev/mapper/root errs: wr 0, rd 1",
+                Some("dm-0"),
+            ),
+            (
+                "BTRFS: error (device nvme0n1p2) in btrfs_commit_transaction",
+                Some("nvme0n1"),
+            ),
+            (
+                "XFS (sdc1): Metadata corruption detected at xfs_buf_ioend",
+                Some("sdc"),
+            ),
+            ("XFS (sdc1): Mounting V5 Filesystem", None),
+            (
+                "sd 2:0:0:0: [sdb] tag#0 FAILED Result: hostbyte=DID_OK driverbyte=DRIVER_OK",
+                Some("sdb"),
+            ),
+            ("sd 2:0:0:0: [sdb] Attached SCSI disk", None),
+            (
+                "nvme nvme0: I/O 123 QID 4 timeout, reset controller",
+                Some("nvme0"),
+            ),
+            (
+                "nvme0n1: I/O Cmd(0x2) @ LBA 1000, 8 blocks, I/O Error (sct 0x2 / sc 0x81)",
+                Some("nvme0n1"),
+            ),
+            (
+                "mmcblk0p1: I/O error, dev mmcblk0p1, sector 8",
+                Some("mmcblk0"),
+            ),
+            ("usb 1-1: device descriptor read/64, error -71", None),
+        ];
+        for (message, device) in cases {
+            assert_eq!(storage_device(message).as_deref(), device, "{message}");
+        }
+    }
+}
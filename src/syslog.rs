@@ -0,0 +1,676 @@
+// 纯文本 syslog 文件 — `--syslog-file /var/log/syslog` 读取 rsyslog 等写出的文件，逐行按 RFC 5424
+// 或 RFC 3164（BSD）格式解析，转成与 `journalctl --output=json` 相同字段的一行，照常走同一套归类、排行流程
+//
+// 支持 `<PRI>` 前缀（可有可无）、传统的 `Jan  3 08:05:09` 与 RFC 3339 时间戳（rsyslog 高精度格式）。
+// 传统时间戳没有年份与时区：按本地时间，取不晚于现在的最近一年。文件中通常没有 `<PRI>`，这时按消息中的
+// panic / error / warning 等关键词推测级别，其余按 info。单元、UID 等 journal 字段在文件中不存在，
+// 相应的过滤在解析参数时拒绝；无法解析的行计为解析错误。
+//
+// logrotate 轮转出的 `syslog.1`、`kern.log.2.gz` 等也可直接指定：gzip 压缩的文件经 `gzip -dc` 解压，
+// 多个文件按时间戳合并，早于 journal 保留期限的旧事件也能分析。
+
+use crate::socket::Peer;
+use crate::timeutil::{
+    civil_from_unix, days_from_civil, local_utc_offset_secs, resolve_time_spec, unix_now_secs,
+};
+use crate::{Config, JournalEvent};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek};
+use std::iter::Peekable;
+use std::os::unix::fs::OpenOptionsExt;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::rc::Rc;
+
+/// gzip 文件头的前两个字节
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// 打开时不因 FIFO 没有写端而阻塞；对普通文件的读取没有影响
+const O_NONBLOCK: i32 = 0o4000;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// 一行 syslog 解析出的事件；JournalEvent 没有进程号字段，另外保留 tag 中的 PID
+#[derive(Debug, Clone)]
+pub struct SyslogLine {
+    pub event: JournalEvent,
+    pub pid: Option<u32>,
+}
+
+/// 传统时间戳换算所需的“现在”与本地时区
+#[derive(Debug, Clone, Copy)]
+pub struct Clock {
+    pub now_secs: u64,
+    pub offset_secs: i64,
+}
+
+impl Clock {
+    pub fn system() -> Self {
+        Clock {
+            now_secs: unix_now_secs(),
+            offset_secs: local_utc_offset_secs(),
+        }
+    }
+}
+
+pub fn parse_syslog_line(line: &str, clock: Clock) -> Option<SyslogLine> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let (pri, rest) = match line.strip_prefix('<') {
+        Some(body) => {
+            let (digits, rest) = body.split_once('>')?;
+            let pri = digits.parse::<u32>().ok().filter(|p| *p <= 191)?;
+            (Some((pri & 7) as u8), rest)
+        }
+        None => (None, line),
+    };
+    let mut parsed = match rest.strip_prefix("1 ") {
+        Some(body) if pri.is_some() => parse_rfc5424(body)?,
+        _ => parse_rfc3164(rest, clock)?,
+    };
+    let priority = pri.unwrap_or_else(|| infer_priority(&parsed.event.message));
+    parsed.event.priority = Some(priority);
+    Some(parsed)
+}
+
+/// `2024-01-03T08:05:09.003Z host app 1234 ID47 [sd id="1"] 消息`，空值为 `-`
+fn parse_rfc5424(body: &str) -> Option<SyslogLine> {
+    let mut rest = body;
+    let mut next = || {
+        let (field, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+        rest = tail;
+        (field != "-").then(|| field.to_string())
+    };
+    let timestamp = next();
+    let hostname = next();
+    let app = next();
+    let pid = next();
+    let _msgid = next();
+    let message = skip_structured_data(rest)?;
+    let message = message.strip_prefix('\u{feff}').unwrap_or(message);
+    Some(SyslogLine {
+        event: JournalEvent {
+            message: message.to_string(),
+            identifier: app,
+            timestamp_us: timestamp.as_deref().and_then(parse_rfc3339_us),
+            hostname,
+            ..JournalEvent::default()
+        },
+        pid: pid.and_then(|p| p.parse().ok()),
+    })
+}
+
+/// STRUCTURED-DATA 为 `-` 或若干 `[…]`，值中的 `]` 与 `"` 以反斜杠转义
+fn skip_structured_data(rest: &str) -> Option<&str> {
+    if let Some(message) = rest.strip_prefix('-') {
+        return Some(message.strip_prefix(' ').unwrap_or(message));
+    }
+    let bytes = rest.as_bytes();
+    let mut i = 0;
+    while bytes.get(i) == Some(&b'[') {
+        let mut quoted = false;
+        i += 1;
+        loop {
+            match *bytes.get(i)? {
+                b'\\' => i += 1,
+                b'"' => quoted = !quoted,
+                b']' if !quoted => break,
+                _ => {}
+            }
+            i += 1;
+        }
+        i += 1;
+    }
+    let message = &rest[i..];
+    Some(message.strip_prefix(' ').unwrap_or(message))
+}
+
+/// `Jan  3 08:05:09 host sshd[123]: 消息` 或 `2024-01-03T08:05:09.123456+08:00 host kernel: 消息`
+fn parse_rfc3164(line: &str, clock: Clock) -> Option<SyslogLine> {
+    let (timestamp_us, rest) = match line.get(..3).filter(|m| MONTHS.contains(m)) {
+        Some(_) => {
+            let stamp = line.get(..15)?;
+            (parse_legacy_us(stamp, clock), line.get(15..)?.trim_start())
+        }
+        None => {
+            let (stamp, rest) = line.split_once(' ')?;
+            (Some(parse_rfc3339_us(stamp)?), rest)
+        }
+    };
+    let (hostname, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    let (identifier, pid, message) = match rest.split_once(": ") {
+        Some((tag, message)) if !tag.is_empty() && !tag.contains(' ') => {
+            let (name, pid) = match tag.strip_suffix(']').and_then(|t| t.split_once('[')) {
+                Some((name, pid)) => (name, pid.parse().ok()),
+                None => (tag, None),
+            };
+            (Some(name.to_string()), pid, message)
+        }
+        _ => (None, None, rest),
+    };
+    // kern.log 中的内核消息带有 `[   12.345678] ` 形式的启动后时间
+    let message = match identifier.as_deref() {
+        Some("kernel") => strip_uptime_prefix(message),
+        _ => message,
+    };
+    Some(SyslogLine {
+        event: JournalEvent {
+            message: message.to_string(),
+            identifier,
+            timestamp_us,
+            hostname: (!hostname.is_empty()).then(|| hostname.to_string()),
+            ..JournalEvent::default()
+        },
+        pid,
+    })
+}
+
+fn strip_uptime_prefix(message: &str) -> &str {
+    message
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once("] "))
+        .filter(|(uptime, _)| {
+            uptime
+                .trim()
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == '.')
+        })
+        .map_or(message, |(_, rest)| rest)
+}
+
+/// `Jan  3 08:05:09`：本地时间，年份取使结果不晚于现在一天以上的最近一年
+fn parse_legacy_us(stamp: &str, clock: Clock) -> Option<u64> {
+    let month = MONTHS.iter().position(|m| *m == &stamp[..3])? as u32 + 1;
+    let day = stamp.get(4..6)?.trim().parse::<u32>().ok()?;
+    let seconds = clock_seconds(stamp.get(7..15)?)?;
+    let now_local = clock.now_secs as i64 + clock.offset_secs;
+    let this_year = civil_from_unix(now_local).year;
+    [this_year, this_year - 1].into_iter().find_map(|year| {
+        let local = days_from_civil(year, month, day) * 86_400 + seconds;
+        (local <= now_local + 86_400)
+            .then(|| u64::try_from(local - clock.offset_secs).ok())
+            .flatten()
+            .map(|secs| secs * 1_000_000)
+    })
+}
+
+/// `08:05:09` → 当天的秒数
+fn clock_seconds(time: &str) -> Option<i64> {
+    let mut parts = time.split(':').map(|p| p.parse::<i64>().ok());
+    let (hour, minute, second) = (parts.next()??, parts.next()??, parts.next()??);
+    ((0..24).contains(&hour) && (0..60).contains(&minute) && (0..61).contains(&second))
+        .then_some(hour * 3600 + minute * 60 + second)
+}
+
+/// `2024-01-03T08:05:09[.123456](Z|+08:00)` → UTC 微秒
+pub fn parse_rfc3339_us(stamp: &str) -> Option<u64> {
+    let (date, time) = stamp.split_once('T')?;
+    let mut date_parts = date.split('-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (
+        date_parts.next()??,
+        date_parts.next()??,
+        date_parts.next()??,
+    );
+    let (clock, offset_secs) = if let Some(clock) = time.strip_suffix('Z') {
+        (clock, 0)
+    } else {
+        let at = time.rfind(['+', '-'])?;
+        let (hours, minutes) = time[at + 1..].split_once(':')?;
+        let offset = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+        let sign = if time.as_bytes()[at] == b'-' { -1 } else { 1 };
+        (&time[..at], sign * offset)
+    };
+    let (whole, fraction) = clock.split_once('.').unwrap_or((clock, ""));
+    let micros = format!("{fraction:0<6}").get(..6)?.parse::<u64>().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let local = days_from_civil(year, month as u32, day as u32) * 86_400 + clock_seconds(whole)?;
+    let secs = u64::try_from(local - offset_secs).ok()?;
+    Some(secs * 1_000_000 + micros)
+}
+
+/// 没有 `<PRI>` 的行按关键词推测级别：panic/fatal 为严重（2），error/failed 等为错误（3），warn 为警告（4）
+pub fn infer_priority(message: &str) -> u8 {
+    let lower = message.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|word| lower.contains(word));
+    if has(&["kernel panic", "fatal", "critical"]) {
+        2
+    } else if has(&[
+        "error",
+        "failed",
+        "failure",
+        "segfault",
+        "call trace",
+        "oops",
+        "denied",
+        "timed out",
+    ]) {
+        3
+    } else if has(&["warn"]) {
+        4
+    } else {
+        6
+    }
+}
+
+/// 与 journald 相同的字段名，供分析循环按 journal JSON 行处理
+pub fn journal_line(parsed: &SyslogLine) -> String {
+    let event = &parsed.event;
+    let mut fields = serde_json::Map::new();
+    fields.insert("MESSAGE".into(), event.message.clone().into());
+    if let Some(priority) = event.priority {
+        fields.insert("PRIORITY".into(), priority.to_string().into());
+    }
+    if let Some(identifier) = &event.identifier {
+        fields.insert("SYSLOG_IDENTIFIER".into(), identifier.clone().into());
+    }
+    if let Some(pid) = parsed.pid {
+        fields.insert("_PID".into(), pid.to_string().into());
+    }
+    if let Some(hostname) = &event.hostname {
+        fields.insert("_HOSTNAME".into(), hostname.clone().into());
+    }
+    if let Some(timestamp_us) = event.timestamp_us {
+        fields.insert(
+            "__REALTIME_TIMESTAMP".into(),
+            timestamp_us.to_string().into(),
+        );
+    }
+    serde_json::Value::Object(fields).to_string()
+}
+
+/// 按 --since / --until、--priority、--identifier 与 --dmesg 过滤，其余过滤由分析循环照常处理
+#[derive(Clone, Copy)]
+struct LineFilter {
+    since_us: Option<u64>,
+    until_us: Option<u64>,
+}
+
+impl LineFilter {
+    fn new(config: &Config, clock: Clock) -> Result<Self, String> {
+        let resolve = |flag: &str, spec: &Option<String>| {
+            match spec {
+            Some(spec) => resolve_time_spec(spec, clock.now_secs, clock.offset_secs)
+                .map(|secs| Some(secs * 1_000_000))
+                .ok_or_else(|| {
+                    format!(
+                        "--syslog-file 无法换算 {flag} {spec}\n修复：改用 --last 2h，或写成 --since \"2024-01-03 08:00\" / \"2 hours ago\""
+                    )
+                }),
+            None => Ok(None),
+        }
+        };
+        Ok(LineFilter {
+            since_us: resolve("--since", &config.since)?,
+            until_us: resolve("--until", &config.until)?,
+        })
+    }
+
+    fn keeps(&self, event: &JournalEvent, config: &Config) -> bool {
+        let in_window = event.timestamp_us.is_none_or(|ts| {
+            self.since_us.is_none_or(|since| ts >= since)
+                && self.until_us.is_none_or(|until| ts < until)
+        });
+        let identifier = event.identifier.as_deref().unwrap_or_default();
+        in_window
+            && event.priority.is_none_or(|p| config.priority.contains(p))
+            && (config.identifiers.is_empty()
+                || config.identifiers.iter().any(|id| id == identifier))
+            && (!config.kernel_only || identifier == "kernel")
+    }
+}
+
+/// 轮转文件的序号：`syslog` 为 0，`syslog.1` 为 1，`kern.log.2.gz` 为 2
+fn rotation_index(path: &str) -> u32 {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    name.rsplit_once('.')
+        .and_then(|(_, suffix)| suffix.parse().ok())
+        .unwrap_or(0)
+}
+
+/// 轮转文件从旧到新排列（`syslog.3.gz`、`syslog.2.gz`、`syslog.1`、`syslog`），序号相同时按路径
+pub fn sort_rotations(files: &mut [String]) {
+    files.sort_by(|a, b| {
+        rotation_index(b)
+            .cmp(&rotation_index(a))
+            .then_with(|| a.cmp(b))
+    });
+}
+
+/// 打开一个 syslog 文件；gzip 压缩的文件（按文件头判断）经 `gzip -dc` 边解压边读。
+/// daemon 以 root 运行，请求中的路径不可信：只打开普通文件，peer 为 Some 时还要求对端自己能读取
+fn open_syslog_file(path: &str, peer: Option<&Peer>) -> Result<Box<dyn BufRead>, String> {
+    let open_error = |err: io::Error| format!("打开 syslog 文件 {path} 失败：{err}");
+    let not_regular = || format!("--syslog-file 需要普通文件：{path}");
+    let real = fs::canonicalize(path).map_err(open_error)?;
+    // 设备文件在打开时就可能有副作用，先检查再打开；打开后再按实际打开的文件复核
+    if !fs::metadata(&real).map_err(open_error)?.is_file() {
+        return Err(not_regular());
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .custom_flags(O_NONBLOCK)
+        .open(&real)
+        .map_err(open_error)?;
+    let meta = file.metadata().map_err(open_error)?;
+    if !meta.is_file() {
+        return Err(not_regular());
+    }
+    if let Some(peer) = peer
+        && !peer.may_read(&real, &meta)
+    {
+        return Err(format!(
+            "无权读取 syslog 文件 {path}：daemon 按发起请求的用户（uid {}）的权限检查\n\
+             修复：确认该用户能读取此文件，如 sudo usermod -aG adm $USER 后重新登录",
+            peer.uid
+        ));
+    }
+    let mut magic = [0u8; 2];
+    let compressed = file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC;
+    file.rewind().map_err(open_error)?;
+    if !compressed {
+        return Ok(Box::new(BufReader::new(file)));
+    }
+    // 以上检查都在启动 gzip 之前：root 的解压进程只会拿到已检查过的文件描述符
+    let mut child = Command::new("gzip")
+        .arg("-dc")
+        .stdin(file)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| {
+            format!("无法运行 gzip 解压 {path}：{err}\n修复：安装 gzip 软件包，或先用 zcat 解压")
+        })?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| format!("无法获取解压 {path} 的 gzip 标准输出"))?;
+    Ok(Box::new(BufReader::new(Gunzip {
+        path: path.to_string(),
+        child,
+        stdout,
+        finished: false,
+    })))
+}
+
+/// gzip 子进程的输出；读到结尾时检查退出状态，损坏或截断的压缩文件报错而不是悄悄少算
+struct Gunzip {
+    path: String,
+    child: Child,
+    stdout: ChildStdout,
+    finished: bool,
+}
+
+impl Read for Gunzip {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.finished {
+            return Ok(0);
+        }
+        let n = self.stdout.read(buf)?;
+        if n == 0 {
+            self.finished = true;
+            let status = self.child.wait()?;
+            if !status.success() {
+                return Err(io::Error::other(format!(
+                    "解压 {} 失败（gzip {status}），文件可能已损坏或不完整",
+                    self.path
+                )));
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for Gunzip {
+    /// 达到 --max-lines 等提前结束时不留下 gzip 进程
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// 转换后的一行及其时间戳；无法解析的行没有时间戳
+type Entry = (Option<u64>, String);
+
+type Entries = Peekable<Box<dyn Iterator<Item = io::Result<Entry>>>>;
+
+/// 按时间戳合并各文件（每个文件内部有序）：同一日志的各轮转文件、互有重叠的 kern.log 与 syslog
+/// 都按时间先后交给分析循环；时间相同时排在前面的（更旧的轮转文件）先输出
+struct MergeByTime {
+    sources: Vec<Entries>,
+}
+
+impl Iterator for MergeByTime {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut earliest: Option<(usize, u64)> = None;
+        for (index, source) in self.sources.iter_mut().enumerate() {
+            match source.peek() {
+                None => {}
+                Some(Ok((Some(ts), _))) if earliest.is_none_or(|(_, best)| *ts < best) => {
+                    earliest = Some((index, *ts));
+                }
+                Some(Ok((Some(_), _))) => {}
+                // 读取错误与无法解析的行按所在文件中的位置立即输出
+                Some(_) => return source.next().map(|entry| entry.map(|(_, line)| line)),
+            }
+        }
+        let (index, _) = earliest?;
+        self.sources[index]
+            .next()
+            .map(|entry| entry.map(|(_, line)| line))
+    }
+}
+
+/// 读取 --syslog-file 的各文件（含 .gz 轮转文件）并按时间合并，返回 journal JSON 行；
+/// 无法解析的行原样返回，由分析循环计为解析错误
+pub fn read_syslog_files(
+    config: &Config,
+) -> Result<Box<dyn Iterator<Item = io::Result<String>>>, String> {
+    let clock = Clock::system();
+    let filter = LineFilter::new(config, clock)?;
+    let config = Rc::new(config.clone());
+    let mut sources = Vec::new();
+    for path in &config.syslog_files {
+        let reader = open_syslog_file(path, config.peer.as_ref())?;
+        let config = Rc::clone(&config);
+        let entries = reader.lines().filter_map(move |line| {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+            match parse_syslog_line(&line, clock) {
+                Some(parsed) => filter
+                    .keeps(&parsed.event, &config)
+                    .then(|| Ok((parsed.event.timestamp_us, journal_line(&parsed)))),
+                None => Some(Ok((None, line))),
+            }
+        });
+        let entries: Box<dyn Iterator<Item = io::Result<Entry>>> = Box::new(entries);
+        sources.push(entries.peekable());
+    }
+    Ok(Box::new(MergeByTime { sources }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json_event;
+
+    // 2024-01-03 12:00 UTC，本地时区 +08:00
+    const CLOCK: Clock = Clock {
+        now_secs: 1_704_283_200,
+        offset_secs: 8 * 3600,
+    };
+
+    #[test]
+    fn parses_bsd_and_rfc5424_lines() {
+        let bsd = parse_syslog_line(
+            "Jan  3 08:05:09 web1 nginx[812]: connect() failed (111: Connection refused)",
+            CLOCK,
+        )
+        .expect("应解析 RFC 3164 行");
+        assert_eq!(bsd.event.identifier.as_deref(), Some("nginx"));
+        assert_eq!(bsd.pid, Some(812));
+        assert_eq!(bsd.event.hostname.as_deref(), Some("web1"));
+        // 本地 08:05:09 为 UTC 00:05:09
+        assert_eq!(bsd.event.timestamp_us, Some(1_704_240_309_000_000));
+        assert_eq!(bsd.event.priority, Some(3));
+
+        // 12 月的记录早于现在，属于去年
+        let december = parse_syslog_line("Dec 31 23:00:00 web1 cron[1]: job started", CLOCK)
+            .expect("应解析跨年的行");
+        assert_eq!(december.event.timestamp_us, Some(1_704_034_800_000_000));
+        assert_eq!(december.event.priority, Some(6));
+
+        let kernel = parse_syslog_line(
+            "2024-01-03T08:05:09.250000+08:00 web1 kernel: [   12.345678] EXT4-fs warning (device sda1): ext4_end_bio",
+            CLOCK,
+        )
+        .expect("应解析 RFC 3339 时间戳");
+        assert_eq!(
+            kernel.event.message,
+            "EXT4-fs warning (device sda1): ext4_end_bio"
+        );
+        assert_eq!(kernel.event.timestamp_us, Some(1_704_240_309_250_000));
+        assert_eq!(kernel.event.priority, Some(4));
+
+        let rfc5424 = parse_syslog_line(
+            r#"<165>1 2024-01-03T00:05:09.003Z db1 postgres 4242 ID47 [origin ip="10.0.0.1" note="a\]b"] replication slot lost"#,
+            CLOCK,
+        )
+        .expect("应解析 RFC 5424 行");
+        assert_eq!(rfc5424.event.priority, Some(5));
+        assert_eq!(rfc5424.event.identifier.as_deref(), Some("postgres"));
+        assert_eq!(rfc5424.event.message, "replication slot lost");
+        assert_eq!(rfc5424.event.timestamp_us, Some(1_704_240_309_003_000));
+
+        let event = parse_json_event(&journal_line(&bsd)).expect("转换结果应是 journal JSON");
+        assert_eq!(
+            (
+                event.message,
+                event.identifier,
+                event.timestamp_us,
+                event.priority
+            ),
+            (
+                bsd.event.message,
+                bsd.event.identifier,
+                bsd.event.timestamp_us,
+                bsd.event.priority
+            )
+        );
+        assert!(parse_syslog_line("not a syslog line", CLOCK).is_none());
+    }
+
+    #[test]
+    fn rotations_are_merged_in_time_order() {
+        let dir = std::env::temp_dir().join(format!("logtool-rotations-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("创建临时目录应成功");
+        let write = |name: &str, lines: &[&str]| {
+            let path = dir.join(name);
+            std::fs::write(&path, lines.join("\n") + "\n").expect("写入应成功");
+            path.to_string_lossy().into_owned()
+        };
+        let stamp = |minute: u32| format!("2024-01-03T08:{minute:02}:00+08:00 web1");
+        let current = write("syslog", &[&format!("{} app: error 4", stamp(4))]);
+        let first = write("syslog.1", &[&format!("{} app: error 3", stamp(3))]);
+        let kern = write(
+            "kern.log",
+            &[
+                &format!("{} kernel: error 1", stamp(1)),
+                &format!("{} kernel: error 5", stamp(5)),
+            ],
+        );
+        let second = write("syslog.2", &[&format!("{} app: error 2", stamp(2))]);
+        let status = Command::new("gzip")
+            .arg(&second)
+            .status()
+            .expect("运行 gzip 应成功");
+        assert!(status.success());
+        let compressed = format!("{second}.gz");
+
+        let mut files = vec![
+            current.clone(),
+            kern.clone(),
+            compressed.clone(),
+            first.clone(),
+        ];
+        sort_rotations(&mut files);
+        assert_eq!(files, [compressed, first, kern, current]);
+
+        let config = Config {
+            syslog_files: files,
+            since: None,
+            ..Config::default()
+        };
+        let messages = read_syslog_files(&config)
+            .expect("应能打开各文件")
+            .map(|line| parse_json_event(&line.expect("读取应成功")).expect("应为 journal JSON"))
+            .map(|event| event.message)
+            .collect::<Vec<_>>();
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(
+            messages,
+            ["error 1", "error 2", "error 3", "error 4", "error 5"]
+        );
+    }
+    #[test]
+    fn only_regular_files_the_requesting_user_can_read_are_opened() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("logtool-syslog-peer-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("创建临时目录应成功");
+        let fifo = dir.join("fifo");
+        let status = Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .expect("运行 mkfifo 应成功");
+        assert!(status.success());
+        // 没有写端的 FIFO 与设备文件都不打开，也不会卡住
+        for path in [fifo.to_str().unwrap(), "/dev/zero"] {
+            let err = open_syslog_file(path, None).err().expect("应拒绝");
+            assert!(err.contains("普通文件"), "{err}");
+        }
+
+        let secret = dir.join("secret.log");
+        std::fs::write(&secret, "2024-01-03T08:00:00+08:00 web1 app: error\n").expect("写入");
+        std::fs::set_permissions(&secret, std::fs::Permissions::from_mode(0o600))
+            .expect("设置权限");
+        let secret = secret.to_str().unwrap();
+        let err = open_syslog_file(secret, Some(&Peer::anonymous()))
+            .err()
+            .expect("其他用户不应读到");
+        assert!(err.contains("无权读取"), "{err}");
+        let root = Peer {
+            uid: 0,
+            groups: vec![0],
+        };
+        assert!(open_syslog_file(secret, Some(&root)).is_ok());
+        assert!(open_syslog_file(secret, None).is_ok());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn compressed_files_are_checked_before_gzip_runs() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("logtool-syslog-gz-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("创建临时目录应成功");
+        // 只有 gzip 文件头：若先启动了 gzip，报的会是解压失败而不是权限
+        let path = dir.join("syslog.2.gz");
+        std::fs::write(&path, GZIP_MAGIC).expect("写入");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).expect("设置权限");
+
+        let err = open_syslog_file(path.to_str().unwrap(), Some(&Peer::anonymous()))
+            .err()
+            .expect("其他用户不应读到");
+        assert!(err.contains("无权读取"), "{err}");
+        assert!(!err.contains("gzip"), "{err}");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
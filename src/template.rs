@@ -0,0 +1,259 @@
+// 报告模板 — 极简 mustache 风格渲染（--template）
+//
+// 支持的语法：
+//   {{name}} / {{a.b}}        变量（先在当前层查找，再逐层向外）
+//   {{.}}                     当前值
+//   {{#name}}...{{/name}}     数组逐项渲染；对象/真值渲染一次；空值跳过
+//   {{^name}}...{{/name}}     反向区块：值为空/假/空数组时渲染
+//   {{! 注释 }}               注释
+// 输出为纯文本，不做 HTML 转义。
+
+use crate::{AnalyzeResponse, priority_label_cn, source_label_cn, suspect_id};
+use serde_json::{Value, json};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Var(String),
+    Section {
+        name: String,
+        inverted: bool,
+        children: Vec<Node>,
+    },
+}
+
+/// 将分析结果整理为模板上下文：只包含前 top 个可疑来源，并补充排名、标签等派生字段
+pub fn template_context(response: &AnalyzeResponse) -> Value {
+    let suspects = response
+        .suspects
+        .iter()
+        .take(response.top)
+        .enumerate()
+        .map(|(index, suspect)| {
+            let mut item = serde_json::to_value(suspect).unwrap_or(Value::Null);
+            if let Value::Object(map) = &mut item {
+                map.insert("rank".to_string(), json!(index + 1));
+                map.insert(
+                    "id".to_string(),
+                    json!(suspect_id(suspect.kind, &suspect.source)),
+                );
+                map.insert("label".to_string(), json!(source_label_cn(suspect.kind)));
+                map.insert(
+                    "priority_label".to_string(),
+                    json!(priority_label_cn(suspect.worst_priority)),
+                );
+                if let Some(breakdown) = suspect.priority_breakdown() {
+                    map.insert("priority_breakdown".to_string(), json!(breakdown));
+                }
+            }
+            item
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "metrics": response.metrics,
+        "source_count": response.suspects.len(),
+        "top": response.top,
+        "suspects": suspects,
+        "environment": response.environment,
+        "namespace": response.namespace,
+        "timeline": response.timeline,
+        "reboots": response.reboots,
+    })
+}
+
+pub fn render_template(template: &str, data: &Value) -> Result<String, String> {
+    let nodes = parse_template(template)?;
+    let mut out = String::new();
+    let mut stack = vec![data];
+    render_nodes(&nodes, &mut stack, &mut out);
+    Ok(out)
+}
+
+fn parse_template(template: &str) -> Result<Vec<Node>, String> {
+    // 每层：(区块名, 是否反向, 已解析的子节点)
+    let mut stack: Vec<(String, bool, Vec<Node>)> = vec![(String::new(), false, Vec::new())];
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            push_node(&mut stack, Node::Text(rest[..start].to_string()));
+        }
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| "模板标签未闭合：缺少 }}".to_string())?;
+        let tag = after[..end].trim();
+        rest = &after[end + 2..];
+
+        if let Some(name) = tag.strip_prefix('#') {
+            stack.push((name.trim().to_string(), false, Vec::new()));
+        } else if let Some(name) = tag.strip_prefix('^') {
+            stack.push((name.trim().to_string(), true, Vec::new()));
+        } else if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            if stack.len() == 1 {
+                return Err(format!("模板区块 {{{{/{name}}}}} 没有对应的开始标签"));
+            }
+            let (open, inverted, children) = stack.pop().unwrap_or_default();
+            if open != name {
+                return Err(format!(
+                    "模板区块不匹配：{{{{#{open}}}}} 被 {{{{/{name}}}}} 关闭"
+                ));
+            }
+            push_node(
+                &mut stack,
+                Node::Section {
+                    name: open,
+                    inverted,
+                    children,
+                },
+            );
+        } else if tag.starts_with('!') {
+            continue;
+        } else if tag.is_empty() {
+            return Err("模板中存在空标签 {{}}".to_string());
+        } else {
+            push_node(&mut stack, Node::Var(tag.to_string()));
+        }
+    }
+
+    if !rest.is_empty() {
+        push_node(&mut stack, Node::Text(rest.to_string()));
+    }
+    if stack.len() > 1 {
+        let open = stack
+            .last()
+            .map(|(name, ..)| name.clone())
+            .unwrap_or_default();
+        return Err(format!("模板区块 {{{{#{open}}}}} 未闭合"));
+    }
+    Ok(stack.pop().map(|(_, _, nodes)| nodes).unwrap_or_default())
+}
+
+fn push_node(stack: &mut [(String, bool, Vec<Node>)], node: Node) {
+    if let Some((_, _, nodes)) = stack.last_mut() {
+        nodes.push(node);
+    }
+}
+
+fn render_nodes(nodes: &[Node], stack: &mut Vec<&Value>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Var(name) => {
+                if let Some(value) = lookup(stack, name) {
+                    out.push_str(&value_to_text(value));
+                }
+            }
+            Node::Section {
+                name,
+                inverted,
+                children,
+            } => {
+                let value = lookup(stack, name);
+                if *inverted {
+                    if !value.is_some_and(is_truthy) {
+                        render_nodes(children, stack, out);
+                    }
+                    continue;
+                }
+                let Some(value) = value else {
+                    continue;
+                };
+                match value {
+                    Value::Array(items) => {
+                        for item in items {
+                            render_child(children, stack, item, out);
+                        }
+                    }
+                    Value::Object(_) => render_child(children, stack, value, out),
+                    other if is_truthy(other) => render_nodes(children, stack, out),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn render_child<'a>(
+    children: &[Node],
+    stack: &mut Vec<&'a Value>,
+    value: &'a Value,
+    out: &mut String,
+) {
+    stack.push(value);
+    render_nodes(children, stack, out);
+    stack.pop();
+}
+
+fn lookup<'a>(stack: &[&'a Value], name: &str) -> Option<&'a Value> {
+    if name == "." {
+        return stack.last().copied();
+    }
+
+    let mut parts = name.split('.');
+    let first = parts.next()?;
+    let mut current = stack
+        .iter()
+        .rev()
+        .find_map(|ctx| ctx.as_object().and_then(|map| map.get(first)))?;
+    for part in parts {
+        current = current.get(part)?;
+    }
+    Some(current)
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Array(items) => !items.is_empty(),
+        Value::String(s) => !s.is_empty(),
+        Value::Number(n) => n.as_f64().is_some_and(|v| v != 0.0),
+        Value::Object(_) => true,
+    }
+}
+
+fn value_to_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_variables_and_sections() {
+        let data = json!({
+            "title": "报告",
+            "items": [{"name": "a", "n": 1}, {"name": "b", "n": 2}],
+        });
+        let out = render_template("{{title}}:{{#items}} {{name}}={{n}}{{/items}}", &data)
+            .expect("渲染应成功");
+        assert_eq!(out, "报告: a=1 b=2");
+    }
+
+    #[test]
+    fn inverted_section_and_outer_lookup() {
+        let data = json!({"unit": "x", "items": [], "nested": {"k": "v"}});
+        let out = render_template(
+            "{{^items}}无{{/items}}{{#nested}}{{k}}/{{unit}}{{/nested}}{{! 注释 }}",
+            &data,
+        )
+        .expect("渲染应成功");
+        assert_eq!(out, "无v/x");
+    }
+
+    #[test]
+    fn rejects_unbalanced_sections() {
+        let data = json!({});
+        assert!(render_template("{{#a}}x", &data).is_err());
+        assert!(render_template("{{#a}}x{{/b}}", &data).is_err());
+        assert!(render_template("{{a", &data).is_err());
+    }
+}
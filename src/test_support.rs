@@ -0,0 +1,14 @@
+// 测试共用的构造函数 — 各模块测试中的可疑来源都从这里生成，只写与用例有关的字段
+
+use crate::{SourceKind, SourceStats};
+
+/// 只填来源、事件数与最高级别；其余字段按需写成 `SourceStats { package: …, ..sample_suspect(…) }`
+pub(crate) fn sample_suspect(kind: SourceKind, source: &str, count: u64, worst: u8) -> SourceStats {
+    SourceStats {
+        kind,
+        source: source.to_string(),
+        count,
+        worst_priority: worst,
+        ..SourceStats::default()
+    }
+}
@@ -0,0 +1,334 @@
+// 时间线 — `--timeline` 把排行前列来源的首次出现与集中爆发按时间交错排列
+//
+// 分析时按分钟累计每个来源的事件数；非空分钟之间最多隔 1 个空分钟即合并为一段，
+// 事件数达到 BURST_MIN_EVENTS 的段视为爆发，每个来源最多列出 MAX_BURSTS_PER_SOURCE 段。
+// 这样“先出现磁盘错误，接着服务 X 失败，然后 OOM killer”的先后顺序一眼可见。
+
+use crate::timeutil::{format_local_minute, format_local_second};
+use crate::{SourceKind, SourceStats, source_label_cn, suspect_id};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+/// 一段连续活动至少包含这么多事件才算爆发
+pub const BURST_MIN_EVENTS: u64 = 10;
+pub const MAX_BURSTS_PER_SOURCE: usize = 3;
+
+const MINUTE_US: u64 = 60_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineEventKind {
+    FirstSeen,
+    Burst,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    pub kind: TimelineEventKind,
+    pub id: String,
+    pub source_kind: SourceKind,
+    pub source: String,
+    /// 首次出现的时间，或爆发段的起始时间（UTC 微秒；起始分钟，但不早于该来源的首次出现）
+    pub start_us: u64,
+    /// 爆发段的结束分钟（含）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_us: Option<u64>,
+    /// 爆发段内的事件数；首次出现为 1
+    pub count: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u8>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+struct Track {
+    first_us: u64,
+    first_message: String,
+    first_priority: Option<u8>,
+    /// 分钟序号 → 事件数
+    minutes: BTreeMap<u64, u64>,
+}
+
+/// 分析过程中累计各来源的时间分布
+#[derive(Debug, Default)]
+pub struct TimelineTracker {
+    tracks: HashMap<(SourceKind, String), Track>,
+}
+
+impl TimelineTracker {
+    pub fn record(
+        &mut self,
+        kind: SourceKind,
+        source: &str,
+        timestamp_us: u64,
+        message: &str,
+        priority: Option<u8>,
+    ) {
+        let track = self
+            .tracks
+            .entry((kind, source.to_string()))
+            .or_insert_with(|| Track {
+                first_us: u64::MAX,
+                ..Track::default()
+            });
+        if timestamp_us < track.first_us {
+            track.first_us = timestamp_us;
+            track.first_message = message.to_string();
+            track.first_priority = priority;
+        }
+        *track.minutes.entry(timestamp_us / MINUTE_US).or_insert(0) += 1;
+    }
+
+    /// 为排行前 top 的来源生成按时间排序的条目
+    pub fn build(&self, suspects: &[SourceStats], top: usize) -> Vec<TimelineEntry> {
+        let mut entries = Vec::new();
+        for suspect in suspects.iter().take(top) {
+            let Some(track) = self.tracks.get(&(suspect.kind, suspect.source.clone())) else {
+                continue;
+            };
+            let id = suspect_id(suspect.kind, &suspect.source);
+            let entry = |kind, start_us, end_us, count, priority, message: &str| TimelineEntry {
+                kind,
+                id: id.clone(),
+                source_kind: suspect.kind,
+                source: suspect.source.clone(),
+                start_us,
+                end_us,
+                count,
+                priority,
+                message: message.to_string(),
+            };
+            entries.push(entry(
+                TimelineEventKind::FirstSeen,
+                track.first_us,
+                None,
+                1,
+                track.first_priority,
+                &track.first_message,
+            ));
+
+            let mut bursts = minute_runs(&track.minutes)
+                .into_iter()
+                .filter(|run| run.2 >= BURST_MIN_EVENTS)
+                .collect::<Vec<_>>();
+            bursts.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+            bursts.truncate(MAX_BURSTS_PER_SOURCE);
+            for (start, end, count) in bursts {
+                // 首次出现不在整分钟上时，取整到分钟的起点早于它，爆发会排到首次出现之前
+                entries.push(entry(
+                    TimelineEventKind::Burst,
+                    (start * MINUTE_US).max(track.first_us),
+                    Some(end * MINUTE_US),
+                    count,
+                    None,
+                    "",
+                ));
+            }
+        }
+        // 稳定排序：同一时刻同一来源的首次出现排在爆发之前
+        entries.sort_by(|a, b| a.start_us.cmp(&b.start_us).then_with(|| a.id.cmp(&b.id)));
+        entries
+    }
+}
+
+/// 合并相邻分钟：返回 (起始分钟, 结束分钟, 事件数)
+fn minute_runs(minutes: &BTreeMap<u64, u64>) -> Vec<(u64, u64, u64)> {
+    let mut runs: Vec<(u64, u64, u64)> = Vec::new();
+    for (&minute, &count) in minutes {
+        match runs.last_mut() {
+            Some(run) if minute <= run.1 + 2 => {
+                run.1 = minute;
+                run.2 += count;
+            }
+            _ => runs.push((minute, minute, count)),
+        }
+    }
+    runs
+}
+
+pub fn write_timeline_section<W: Write>(
+    out: &mut W,
+    entries: &[TimelineEntry],
+    offset_secs: i64,
+) -> io::Result<()> {
+    for entry in entries {
+        let label = source_label_cn(entry.source_kind);
+        match entry.kind {
+            TimelineEventKind::FirstSeen => writeln!(
+                out,
+                "  {}  首次出现  [{label}] {} | {}",
+                format_local_second(entry.start_us, offset_secs),
+                entry.source,
+                entry.message
+            )?,
+            TimelineEventKind::Burst => writeln!(
+                out,
+                "  {}     爆发      [{label}] {} ×{}（至 {}）",
+                format_local_minute(entry.start_us, offset_secs),
+                entry.source,
+                entry.count,
+                end_clock(entry, offset_secs)
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// 逐句版本（--linear）
+pub fn timeline_sentences(entries: &[TimelineEntry], offset_secs: i64) -> Vec<String> {
+    entries
+        .iter()
+        .map(|entry| {
+            let label = source_label_cn(entry.source_kind);
+            match entry.kind {
+                TimelineEventKind::FirstSeen => format!(
+                    "{}，{label} {} 首次出现：{}。",
+                    format_local_second(entry.start_us, offset_secs),
+                    entry.source,
+                    entry.message
+                ),
+                TimelineEventKind::Burst => format!(
+                    "{} 至 {}，{label} {} 集中出现 {} 条事件。",
+                    format_local_minute(entry.start_us, offset_secs),
+                    end_clock(entry, offset_secs),
+                    entry.source,
+                    entry.count
+                ),
+            }
+        })
+        .collect()
+}
+
+/// 爆发段结束分钟的 `HH:MM`（跨日时显示完整日期）；结束分钟本身也包含在内，故 +1 分钟
+fn end_clock(entry: &TimelineEntry, offset_secs: i64) -> String {
+    let end = entry.end_us.unwrap_or(entry.start_us) + MINUTE_US;
+    let start = format_local_minute(entry.start_us, offset_secs);
+    let end = format_local_minute(end, offset_secs);
+    if start[..10] == end[..10] {
+        end[11..].to_string()
+    } else {
+        end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_suspect;
+
+    #[test]
+    fn interleaves_first_occurrences_and_bursts() {
+        let base = 1_704_069_000_000_000; // 2024-01-01 00:30 UTC
+        let mut tracker = TimelineTracker::default();
+        tracker.record(SourceKind::Kernel, "kernel", base, "I/O error", Some(3));
+        for i in 0..12 {
+            // 00:35 这一分钟内 12 条：一次爆发
+            tracker.record(
+                SourceKind::Unit,
+                "db.service",
+                base + 5 * MINUTE_US + i * 5_000_000,
+                "failed",
+                Some(3),
+            );
+        }
+        tracker.record(
+            SourceKind::Unit,
+            "db.service",
+            base + 60 * MINUTE_US,
+            "failed",
+            Some(3),
+        );
+
+        let suspects = vec![
+            sample_suspect(SourceKind::Unit, "db.service", 0, 3),
+            sample_suspect(SourceKind::Kernel, "kernel", 0, 3),
+        ];
+        let entries = tracker.build(&suspects, 10);
+        let summary = entries
+            .iter()
+            .map(|e| (e.kind, e.id.as_str(), e.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (TimelineEventKind::FirstSeen, "kernel:kernel", 1),
+                (TimelineEventKind::FirstSeen, "unit:db.service", 1),
+                (TimelineEventKind::Burst, "unit:db.service", 12),
+            ]
+        );
+
+        let mut out = Vec::new();
+        write_timeline_section(&mut out, &entries, 8 * 3600).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert!(text.contains("2024-01-01 08:30:00  首次出现  [内核] kernel | I/O error"));
+        assert!(
+            text.contains("2024-01-01 08:35     爆发      [服务单元] db.service ×12（至 08:36）")
+        );
+
+        // 只为排行前 top 的来源生成条目
+        assert_eq!(tracker.build(&suspects, 1).len(), 2);
+    }
+
+    #[test]
+    fn burst_never_precedes_first_occurrence_within_its_minute() {
+        let minute = 1_704_069_300_000_000; // 2024-01-01 00:35 UTC
+        let mut tracker = TimelineTracker::default();
+        tracker.record(
+            SourceKind::Kernel,
+            "kernel",
+            minute + 10_000_000,
+            "I/O error",
+            Some(3),
+        );
+        for i in 0..12 {
+            // 00:35:30 起 12 条，首条不在整分钟上
+            tracker.record(
+                SourceKind::Unit,
+                "db.service",
+                minute + 30_000_000 + i * 2_000_000,
+                "failed",
+                Some(3),
+            );
+        }
+
+        let suspects = vec![
+            sample_suspect(SourceKind::Unit, "db.service", 0, 3),
+            sample_suspect(SourceKind::Kernel, "kernel", 0, 3),
+        ];
+        let entries = tracker.build(&suspects, 10);
+        let summary = entries
+            .iter()
+            .map(|e| (e.kind, e.id.as_str(), e.start_us))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    TimelineEventKind::FirstSeen,
+                    "kernel:kernel",
+                    minute + 10_000_000
+                ),
+                (
+                    TimelineEventKind::FirstSeen,
+                    "unit:db.service",
+                    minute + 30_000_000
+                ),
+                (
+                    TimelineEventKind::Burst,
+                    "unit:db.service",
+                    minute + 30_000_000
+                ),
+            ]
+        );
+        assert_eq!(entries[2].end_us, Some(minute));
+
+        let mut out = Vec::new();
+        write_timeline_section(&mut out, &entries, 8 * 3600).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert!(
+            text.contains("2024-01-01 08:35     爆发      [服务单元] db.service ×12（至 08:36）")
+        );
+    }
+}
@@ -0,0 +1,311 @@
+// 时间工具 — 不依赖第三方库的 UTC/本地时间换算
+//
+// journal 时间戳为 UTC 微秒；本地时区偏移通过 `date +%z` 获取一次，
+// 分析窗口内忽略夏令时切换带来的误差。
+
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 日历时间（已应用时区偏移）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CivilTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    /// 0 = 周日，6 = 周六
+    pub weekday: u32,
+}
+
+/// 将 Unix 秒换算为日历时间（Howard Hinnant 的 civil_from_days 算法）
+pub fn civil_from_unix(secs: i64) -> CivilTime {
+    let days = secs.div_euclid(86_400);
+    let rem = secs.rem_euclid(86_400);
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    CivilTime {
+        year,
+        month,
+        day,
+        hour: (rem / 3600) as u32,
+        minute: (rem % 3600 / 60) as u32,
+        second: (rem % 60) as u32,
+        // 1970-01-01 为周四
+        weekday: (days + 4).rem_euclid(7) as u32,
+    }
+}
+
+/// 日历日期 → 自 1970-01-01 起的天数（civil_from_unix 的逆运算）
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// 把 journalctl 常见的时间写法换算为 Unix 秒：`now`、`today`、`yesterday`、`@1700000000`、
+/// `2 hours ago` / `-2h` / `1h 30min ago`、`2024-01-03`、`2024-01-03 08:05[:09]`（本地时间）。
+/// 其他写法（如 `last monday`）返回 None，journalctl 本身仍能接受
+pub fn resolve_time_spec(spec: &str, now_secs: u64, offset_secs: i64) -> Option<u64> {
+    let spec = spec.trim();
+    let local_midnight = |secs: u64| {
+        let local = secs as i64 + offset_secs;
+        (local - local.rem_euclid(86_400) - offset_secs).max(0) as u64
+    };
+    match spec {
+        "now" => return Some(now_secs),
+        "today" => return Some(local_midnight(now_secs)),
+        "yesterday" => return local_midnight(now_secs).checked_sub(86_400),
+        _ => {}
+    }
+    if let Some(epoch) = spec.strip_prefix('@') {
+        return epoch.parse().ok();
+    }
+    let relative = spec.strip_suffix("ago").or_else(|| spec.strip_prefix('-'));
+    if let Some(span) = relative {
+        return now_secs.checked_sub(parse_time_span(span)?);
+    }
+
+    let (date, time) = spec.split_once([' ', 'T']).unwrap_or((spec, "00:00:00"));
+    let date = date
+        .split('-')
+        .map(str::parse::<i64>)
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    let time = time
+        .split(':')
+        .map(str::parse::<i64>)
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    let ([year, month, day], [hour, minute, second @ ..]) = (date.as_slice(), time.as_slice())
+    else {
+        return None;
+    };
+    let second = match second {
+        [] => 0,
+        [second] => *second,
+        _ => return None,
+    };
+    if !(1..=12).contains(month)
+        || !(1..=31).contains(day)
+        || !(0..24).contains(hour)
+        || !(0..60).contains(minute)
+        || !(0..60).contains(&second)
+    {
+        return None;
+    }
+    let days = days_from_civil(*year, *month as u32, *day as u32);
+    let local = days * 86_400 + hour * 3600 + minute * 60 + second;
+    u64::try_from(local - offset_secs).ok()
+}
+
+/// `2 hours`、`1h 30min`、`45s` → 秒
+fn parse_time_span(span: &str) -> Option<u64> {
+    let compact = span.split_whitespace().collect::<String>();
+    if compact.is_empty() {
+        return None;
+    }
+    let mut total = 0u64;
+    let mut rest = compact.as_str();
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let count: u64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit = match &rest[..unit_len] {
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60,
+            "h" | "hr" | "hour" | "hours" => 3600,
+            "d" | "day" | "days" => 86_400,
+            "w" | "week" | "weeks" => 7 * 86_400,
+            _ => return None,
+        };
+        rest = &rest[unit_len..];
+        total = total.checked_add(count.checked_mul(unit)?)?;
+    }
+    Some(total)
+}
+
+/// 当前 Unix 秒，系统时钟早于 1970 时返回 0
+pub fn unix_now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 当前本地时区相对 UTC 的偏移秒数，获取失败时按 UTC 处理
+pub fn local_utc_offset_secs() -> i64 {
+    Command::new("date")
+        .arg("+%z")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| parse_utc_offset(String::from_utf8_lossy(&out.stdout).trim()))
+        .unwrap_or(0)
+}
+
+/// 解析 `+0800` / `-0530` 形式的时区偏移
+pub fn parse_utc_offset(value: &str) -> Option<i64> {
+    let (sign, digits) = match value.as_bytes().first()? {
+        b'+' => (1, &value[1..]),
+        b'-' => (-1, &value[1..]),
+        _ => return None,
+    };
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i64 = digits[..2].parse().ok()?;
+    let minutes: i64 = digits[2..].parse().ok()?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// journal 微秒时间戳 → 本地日历时间
+pub fn local_civil_from_micros(timestamp_us: u64, offset_secs: i64) -> CivilTime {
+    civil_from_unix((timestamp_us / 1_000_000) as i64 + offset_secs)
+}
+
+/// `2024-01-03 08:05` 形式的本地时间
+pub fn format_local_minute(timestamp_us: u64, offset_secs: i64) -> String {
+    let t = local_civil_from_micros(timestamp_us, offset_secs);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        t.year, t.month, t.day, t.hour, t.minute
+    )
+}
+
+/// `2024-01-03 08:05:09` 形式的本地时间
+pub fn format_local_second(timestamp_us: u64, offset_secs: i64) -> String {
+    let t = local_civil_from_micros(timestamp_us, offset_secs);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        t.year, t.month, t.day, t.hour, t.minute, t.second
+    )
+}
+
+/// `3 天 2 小时`、`7 小时 12 分`、`45 秒` 形式的时长，只保留最大的两级
+pub fn format_duration_cn(secs: u64) -> String {
+    let units = [(86_400, "天"), (3600, "小时"), (60, "分"), (1, "秒")];
+    let parts = units
+        .iter()
+        .scan(secs, |rest, &(size, name)| {
+            let value = *rest / size;
+            *rest %= size;
+            Some((value, name))
+        })
+        .skip_while(|(value, _)| *value == 0)
+        .take(2)
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, name)| format!("{value} {name}"))
+        .collect::<Vec<_>>();
+    if parts.is_empty() {
+        "0 秒".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+/// `2024-01-03T08:05:09Z` 形式的 UTC 时间（RFC 3339）
+pub fn format_utc_rfc3339(timestamp_us: u64) -> String {
+    let t = civil_from_unix((timestamp_us / 1_000_000) as i64);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        t.year, t.month, t.day, t.hour, t.minute, t.second
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn civil_from_unix_handles_epoch_and_leap_day() {
+        let epoch = civil_from_unix(0);
+        assert_eq!((epoch.year, epoch.month, epoch.day), (1970, 1, 1));
+        assert_eq!(epoch.weekday, 4);
+
+        // 2024-02-29 13:45:10 UTC
+        let leap = civil_from_unix(1_709_214_310);
+        assert_eq!((leap.year, leap.month, leap.day), (2024, 2, 29));
+        assert_eq!((leap.hour, leap.minute, leap.second), (13, 45, 10));
+        assert_eq!(leap.weekday, 4);
+    }
+
+    #[test]
+    fn parses_utc_offsets() {
+        assert_eq!(parse_utc_offset("+0800"), Some(8 * 3600));
+        assert_eq!(parse_utc_offset("-0530"), Some(-(5 * 3600 + 30 * 60)));
+        assert_eq!(parse_utc_offset("0800"), None);
+        assert_eq!(parse_utc_offset("+08"), None);
+    }
+
+    #[test]
+    fn formats_local_minute_with_offset() {
+        // 2024-01-01 00:30 UTC → 08:30 CST
+        let text = format_local_minute(1_704_069_000_000_000, 8 * 3600);
+        assert_eq!(text, "2024-01-01 08:30");
+        let text = format_local_second(1_704_069_009_000_000, 8 * 3600);
+        assert_eq!(text, "2024-01-01 08:30:09");
+    }
+
+    #[test]
+    fn resolves_common_time_specs() {
+        let now = 1_704_069_000; // 2024-01-01 00:30 UTC，即 08:30 CST
+        let cst = 8 * 3600;
+        assert_eq!(days_from_civil(2024, 2, 29), 19_782);
+        assert_eq!(resolve_time_spec("now", now, cst), Some(now));
+        assert_eq!(resolve_time_spec("2 hours ago", now, cst), Some(now - 7200));
+        assert_eq!(resolve_time_spec("30 min ago", now, cst), Some(now - 1800));
+        assert_eq!(resolve_time_spec("-1h 30min", now, cst), Some(now - 5400));
+        assert_eq!(
+            resolve_time_spec("@1700000000", now, cst),
+            Some(1_700_000_000)
+        );
+        // 本地 2024-01-01 00:00 CST = 2023-12-31 16:00 UTC
+        assert_eq!(resolve_time_spec("today", now, cst), Some(1_704_038_400));
+        assert_eq!(
+            resolve_time_spec("yesterday", now, cst),
+            Some(1_703_952_000)
+        );
+        assert_eq!(
+            resolve_time_spec("2024-01-01", now, cst),
+            Some(1_704_038_400)
+        );
+        assert_eq!(resolve_time_spec("2024-01-01 08:30", now, cst), Some(now));
+        assert_eq!(
+            resolve_time_spec("2024-01-01T08:30:09", now, 0),
+            Some(now + 8 * 3600 + 9)
+        );
+        for unknown in ["last monday", "2 fortnights ago", "2024-13-01", "ago"] {
+            assert_eq!(resolve_time_spec(unknown, now, cst), None, "{unknown}");
+        }
+    }
+
+    #[test]
+    fn formats_duration_with_two_largest_units() {
+        assert_eq!(format_duration_cn(0), "0 秒");
+        assert_eq!(format_duration_cn(45), "45 秒");
+        assert_eq!(format_duration_cn(7 * 3600 + 12 * 60 + 5), "7 小时 12 分");
+        assert_eq!(format_duration_cn(3 * 86_400 + 30), "3 天");
+    }
+}
@@ -0,0 +1,307 @@
+// 趋势对比 — `--compare-previous` 用同样的过滤条件再查一次紧挨着的上一个等长窗口，
+// 给出前列来源的变化（新出现、增加、减少、已消失），回答“上次部署之后是不是更糟了”
+//
+// 当前窗口由 --since / --until（缺省为现在）换算成绝对时间，上一窗口为 [since - 长度, since)，
+// 以 `@Unix 秒` 交给 journalctl。无法换算的时间写法（如 `last monday`）在解析参数时报错。
+
+use crate::options::display_width;
+use crate::timeutil::{format_duration_cn, format_local_minute, resolve_time_spec};
+use crate::{Config, SourceKind, SourceStats, source_label_cn, suspect_id};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrendKind {
+    New,
+    Increased,
+    Decreased,
+    Resolved,
+    Unchanged,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuspectTrend {
+    pub id: String,
+    pub source_kind: SourceKind,
+    pub source: String,
+    pub kind: TrendKind,
+    pub previous: u64,
+    pub current: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrendReport {
+    /// 上一窗口 [since, until)（UTC 微秒）
+    pub previous_since_us: u64,
+    pub previous_until_us: u64,
+    pub previous_matched: usize,
+    /// 当前前 top 个来源按排名，之后是上一窗口前 top 个中本窗口已消失的来源
+    pub changes: Vec<SuspectTrend>,
+}
+
+/// 当前窗口 (since, until)，Unix 秒；flag 为需要窗口的选项，用于错误提示
+pub fn current_window(
+    config: &Config,
+    flag: &str,
+    now_secs: u64,
+    offset_secs: i64,
+) -> Result<(u64, u64), String> {
+    let hint = "修复：改用 --last 2h，或写成 --since \"2024-01-03 08:00\" / \"2 hours ago\"";
+    let Some(since) = &config.since else {
+        return Err(format!("{flag} 需要时间窗口起点\n{hint}"));
+    };
+    let start = resolve_time_spec(since, now_secs, offset_secs)
+        .ok_or_else(|| format!("{flag} 无法换算 --since {since}\n{hint}"))?;
+    let end = match &config.until {
+        Some(until) => resolve_time_spec(until, now_secs, offset_secs)
+            .ok_or_else(|| format!("{flag} 无法换算 --until {until}\n{hint}"))?,
+        None => now_secs,
+    };
+    if end <= start {
+        return Err(format!(
+            "{flag} 的窗口为空：--since {since} 不早于结束时间\n{hint}"
+        ));
+    }
+    Ok((start, end))
+}
+
+/// 上一窗口的查询：只保留计数所需的部分，不再生成时间线、聚类等附加内容
+pub fn previous_window_config(config: &Config, since: u64, until: u64) -> Config {
+    Config {
+        since: Some(format!("@{since}")),
+        until: Some(format!("@{until}")),
+        compare_previous: false,
+        with_failed_units: false,
+        smart: false,
+        crashes: false,
+        hangs: false,
+        network: false,
+        hardware: false,
+        audit: false,
+        by_package: false,
+        by_user: false,
+        per_boot: false,
+        timeline: false,
+        histogram: None,
+        bursts: false,
+        samples: None,
+        clusters: None,
+        evidence_dir: None,
+        boot_history: false,
+        reliability: false,
+        env_snapshot: false,
+        ..config.clone()
+    }
+}
+
+pub fn compare_suspects(
+    current: &[SourceStats],
+    previous: &[SourceStats],
+    top: usize,
+) -> Vec<SuspectTrend> {
+    let previous_counts = previous
+        .iter()
+        .map(|s| ((s.kind, s.source.as_str()), s.count))
+        .collect::<HashMap<_, _>>();
+    let current_counts = current
+        .iter()
+        .map(|s| ((s.kind, s.source.as_str()), s.count))
+        .collect::<HashMap<_, _>>();
+    let trend = |suspect: &SourceStats, previous: u64, current: u64| {
+        let kind = match (previous, current) {
+            (0, _) => TrendKind::New,
+            (_, 0) => TrendKind::Resolved,
+            (p, c) if c > p => TrendKind::Increased,
+            (p, c) if c < p => TrendKind::Decreased,
+            _ => TrendKind::Unchanged,
+        };
+        SuspectTrend {
+            id: suspect_id(suspect.kind, &suspect.source),
+            source_kind: suspect.kind,
+            source: suspect.source.clone(),
+            kind,
+            previous,
+            current,
+        }
+    };
+    let mut changes = current
+        .iter()
+        .take(top)
+        .map(|s| {
+            let previous = previous_counts
+                .get(&(s.kind, s.source.as_str()))
+                .copied()
+                .unwrap_or(0);
+            trend(s, previous, s.count)
+        })
+        .collect::<Vec<_>>();
+    changes.extend(
+        previous
+            .iter()
+            .take(top)
+            .filter(|s| !current_counts.contains_key(&(s.kind, s.source.as_str())))
+            .map(|s| trend(s, s.count, 0)),
+    );
+    changes
+}
+
+fn kind_label(kind: TrendKind) -> &'static str {
+    match kind {
+        TrendKind::New => "新出现",
+        TrendKind::Increased => "增加",
+        TrendKind::Decreased => "减少",
+        TrendKind::Resolved => "已消失",
+        TrendKind::Unchanged => "持平",
+    }
+}
+
+fn delta(change: &SuspectTrend) -> String {
+    match change.current.cmp(&change.previous) {
+        std::cmp::Ordering::Greater => format!("+{}", change.current - change.previous),
+        std::cmp::Ordering::Less => format!("-{}", change.previous - change.current),
+        std::cmp::Ordering::Equal => "±0".to_string(),
+    }
+}
+
+fn window_label(report: &TrendReport, offset_secs: i64) -> String {
+    format!(
+        "{} 至 {}（{}）",
+        format_local_minute(report.previous_since_us, offset_secs),
+        format_local_minute(report.previous_until_us, offset_secs),
+        format_duration_cn((report.previous_until_us - report.previous_since_us) / 1_000_000)
+    )
+}
+
+pub fn write_trend_section<W: Write>(
+    out: &mut W,
+    report: &TrendReport,
+    matched: usize,
+    offset_secs: i64,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "  上一窗口：{}，共 {} 条；本窗口 {} 条",
+        window_label(report, offset_secs),
+        report.previous_matched,
+        matched
+    )?;
+    for change in &report.changes {
+        write_change_line(out, change)?;
+    }
+    Ok(())
+}
+
+/// `  增加    [服务单元] web.service  1 → 40（+39）`，logtool diff 也用这一格式
+pub(crate) fn write_change_line<W: Write>(out: &mut W, change: &SuspectTrend) -> io::Result<()> {
+    let label = kind_label(change.kind);
+    writeln!(
+        out,
+        "  {label}{}  [{}] {}  {} → {}（{}）",
+        " ".repeat(6 - display_width(label)),
+        source_label_cn(change.source_kind),
+        change.source,
+        change.previous,
+        change.current,
+        delta(change)
+    )
+}
+
+/// 逐句版本（--linear）
+pub fn trend_sentences(report: &TrendReport, matched: usize, offset_secs: i64) -> Vec<String> {
+    let mut sentences = vec![format!(
+        "与上一窗口 {} 对比：上一窗口 {} 条，本窗口 {} 条。",
+        window_label(report, offset_secs),
+        report.previous_matched,
+        matched
+    )];
+    sentences.extend(report.changes.iter().map(|change| {
+        format!(
+            "{} {} {}，上一窗口 {} 条，本窗口 {} 条。",
+            source_label_cn(change.source_kind),
+            change.source,
+            kind_label(change.kind),
+            change.previous,
+            change.current
+        )
+    }));
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_suspect;
+
+    #[test]
+    fn classifies_changes_against_the_previous_window() {
+        let current = [
+            sample_suspect(SourceKind::Unit, "web.service", 40, 3),
+            sample_suspect(SourceKind::Unit, "db.service", 3, 3),
+            sample_suspect(SourceKind::Unit, "cron.service", 2, 3),
+            sample_suspect(SourceKind::Unit, "tail.service", 1, 3),
+        ];
+        let previous = [
+            sample_suspect(SourceKind::Unit, "db.service", 9, 3),
+            sample_suspect(SourceKind::Unit, "old.service", 7, 3),
+            sample_suspect(SourceKind::Unit, "cron.service", 2, 3),
+            sample_suspect(SourceKind::Unit, "web.service", 1, 3),
+        ];
+        let changes = compare_suspects(&current, &previous, 3);
+        let summary = changes
+            .iter()
+            .map(|c| (c.source.as_str(), c.kind, c.previous, c.current))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("web.service", TrendKind::Increased, 1, 40),
+                ("db.service", TrendKind::Decreased, 9, 3),
+                ("cron.service", TrendKind::Unchanged, 2, 2),
+                ("old.service", TrendKind::Resolved, 7, 0),
+            ]
+        );
+
+        let report = TrendReport {
+            previous_since_us: 1_704_061_800_000_000, // 2023-12-31 22:30 UTC
+            previous_until_us: 1_704_069_000_000_000,
+            previous_matched: 19,
+            changes,
+        };
+        let mut out = Vec::new();
+        write_trend_section(&mut out, &report, 46, 0).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert!(text.contains(
+            "上一窗口：2023-12-31 22:30 至 2024-01-01 00:30（2 小时），共 19 条；本窗口 46 条"
+        ));
+        assert!(text.contains("  增加    [服务单元] web.service  1 → 40（+39）"));
+        assert!(text.contains("  已消失  [服务单元] old.service  7 → 0（-7）"));
+        assert_eq!(
+            trend_sentences(&report, 46, 0)[4],
+            "服务单元 old.service 已消失，上一窗口 7 条，本窗口 0 条。"
+        );
+    }
+
+    #[test]
+    fn previous_window_has_equal_length() {
+        let now = 1_704_069_000;
+        let config = Config {
+            since: Some("2 hours ago".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            current_window(&config, "--compare-previous", now, 0),
+            Ok((now - 7200, now))
+        );
+        let previous = previous_window_config(&config, now - 14_400, now - 7200);
+        assert_eq!(previous.since.as_deref(), Some("@1704054600"));
+        assert_eq!(previous.until.as_deref(), Some("@1704061800"));
+
+        let config = Config {
+            since: Some("last monday".to_string()),
+            ..Config::default()
+        };
+        assert!(current_window(&config, "--compare-previous", now, 0).is_err());
+    }
+}
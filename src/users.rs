@@ -0,0 +1,334 @@
+// 按用户汇总 — `--by-user` 按事件的 _UID 统计每个用户的进程与会话产生了多少事件、主要来自哪些来源，
+// 回答多用户终端服务器上“是谁的程序在报错”
+//
+// 统计在分析循环中逐条进行，与来源排行互不影响：同一来源（如 python3）可以分属多个用户。
+// _UID 由 journald 根据发送进程填写，内核消息等没有 _UID 的事件单独计数。系统服务多以 root
+// 或各自的服务账户运行，同样列出。用户名经 `getent passwd` 解析（与 NSS/LDAP 一致），查不到时只给 UID。
+
+use crate::redact::{Redactor, USER_MASK};
+use crate::{SourceKind, priority_label_cn, suspect_id};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// 每个用户列出的来源数
+pub const MAX_USER_SOURCES: usize = 3;
+/// 普通用户的 UID 范围（Debian/Ubuntu 的 FIRST_UID..=LAST_UID）
+const REGULAR_UIDS: std::ops::RangeInclusive<u32> = 1000..=59999;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserSource {
+    pub id: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserSummary {
+    pub uid: u32,
+    /// 用户名；getent 查不到时为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    pub count: u64,
+    pub worst_priority: u8,
+    /// 该用户事件最多的来源，按事件数降序，最多 MAX_USER_SOURCES 个
+    pub sources: Vec<UserSource>,
+    /// 该用户涉及的来源总数
+    pub source_count: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserView {
+    /// 按事件数降序，最多 top 个
+    pub users: Vec<UserSummary>,
+    /// 没有 _UID 的事件数（内核消息等）
+    pub without_uid: u64,
+}
+
+impl UserView {
+    /// --redact：用户名经 getent 解析，不一定在本机 /etc/passwd 中，整个替换为 `<user>`，仍按 UID 分列；
+    /// 来源中的路径（如 exe:/home/alice/bin/job）与主消息一样遮盖
+    pub fn redact(&mut self, redactor: &Redactor) {
+        for summary in &mut self.users {
+            if summary.user.is_some() {
+                summary.user = Some(USER_MASK.to_string());
+            }
+            for source in &mut summary.sources {
+                source.id = redactor.redact(&source.id);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct UserCounter {
+    count: u64,
+    worst_priority: u8,
+    sources: HashMap<(SourceKind, String), u64>,
+}
+
+/// 分析循环中逐条记录事件所属的用户与来源
+#[derive(Default)]
+pub struct UserTracker {
+    users: HashMap<u32, UserCounter>,
+    without_uid: u64,
+}
+
+impl UserTracker {
+    pub fn record(
+        &mut self,
+        uid: Option<u32>,
+        kind: SourceKind,
+        source: &str,
+        priority: Option<u8>,
+    ) {
+        let Some(uid) = uid else {
+            self.without_uid += 1;
+            return;
+        };
+        let counter = self.users.entry(uid).or_insert_with(|| UserCounter {
+            worst_priority: 7,
+            ..UserCounter::default()
+        });
+        counter.count += 1;
+        if let Some(p) = priority {
+            counter.worst_priority = counter.worst_priority.min(p);
+        }
+        *counter
+            .sources
+            .entry((kind, source.to_string()))
+            .or_default() += 1;
+    }
+
+    /// 取前 top 个用户并解析用户名
+    pub fn finish(self, top: usize) -> UserView {
+        let mut users = self
+            .users
+            .into_iter()
+            .map(|(uid, counter)| {
+                let mut sources = counter
+                    .sources
+                    .iter()
+                    .map(|((kind, source), count)| UserSource {
+                        id: suspect_id(*kind, source),
+                        count: *count,
+                    })
+                    .collect::<Vec<_>>();
+                sources.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.id.cmp(&b.id)));
+                sources.truncate(MAX_USER_SOURCES);
+                UserSummary {
+                    uid,
+                    user: None,
+                    count: counter.count,
+                    worst_priority: counter.worst_priority,
+                    sources,
+                    source_count: counter.sources.len(),
+                }
+            })
+            .collect::<Vec<_>>();
+        users.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then(a.worst_priority.cmp(&b.worst_priority))
+                .then(a.uid.cmp(&b.uid))
+        });
+        users.truncate(top);
+        let names = lookup_user_names(users.iter().map(|u| u.uid));
+        for summary in &mut users {
+            summary.user = names.get(&summary.uid).cloned();
+        }
+        UserView {
+            users,
+            without_uid: self.without_uid,
+        }
+    }
+}
+
+/// 一次 `getent passwd <uid>…` 查询全部用户名；部分 UID 查不到时 getent 以 2 退出，但仍输出其余条目
+fn lookup_user_names(uids: impl Iterator<Item = u32>) -> HashMap<u32, String> {
+    let uids = uids.map(|uid| uid.to_string()).collect::<Vec<_>>();
+    if uids.is_empty() {
+        return HashMap::new();
+    }
+    Command::new("getent")
+        .arg("passwd")
+        .args(&uids)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .map(|output| parse_passwd_names(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+/// `alice:x:1000:1000:Alice,,,:/home/alice:/bin/bash` → 1000 → alice
+pub fn parse_passwd_names(text: &str) -> HashMap<u32, String> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next().filter(|name| !name.is_empty())?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+/// `alice（UID 1000）`；系统账户注明，便于与普通用户区分
+fn user_label(summary: &UserSummary) -> String {
+    let kind = if REGULAR_UIDS.contains(&summary.uid) {
+        ""
+    } else {
+        "，系统账户"
+    };
+    match &summary.user {
+        Some(user) => format!("{user}（UID {}{kind}）", summary.uid),
+        None => format!("UID {}（未知用户{kind}）", summary.uid),
+    }
+}
+
+/// `unit:session-4.scope（80）、exe:/usr/bin/python3（40） 等 5 个来源`
+fn source_list(summary: &UserSummary) -> String {
+    let mut text = summary
+        .sources
+        .iter()
+        .map(|source| format!("{}（{}）", source.id, source.count))
+        .collect::<Vec<_>>()
+        .join("、");
+    if summary.source_count > summary.sources.len() {
+        text.push_str(&format!(" 等 {} 个来源", summary.source_count));
+    }
+    text
+}
+
+pub fn write_user_section<W: Write>(out: &mut W, view: &UserView) -> io::Result<()> {
+    for summary in &view.users {
+        writeln!(
+            out,
+            "  ×{} {}  最高严重级别={}({})  来源：{}",
+            summary.count,
+            user_label(summary),
+            summary.worst_priority,
+            priority_label_cn(summary.worst_priority),
+            source_list(summary)
+        )?;
+    }
+    if view.without_uid > 0 {
+        writeln!(
+            out,
+            "  另有 {} 条事件没有 UID（内核消息等）",
+            view.without_uid
+        )?;
+    }
+    Ok(())
+}
+
+/// 逐句版本（--linear）
+pub fn user_sentences(view: &UserView) -> Vec<String> {
+    let mut sentences = view
+        .users
+        .iter()
+        .map(|summary| {
+            format!(
+                "用户 {} 共 {} 条事件，最高严重级别为{}（{}），来自 {}。",
+                user_label(summary),
+                summary.count,
+                priority_label_cn(summary.worst_priority),
+                summary.worst_priority,
+                source_list(summary)
+            )
+        })
+        .collect::<Vec<_>>();
+    if view.without_uid > 0 {
+        sentences.push(format!(
+            "另有 {} 条事件没有 UID（内核消息等）。",
+            view.without_uid
+        ));
+    }
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_events_per_uid() {
+        let mut tracker = UserTracker::default();
+        for _ in 0..3 {
+            tracker.record(
+                Some(1000),
+                SourceKind::Executable,
+                "/usr/bin/python3",
+                Some(3),
+            );
+        }
+        tracker.record(Some(1000), SourceKind::Unit, "session-4.scope", Some(2));
+        tracker.record(
+            Some(1001),
+            SourceKind::Executable,
+            "/usr/bin/python3",
+            Some(4),
+        );
+        tracker.record(None, SourceKind::Kernel, "kernel", Some(3));
+        let mut view = tracker.finish(10);
+        assert_eq!(view.without_uid, 1);
+        assert_eq!(
+            view.users
+                .iter()
+                .map(|u| (u.uid, u.count, u.worst_priority))
+                .collect::<Vec<_>>(),
+            [(1000, 4, 2), (1001, 1, 4)]
+        );
+
+        view.users[0].user = Some("alice".to_string());
+        let mut out = Vec::new();
+        write_user_section(&mut out, &view).expect("写入应成功");
+        let text = String::from_utf8(out).expect("UTF-8");
+        assert!(text.starts_with(
+            "  ×4 alice（UID 1000）  最高严重级别=2(严重)  来源：exe:/usr/bin/python3（3）、unit:session-4.scope（1）\n"
+        ));
+        assert!(text.ends_with("  另有 1 条事件没有 UID（内核消息等）\n"));
+
+        view.users[1].uid = 113;
+        assert_eq!(
+            user_sentences(&view)[1],
+            "用户 UID 113（未知用户，系统账户） 共 1 条事件，最高严重级别为警告（4），来自 exe:/usr/bin/python3（1）。"
+        );
+
+        let names = parse_passwd_names(
+            "root:x:0:0:root:/root:/bin/bash\nalice:x:1000:1000:Alice,,,:/home/alice:/bin/bash\n",
+        );
+        assert_eq!(names.get(&1000).map(String::as_str), Some("alice"));
+        assert_eq!(names.get(&0).map(String::as_str), Some("root"));
+    }
+
+    #[test]
+    fn redact_masks_user_names_but_keeps_uid_buckets() {
+        let mut tracker = UserTracker::default();
+        tracker.record(
+            Some(1000),
+            SourceKind::Executable,
+            "/home/alice/bin/job",
+            Some(3),
+        );
+        tracker.record(Some(1001), SourceKind::Unit, "session-4.scope", Some(4));
+        let mut view = tracker.finish(10);
+        view.users[0].user = Some("alice".to_string());
+        view.users[1].user = Some("ldap-bob".to_string());
+
+        view.redact(&Redactor::with_identity(
+            Vec::new(),
+            vec!["alice".to_string()],
+        ));
+        let text = user_sentences(&view).join("\n");
+        assert!(
+            !text.contains("alice") && !text.contains("ldap-bob"),
+            "{text}"
+        );
+        assert!(text.contains("<user>（UID 1000）"), "{text}");
+        assert!(text.contains("exe:/home/<user>/bin/job（1）"), "{text}");
+        assert_eq!(
+            view.users.iter().map(|u| u.uid).collect::<Vec<_>>(),
+            [1000, 1001]
+        );
+    }
+}
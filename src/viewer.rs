@@ -0,0 +1,371 @@
+// 只读查看接口 — 供状态栏、桌面小部件等无 journal 权限的程序显示系统健康状况
+//
+// daemon 在 VIEWER_SOCKET_PATH 上另开一个所有人可连接（0666）的 socket：连接后立即写出一行
+// ViewerSnapshot JSON 并关闭，不读取任何请求，也从不启动 journalctl。
+// 快照只来自已缓存的内容：最近一次不带来源/关键词过滤的分析摘要（Digest）、据此给出的健康结论，
+// 以及最近 TREND_POINTS 次此类分析的事件数走势。摘要只含计数与来源标识，不含日志消息。
+// `logtool status` 读取同一份快照，压缩为一行文字或 StatusDocument，供状态栏每几秒轮询。
+
+use crate::timeutil::format_duration_cn;
+use crate::{AnalyzeResponse, Config, suspect_id};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+pub const VIEWER_SOCKET_PATH: &str = "/run/logtool-viewer.sock";
+/// 与主 socket 放在同一目录（见 socket 模块）
+pub const VIEWER_SOCKET_NAME: &str = "logtool-viewer.sock";
+/// 走势保留的分析次数
+pub const TREND_POINTS: usize = 24;
+/// 摘要中列出的来源数
+pub const DIGEST_TOP: usize = 3;
+/// 最严重事件达到该级别（crit）及以上时判为严重
+const CRITICAL_PRIORITY: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthVerdict {
+    /// 窗口内没有匹配的事件
+    Ok,
+    Warning,
+    Critical,
+    /// daemon 启动以来还没有可用的分析
+    Unknown,
+}
+
+impl HealthVerdict {
+    pub fn label_cn(self) -> &'static str {
+        match self {
+            HealthVerdict::Ok => "✅ 正常",
+            HealthVerdict::Warning => "⚠️ 警告",
+            HealthVerdict::Critical => "🔴 严重",
+            HealthVerdict::Unknown => "❔ 未知",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DigestSuspect {
+    pub id: String,
+    pub count: u64,
+    pub worst_priority: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+}
+
+/// 一次分析的摘要
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Digest {
+    /// 分析完成时间（Unix 秒）
+    pub at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+    /// 分析使用的优先级条件，如 `3`、`2..4`
+    pub priority: String,
+    pub matched: usize,
+    /// 分析开始前一小时内的事件数
+    #[serde(default)]
+    pub last_hour: usize,
+    pub sources: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worst_priority: Option<u8>,
+    #[serde(default)]
+    pub top: Vec<DigestSuspect>,
+}
+
+impl Digest {
+    pub fn from_response(config: &Config, response: &AnalyzeResponse, at: u64) -> Self {
+        Self {
+            at,
+            since: config.since.clone(),
+            until: config.until.clone(),
+            priority: config.priority.to_string(),
+            matched: response.metrics.matched,
+            last_hour: response.metrics.last_hour,
+            sources: response.suspects.len(),
+            worst_priority: response.suspects.iter().map(|s| s.worst_priority).min(),
+            top: response
+                .suspects
+                .iter()
+                .take(DIGEST_TOP)
+                .map(|s| DigestSuspect {
+                    id: suspect_id(s.kind, &s.source),
+                    count: s.count,
+                    worst_priority: s.worst_priority,
+                    package: s.package.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn verdict(&self) -> HealthVerdict {
+        match self.worst_priority {
+            None => HealthVerdict::Ok,
+            Some(p) if p <= CRITICAL_PRIORITY => HealthVerdict::Critical,
+            Some(_) => HealthVerdict::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrendPoint {
+    pub at: u64,
+    pub matched: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worst_priority: Option<u8>,
+}
+
+/// 查看 socket 返回的文档
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewerSnapshot {
+    pub daemon_started_at: u64,
+    pub verdict: HealthVerdict,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<Digest>,
+    /// 按时间正序
+    #[serde(default)]
+    pub trend: Vec<TrendPoint>,
+}
+
+/// `logtool status --json` 的输出：只有状态栏需要的几个字段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusDocument {
+    pub verdict: HealthVerdict,
+    /// 摘要对应的分析时间（Unix 秒）及距今秒数；还没有分析时省略
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_secs: Option<u64>,
+    #[serde(default)]
+    pub last_hour: usize,
+    #[serde(default)]
+    pub matched: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_suspect: Option<DigestSuspect>,
+    /// 无法连接查看 socket 等情况；此时 verdict 为 unknown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl StatusDocument {
+    pub fn from_snapshot(snapshot: &ViewerSnapshot, now: u64) -> Self {
+        let digest = snapshot.digest.as_ref();
+        Self {
+            verdict: snapshot.verdict,
+            updated_at: digest.map(|d| d.at),
+            age_secs: digest.map(|d| now.saturating_sub(d.at)),
+            last_hour: digest.map_or(0, |d| d.last_hour),
+            matched: digest.map_or(0, |d| d.matched),
+            top_suspect: digest.and_then(|d| d.top.first().cloned()),
+            error: None,
+        }
+    }
+
+    pub fn unavailable(error: String) -> Self {
+        Self {
+            verdict: HealthVerdict::Unknown,
+            updated_at: None,
+            age_secs: None,
+            last_hour: 0,
+            matched: 0,
+            top_suspect: None,
+            error: Some(error),
+        }
+    }
+
+    /// `⚠️ 警告 | 最近 1 小时 12 条 | unit:nginx.service 40 条 | 3 分 20 秒前`
+    pub fn line(&self) -> String {
+        let mut parts = vec![self.verdict.label_cn().to_string()];
+        if let Some(error) = &self.error {
+            parts.push(error.lines().next().unwrap_or_default().to_string());
+        } else if let Some(age) = self.age_secs {
+            parts.push(format!("最近 1 小时 {} 条", self.last_hour));
+            if let Some(top) = &self.top_suspect {
+                parts.push(format!("{} {} 条", top.id, top.count));
+            }
+            parts.push(format!("{}前", format_duration_cn(age)));
+        } else {
+            parts.push("守护进程启动以来还没有完成分析".to_string());
+        }
+        parts.join(" | ")
+    }
+}
+
+/// daemon 内的缓存；只记录能代表整体健康状况的分析
+#[derive(Debug, Default)]
+pub struct ViewerCache {
+    digest: Option<Digest>,
+    trend: VecDeque<TrendPoint>,
+}
+
+impl ViewerCache {
+    /// 带单元、关键词、PID 等条件、限于某次启动或命名空间的分析只反映局部，
+    /// 读取其他目录或文件（--directory / --file / --syslog-file）、合并远程日志（--merge）的分析与本机无关，
+    /// 退回内核缓冲区的分析只含内核消息，--dedup 的计数不可比，都不计入
+    pub fn record(&mut self, config: &Config, response: &AnalyzeResponse, at: u64) {
+        if crate::filters_narrow_window(config)
+            || config.boot != crate::BootFilter::Disabled
+            || config.namespace.is_some()
+            || config.directory.is_some()
+            || !config.files.is_empty()
+            || !config.syslog_files.is_empty()
+            || response.kernel_fallback.is_some()
+            || config.merge
+            || config.dedup
+        {
+            return;
+        }
+        let digest = Digest::from_response(config, response, at);
+        if self.trend.len() == TREND_POINTS {
+            self.trend.pop_front();
+        }
+        self.trend.push_back(TrendPoint {
+            at,
+            matched: digest.matched,
+            worst_priority: digest.worst_priority,
+        });
+        self.digest = Some(digest);
+    }
+
+    pub fn snapshot(&self, daemon_started_at: u64) -> ViewerSnapshot {
+        ViewerSnapshot {
+            daemon_started_at,
+            verdict: self
+                .digest
+                .as_ref()
+                .map_or(HealthVerdict::Unknown, Digest::verdict),
+            digest: self.digest.clone(),
+            trend: self.trend.iter().copied().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmsg::{FallbackReason, KernelFallback};
+    use crate::test_support::sample_suspect;
+    use crate::{AnalyzeMetrics, SourceKind, SourceStats};
+
+    fn response(worst: &[u8]) -> AnalyzeResponse {
+        AnalyzeResponse {
+            metrics: AnalyzeMetrics {
+                matched: worst.len(),
+                ..AnalyzeMetrics::default()
+            },
+            suspects: worst
+                .iter()
+                .enumerate()
+                .map(|(i, p)| SourceStats {
+                    sample_message: "secret message".to_string(),
+                    ..sample_suspect(SourceKind::Unit, &format!("u{i}.service"), 1, *p)
+                })
+                .collect(),
+            top: 10,
+            environment: None,
+            rule_hits: Vec::new(),
+            namespace: None,
+            directory: None,
+            files: Vec::new(),
+            syslog_files: Vec::new(),
+            kernel_fallback: None,
+            hosts: Vec::new(),
+            boots: Vec::new(),
+            timeline: Vec::new(),
+            histogram: None,
+            bursts: Vec::new(),
+            trend: None,
+            baseline: None,
+            reboots: Vec::new(),
+            crashes: Vec::new(),
+            hangs: Vec::new(),
+            network: Vec::new(),
+            hardware: Vec::new(),
+            audit: Vec::new(),
+            failed_units: Vec::new(),
+            root_causes: Vec::new(),
+            smart: Vec::new(),
+            by_package: None,
+            by_user: None,
+            per_boot: None,
+            journalctl_usage: None,
+            truncated: None,
+            cached_at: None,
+            evidence: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn cache_records_unfiltered_analyses_only() {
+        let mut cache = ViewerCache::default();
+        assert_eq!(cache.snapshot(1).verdict, HealthVerdict::Unknown);
+
+        let config = Config::default();
+        cache.record(&config, &response(&[3, 3, 2, 3]), 100);
+        let filtered = Config {
+            units: vec!["ssh.service".to_string()],
+            ..Config::default()
+        };
+        cache.record(&filtered, &response(&[]), 200);
+        let syslog = Config {
+            syslog_files: vec!["/var/log/syslog".to_string()],
+            ..Config::default()
+        };
+        cache.record(&syslog, &response(&[]), 300);
+        let kernel_only = AnalyzeResponse {
+            kernel_fallback: Some(KernelFallback {
+                reason: FallbackReason::NoJournalctl,
+                source: "/dev/kmsg".to_string(),
+            }),
+            ..response(&[])
+        };
+        cache.record(&config, &kernel_only, 400);
+
+        let snapshot = cache.snapshot(1);
+        assert_eq!(snapshot.verdict, HealthVerdict::Critical);
+        let digest = snapshot.digest.expect("应有摘要");
+        assert_eq!((digest.at, digest.matched, digest.sources), (100, 4, 4));
+        assert_eq!(digest.top.len(), DIGEST_TOP);
+        assert_eq!(digest.top[0].id, "unit:u0.service");
+        let json = serde_json::to_string(&cache.snapshot(1)).expect("序列化");
+        assert!(!json.contains("secret"));
+
+        for at in 0..30 {
+            cache.record(&config, &response(&[]), 1000 + at);
+        }
+        let snapshot = cache.snapshot(1);
+        assert_eq!(snapshot.verdict, HealthVerdict::Ok);
+        assert_eq!(snapshot.trend.len(), TREND_POINTS);
+        assert_eq!(snapshot.trend[0].at, 1006);
+    }
+
+    #[test]
+    fn status_document_condenses_snapshot() {
+        let mut cache = ViewerCache::default();
+        let empty = StatusDocument::from_snapshot(&cache.snapshot(1), 50);
+        assert_eq!(empty.verdict, HealthVerdict::Unknown);
+        assert_eq!(
+            serde_json::to_string(&empty).expect("序列化"),
+            r#"{"verdict":"unknown","last_hour":0,"matched":0}"#
+        );
+        assert!(empty.line().contains("还没有完成分析"));
+
+        let mut busy = response(&[4, 3]);
+        busy.metrics.last_hour = 1;
+        cache.record(&Config::default(), &busy, 100);
+        let status = StatusDocument::from_snapshot(&cache.snapshot(1), 230);
+        assert_eq!(status.verdict, HealthVerdict::Warning);
+        assert_eq!(
+            (status.age_secs, status.last_hour, status.matched),
+            (Some(130), 1, 2)
+        );
+        assert_eq!(
+            status.line(),
+            "⚠️ 警告 | 最近 1 小时 1 条 | unit:u0.service 1 条 | 2 分 10 秒前"
+        );
+
+        let down = StatusDocument::unavailable("无法连接\n修复：启动服务".to_string());
+        assert_eq!(down.line(), "❔ 未知 | 无法连接");
+    }
+}
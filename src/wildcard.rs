@@ -0,0 +1,57 @@
+// 通配匹配 — 规则模式与 --exclude-unit 共用
+//
+// `*` 匹配任意串，`?` 匹配单个字符；不依赖规则引擎，关闭 `rules` 特性时照常可用。
+
+/// 大小写不敏感的通配匹配（`*` / `?`）
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    wildcard_match_lower(&pattern.to_ascii_lowercase(), &text.to_ascii_lowercase())
+}
+
+/// 通配匹配，调用方负责大小写归一
+pub(crate) fn wildcard_match_lower(pattern: &str, text: &str) -> bool {
+    let p = pattern.chars().collect::<Vec<_>>();
+    let t = text.chars().collect::<Vec<_>>();
+    let (mut pi, mut ti) = (0usize, 0usize);
+    let mut star: Option<usize> = None;
+    let mut star_ti = 0usize;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some(pi);
+            star_ti = ti;
+            pi += 1;
+        } else if let Some(star_pi) = star {
+            // 回溯：让上一个 `*` 多吞一个字符
+            pi = star_pi + 1;
+            star_ti += 1;
+            ti = star_ti;
+        } else {
+            return false;
+        }
+    }
+
+    while pi < p.len() && p[pi] == '*' {
+        pi += 1;
+    }
+    pi == p.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_supports_star_and_question_mark() {
+        assert!(wildcard_match(
+            "*acpi error*",
+            "kernel: ACPI Error: AE_NOT_FOUND"
+        ));
+        assert!(wildcard_match("ata?.00: *", "ata3.00: exception Emask"));
+        assert!(!wildcard_match("ata?.00: *", "ata10.00: exception"));
+        assert!(wildcard_match("*", ""));
+        assert!(!wildcard_match("abc", "abcd"));
+    }
+}
@@ -0,0 +1,1211 @@
+// CLI ↔ daemon 端到端测试：真实的两个二进制通过临时 Socket 通信，journalctl 为 support 中的假命令
+#![cfg(all(feature = "cli", feature = "daemon"))]
+
+mod support;
+
+use std::thread;
+use std::time::{Duration, Instant};
+use support::{Harness, Reply, journal_line};
+
+const ANALYSIS_QUERY: &str = "--output-fields=PRIORITY,MESSAGE";
+const EMERGENCY_QUERY: &str = "--priority=0..1";
+
+fn stdout_of(output: &std::process::Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr_of(output: &std::process::Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn analysis_round_trip_ranks_sources_and_resolves_packages() {
+    let mut harness = Harness::new("analysis");
+    let journal = [
+        journal_line(3, "app.service", "/usr/bin/fakeapp", "request failed"),
+        journal_line(2, "app.service", "/usr/bin/fakeapp", "worker crashed"),
+        journal_line(3, "other.service", "/usr/bin/other", "disk slow"),
+        journal_line(3, "app.service", "/usr/bin/fakeapp", "request failed"),
+    ]
+    .concat();
+    harness
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .package("/usr/bin/fakeapp", "fakepkg");
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    assert_eq!(report["metrics"]["matched"], 4);
+    assert_eq!(report["suspects"][0]["source"], "app.service");
+    assert_eq!(report["suspects"][0]["count"], 3);
+    let top_messages = &report["suspects"][0]["top_messages"];
+    assert_eq!(top_messages[0]["message"], "request failed");
+    assert_eq!(top_messages[0]["count"], 2);
+    assert_eq!(top_messages[1]["message"], "worker crashed");
+    assert_eq!(report["suspects"][0]["package"], "fakepkg");
+    assert!(report["suspects"][1]["package"].is_null());
+
+    let calls = harness.journalctl_calls();
+    let query = calls
+        .iter()
+        .find(|call| call.contains(ANALYSIS_QUERY))
+        .expect("应执行分析查询");
+    assert!(query.contains("--since 2 hours ago"), "{query}");
+    assert!(query.contains("--priority=3"), "{query}");
+}
+
+#[test]
+fn packages_of_many_suspects_are_resolved_in_parallel() {
+    let mut harness = Harness::new("many-packages");
+    let journal = (0..12)
+        .flat_map(|i| {
+            // 次数各不相同，排名固定：app0 最多
+            let line = journal_line(
+                3,
+                &format!("app{i}.service"),
+                &format!("/usr/bin/app{i}"),
+                "failed",
+            );
+            std::iter::repeat_n(line, 13 - i)
+        })
+        .collect::<String>();
+    harness
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal))
+        .reply(Reply::on(EMERGENCY_QUERY));
+    for i in 0..12 {
+        harness.package(&format!("/usr/bin/app{i}"), &format!("pkg{i}"));
+    }
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--top", "12", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    for i in 0..12 {
+        let suspect = &report["suspects"][i];
+        assert_eq!(suspect["source"], format!("app{i}.service"), "{report}");
+        assert_eq!(suspect["package"], format!("pkg{i}"), "{report}");
+    }
+}
+
+#[test]
+fn resolved_packages_are_cached_across_daemon_restarts() {
+    let mut harness = Harness::new("pkgcache");
+    harness
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal_line(
+            3,
+            "app.service",
+            "/usr/bin/fakeapp",
+            "request failed",
+        )))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .package("/usr/bin/fakeapp", "fakepkg");
+    harness.start_daemon(&[]);
+    let output = harness.cli(&["--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    harness.stop_daemon();
+
+    // dpkg-query 不再认得该文件，重启后的 daemon 仍从缓存得到所属包
+    harness.clear_packages().start_daemon(&[]);
+    let output = harness.cli(&["--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    assert_eq!(report["suspects"][0]["package"], "fakepkg");
+}
+
+#[test]
+fn snap_executables_are_attributed_to_their_snap() {
+    let mut harness = Harness::new("snap");
+    let journal = [
+        journal_line(
+            3,
+            "app-firefox.scope",
+            "/snap/firefox/4848/usr/lib/firefox/firefox",
+            "GPU process crashed",
+        ),
+        journal_line(
+            3,
+            "app-firefox.scope",
+            "/snap/firefox/4848/usr/lib/firefox/firefox",
+            "GPU process crashed",
+        ),
+        journal_line(3, "app.service", "/usr/bin/fakeapp", "request failed"),
+    ]
+    .concat();
+    harness
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .package("/usr/bin/fakeapp", "fakepkg");
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    assert_eq!(report["suspects"][0]["package"], "snap:firefox (4848)");
+    assert_eq!(report["suspects"][1]["package"], "fakepkg");
+}
+
+#[test]
+fn package_version_and_origin_are_reported() {
+    let mut harness = Harness::new("package-version");
+    harness
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal_line(
+            3,
+            "ssh.service",
+            "/usr/sbin/sshd",
+            "fatal: Timeout before authentication",
+        )))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .package("/usr/sbin/sshd", "openssh-server")
+        .version(
+            "openssh-server",
+            "1:9.6p1-3ubuntu13.5",
+            "openssh-server:
+  Installed: 1:9.6p1-3ubuntu13.5
+  Candidate: 1:9.6p1-3ubuntu13.5
+  Version table:
+ *** 1:9.6p1-3ubuntu13.5 500
+        500 http://archive.ubuntu.com/ubuntu noble-updates/main amd64 Packages
+        500 http://security.ubuntu.com/ubuntu noble-security/main amd64 Packages
+        100 /var/lib/dpkg/status
+",
+        );
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    assert_eq!(
+        report["suspects"][0]["package_version"],
+        serde_json::json!({ "version": "1:9.6p1-3ubuntu13.5", "origin": "noble-security" })
+    );
+
+    let output = harness.cli(&["--top", "5"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report = stdout_of(&output);
+    assert!(
+        report.contains("所属包  ：openssh-server 1:9.6p1-3ubuntu13.5（noble-security）"),
+        "{report}"
+    );
+}
+
+#[test]
+fn evidence_writes_counted_entries_of_top_suspects() {
+    let mut harness = Harness::new("evidence");
+    let journal = [
+        journal_line(3, "app.service", "/usr/bin/app", "first failure"),
+        journal_line(3, "other.service", "/usr/bin/other", "unrelated"),
+        journal_line(2, "app.service", "/usr/bin/app", "second failure"),
+        journal_line(3, "app.service", "/usr/bin/app", "filtered out"),
+    ]
+    .concat();
+    harness
+        .reply(Reply::on("--priority=3 --output=json").stdout(journal))
+        .reply(Reply::on(EMERGENCY_QUERY));
+    harness.start_daemon(&[]);
+
+    let dir = harness.socket_dir().join("evidence");
+    let dir_arg = dir.to_string_lossy().into_owned();
+    let args = ["--format", "json", "--top", "1", "--exclude", "filtered"];
+    let output = harness.cli(&[&args[..], &["--evidence", &dir_arg]].concat());
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    assert!(stderr_of(&output).contains("1 个来源，共 2 条"));
+    // 报告本身不带原始条目
+    assert!(!stdout_of(&output).contains("\"evidence\""));
+
+    let mut files = std::fs::read_dir(&dir)
+        .expect("证据目录应已创建")
+        .map(|entry| entry.expect("读取目录项").file_name())
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(files, ["01-unit_app.service.jsonl"]);
+    let contents = std::fs::read_to_string(dir.join(&files[0])).expect("读取证据文件");
+    let messages = contents
+        .lines()
+        .map(|line| {
+            let entry: serde_json::Value = serde_json::from_str(line).expect("每行应为 JSON");
+            entry["MESSAGE"].as_str().unwrap_or_default().to_string()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(messages, ["first failure", "second failure"]);
+
+    // 需要完整条目，分析查询不再限制 --output-fields
+    let calls = harness.journalctl_calls();
+    assert!(
+        calls
+            .iter()
+            .any(|call| call.ends_with("--priority=3 --output=json")),
+        "{calls:#?}"
+    );
+}
+
+#[test]
+fn suspects_are_annotated_with_unit_restarts() {
+    let mut harness = Harness::new("restarts");
+    let journal = [
+        journal_line(3, "app.service", "/usr/bin/app", "request failed"),
+        journal_line(3, "other.service", "/usr/bin/other", "disk slow"),
+    ]
+    .concat();
+    let systemd = [
+        r#"{"UNIT":"app.service","MESSAGE":"app.service: Failed with result 'exit-code'."}"#,
+        r#"{"UNIT":"app.service","MESSAGE":"app.service: Scheduled restart job, restart counter is at 1."}"#,
+        r#"{"UNIT":"app.service","MESSAGE":"Started app.service."}"#,
+    ]
+    .map(|line| format!("{line}\n"))
+    .concat();
+    harness
+        .reply(Reply::on("_PID=1").stdout(systemd))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal));
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    let app = &report["suspects"][0];
+    assert_eq!(app["source"], "app.service");
+    assert_eq!(app["unit_events"]["restarts"], 1);
+    assert_eq!(app["unit_events"]["failures"], 1);
+    assert!(report["suspects"][1].get("unit_events").is_none());
+
+    // 两个单元合成一次查询
+    let calls = harness.journalctl_calls();
+    let lifecycle = calls
+        .iter()
+        .filter(|call| call.contains("_PID=1"))
+        .collect::<Vec<_>>();
+    assert_eq!(lifecycle.len(), 1, "{calls:?}");
+    assert!(lifecycle[0].contains("UNIT=app.service UNIT=other.service"));
+}
+
+#[test]
+fn extra_sections_are_queried_only_when_requested() {
+    let mut harness = Harness::new("sections");
+    harness
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal_line(
+            3,
+            "app.service",
+            "/usr/bin/app",
+            "x",
+        )))
+        .reply(Reply::on(EMERGENCY_QUERY));
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--top", "5"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let grep_calls = |harness: &Harness| {
+        harness
+            .journalctl_calls()
+            .iter()
+            .filter(|call| call.contains("--grep="))
+            .count()
+    };
+    assert_eq!(grep_calls(&harness), 0, "{:?}", harness.journalctl_calls());
+
+    let output = harness.cli(&[
+        "--top",
+        "5",
+        "--crashes",
+        "--hangs",
+        "--network",
+        "--hardware",
+        "--audit",
+    ]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    assert_eq!(grep_calls(&harness), 5, "{:?}", harness.journalctl_calls());
+}
+
+#[test]
+fn kernel_segfaults_are_listed_as_crashes() {
+    let mut harness = Harness::new("crashes");
+    let kernel = [
+        r#"{"MESSAGE":"php-fpm8.3[4121]: segfault at 0 ip 00007f3a2c10b3d1 sp 00007ffd5e1c8a40 error 4 in libfoo.so.1[7f3a2c100000+20000]","__REALTIME_TIMESTAMP":"1704069000000000"}"#,
+        r#"{"MESSAGE":"php-fpm8.3[4200]: segfault at 0 ip 00007f3a2c10b3d1 sp 00007ffd5e1c8a40 error 4 in libfoo.so.1[7f3a2c100000+20000]","__REALTIME_TIMESTAMP":"1704069060000000"}"#,
+    ]
+    .map(|line| format!("{line}\n"))
+    .concat();
+    harness
+        .reply(Reply::on("_TRANSPORT=kernel").stdout(kernel))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal_line(
+            3,
+            "app.service",
+            "/usr/bin/app",
+            "x",
+        )))
+        .package("*/libfoo.so.1", "libfoo1");
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--crashes", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    let crash = &report["crashes"][0];
+    assert_eq!(crash["process"], "php-fpm8.3");
+    assert_eq!(crash["object"], "libfoo.so.1");
+    assert_eq!(crash["package"], "libfoo1");
+    assert_eq!(crash["count"], 2);
+    assert_eq!(crash["last_pid"], 4200);
+
+    let calls = harness.journalctl_calls();
+    let query = calls
+        .iter()
+        .find(|call| call.contains("_TRANSPORT=kernel"))
+        .expect("应查询内核崩溃记录");
+    assert!(query.contains("--grep=segfault at |traps: "), "{query}");
+}
+
+#[test]
+fn hung_tasks_are_listed_with_their_backtrace() {
+    let mut harness = Harness::new("hangs");
+    let kernel = [
+        "INFO: task jbd2/sda1-8:312 blocked for more than 120 seconds.",
+        "Call Trace:",
+        " __schedule+0x27c/0x6b0",
+        " io_schedule+0x46/0x80",
+        " bit_wait_io+0x11/0x70",
+        " jbd2_journal_commit_transaction+0x1a2/0x1c50 [jbd2]",
+        " </TASK>",
+        "INFO: task jbd2/sda1-8:312 blocked for more than 241 seconds.",
+    ]
+    .map(|message| serde_json::json!({ "MESSAGE": message }).to_string() + "\n")
+    .concat();
+    harness
+        .reply(Reply::on("blocked for more than").stdout(kernel))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal_line(
+            3,
+            "app.service",
+            "/usr/bin/app",
+            "x",
+        )));
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--hangs", "--linear"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report = stdout_of(&output);
+    assert!(report.contains("内核挂起，按次数从多到少："), "{report}");
+    assert!(
+        report.contains(
+            "jbd2/sda1-8 2 次：任务阻塞（D 状态）超过 241 秒，卡在 bit_wait_io ← jbd2_journal_commit_transaction，pid 312。"
+        ),
+        "{report}"
+    );
+}
+
+#[test]
+fn network_failures_are_listed_per_interface() {
+    let mut harness = Harness::new("network");
+    let network = [
+        "<info>  [1704069000.1234] device (wlp2s0): state change: activated -> deactivating (reason 'supplicant-disconnect', sys-iface-state: 'managed')",
+        "<info>  [1704069100.1234] device (wlp2s0): state change: activated -> unavailable (reason 'carrier-changed', sys-iface-state: 'managed')",
+        "Using degraded feature set UDP instead of UDP+EDNS0 for DNS server 192.168.1.1.",
+        "<warn>  [1704069200.1234] device (wlp2s0): Activation: failed for connection 'Cafe Corner 5G'",
+    ]
+    .map(|message| serde_json::json!({ "MESSAGE": message }).to_string() + "\n")
+    .concat();
+    harness
+        .reply(Reply::on("_SYSTEMD_UNIT=NetworkManager.service").stdout(network))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal_line(
+            3,
+            "app.service",
+            "/usr/bin/app",
+            "x",
+        )));
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--network", "--linear"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report = stdout_of(&output);
+    assert!(report.contains("网络状况，按次数从多到少："), "{report}");
+    assert!(
+        report.contains("接口 wlp2s0：网络断开 2 次，原因 carrier-changed。"),
+        "{report}"
+    );
+    assert!(
+        report.contains("DNS 服务器 192.168.1.1：DNS 降级 1 次，改用 UDP。"),
+        "{report}"
+    );
+
+    assert!(report.contains("Cafe Corner 5G"), "{report}");
+
+    // --redact 同样遮盖 DNS 服务器地址与连接名（Wi-Fi 名称）
+    let output = harness.cli(&["--network", "--redact", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report = stdout_of(&output);
+    assert!(!report.contains("192.168.1.1"), "{report}");
+    assert!(!report.contains("Cafe Corner 5G"), "{report}");
+    let report: serde_json::Value = serde_json::from_str(&report).expect("输出应为 JSON");
+    let issues = report["network"].as_array().expect("应有网络状况");
+    assert!(
+        issues.iter().any(|issue| issue["target"] == "<ip>"),
+        "{report}"
+    );
+    assert!(
+        issues
+            .iter()
+            .any(|issue| issue["detail"] == "<connection>" && issue["target"] == "wlp2s0"),
+        "{report}"
+    );
+
+    let calls = harness.journalctl_calls();
+    let query = calls
+        .iter()
+        .find(|call| call.contains("_SYSTEMD_UNIT=systemd-resolved.service"))
+        .expect("应查询网络相关单元");
+    assert!(query.contains("--grep="), "{query}");
+    assert!(!query.contains("--priority"), "{query}");
+}
+
+#[test]
+fn redact_masks_hostnames_in_host_breakdown() {
+    let mut harness = Harness::new("redact-hosts");
+    let line =
+        |host: &str| journal_line(3, "app.service", "/usr/bin/app", "x").replace("testhost", host);
+    harness
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(line("10.0.0.7") + &line("web-2")));
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    assert!(stdout_of(&output).contains("10.0.0.7"));
+
+    let output = harness.cli(&["--redact", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report = stdout_of(&output);
+    assert!(!report.contains("10.0.0.7"), "{report}");
+    let report: serde_json::Value = serde_json::from_str(&report).expect("输出应为 JSON");
+    assert_eq!(report["hosts"], serde_json::json!(["<ip>", "web-2"]));
+    let hosts = report["suspects"][0]["hosts"]
+        .as_array()
+        .expect("应有各主机计数");
+    assert!(hosts.iter().any(|h| h["host"] == "<ip>"), "{report}");
+}
+
+#[test]
+fn hardware_errors_are_flagged_at_the_top() {
+    let mut harness = Harness::new("hardware");
+    let kernel = [
+        "EDAC MC0: 1 UE memory read error on mc#0csrow#2channel#0 (page:0x0 offset:0x0 grain:8)",
+        "mce: CPU3: Core temperature above threshold, cpu clock throttled (total events = 12)",
+    ]
+    .map(|message| serde_json::json!({ "MESSAGE": message }).to_string() + "\n")
+    .concat();
+    harness
+        .reply(Reply::on("temperature above threshold").stdout(kernel))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal_line(
+            3,
+            "app.service",
+            "/usr/bin/app",
+            "x",
+        )));
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--hardware", "--linear"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report = stdout_of(&output);
+    assert!(
+        report.starts_with(
+            "硬件警告：检测到严重硬件故障：内存错误 1 次、过热降频 1 次；这通常是 CPU、内存或散热出了问题，而不是软件缺陷。\n"
+        ),
+        "{report}"
+    );
+    assert!(
+        report.contains(
+            "MC0 mc#0csrow#2channel#0 1 次：内存错误（EDAC，含不可纠正错误），属于严重故障。"
+        ),
+        "{report}"
+    );
+}
+
+#[test]
+fn failing_dependencies_are_reported_as_root_causes() {
+    let mut harness = Harness::new("dependencies");
+    let journal = [
+        journal_line(3, "app.service", "/usr/bin/app", "database unavailable"),
+        journal_line(3, "app.service", "/usr/bin/app", "database unavailable"),
+        journal_line(
+            3,
+            "postgresql.service",
+            "/usr/bin/postgres",
+            "could not bind",
+        ),
+    ]
+    .concat();
+    harness
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .dependencies("app.service", &["postgresql.service", "system.slice"])
+        .dependencies("postgresql.service", &["system.slice"]);
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    assert_eq!(
+        report["root_causes"],
+        serde_json::json!([{ "unit": "app.service", "causes": ["postgresql.service"] }])
+    );
+
+    let output = harness.cli(&["--linear"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report = stdout_of(&output);
+    assert!(
+        report.contains("它依赖的 postgresql.service 同样在报错，这里的错误可能是连带的。"),
+        "{report}"
+    );
+    assert!(
+        report.contains("依赖它的 app.service 同样在报错，宜优先排查。"),
+        "{report}"
+    );
+}
+
+#[test]
+fn failing_disks_are_reported_by_smart_health() {
+    let mut harness = Harness::new("smart");
+    let kernel = |message: &str| {
+        serde_json::json!({
+            "PRIORITY": "3",
+            "MESSAGE": message,
+            "SYSLOG_IDENTIFIER": "kernel",
+            "_TRANSPORT": "kernel",
+        })
+        .to_string()
+            + "\n"
+    };
+    let journal = [
+        kernel("blk_update_request: I/O error, dev sda, sector 2048 op 0x0:(READ)"),
+        kernel("blk_update_request: I/O error, dev sda, sector 4096 op 0x0:(READ)"),
+        kernel("I/O error, dev nvme0n1, sector 1000 op 0x1:(WRITE) flags 0x0"),
+    ]
+    .concat();
+    harness
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .smart(
+            "sda",
+            r#"{"smartctl":{"exit_status":8},"model_name":"ST2000DM008","smart_status":{"passed":false}}"#,
+        );
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--smart", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    assert_eq!(report["smart"][0]["device"], "/dev/sda");
+    assert_eq!(report["smart"][0]["passed"], false);
+    assert_eq!(report["smart"][1]["device"], "/dev/nvme0n1");
+    assert_eq!(report["smart"][1]["error"], "Permission denied");
+
+    let output = harness.cli(&["--smart"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report = stdout_of(&output);
+    assert!(
+        report.contains(
+            "SMART   ：/dev/sda（ST2000DM008）健康检查未通过，磁盘正在损坏，尽快备份并更换"
+        ),
+        "{report}"
+    );
+
+    // 不加 --smart 时不检查
+    let output = harness.cli(&["--format", "json"]);
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    assert!(report.get("smart").is_none(), "{report}");
+}
+
+#[test]
+fn audit_denials_are_decoded_and_attributed() {
+    let mut harness = Harness::new("audit");
+    let audit = [
+        r#"{"MESSAGE":"AVC apparmor=\"DENIED\" operation=\"open\" class=\"file\" profile=\"/usr/sbin/cupsd\" name=\"/etc/ssl/private/key.pem\" pid=1234 comm=\"cupsd\" requested_mask=\"r\" denied_mask=\"r\"","__REALTIME_TIMESTAMP":"1704069000000000"}"#,
+        r#"{"MESSAGE":"AVC apparmor=\"DENIED\" operation=\"open\" class=\"file\" profile=\"/usr/sbin/cupsd\" name=\"/etc/ssl/private/other.pem\" pid=1234 comm=\"cupsd\" requested_mask=\"r\" denied_mask=\"r\"","__REALTIME_TIMESTAMP":"1704069060000000"}"#,
+        r#"{"MESSAGE":"SECCOMP auid=1000 uid=1000 pid=4321 comm=\"chrome\" exe=\"/opt/google/chrome/chrome\" sig=0 arch=c000003e syscall=257 compat=0 ip=0x7f3a code=0x50000","__REALTIME_TIMESTAMP":"1704069030000000"}"#,
+    ]
+    .map(|line| format!("{line}\n"))
+    .concat();
+    harness
+        .reply(Reply::on("_TRANSPORT=audit").stdout(audit))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal_line(
+            3,
+            "app.service",
+            "/usr/bin/app",
+            "x",
+        )))
+        .package("/usr/sbin/cupsd", "cups-daemon");
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--audit", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    let denial = &report["audit"][0];
+    assert_eq!(denial["mechanism"], "app_armor");
+    assert_eq!(denial["process"], "cupsd");
+    assert_eq!(denial["operation"], "open（r）");
+    assert_eq!(denial["target"], "/etc/ssl/private/other.pem");
+    assert_eq!(denial["count"], 2);
+    let seccomp = &report["audit"][1];
+    assert_eq!(seccomp["operation"], "openat（ERRNO）");
+
+    assert_eq!(denial["package"], "cups-daemon");
+
+    let linear = harness.cli(&["--audit", "--linear"]);
+    assert!(linear.status.success(), "{}", stderr_of(&linear));
+    let stdout = stdout_of(&linear);
+    assert!(
+        stdout.contains("seccomp 拒绝进程 chrome 的 openat（ERRNO） 共 1 次"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn known_issues_attach_hints_to_suspects() {
+    let mut harness = Harness::new("known-issues");
+    let journal = [
+        journal_line(
+            3,
+            "app.service",
+            "/usr/bin/app",
+            "write /var/lib/app/db: No space left on device",
+        ),
+        journal_line(3, "other.service", "/usr/bin/other", "something odd"),
+    ]
+    .concat();
+    harness
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal))
+        .reply(Reply::on(EMERGENCY_QUERY));
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    let hint = &report["suspects"][0]["hint"];
+    assert_eq!(hint["rule_id"], "kb-disk-full");
+    assert!(
+        hint["suggestion"]
+            .as_str()
+            .unwrap_or_default()
+            .contains("df -h")
+    );
+    assert!(report["suspects"][1].get("hint").is_none());
+}
+
+#[test]
+fn baseline_flags_sources_above_the_saved_rate() {
+    let mut harness = Harness::new("baseline");
+    let journal = [
+        vec![journal_line(3, "app.service", "/usr/bin/app", "request failed"); 6],
+        vec![journal_line(3, "other.service", "/usr/bin/other", "slow"); 5],
+    ]
+    .concat()
+    .concat();
+    harness
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal));
+    harness.start_daemon(&[]);
+
+    let path = harness.socket_dir().join("baseline.json");
+    let path_arg = path.to_str().expect("路径应为 UTF-8");
+    let output = harness.cli(&["baseline", "save", path_arg, "--last", "2h"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let mut saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).expect("应写入基线文件"))
+            .expect("基线应为 JSON");
+    assert_eq!(saved["window_secs"], 7200);
+    assert_eq!(saved["sources"][0]["source"], "app.service");
+    assert_eq!(saved["sources"][0]["count"], 6);
+
+    // 与刚保存的基线相同：没有来源偏高
+    let args = [
+        "--last",
+        "2h",
+        "--against-baseline",
+        path_arg,
+        "--format",
+        "json",
+    ];
+    let output = harness.cli(&args);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    assert_eq!(report["baseline"]["exceeded"], serde_json::json!([]));
+
+    // 平常一天只有 1 条 app.service、没有 other.service
+    saved["window_secs"] = 86_400.into();
+    saved["sources"] = serde_json::json!([{"kind": "Unit", "source": "app.service", "count": 1}]);
+    std::fs::write(&path, saved.to_string()).expect("写回基线应成功");
+    let output = harness.cli(&args);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    let exceeded = report["baseline"]["exceeded"]
+        .as_array()
+        .expect("应列出偏高的来源")
+        .iter()
+        .map(|d| {
+            (
+                d["source"].as_str().unwrap_or_default(),
+                d["baseline"].as_u64(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        exceeded,
+        [("app.service", Some(1)), ("other.service", Some(0))]
+    );
+}
+
+#[test]
+fn group_by_package_merges_sources_of_one_package() {
+    let mut harness = Harness::new("group-by-package");
+    let journal = [
+        journal_line(3, "api.service", "/usr/bin/fakeapp", "request failed"),
+        journal_line(3, "worker.service", "/usr/bin/fakeapp-worker", "job failed"),
+        journal_line(3, "worker.service", "/usr/bin/fakeapp-worker", "job failed"),
+        journal_line(3, "other.service", "/usr/bin/other", "disk slow"),
+    ]
+    .concat();
+    harness
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .package("/usr/bin/fakeapp", "fakepkg")
+        .package("/usr/bin/fakeapp-worker", "fakepkg");
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--group-by", "package", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    let suspects = report["suspects"]
+        .as_array()
+        .expect("应有可疑来源")
+        .iter()
+        .map(|s| {
+            (
+                s["kind"].as_str().unwrap_or_default(),
+                s["source"].as_str().unwrap_or_default(),
+                s["count"].as_u64().unwrap_or_default(),
+            )
+        })
+        .collect::<Vec<_>>();
+    // 查不到包的来源按默认次序归为单元
+    assert_eq!(
+        suspects,
+        [("Package", "fakepkg", 3), ("Unit", "other.service", 1)]
+    );
+}
+
+#[test]
+fn by_package_sums_sources_owned_by_one_package() {
+    let mut harness = Harness::new("by-package");
+    let journal = [
+        journal_line(3, "api.service", "/usr/bin/fakeapp", "request failed"),
+        journal_line(3, "worker.service", "/usr/bin/fakeapp-worker", "job failed"),
+        journal_line(3, "worker.service", "/usr/bin/fakeapp-worker", "job failed"),
+        journal_line(3, "other.service", "/usr/bin/other", "disk slow"),
+    ]
+    .concat();
+    harness
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .package("/usr/bin/fakeapp", "fakepkg")
+        .package("/usr/bin/fakeapp-worker", "fakepkg");
+    harness.start_daemon(&[]);
+
+    // --top 1：汇总仍覆盖排在后面的来源
+    let output = harness.cli(&["--by-package", "--top", "1", "--linear"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report = stdout_of(&output);
+    assert!(
+        report.contains(
+            "软件包 fakepkg 共 3 条事件，最高严重级别为错误（3），来自 unit:worker.service（2）、unit:api.service（1）。"
+        ),
+        "{report}"
+    );
+    assert!(
+        report.contains("另有 1 条事件的来源不属于已知的包。"),
+        "{report}"
+    );
+}
+
+#[test]
+fn by_user_attributes_events_to_uids() {
+    let mut harness = Harness::new("by-user");
+    let with_uid = |uid: u32, exe: &str, message: &str| {
+        let mut entry: serde_json::Value =
+            serde_json::from_str(&journal_line(3, "session-4.scope", exe, message))
+                .expect("测试数据应为 JSON");
+        entry["_UID"] = uid.to_string().into();
+        entry.to_string() + "\n"
+    };
+    let journal = [
+        with_uid(4242, "/usr/bin/python3", "Traceback"),
+        with_uid(4242, "/usr/bin/python3", "Traceback"),
+        with_uid(0, "/usr/bin/python3", "Traceback"),
+        journal_line(3, "", "", "EXT4-fs error"),
+    ]
+    .concat();
+    harness
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal))
+        .reply(Reply::on(EMERGENCY_QUERY));
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--by-user", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    let view = &report["by_user"];
+    assert_eq!(view["without_uid"], 1, "{view}");
+    assert_eq!(view["users"][0]["uid"], 4242);
+    assert_eq!(view["users"][0]["count"], 2);
+    assert!(view["users"][0].get("user").is_none(), "{view}");
+    assert_eq!(view["users"][1]["user"], "root");
+    assert_eq!(view["users"][1]["sources"][0]["id"], "unit:session-4.scope");
+}
+
+#[test]
+fn per_boot_lists_each_boot_with_its_top_source() {
+    let mut harness = Harness::new("per-boot");
+    const OLD: &str = "0f1e2d3c4b5a69788796a5b4c3d2e1f0";
+    const NEW: &str = "a1b2c3d4e5f60718293a4b5c6d7e8f90";
+    let in_boot = |boot_id: &str, unit: &str, message: &str| {
+        let mut entry: serde_json::Value =
+            serde_json::from_str(&journal_line(3, unit, "/usr/bin/app", message))
+                .expect("测试数据应为 JSON");
+        entry["_BOOT_ID"] = boot_id.into();
+        entry.to_string() + "\n"
+    };
+    let journal = [
+        in_boot(OLD, "disk.service", "I/O error"),
+        in_boot(OLD, "disk.service", "I/O error"),
+        in_boot(OLD, "web.service", "timeout"),
+        in_boot(NEW, "web.service", "timeout"),
+    ]
+    .concat();
+    let boots = format!(
+        r#"[{{"index":-1,"boot_id":"{OLD}","first_entry":1704069000000000,"last_entry":1704075000000000}},{{"index":0,"boot_id":"{NEW}","first_entry":1704078000000000,"last_entry":1704080000000000}}]"#
+    );
+    harness
+        .reply(Reply::on("--list-boots").stdout(boots))
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal))
+        .reply(Reply::on(EMERGENCY_QUERY));
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--per-boot", "--linear"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report = stdout_of(&output);
+    assert!(
+        report.contains("按启动周期分列，按开机时间先后："),
+        "{report}"
+    );
+    let old = report.find("启动周期 -1（").expect("应列出上一次启动");
+    let new = report.find("启动周期 0（").expect("应列出本次启动");
+    assert!(old < new, "{report}");
+    assert!(
+        report.contains(
+            "共 3 条事件，最高严重级别为错误（3），首要来源 unit:disk.service（2 条），事件最多。"
+        ),
+        "{report}"
+    );
+}
+
+#[test]
+fn compare_previous_queries_the_preceding_window() {
+    let mut harness = Harness::new("compare-previous");
+    let previous = [
+        journal_line(3, "app.service", "/usr/bin/app", "failed"),
+        journal_line(3, "old.service", "/usr/bin/old", "gone now"),
+    ]
+    .concat();
+    let current = [
+        journal_line(3, "app.service", "/usr/bin/app", "failed"),
+        journal_line(3, "app.service", "/usr/bin/app", "failed again"),
+        journal_line(3, "new.service", "/usr/bin/new", "fresh"),
+    ]
+    .concat();
+    // 上一窗口的查询以 `--until @Unix 秒` 结束
+    harness
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .reply(Reply::on("--until @").stdout(previous))
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(current));
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--format", "json", "--last", "1h", "--compare-previous"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    let trend = &report["trend"];
+    assert_eq!(trend["previous_matched"], 2);
+    assert_eq!(
+        trend["previous_until_us"].as_u64().unwrap() - trend["previous_since_us"].as_u64().unwrap(),
+        3_600_000_000
+    );
+    let changes = trend["changes"]
+        .as_array()
+        .expect("changes 应为数组")
+        .iter()
+        .map(|c| {
+            format!(
+                "{} {} {}→{}",
+                c["source"].as_str().unwrap_or_default(),
+                c["kind"].as_str().unwrap_or_default(),
+                c["previous"],
+                c["current"]
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        [
+            "app.service increased 1→2",
+            "new.service new 0→1",
+            "old.service resolved 1→0"
+        ]
+    );
+
+    let calls = harness.journalctl_calls();
+    let previous_query = calls
+        .iter()
+        .find(|call| call.contains("--until @") && call.contains(ANALYSIS_QUERY))
+        .expect("应查询上一窗口");
+    assert!(previous_query.contains("--since @"), "{previous_query}");
+}
+
+#[test]
+fn stream_ends_with_journalctl_and_stops_at_max_lines() {
+    let lines = (1..=20)
+        .map(|n| format!("2024-01-01T00:00:{n:02}+0000 testhost app[1]: line {n}\n"))
+        .collect::<String>();
+    let mut harness = Harness::new("stream");
+    harness
+        .reply(Reply::on("--follow").stdout(lines.clone()).hold(30))
+        .reply(Reply::on("--output=short-iso").stdout(lines));
+    harness.start_daemon(&[]);
+
+    // journalctl 正常结束时流随之结束
+    let output = harness.cli(&["--stream", "--max-lines", "100"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    assert_eq!(stdout_of(&output).lines().count(), 20);
+
+    // --follow 下 journalctl 不会结束：达到 --max-lines 后 daemon 应结束流并终止 journalctl
+    let started = Instant::now();
+    let output = harness.cli(&["--stream", "--follow", "--max-lines", "5"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    assert!(started.elapsed() < Duration::from_secs(20));
+    let stdout = stdout_of(&output);
+    assert_eq!(stdout.lines().count(), 5, "{stdout}");
+    assert!(stdout.lines().last().is_some_and(|l| l.ends_with("line 5")));
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while harness.running_journalctl() > 0 {
+        assert!(
+            Instant::now() < deadline,
+            "journalctl 未被终止：\n{}",
+            harness.daemon_log()
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn kernel_buffer_is_analyzed_when_journalctl_is_unusable() {
+    let mut harness = Harness::new("kmsg");
+    harness
+        .reply(Reply::on("--version").exit(1))
+        .dmesg(
+            r#"{"dmesg":[{"pri":3,"time":@UPTIME@,"msg":"EXT4-fs error (device sda1): bad block"},{"pri":6,"time":@UPTIME@,"msg":"usb 1-1: new device"}]}"#,
+        );
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--format", "json"]);
+    assert!(
+        output.status.success(),
+        "{}\n{}",
+        stderr_of(&output),
+        harness.daemon_log()
+    );
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    assert_eq!(report["kernel_fallback"]["reason"], "no_journalctl");
+    assert_eq!(report["kernel_fallback"]["source"], "dmesg --json");
+    // 只有错误级别的一条进入默认的 --priority 范围
+    assert_eq!(report["metrics"]["matched"], 1);
+    assert_eq!(report["suspects"][0]["kind"], "Storage");
+    assert_eq!(report["suspects"][0]["source"], "sda");
+}
+
+#[test]
+fn gpu_faults_are_attributed_to_the_driver_package() {
+    let mut harness = Harness::new("gpu");
+    let kernel = |message: &str| {
+        serde_json::json!({
+            "PRIORITY": "3",
+            "MESSAGE": message,
+            "SYSLOG_IDENTIFIER": "kernel",
+            "_TRANSPORT": "kernel",
+        })
+        .to_string()
+            + "\n"
+    };
+    let journal = [
+        kernel("i915 0000:00:02.0: [drm] GPU HANG: ecode 9:1:85dffffb, in gnome-shell [1534]"),
+        kernel("i915 0000:00:02.0: [drm] Resetting chip for stopped heartbeat on rcs0"),
+        kernel("NVRM: Xid (PCI:0000:01:00): 79, pid=1234, GPU has fallen off the bus."),
+    ]
+    .concat();
+    let i915 = "/lib/modules/6.8.0-45-generic/kernel/drivers/gpu/drm/i915/i915.ko.zst";
+    harness
+        .reply(Reply::on(ANALYSIS_QUERY).stdout(journal))
+        .reply(Reply::on(EMERGENCY_QUERY))
+        .module("i915", i915, "")
+        .module(
+            "nvidia",
+            "/lib/modules/6.8.0-45-generic/updates/dkms/nvidia.ko.zst",
+            "550.107.02",
+        )
+        .package(i915, "linux-modules-6.8.0-45-generic")
+        .package("/usr/src/nvidia-550.107.02", "nvidia-dkms-550");
+    harness.start_daemon(&[]);
+
+    let output = harness.cli(&["--format", "json"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    let suspects = &report["suspects"];
+    assert_eq!(suspects[0]["kind"], "Gpu");
+    assert_eq!(suspects[0]["source"], "i915");
+    assert_eq!(suspects[0]["count"], 2);
+    assert_eq!(suspects[0]["package"], "linux-modules-6.8.0-45-generic");
+    // DKMS 编译的模块文件不属于任何包，按源码目录找到 nvidia-dkms
+    assert_eq!(suspects[1]["source"], "nvidia");
+    assert_eq!(suspects[1]["package"], "nvidia-dkms-550");
+
+    let output = harness.cli(&["--linear"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+    assert!(
+        stdout_of(&output).contains("第 1 名：显卡驱动 i915，共 2 条事件"),
+        "{}",
+        stdout_of(&output)
+    );
+}
+
+#[test]
+fn syslog_files_are_analyzed_without_journalctl() {
+    let mut harness = Harness::new("syslog");
+    harness.start_daemon(&[]);
+    let path = harness.socket_dir().with_file_name("syslog");
+    std::fs::write(
+        &path,
+        "Jan  3 08:05:09 web1 nginx[812]: connect() failed (111: Connection refused)\n\
+         Jan  3 08:05:10 web1 nginx[812]: connect() failed (111: Connection refused)\n\
+         Jan  3 08:05:11 web1 CRON[900]: (root) CMD (run-parts /etc/cron.hourly)\n\
+         <11>1 2024-01-03T00:05:12Z web1 postgres 4242 - - could not write to file\n\
+         garbage\n",
+    )
+    .expect("写入 syslog 文件应成功");
+
+    let output = harness.cli(&["--syslog-file", path.to_str().unwrap(), "--format", "json"]);
+    assert!(
+        output.status.success(),
+        "{}\n{}",
+        stderr_of(&output),
+        harness.daemon_log()
+    );
+    let report: serde_json::Value =
+        serde_json::from_str(&stdout_of(&output)).expect("输出应为 JSON");
+    // CRON 一行推测为 info，与 journalctl 的 --priority 一样在读取时就滤掉
+    assert_eq!(report["metrics"]["lines_read"], 4);
+    assert_eq!(report["metrics"]["parse_errors"], 1);
+    assert_eq!(report["metrics"]["matched"], 3);
+    assert_eq!(report["suspects"][0]["source"], "nginx");
+    assert_eq!(report["suspects"][0]["count"], 2);
+    assert_eq!(report["suspects"][1]["source"], "postgres");
+    let calls = harness.journalctl_calls();
+    assert!(calls.is_empty(), "{calls:?}");
+}
+
+#[test]
+fn journalctl_failure_reaches_the_client() {
+    let mut harness = Harness::new("failure");
+    harness
+        .reply(
+            Reply::on(ANALYSIS_QUERY)
+                .stderr("Failed to open journal: fake permission denied")
+                .exit(1),
+        )
+        .reply(
+            Reply::on("--output=short-iso")
+                .stdout("2024-01-01T00:00:00+0000 testhost app[1]: before failure\n")
+                .stderr("Failed to open journal: fake permission denied")
+                .exit(1),
+        );
+    harness.start_daemon(&[]);
+
+    // 分析：错误码与修复提示随错误响应返回
+    let output = harness.cli(&["run"]);
+    assert!(!output.status.success());
+    let stderr = stderr_of(&output);
+    assert!(stderr.contains("journalctl_failed"), "{stderr}");
+    assert!(stderr.contains("exit status: 1"), "{stderr}");
+
+    // 流：已输出的行照常送达，journalctl 的 stderr 附在错误中
+    let output = harness.cli(&["--stream"]);
+    assert!(!output.status.success());
+    assert_eq!(stdout_of(&output).lines().count(), 1);
+    let stderr = stderr_of(&output);
+    assert!(stderr.contains("fake permission denied"), "{stderr}");
+
+    // daemon 在失败后继续服务
+    let output = harness.cli(&["--bogus-option"]);
+    assert!(!output.status.success());
+    let output = harness.cli(&["stats"]);
+    assert!(output.status.success(), "{}", stderr_of(&output));
+}
+
+#[test]
+fn concurrent_requests_are_served_in_parallel() {
+    const CLIENTS: usize = 4;
+    const DELAY_SECS: f64 = 1.5;
+    let mut harness = Harness::new("concurrency");
+    harness
+        .reply(
+            Reply::on(ANALYSIS_QUERY)
+                .delay(DELAY_SECS)
+                .stdout(journal_line(3, "app.service", "/usr/bin/app", "boom")),
+        )
+        .reply(Reply::on(EMERGENCY_QUERY));
+    harness.start_daemon(&[]);
+
+    let started = Instant::now();
+    let clients = (0..CLIENTS)
+        .map(|_| harness.cli_spawn(&["--format", "json"]))
+        .collect::<Vec<_>>();
+    for client in clients {
+        let output = client.wait_with_output().expect("等待 logtool 应成功");
+        assert!(output.status.success(), "{}", stderr_of(&output));
+        assert!(stdout_of(&output).contains("app.service"));
+    }
+    // 串行处理至少需要 CLIENTS × DELAY_SECS 秒
+    let serial = Duration::from_secs_f64(CLIENTS as f64 * DELAY_SECS);
+    assert!(
+        started.elapsed() < serial,
+        "{} 个请求耗时 {:?}",
+        CLIENTS,
+        started.elapsed()
+    );
+}
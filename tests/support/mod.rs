@@ -0,0 +1,424 @@
+// 集成测试支持 — 在临时目录中搭建假的 journalctl / dpkg-query / systemctl，
+// 并以该目录下的 Socket 运行真实的 logtool-daemon 与 logtool CLI
+//
+// 假 journalctl 把每次调用的参数追加到 journalctl.calls、进程号追加到 journalctl.pids，再按
+// journalctl.rules（每行 `pattern|输出文件|stderr|延迟|保持|退出码`）中第一条参数包含 pattern 的规则回放：可设输出前的延迟、输出文件、stderr、
+// 输出后保持运行的时间（模拟 --follow）与退出码；没有规则匹配时静默成功。
+// 假 dpkg-query -S <路径> 按 dpkg.map 中的 `路径<TAB>包名` 回答。
+// 假 dmesg --json 输出 dmesg.json（其中的 @UPTIME@ 换成当前的启动后秒数），没有时输出空缓冲区。
+
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 等待 daemon 创建 Socket 的上限
+const DAEMON_START_TIMEOUT: Duration = Duration::from_secs(10);
+
+const FAKE_JOURNALCTL: &str = r#"#!/bin/sh
+root="$(dirname "$0")/.."
+args="$*"
+printf '%s\n' "$args" >> "$root/journalctl.calls"
+echo $$ >> "$root/journalctl.pids"
+[ -f "$root/journalctl.rules" ] || exit 0
+while IFS='|' read -r pattern stdout stderr delay hold code; do
+  case "$args" in
+    *"$pattern"*)
+      [ "$delay" = 0 ] || sleep "$delay"
+      [ -z "$stdout" ] || cat "$root/fixtures/$stdout"
+      [ -z "$stderr" ] || printf '%s\n' "$stderr" >&2
+      [ "$hold" = 0 ] || exec sleep "$hold"
+      exit "$code" ;;
+  esac
+done < "$root/journalctl.rules"
+exit 0
+"#;
+
+/// `dpkg-query -S <路径>` 按 dpkg.map、`dpkg-query -W -f=… <包>` 按 versions.map 回答
+const FAKE_DPKG_QUERY: &str = r#"#!/bin/sh
+root="$(dirname "$0")/.."
+[ "$1" = "--version" ] && exit 0
+if [ "$1" = "-W" ]; then
+  [ -f "$root/versions.map" ] || exit 1
+  while IFS='	' read -r package version; do
+    [ "$package" = "$3" ] && { echo "$version"; exit 0; }
+  done < "$root/versions.map"
+  exit 1
+fi
+[ "$1" = "-S" ] || exit 1
+[ -f "$root/dpkg.map" ] || exit 1
+while IFS='	' read -r path package; do
+  [ "$path" = "$2" ] && { printf '%s: %s\n' "$package" "$path"; exit 0; }
+done < "$root/dpkg.map"
+exit 1
+"#;
+
+/// `apt-cache policy <包>`，按 policy/<包> 回答
+const FAKE_APT_CACHE: &str = r#"#!/bin/sh
+root="$(dirname "$0")/.."
+[ "$1" = policy ] && [ -f "$root/policy/$2" ] || exit 0
+cat "$root/policy/$2"
+"#;
+
+const FAKE_DMESG: &str = r#"#!/bin/sh
+root="$(dirname "$0")/.."
+[ -f "$root/dmesg.json" ] || { echo '{"dmesg":[]}'; exit 0; }
+sed "s/@UPTIME@/$(cut -d' ' -f1 /proc/uptime)/g" "$root/dmesg.json"
+"#;
+
+/// `modinfo -n <模块>` 与 `modinfo -F version <模块>`，按 modules.map 回答
+const FAKE_MODINFO: &str = r#"#!/bin/sh
+root="$(dirname "$0")/.."
+[ -f "$root/modules.map" ] || exit 1
+case "$1" in
+  -n) want=path ;;
+  -F) [ "$2" = version ] || exit 1; want=version; shift ;;
+  *) exit 1 ;;
+esac
+while IFS='	' read -r name path version; do
+  [ "$name" = "$2" ] || continue
+  if [ "$want" = path ]; then echo "$path"; else echo "$version"; fi
+  exit 0
+done < "$root/modules.map"
+exit 1
+"#;
+
+/// 单元反查与 systemd 版本检测都当作不可用；list-dependencies 按 deps/<单元> 回答
+const FAKE_SYSTEMCTL: &str = r#"#!/bin/sh
+root="$(dirname "$0")/.."
+if [ "$1" = list-dependencies ]; then
+  for unit; do :; done
+  [ -f "$root/deps/$unit" ] && { cat "$root/deps/$unit"; exit 0; }
+fi
+exit 1
+"#;
+
+/// `smartctl -H -j /dev/<设备>`，按 smart/<设备> 回答；没有时像无权限那样只给提示
+const FAKE_SMARTCTL: &str = r#"#!/bin/sh
+root="$(dirname "$0")/.."
+for device; do :; done
+name="$(basename "$device")"
+[ -f "$root/smart/$name" ] && { cat "$root/smart/$name"; exit 0; }
+echo '{"smartctl":{"messages":[{"string":"Permission denied","severity":"error"}],"exit_status":2}}'
+exit 2
+"#;
+
+/// 假 journalctl 的一条回放规则
+pub struct Reply {
+    pattern: String,
+    stdout: Option<String>,
+    stderr: String,
+    delay_secs: f64,
+    hold_secs: u64,
+    code: i32,
+}
+
+impl Reply {
+    /// 参数中包含 pattern 的调用
+    pub fn on(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            stdout: None,
+            stderr: String::new(),
+            delay_secs: 0.0,
+            hold_secs: 0,
+            code: 0,
+        }
+    }
+
+    pub fn stdout(mut self, text: impl Into<String>) -> Self {
+        self.stdout = Some(text.into());
+        self
+    }
+
+    pub fn stderr(mut self, text: &str) -> Self {
+        self.stderr = text.to_string();
+        self
+    }
+
+    /// 输出之前先等待（秒，可为小数）
+    pub fn delay(mut self, secs: f64) -> Self {
+        self.delay_secs = secs;
+        self
+    }
+
+    /// 输出之后不退出，像 --follow 一样保持运行（秒）
+    pub fn hold(mut self, secs: u64) -> Self {
+        self.hold_secs = secs;
+        self
+    }
+
+    pub fn exit(mut self, code: i32) -> Self {
+        self.code = code;
+        self
+    }
+}
+
+pub struct Harness {
+    root: PathBuf,
+    daemon: Option<Child>,
+    rules: usize,
+}
+
+impl Harness {
+    /// name 区分同一进程中的多个测试
+    pub fn new(name: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let root = env::temp_dir().join(format!(
+            "logtool-it-{name}-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&root);
+        for dir in ["bin", "fixtures", "sock", "config/logtool", "state"] {
+            fs::create_dir_all(root.join(dir)).expect("创建测试目录应成功");
+        }
+        for (name, script) in [
+            ("journalctl", FAKE_JOURNALCTL),
+            ("dpkg-query", FAKE_DPKG_QUERY),
+            ("systemctl", FAKE_SYSTEMCTL),
+            ("dmesg", FAKE_DMESG),
+            ("modinfo", FAKE_MODINFO),
+            ("smartctl", FAKE_SMARTCTL),
+            ("apt-cache", FAKE_APT_CACHE),
+        ] {
+            let path = root.join("bin").join(name);
+            fs::write(&path, script).expect("写入假命令应成功");
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+                .expect("设置执行权限应成功");
+        }
+        fs::write(
+            root.join("config/logtool/config.json"),
+            "{\"local_mode\":false}\n",
+        )
+        .expect("写入 CLI 配置应成功");
+        Self {
+            root,
+            daemon: None,
+            rules: 0,
+        }
+    }
+
+    /// 规则按添加顺序匹配，先加更具体的
+    pub fn reply(&mut self, reply: Reply) -> &mut Self {
+        let stdout = match reply.stdout {
+            Some(text) => {
+                let name = format!("out-{}.txt", self.rules);
+                fs::write(self.root.join("fixtures").join(&name), text)
+                    .expect("写入 fixture 应成功");
+                name
+            }
+            None => String::new(),
+        };
+        self.rules += 1;
+        // 规则文件以 | 分隔（制表符属于 IFS 空白，空字段会被合并）
+        assert!(!reply.pattern.contains('|') && !reply.stderr.contains('|'));
+        let line = format!(
+            "{}|{stdout}|{}|{}|{}|{}\n",
+            reply.pattern, reply.stderr, reply.delay_secs, reply.hold_secs, reply.code
+        );
+        append(&self.root.join("journalctl.rules"), &line);
+        self
+    }
+
+    /// dpkg-query -S path 的回答
+    pub fn package(&mut self, path: &str, package: &str) -> &mut Self {
+        append(&self.root.join("dpkg.map"), &format!("{path}\t{package}\n"));
+        self
+    }
+
+    /// dpkg-query -W 给出的安装版本，以及 apt-cache policy 的输出
+    pub fn version(&mut self, package: &str, version: &str, policy: &str) -> &mut Self {
+        append(
+            &self.root.join("versions.map"),
+            &format!("{package}\t{version}\n"),
+        );
+        let dir = self.root.join("policy");
+        fs::create_dir_all(&dir).expect("创建目录应成功");
+        fs::write(dir.join(package), policy).expect("写入 apt-cache 输出应成功");
+        self
+    }
+
+    /// 之后的 dpkg-query -S 都查不到
+    pub fn clear_packages(&mut self) -> &mut Self {
+        let _ = fs::remove_file(self.root.join("dpkg.map"));
+        self
+    }
+
+    /// systemctl list-dependencies unit 的回答
+    pub fn dependencies(&mut self, unit: &str, dependencies: &[&str]) -> &mut Self {
+        let dir = self.root.join("deps");
+        fs::create_dir_all(&dir).expect("创建目录应成功");
+        let listing = std::iter::once(unit.to_string())
+            .chain(dependencies.iter().map(|dep| format!("  {dep}")))
+            .collect::<Vec<_>>()
+            .join("\n");
+        fs::write(dir.join(unit), listing + "\n").expect("写入依赖列表应成功");
+        self
+    }
+
+    /// modinfo 对内核模块的回答：模块文件路径与版本
+    pub fn module(&mut self, name: &str, path: &str, version: &str) -> &mut Self {
+        append(
+            &self.root.join("modules.map"),
+            &format!("{name}\t{path}\t{version}\n"),
+        );
+        self
+    }
+
+    /// smartctl -H -j 对设备的 JSON 回答
+    pub fn smart(&mut self, device: &str, json: &str) -> &mut Self {
+        let dir = self.root.join("smart");
+        fs::create_dir_all(&dir).expect("创建目录应成功");
+        fs::write(dir.join(device), json).expect("写入 SMART 输出应成功");
+        self
+    }
+
+    /// 假 dmesg --json 的输出
+    pub fn dmesg(&mut self, json: &str) -> &mut Self {
+        fs::write(self.root.join("dmesg.json"), json).expect("写入 dmesg 输出应成功");
+        self
+    }
+
+    pub fn socket_dir(&self) -> PathBuf {
+        self.root.join("sock")
+    }
+
+    /// 启动 daemon 并等到它开始监听（Socket 文件在 bind 时就已出现，listen 之前连接会被拒绝）
+    pub fn start_daemon(&mut self, args: &[&str]) {
+        let log = fs::File::create(self.root.join("daemon.log")).expect("创建日志文件应成功");
+        let child = self
+            .command(env!("CARGO_BIN_EXE_logtool-daemon"))
+            .args(["--foreground", "--socket-dir"])
+            .arg(self.socket_dir())
+            .args(args)
+            .stdout(Stdio::null())
+            .stderr(log)
+            .spawn()
+            .expect("启动 logtool-daemon 应成功");
+        self.daemon = Some(child);
+        let socket = self.socket_dir().join("logtool.sock");
+        let started = Instant::now();
+        while !socket.exists() || !self.daemon_log().contains("守护进程已启动") {
+            assert!(
+                started.elapsed() < DAEMON_START_TIMEOUT,
+                "daemon 未在时限内创建 Socket：\n{}",
+                self.daemon_log()
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    /// 结束 daemon，之后可再次 start_daemon
+    pub fn stop_daemon(&mut self) {
+        if let Some(mut daemon) = self.daemon.take() {
+            let _ = daemon.kill();
+            let _ = daemon.wait();
+        }
+    }
+
+    /// 在假环境中运行 CLI 并等待结束
+    pub fn cli(&self, args: &[&str]) -> Output {
+        self.cli_spawn(args)
+            .wait_with_output()
+            .expect("等待 logtool 应成功")
+    }
+
+    pub fn cli_spawn(&self, args: &[&str]) -> Child {
+        self.command(env!("CARGO_BIN_EXE_logtool"))
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("启动 logtool 应成功")
+    }
+
+    /// 假 journalctl 收到的各次调用参数（空格连接）
+    pub fn journalctl_calls(&self) -> Vec<String> {
+        fs::read_to_string(self.root.join("journalctl.calls"))
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn daemon_log(&self) -> String {
+        fs::read_to_string(self.root.join("daemon.log")).unwrap_or_default()
+    }
+
+    /// 仍在运行（未退出且未成为僵尸进程）的假 journalctl 数
+    pub fn running_journalctl(&self) -> usize {
+        fs::read_to_string(self.root.join("journalctl.pids"))
+            .unwrap_or_default()
+            .lines()
+            .filter(|pid| {
+                fs::read_to_string(format!("/proc/{pid}/stat")).is_ok_and(|stat| {
+                    // `pid (comm) state ...`
+                    stat.rsplit_once(") ")
+                        .is_some_and(|(_, rest)| !rest.starts_with('Z'))
+                })
+            })
+            .count()
+    }
+
+    fn command(&self, program: &str) -> Command {
+        let path = match env::var_os("PATH") {
+            Some(path) => {
+                let mut dirs = vec![self.root.join("bin")];
+                dirs.extend(env::split_paths(&path));
+                env::join_paths(dirs).expect("PATH 应可拼接")
+            }
+            None => self.root.join("bin").into_os_string(),
+        };
+        let mut cmd = Command::new(program);
+        cmd.env("PATH", path)
+            .env("LOGTOOL_SOCKET_DIR", self.socket_dir())
+            .env("XDG_CONFIG_HOME", self.root.join("config"))
+            .env("LOGTOOL_STATE", self.root.join("state/state.json"))
+            .env("LOGTOOL_PKGCACHE", self.root.join("state/pkgcache.json"))
+            .env_remove("LOGTOOL_UPDATE_GOLDEN");
+        cmd
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        if let Some(mut daemon) = self.daemon.take() {
+            let _ = daemon.kill();
+            let _ = daemon.wait();
+        }
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn append(path: &Path, text: &str) {
+    use std::io::Write;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .expect("打开文件应成功");
+    file.write_all(text.as_bytes()).expect("写入应成功");
+}
+
+/// `journalctl --output=json` 格式的一行
+pub fn journal_line(priority: u8, unit: &str, exe: &str, message: &str) -> String {
+    serde_json::json!({
+        "PRIORITY": priority.to_string(),
+        "MESSAGE": message,
+        "_SYSTEMD_UNIT": unit,
+        "_EXE": exe,
+        "_COMM": Path::new(exe).file_name().and_then(|n| n.to_str()).unwrap_or_default(),
+        "SYSLOG_IDENTIFIER": Path::new(exe).file_name().and_then(|n| n.to_str()).unwrap_or_default(),
+        "_HOSTNAME": "testhost",
+    })
+    .to_string()
+        + "\n"
+}